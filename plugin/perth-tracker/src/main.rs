@@ -0,0 +1,139 @@
+//! Companion plugin for `zdrive watch --plugin`.
+//!
+//! `zdrive watch` subscribes with `zellij pipe --name perth-tracker`. The
+//! plugin keeps each subscriber's pipe open and, whenever Zellij reports a
+//! pane or tab change, writes the session's terminal panes to it as one JSON
+//! line:
+//!
+//! ```json
+//! {"panes":[{"id":3,"title":"api","tab":"backend","focused":true}]}
+//! ```
+//!
+//! Zellij doesn't tell a plugin when a pipe's client goes away, so
+//! subscribers hold a lease: every [`LEASE_SECS`] the plugin lets each pipe
+//! read its next input line, which `zdrive watch` keeps supplying. A pipe
+//! whose input has ended, or that sends nothing for [`MAX_MISSED`] leases,
+//! is dropped.
+//!
+//! Build with `cargo build --release --target wasm32-wasip1`.
+
+use serde_json::json;
+use std::collections::BTreeMap;
+use zellij_tile::prelude::*;
+
+/// Must match `tracker::PIPE_NAME` in zdrive.
+const PIPE_NAME: &str = "perth-tracker";
+
+/// How often subscribers are asked for a line; matches
+/// `tracker::PING_INTERVAL` in zdrive.
+const LEASE_SECS: f64 = 30.0;
+
+/// Leases a subscriber may let pass without a line before it is dropped
+const MAX_MISSED: u32 = 2;
+
+#[derive(Default)]
+struct State {
+    tabs: Vec<TabInfo>,
+    panes: Option<PaneManifest>,
+    /// IDs of the `zellij pipe` invocations waiting for updates, with the
+    /// leases each has missed in a row
+    subscribers: BTreeMap<String, u32>,
+    last_sent: Option<String>,
+}
+
+register_plugin!(State);
+
+impl ZellijPlugin for State {
+    fn load(&mut self, _configuration: BTreeMap<String, String>) {
+        request_permission(&[PermissionType::ReadApplicationState, PermissionType::ReadCliPipes]);
+        subscribe(&[EventType::PaneUpdate, EventType::TabUpdate, EventType::Timer]);
+        set_timeout(LEASE_SECS);
+    }
+
+    fn update(&mut self, event: Event) -> bool {
+        match event {
+            Event::PaneUpdate(manifest) => self.panes = Some(manifest),
+            Event::TabUpdate(tabs) => self.tabs = tabs,
+            Event::Timer(_) => {
+                self.renew_leases();
+                set_timeout(LEASE_SECS);
+                return false;
+            }
+            _ => return false,
+        }
+        self.broadcast(false);
+        false
+    }
+
+    fn pipe(&mut self, message: PipeMessage) -> bool {
+        if message.name != PIPE_NAME {
+            return false;
+        }
+        let PipeSource::Cli(pipe_id) = message.source else {
+            return false;
+        };
+        if message.payload.is_none() {
+            // The subscriber's input ended: it is going away
+            self.subscribers.remove(&pipe_id);
+            unblock_cli_pipe_input(&pipe_id);
+            return false;
+        }
+        // Holding the input keeps the pipe open for output
+        block_cli_pipe_input(&pipe_id);
+        if self.subscribers.insert(pipe_id, 0).is_none() {
+            // A new subscriber needs the current state even if nothing changed
+            self.broadcast(true);
+        }
+        false
+    }
+}
+
+impl State {
+    /// The session's terminal panes as one JSON line, once both the tabs and
+    /// the panes have been reported.
+    fn manifest_line(&self) -> Option<String> {
+        let manifest = self.panes.as_ref()?;
+        if self.tabs.is_empty() {
+            return None;
+        }
+        let mut panes = Vec::new();
+        for tab in &self.tabs {
+            let Some(tab_panes) = manifest.panes.get(&tab.position) else {
+                continue;
+            };
+            for pane in tab_panes.iter().filter(|pane| !pane.is_plugin) {
+                panes.push(json!({
+                    "id": pane.id,
+                    "title": pane.title,
+                    "tab": tab.name,
+                    "focused": pane.is_focused && tab.active,
+                }));
+            }
+        }
+        Some(format!("{}\n", json!({ "panes": panes })))
+    }
+
+    /// Drop subscribers that have missed too many leases, and let the rest
+    /// read their next line to renew.
+    fn renew_leases(&mut self) {
+        self.subscribers.retain(|_, missed| *missed < MAX_MISSED);
+        for (pipe_id, missed) in &mut self.subscribers {
+            *missed += 1;
+            unblock_cli_pipe_input(pipe_id);
+        }
+    }
+
+    /// Send the manifest to every subscriber when it changed, or always with `force`.
+    fn broadcast(&mut self, force: bool) {
+        let Some(line) = self.manifest_line() else {
+            return;
+        };
+        if !force && self.last_sent.as_ref() == Some(&line) {
+            return;
+        }
+        for pipe_id in self.subscribers.keys() {
+            cli_pipe_output(pipe_id, &line);
+        }
+        self.last_sent = Some(line);
+    }
+}
@@ -0,0 +1,322 @@
+//! Activity heuristics for adaptive snapshot triggering.
+//!
+//! The snapshot daemon samples the working directory on every tick and only
+//! asks the LLM for a summary when the samples suggest a meaningful chunk of
+//! work was completed:
+//!
+//! - a new commit landed (HEAD moved)
+//! - the branch was switched
+//! - a test command ran after several files were edited
+//!
+//! Branch switches are also logged per pane as automated checkpoints, since
+//! they are natural context boundaries, and the git post-commit hook logs
+//! each commit the same way.
+
+use crate::types::{IntentEntry, IntentSource, IntentType};
+use regex::Regex;
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// Default number of edited files before a test run counts as a checkpoint.
+pub const DEFAULT_MIN_EDITS: usize = 3;
+
+static TEST_COMMAND: OnceLock<Regex> = OnceLock::new();
+
+fn test_command_regex() -> &'static Regex {
+    TEST_COMMAND.get_or_init(|| {
+        Regex::new(
+            r"^\s*(cargo (test|nextest)|npm (run )?test|yarn test|pnpm test|bun test|pytest|python -m pytest|go test|make test|mix test|rspec|jest|vitest|npx (jest|vitest))\b",
+        )
+        .expect("invalid test command regex")
+    })
+}
+
+/// Whether a shell command looks like a test run.
+pub fn is_test_command(command: &str) -> bool {
+    test_command_regex().is_match(command)
+}
+
+/// Point-in-time view of repository and shell activity.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ActivitySample {
+    /// Current HEAD commit hash
+    pub head: Option<String>,
+    /// Current branch name
+    pub branch: Option<String>,
+    /// Number of files with uncommitted changes
+    pub dirty_files: usize,
+    /// Most recent shell command
+    pub last_command: Option<String>,
+}
+
+impl ActivitySample {
+    /// Sample git state in `cwd`, pairing it with the latest shell command.
+    pub fn collect(cwd: &Path, last_command: Option<String>) -> Self {
+        Self {
+            head: git_output(cwd, &["rev-parse", "HEAD"]),
+            branch: current_branch(cwd),
+            dirty_files: git_output(cwd, &["status", "--porcelain"])
+                .map(|s| s.lines().count())
+                .unwrap_or(0),
+            last_command,
+        }
+    }
+}
+
+/// Current git branch in `cwd`, if it is inside a work tree on a named branch.
+pub fn current_branch(cwd: &Path) -> Option<String> {
+    git_output(cwd, &["branch", "--show-current"])
+}
+
+/// Tab name for the checkout containing `cwd`, in the `repo(branch)` form
+/// of the tab naming convention. A detached HEAD uses the short commit hash.
+/// `None` outside a git work tree.
+pub fn git_tab_name(cwd: &Path) -> Option<String> {
+    let root = git_output(cwd, &["rev-parse", "--show-toplevel"])?;
+    let repo = Path::new(&root).file_name()?.to_string_lossy().to_string();
+    let context = current_branch(cwd).or_else(|| git_output(cwd, &["rev-parse", "--short", "HEAD"]))?;
+    Some(format_tab_name(&repo, &context))
+}
+
+/// `repo(context)` with anything but letters, digits, '-' and '_' turned into
+/// '-', so `feature/login` becomes `feature-login`.
+fn format_tab_name(repo: &str, context: &str) -> String {
+    fn clean(part: &str) -> String {
+        let replaced: String =
+            part.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '-' }).collect();
+        replaced.split('-').filter(|piece| !piece.is_empty()).collect::<Vec<_>>().join("-")
+    }
+    format!("{}({})", clean(repo), clean(context))
+}
+
+/// Build the automated entry logged when a pane moves between branches.
+/// Returns `None` on first sight of a branch or when it is unchanged.
+pub fn branch_switch_entry(previous: Option<&str>, current: &str) -> Option<IntentEntry> {
+    let previous = previous.filter(|p| *p != current)?;
+    Some(
+        IntentEntry::new(format!("Switched from {} to {}", previous, current))
+            .with_type(IntentType::Checkpoint)
+            .with_source(IntentSource::Automated),
+    )
+}
+
+/// The commit at HEAD, as recorded by the post-commit hook.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommitInfo {
+    pub sha: String,
+    pub subject: String,
+    /// Absolute paths of the files the commit changed
+    pub files: Vec<String>,
+}
+
+/// Read HEAD's commit in the repository at `root`.
+pub fn last_commit(root: &Path) -> Option<CommitInfo> {
+    let sha = git_output(root, &["rev-parse", "HEAD"])?;
+    let subject = git_output(root, &["log", "-1", "--format=%s"]).unwrap_or_default();
+    let files = git_output(root, &["diff-tree", "--no-commit-id", "--name-only", "-r", "--root", "HEAD"])
+        .map(|out| out.lines().map(|file| root.join(file).to_string_lossy().to_string()).collect())
+        .unwrap_or_default();
+    Some(CommitInfo { sha, subject, files })
+}
+
+/// Build the automated entry logged for a commit.
+pub fn commit_entry(commit: &CommitInfo) -> IntentEntry {
+    IntentEntry::new(format!("Committed: {}", commit.subject))
+        .with_type(IntentType::Checkpoint)
+        .with_source(IntentSource::Automated)
+        .with_commit(&commit.sha)
+        .with_artifacts(commit.files.clone())
+}
+
+/// Trimmed stdout of a successful git command, if not empty.
+pub(crate) fn git_output(cwd: &Path, args: &[&str]) -> Option<String> {
+    Command::new("git")
+        .args(args)
+        .current_dir(cwd)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Reason a snapshot was triggered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Trigger {
+    /// HEAD moved to a new commit
+    Commit(String),
+    /// Branch changed from one name to another
+    BranchSwitch { from: String, to: String },
+    /// A test command ran after this many files were edited
+    TestRunAfterEdits(usize),
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trigger::Commit(head) => {
+                write!(f, "commit detected ({})", &head[..head.len().min(7)])
+            }
+            Trigger::BranchSwitch { from, to } => write!(f, "branch switched {} → {}", from, to),
+            Trigger::TestRunAfterEdits(edits) => {
+                write!(f, "test run after {} edited file{}", edits, if *edits == 1 { "" } else { "s" })
+            }
+        }
+    }
+}
+
+/// Tracks samples across daemon ticks and decides when to snapshot.
+#[derive(Debug, Clone)]
+pub struct ActivityMonitor {
+    last: Option<ActivitySample>,
+    edits_since_trigger: usize,
+    min_edits: usize,
+}
+
+impl ActivityMonitor {
+    pub fn new(min_edits: usize) -> Self {
+        Self {
+            last: None,
+            edits_since_trigger: 0,
+            min_edits,
+        }
+    }
+
+    /// Record a sample, returning a trigger if it marks the end of a chunk of work.
+    /// The first sample only establishes a baseline.
+    pub fn observe(&mut self, sample: ActivitySample) -> Option<Trigger> {
+        self.edits_since_trigger = self.edits_since_trigger.max(sample.dirty_files);
+
+        let prev = self.last.replace(sample.clone())?;
+
+        let trigger = match (&prev.branch, &sample.branch) {
+            (Some(from), Some(to)) if from != to => Some(Trigger::BranchSwitch {
+                from: from.clone(),
+                to: to.clone(),
+            }),
+            _ => None,
+        }
+        .or_else(|| match (&prev.head, &sample.head) {
+            (Some(old), Some(new)) if old != new => Some(Trigger::Commit(new.clone())),
+            _ => None,
+        })
+        .or_else(|| {
+            let new_command = sample.last_command.is_some() && sample.last_command != prev.last_command;
+            let ran_tests = sample.last_command.as_deref().is_some_and(is_test_command);
+            (new_command && ran_tests && self.edits_since_trigger >= self.min_edits)
+                .then_some(Trigger::TestRunAfterEdits(self.edits_since_trigger))
+        });
+
+        if trigger.is_some() {
+            self.edits_since_trigger = sample.dirty_files;
+        }
+
+        trigger
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(head: &str, branch: &str, dirty: usize, cmd: Option<&str>) -> ActivitySample {
+        ActivitySample {
+            head: Some(head.to_string()),
+            branch: Some(branch.to_string()),
+            dirty_files: dirty,
+            last_command: cmd.map(|c| c.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_commit_entry() {
+        let commit = CommitInfo {
+            sha: "def4567890abcdef".to_string(),
+            subject: "Add rate limiting".to_string(),
+            files: vec!["/work/api/src/limit.rs".to_string()],
+        };
+        let entry = commit_entry(&commit);
+        assert_eq!(entry.summary, "Committed: Add rate limiting");
+        assert_eq!(entry.source, IntentSource::Automated);
+        assert_eq!(entry.commit.as_deref(), Some("def4567890abcdef"));
+        assert_eq!(entry.artifacts, vec!["/work/api/src/limit.rs"]);
+    }
+
+    #[test]
+    fn test_format_tab_name() {
+        assert_eq!(format_tab_name("zellij-driver", "main"), "zellij-driver(main)");
+        assert_eq!(format_tab_name("my.app", "feature/login--v2"), "my-app(feature-login-v2)");
+    }
+
+    #[test]
+    fn test_first_sample_is_baseline() {
+        let mut monitor = ActivityMonitor::new(DEFAULT_MIN_EDITS);
+        assert_eq!(monitor.observe(sample("abc", "main", 10, Some("cargo test"))), None);
+    }
+
+    #[test]
+    fn test_commit_triggers() {
+        let mut monitor = ActivityMonitor::new(DEFAULT_MIN_EDITS);
+        monitor.observe(sample("abc1234567", "main", 2, None));
+        let trigger = monitor.observe(sample("def4567890", "main", 0, None));
+        assert_eq!(trigger, Some(Trigger::Commit("def4567890".to_string())));
+        assert_eq!(trigger.unwrap().to_string(), "commit detected (def4567)");
+    }
+
+    #[test]
+    fn test_branch_switch_takes_precedence_over_commit() {
+        let mut monitor = ActivityMonitor::new(DEFAULT_MIN_EDITS);
+        monitor.observe(sample("abc", "main", 0, None));
+        let trigger = monitor.observe(sample("def", "feature/x", 0, None));
+        assert_eq!(
+            trigger,
+            Some(Trigger::BranchSwitch {
+                from: "main".to_string(),
+                to: "feature/x".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_test_run_requires_enough_edits() {
+        let mut monitor = ActivityMonitor::new(3);
+        monitor.observe(sample("abc", "main", 1, Some("ls")));
+        assert_eq!(monitor.observe(sample("abc", "main", 2, Some("cargo test"))), None);
+
+        monitor.observe(sample("abc", "main", 4, Some("vim src/lib.rs")));
+        assert_eq!(
+            monitor.observe(sample("abc", "main", 4, Some("cargo test --lib"))),
+            Some(Trigger::TestRunAfterEdits(4))
+        );
+    }
+
+    #[test]
+    fn test_same_test_command_does_not_retrigger() {
+        let mut monitor = ActivityMonitor::new(1);
+        monitor.observe(sample("abc", "main", 2, Some("ls")));
+        assert!(monitor.observe(sample("abc", "main", 2, Some("pytest"))).is_some());
+        assert_eq!(monitor.observe(sample("abc", "main", 2, Some("pytest"))), None);
+    }
+
+    #[test]
+    fn test_branch_switch_entry() {
+        let entry = branch_switch_entry(Some("feature/a"), "feature/b").unwrap();
+        assert_eq!(entry.summary, "Switched from feature/a to feature/b");
+        assert_eq!(entry.source, IntentSource::Automated);
+
+        assert!(branch_switch_entry(None, "main").is_none());
+        assert!(branch_switch_entry(Some("main"), "main").is_none());
+    }
+
+    #[test]
+    fn test_is_test_command() {
+        assert!(is_test_command("cargo test --workspace"));
+        assert!(is_test_command("npm run test"));
+        assert!(is_test_command("go test ./..."));
+        assert!(!is_test_command("cargo build"));
+        assert!(!is_test_command("echo pytest"));
+    }
+}
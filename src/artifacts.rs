@@ -0,0 +1,109 @@
+//! Artifact checks for `zdrive pane artifacts`: which paths a pane's history
+//! references, whether they still exist, and rewriting them after a move.
+//!
+//! Only absolute paths are checked. URLs, names and relative paths have
+//! no fixed base to resolve against, so they are reported unchecked.
+
+use crate::types::IntentEntry;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Whether an artifact's path still exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtifactStatus {
+    Exists,
+    Missing,
+    /// Not an absolute path, so there is nothing to check
+    Unchecked,
+}
+
+/// One artifact referenced in a pane's history.
+#[derive(Debug, Clone, Serialize)]
+pub struct ArtifactReport {
+    pub path: String,
+    /// Number of entries that list it
+    pub entries: usize,
+    /// When an entry last listed it
+    pub last_logged: DateTime<Utc>,
+    /// Set with `--check`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<ArtifactStatus>,
+}
+
+/// Artifacts listed in `entries`, most recently logged first.
+pub fn collect(entries: &[IntentEntry]) -> Vec<ArtifactReport> {
+    let mut reports: HashMap<&str, ArtifactReport> = HashMap::new();
+    for entry in entries {
+        for artifact in &entry.artifacts {
+            let report = reports.entry(artifact.as_str()).or_insert_with(|| ArtifactReport {
+                path: artifact.clone(),
+                entries: 0,
+                last_logged: entry.timestamp,
+                status: None,
+            });
+            report.entries += 1;
+            report.last_logged = report.last_logged.max(entry.timestamp);
+        }
+    }
+    let mut reports: Vec<ArtifactReport> = reports.into_values().collect();
+    reports.sort_by(|a, b| b.last_logged.cmp(&a.last_logged).then_with(|| a.path.cmp(&b.path)));
+    reports
+}
+
+/// Whether an absolute path still exists; anything else is unchecked.
+pub fn check(artifact: &str) -> ArtifactStatus {
+    let path = Path::new(artifact);
+    if !path.is_absolute() {
+        ArtifactStatus::Unchecked
+    } else if path.exists() {
+        ArtifactStatus::Exists
+    } else {
+        ArtifactStatus::Missing
+    }
+}
+
+/// `artifact` with the `old` prefix replaced by `new`, if it starts with
+/// `old` at a path component boundary (`/src` matches `/src/a.rs`, not
+/// `/srcs/a.rs`).
+pub fn remap(artifact: &str, old: &str, new: &str) -> Option<String> {
+    let old = old.trim_end_matches('/');
+    let new = new.trim_end_matches('/');
+    let rest = artifact.strip_prefix(old)?;
+    (rest.is_empty() || rest.starts_with('/')).then(|| format!("{}{}", new, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_counts_and_orders_by_last_use() {
+        let mut older = IntentEntry::new("Sketched").with_artifacts(vec!["/repo/a.rs".to_string(), "/repo/b.rs".to_string()]);
+        older.timestamp -= chrono::Duration::hours(1);
+        let newer = IntentEntry::new("Finished").with_artifacts(vec!["/repo/b.rs".to_string()]);
+
+        let reports = collect(&[newer, older]);
+        let summary: Vec<(&str, usize)> = reports.iter().map(|r| (r.path.as_str(), r.entries)).collect();
+        assert_eq!(summary, vec![("/repo/b.rs", 2), ("/repo/a.rs", 1)]);
+    }
+
+    #[test]
+    fn test_check_only_absolute_paths() {
+        let dir = std::env::temp_dir();
+        assert_eq!(check(&dir.to_string_lossy()), ArtifactStatus::Exists);
+        assert_eq!(check(&dir.join("perth-missing-artifact").to_string_lossy()), ArtifactStatus::Missing);
+        assert_eq!(check("https://example.com/pr/42"), ArtifactStatus::Unchecked);
+        assert_eq!(check("src/a.rs"), ArtifactStatus::Unchecked);
+    }
+
+    #[test]
+    fn test_remap_respects_component_boundaries() {
+        assert_eq!(remap("/old/src/a.rs", "/old/src/", "/new/lib").as_deref(), Some("/new/lib/a.rs"));
+        assert_eq!(remap("/old/src", "/old/src", "/new/lib").as_deref(), Some("/new/lib"));
+        assert_eq!(remap("/old/srcs/a.rs", "/old/src", "/new/lib"), None);
+        assert_eq!(remap("/other/a.rs", "/old", "/new"), None);
+    }
+}
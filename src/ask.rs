@@ -0,0 +1,211 @@
+//! Retrieval for `zdrive ask`: pick the intent entries relevant to a
+//! free-form question and assemble them into an LLM prompt.
+//!
+//! Relevance is keyword based. Question words are matched against each
+//! entry's summary, goal delta and artifacts, and against the pane name
+//! (which usually carries the project and branch, e.g. `api(payments)`).
+
+use crate::types::{IntentEntry, IntentType};
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+
+/// Entries sent with a question unless `--limit` says otherwise.
+pub const DEFAULT_LIMIT: usize = 20;
+
+/// Words too common to say anything about which entries are relevant.
+const STOPWORDS: &[&str] = &[
+    "about", "after", "and", "are", "before", "did", "does", "doing", "for", "from", "had", "has",
+    "have", "how", "into", "last", "the", "that", "then", "this", "was", "were", "what", "when",
+    "where", "which", "while", "who", "why", "with", "work", "working", "you", "your",
+    "today", "yesterday", "week", "month",
+];
+
+/// Which history a question is answered from.
+#[derive(Debug, Clone)]
+pub struct AskOptions {
+    /// Only this pane's history, instead of every pane's
+    pub pane: Option<String>,
+    /// Only entries logged since then
+    pub since: Option<DateTime<Utc>>,
+    /// Most entries to send with the question
+    pub limit: usize,
+}
+
+impl Default for AskOptions {
+    fn default() -> Self {
+        Self { pane: None, since: None, limit: DEFAULT_LIMIT }
+    }
+}
+
+/// A history entry together with the pane it was logged in.
+#[derive(Debug, Clone)]
+pub struct PaneEntry {
+    pub pane_name: String,
+    pub entry: IntentEntry,
+}
+
+/// Lowercased search terms in a question, without stopwords, short words
+/// or duplicates.
+pub fn search_terms(question: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    question
+        .split(|c: char| !c.is_alphanumeric() && c != '-' && c != '_' && c != '.' && c != '/')
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+        .filter(|word| word.chars().count() >= 3 && !STOPWORDS.contains(&word.as_str()))
+        .filter(|word| seen.insert(word.clone()))
+        .collect()
+}
+
+/// Number of terms that appear in the entry or its pane name.
+fn score(candidate: &PaneEntry, terms: &[String]) -> usize {
+    let entry = &candidate.entry;
+    let mut haystack = format!("{} {}", candidate.pane_name, entry.summary);
+    if let Some(delta) = &entry.goal_delta {
+        haystack.push(' ');
+        haystack.push_str(delta);
+    }
+    for artifact in &entry.artifacts {
+        haystack.push(' ');
+        haystack.push_str(artifact);
+    }
+    let haystack = haystack.to_lowercase();
+    terms.iter().filter(|term| haystack.contains(term.as_str())).count()
+}
+
+/// Pick up to `limit` entries for the question: those matching the most
+/// terms first, newest first among equals. When nothing matches, the most
+/// recent entries are used so the model still has something to go on.
+pub fn select_entries(candidates: Vec<PaneEntry>, question: &str, limit: usize) -> Vec<PaneEntry> {
+    let terms = search_terms(question);
+    let mut scored: Vec<(usize, PaneEntry)> = candidates
+        .into_iter()
+        .map(|candidate| (score(&candidate, &terms), candidate))
+        .collect();
+
+    if scored.iter().any(|(score, _)| *score > 0) {
+        scored.retain(|(score, _)| *score > 0);
+    }
+    scored.sort_by(|(a_score, a), (b_score, b)| {
+        b_score.cmp(a_score).then(b.entry.timestamp.cmp(&a.entry.timestamp))
+    });
+    scored.into_iter().take(limit).map(|(_, candidate)| candidate).collect()
+}
+
+/// Build the prompt: every selected entry in ranked order, each with its
+/// pane and full date so questions about when things happened can be
+/// answered, followed by the question.
+pub fn build_prompt(question: &str, selected: &[PaneEntry]) -> String {
+    let mut prompt = String::new();
+    prompt.push_str("You are a developer assistant answering questions about a developer's past work. ");
+    prompt.push_str("The following entries from their intent history are the most relevant to the question, ");
+    prompt.push_str("most relevant first. Each names the terminal pane it was logged in ");
+    prompt.push_str("(pane names are usually project(branch)).\n\n");
+
+    prompt.push_str("## History\n");
+    if selected.is_empty() {
+        prompt.push_str("No history entries were found.\n");
+    }
+    for candidate in selected {
+        prompt.push_str(&format_entry(candidate));
+    }
+    prompt.push('\n');
+
+    prompt.push_str(&format!("Today is {}.\n\n", Utc::now().format("%Y-%m-%d (%A)")));
+    prompt.push_str("## Question\n");
+    prompt.push_str(question.trim());
+    prompt.push_str("\n\nAnswer concisely from the history above. ");
+    prompt.push_str("If it does not contain the answer, say so rather than guessing.");
+    prompt
+}
+
+/// One entry as a list item: when, where, what, and any goal progress,
+/// files and tags.
+fn format_entry(candidate: &PaneEntry) -> String {
+    let entry = &candidate.entry;
+    let marker = match entry.entry_type {
+        IntentType::Milestone => " [milestone]",
+        IntentType::Exploration => " [exploration]",
+        IntentType::Checkpoint => "",
+    };
+    let mut line = format!(
+        "- {} UTC, {}: {}{}\n",
+        entry.timestamp.format("%Y-%m-%d %H:%M (%a)"),
+        candidate.pane_name,
+        entry.summary,
+        marker
+    );
+    if let Some(delta) = &entry.goal_delta {
+        line.push_str(&format!("  Goal progress: {}\n", delta));
+    }
+    if !entry.artifacts.is_empty() {
+        line.push_str(&format!("  Files: {}\n", entry.artifacts.join(", ")));
+    }
+    if !entry.tags.is_empty() {
+        line.push_str(&format!("  Tags: {}\n", entry.tags.join(", ")));
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+
+    fn candidate(pane_name: &str, summary: &str, hours_ago: i64) -> PaneEntry {
+        let mut entry = IntentEntry::new(summary);
+        entry.timestamp = Utc::now() - Duration::hours(hours_ago);
+        PaneEntry { pane_name: pane_name.to_string(), entry }
+    }
+
+    #[test]
+    fn test_search_terms() {
+        assert_eq!(
+            search_terms("What was I doing on the payments branch last week?"),
+            vec!["payments", "branch"]
+        );
+        assert_eq!(search_terms("Why did src/auth.rs fail? auth.rs!"), vec!["src/auth.rs", "fail", "auth.rs"]);
+        assert!(search_terms("what was I doing?").is_empty());
+    }
+
+    #[test]
+    fn test_select_prefers_matches_then_recency() {
+        let candidates = vec![
+            candidate("api(main)", "Bumped dependencies", 1),
+            candidate("api(payments)", "Added refund endpoint", 48),
+            candidate("web(main)", "Wired payments form to refund API", 5),
+            candidate("api(payments)", "Stripe webhook retries", 24),
+        ];
+
+        let selected = select_entries(candidates, "payments refund work", 2);
+        let summaries: Vec<_> = selected.iter().map(|c| c.entry.summary.as_str()).collect();
+        // Both match two terms; the newer wins the tie
+        assert_eq!(summaries, vec!["Wired payments form to refund API", "Added refund endpoint"]);
+    }
+
+    #[test]
+    fn test_select_falls_back_to_recent() {
+        let candidates = vec![
+            candidate("api(main)", "Older work", 10),
+            candidate("api(main)", "Newest work", 1),
+        ];
+        let selected = select_entries(candidates, "kubernetes", 1);
+        assert_eq!(selected[0].entry.summary, "Newest work");
+    }
+
+    #[test]
+    fn test_build_prompt_keeps_ranked_entries_with_dates() {
+        let mut selected: Vec<PaneEntry> =
+            (0..8).map(|i| candidate("api(payments)", &format!("Refund step {}", i), i * 24)).collect();
+        selected.push(candidate("web(main)", "Wired payments form", 200));
+        let prompt = build_prompt("  what happened to payments last week?  ", &selected);
+
+        // All of them, not just the newest few per pane, in ranked order
+        let positions: Vec<_> = selected.iter().map(|c| prompt.find(&c.entry.summary).unwrap()).collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let old = &selected[8].entry;
+        assert!(prompt.contains(&format!("- {} UTC, web(main): Wired payments form\n", old.timestamp.format("%Y-%m-%d %H:%M (%a)"))));
+        assert!(prompt.ends_with("If it does not contain the answer, say so rather than guessing."));
+        assert!(prompt.contains("## Question\nwhat happened to payments last week?\n"));
+    }
+}
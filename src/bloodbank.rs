@@ -9,22 +9,62 @@
 //! - `perth.tab.created` - A new tab was created
 //! - `perth.intent.logged` - An intent entry was logged
 //! - `perth.milestone.recorded` - A milestone was recorded (intent with type=milestone)
+//! - `perth.tab.archived` - A tab was archived after its correlated work completed
+//! - `perth.pane.stale` - A pane was found missing from Zellij and marked stale
+//! - `perth.pane.reconciled` - A stale pane was found again by `zdrive reconcile`
+//! - `perth.pane.deleted` - A stale pane's record was pruned by `zdrive reconcile --prune`
+//! - `perth.snapshot.created` - A session snapshot was saved
+//! - `perth.snapshot.restored` - A session snapshot was restored
+//!
+//! `zdrive listen` also consumes external events (see [`subscribe`]),
+//! including commands other producers send to Perth:
+//! - `perth.tab.create.requested` - Create a tab, optionally with a correlation ID
+//! - `perth.pane.create.requested` - Create or open a pane, optionally in a correlated tab
+//!
+//! Each command is answered with `<command>.completed` or `<command>.failed`,
+//! e.g. `perth.tab.create.completed`.
+//!
+//! Publishing uses publisher confirms: an event counts as sent only once the
+//! broker acknowledges it, so delivery is at-least-once. Events that can't be
+//! published or confirmed are spooled to the Redis outbox (`perth:events:outbox`) and sent, in order, before the next
+//! event that can be, or by `zdrive events flush`.
+//!
+//! With `bloodbank.event_log` on, every event is also appended to a local
+//! JSONL file (see [`event_log_path`]), whether or not a broker is configured.
 
+#[cfg(feature = "events")]
+use crate::config::mask_amqp_url;
 use crate::config::BloodbankConfig;
-use crate::types::{IntentEntry, IntentType, PaneRecord, TabRecord};
+use crate::metrics;
+use crate::state::EventOutbox;
+use crate::types::{IntentEntry, IntentType, PaneRecord, RestoreReport, RestoreStatus, SessionSnapshot, TabRecord};
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
+#[cfg(feature = "events")]
 use lapin::{
-    options::{BasicPublishOptions, ExchangeDeclareOptions},
+    options::{
+        BasicConsumeOptions, BasicPublishOptions, ConfirmSelectOptions, ExchangeDeclareOptions, QueueBindOptions,
+        QueueDeclareOptions,
+    },
     types::FieldTable,
-    BasicProperties, Channel, Connection, ConnectionProperties, ExchangeKind,
+    BasicProperties, Channel, Connection, ConnectionProperties, Consumer, ExchangeKind,
 };
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "events")]
 use std::sync::Arc;
+#[cfg(feature = "events")]
+use std::time::Duration;
+#[cfg(feature = "events")]
 use tokio::sync::RwLock;
 
 /// Event envelope wrapping all Bloodbank events
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct EventEnvelope<T: Serialize> {
     /// Event type following Bloodbank naming: source.entity.action
     pub event_type: String,
@@ -37,7 +77,7 @@ pub struct EventEnvelope<T: Serialize> {
 }
 
 /// Metadata attached to every event
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct EventMetadata {
     /// Source system identifier
     pub source: String,
@@ -109,6 +149,27 @@ pub struct PaneOpenedPayload {
     pub session: String,
 }
 
+/// Payload for pane.stale and pane.reconciled events
+#[derive(Debug, Clone, Serialize)]
+pub struct PaneStatusPayload {
+    pub pane_name: String,
+    pub tab: String,
+    pub session: String,
+    /// Why the pane's status changed, e.g. `reconcile` or `tab archived`
+    pub reason: String,
+}
+
+impl PaneStatusPayload {
+    pub fn new(record: &PaneRecord, reason: &str) -> Self {
+        Self {
+            pane_name: record.pane_name.clone(),
+            tab: record.tab.clone(),
+            session: record.session.clone(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
 /// Payload for tab.created event
 #[derive(Debug, Clone, Serialize)]
 pub struct TabCreatedPayload {
@@ -128,6 +189,84 @@ impl From<&TabRecord> for TabCreatedPayload {
     }
 }
 
+/// Payload for the `.completed` and `.failed` answers to a command
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandResultPayload {
+    /// The command event answered, e.g. `perth.tab.create.requested`
+    pub request_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Payload for tab.archived event
+#[derive(Debug, Clone, Serialize)]
+pub struct TabArchivedPayload {
+    pub tab_name: String,
+    pub session: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    /// Event that completed the tab's work, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Payload for snapshot.created event
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotCreatedPayload {
+    pub snapshot_id: String,
+    pub name: String,
+    pub session: String,
+    pub tab_count: usize,
+    pub pane_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+}
+
+impl From<&SessionSnapshot> for SnapshotCreatedPayload {
+    fn from(snapshot: &SessionSnapshot) -> Self {
+        Self {
+            snapshot_id: snapshot.id.to_string(),
+            name: snapshot.name.clone(),
+            session: snapshot.session.clone(),
+            tab_count: snapshot.tabs.len(),
+            pane_count: snapshot.pane_count,
+            parent_id: snapshot.parent_id.map(|id| id.to_string()),
+        }
+    }
+}
+
+/// Payload for snapshot.restored event
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotRestoredPayload {
+    pub snapshot_id: String,
+    pub name: String,
+    pub session: String,
+    pub status: RestoreStatus,
+    pub tabs_restored: usize,
+    pub panes_restored: usize,
+    pub tabs_failed: usize,
+    pub panes_failed: usize,
+}
+
+impl SnapshotRestoredPayload {
+    pub fn new(snapshot: &SessionSnapshot, report: &RestoreReport) -> Self {
+        Self {
+            snapshot_id: snapshot.id.to_string(),
+            name: snapshot.name.clone(),
+            session: report.session.clone(),
+            status: report.status,
+            tabs_restored: report.tabs_restored,
+            panes_restored: report.panes_restored,
+            tabs_failed: report.tabs_failed,
+            panes_failed: report.panes_failed,
+        }
+    }
+}
+
 /// Payload for intent.logged event
 #[derive(Debug, Clone, Serialize)]
 pub struct IntentLoggedPayload {
@@ -136,6 +275,8 @@ pub struct IntentLoggedPayload {
     pub summary: String,
     pub entry_type: String,
     pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_detail: Option<String>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub artifacts: Vec<String>,
 }
@@ -148,6 +289,7 @@ impl IntentLoggedPayload {
             summary: entry.summary.clone(),
             entry_type: entry.entry_type_str().to_lowercase(),
             source: entry.source_str().to_string(),
+            source_detail: entry.source_detail.clone(),
             artifacts: entry.artifacts.clone(),
         }
     }
@@ -174,11 +316,234 @@ impl MilestoneRecordedPayload {
     }
 }
 
+// ============================================================================
+// Incoming Events
+// ============================================================================
+
+/// An event received from the exchange. Only the envelope fields Perth
+/// needs are parsed, so events from other Bloodbank producers are accepted.
+#[derive(Debug, Clone, Deserialize)]
+pub struct IncomingEvent {
+    #[serde(default)]
+    pub event_type: String,
+    #[serde(default)]
+    pub payload: Value,
+    #[serde(default)]
+    pub metadata: Value,
+}
+
+impl IncomingEvent {
+    /// Parse a delivery body. The routing key stands in for a missing event_type.
+    pub fn parse(routing_key: &str, body: &[u8]) -> Result<Self> {
+        let mut event: Self = serde_json::from_slice(body).context("event body is not a JSON envelope")?;
+        if event.event_type.is_empty() {
+            event.event_type = routing_key.to_string();
+        }
+        Ok(event)
+    }
+
+    /// Correlation ID from the metadata, falling back to the payload.
+    pub fn correlation_id(&self) -> Option<&str> {
+        [&self.metadata, &self.payload]
+            .into_iter()
+            .find_map(|v| v.get("correlation_id").and_then(Value::as_str))
+            .filter(|id| !id.is_empty())
+    }
+}
+
+/// Event requesting a new tab.
+pub const TAB_CREATE_REQUESTED: &str = "perth.tab.create.requested";
+
+/// Event requesting a new (or existing) pane.
+pub const PANE_CREATE_REQUESTED: &str = "perth.pane.create.requested";
+
+/// A command sent to Perth over Bloodbank.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandRequest {
+    /// Create a tab, named `<tab_name>-<correlation_id>` when an ID is given
+    CreateTab {
+        tab_name: String,
+        correlation_id: Option<String>,
+        meta: HashMap<String, String>,
+    },
+    /// Create or open a pane, in `tab` if given (created with the
+    /// correlation ID when one is supplied)
+    CreatePane {
+        pane_name: String,
+        tab: Option<String>,
+        correlation_id: Option<String>,
+        meta: HashMap<String, String>,
+    },
+}
+
+impl IncomingEvent {
+    /// The command this event requests, if its type is one Perth carries
+    /// out. Errors when a command's payload is missing required fields.
+    pub fn command(&self) -> Option<Result<CommandRequest>> {
+        let field = |name: &str| self.payload.get(name).and_then(Value::as_str).filter(|v| !v.is_empty());
+        let required = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| field(name))
+                .map(str::to_string)
+                .ok_or_else(|| anyhow::anyhow!("{} payload needs a {} field", self.event_type, names[0]))
+        };
+        let correlation_id = self.correlation_id().map(str::to_string);
+        let meta: HashMap<String, String> = self
+            .payload
+            .get("meta")
+            .and_then(Value::as_object)
+            .map(|meta| {
+                meta.iter()
+                    .filter_map(|(key, value)| value.as_str().map(|v| (key.clone(), v.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        match self.event_type.as_str() {
+            TAB_CREATE_REQUESTED => Some(required(&["tab_name", "name"]).map(|tab_name| CommandRequest::CreateTab {
+                tab_name,
+                correlation_id,
+                meta,
+            })),
+            PANE_CREATE_REQUESTED => Some(required(&["pane_name", "name"]).map(|pane_name| CommandRequest::CreatePane {
+                pane_name,
+                tab: field("tab").map(str::to_string),
+                correlation_id,
+                meta,
+            })),
+            _ => None,
+        }
+    }
+
+    /// Caller's ID for the request, echoed in the result event so it can be
+    /// matched up.
+    pub fn request_id(&self) -> Option<&str> {
+        [&self.metadata, &self.payload]
+            .into_iter()
+            .find_map(|v| v.get("request_id").and_then(Value::as_str))
+            .filter(|id| !id.is_empty())
+    }
+}
+
+/// Whether `routing_key` matches the AMQP topic `pattern`, where `*` stands
+/// for one word and `#` for zero or more.
+pub fn topic_matches(pattern: &str, routing_key: &str) -> bool {
+    fn matches(pattern: &[&str], key: &[&str]) -> bool {
+        match (pattern.split_first(), key.split_first()) {
+            (None, None) => true,
+            (Some((&"#", rest)), _) => matches(rest, key) || (!key.is_empty() && matches(pattern, &key[1..])),
+            (Some((&word, rest)), Some((&first, key_rest))) => (word == "*" || word == first) && matches(rest, key_rest),
+            _ => false,
+        }
+    }
+    let pattern: Vec<&str> = pattern.split('.').collect();
+    let key: Vec<&str> = routing_key.split('.').collect();
+    matches(&pattern, &key)
+}
+
+/// Bind a durable queue (`bloodbank.listen_queue`) to `routing_keys`
+/// on the exchange and start consuming it. The queue outlives the listener,
+/// so events published while it is down are delivered on restart.
+#[cfg(feature = "events")]
+pub async fn subscribe(config: &BloodbankConfig, routing_keys: &[String]) -> Result<Consumer> {
+    let channel = consumer_channel(config).await?;
+    let queue = config.listen_queue();
+    channel
+        .queue_declare(
+            &queue,
+            QueueDeclareOptions {
+                durable: true,
+                ..Default::default()
+            },
+            FieldTable::default(),
+        )
+        .await
+        .context("failed to declare queue")?;
+
+    for routing_key in routing_keys {
+        channel
+            .queue_bind(&queue, &config.exchange, routing_key, QueueBindOptions::default(), FieldTable::default())
+            .await
+            .with_context(|| format!("failed to bind queue to {}", routing_key))?;
+    }
+
+    channel
+        .basic_consume(&queue, "zdrive-listen", BasicConsumeOptions::default(), FieldTable::default())
+        .await
+        .context("failed to start consuming")
+}
+
+/// Watch `routing_keys` through a temporary queue that the broker deletes
+/// when the consumer goes away. Deliveries need no ack, and nothing is
+/// taken from other consumers' queues.
+#[cfg(feature = "events")]
+pub async fn tail(config: &BloodbankConfig, routing_keys: &[String]) -> Result<Consumer> {
+    let channel = consumer_channel(config).await?;
+    let queue = channel
+        .queue_declare(
+            "",
+            QueueDeclareOptions {
+                exclusive: true,
+                auto_delete: true,
+                ..Default::default()
+            },
+            FieldTable::default(),
+        )
+        .await
+        .context("failed to declare queue")?;
+
+    for routing_key in routing_keys {
+        channel
+            .queue_bind(queue.name().as_str(), &config.exchange, routing_key, QueueBindOptions::default(), FieldTable::default())
+            .await
+            .with_context(|| format!("failed to bind queue to {}", routing_key))?;
+    }
+
+    channel
+        .basic_consume(
+            queue.name().as_str(),
+            "zdrive-events-tail",
+            BasicConsumeOptions {
+                no_ack: true,
+                ..Default::default()
+            },
+            FieldTable::default(),
+        )
+        .await
+        .context("failed to start consuming")
+}
+
+/// Connect and declare the exchange for a consumer.
+#[cfg(feature = "events")]
+async fn consumer_channel(config: &BloodbankConfig) -> Result<Channel> {
+    tracing::debug!(amqp_url = %mask_amqp_url(&config.amqp_url), exchange = %config.exchange, "connecting to Bloodbank");
+    let conn = Connection::connect(&config.amqp_url, ConnectionProperties::default())
+        .await
+        .context("failed to connect to RabbitMQ")?;
+    let channel = conn.create_channel().await.context("failed to create channel")?;
+
+    channel
+        .exchange_declare(
+            &config.exchange,
+            ExchangeKind::Topic,
+            ExchangeDeclareOptions {
+                durable: true,
+                ..Default::default()
+            },
+            FieldTable::default(),
+        )
+        .await
+        .context("failed to declare exchange")?;
+    Ok(channel)
+}
+
 // ============================================================================
 // Event Publisher
 // ============================================================================
 
 /// Connection state for the event publisher
+#[cfg(feature = "events")]
 enum ConnectionState {
     /// Not connected, will attempt on next publish
     Disconnected,
@@ -188,14 +553,80 @@ enum ConnectionState {
     Disabled,
 }
 
+/// Outbox events read per round trip when flushing
+#[cfg(feature = "events")]
+const OUTBOX_BATCH: usize = 100;
+
+/// How long to wait for the broker to confirm an event before treating it
+/// as unpublished
+#[cfg(feature = "events")]
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Rotated event logs kept beside the current one (`events.jsonl.1` is the newest)
+const EVENT_LOG_ROTATIONS: usize = 3;
+
+/// Local event log: `$XDG_DATA_HOME/perth/events.jsonl`, or
+/// `~/.local/share/perth/events.jsonl`.
+pub fn event_log_path() -> PathBuf {
+    let data_home = match std::env::var("XDG_DATA_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => {
+            let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+            Path::new(&home).join(".local").join("share")
+        }
+    };
+    data_home.join("perth").join("events.jsonl")
+}
+
+/// Append one serialized event to the log at `path`, rotating it first if
+/// the line would take it past `max_bytes`.
+fn append_event_log(path: &Path, event: &str, max_bytes: u64) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    let line = format!("{}\n", event);
+    let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    if size > 0 && size + line.len() as u64 > max_bytes {
+        rotate_event_log(path)?;
+    }
+    // One write per line, so concurrent zdrive processes don't interleave
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Shift `events.jsonl.N` to `.N+1`, dropping the oldest, and move the
+/// current log to `.1`.
+fn rotate_event_log(path: &Path) -> Result<()> {
+    let rotated = |n: usize| PathBuf::from(format!("{}.{}", path.display(), n));
+    for n in (1..EVENT_LOG_ROTATIONS).rev() {
+        let from = rotated(n);
+        if from.exists() {
+            fs::rename(&from, rotated(n + 1)).with_context(|| format!("failed to rotate {}", from.display()))?;
+        }
+    }
+    fs::rename(path, rotated(1)).with_context(|| format!("failed to rotate {}", path.display()))
+}
+
+#[cfg(not(feature = "events"))]
+fn events_unavailable() -> anyhow::Error {
+    anyhow::anyhow!("Bloodbank publishing is not available: zdrive was built without the `events` feature")
+}
+
 /// Publisher for Bloodbank events via RabbitMQ
 pub struct EventPublisher {
     config: BloodbankConfig,
+    #[cfg(feature = "events")]
     state: Arc<RwLock<ConnectionState>>,
+    outbox: Option<EventOutbox>,
 }
 
 impl EventPublisher {
     /// Create a new event publisher with the given configuration
+    #[cfg(feature = "events")]
     pub fn new(config: BloodbankConfig) -> Self {
         let initial_state = if config.enabled {
             ConnectionState::Disconnected
@@ -206,16 +637,37 @@ impl EventPublisher {
         Self {
             config,
             state: Arc::new(RwLock::new(initial_state)),
+            outbox: None,
         }
     }
 
+    /// Create a new event publisher with the given configuration. Built
+    /// without the `events` feature there is no broker client, so only the
+    /// local event log is written, if `bloodbank.event_log` is on.
+    #[cfg(not(feature = "events"))]
+    pub fn new(mut config: BloodbankConfig) -> Self {
+        config.enabled = false;
+        Self { config, outbox: None }
+    }
+
+    /// Builder method to spool events that can't be published to `outbox`
+    /// instead of dropping them.
+    pub fn with_outbox(mut self, outbox: EventOutbox) -> Self {
+        self.outbox = Some(outbox);
+        self
+    }
+
     /// Check if publishing is enabled
+    ///
+    /// The local event log is independent of this; see [`event_log_path`].
     pub fn is_enabled(&self) -> bool {
         self.config.enabled
     }
 
     /// Attempt to connect to RabbitMQ
+    #[cfg(feature = "events")]
     async fn connect(&self) -> Result<Channel> {
+        tracing::debug!(amqp_url = %mask_amqp_url(&self.config.amqp_url), exchange = %self.config.exchange, "connecting to Bloodbank");
         let conn = Connection::connect(&self.config.amqp_url, ConnectionProperties::default())
             .await
             .context("failed to connect to RabbitMQ")?;
@@ -236,10 +688,16 @@ impl EventPublisher {
             .await
             .context("failed to declare exchange")?;
 
+        channel
+            .confirm_select(ConfirmSelectOptions::default())
+            .await
+            .context("failed to enable publisher confirms")?;
+
         Ok(channel)
     }
 
     /// Get or create a channel for publishing
+    #[cfg(feature = "events")]
     async fn get_channel(&self) -> Result<Channel> {
         // Check current state
         {
@@ -275,9 +733,10 @@ impl EventPublisher {
     /// Publish an event to Bloodbank
     ///
     /// This method handles connection failures gracefully - if RabbitMQ is
-    /// unavailable, it logs a warning but does not return an error.
+    /// unavailable, the event is queued in the outbox (when one is set) and
+    /// a warning is logged, but no error is returned.
     pub async fn publish<T: Serialize>(&self, event_type: &str, payload: T, metadata: EventMetadata) {
-        if !self.config.enabled {
+        if !self.config.enabled && !self.config.event_log {
             return;
         }
 
@@ -287,42 +746,164 @@ impl EventPublisher {
             payload,
             metadata,
         };
+        // Context for diagnostics below
+        let correlation_id = envelope.metadata.correlation_id.as_deref();
+        let session = envelope.metadata.session.as_deref();
 
-        // Build routing key: perth.pane.created -> perth.pane.created
-        let routing_key = event_type;
-
-        let body = match serde_json::to_vec(&envelope) {
+        let body = match serde_json::to_string(&envelope) {
             Ok(b) => b,
             Err(e) => {
-                eprintln!("Warning: Failed to serialize event {}: {}", event_type, e);
+                tracing::warn!(event_type, correlation_id, session, "failed to serialize event: {}", e);
                 return;
             }
         };
 
-        let channel = match self.get_channel().await {
-            Ok(c) => c,
-            Err(e) => {
-                // Graceful degradation: log warning but don't fail
-                eprintln!("Warning: Bloodbank unavailable, event {} not published: {}", event_type, e);
-                return;
+        if self.config.event_log {
+            let max_bytes = self.config.event_log_max_mb.max(1) * 1024 * 1024;
+            if let Err(e) = append_event_log(&event_log_path(), &body, max_bytes) {
+                tracing::warn!(event_type, correlation_id, session, "failed to append to the event log: {:#}", e);
             }
-        };
+        }
+        if !self.config.enabled {
+            return;
+        }
+
+        // Routing key is the event type: perth.pane.created -> perth.pane.created
+        let labels = [("event_type", event_type)];
+        if let Err(e) = self.deliver(event_type, &body).await {
+            // Graceful degradation: log warning but don't fail
+            match &self.outbox {
+                Some(outbox) => match outbox.push(&body).await {
+                    Ok(queued) => {
+                        metrics::inc("perth_events_queued_total", &labels);
+                        tracing::warn!(
+                            event_type,
+                            correlation_id,
+                            session,
+                            "Bloodbank unavailable, event queued for retry ({} pending): {:#}",
+                            queued,
+                            e
+                        )
+                    }
+                    Err(spool_err) => {
+                        metrics::inc("perth_events_dropped_total", &labels);
+                        tracing::warn!(
+                            event_type,
+                            correlation_id,
+                            session,
+                            "Bloodbank unavailable, event not published: {:#} (outbox: {:#})",
+                            e,
+                            spool_err
+                        )
+                    }
+                },
+                None => {
+                    metrics::inc("perth_events_dropped_total", &labels);
+                    tracing::warn!(event_type, correlation_id, session, "Bloodbank unavailable, event not published: {:#}", e)
+                }
+            }
+        } else {
+            metrics::inc("perth_events_published_total", &labels);
+        }
+    }
+
+    /// Publish `body`, sending anything already in the outbox first so
+    /// consumers see events in the order they happened.
+    #[cfg(feature = "events")]
+    async fn deliver(&self, routing_key: &str, body: &str) -> Result<()> {
+        let channel = self.get_channel().await?;
+        self.flush_outbox(&channel, &mut 0).await?;
+        self.basic_publish(&channel, routing_key, body).await
+    }
 
+    #[cfg(not(feature = "events"))]
+    async fn deliver(&self, _routing_key: &str, _body: &str) -> Result<()> {
+        Err(events_unavailable())
+    }
+
+    #[cfg(feature = "events")]
+    async fn basic_publish(&self, channel: &Channel, routing_key: &str, body: &str) -> Result<()> {
         let props = BasicProperties::default()
             .with_content_type("application/json".into())
             .with_delivery_mode(2); // Persistent
 
-        if let Err(e) = channel
+        let confirm = channel
             .basic_publish(
                 &self.config.exchange,
                 routing_key,
                 BasicPublishOptions::default(),
-                &body,
+                body.as_bytes(),
                 props,
             )
             .await
-        {
-            eprintln!("Warning: Failed to publish event {}: {}", event_type, e);
+            .with_context(|| format!("failed to publish event {}", routing_key))?;
+
+        // A timed-out event may still reach the broker and be sent again
+        // from the outbox; consumers should tolerate duplicates
+        match tokio::time::timeout(CONFIRM_TIMEOUT, confirm).await {
+            Ok(Ok(confirmation)) if confirmation.is_nack() => {
+                Err(anyhow::anyhow!("broker rejected event {}", routing_key))
+            }
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(e)) => Err(e).with_context(|| format!("failed to confirm event {}", routing_key)),
+            Err(_) => Err(anyhow::anyhow!(
+                "broker did not confirm event {} within {}s",
+                routing_key,
+                CONFIRM_TIMEOUT.as_secs()
+            )),
+        }
+    }
+
+    /// Publish queued events oldest first, counting them in `sent`, until
+    /// the outbox is empty or one fails.
+    #[cfg(feature = "events")]
+    async fn flush_outbox(&self, channel: &Channel, sent: &mut usize) -> Result<()> {
+        let Some(outbox) = &self.outbox else {
+            return Ok(());
+        };
+        loop {
+            let events = outbox.peek(OUTBOX_BATCH).await?;
+            if events.is_empty() {
+                return Ok(());
+            }
+            for event in events {
+                match serde_json::from_str::<Value>(&event)
+                    .ok()
+                    .and_then(|envelope| envelope.get("event_type")?.as_str().map(str::to_string))
+                {
+                    Some(routing_key) => {
+                        self.basic_publish(channel, &routing_key, &event).await?;
+                        metrics::inc("perth_events_published_total", &[("event_type", &routing_key)]);
+                    }
+                    None => tracing::warn!("dropping malformed event from outbox: {}", event),
+                }
+                // Another process flushing at the same time may have taken it already
+                if outbox.remove_front(&event).await? {
+                    *sent += 1;
+                }
+            }
+        }
+    }
+
+    /// Publish everything in the outbox now. Returns how many events were
+    /// sent; on failure the rest stay queued.
+    #[cfg(feature = "events")]
+    pub async fn flush(&self) -> Result<usize> {
+        if self.outbox.is_none() {
+            return Ok(0);
+        }
+        let channel = self.get_channel().await?;
+        let mut sent = 0;
+        let result = self.flush_outbox(&channel, &mut sent).await;
+        result.with_context(|| format!("stopped after publishing {} queued events", sent))?;
+        Ok(sent)
+    }
+
+    #[cfg(not(feature = "events"))]
+    pub async fn flush(&self) -> Result<usize> {
+        match self.outbox {
+            Some(_) => Err(events_unavailable()),
+            None => Ok(0),
         }
     }
 
@@ -348,6 +929,41 @@ impl EventPublisher {
         self.publish("perth.pane.opened", payload, metadata).await;
     }
 
+    /// Publish pane.stale event
+    pub async fn pane_stale(&self, record: &PaneRecord, reason: &str) {
+        let payload = PaneStatusPayload::new(record, reason);
+        let metadata = EventMetadata::default().with_session(&record.session);
+        self.publish("perth.pane.stale", payload, metadata).await;
+    }
+
+    /// Publish pane.reconciled event
+    pub async fn pane_reconciled(&self, record: &PaneRecord) {
+        let payload = PaneStatusPayload::new(record, "reconcile");
+        let metadata = EventMetadata::default().with_session(&record.session);
+        self.publish("perth.pane.reconciled", payload, metadata).await;
+    }
+
+    /// Publish pane.deleted event
+    pub async fn pane_deleted(&self, record: &PaneRecord, reason: &str) {
+        let payload = PaneStatusPayload::new(record, reason);
+        let metadata = EventMetadata::default().with_session(&record.session);
+        self.publish("perth.pane.deleted", payload, metadata).await;
+    }
+
+    /// Publish snapshot.created event
+    pub async fn snapshot_created(&self, snapshot: &SessionSnapshot) {
+        let payload = SnapshotCreatedPayload::from(snapshot);
+        let metadata = EventMetadata::default().with_session(&snapshot.session);
+        self.publish("perth.snapshot.created", payload, metadata).await;
+    }
+
+    /// Publish snapshot.restored event
+    pub async fn snapshot_restored(&self, snapshot: &SessionSnapshot, report: &RestoreReport) {
+        let payload = SnapshotRestoredPayload::new(snapshot, report);
+        let metadata = EventMetadata::default().with_session(&report.session);
+        self.publish("perth.snapshot.restored", payload, metadata).await;
+    }
+
     /// Publish tab.created event
     pub async fn tab_created(&self, record: &TabRecord) {
         let payload = TabCreatedPayload::from(record);
@@ -358,6 +974,42 @@ impl EventPublisher {
         self.publish("perth.tab.created", payload, metadata).await;
     }
 
+    /// Publish tab.archived event
+    pub async fn tab_archived(&self, record: &TabRecord, reason: Option<&str>) {
+        let payload = TabArchivedPayload {
+            tab_name: record.tab_name.clone(),
+            session: record.session.clone(),
+            correlation_id: record.correlation_id.clone(),
+            reason: reason.map(str::to_string),
+        };
+        let mut metadata = EventMetadata::default().with_session(&record.session);
+        if let Some(ref cid) = record.correlation_id {
+            metadata = metadata.with_correlation_id(cid);
+        }
+        self.publish("perth.tab.archived", payload, metadata).await;
+    }
+
+    /// Answer a command event with `<command>.completed` (carrying its
+    /// result) or `<command>.failed` (carrying the error).
+    pub async fn command_result(&self, request: &IncomingEvent, outcome: Result<&Value, &anyhow::Error>) {
+        let base = request.event_type.strip_suffix(".requested").unwrap_or(&request.event_type);
+        let (event_type, result, error) = match outcome {
+            Ok(result) => (format!("{}.completed", base), Some(result.clone()), None),
+            Err(e) => (format!("{}.failed", base), None, Some(format!("{:#}", e))),
+        };
+        let payload = CommandResultPayload {
+            request_type: request.event_type.clone(),
+            request_id: request.request_id().map(str::to_string),
+            result,
+            error,
+        };
+        let mut metadata = EventMetadata::default();
+        if let Some(cid) = request.correlation_id() {
+            metadata = metadata.with_correlation_id(cid);
+        }
+        self.publish(&event_type, payload, metadata).await;
+    }
+
     /// Publish intent.logged event
     pub async fn intent_logged(&self, pane_name: &str, entry: &IntentEntry, session: Option<&str>) {
         let payload = IntentLoggedPayload::new(pane_name, entry);
@@ -365,6 +1017,9 @@ impl EventPublisher {
         if let Some(s) = session {
             metadata = metadata.with_session(s);
         }
+        if let Some(ref cid) = entry.correlation_id {
+            metadata = metadata.with_correlation_id(cid);
+        }
         self.publish("perth.intent.logged", payload, metadata.clone()).await;
 
         // If it's a milestone, also publish the milestone.recorded event
@@ -406,7 +1061,8 @@ mod tests {
     fn test_intent_logged_payload() {
         let entry = IntentEntry::new("Test milestone")
             .with_type(IntentType::Milestone)
-            .with_source(IntentSource::Agent);
+            .with_source(IntentSource::Agent)
+            .with_source_detail("goose");
 
         let payload = IntentLoggedPayload::new("test-pane", &entry);
 
@@ -414,6 +1070,20 @@ mod tests {
         assert_eq!(payload.summary, "Test milestone");
         assert_eq!(payload.entry_type, "milestone");
         assert_eq!(payload.source, "agent");
+        assert_eq!(payload.source_detail.as_deref(), Some("goose"));
+    }
+
+    #[test]
+    fn test_snapshot_created_payload() {
+        let mut snapshot = SessionSnapshot::new("before-refactor", "dev");
+        snapshot.pane_count = 3;
+
+        let json = serde_json::to_value(SnapshotCreatedPayload::from(&snapshot)).unwrap();
+        assert_eq!(json["name"], "before-refactor");
+        assert_eq!(json["session"], "dev");
+        assert_eq!(json["snapshot_id"], snapshot.id.to_string());
+        assert_eq!(json["pane_count"], 3);
+        assert!(json.get("parent_id").is_none());
     }
 
     #[test]
@@ -427,6 +1097,62 @@ mod tests {
         assert_eq!(metadata.source, "perth");
     }
 
+    #[test]
+    fn test_incoming_event_correlation_id() {
+        let body = br#"{"event_type": "github.pr.merged", "payload": {"number": 42}, "metadata": {"correlation_id": "pr-42"}}"#;
+        let event = IncomingEvent::parse("github.pr.merged", body).unwrap();
+        assert_eq!(event.event_type, "github.pr.merged");
+        assert_eq!(event.correlation_id(), Some("pr-42"));
+
+        // Producers that put the ID in the payload, without an event_type
+        let body = br#"{"payload": {"correlation_id": "pr-7"}}"#;
+        let event = IncomingEvent::parse("github.pr.merged", body).unwrap();
+        assert_eq!(event.event_type, "github.pr.merged");
+        assert_eq!(event.correlation_id(), Some("pr-7"));
+
+        assert!(IncomingEvent::parse("x", b"not json").is_err());
+    }
+
+    #[test]
+    fn test_command_requests() {
+        let body = br#"{"payload": {"tab_name": "api(review)", "meta": {"owner": "ci", "n": 1}, "request_id": "r-1"},
+                        "metadata": {"correlation_id": "pr-42"}}"#;
+        let event = IncomingEvent::parse(TAB_CREATE_REQUESTED, body).unwrap();
+        let CommandRequest::CreateTab { tab_name, correlation_id, meta } = event.command().unwrap().unwrap() else {
+            panic!("expected a tab command");
+        };
+        assert_eq!(tab_name, "api(review)");
+        assert_eq!(correlation_id.as_deref(), Some("pr-42"));
+        assert_eq!(meta, HashMap::from([("owner".to_string(), "ci".to_string())]));
+        assert_eq!(event.request_id(), Some("r-1"));
+
+        let event = IncomingEvent::parse(PANE_CREATE_REQUESTED, br#"{"payload": {"name": "tests", "tab": "api"}}"#).unwrap();
+        assert_eq!(
+            event.command().unwrap().unwrap(),
+            CommandRequest::CreatePane {
+                pane_name: "tests".to_string(),
+                tab: Some("api".to_string()),
+                correlation_id: None,
+                meta: HashMap::new(),
+            }
+        );
+
+        let missing = IncomingEvent::parse(PANE_CREATE_REQUESTED, br#"{"payload": {}}"#).unwrap();
+        assert!(missing.command().unwrap().is_err());
+        let other = IncomingEvent::parse("github.pr.merged", br#"{"payload": {}}"#).unwrap();
+        assert!(other.command().is_none());
+    }
+
+    #[test]
+    fn test_topic_matches() {
+        assert!(topic_matches("perth.tab.create.requested", "perth.tab.create.requested"));
+        assert!(topic_matches("perth.*.create.requested", "perth.pane.create.requested"));
+        assert!(topic_matches("perth.#.requested", "perth.tab.create.requested"));
+        assert!(topic_matches("#", "github.pr.merged"));
+        assert!(!topic_matches("perth.*.requested", "perth.tab.create.requested"));
+        assert!(!topic_matches("github.pr.merged", "github.pr.merged.late"));
+    }
+
     #[test]
     fn test_publisher_disabled() {
         let config = BloodbankConfig {
@@ -437,4 +1163,29 @@ mod tests {
         let publisher = EventPublisher::new(config);
         assert!(!publisher.is_enabled());
     }
+
+    #[test]
+    fn test_event_log_rotation() {
+        let dir = std::env::temp_dir().join(format!("perth-events-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("events.jsonl");
+        let event = r#"{"event_type":"perth.pane.created"}"#;
+        let line_len = event.len() as u64 + 1;
+
+        // Two lines fit; each further line rotates
+        for _ in 0..6 {
+            append_event_log(&path, event, line_len * 2).unwrap();
+        }
+        let lines = |p: &Path| fs::read_to_string(p).map(|s| s.lines().count()).unwrap_or(0);
+        assert_eq!(lines(&path), 2);
+        assert_eq!(lines(&dir.join("events.jsonl.1")), 2);
+        assert_eq!(lines(&dir.join("events.jsonl.2")), 2);
+        assert!(!dir.join("events.jsonl.3").exists());
+
+        for _ in 0..6 {
+            append_event_log(&path, event, line_len * 2).unwrap();
+        }
+        assert!(dir.join("events.jsonl.3").exists());
+        assert!(!dir.join("events.jsonl.4").exists(), "only {} rotations are kept", EVENT_LOG_ROTATIONS);
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,226 @@
+//! Capability report for wrapper tools and agents (`zdrive capabilities`).
+//!
+//! Describes what this binary and its environment support so integrators
+//! can adapt up front instead of probing commands and parsing errors.
+
+use crate::config::{mask_amqp_url, mask_redis_url, Config};
+use crate::llm::{create_provider, LLMConfig};
+use crate::output::messages::count;
+use crate::zellij::{ZellijDriver, MIN_ZELLIJ_VERSION};
+use clap::CommandFactory;
+use semver::{Version, VersionReq};
+use serde::Serialize;
+use zellij_driver::multiplexer::MultiplexerDriver;
+
+pub const CAPABILITIES_SCHEMA_VERSION: &str = "1.0";
+
+const LLM_PROVIDERS: [&str; 5] = ["anthropic", "openai", "openai-compatible", "gemini", "ollama"];
+
+/// Zellij CLI features Perth relies on, with the version that introduced them.
+const ZELLIJ_FEATURES: [(&str, &str); 4] = [
+    ("query_tab_names", ">=0.39.0"),
+    ("dump_layout", ">=0.39.0"),
+    ("floating_panes", ">=0.39.0"),
+    ("pipe", ">=0.40.0"),
+];
+
+#[derive(Debug, Serialize)]
+pub struct Capabilities {
+    pub schema_version: &'static str,
+    pub version: &'static str,
+    pub commands: Vec<String>,
+    pub storage: StorageCapabilities,
+    pub llm: LlmCapabilities,
+    pub events: EventCapabilities,
+    pub plugin_support: bool,
+    pub zellij: ZellijCapabilities,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StorageCapabilities {
+    pub backend: &'static str,
+    pub url: String,
+    pub history_archive: bool,
+    pub trash_ttl_days: u64,
+    pub pane_ttl_days: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LlmCapabilities {
+    /// Provider selected in config
+    pub configured: String,
+    pub providers: Vec<ProviderCapability>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProviderCapability {
+    pub name: &'static str,
+    /// Credentials or endpoint are present, so the provider can be selected
+    pub available: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EventCapabilities {
+    pub bloodbank: BloodbankCapability,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BloodbankCapability {
+    pub enabled: bool,
+    pub url: String,
+    pub exchange: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ZellijCapabilities {
+    pub installed: bool,
+    pub version: Option<String>,
+    pub min_version: &'static str,
+    pub meets_minimum: bool,
+    pub in_session: bool,
+    pub session: Option<String>,
+    /// Zellij CLI features available with the installed version
+    pub features: Vec<&'static str>,
+}
+
+/// Probe the binary, configuration, and Zellij installation.
+pub async fn probe(config: &Config, zellij: &ZellijDriver) -> Capabilities {
+    let version = zellij.installed_version().await.ok();
+    let session = zellij.active_session_name();
+
+    Capabilities {
+        schema_version: CAPABILITIES_SCHEMA_VERSION,
+        version: env!("CARGO_PKG_VERSION"),
+        commands: crate::cli::Cli::command()
+            .get_subcommands()
+            .filter(|cmd| !cmd.is_hide_set())
+            .map(|cmd| cmd.get_name().to_string())
+            .collect(),
+        storage: StorageCapabilities {
+            backend: "redis",
+            url: mask_redis_url(&config.redis_url),
+            history_archive: config.storage.archive_history,
+            trash_ttl_days: config.storage.trash_ttl_days,
+            pane_ttl_days: config.storage.pane_ttl_days,
+        },
+        llm: LlmCapabilities {
+            configured: config.llm.provider.clone(),
+            providers: LLM_PROVIDERS
+                .iter()
+                .map(|&name| ProviderCapability {
+                    name,
+                    available: provider_available(&config.llm, name),
+                })
+                .collect(),
+        },
+        events: EventCapabilities {
+            bloodbank: BloodbankCapability {
+                enabled: config.bloodbank.enabled,
+                url: mask_amqp_url(&config.bloodbank.amqp_url),
+                exchange: config.bloodbank.exchange.clone(),
+            },
+        },
+        plugin_support: false,
+        zellij: ZellijCapabilities {
+            installed: version.is_some(),
+            version: version.as_ref().map(Version::to_string),
+            min_version: MIN_ZELLIJ_VERSION.trim_start_matches(">="),
+            meets_minimum: version.as_ref().is_some_and(|v| version_matches(v, MIN_ZELLIJ_VERSION)),
+            in_session: session.is_some(),
+            session,
+            features: version.as_ref().map(zellij_features).unwrap_or_default(),
+        },
+    }
+}
+
+fn provider_available(llm: &LLMConfig, name: &str) -> bool {
+    let config = LLMConfig {
+        provider: name.to_string(),
+        ..llm.clone()
+    };
+    let provider = create_provider(&config);
+    provider.name() != "noop" && provider.is_available()
+}
+
+fn version_matches(version: &Version, requirement: &str) -> bool {
+    VersionReq::parse(requirement).is_ok_and(|req| req.matches(version))
+}
+
+/// Zellij features supported by `version`.
+pub fn zellij_features(version: &Version) -> Vec<&'static str> {
+    ZELLIJ_FEATURES
+        .iter()
+        .filter(|(_, requirement)| version_matches(version, requirement))
+        .map(|(feature, _)| *feature)
+        .collect()
+}
+
+impl Capabilities {
+    /// Human-readable summary.
+    pub fn display(&self) -> String {
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+        let mut lines = vec![format!("zdrive {}", self.version), String::new()];
+
+        lines.push("Storage:".to_string());
+        lines.push(format!("  backend: {} ({})", self.storage.backend, self.storage.url));
+        lines.push(format!("  history archive: {}", yes_no(self.storage.history_archive)));
+        lines.push(format!("  trash: {}", count(self.storage.trash_ttl_days as usize, "day")));
+
+        lines.push(String::new());
+        lines.push(format!("LLM providers (configured: {}):", self.llm.configured));
+        for provider in &self.llm.providers {
+            lines.push(format!("  {}: {}", provider.name, if provider.available { "available" } else { "not configured" }));
+        }
+
+        lines.push(String::new());
+        lines.push("Event sinks:".to_string());
+        lines.push(format!("  bloodbank: {}", if self.events.bloodbank.enabled { "enabled" } else { "disabled" }));
+
+        lines.push(String::new());
+        lines.push("Zellij:".to_string());
+        match &self.zellij.version {
+            Some(version) => lines.push(format!(
+                "  version: {} (requires {}+{})",
+                version,
+                self.zellij.min_version,
+                if self.zellij.meets_minimum { "" } else { ", too old" }
+            )),
+            None => lines.push("  version: not installed".to_string()),
+        }
+        lines.push(format!(
+            "  session: {}",
+            self.zellij.session.as_deref().unwrap_or("(not inside a session)")
+        ));
+        if !self.zellij.features.is_empty() {
+            lines.push(format!("  features: {}", self.zellij.features.join(", ")));
+        }
+        lines.push(format!("  plugin support: {}", yes_no(self.plugin_support)));
+
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zellij_features_by_version() {
+        let old = Version::parse("0.39.2").unwrap();
+        assert!(!zellij_features(&old).contains(&"pipe"));
+        assert!(zellij_features(&old).contains(&"dump_layout"));
+
+        let new = Version::parse("0.41.0").unwrap();
+        assert!(zellij_features(&new).contains(&"pipe"));
+    }
+
+    #[test]
+    fn test_provider_available_requires_key() {
+        let llm = LLMConfig {
+            openai_api_key: Some("sk-test".to_string()),
+            ..Default::default()
+        };
+        assert!(provider_available(&llm, "openai"));
+        assert!(provider_available(&llm, "ollama"));
+    }
+}
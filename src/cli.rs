@@ -1,6 +1,10 @@
+pub use crate::output::OutputFormat;
+use crate::retention::parse_duration;
 use crate::types::{IntentSource, IntentType};
-use clap::{Args, Parser, Subcommand, ValueEnum};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 /// Split direction for pane creation
 #[derive(Debug, Clone, Copy, Default, ValueEnum)]
@@ -12,42 +16,1294 @@ pub enum SplitDirection {
     Horizontal,
 }
 
-/// Output format for commands
-#[derive(Debug, Clone, Copy, Default, ValueEnum)]
-pub enum OutputFormat {
-    /// Human-readable text (default)
-    #[default]
-    Text,
-    /// Pretty-printed JSON
-    Json,
-    /// Compact single-line JSON
-    JsonCompact,
-    /// Markdown with YAML frontmatter (Obsidian-compatible)
-    Markdown,
-    /// LLM-optimized context for prompt injection (~1000 tokens)
-    Context,
-}
-
 #[derive(Parser)]
 #[command(version, about = "Redis-backed Zellij pane manager")]
 pub struct Cli {
     #[command(subcommand)]
     pub command: Command,
+
+    /// Read from the primary Redis even when a read replica is configured
+    #[arg(long, global = true,
+          help = "Read from the primary Redis instead of redis_replica_url (avoids replication lag)")]
+    pub primary: bool,
+
+    /// Output format for every command
+    ///
+    /// The JSON formats print one document per command, carrying
+    /// schema_version and command fields alongside the command's own;
+    /// markdown, context, csv and jsonl apply to pane history and digest,
+    /// html to pane history.
+    #[arg(short = 'f', long, global = true, default_value = "text", value_enum,
+          help = "Output format: text, json, or json-compact (markdown, context, csv and jsonl for pane history and digest, html for pane history)")]
+    pub format: OutputFormat,
+
+    /// Show more diagnostics: -v for info, -vv for debug (Zellij actions, AMQP)
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Append diagnostics to this file instead of stderr (overrides log.file)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Never color output (same as NO_COLOR or display.theme.color = false)
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
+    /// Use ASCII in place of Unicode icons and emoji (overrides display.theme.ascii)
+    #[arg(long, global = true)]
+    pub ascii: bool,
 }
 
 #[derive(Subcommand)]
 pub enum Command {
     Pane(PaneArgs),
     Tab(TabArgs),
-    Reconcile,
+    /// Mark panes missing from the current session's layout as stale
+    ///
+    /// Panes found again are un-marked. With --prune, stale panes (in any
+    /// session) last seen longer ago than the threshold are deleted along
+    /// with their history, after confirmation.
+    #[command(
+        after_help = "EXAMPLES:
+    # Mark missing panes stale
+    zdrive reconcile
+
+    # Also delete panes stale for over a week (asks first)
+    zdrive reconcile --prune
+
+    # Unattended: delete panes not seen for 30 days, report as JSON
+    zdrive reconcile --prune=30d --yes --format json | jq '.pruned_panes'"
+    )]
+    Reconcile(ReconcileArgs),
+    /// Create the tabs and panes described in a workspace file
+    ///
+    /// Reads perth.toml or perth.kdl from the current directory or the
+    /// nearest parent that has one. Missing tabs and panes are created in
+    /// the current session with their cwds and run commands, and registered
+    /// in Redis; panes without history get the file's intent. Anything
+    /// already there is left alone, so running it again changes nothing.
+    #[command(
+        after_help = "EXAMPLES:
+    # Bring up the project's workspace
+    zdrive up
+
+    # Show what would be created, without touching anything
+    zdrive up --diff
+
+    # Use a specific file
+    zdrive up --file ~/work/api/perth.kdl
+
+WORKSPACE FILE (perth.toml):
+    [[tab]]
+    name = \"backend\"
+    cwd = \"services\"          # relative to the file
+    layout = \"vertical\"       # or horizontal
+
+    [[tab.pane]]
+    name = \"api\"
+    cwd = \"api\"               # relative to the tab's cwd
+    run = \"cargo watch -x run\"
+    intent = \"Wire up auth middleware\"
+
+WORKSPACE FILE (perth.kdl):
+    tab name=\"backend\" cwd=\"services\" {
+        pane name=\"api\" cwd=\"api\" run=\"cargo watch -x run\"
+    }
+
+NOTES:
+    - Existing panes are never modified; a different cwd, command or tab
+      is reported as drift
+    - Pane names are global, so each must be unique across the file"
+    )]
+    Up {
+        /// Workspace file to use instead of searching for one
+        #[arg(short, long, value_name = "PATH")]
+        file: Option<PathBuf>,
+
+        /// Show what would change without changing anything
+        #[arg(long)]
+        diff: bool,
+    },
     /// List all known panes organized by session and tab
-    List,
+    #[command(
+        after_help = "EXAMPLES:
+    # Everything, grouped by session and tab
+    zdrive list
+
+    # What was I doing in this session, most recent first?
+    zdrive list --session dev --sort last-accessed --long
+
+    # Panes reconcile couldn't find
+    zdrive list --stale-only"
+    )]
+    List(ListArgs),
+    /// Fuzzy-find a tracked pane and jump to it
+    ///
+    /// Matches against each pane's name, tab, session and last intent. The
+    /// best match is previewed while typing, Tab lists all matches, and
+    /// Enter switches to the pane's tab and focuses it. Panes in another
+    /// session print instructions for attaching to it instead.
+    #[command(
+        after_help = "EXAMPLES:
+    # Pick interactively (Enter on an empty query: most recently used pane)
+    zdrive jump
+
+    # Jump straight to the best match, e.g. from a keybinding
+    zdrive jump api refac"
+    )]
+    Jump {
+        /// Jump to the best match for this query without prompting
+        #[arg(num_args = 0.., trailing_var_arg = true)]
+        query: Vec<String>,
+
+        /// Include panes marked stale by reconcile
+        #[arg(long)]
+        stale: bool,
+    },
     /// Migrate data from v1.0 (znav:*) to v2.0 (perth:*) keyspace
     Migrate(MigrateArgs),
     /// View or modify configuration settings
     Config(ConfigArgs),
     /// Manage session snapshots for restoration
     Snapshot(SnapshotArgs),
+    /// Save session structures as reusable, parameterized templates
+    Template(TemplateArgs),
+    /// Seed a new pane from another pane's handoff bundle
+    ///
+    /// Parses a history export (markdown or JSON), creates the target pane,
+    /// seeds its history with the bundle's entries marked as imported, and
+    /// carries the goal over as pane metadata.
+    #[command(
+        after_help = "EXAMPLES:
+    # Export history from the current owner
+    zdrive pane history old-owner --format markdown > handoff.md
+
+    # Take over in a new pane
+    zdrive takeover handoff.md --pane new-owner-pane
+
+    # Take over into a specific tab with a new goal
+    zdrive takeover handoff.json --pane new-owner-pane --tab \"myapp(auth)\" --goal \"Finish token refresh\"
+
+BUNDLE FORMATS:
+    markdown     Output of 'pane history --format markdown' (goal: in frontmatter)
+    json         Output of 'pane history --format json' (optional \"goal\" field)"
+    )]
+    Takeover(TakeoverArgs),
+    /// Quickly log an entry for the current pane
+    ///
+    /// Prompts for a one-line summary and logs it (checkpoint by default).
+    /// Without a pane name, logs to the current pane (see 'zdrive here').
+    /// Designed to run in a floating pane launched by 'zdrive keybind install'.
+    #[command(
+        after_help = "PROMPT KEYS:
+    m / c / e    On an empty line, switch to milestone / checkpoint / exploration
+    #<TAB>       Complete tags used in this pane's history
+    @<TAB>       Complete a file path; @path tokens are logged as artifacts
+    Enter        Log the entry and exit
+    Ctrl-C       Exit without logging
+
+EXAMPLES:
+    # Log for the current pane
+    zdrive quicklog
+
+    # Log for a specific pane
+    zdrive quicklog my-feature"
+    )]
+    Quicklog {
+        /// Pane name (defaults to the current pane)
+        #[arg(help = "Pane to log for (default: the current pane)")]
+        pane: Option<String>,
+    },
+    /// Log an intent entry for the pane you're in
+    ///
+    /// Shortcut for 'zdrive pane log' that works out the current pane (see
+    /// 'zdrive here'), so it can be run from any shell inside Zellij.
+    #[command(
+        after_help = "EXAMPLES:
+    # Log a checkpoint for the current pane
+    zdrive log \"Fixed authentication bug\"
+
+    # Log a milestone with artifacts
+    zdrive log \"Completed REST API redesign\" --type milestone --artifacts src/api.rs
+
+    # Log for another pane
+    zdrive log \"Investigated caching strategies\" --pane research --type exploration"
+    )]
+    Log {
+        /// Brief description of what you accomplished or worked on
+        #[arg(help = "Summary of your work (e.g., 'Fixed login timeout issue')")]
+        summary: String,
+
+        /// Pane to log for instead of the current one
+        #[arg(short, long, help = "Pane to log for (default: the current pane)")]
+        pane: Option<String>,
+
+        /// Categorize this entry by type
+        #[arg(short = 't', long, default_value = "checkpoint", value_enum,
+              help = "Entry type: checkpoint (default), milestone, or exploration")]
+        entry_type: IntentType,
+
+        /// Source of this log entry
+        #[arg(short = 's', long, default_value = "manual", value_enum,
+              help = "Entry source: manual (default) or agent")]
+        source: IntentSource,
+
+        /// Program that logged the entry, e.g. claude-code, aider, goose
+        #[arg(long, value_name = "PROGRAM",
+              help = "Program logging the entry, e.g. claude-code (default: $PERTH_SOURCE_DETAIL)")]
+        source_detail: Option<String>,
+
+        /// Files or paths related to this work
+        #[arg(short = 'a', long = "artifacts", num_args = 1..,
+              help = "Files or artifacts associated with this work")]
+        artifacts: Vec<String>,
+
+        /// Labels for filtering history later, e.g. bugfix
+        #[arg(long = "tag", value_name = "TAG", value_parser = parse_tag,
+              help = "Tag the entry; repeat for several (e.g. --tag bugfix --tag auth)")]
+        tags: Vec<String>,
+    },
+    /// Show which tracked pane you're in
+    ///
+    /// Works out the current pane from $PERTH_PANE, then $ZELLIJ_PANE_ID,
+    /// then the session's focused pane, and finally the pane whose working
+    /// directory holds the current directory; if none is tracked it's an
+    /// error. 'zdrive log', 'zdrive quicklog' and the shell hook resolve the
+    /// pane the same way.
+    #[command(
+        after_help = "EXAMPLES:
+    # Which pane am I in?
+    zdrive here
+
+    # Just the name, for scripts
+    zdrive here -f json | jq -r .pane"
+    )]
+    Here,
+    /// Manage Zellij keybindings for zdrive helpers
+    Keybind(KeybindArgs),
+    /// Manage shell hooks that log checkpoints automatically
+    Hook(HookArgs),
+    /// Called by the shell hook after each command
+    #[command(name = "_hook", hide = true)]
+    HookTick,
+    /// Inspect and maintain stored Perth state
+    State(StateArgs),
+    /// Generate a Markdown document describing a workspace
+    ///
+    /// Documents each tab's layout and each pane's purpose (goal or latest
+    /// milestone), working directory, startup command, and recent milestones.
+    /// Living documentation for collaborators or agents joining a
+    /// long-lived workspace.
+    #[command(
+        after_help = "EXAMPLES:
+    # Describe every tab in the current session
+    zdrive describe
+
+    # Describe one tab and write it to a file
+    zdrive describe --tab \"myapp(dev)\" --output WORKSPACE.md
+
+    # Describe a session you're not attached to
+    zdrive describe --session work"
+    )]
+    Describe {
+        /// Only describe this tab
+        #[arg(long)]
+        tab: Option<String>,
+
+        /// Session to describe (default: current session)
+        #[arg(long)]
+        session: Option<String>,
+
+        /// Write to a file instead of stdout
+        #[arg(short = 'o', long)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Report the features available in this binary and environment
+    ///
+    /// Lists commands, storage settings, LLM providers with credentials,
+    /// event sinks, and the installed Zellij version and feature flags, so
+    /// wrapper tools and agents can adapt without trial and error.
+    #[command(
+        after_help = "EXAMPLES:
+    # Human-readable summary
+    zdrive capabilities
+
+    # Machine-readable report for integrations
+    zdrive capabilities --format json | jq '.zellij.features'"
+    )]
+    Capabilities,
+    /// Print the JSON Schema for a machine-readable output type
+    ///
+    /// Schemas are generated from the types Perth serializes and carry the
+    /// schema_version of the documents they describe. Without a type, all
+    /// schemas are printed in one object keyed by name.
+    #[command(
+        after_help = "EXAMPLES:
+    # Contract for `zdrive pane history --format json`
+    zdrive schema history
+
+    # Every schema on one line, e.g. for an agent's tool definitions
+    zdrive schema --format json-compact > perth-schemas.json"
+    )]
+    Schema {
+        /// Output type to describe (default: all)
+        #[arg(value_enum)]
+        kind: Option<crate::schema::SchemaKind>,
+    },
+    /// Low-level, stable, JSON-only commands for scripts and agents
+    ///
+    /// Plumbing output is a compatibility contract: every command prints one
+    /// JSON object with a plumbing_version field, and errors are printed as
+    /// {"error": "..."} with a non-zero exit status. Prefer these over
+    /// human-oriented commands when scripting.
+    #[command(
+        after_help = "EXAMPLES:
+    # Read a pane record
+    zdrive plumbing get-pane my-feature
+
+    # Write a fully-formed entry (JSON from stdin or --json)
+    echo '{\"summary\": \"Ran migrations\", \"entry_type\": \"checkpoint\"}' | zdrive plumbing put-entry my-feature
+
+    # List Perth keys and their Redis types
+    zdrive plumbing scan-keys --pattern 'perth:pane:*'
+
+    # Publish a custom event to Bloodbank
+    zdrive plumbing raw-event perth.agent.handoff --json '{\"pane\": \"my-feature\"}'"
+    )]
+    Plumbing(PlumbingArgs),
+    /// Recover or purge deleted snapshots and history
+    ///
+    /// Destructive commands move data to the trash instead of deleting it.
+    /// Trashed items are purged automatically after storage.trash_ttl_days
+    /// (default: 7). Set it to 0 to delete immediately.
+    #[command(
+        after_help = "EXAMPLES:
+    # See what was deleted recently
+    zdrive trash list
+
+    # Put a deleted snapshot or history back
+    zdrive trash restore 20250102T030405-1a2b3c4d
+
+    # Purge one item, or everything, permanently
+    zdrive trash empty 20250102T030405-1a2b3c4d
+    zdrive trash empty"
+    )]
+    Trash(TrashArgs),
+    /// Inspect LLM usage and spend
+    Llm(LlmArgs),
+    /// Test the secret filter that runs before anything is sent to an LLM
+    Filter(FilterArgs),
+    /// Consume external Bloodbank events and react to them
+    ///
+    /// Subscribes to bloodbank.completion_events (e.g. github.pr.merged).
+    /// When one arrives, every pane in the tabs with a matching correlation
+    /// ID gets a milestone, and those tabs are archived after
+    /// bloodbank.archive_grace (default 10m): closed in Zellij, their panes
+    /// marked stale, and perth.tab.archived published.
+    ///
+    /// Also carries out commands from bloodbank.command_events
+    /// (perth.tab.create.requested, perth.pane.create.requested), answering
+    /// each with a .completed or .failed event carrying the same
+    /// correlation ID.
+    #[command(
+        after_help = "EXAMPLES:
+    # Archive a PR's tab when the PR merges
+    zdrive config set bloodbank.enabled true
+    zdrive config set bloodbank.completion_events github.pr.merged
+    zdrive tab create \"myapp(review)\" --correlation-id pr-42
+    zdrive listen
+
+    # Give yourself an hour before the tab closes
+    zdrive config set bloodbank.archive_grace 1h
+
+    # Let another service open a tab; it gets perth.tab.create.completed back
+    #   routing key: perth.tab.create.requested
+    #   body: {\"payload\": {\"tab_name\": \"myapp(review)\", \"request_id\": \"r-1\"},
+    #          \"metadata\": {\"correlation_id\": \"pr-42\"}}
+    # perth.pane.create.requested takes pane_name, and optionally tab and meta.
+
+    # Only handle tab requests, on a queue of your own
+    zdrive config set bloodbank.command_events perth.tab.create.requested
+    zdrive config set bloodbank.listen_queue perth.ci.listen
+
+The correlation ID is read from the event's metadata.correlation_id, or
+payload.correlation_id. Events are consumed from the durable queue
+bloodbank.listen_queue (default <routing_key_prefix>.listen), so events
+sent while listen is stopped are handled when it restarts.
+
+With --metrics-addr, Prometheus metrics (commands, Redis and LLM latency,
+LLM tokens, events published/queued/dropped) are served at /metrics:
+    zdrive listen --metrics-addr 127.0.0.1:9464"
+    )]
+    Listen {
+        /// Serve Prometheus metrics on this address
+        #[arg(long, value_name = "ADDR",
+              help = "Serve Prometheus metrics at http://ADDR/metrics, e.g. 127.0.0.1:9464")]
+        metrics_addr: Option<std::net::SocketAddr>,
+    },
+    /// Keep pane state in Redis fresh in the background
+    ///
+    /// Runs reconcile on an interval: panes still in the layout get their
+    /// last_seen refreshed, panes closed in Zellij are marked stale
+    /// (perth.pane.stale), and stale panes that reappear are restored
+    /// (perth.pane.reconciled). Optionally snapshots the session too.
+    #[command(
+        after_help = "EXAMPLES:
+    # Reconcile every 30 seconds, from a background pane
+    zdrive watch
+
+    # Also snapshot the session every 10 minutes
+    zdrive watch --snapshot-interval 600
+
+    # Let Prometheus scrape it
+    zdrive watch --metrics-addr 127.0.0.1:9464
+
+    # Track pane changes as they happen with the perth-tracker plugin
+    zdrive watch --plugin ~/.local/share/perth/perth-tracker.wasm
+
+With --plugin, the plugin reports panes opening, closing, being renamed and
+gaining focus the moment Zellij does, so closed panes are marked stale at
+once; reconciles keep running on --interval as a safety net. Zellij asks to
+grant the plugin's permissions the first time it loads.
+
+Must run inside the Zellij session it watches; CTRL+C to stop."
+    )]
+    Watch {
+        /// Seconds between reconciles
+        #[arg(short, long, default_value = "30")]
+        interval: u64,
+
+        /// Also snapshot the session every this many seconds
+        #[arg(long, value_name = "SECS")]
+        snapshot_interval: Option<u64>,
+
+        /// Prefix for snapshot names
+        #[arg(long, default_value = "watch", requires = "snapshot_interval")]
+        prefix: String,
+
+        /// perth-tracker plugin to stream pane changes from
+        #[arg(long, value_name = "PATH|URL",
+              help = "perth-tracker.wasm path or Zellij plugin URL for real-time tracking")]
+        plugin: Option<String>,
+
+        /// Serve Prometheus metrics on this address
+        #[arg(long, value_name = "ADDR",
+              help = "Serve Prometheus metrics at http://ADDR/metrics, e.g. 127.0.0.1:9464")]
+        metrics_addr: Option<std::net::SocketAddr>,
+    },
+    /// Serve a local HTTP/JSON API for editors, status bars and dashboards
+    ///
+    /// Exposes pane info, history, logging, snapshots and metrics over HTTP,
+    /// backed by the same state as the CLI, so integrations don't have to
+    /// start zdrive for every request. Responses use the plumbing JSON shapes.
+    #[command(
+        after_help = "EXAMPLES:
+    # Serve on the default address
+    zdrive serve
+
+    # What the API serves
+    curl localhost:7433/panes
+    curl localhost:7433/panes/api-work
+    curl 'localhost:7433/panes/api-work/history?last=5'
+    curl -X POST localhost:7433/panes/api-work/history -d '{\"summary\": \"Wired token refresh\"}'
+    curl -X POST localhost:7433/snapshots -d '{\"name\": \"before-refactor\"}'
+    curl localhost:7433/snapshots
+    curl localhost:7433/metrics
+
+Errors are {\"error\": ..., \"kind\": ...} with a matching status: 404 for
+not_found, 503 when Redis or Zellij is unavailable. Taking a snapshot needs
+serve to run inside the Zellij session. There is no authentication, so keep
+the default loopback address unless the network is trusted."
+    )]
+    Serve {
+        /// Address to listen on
+        #[arg(long, value_name = "ADDR", default_value = crate::serve::DEFAULT_ADDR)]
+        listen: std::net::SocketAddr,
+    },
+    /// Manage Bloodbank events that couldn't be published
+    Events(EventsArgs),
+    /// Ask the configured LLM a question about your intent history
+    ///
+    /// Picks the history entries most relevant to the question (by keyword,
+    /// matched against summaries, artifacts and pane names), sends them with
+    /// the question, and streams the answer. Requires LLM consent.
+    #[command(
+        after_help = "EXAMPLES:
+    # Search across every pane
+    zdrive ask what was I doing on the payments branch last week --since 14d
+
+    # Only one pane's history
+    zdrive ask --pane \"api(payments)\" why did we drop the retry queue
+
+    # Send more context for broad questions
+    zdrive ask --limit 50 summarize this month's milestones --since 30d"
+    )]
+    Ask {
+        /// The question, in plain words
+        #[arg(required = true, trailing_var_arg = true, value_name = "QUESTION")]
+        question: Vec<String>,
+
+        /// Only search this pane's history
+        #[arg(long, help = "Only search this pane's history")]
+        pane: Option<String>,
+
+        /// Only consider entries at or after this time
+        #[arg(long, value_parser = parse_time_bound,
+              help = "Only consider entries since a timestamp, date, or duration ago (e.g. '7d')")]
+        since: Option<DateTime<Utc>>,
+
+        /// Maximum entries to send as context
+        #[arg(long, default_value_t = crate::ask::DEFAULT_LIMIT,
+              help = "Maximum history entries to send with the question")]
+        limit: usize,
+    },
+    /// Summarize intent entries across all panes, by day and pane
+    ///
+    /// Collects live and archived history from every pane in the period.
+    /// With --summary, the configured LLM adds an executive summary on top
+    /// (requires LLM consent).
+    #[command(
+        after_help = "EXAMPLES:
+    # What happened in the last day (for standup)
+    zdrive digest
+
+    # Weekly review as Markdown, with an executive summary
+    zdrive digest --since 7d --format markdown --summary > week.md
+
+    # A specific week
+    zdrive digest --since 2025-03-03 --until 2025-03-09"
+    )]
+    Digest {
+        /// Start of the period
+        #[arg(long, value_parser = parse_time_bound, default_value = "1d",
+              help = "Include entries since a timestamp, date, or duration ago (default: 1d)")]
+        since: DateTime<Utc>,
+
+        /// End of the period (default: now)
+        #[arg(long, value_parser = parse_time_bound,
+              help = "Include entries until a timestamp, date, or duration ago (default: now)")]
+        until: Option<DateTime<Utc>>,
+
+        /// Add an LLM-written executive summary
+        #[arg(long, help = "Add an executive summary from the configured LLM")]
+        summary: bool,
+    },
+    /// List the tags used in intent history and how often, per pane
+    ///
+    /// Counts tags given with 'pane log --tag' and 'pane snapshot --tag'
+    /// across live and archived history. Panes without tagged entries are
+    /// left out.
+    #[command(
+        after_help = "EXAMPLES:
+    # Tags across every pane
+    zdrive tags
+
+    # One pane, as JSON
+    zdrive tags --pane my-feature -f json
+
+    # Then show the entries for a tag
+    zdrive pane history my-feature --tag bugfix"
+    )]
+    Tags {
+        /// Only count this pane's history
+        #[arg(long, help = "Only count tags in this pane's history")]
+        pane: Option<String>,
+    },
+    /// Track what a pane is working toward
+    ///
+    /// A pane has at most one active goal. Entries logged with
+    /// 'pane log --goal-delta' record progress toward it, and
+    /// 'pane history --format context' shows the goal with that progress.
+    /// Completing a goal logs a milestone.
+    #[command(
+        after_help = "EXAMPLES:
+    # Set the goal (replaces an active one, marking it abandoned)
+    zdrive goal set api \"Ship OAuth\"
+
+    # Record progress toward it
+    zdrive pane log api \"Wired token refresh\" --goal-delta \"Refresh tokens rotate\"
+
+    # Where things stand
+    zdrive goal show api
+
+    # Done: logs the milestone \"Completed goal: Ship OAuth\"
+    zdrive goal complete api --note \"Merged in #142\""
+    )]
+    Goal(GoalArgs),
+    /// Find the history entries that touched a file
+    Artifacts(ArtifactsArgs),
+    /// Reports derived from intent history
+    Report(ReportArgs),
+    /// Export intent history to files
+    Export(ExportArgs),
+    /// Keep notes in other tools up to date with intent history
+    Sync(SyncArgs),
+    /// Activity dashboard: entries by type, source and day, busiest panes
+    ///
+    /// Counts live and archived history in the period. Also shows the share
+    /// of entries that are milestones and the average time between
+    /// checkpoints within a pane. Bars fall back to ASCII when NO_COLOR is
+    /// set or output is not a terminal.
+    #[command(
+        after_help = "EXAMPLES:
+    # The last 30 days across every pane
+    zdrive stats
+
+    # One pane over the last week
+    zdrive stats --pane my-feature --since 7d
+
+    # For a dashboard
+    zdrive stats --top 20 -f json"
+    )]
+    Stats {
+        /// Only count entries from this pane
+        #[arg(long, help = "Only count entries from this pane")]
+        pane: Option<String>,
+
+        /// Start of the period
+        #[arg(long, value_parser = parse_time_bound, default_value = "30d",
+              help = "Include entries since a timestamp, date, or duration ago (default: 30d)")]
+        since: DateTime<Utc>,
+
+        /// End of the period (default: now)
+        #[arg(long, value_parser = parse_time_bound,
+              help = "Include entries until a timestamp, date, or duration ago (default: now)")]
+        until: Option<DateTime<Utc>>,
+
+        /// How many of the busiest panes to list
+        #[arg(long, default_value_t = 5, help = "Number of busiest panes to list (default: 5)")]
+        top: usize,
+    },
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    #[command(subcommand)]
+    pub action: ExportAction,
+}
+
+#[derive(Subcommand)]
+pub enum ExportAction {
+    /// Write an HTML timeline per pane and an index page linking them
+    ///
+    /// Each page is standalone (styles inline, no scripts) with one
+    /// collapsible section per day, the same as 'pane history --format
+    /// html'. Panes without entries are skipped. Existing pages in the
+    /// directory are overwritten.
+    #[command(
+        after_help = "EXAMPLES:
+    # Every pane, into ./recap
+    zdrive export html --out recap/
+
+    # One pane, including archived history
+    zdrive export html --out recap/ --pane my-feature --include-archived"
+    )]
+    Html {
+        /// Directory to write the pages to (created if missing)
+        #[arg(long, help = "Directory to write index.html and one page per pane to")]
+        out: PathBuf,
+
+        /// Only export this pane
+        #[arg(long, help = "Only export this pane")]
+        pane: Option<String>,
+
+        /// Also export entries archived beyond the history cap
+        #[arg(long, help = "Include archived entries evicted from the 100-entry history cap")]
+        include_archived: bool,
+    },
+}
+
+#[derive(Args)]
+pub struct SyncArgs {
+    #[command(subcommand)]
+    pub action: SyncAction,
+}
+
+#[derive(Subcommand)]
+pub enum SyncAction {
+    /// Write one note per pane into an Obsidian vault
+    ///
+    /// Notes carry YAML frontmatter (pane, session, tab, tags) and the
+    /// pane's history as Markdown. File names are stable per pane name.
+    /// Only notes whose history changed since the last sync are rewritten,
+    /// and nothing is ever deleted, including notes for panes that no
+    /// longer exist.
+    #[command(
+        after_help = "EXAMPLES:
+    # Sync every pane into ~/notes/zdrive
+    zdrive sync obsidian --vault ~/notes
+
+    # Into a different folder, with archived history
+    zdrive sync obsidian --vault ~/notes --folder Projects/Sessions --include-archived"
+    )]
+    Obsidian {
+        /// Path to the vault
+        #[arg(long, help = "Path to the Obsidian vault")]
+        vault: PathBuf,
+
+        /// Folder within the vault for the notes
+        #[arg(long, default_value = "zdrive",
+              help = "Folder within the vault to write notes to (default: zdrive)")]
+        folder: PathBuf,
+
+        /// Also include entries archived beyond the history cap
+        #[arg(long, help = "Include archived entries evicted from the 100-entry history cap")]
+        include_archived: bool,
+    },
+}
+
+#[derive(Args)]
+pub struct ReportArgs {
+    #[command(subcommand)]
+    pub action: ReportAction,
+}
+
+#[derive(Subcommand)]
+pub enum ReportAction {
+    /// Approximate hours spent per pane, tab or tag
+    ///
+    /// Work sessions are inferred from entry timestamps: entries further
+    /// apart than --gap start a new session, and each session counts from
+    /// its first entry to its last plus 15 minutes of lead-in. Reads live
+    /// and archived history from every pane. An estimate for invoicing or
+    /// retrospectives, not a timesheet.
+    #[command(
+        after_help = "EXAMPLES:
+    # Hours per pane over the last 7 days
+    zdrive report time
+
+    # This week (since Monday), by tag
+    zdrive report time --week --by tag
+
+    # A month by tab, as CSV for a spreadsheet
+    zdrive report time --since 2025-03-01 --until 2025-04-01 --by tab --csv > march.csv
+
+    # Treat breaks over an hour as the end of a session
+    zdrive report time --gap 1h -f json"
+    )]
+    Time {
+        /// What to group hours by
+        #[arg(long, value_enum, default_value = "pane",
+              help = "Group by pane (default), tab, or tag")]
+        by: crate::report::ReportGroup,
+
+        /// Report on the current week, from Monday
+        #[arg(long, conflicts_with = "since", help = "Report since Monday 00:00 local time")]
+        week: bool,
+
+        /// Start of the period
+        #[arg(long, value_parser = parse_time_bound, default_value = "7d",
+              help = "Include entries since a timestamp, date, or duration ago (default: 7d)")]
+        since: DateTime<Utc>,
+
+        /// End of the period (default: now)
+        #[arg(long, value_parser = parse_time_bound,
+              help = "Include entries until a timestamp, date, or duration ago (default: now)")]
+        until: Option<DateTime<Utc>>,
+
+        /// Idle time that ends a work session
+        #[arg(long, value_parser = parse_duration, default_value = "30m",
+              help = "Gap between entries that ends a session (default: 30m)")]
+        gap: Duration,
+
+        /// Print CSV instead of a table
+        #[arg(long, help = "Print CSV (key,hours,sessions,entries) for spreadsheets")]
+        csv: bool,
+    },
+}
+
+#[derive(Args)]
+pub struct ArtifactsArgs {
+    #[command(subcommand)]
+    pub action: ArtifactsAction,
+}
+
+#[derive(Subcommand)]
+pub enum ArtifactsAction {
+    /// Show which panes logged work on a file, most recent first
+    ///
+    /// Every logged entry's artifacts are indexed, so this answers "where
+    /// was I when I last worked on this file?" without reading every
+    /// pane's history. The path is matched both as given and resolved to
+    /// an absolute path, the way 'pane log --artifacts' stores it.
+    #[command(
+        after_help = "EXAMPLES:
+    # Where did I last work on this file?
+    zdrive artifacts who-touched src/auth.rs
+
+    # Every entry, as JSON
+    zdrive artifacts who-touched src/auth.rs --last 100 -f json
+
+    # Index entries logged before the index existed
+    zdrive artifacts reindex"
+    )]
+    WhoTouched {
+        /// File or artifact to look up
+        #[arg(help = "Path or artifact name as logged")]
+        path: String,
+
+        /// Maximum entries to show
+        #[arg(short = 'n', long = "last", default_value_t = 10,
+              help = "Show at most N entries (default: 10)")]
+        last: usize,
+    },
+    /// Rebuild the artifact index from all stored history
+    Reindex,
+}
+
+#[derive(Args)]
+pub struct GoalArgs {
+    #[command(subcommand)]
+    pub action: GoalAction,
+}
+
+#[derive(Subcommand)]
+pub enum GoalAction {
+    /// Set the goal a pane is working toward
+    Set {
+        /// Pane name
+        #[arg(help = "Name of the pane")]
+        name: String,
+
+        /// What the pane is working toward
+        #[arg(help = "The goal, e.g. 'Ship OAuth'")]
+        title: String,
+    },
+    /// Show the active goal and the progress logged toward it
+    Show {
+        /// Pane name
+        #[arg(help = "Name of the pane")]
+        name: String,
+    },
+    /// Complete the active goal and log a milestone for it
+    Complete {
+        /// Pane name
+        #[arg(help = "Name of the pane")]
+        name: String,
+
+        /// How the goal was reached, recorded as the milestone's goal delta
+        #[arg(short = 'n', long, help = "Closing note recorded on the milestone")]
+        note: Option<String>,
+    },
+    /// List every goal set for a pane, oldest first
+    List {
+        /// Pane name
+        #[arg(help = "Name of the pane")]
+        name: String,
+    },
+}
+
+#[derive(Args)]
+pub struct EventsArgs {
+    #[command(subcommand)]
+    pub action: EventsAction,
+}
+
+#[derive(Subcommand)]
+pub enum EventsAction {
+    /// Publish events queued while RabbitMQ was unavailable
+    ///
+    /// When an event can't be published, or the broker doesn't confirm it
+    /// within 5 seconds, it is kept in Redis (perth:events:outbox, up to
+    /// 10,000 events) instead of being dropped. Delivery is at-least-once,
+    /// so an event whose confirmation timed out may arrive twice.
+    /// The queue is sent oldest first before the next event that can be
+    /// published; this command sends it right away. Correlation IDs and
+    /// timestamps are kept as they were.
+    #[command(
+        after_help = "EXAMPLES:
+    # RabbitMQ is back; send what was missed
+    zdrive events flush"
+    )]
+    Flush,
+    /// Print Perth events from the exchange as they are published
+    ///
+    /// Binds a temporary queue to the Bloodbank exchange, so it sees events
+    /// without taking them from other consumers, and prints each one as it
+    /// arrives. The queue is removed when tail exits. Only RabbitMQ is
+    /// needed; Redis and Zellij are not.
+    #[command(
+        after_help = "EXAMPLES:
+    # Everything Perth publishes (<routing_key_prefix>.#)
+    zdrive events tail
+
+    # Only snapshot and tab events
+    zdrive events tail 'perth.snapshot.*' 'perth.tab.#'
+
+    # One envelope per line, for jq
+    zdrive events tail -f json-compact | jq .payload"
+    )]
+    Tail {
+        /// Routing-key patterns to watch
+        #[arg(help = "Routing-key patterns to watch, * for one word and # for any (default: <routing_key_prefix>.#)")]
+        patterns: Vec<String>,
+    },
+}
+
+#[derive(Args)]
+pub struct FilterArgs {
+    #[command(subcommand)]
+    pub action: FilterAction,
+}
+
+#[derive(Subcommand)]
+pub enum FilterAction {
+    /// Report what the secret filter would redact from a file or stdin
+    ///
+    /// Runs the same patterns snapshots use (including PII patterns when
+    /// filter.redact_pii is on), line by line, and lists each redaction
+    /// with the pattern that matched and a masked preview. Nothing is sent
+    /// anywhere and Redis is not needed. Exits with status 1 when anything
+    /// was redacted, so it can guard scripts.
+    #[command(
+        after_help = "EXAMPLES:
+    # Audit a file before sharing it
+    zdrive filter check .env.example
+
+    # Check what would be redacted from your shell history
+    tail -n 200 ~/.zsh_history | zdrive filter check -
+
+    # Machine-readable report
+    git diff | zdrive filter check - --format json"
+    )]
+    Check {
+        /// File to check, or - for stdin
+        #[arg(value_name = "FILE", help = "File to check, or - to read stdin")]
+        input: String,
+    },
+}
+
+#[derive(Args)]
+pub struct LlmArgs {
+    #[command(subcommand)]
+    pub action: LlmAction,
+}
+
+#[derive(Subcommand)]
+pub enum LlmAction {
+    /// Report tokens and estimated spend per provider and model
+    ///
+    /// Every snapshot records its input/output tokens and an estimated cost
+    /// from a built-in price table. Costs are list-price estimates; models
+    /// without a known price are shown as '-'. Local Ollama models are free.
+    #[command(
+        after_help = "EXAMPLES:
+    # All-time totals
+    zdrive llm usage
+
+    # This month only
+    zdrive llm usage --month
+
+    # A specific month
+    zdrive llm usage --month 2025-01
+
+    # Machine-readable report
+    zdrive llm usage --format json"
+    )]
+    Usage {
+        /// Only report this month (YYYY-MM); bare --month means the current month
+        #[arg(long, num_args = 0..=1, default_missing_value = "current", value_parser = parse_month,
+              help = "Only report one month, as YYYY-MM (default with no value: current month)")]
+        month: Option<String>,
+    },
+}
+
+#[derive(Args)]
+pub struct PlumbingArgs {
+    #[command(subcommand)]
+    pub action: PlumbingAction,
+}
+
+#[derive(Subcommand)]
+pub enum PlumbingAction {
+    /// Print a pane record ({"pane": null} if unknown)
+    GetPane {
+        /// Pane name
+        name: String,
+    },
+    /// Log an intent entry given as JSON
+    ///
+    /// Accepts the same shape as entries in 'pane history --format json'.
+    /// Only "summary" is required; id, timestamp, type and source default
+    /// as they would for 'pane log'.
+    PutEntry {
+        /// Pane name
+        pane: String,
+        /// Entry JSON (default: read from stdin)
+        #[arg(long)]
+        json: Option<String>,
+    },
+    /// List stored keys with their Redis types
+    ScanKeys {
+        /// Redis glob pattern
+        #[arg(long, default_value = "perth:*")]
+        pattern: String,
+    },
+    /// Publish an arbitrary event to Bloodbank
+    RawEvent {
+        /// Event type, e.g. perth.agent.handoff
+        event_type: String,
+        /// Payload JSON (default: read from stdin)
+        #[arg(long)]
+        json: Option<String>,
+        /// Correlation ID attached to the event metadata
+        #[arg(long)]
+        correlation_id: Option<String>,
+    },
+}
+
+#[derive(Args)]
+pub struct TrashArgs {
+    #[command(subcommand)]
+    pub action: TrashAction,
+}
+
+#[derive(Subcommand)]
+pub enum TrashAction {
+    /// List trashed items, most recently deleted first
+    List,
+    /// Restore a trashed item to where it was deleted from
+    Restore {
+        /// Trash item ID (from 'zdrive trash list')
+        #[arg(help = "ID of the trashed item to restore")]
+        id: String,
+    },
+    /// Permanently delete trashed items
+    Empty {
+        /// Only purge this item (default: purge everything)
+        #[arg(help = "ID of a single item to purge (default: all)")]
+        id: Option<String>,
+    },
+}
+
+#[derive(Args)]
+pub struct StateArgs {
+    #[command(subcommand)]
+    pub action: StateAction,
+}
+
+#[derive(Subcommand)]
+pub enum StateAction {
+    /// Delete history entries older than the configured retention policy
+    ///
+    /// Applies the [retention] config to every known pane's history,
+    /// including archived entries. Tag rules take precedence over type
+    /// rules; entries with no matching rule are kept.
+    #[command(
+        after_help = "EXAMPLES:
+    # Preview what would be removed
+    zdrive state gc --dry-run
+
+    # Remove expired entries
+    zdrive state gc
+
+CONFIGURATION (~/.config/zellij-driver/config.toml):
+    [retention]
+    exploration = \"30d\"
+    checkpoint = \"90d\"
+    milestone = \"never\"
+
+    [retention.tags]
+    wip = \"7d\"          # entries mentioning #wip
+    decision = \"never\"  # keep #decision entries regardless of type"
+    )]
+    Gc {
+        /// Show what would be removed without deleting anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Report key counts and approximate memory use
+    ///
+    /// Groups Perth's Redis keys into panes, histories, archives, annotations,
+    /// tabs, snapshots, indexes, and trash. Memory is estimated with MEMORY
+    /// USAGE on a sample of keys per group, so figures are approximate.
+    #[command(
+        after_help = "EXAMPLES:
+    # Overview plus the 10 longest histories
+    zdrive state stats
+
+    # Measure more keys for a tighter estimate
+    zdrive state stats --sample 1000 --top 20
+
+    # Machine-readable report
+    zdrive state stats --format json
+
+Use the results to tune [retention] and storage.pane_ttl_days before
+Redis grows unbounded (see 'zdrive state gc --help')."
+    )]
+    Stats {
+        /// Number of largest histories to list
+        #[arg(long, default_value_t = 10, help = "Number of largest histories to list")]
+        top: usize,
+
+        /// Keys measured per group for the memory estimate
+        #[arg(long, default_value_t = 100,
+              help = "Keys sampled per group with MEMORY USAGE (0 skips memory estimates)")]
+        sample: usize,
+    },
+
+    /// Re-encrypt stored history and snapshots under a new key
+    ///
+    /// Walks every Perth key in batches, moving values encrypted with the
+    /// old key to the new one in place, so rotating the key needs no
+    /// export/import cycle. Progress is saved after each batch: if the run
+    /// is interrupted, running the command again picks up where it stopped.
+    #[command(
+        after_help = "EXAMPLES:
+    # Generate a new key and rotate to it
+    export PERTH_KEY_NEW=$(openssl rand -base64 32)
+    zdrive state rekey --old-key-env PERTH_KEY --new-key-env PERTH_KEY_NEW
+    zdrive config set storage.encryption_key_env PERTH_KEY_NEW
+
+    # Ignore an interrupted run and start from the beginning
+    zdrive state rekey --old-key-env PERTH_KEY --new-key-env PERTH_KEY_NEW --restart
+
+Values already under the new key are skipped, so repeating a run is safe.
+Values written while a batch is being moved are left alone and reported as
+changed; run the command again to pick them up."
+    )]
+    Rekey {
+        /// Environment variable holding the current key
+        #[arg(long, value_name = "VAR")]
+        old_key_env: String,
+
+        /// Environment variable holding the key to move to
+        #[arg(long, value_name = "VAR")]
+        new_key_env: String,
+
+        /// Keys scanned per batch
+        #[arg(long, default_value_t = 100)]
+        batch: usize,
+
+        /// Start over instead of resuming an interrupted run
+        #[arg(long)]
+        restart: bool,
+    },
+}
+
+#[derive(Args)]
+pub struct KeybindArgs {
+    #[command(subcommand)]
+    pub action: KeybindAction,
+}
+
+#[derive(Subcommand)]
+pub enum KeybindAction {
+    /// Install a keybinding that opens 'zdrive quicklog' in a floating pane
+    ///
+    /// Adds the binding to your Zellij config. Re-running replaces the
+    /// previously installed binding rather than adding a second one.
+    #[command(
+        after_help = "EXAMPLES:
+    # Install with the default key (Alt q)
+    zdrive keybind install
+
+    # Use a different key
+    zdrive keybind install --key \"Ctrl Alt l\"
+
+    # Print the snippet without modifying the config
+    zdrive keybind install --print
+
+CONFIG FILE LOCATION:
+    $ZELLIJ_CONFIG_FILE, $ZELLIJ_CONFIG_DIR/config.kdl,
+    or ~/.config/zellij/config.kdl"
+    )]
+    Install {
+        /// Key combination to bind
+        #[arg(short, long, default_value = crate::keybind::DEFAULT_KEY,
+              help = "Zellij key combination (default: 'Alt q')")]
+        key: String,
+
+        /// Path to the Zellij config file
+        #[arg(short, long, help = "Zellij config file (default: auto-detected)")]
+        config: Option<String>,
+
+        /// Print the snippet instead of writing it
+        #[arg(long, help = "Print the keybinding snippet without modifying the config")]
+        print: bool,
+    },
+}
+
+#[derive(Args)]
+pub struct HookArgs {
+    #[command(subcommand)]
+    pub action: HookAction,
+}
+
+#[derive(Subcommand)]
+pub enum HookAction {
+    /// Install a prompt hook that logs checkpoints as you work
+    ///
+    /// After each command run inside Zellij, the hook counts it against the
+    /// current pane. Once hooks.checkpoint_commands commands have run, or
+    /// hooks.checkpoint_minutes have passed since the first of them, an
+    /// automated checkpoint is logged with the command count. Re-running
+    /// replaces the previously installed hook.
+    #[command(
+        after_help = "EXAMPLES:
+    # Install for your login shell ($SHELL)
+    zdrive hook install
+
+    # Install for a specific shell
+    zdrive hook install --shell fish
+
+    # Print the hook instead of editing your rc file
+    zdrive hook install --shell bash --print
+
+    # Checkpoint every 50 commands or hour, whichever comes first
+    zdrive config set hooks.checkpoint_commands 50
+    zdrive config set hooks.checkpoint_minutes 60
+
+RC FILE LOCATION:
+    zsh   $ZDOTDIR/.zshrc or ~/.zshrc
+    bash  ~/.bashrc
+    fish  $XDG_CONFIG_HOME/fish/config.fish or ~/.config/fish/config.fish"
+    )]
+    Install {
+        /// Shell to install for
+        #[arg(short, long, value_enum, help = "Shell to install for (default: detected from $SHELL)")]
+        shell: Option<crate::hook::Shell>,
+
+        /// Path to the rc file
+        #[arg(long, help = "Shell rc file (default: the shell's usual rc file)")]
+        rc: Option<String>,
+
+        /// Print the hook instead of writing it
+        #[arg(long, help = "Print the hook without modifying the rc file")]
+        print: bool,
+    },
+    /// Install a git post-commit hook that logs each commit
+    ///
+    /// Every commit is logged as an automated checkpoint with its subject,
+    /// SHA and changed files, to the tracked pane whose working directory is
+    /// inside the repository. Commits made where no pane is working are
+    /// skipped. An existing post-commit hook is kept; re-running replaces
+    /// the previously installed block.
+    #[command(
+        name = "install-git",
+        after_help = "EXAMPLES:
+    # Install in the repository you're in
+    zdrive hook install-git
+
+    # Install in another repository
+    zdrive hook install-git --repo ~/code/api
+
+    # Print the hook block without installing it
+    zdrive hook install-git --print"
+    )]
+    InstallGit {
+        /// Repository to install into
+        #[arg(long, help = "Repository to install into (default: current directory)")]
+        repo: Option<std::path::PathBuf>,
+
+        /// Print the hook block instead of writing it
+        #[arg(long, help = "Print the hook block without installing it")]
+        print: bool,
+    },
+    /// Called by the git post-commit hook
+    #[command(hide = true)]
+    Commit,
 }
 
 #[derive(Args)]
@@ -88,8 +1344,58 @@ CONFIG FILE LOCATION:
     # Set Redis with authentication
     zdrive config set redis_url redis://:password@localhost:6379/0
 
+    # Send read-only commands to a replica (see READ REPLICAS below)
+    zdrive config set redis_replica_url redis://replica.internal:6379/
+
 AVAILABLE SETTINGS:
-    redis_url    Redis connection URL (default: redis://127.0.0.1:6379/)"
+    redis_url              Redis connection URL (default: redis://127.0.0.1:6379/)
+    redis_replica_url      Read replica for list, history, state stats, describe (default: none)
+    storage.pane_ttl_days  Expire idle pane records after N days (default: 0 = never)
+    storage.archive_history  Archive entries beyond the 100-entry history cap (default: false)
+    storage.trash_ttl_days Keep deleted data in the trash for N days (default: 7, 0 = off)
+    storage.encryption_key_env  Env var holding the key history and snapshots are encrypted with
+    snapshot.auto_backup   Snapshot the session before prune, tab delete and restore (default: true)
+    retention.milestone    Max age of milestone entries, e.g. 365d (default: never)
+    retention.checkpoint   Max age of checkpoint entries, e.g. 90d (default: never)
+    retention.exploration  Max age of exploration entries, e.g. 30d (default: never)
+    release.auto_milestone Log \"released vX.Y.Z\" when the project version bumps (default: true)
+    release.changelog      Changelog diffed for release milestones (default: CHANGELOG.md)
+    bloodbank.completion_events  Comma-separated events that complete a correlated tab (zdrive listen)
+    bloodbank.archive_grace  Delay before a completed tab is archived, e.g. 1h (default: 10m)
+    bloodbank.command_events  Comma-separated command events zdrive listen carries out (default: perth.tab.create.requested,perth.pane.create.requested)
+    bloodbank.listen_queue  Durable queue zdrive listen consumes (default: <routing_key_prefix>.listen)
+    bloodbank.event_log  Append every event to $XDG_DATA_HOME/perth/events.jsonl, even without a broker (default: false)
+    bloodbank.event_log_max_mb  Size at which the event log rotates; 3 old logs are kept (default: 10)
+    filter.redact_pii      Also scrub emails, IP addresses and /home/<user> paths before
+                           sending anything to an LLM (default: false)
+    context.ignore_patterns  Comma-separated gitignore-style patterns skipped when finding
+                           recently modified files, e.g. \"fixtures/, *.parquet\" (.gitignore
+                           and dependency/build dirs are always skipped)
+    context.history_weight   Share of the LLM context budget for shell history (default: 3)
+    context.diff_weight      Share for the git diff (default: 4)
+    context.files_weight     Share for recently modified files (default: 1)
+    context.scrollback_weight  Share for terminal output (default: 2, 0 = drop when over budget)
+    hooks.checkpoint_commands  Shell hook logs a checkpoint after N commands (default: 25, 0 = off)
+    hooks.checkpoint_minutes   ...or once buffered commands span N minutes (default: 30, 0 = off)
+    tab.naming_pattern     Regex new tab names must match, without the correlation suffix
+                           (default: {name}({context}) form; empty = any name)
+    tab.enforce_naming     Reject non-conforming tab names unless --force (default: false)
+    log.file               Append diagnostics to this file instead of stderr (default: none)
+    observability.otlp_endpoint  OTLP/HTTP collector for traces of Zellij, Redis and LLM calls,
+                           e.g. http://localhost:4318 (default: none = tracing off)
+
+    Per-project overrides go in the config file, keyed by directory name or path:
+    [release.projects.scratch]
+    auto_milestone = false
+
+READ REPLICAS:
+    With redis_replica_url set, read-only commands (list, pane history,
+    state stats, describe) read from the replica; everything else uses
+    redis_url. Replicas apply writes asynchronously, so a read right after
+    a write may miss it, typically by well under a second and by longer
+    if the replica is lagging or reconnecting. Pass --primary to any
+    command to read from the primary, e.g. in scripts that log and then
+    immediately read back."
     )]
     Set {
         /// Configuration key to set
@@ -113,6 +1419,9 @@ AVAILABLE SETTINGS:
     # Revoke previously granted consent
     zdrive config consent --revoke
 
+    # Also allow the pane's recent terminal output (test failures, stack traces)
+    zdrive config consent --grant --scrollback
+
     # Check current consent status
     zdrive config show | grep consent
 
@@ -120,16 +1429,24 @@ WHAT DATA IS SHARED:
     When using the snapshot command with an LLM provider, the following
     data may be sent to the provider's servers:
 
-    - Shell command history (last ~50 commands)
-    - Git diff output showing recent changes
+    - Shell command history (last ~50 commands; from Atuin when it is active)
+    - Git diff stats (unstaged and staged), recent commit subjects, and
+      how far the branch is ahead of or behind its upstream
     - Current working directory path
     - Names of recently modified files
+    - With --scrollback consent: the last ~60 lines of the pane's terminal
+      output, captured with 'zellij action dump-screen' when the snapshot
+      runs from inside the pane
 
     This data is used to generate an AI-powered summary of your work.
     No data is sent without your explicit consent.
 
 PRIVACY NOTES:
-    - Secrets (API keys, passwords, tokens) are automatically filtered
+    - Secrets (API keys, passwords, tokens) are automatically filtered;
+      see what was removed with 'zdrive pane snapshot <PANE> --show-redactions'
+      or test any text with 'zdrive filter check <FILE|->'
+    - Emails, IP addresses and home directory paths are also scrubbed with
+      'zdrive config set filter.redact_pii true'
     - Data is sent only when you run the 'snapshot' command
     - You can revoke consent at any time
     - The 'none' provider never sends any data"
@@ -142,17 +1459,122 @@ PRIVACY NOTES:
         /// Revoke consent for LLM data sharing
         #[arg(long, conflicts_with = "grant")]
         revoke: bool,
+
+        /// Apply --grant/--revoke to including pane scrollback only
+        #[arg(long)]
+        scrollback: bool,
     },
 }
 
 #[derive(Args)]
-pub struct MigrateArgs {
-    /// Show what would be migrated without making changes
+#[command(after_help = "CONFLICT POLICIES:
+    keep-new     Leave the existing perth:* key untouched (default)
+    keep-old     Overwrite it with the v1 znav:* data
+    merge        Combine fields; where both have one, the most recently seen record wins
+    prompt       Show a field-level diff and choose old or new per field
+
+EXAMPLES:
+    # See which keys conflict
+    zdrive migrate --dry-run
+
+    # Decide field by field
+    zdrive migrate --on-conflict prompt")]
+pub struct MigrateArgs {
+    /// Show what would be migrated without making changes
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// What to do when the v2 key already exists with different data
+    #[arg(long, value_enum, default_value = "keep-new",
+          help = "When the target key already exists: keep-old, keep-new, merge, or prompt")]
+    pub on_conflict: OnConflict,
+}
+
+/// Resolution for migration targets that already exist
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OnConflict {
+    /// Overwrite with the v1 data
+    KeepOld,
+    /// Leave the existing v2 data
+    #[default]
+    KeepNew,
+    /// Combine fields, preferring the most recently seen record
+    Merge,
+    /// Choose per field interactively
+    Prompt,
+}
+
+#[derive(Args)]
+pub struct ReconcileArgs {
+    /// Delete stale panes last seen longer ago than this (default: 7d)
+    #[arg(long, value_name = "OLDER_THAN", num_args = 0..=1, require_equals = true,
+          default_missing_value = "7d", value_parser = parse_duration)]
+    pub prune: Option<Duration>,
+
+    /// Prune without asking for confirmation
+    #[arg(short, long, requires = "prune")]
+    pub yes: bool,
+}
+
+#[derive(Args)]
+pub struct ListArgs {
+    /// Only panes in this session
+    #[arg(long)]
+    pub session: Option<String>,
+
+    /// Only panes in this tab
+    #[arg(long)]
+    pub tab: Option<String>,
+
+    /// Only panes marked stale by reconcile
+    #[arg(long)]
+    pub stale_only: bool,
+
+    /// Pane order
+    #[arg(long, value_enum, default_value = "name")]
+    pub sort: crate::orchestrator::PaneSort,
+
+    /// Show each pane's last intent and when it was last accessed
+    #[arg(short, long)]
+    pub long: bool,
+}
+
+impl From<ListArgs> for crate::orchestrator::PaneListQuery {
+    fn from(args: ListArgs) -> Self {
+        Self {
+            session: args.session,
+            tab: args.tab,
+            stale_only: args.stale_only,
+            sort: args.sort,
+            details: args.long,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct TakeoverArgs {
+    /// Path to the handoff bundle
+    #[arg(help = "Handoff bundle file (markdown or JSON history export)")]
+    pub bundle: String,
+
+    /// Pane that takes over the work
+    #[arg(short, long, help = "Name of the pane to create and seed")]
+    pub pane: String,
+
+    /// Tab to create the pane in
     #[arg(long)]
-    pub dry_run: bool,
+    pub tab: Option<String>,
+
+    /// Session to create the pane in
+    #[arg(long)]
+    pub session: Option<String>,
+
+    /// Override the goal recorded in the bundle
+    #[arg(short, long, help = "Goal for the new owner (overrides the bundle's goal)")]
+    pub goal: Option<String>,
 }
 
-#[derive(Args)]
+#[derive(Args, Default)]
 pub struct PaneArgs {
     #[command(subcommand)]
     pub action: Option<PaneAction>,
@@ -163,6 +1585,18 @@ pub struct PaneArgs {
     pub session: Option<String>,
     #[arg(long = "meta", value_parser = parse_key_val)]
     pub meta: Vec<(String, String)>,
+    /// Command to run if the pane is created, e.g. "cargo watch -x test"
+    #[arg(long, value_name = "CMD")]
+    pub run: Option<String>,
+    /// Start --session in the background if it isn't running
+    #[arg(long, requires = "session")]
+    pub create_session: bool,
+    /// Attach to the pane's session afterwards without asking
+    #[arg(long, conflicts_with = "no_attach")]
+    pub attach: bool,
+    /// Leave the pane's session detached, just saying how to attach
+    #[arg(long)]
+    pub no_attach: bool,
 }
 
 #[derive(Subcommand)]
@@ -187,6 +1621,10 @@ pub enum PaneAction {
     # Use horizontal layout (stacked)
     znav pane batch --tab \"myapp(fixes)\" --panes a,b,c --layout horizontal
 
+    # Run a watcher, the tests and a server side by side
+    znav pane batch --tab \"myapp(dev)\" --panes watch,tests,server \
+        --run \"cargo watch -x check\" --run \"cargo test\" --run \"cargo run\"
+
 LAYOUT OPTIONS:
     vertical     Panes arranged side by side (default)
     horizontal   Panes stacked top to bottom
@@ -194,6 +1632,8 @@ LAYOUT OPTIONS:
 NOTES:
     - Creates panes sequentially in the specified tab
     - If --cwd has fewer entries than --panes, remaining panes use current dir
+    - Each --run goes to the next pane; panes past the last --run get a shell
+    - Commands are kept in pane metadata and run again when a snapshot is restored
     - All panes are registered in Redis for tracking
 
 RELATED COMMANDS:
@@ -216,6 +1656,11 @@ RELATED COMMANDS:
               help = "Working directories for each pane (e.g., '../dir1,../dir2')")]
         cwd: Vec<String>,
 
+        /// Command to run in each pane, in order (repeatable)
+        #[arg(short = 'r', long = "run", value_name = "CMD",
+              help = "Command to run in the next pane; repeat for each pane")]
+        run: Vec<String>,
+
         /// Split layout direction
         #[arg(short = 'l', long, default_value = "vertical", value_enum,
               help = "Pane layout: vertical (side by side) or horizontal (stacked)")]
@@ -225,7 +1670,18 @@ RELATED COMMANDS:
     /// Auto-generate an intent summary from recent work using LLM
     ///
     /// Collects context (shell history, git diff, modified files) and uses
-    /// an LLM provider to generate a summary of your recent work.
+    /// an LLM provider to generate a summary of your recent work. Progress
+    /// is shown while the response streams in; Ctrl-C cancels without
+    /// logging anything.
+    ///
+    /// With no provider configured (llm.provider = none), a checkpoint is
+    /// summarized locally from the branch, git status and most frequent
+    /// commands, without any network call. The same local summary is
+    /// offered when the LLM fails.
+    ///
+    /// Git and file context come from the pane's working directory: its
+    /// `cwd` metadata, or the one Zellij reports, falling back to the
+    /// current directory.
     #[command(
         after_help = "EXAMPLES:
     # Generate a snapshot for a pane
@@ -234,14 +1690,48 @@ RELATED COMMANDS:
     # Generate snapshot and view the result
     zdrive pane snapshot my-feature && zdrive pane history my-feature --last 1
 
+    # Inspect exactly what would be sent before granting consent
+    zdrive pane snapshot my-feature --dry-run
+
+    # Check what secret filtering removed, with the prompt
+    zdrive pane snapshot my-feature --dry-run --show-redactions
+
+    # Fall back to the local summary without asking if the LLM fails
+    zdrive pane snapshot my-feature --fallback
+
+    # Give a slow local model more time than the configured llm.timeout_secs
+    zdrive pane snapshot my-feature --timeout 180
+
+    # Tag the logged entry
+    zdrive pane snapshot my-feature --tag release
+
+    # Summarize a pane opened elsewhere from its own project directory
+    zdrive pane api --meta cwd=$HOME/src/api
+    zdrive pane snapshot api
+
 CONFIGURATION:
-    Requires an LLM provider to be configured. Set up in config:
+    Without a provider, summaries are built locally (no LLM). To use one:
     zdrive config set llm.provider anthropic
     zdrive config set llm.anthropic_api_key YOUR_API_KEY
 
     Or via environment variable:
     export ANTHROPIC_API_KEY=YOUR_API_KEY
 
+    Or point at a local OpenAI-compatible server (llama.cpp, vLLM, LM Studio):
+    zdrive config set llm.provider openai-compatible
+    zdrive config set llm.base_url http://localhost:8080/v1
+
+    Rate-limited (429) and server (5xx) errors are retried with backoff,
+    honoring Retry-After (default 3 retries; 0 disables):
+    zdrive config set llm.max_retries 5
+
+    Requests give up after llm.timeout_secs (default 30, max 600):
+    zdrive config set llm.timeout_secs 120
+
+    History, diff, files, and scrollback are trimmed to llm.context_tokens
+    (default 4000, 1500 for ollama), split by the context.*_weight settings:
+    zdrive config set llm.context_tokens 16000
+
 RELATED COMMANDS:
     zdrive pane log <PANE> <SUMMARY>  Manual entry logging
     zdrive pane history <PANE>        View logged entries"
@@ -250,6 +1740,29 @@ RELATED COMMANDS:
         /// Pane name to generate snapshot for
         #[arg(help = "Name of the pane to snapshot")]
         name: String,
+
+        /// Print the prompt that would be sent, without calling the LLM
+        #[arg(long, help = "Show the prompt, estimated tokens, and provider/model without sending anything")]
+        dry_run: bool,
+
+        /// List what secret filtering removed, without calling the LLM
+        #[arg(long, help = "Show which patterns redacted what (masked), without sending anything")]
+        show_redactions: bool,
+
+        /// Log the local heuristic summary if the LLM fails, without asking
+        #[arg(long, conflicts_with_all = ["dry_run", "show_redactions"],
+              help = "On LLM failure, log a local (no-LLM) summary instead of asking")]
+        fallback: bool,
+
+        /// Seconds to wait for the LLM, overriding llm.timeout_secs
+        #[arg(long, value_name = "SECS", value_parser = crate::llm::parse_timeout_secs, conflicts_with_all = ["dry_run", "show_redactions"],
+              help = "Seconds to wait for the LLM (1-600, default: llm.timeout_secs)")]
+        timeout: Option<u64>,
+
+        /// Labels for the logged entry, e.g. bugfix
+        #[arg(long = "tag", value_name = "TAG", value_parser = parse_tag, conflicts_with_all = ["dry_run", "show_redactions"],
+              help = "Tag the logged entry; repeat for several")]
+        tags: Vec<String>,
     },
 
     /// Log an intent entry to track your work on a pane
@@ -271,6 +1784,22 @@ RELATED COMMANDS:
     # Log from an AI agent (for agent integration)
     zdrive pane log my-feature \"Completed task analysis\" --source agent
 
+    # Attribute the entry to the agent that wrote it
+    zdrive pane log my-feature \"Completed task analysis\" --source agent --source-detail claude-code
+    PERTH_SOURCE_DETAIL=aider zdrive pane log my-feature \"Refactored parser\" --source agent
+
+    # Add files to the newest entry instead of logging a new one
+    zdrive pane log my-feature --append-artifacts last src/retry.rs tests/retry.rs
+
+    # ...or to an earlier entry, by the short ID shown in history
+    zdrive pane log my-feature --append-artifacts 3f2a9c1d docs/retry.md
+
+    # Tag the entry for filtering later
+    zdrive pane log my-feature \"Fixed token refresh\" --tag bugfix --tag auth
+
+    # Record progress toward the goal set with 'zdrive goal set'
+    zdrive pane log my-feature \"Wired token refresh\" --goal-delta \"Refresh tokens rotate\"
+
 RELATED COMMANDS:
     zdrive pane history <PANE>  View logged entries
     zdrive pane info <PANE>     Check pane status"
@@ -281,8 +1810,9 @@ RELATED COMMANDS:
         name: String,
 
         /// Brief description of what you accomplished or worked on
-        #[arg(help = "Summary of your work (e.g., 'Fixed login timeout issue')")]
-        summary: String,
+        #[arg(required_unless_present = "append_artifacts",
+              help = "Summary of your work (e.g., 'Fixed login timeout issue')")]
+        summary: Option<String>,
 
         /// Categorize this entry by type
         ///
@@ -301,6 +1831,14 @@ RELATED COMMANDS:
               help = "Entry source: manual (default) or agent")]
         source: IntentSource,
 
+        /// Program that logged the entry, e.g. claude-code, aider, goose
+        ///
+        /// Defaults to $PERTH_SOURCE_DETAIL, so an agent's environment can
+        /// attribute every entry it logs.
+        #[arg(long, value_name = "PROGRAM",
+              help = "Program logging the entry, e.g. claude-code (default: $PERTH_SOURCE_DETAIL)")]
+        source_detail: Option<String>,
+
         /// Files or paths related to this work
         ///
         /// Useful for tracking which files were modified or created.
@@ -308,6 +1846,128 @@ RELATED COMMANDS:
         #[arg(short = 'a', long = "artifacts", num_args = 1..,
               help = "Files or artifacts associated with this work")]
         artifacts: Vec<String>,
+
+        /// Labels for filtering history later, e.g. bugfix
+        ///
+        /// Stored lowercase without a leading '#'. Filter with
+        /// 'pane history --tag' and list with 'zdrive tags'.
+        #[arg(long = "tag", value_name = "TAG", value_parser = parse_tag,
+              help = "Tag the entry; repeat for several (e.g. --tag bugfix --tag auth)")]
+        tags: Vec<String>,
+
+        /// Link the entry to the pane's active goal
+        #[arg(long, help = "Link the entry to the pane's active goal (see 'zdrive goal')")]
+        goal: bool,
+
+        /// Progress made toward the active goal; links the entry to it
+        #[arg(long, value_name = "TEXT",
+              help = "Progress toward the active goal, shown in context output")]
+        goal_delta: Option<String>,
+
+        /// Add artifacts to an existing entry instead of logging a new one
+        ///
+        /// Takes the entry ID ('last' for the newest entry) followed by the
+        /// paths. The entry is updated in place in the pane's history.
+        #[arg(long = "append-artifacts", num_args = 2.., value_names = ["ENTRY", "PATH"],
+              conflicts_with_all = ["summary", "artifacts", "tags", "goal", "goal_delta"],
+              help = "Add paths to an existing entry: <ENTRY|last> <PATH>...")]
+        append_artifacts: Vec<String>,
+    },
+
+    /// Log an automated checkpoint if the pane's git branch changed
+    ///
+    /// Compares the current git branch with the last one seen for this pane
+    /// and logs "Switched from <old> to <new>" when they differ. Cheap enough
+    /// to run from a shell prompt hook. 'pane log' runs this check too.
+    #[command(
+        after_help = "EXAMPLES:
+    # Check the current directory's branch for a pane
+    zdrive pane branch-check my-feature
+
+    # Run from a bash prompt hook
+    PROMPT_COMMAND='zdrive pane branch-check my-feature >/dev/null 2>&1'"
+    )]
+    BranchCheck {
+        /// Pane name to check
+        #[arg(help = "Name of the pane to check")]
+        name: String,
+
+        /// Directory to read the branch from (default: current directory)
+        #[arg(long, help = "Working directory of the pane (default: current directory)")]
+        cwd: Option<String>,
+    },
+
+    /// Attach a note to an existing history entry
+    ///
+    /// Annotations form a thread under the entry rather than adding new
+    /// entries, so review findings stay attached to the work they refer to.
+    /// They are shown beneath the entry in history output and carried in
+    /// handoff bundles.
+    #[command(
+        after_help = "EXAMPLES:
+    # Find the entry ID (shown after the timestamp)
+    zdrive pane history my-feature --last 5
+
+    # Annotate it using the short ID
+    zdrive pane annotate my-feature 3f2a9c1d --note \"review: missing error handling in retry loop\"
+
+    # Record who left the note
+    zdrive pane annotate my-feature 3f2a9c1d -n \"fixed in 4e1b\" --author sam"
+    )]
+    Annotate {
+        /// Pane name whose history holds the entry
+        #[arg(help = "Name of the pane")]
+        name: String,
+
+        /// Entry ID or unambiguous prefix
+        #[arg(help = "Entry ID (full UUID or the short ID shown by 'pane history')")]
+        entry_id: String,
+
+        /// The annotation text
+        #[arg(short = 'n', long, help = "Annotation text")]
+        note: String,
+
+        /// Author of the annotation (default: $USER)
+        #[arg(long, help = "Who is adding the note (default: $USER)")]
+        author: Option<String>,
+    },
+
+    /// List the artifacts in a pane's history, checking or rewriting paths
+    ///
+    /// Artifacts are stored as absolute paths, which go stale when files
+    /// move. --check reports paths that no longer exist (exit code 1 if
+    /// any are missing); URLs and relative paths are not checked. --remap
+    /// rewrites a path prefix in the live history. Archived entries are
+    /// read-only and only counted.
+    #[command(
+        after_help = "EXAMPLES:
+    # Which files has this pane logged?
+    zdrive pane artifacts my-feature
+
+    # Flag paths that no longer exist
+    zdrive pane artifacts my-feature --check
+
+    # After moving the repo, preview and then rewrite its paths
+    zdrive pane artifacts my-feature --remap $HOME/code/api=$HOME/src/api --dry-run
+    zdrive pane artifacts my-feature --remap $HOME/code/api=$HOME/src/api --check"
+    )]
+    Artifacts {
+        /// Pane name whose history to read
+        #[arg(help = "Name of the pane")]
+        name: String,
+
+        /// Check that each absolute path still exists
+        #[arg(long, help = "Flag artifact paths that no longer exist")]
+        check: bool,
+
+        /// Rewrite paths under OLD to sit under NEW
+        #[arg(long, value_name = "OLD=NEW", value_parser = parse_remap,
+              help = "Replace the path prefix OLD with NEW in logged artifacts")]
+        remap: Option<(String, String)>,
+
+        /// Show what --remap would change without writing
+        #[arg(long, requires = "remap", help = "Preview --remap without changing history")]
+        dry_run: bool,
     },
 
     /// View the intent history for a pane
@@ -334,12 +1994,35 @@ RELATED COMMANDS:
     # Get LLM-optimized context for agent integration
     zdrive pane history my-feature --format context
 
+    # Page through history 20 entries at a time
+    zdrive pane history my-feature --last 20 --offset 20
+
+    # Entries from the last 2 days, or within a date range
+    zdrive pane history my-feature --since 2d
+    zdrive pane history my-feature --since 2025-01-01 --until 2025-01-31T18:00:00Z
+
+    # Include entries moved to the archive (requires storage.archive_history)
+    zdrive pane history my-feature --include-archived --since 90d
+
+    # Only entries tagged bugfix (see 'zdrive tags')
+    zdrive pane history my-feature --tag bugfix
+
+    # Read long histories a screenful at a time (Enter for more, q to quit)
+    zdrive pane history my-feature --page-size 10
+
+    # Show recent entries, then keep printing new ones as they are logged
+    zdrive pane history my-feature --last 5 --follow
+
+    # Stream new entries as JSON lines to another tool
+    zdrive pane history my-feature --last 0 --follow --format json-compact | jq .summary
+
 OUTPUT FORMATS:
     text         Human-readable with colors and relative timestamps
     json         Pretty-printed JSON with schema version
     json-compact Single-line JSON for scripting
     markdown     Markdown with YAML frontmatter (Obsidian-compatible)
-    context      LLM-optimized narrative for prompt injection (~1000 tokens)
+    context      LLM-optimized narrative for prompt injection, led by the active
+                 goal (~1000 tokens)
 
 RELATED COMMANDS:
     zdrive pane log <PANE> <SUMMARY>  Add new entries
@@ -357,6 +2040,25 @@ RELATED COMMANDS:
               help = "Show only the last N entries (default: all, up to 100)")]
         last: Option<usize>,
 
+        /// Skip the newest N matching entries
+        #[arg(long, default_value_t = 0,
+              help = "Skip the newest N matching entries (for paging)")]
+        offset: usize,
+
+        /// Only show entries at or after this time
+        #[arg(long, value_parser = parse_time_bound,
+              help = "Show entries since a timestamp, date, or duration ago (e.g. '2h', '7d')")]
+        since: Option<DateTime<Utc>>,
+
+        /// Only show entries at or before this time
+        #[arg(long, value_parser = parse_time_bound,
+              help = "Show entries until a timestamp, date, or duration ago (e.g. '30m')")]
+        until: Option<DateTime<Utc>>,
+
+        /// Also read entries archived beyond the history cap
+        #[arg(long, help = "Include archived entries evicted from the 100-entry history cap")]
+        include_archived: bool,
+
         /// Filter by entry type
         ///
         /// Show only entries of a specific type (milestone, checkpoint, exploration).
@@ -365,12 +2067,40 @@ RELATED COMMANDS:
               help = "Filter by entry type: milestone, checkpoint, or exploration")]
         entry_type: Option<crate::types::IntentType>,
 
-        /// Choose the output format
+        /// Only show entries with this tag
+        #[arg(long, value_parser = parse_tag,
+              help = "Filter by tag, e.g. bugfix")]
+        tag: Option<String>,
+
+        /// Show entries a page at a time
+        ///
+        /// On a terminal, waits for Enter between pages (q quits); otherwise
+        /// prints every page. Text format only.
+        #[arg(long, value_name = "N",
+              help = "Show N entries at a time, waiting for Enter between pages")]
+        page_size: Option<usize>,
+
+        /// Approximate token budget for --format context
+        ///
+        /// Overrides display.context.budget_tokens. Over budget, suggested
+        /// next steps are dropped first, then older recent entries, then
+        /// milestones.
+        #[arg(long, value_name = "TOKENS",
+              help = "Trim --format context output to about this many tokens")]
+        budget: Option<usize>,
+
+        /// Keep running and print entries as they are logged
+        ///
+        /// JSON formats print one entry per line. Stop with Ctrl-C.
+        #[arg(long, help = "Keep printing new entries as they are logged (text or JSON)")]
+        follow: bool,
+
+        /// Print straight to the terminal instead of through a pager
         ///
-        /// Use 'text' for reading, 'json' for tooling, 'context' for agents.
-        #[arg(short = 'f', long, default_value = "text", value_enum,
-              help = "Output format: text, json, json-compact, markdown, or context")]
-        format: OutputFormat,
+        /// Text and markdown output taller than the terminal normally goes
+        /// through display.pager, $PAGER or less.
+        #[arg(long, help = "Don't pipe long text or markdown output through a pager")]
+        no_pager: bool,
     },
 }
 
@@ -400,6 +2130,19 @@ pub enum TabAction {
     # Create tab with metadata
     znav tab create debug-session --correlation-id issue-123 --meta project=perth
 
+    # Name the tab after the checkout you're in, e.g. \"perth(feature-login)\"
+    znav tab create --from-git
+
+    # Require the {name}({context}) convention, with a one-off exception
+    znav config set tab.enforce_naming true
+    znav tab create scratch --force
+
+NAMING CONVENTION:
+    Names are checked against tab.naming_pattern (by default {name}({context}),
+    e.g. \"myapp(review)\"). Non-conforming names warn, or fail with --strict
+    or tab.enforce_naming; either way a corrected name is suggested.
+    An empty pattern turns the check off.
+
 CORRELATION IDS:
     Correlation IDs link tabs to events from external systems like Bloodbank.
     This enables end-to-end traceability in agentic workflows.
@@ -410,8 +2153,16 @@ RELATED COMMANDS:
     )]
     Create {
         /// Name for the new tab
-        #[arg(help = "Tab name (e.g., 'myapp(fixes)')")]
-        name: String,
+        #[arg(required_unless_present = "from_git", conflicts_with = "from_git",
+              help = "Tab name (e.g., 'myapp(fixes)')")]
+        name: Option<String>,
+
+        /// Name the tab after the git checkout in the current directory
+        ///
+        /// Uses the repository directory and branch, e.g. "perth(feature-login)",
+        /// or the short commit hash on a detached HEAD.
+        #[arg(long = "from-git", help = "Name the tab repo(branch) from the current git checkout")]
+        from_git: bool,
 
         /// Correlation ID for event traceability
         ///
@@ -423,11 +2174,16 @@ RELATED COMMANDS:
 
         /// Enforce tab naming convention
         ///
-        /// Fails if tab name doesn't match pattern: {name}({context})
-        /// Without --strict, non-conforming names show a warning but proceed.
+        /// Fails if tab name doesn't match tab.naming_pattern, {name}({context})
+        /// by default. Without --strict (or tab.enforce_naming), non-conforming
+        /// names show a warning but proceed.
         #[arg(long, help = "Enforce naming pattern, fail on non-conforming names")]
         strict: bool,
 
+        /// Create the tab even if its name breaks the naming convention
+        #[arg(long, conflicts_with = "strict", help = "Skip the naming convention check")]
+        force: bool,
+
         /// Additional metadata key=value pairs
         #[arg(long = "meta", value_parser = parse_key_val,
               help = "Metadata as key=value pairs")]
@@ -439,6 +2195,189 @@ RELATED COMMANDS:
         /// Tab name to get info for
         name: String,
     },
+
+    /// Find the tabs created for a correlation ID, in any session
+    #[command(
+        after_help = "EXAMPLES:
+    # Which tab was opened for PR 42?
+    zdrive tab find --correlation-id pr-42
+
+    # Jump an agent to it
+    zdrive tab find -c pr-42 --format json | jq -r '.tabs[0].tab_name'"
+    )]
+    Find {
+        /// Correlation ID the tab was created with
+        #[arg(short = 'c', long = "correlation-id",
+              help = "Correlation ID to look up (e.g., 'pr-42')")]
+        correlation_id: String,
+    },
+
+    /// List tracked tabs with correlation IDs, pane counts and last access
+    #[command(
+        after_help = "EXAMPLES:
+    # Tabs in the current session, most recently used first
+    zdrive tab list
+
+    # Tabs opened for a PR, across sessions
+    zdrive tab list --all-sessions --format json | jq '.tabs[] | select(.correlation_id == \"pr-42\")'"
+    )]
+    List {
+        /// Session to list (defaults to the current session)
+        #[arg(short, long, conflicts_with = "all_sessions")]
+        session: Option<String>,
+
+        /// List tabs from every session
+        #[arg(long, help = "Show tabs from all sessions")]
+        all_sessions: bool,
+    },
+
+    /// Stop tracking a tab (kept in the trash for storage.trash_ttl_days)
+    ///
+    /// Only the tab record is removed unless --close-zellij or --with-panes
+    /// is given. Closing the tab marks its panes stale; --with-panes deletes
+    /// their records and history as well.
+    #[command(
+        after_help = "EXAMPLES:
+    # Forget a tab, leaving it open
+    zdrive tab delete \"myapp(review)-pr-42\"
+
+    # Close it and delete its panes' history too
+    zdrive tab delete \"myapp(review)-pr-42\" --close-zellij --with-panes"
+    )]
+    Delete {
+        /// Tab name to delete
+        name: String,
+
+        /// Session the tab belongs to (defaults to the current session)
+        #[arg(short, long)]
+        session: Option<String>,
+
+        /// Also close the tab in Zellij if it's open
+        #[arg(long)]
+        close_zellij: bool,
+
+        /// Also delete the records and history of the tab's panes
+        #[arg(long)]
+        with_panes: bool,
+    },
+}
+
+#[derive(Args)]
+pub struct TemplateArgs {
+    #[command(subcommand)]
+    pub action: TemplateAction,
+}
+
+#[derive(Subcommand)]
+pub enum TemplateAction {
+    /// Save a session's tabs and tracked panes as a template
+    ///
+    /// Records each tracked pane's tab, name, working directory and --run
+    /// command. Every --var replaces its value wherever it appears with a
+    /// {{name}} placeholder, so the template can be applied to another
+    /// checkout or branch.
+    #[command(
+        after_help = "EXAMPLES:
+    # Save the current session's layout
+    zdrive template save api-dev
+
+    # Parameterize the repository path and branch
+    zdrive template save api-dev --var repo=$PWD --var branch=main
+
+    # Save a single tab of another session
+    zdrive template save review --session dev --tab \"api(review)\"
+
+REDIS SCHEMA:
+    Templates are stored at: perth:templates:{name}"
+    )]
+    Save {
+        /// Name for the template
+        name: String,
+
+        /// Description of what the template is for
+        #[arg(short, long)]
+        description: Option<String>,
+
+        /// Session to save (default: current session)
+        #[arg(short, long)]
+        session: Option<String>,
+
+        /// Only save this tab
+        #[arg(short, long)]
+        tab: Option<String>,
+
+        /// Replace VALUE with a {{NAME}} placeholder (repeatable)
+        #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_var)]
+        vars: Vec<(String, String)>,
+
+        /// Replace an existing template with the same name
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Create a template's tabs and panes in a session
+    ///
+    /// Placeholders are filled from --var, falling back to the values they
+    /// replaced when the template was saved. Like 'zdrive up', only missing
+    /// tabs and panes are created, so applying twice changes nothing.
+    #[command(
+        after_help = "EXAMPLES:
+    # Recreate the template in the current session
+    zdrive template apply api-dev
+
+    # Point it at another checkout and branch
+    zdrive template apply api-dev --var repo=~/work/api-fix --var branch=fix-auth
+
+    # From outside Zellij: set up a new session in the background
+    zdrive template apply api-dev --session fix-auth --var branch=fix-auth
+
+    # Put every pane in one tab of the current session
+    zdrive template apply api-dev --tab scratch
+
+    # Preview without creating anything
+    zdrive template apply api-dev --var branch=fix-auth --diff
+
+NOTES:
+    - Pane names are global; put a variable in them (e.g. api-{{branch}})
+      to apply a template more than once
+    - Inside Zellij only the current session can be targeted"
+    )]
+    Apply {
+        /// Template to apply
+        name: String,
+
+        /// Session to create the panes in (default: current session);
+        /// started in the background if it isn't running
+        #[arg(short, long)]
+        session: Option<String>,
+
+        /// Put every pane in this tab instead of the template's tabs
+        #[arg(short, long)]
+        tab: Option<String>,
+
+        /// Value for a template variable (repeatable)
+        #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_var)]
+        vars: Vec<(String, String)>,
+
+        /// Show what would change without changing anything
+        #[arg(long)]
+        diff: bool,
+    },
+
+    /// List saved templates
+    List,
+
+    /// Show a template's tabs, panes and variables
+    Show {
+        /// Template name
+        name: String,
+    },
+
+    /// Delete a template (kept in the trash for storage.trash_ttl_days)
+    Delete {
+        /// Template name
+        name: String,
+    },
 }
 
 #[derive(Args)]
@@ -468,9 +2407,19 @@ pub enum SnapshotAction {
     # Create snapshot and view as JSON
     zdrive snapshot create my-work --format json
 
+    # Keep the last 500 lines of every pane's output
+    zdrive snapshot create my-work --scrollback --scrollback-lines 500
+
+SCROLLBACK:
+    With --scrollback each pane is focused in turn and dumped with
+    'zellij action dump-screen'. Secrets are redacted and the text is
+    stored compressed in the snapshot; 'zdrive snapshot show --scrollback'
+    prints it. Requires consent: zdrive config consent --grant --scrollback
+
 REDIS SCHEMA:
     Snapshots are stored at: perth:snapshots:{session}:{name}
-    Metadata is indexed for fast lookup and listing."
+    perth:snapshot-ids:{uuid} points at each one, so parents are
+    resolved directly."
     )]
     Create {
         /// Name for this snapshot
@@ -487,10 +2436,15 @@ REDIS SCHEMA:
               help = "Parent snapshot for delta/incremental snapshot")]
         parent: Option<String>,
 
-        /// Output format
-        #[arg(short = 'f', long, default_value = "text", value_enum,
-              help = "Output format: text, json, or json-compact")]
-        format: OutputFormat,
+        /// Store the tail of each pane's terminal output
+        #[arg(long,
+              help = "Capture each pane's recent output (secret-filtered)")]
+        scrollback: bool,
+
+        /// Lines of output to keep per pane
+        #[arg(long, default_value_t = 200, requires = "scrollback",
+              help = "Lines of output to keep per pane with --scrollback (default: 200)")]
+        scrollback_lines: usize,
     },
 
     /// List all snapshots for the current session
@@ -513,11 +2467,6 @@ REDIS SCHEMA:
         #[arg(long,
               help = "Show snapshots from all sessions")]
         all_sessions: bool,
-
-        /// Output format
-        #[arg(short = 'f', long, default_value = "text", value_enum,
-              help = "Output format: text, json, or json-compact")]
-        format: OutputFormat,
     },
 
     /// Show details of a specific snapshot
@@ -529,19 +2478,75 @@ REDIS SCHEMA:
         #[arg(help = "Name of the snapshot to show")]
         name: String,
 
-        /// Output format
-        #[arg(short = 'f', long, default_value = "text", value_enum,
-              help = "Output format: text, json, or json-compact")]
-        format: OutputFormat,
+        /// Print each pane's captured output
+        #[arg(long,
+              help = "Print the output captured with 'snapshot create --scrollback'")]
+        scrollback: bool,
     },
 
-    /// Delete a snapshot
+    /// Delete a snapshot (kept in the trash for storage.trash_ttl_days)
     Delete {
         /// Snapshot name
         #[arg(help = "Name of the snapshot to delete")]
         name: String,
     },
 
+    /// Write a snapshot to a file, e.g. to move it to another machine
+    #[command(
+        after_help = "EXAMPLES:
+    # Export as JSON
+    zdrive snapshot export my-work -o my-work.json
+
+    # Export zstd-compressed (chosen by the .zst extension)
+    zdrive snapshot export my-work -o ~/dotfiles/perth/my-work.json.zst"
+    )]
+    Export {
+        /// Snapshot name
+        #[arg(help = "Name of the snapshot to export")]
+        name: String,
+
+        /// File to write
+        #[arg(short, long, value_name = "FILE",
+              help = "Output file; compressed with zstd when it ends in .zst")]
+        output: std::path::PathBuf,
+    },
+
+    /// Load a snapshot from a file written by 'snapshot export'
+    #[command(
+        after_help = "EXAMPLES:
+    # Import into the current session (or the one it was taken in)
+    zdrive snapshot import my-work.json.zst
+
+    # Import under another name into a specific session
+    zdrive snapshot import my-work.json --name laptop-work --session dev
+
+BEHAVIOR:
+    - Compressed and plain exports are both accepted
+    - The export's schema_version must have the same major version as
+      this zdrive's snapshots
+    - An existing snapshot with the same name is kept unless --force"
+    )]
+    Import {
+        /// File to read
+        #[arg(value_name = "FILE", help = "Snapshot export file (.json or .json.zst)")]
+        file: std::path::PathBuf,
+
+        /// Store under another name
+        #[arg(long,
+              help = "Name for the imported snapshot (default: its exported name)")]
+        name: Option<String>,
+
+        /// Session to store it under
+        #[arg(short, long,
+              help = "Session to import into (default: current, else the snapshot's own)")]
+        session: Option<String>,
+
+        /// Replace an existing snapshot with the same name
+        #[arg(long,
+              help = "Overwrite an existing snapshot with the same name")]
+        force: bool,
+    },
+
     /// Restore a session from a snapshot
     ///
     /// Recreates tabs and panes from a saved snapshot. Handles missing panes
@@ -557,11 +2562,25 @@ REDIS SCHEMA:
     # Dry run (show what would be restored)
     zdrive snapshot restore my-work --dry-run
 
+    # Restore into a new background session
+    zdrive snapshot restore my-work --into laptop --create-session
+
+    # Bring back just the debug tab from an older snapshot
+    zdrive snapshot restore last-week --tab debug
+
+    # Bring back one pane into its tab
+    zdrive snapshot restore last-week --pane api-logs
+
 BEHAVIOR:
     - Creates missing tabs
     - Creates panes with correct names and working directories
     - Restores focus state
     - Handles warnings for unnamed or failed panes
+    - Reports working directories missing on this machine; those panes
+      open in the session's default directory
+    - With --into, moves tracked pane and tab records to the new session
+    - With --tab/--pane, only the matching tab or pane is restored; panes
+      restored into an existing tab are added next to the ones there
     - Generates detailed restoration report"
     )]
     Restore {
@@ -574,10 +2593,25 @@ BEHAVIOR:
               help = "Dry run mode - show restoration plan without executing")]
         dry_run: bool,
 
-        /// Output format
-        #[arg(short = 'f', long, default_value = "text", value_enum,
-              help = "Output format: text, json, or json-compact")]
-        format: OutputFormat,
+        /// Session to restore into
+        #[arg(long, value_name = "SESSION",
+              help = "Restore into this session instead of the current one")]
+        into: Option<String>,
+
+        /// Start the --into session when it isn't running
+        #[arg(long, requires = "into",
+              help = "Start the --into session in the background if needed")]
+        create_session: bool,
+
+        /// Only restore this tab
+        #[arg(long, value_name = "NAME",
+              help = "Restore only the tab with this name")]
+        tab: Option<String>,
+
+        /// Only restore this pane
+        #[arg(long, value_name = "NAME",
+              help = "Restore only the pane with this name (with --tab, only from that tab)")]
+        pane: Option<String>,
     },
 
     /// Run snapshot daemon for automatic periodic snapshots
@@ -595,12 +2629,26 @@ BEHAVIOR:
     # Create incremental snapshots linked to previous auto-snapshot
     zdrive snapshot daemon --interval 600 --incremental
 
+    # Only snapshot (and summarize my-feature) when work looks complete
+    zdrive snapshot daemon --interval 30 --adaptive --pane my-feature
+
+    # Let Prometheus scrape the daemon
+    zdrive snapshot daemon --metrics-addr 127.0.0.1:9464
+
 BEHAVIOR:
     - Auto-names: <prefix>-YYYY-MM-DD-HHMMSS
     - Runs in foreground (CTRL+C to stop)
     - Logs each snapshot creation
     - Skips snapshot if no changes detected
-    - With --incremental, links to previous auto-snapshot"
+    - With --incremental, links to previous auto-snapshot
+
+ADAPTIVE MODE:
+    With --adaptive, --interval is the sampling period. A snapshot and an
+    LLM summary for --pane are only created when activity suggests a chunk
+    of work was completed:
+    - a new commit was made
+    - the git branch was switched
+    - a test command ran after --min-edits files were changed"
     )]
     Daemon {
         /// Interval between snapshots in seconds
@@ -617,6 +2665,26 @@ BEHAVIOR:
         #[arg(long,
               help = "Link snapshots as incremental (uses previous auto-snapshot as parent)")]
         incremental: bool,
+
+        /// Trigger snapshots from activity heuristics instead of every interval
+        #[arg(long, requires = "pane",
+              help = "Only snapshot when activity heuristics detect completed work")]
+        adaptive: bool,
+
+        /// Pane to generate LLM summaries for in adaptive mode
+        #[arg(long, requires = "adaptive",
+              help = "Pane to log LLM summaries for when a trigger fires")]
+        pane: Option<String>,
+
+        /// Edited files required before a test run triggers a snapshot
+        #[arg(long, default_value_t = crate::activity::DEFAULT_MIN_EDITS,
+              help = "Files edited before a test run counts as completed work (default: 3)")]
+        min_edits: usize,
+
+        /// Serve Prometheus metrics on this address
+        #[arg(long, value_name = "ADDR",
+              help = "Serve Prometheus metrics at http://ADDR/metrics, e.g. 127.0.0.1:9464")]
+        metrics_addr: Option<std::net::SocketAddr>,
     },
 }
 
@@ -639,6 +2707,15 @@ pub fn collect_meta(pairs: Vec<(String, String)>) -> HashMap<String, String> {
     map
 }
 
+fn parse_month(input: &str) -> Result<String, String> {
+    if input == "current" {
+        return Ok(Utc::now().format("%Y-%m").to_string());
+    }
+    NaiveDate::parse_from_str(&format!("{}-01", input), "%Y-%m-%d")
+        .map(|date| date.format("%Y-%m").to_string())
+        .map_err(|_| format!("invalid month '{}': expected YYYY-MM", input))
+}
+
 fn parse_key_val(input: &str) -> Result<(String, String), String> {
     let mut parts = input.splitn(2, '=');
     let key = parts
@@ -656,3 +2733,100 @@ fn parse_key_val(input: &str) -> Result<(String, String), String> {
 
     Ok((key.to_string(), value.to_string()))
 }
+
+fn parse_tag(input: &str) -> Result<String, String> {
+    let tag = crate::types::normalize_tag(input);
+    if tag.is_empty() {
+        return Err("tag cannot be empty".to_string());
+    }
+    if tag.contains(char::is_whitespace) {
+        return Err(format!("invalid tag '{}': tags cannot contain spaces", input.trim()));
+    }
+    Ok(tag)
+}
+
+fn parse_remap(input: &str) -> Result<(String, String), String> {
+    let (old, new) = input
+        .split_once('=')
+        .ok_or_else(|| format!("invalid remap '{}': expected OLD=NEW", input))?;
+    let (old, new) = (old.trim(), new.trim());
+    if old.is_empty() || new.is_empty() {
+        return Err(format!("invalid remap '{}': both prefixes are required", input));
+    }
+    Ok((old.to_string(), new.to_string()))
+}
+
+fn parse_var(input: &str) -> Result<(String, String), String> {
+    parse_key_val(input).map_err(|err| err.replacen("meta", "variable", 1))
+}
+
+/// Parse a time bound: RFC 3339 timestamp, `YYYY-MM-DD` date (UTC midnight),
+/// or a duration ago such as `30m`, `2h`, `7d`, `1w`.
+pub fn parse_time_bound(input: &str) -> Result<DateTime<Utc>, String> {
+    let input = input.trim();
+
+    if let Ok(ts) = DateTime::parse_from_rfc3339(input) {
+        return Ok(ts.with_timezone(&Utc));
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).expect("midnight is valid").and_utc());
+    }
+
+    let duration = parse_duration(input)
+        .map_err(|_| format!("invalid time '{}': use RFC 3339, YYYY-MM-DD, or a duration like 2h", input))?;
+
+    Ok(Utc::now() - duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_time_bound_rfc3339() {
+        let ts = parse_time_bound("2025-01-02T03:04:05+02:00").unwrap();
+        assert_eq!(ts.to_rfc3339(), "2025-01-02T01:04:05+00:00");
+    }
+
+    #[test]
+    fn test_parse_time_bound_date() {
+        let ts = parse_time_bound("2025-01-02").unwrap();
+        assert_eq!(ts.to_rfc3339(), "2025-01-02T00:00:00+00:00");
+    }
+
+    #[test]
+    fn test_parse_time_bound_duration() {
+        let ts = parse_time_bound("2h").unwrap();
+        let expected = Utc::now() - Duration::hours(2);
+        assert!((ts - expected).num_seconds().abs() < 5);
+    }
+
+    #[test]
+    fn test_parse_time_bound_rejects_garbage() {
+        assert!(parse_time_bound("yesterday").is_err());
+        assert!(parse_time_bound("5y").is_err());
+    }
+
+    #[test]
+    fn test_parse_month() {
+        assert_eq!(parse_month("2025-01").unwrap(), "2025-01");
+        assert_eq!(parse_month("current").unwrap(), Utc::now().format("%Y-%m").to_string());
+        assert!(parse_month("2025-13").is_err());
+        assert!(parse_month("January").is_err());
+    }
+
+    #[test]
+    fn test_parse_remap() {
+        assert_eq!(parse_remap("/old=/new").unwrap(), ("/old".to_string(), "/new".to_string()));
+        assert!(parse_remap("/old").is_err());
+        assert!(parse_remap("=/new").is_err());
+    }
+
+    #[test]
+    fn test_parse_tag() {
+        assert_eq!(parse_tag("#BugFix").unwrap(), "bugfix");
+        assert!(parse_tag(" # ").is_err());
+        assert!(parse_tag("two words").is_err());
+    }
+}
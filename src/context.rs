@@ -1,19 +1,39 @@
+use crate::activity::git_output;
 use crate::filter::SecretFilter;
 use crate::llm::SessionContext;
 use anyhow::{Context, Result};
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::{WalkBuilder, WalkState};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::time::{Duration, SystemTime};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
 
 const DEFAULT_HISTORY_LINES: usize = 20;
 const RECENT_FILE_THRESHOLD_SECS: u64 = 30 * 60; // 30 minutes
+const RECENT_COMMITS: usize = 5;
+const SCROLLBACK_LINES: usize = 60;
+const SCROLLBACK_MAX_CHARS: usize = 4000;
+/// Stop scanning for recent files after this many entries, so huge
+/// monorepos don't stall a snapshot.
+const MAX_FILES_VISITED: usize = 20_000;
+/// Wall-clock limit for the recent-file scan; slower walks return what
+/// they found in time.
+const RECENT_FILES_BUDGET: Duration = Duration::from_secs(2);
+
+/// Skipped even when not gitignored: dependency, build and virtualenv trees.
+const DEFAULT_IGNORE_PATTERNS: &[&str] =
+    &["node_modules/", "target/", "dist/", "build/", "__pycache__/", "venv/", "vendor/"];
 
 /// Collects context from the shell environment for LLM summarization.
 pub struct ContextCollector {
     filter: SecretFilter,
     history_lines: usize,
     recent_threshold: Duration,
+    scrollback: bool,
+    ignore_patterns: Vec<String>,
 }
 
 impl ContextCollector {
@@ -23,6 +43,8 @@ impl ContextCollector {
             filter: SecretFilter::new()?,
             history_lines: DEFAULT_HISTORY_LINES,
             recent_threshold: Duration::from_secs(RECENT_FILE_THRESHOLD_SECS),
+            scrollback: false,
+            ignore_patterns: Vec::new(),
         })
     }
 
@@ -32,9 +54,34 @@ impl ContextCollector {
             filter: SecretFilter::new()?,
             history_lines,
             recent_threshold: Duration::from_secs(recent_threshold_mins * 60),
+            scrollback: false,
+            ignore_patterns: Vec::new(),
         })
     }
 
+    /// Also capture the tail of the focused Zellij pane's scrollback.
+    ///
+    /// Zellij can only dump the focused pane, so this is meant for snapshots
+    /// run from inside the pane being summarized.
+    pub fn with_scrollback(mut self, enabled: bool) -> Self {
+        self.scrollback = enabled;
+        self
+    }
+
+    /// Filter collected text with `filter` instead of the default secret
+    /// patterns, e.g. to also scrub PII.
+    pub fn with_filter(mut self, filter: SecretFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Skip files matching these gitignore-style patterns (on top of
+    /// .gitignore and the built-in skips) when looking for recent files.
+    pub fn with_ignore_patterns(mut self, patterns: Vec<String>) -> Self {
+        self.ignore_patterns = patterns;
+        self
+    }
+
     /// Collect context from the current environment.
     pub fn collect(&self, pane_name: &str, cwd: Option<&Path>) -> Result<SessionContext> {
         let working_dir = match cwd {
@@ -46,27 +93,63 @@ impl ContextCollector {
         let shell_history = self.collect_shell_history()?;
 
         // Collect git info if in a git repo
-        let (git_branch, git_diff) = self.collect_git_info(&working_dir);
+        let git = self.collect_git_info(&working_dir);
 
         // Collect recently modified files
         let active_files = self.collect_recent_files(&working_dir)?;
 
-        // Apply secret filtering to all text content
-        let (filtered_history, _) = self.filter.filter_lines(&shell_history);
-        let filtered_diff = git_diff.map(|d| self.filter.filter(&d).text);
+        // Terminal output, for failures the command history alone doesn't show
+        let scrollback = if self.scrollback { self.collect_scrollback() } else { None };
+
+        // Apply secret filtering to all text content, keeping a record of
+        // what was removed for redaction reports
+        let (filtered_history, mut redactions) = self.filter.audit_lines(&shell_history, "shell history");
+        let mut scrub = |text: String, source: &str| {
+            let result = self.filter.filter(&text).with_source(source);
+            redactions.extend(result.redactions);
+            result.text
+        };
+        let filtered_diff = git.diff.map(|d| scrub(d, "git diff"));
+        let filtered_staged = git.staged_diff.map(|d| scrub(d, "staged changes"));
+        let filtered_scrollback = scrollback.map(|s| scrub(s, "terminal output"));
+        let cwd = scrub(working_dir.display().to_string(), "working directory");
+        let (filtered_commits, commit_redactions) = self.filter.audit_lines(&git.recent_commits, "recent commits");
+        redactions.extend(commit_redactions);
+        let (filtered_files, file_redactions) = self.filter.audit_lines(&active_files, "active files");
+        redactions.extend(file_redactions);
 
         Ok(SessionContext::new(pane_name)
-            .with_cwd(working_dir.display().to_string())
+            .with_cwd(cwd)
             .with_shell_history(filtered_history)
-            .with_active_files(active_files)
-            .with_optional_git_branch(git_branch)
-            .with_optional_git_diff(filtered_diff))
+            .with_active_files(filtered_files)
+            .with_optional_git_branch(git.branch)
+            .with_optional_git_diff(filtered_diff)
+            .with_optional_staged_diff(filtered_staged)
+            .with_recent_commits(filtered_commits)
+            .with_optional_ahead_behind(git.ahead_behind)
+            .with_optional_scrollback(filtered_scrollback)
+            .with_redactions(redactions))
+    }
+
+    /// Most recent shell commands, oldest first, with secrets filtered.
+    pub fn recent_commands(&self) -> Result<Vec<String>> {
+        let history = self.collect_shell_history()?;
+        Ok(self.filter.filter_lines(&history).0)
     }
 
     /// Collect recent commands from shell history.
+    ///
+    /// Atuin is preferred when its shell integration is active, and tried
+    /// as a last resort when there is no history file at all.
     fn collect_shell_history(&self) -> Result<Vec<String>> {
         let histfile = self.find_history_file();
 
+        if std::env::var_os("ATUIN_SESSION").is_some() || histfile.is_none() {
+            if let Some(commands) = self.collect_atuin_history() {
+                return Ok(commands);
+            }
+        }
+
         let Some(path) = histfile else {
             return Ok(Vec::new());
         };
@@ -81,7 +164,7 @@ impl ContextCollector {
         let content = String::from_utf8_lossy(&bytes);
 
         let shell = self.detect_shell();
-        let lines = self.parse_history(&content, &shell);
+        let lines = parse_history(&content, shell);
 
         // Take the last N lines
         Ok(lines
@@ -92,6 +175,24 @@ impl ContextCollector {
             .collect())
     }
 
+    /// Recent commands from Atuin, oldest first. None if Atuin is not
+    /// installed, fails, or has no history.
+    fn collect_atuin_history(&self) -> Option<Vec<String>> {
+        let output = Command::new("atuin")
+            .args(["history", "list", "--cmd-only", "--print0", "--limit"])
+            .arg(self.history_lines.to_string())
+            .output()
+            .ok()
+            .filter(|o| o.status.success())?;
+
+        let commands = parse_atuin_history(&String::from_utf8_lossy(&output.stdout));
+        if commands.is_empty() {
+            return None;
+        }
+        let skip = commands.len().saturating_sub(self.history_lines);
+        Some(commands.into_iter().skip(skip).collect())
+    }
+
     /// Find the appropriate history file based on shell and environment.
     fn find_history_file(&self) -> Option<PathBuf> {
         // First check HISTFILE environment variable
@@ -117,6 +218,31 @@ impl ContextCollector {
         candidates.into_iter().find(|p| p.exists())
     }
 
+    /// Dump the focused pane's scrollback and keep its tail. Returns None
+    /// outside Zellij or if the dump fails.
+    fn collect_scrollback(&self) -> Option<String> {
+        std::env::var_os("ZELLIJ")?;
+
+        // Created up front so the dump can't be redirected through a
+        // pre-existing file or symlink
+        let nanos = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).ok()?.subsec_nanos();
+        let path = std::env::temp_dir().join(format!("perth-scrollback-{}-{}.txt", std::process::id(), nanos));
+        fs::OpenOptions::new().write(true).create_new(true).open(&path).ok()?;
+
+        let dumped = Command::new("zellij")
+            .args(["action", "dump-screen", "--full"])
+            .arg(&path)
+            .output()
+            .is_ok_and(|o| o.status.success());
+        let bytes = fs::read(&path);
+        let _ = fs::remove_file(&path);
+        if !dumped {
+            return None;
+        }
+
+        scrollback_tail(&String::from_utf8_lossy(&bytes.ok()?), SCROLLBACK_LINES, SCROLLBACK_MAX_CHARS)
+    }
+
     /// Detect the current shell type.
     fn detect_shell(&self) -> ShellType {
         // Check SHELL environment variable
@@ -142,80 +268,8 @@ impl ContextCollector {
         ShellType::Bash // Default
     }
 
-    /// Parse history file content based on shell type.
-    fn parse_history(&self, content: &str, shell: &ShellType) -> Vec<String> {
-        match shell {
-            ShellType::Zsh => self.parse_zsh_history(content),
-            ShellType::Fish => self.parse_fish_history(content),
-            ShellType::Bash => self.parse_bash_history(content),
-        }
-    }
-
-    /// Parse zsh history format.
-    /// Format: `: timestamp:0;command` or just `command`
-    fn parse_zsh_history(&self, content: &str) -> Vec<String> {
-        content
-            .lines()
-            .filter_map(|line| {
-                let line = line.trim();
-                if line.is_empty() {
-                    return None;
-                }
-
-                // Handle extended history format: : timestamp:0;command
-                if line.starts_with(": ") {
-                    // Find the semicolon that separates timestamp from command
-                    if let Some(idx) = line.find(';') {
-                        let cmd = &line[idx + 1..];
-                        if !cmd.is_empty() {
-                            return Some(cmd.to_string());
-                        }
-                    }
-                    None
-                } else {
-                    // Simple format - just the command
-                    Some(line.to_string())
-                }
-            })
-            .collect()
-    }
-
-    /// Parse fish history format (YAML-like).
-    /// Format:
-    /// - cmd: command
-    ///   when: timestamp
-    fn parse_fish_history(&self, content: &str) -> Vec<String> {
-        content
-            .lines()
-            .filter_map(|line| {
-                let line = line.trim();
-                if line.starts_with("- cmd:") {
-                    Some(line.trim_start_matches("- cmd:").trim().to_string())
-                } else {
-                    None
-                }
-            })
-            .collect()
-    }
-
-    /// Parse bash history format (simple line-per-command).
-    fn parse_bash_history(&self, content: &str) -> Vec<String> {
-        content
-            .lines()
-            .filter_map(|line| {
-                let line = line.trim();
-                // Skip timestamp lines (start with #)
-                if line.is_empty() || line.starts_with('#') {
-                    None
-                } else {
-                    Some(line.to_string())
-                }
-            })
-            .collect()
-    }
-
-    /// Collect git branch and diff information.
-    fn collect_git_info(&self, cwd: &Path) -> (Option<String>, Option<String>) {
+    /// Collect branch, working-tree and commit information.
+    fn collect_git_info(&self, cwd: &Path) -> GitInfo {
         // Check if we're in a git repo
         let is_git = Command::new("git")
             .args(["rev-parse", "--is-inside-work-tree"])
@@ -225,52 +279,69 @@ impl ContextCollector {
             .unwrap_or(false);
 
         if !is_git {
-            return (None, None);
+            return GitInfo::default();
         }
 
-        // Get current branch
-        let branch = Command::new("git")
-            .args(["branch", "--show-current"])
-            .current_dir(cwd)
-            .output()
-            .ok()
-            .and_then(|o| {
-                if o.status.success() {
-                    String::from_utf8(o.stdout)
-                        .ok()
-                        .map(|s| s.trim().to_string())
-                        .filter(|s| !s.is_empty())
-                } else {
-                    None
-                }
-            });
-
-        // Get diff stat
-        let diff = Command::new("git")
-            .args(["diff", "--stat"])
-            .current_dir(cwd)
-            .output()
-            .ok()
-            .and_then(|o| {
-                if o.status.success() {
-                    String::from_utf8(o.stdout)
-                        .ok()
-                        .map(|s| s.trim().to_string())
-                        .filter(|s| !s.is_empty())
-                } else {
-                    None
-                }
-            });
-
-        (branch, diff)
+        GitInfo {
+            branch: git_output(cwd, &["branch", "--show-current"]),
+            diff: git_output(cwd, &["diff", "--stat"]),
+            staged_diff: git_output(cwd, &["diff", "--cached", "--stat"]),
+            recent_commits: git_output(cwd, &["log", "-n", &RECENT_COMMITS.to_string(), "--format=%s"])
+                .map(|log| log.lines().map(str::to_string).collect())
+                .unwrap_or_default(),
+            // Fails without an upstream, which leaves this unset
+            ahead_behind: git_output(cwd, &["rev-list", "--left-right", "--count", "@{upstream}...HEAD"])
+                .and_then(|counts| parse_ahead_behind(&counts)),
+        }
     }
 
     /// Collect files modified within the recent threshold.
+    ///
+    /// Directories are walked in parallel. The walk stops after
+    /// [`MAX_FILES_VISITED`] entries or [`RECENT_FILES_BUDGET`], whichever
+    /// comes first, and returns what it found so far.
     fn collect_recent_files(&self, cwd: &Path) -> Result<Vec<String>> {
         let now = SystemTime::now();
-        let mut recent = Vec::new();
+        let deadline = Instant::now() + RECENT_FILES_BUDGET;
+        let overrides = ignore_overrides(cwd, &self.ignore_patterns)?;
+        let visited = AtomicUsize::new(0);
+        let recent = Mutex::new(Vec::new());
+
+        // Hidden files are skipped and .gitignore is honored even outside a
+        // git repository
+        WalkBuilder::new(cwd)
+            .hidden(true)
+            .require_git(false)
+            .overrides(overrides)
+            .build_parallel()
+            .run(|| {
+                let (visited, recent) = (&visited, &recent);
+                Box::new(move |entry| {
+                    if visited.fetch_add(1, Ordering::Relaxed) >= MAX_FILES_VISITED || Instant::now() >= deadline {
+                        return WalkState::Quit;
+                    }
+                    let Ok(entry) = entry else {
+                        return WalkState::Continue;
+                    };
+                    if !entry.file_type().is_some_and(|t| t.is_file()) {
+                        return WalkState::Continue;
+                    }
 
-        self.walk_dir_recent(cwd, cwd, &now, &mut recent)?;
+                    let modified = entry.metadata().ok().and_then(|m| m.modified().ok());
+                    let is_recent = modified
+                        .and_then(|m| now.duration_since(m).ok())
+                        .is_some_and(|elapsed| elapsed < self.recent_threshold);
+                    if is_recent {
+                        // Make path relative to base
+                        if let Ok(relative) = entry.path().strip_prefix(cwd) {
+                            recent.lock().unwrap_or_else(|e| e.into_inner()).push(relative.display().to_string());
+                        }
+                    }
+                    WalkState::Continue
+                })
+            });
+
+        let mut recent = recent.into_inner().unwrap_or_else(|e| e.into_inner());
 
         // Sort by path for consistency
         recent.sort();
@@ -280,56 +351,133 @@ impl ContextCollector {
 
         Ok(recent)
     }
+}
 
-    /// Recursively walk directory looking for recently modified files.
-    fn walk_dir_recent(
-        &self,
-        base: &Path,
-        dir: &Path,
-        now: &SystemTime,
-        results: &mut Vec<String>,
-    ) -> Result<()> {
-        let entries = match fs::read_dir(dir) {
-            Ok(e) => e,
-            Err(_) => return Ok(()), // Skip unreadable directories
-        };
+/// Overrides that exclude the built-in skips plus `patterns`
+/// (gitignore syntax) under `root`.
+pub fn ignore_overrides(root: &Path, patterns: &[String]) -> Result<Override> {
+    let mut builder = OverrideBuilder::new(root);
+    let patterns = DEFAULT_IGNORE_PATTERNS.iter().copied().chain(patterns.iter().map(String::as_str));
+    for pattern in patterns {
+        // A leading ! turns an override glob into an ignore rule
+        builder
+            .add(&format!("!{}", pattern))
+            .with_context(|| format!("invalid ignore pattern '{}'", pattern))?;
+    }
+    builder.build().context("failed to build ignore patterns")
+}
 
-        for entry in entries.filter_map(|e| e.ok()) {
-            let path = entry.path();
-            let file_name = entry.file_name().to_string_lossy().to_string();
-
-            // Skip hidden files and common non-source directories
-            if file_name.starts_with('.')
-                || file_name == "node_modules"
-                || file_name == "target"
-                || file_name == "dist"
-                || file_name == "build"
-                || file_name == "__pycache__"
-                || file_name == ".git"
-            {
-                continue;
-            }
+/// Parse history file content based on shell type.
+fn parse_history(content: &str, shell: ShellType) -> Vec<String> {
+    match shell {
+        ShellType::Zsh => parse_zsh_history(content),
+        ShellType::Fish => parse_fish_history(content),
+        ShellType::Bash => parse_bash_history(content),
+    }
+}
 
-            if path.is_dir() {
-                self.walk_dir_recent(base, &path, now, results)?;
-            } else if path.is_file() {
-                if let Ok(metadata) = path.metadata() {
-                    if let Ok(modified) = metadata.modified() {
-                        if let Ok(elapsed) = now.duration_since(modified) {
-                            if elapsed < self.recent_threshold {
-                                // Make path relative to base
-                                if let Ok(relative) = path.strip_prefix(base) {
-                                    results.push(relative.display().to_string());
-                                }
-                            }
-                        }
-                    }
-                }
+/// Parse zsh history format.
+/// Format: `: timestamp:duration;command` or just `command`. Multi-line
+/// commands are stored with each line but the last ending in a backslash.
+fn parse_zsh_history(content: &str) -> Vec<String> {
+    let mut commands = Vec::new();
+    let mut pending: Option<String> = None;
+
+    for line in content.lines() {
+        let (text, continues) = match line.strip_suffix('\\') {
+            Some(text) => (text, true),
+            None => (line, false),
+        };
+        match pending.as_mut() {
+            Some(command) => {
+                command.push('\n');
+                command.push_str(text);
+            }
+            None => pending = Some(zsh_command(text.trim_start()).to_string()),
+        }
+        if !continues {
+            let command = pending.take().unwrap_or_default();
+            let command = command.trim();
+            if !command.is_empty() {
+                commands.push(command.to_string());
             }
         }
+    }
+    // A trailing continuation at end of file is still a command
+    if let Some(command) = pending.map(|c| c.trim().to_string()).filter(|c| !c.is_empty()) {
+        commands.push(command);
+    }
+    commands
+}
+
+/// Strip the extended-history `: <timestamp>:<duration>;` prefix, if present.
+/// Lines that only look similar (e.g. the `:` builtin) are kept whole.
+fn zsh_command(line: &str) -> &str {
+    let Some(rest) = line.strip_prefix(": ") else {
+        return line;
+    };
+    let Some((meta, command)) = rest.split_once(';') else {
+        return line;
+    };
+    let is_number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    match meta.split_once(':') {
+        Some((timestamp, duration)) if is_number(timestamp) && is_number(duration) => command,
+        _ => line,
+    }
+}
+
+/// Parse fish history format (YAML-like).
+/// Format:
+/// - cmd: command
+///   when: timestamp
+///
+/// Fish escapes newlines in commands as `\n` and backslashes as `\\`.
+fn parse_fish_history(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let command = unescape_fish(line.trim().strip_prefix("- cmd:")?.trim());
+            let command = command.trim();
+            (!command.is_empty()).then(|| command.to_string())
+        })
+        .collect()
+}
 
-        Ok(())
+fn unescape_fish(escaped: &str) -> String {
+    let mut command = String::with_capacity(escaped.len());
+    let mut chars = escaped.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            command.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => command.push('\n'),
+            Some('\\') => command.push('\\'),
+            Some(other) => {
+                command.push('\\');
+                command.push(other);
+            }
+            None => command.push('\\'),
+        }
     }
+    command
+}
+
+/// Parse bash history format (simple line-per-command).
+fn parse_bash_history(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            // Skip timestamp lines (start with #)
+            if line.is_empty() || line.starts_with('#') {
+                None
+            } else {
+                Some(line.to_string())
+            }
+        })
+        .collect()
 }
 
 impl Default for ContextCollector {
@@ -338,6 +486,25 @@ impl Default for ContextCollector {
     }
 }
 
+/// What `collect_git_info` found in the working directory's repository.
+#[derive(Debug, Default)]
+struct GitInfo {
+    branch: Option<String>,
+    diff: Option<String>,
+    staged_diff: Option<String>,
+    recent_commits: Vec<String>,
+    ahead_behind: Option<(usize, usize)>,
+}
+
+/// Parse `git rev-list --left-right --count @{upstream}...HEAD`
+/// ("<behind>\t<ahead>") into (ahead, behind).
+fn parse_ahead_behind(counts: &str) -> Option<(usize, usize)> {
+    let mut counts = counts.split_whitespace().map(str::parse::<usize>);
+    let behind = counts.next()?.ok()?;
+    let ahead = counts.next()?.ok()?;
+    Some((ahead, behind))
+}
+
 #[derive(Debug, Clone, Copy)]
 enum ShellType {
     Bash,
@@ -345,10 +512,43 @@ enum ShellType {
     Fish,
 }
 
+/// Parse `atuin history list --cmd-only --print0` output. Commands are
+/// NUL-separated, so multi-line commands stay whole.
+fn parse_atuin_history(output: &str) -> Vec<String> {
+    output
+        .split('\0')
+        .map(|command| command.trim_end_matches('\n').trim())
+        .filter(|command| !command.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// The last `max_lines` lines of a screen dump, without trailing blank
+/// lines, cut at a line boundary to at most `max_chars` characters.
+pub fn scrollback_tail(dump: &str, max_lines: usize, max_chars: usize) -> Option<String> {
+    let lines: Vec<&str> = dump.lines().map(str::trim_end).collect();
+    let end = lines.iter().rposition(|line| !line.is_empty())? + 1;
+
+    let mut tail = Vec::new();
+    let mut chars = 0;
+    for line in lines[..end].iter().rev().take(max_lines) {
+        chars += line.chars().count() + 1;
+        if chars > max_chars && !tail.is_empty() {
+            break;
+        }
+        tail.push(*line);
+    }
+    tail.reverse();
+    Some(tail.join("\n"))
+}
+
 // Extension trait for SessionContext to support optional fields
 trait SessionContextExt {
     fn with_optional_git_branch(self, branch: Option<String>) -> Self;
     fn with_optional_git_diff(self, diff: Option<String>) -> Self;
+    fn with_optional_scrollback(self, scrollback: Option<String>) -> Self;
+    fn with_optional_staged_diff(self, diff: Option<String>) -> Self;
+    fn with_optional_ahead_behind(self, counts: Option<(usize, usize)>) -> Self;
 }
 
 impl SessionContextExt for SessionContext {
@@ -365,54 +565,162 @@ impl SessionContextExt for SessionContext {
             None => self,
         }
     }
+
+    fn with_optional_scrollback(self, scrollback: Option<String>) -> Self {
+        match scrollback {
+            Some(s) => self.with_scrollback(s),
+            None => self,
+        }
+    }
+
+    fn with_optional_staged_diff(self, diff: Option<String>) -> Self {
+        match diff {
+            Some(d) => self.with_staged_diff(d),
+            None => self,
+        }
+    }
+
+    fn with_optional_ahead_behind(self, counts: Option<(usize, usize)>) -> Self {
+        match counts {
+            Some((ahead, behind)) => self.with_ahead_behind(ahead, behind),
+            None => self,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
-    fn test_parse_bash_history() {
-        let collector = ContextCollector::new().unwrap();
+    fn test_recent_files_respect_ignores() {
+        let root = std::env::temp_dir().join(format!("perth-recent-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for dir in ["src", "node_modules/pkg", "data", "logs"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in ["src/lib.rs", "node_modules/pkg/index.js", "data/big.csv", "logs/run.log", ".env"] {
+            fs::write(root.join(file), "x").unwrap();
+        }
+        fs::write(root.join(".gitignore"), "logs/\n").unwrap();
+
+        let collector = ContextCollector::new().unwrap().with_ignore_patterns(vec!["*.csv".to_string()]);
+        let recent = collector.collect_recent_files(&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
 
+        assert_eq!(recent, vec!["src/lib.rs"]);
+    }
+
+    #[test]
+    fn test_parse_ahead_behind() {
+        assert_eq!(parse_ahead_behind("0\t3\n"), Some((3, 0)));
+        assert_eq!(parse_ahead_behind("2\t1"), Some((1, 2)));
+        assert_eq!(parse_ahead_behind("fatal: no upstream"), None);
+        assert_eq!(parse_ahead_behind("4"), None);
+    }
+
+    #[test]
+    fn test_parse_atuin_history() {
+        let output = "git status\0cargo test \\\n  --release\0\0  \0ls\n\0";
+        assert_eq!(parse_atuin_history(output), vec!["git status", "cargo test \\\n  --release", "ls"]);
+        assert!(parse_atuin_history("").is_empty());
+    }
+
+    #[test]
+    fn test_scrollback_tail() {
+        let dump = "$ cargo test\nrunning 2 tests\ntest a ... ok   \ntest b ... FAILED\n\n$ \n\n\n";
+        assert_eq!(scrollback_tail(dump, 3, 1000).as_deref(), Some("test b ... FAILED\n\n$"));
+        assert_eq!(scrollback_tail(dump, 100, 25).as_deref(), Some("test b ... FAILED\n\n$"));
+        assert_eq!(scrollback_tail("\n  \n", 10, 100), None);
+
+        // A single line longer than the budget is still kept
+        assert_eq!(scrollback_tail(&"x".repeat(50), 10, 20).map(|s| s.len()), Some(50));
+    }
+
+    #[test]
+    fn test_parse_bash_history() {
         let content = "git status\ncargo build\n#12345678\nnpm install\n";
-        let parsed = collector.parse_bash_history(content);
+        let parsed = parse_bash_history(content);
 
         assert_eq!(parsed, vec!["git status", "cargo build", "npm install"]);
     }
 
     #[test]
     fn test_parse_zsh_history() {
-        let collector = ContextCollector::new().unwrap();
-
         // Extended format
         let content = ": 1704067200:0;git status\n: 1704067201:0;cargo build\n";
-        let parsed = collector.parse_zsh_history(content);
+        let parsed = parse_zsh_history(content);
 
         assert_eq!(parsed, vec!["git status", "cargo build"]);
     }
 
     #[test]
     fn test_parse_zsh_history_simple() {
-        let collector = ContextCollector::new().unwrap();
-
         // Simple format (no timestamps)
         let content = "git status\ncargo build\n";
-        let parsed = collector.parse_zsh_history(content);
+        let parsed = parse_zsh_history(content);
 
         assert_eq!(parsed, vec!["git status", "cargo build"]);
     }
 
     #[test]
     fn test_parse_fish_history() {
-        let collector = ContextCollector::new().unwrap();
-
         let content = "- cmd: git status\n  when: 1704067200\n- cmd: cargo build\n  when: 1704067201\n";
-        let parsed = collector.parse_fish_history(content);
+        let parsed = parse_fish_history(content);
 
         assert_eq!(parsed, vec!["git status", "cargo build"]);
     }
 
+    #[test]
+    fn test_parse_multiline_and_lookalike_commands() {
+        let zsh = ": 1704067200:0;for f in *; do\\\n  echo $f\\\ndone\n: true; echo hi\n";
+        assert_eq!(parse_zsh_history(zsh), vec!["for f in *; do\n  echo $f\ndone", ": true; echo hi"]);
+
+        let fish = "- cmd: echo 'a\\nb' C:\\\\dir\n  when: 1\n- cmd:\n";
+        assert_eq!(parse_fish_history(fish), vec!["echo 'a\nb' C:\\dir"]);
+    }
+
+    /// One line of a command: no backslashes or newlines, no surrounding
+    /// whitespace, and not starting with `#`.
+    const LINE: &str = "[^\\s\\\\#]([^\\n\\r\\\\]{0,20}[^\\s\\\\])?";
+
+    proptest! {
+        #[test]
+        fn prop_history_parsers_never_panic(content in "(\\PC|[\\n\\\\:;])*") {
+            for shell in [ShellType::Zsh, ShellType::Fish, ShellType::Bash] {
+                let _ = parse_history(&content, shell);
+            }
+        }
+
+        #[test]
+        fn prop_zsh_round_trip(commands in proptest::collection::vec(proptest::collection::vec(LINE, 1..4), 1..6)) {
+            let commands: Vec<String> = commands.iter().map(|lines| lines.join("\n")).collect();
+            let content: String = commands
+                .iter()
+                .enumerate()
+                .map(|(i, command)| format!(": {}:0;{}\n", 1704067200 + i, command.replace('\n', "\\\n")))
+                .collect();
+            prop_assert_eq!(parse_zsh_history(&content), commands);
+        }
+
+        #[test]
+        fn prop_fish_round_trip(commands in proptest::collection::vec(proptest::collection::vec(LINE, 1..4), 1..6)) {
+            let commands: Vec<String> = commands.iter().map(|lines| lines.join("\n")).collect();
+            let content: String = commands
+                .iter()
+                .map(|command| format!("- cmd: {}\n  when: 1704067200\n", command.replace('\n', "\\n")))
+                .collect();
+            prop_assert_eq!(parse_fish_history(&content), commands);
+        }
+
+        #[test]
+        fn prop_bash_round_trip(commands in proptest::collection::vec(LINE, 1..6)) {
+            let content: String = commands.iter().map(|command| format!("#1704067200\n{}\n", command)).collect();
+            prop_assert_eq!(parse_bash_history(&content), commands);
+        }
+    }
+
     #[test]
     fn test_detect_shell_from_env() {
         // This test just verifies the detect_shell method doesn't panic
@@ -425,10 +733,12 @@ mod tests {
         let collector = ContextCollector::new().unwrap();
         let cwd = std::env::current_dir().unwrap();
 
-        let (branch, _diff) = collector.collect_git_info(&cwd);
+        let git = collector.collect_git_info(&cwd);
 
         // We should be in a git repo for this project
-        assert!(branch.is_some(), "Expected to find a git branch");
+        assert!(git.branch.is_some(), "Expected to find a git branch");
+        assert!(!git.recent_commits.is_empty(), "Expected recent commit subjects");
+        assert!(git.recent_commits.len() <= RECENT_COMMITS);
     }
 
     #[test]
@@ -436,10 +746,13 @@ mod tests {
         let collector = ContextCollector::new().unwrap();
 
         // /tmp is typically not a git repo
-        let (branch, diff) = collector.collect_git_info(Path::new("/tmp"));
+        let git = collector.collect_git_info(Path::new("/tmp"));
 
-        assert!(branch.is_none());
-        assert!(diff.is_none());
+        assert!(git.branch.is_none());
+        assert!(git.diff.is_none());
+        assert!(git.staged_diff.is_none());
+        assert!(git.recent_commits.is_empty());
+        assert!(git.ahead_behind.is_none());
     }
 
     #[test]
@@ -0,0 +1,157 @@
+//! At-rest encryption for history entries and snapshots.
+//!
+//! With `storage.encryption_key_env` set, each history entry, each pane's
+//! last intent and each snapshot is sealed with AES-256-GCM before it is
+//! written to Redis, under the 32-byte key held base64-encoded in that
+//! environment variable. A sealed value is stored as `enc:v1:` followed by
+//! the base64 nonce and ciphertext. Values without the prefix are read as
+//! they are, so encryption can be turned on over existing data.
+//!
+//! `zdrive state rekey` moves sealed values from one key to another.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use std::borrow::Cow;
+
+/// Marks a sealed value; the version leaves room for another scheme.
+pub const SEALED_PREFIX: &str = "enc:v1:";
+
+/// Key length in bytes (AES-256)
+pub const KEY_LEN: usize = 32;
+
+/// Nonce length in bytes (96-bit GCM nonce)
+const NONCE_LEN: usize = 12;
+
+/// An AES-256-GCM key, with where it came from for error messages.
+#[derive(Clone)]
+pub struct Cipher {
+    cipher: Aes256Gcm,
+    source: String,
+}
+
+impl std::fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cipher").field("source", &self.source).finish_non_exhaustive()
+    }
+}
+
+impl Cipher {
+    /// Key from the base64 text `encoded`, described as `source` in errors.
+    pub fn new(encoded: &str, source: impl Into<String>) -> Result<Self> {
+        let source = source.into();
+        let key = base64::engine::general_purpose::STANDARD
+            .decode(encoded.trim())
+            .with_context(|| format!("the key in {} is not valid base64", source))?;
+        if key.len() != KEY_LEN {
+            return Err(anyhow!(
+                "the key in {} is {} bytes; it must be {} (generate one with: openssl rand -base64 32)",
+                source,
+                key.len(),
+                KEY_LEN
+            ));
+        }
+        Ok(Self { cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)), source })
+    }
+
+    /// Key from the environment variable `var`.
+    pub fn from_env(var: &str) -> Result<Self> {
+        let encoded = std::env::var(var).map_err(|_| anyhow!("${} is not set; it should hold the encryption key", var))?;
+        Self::new(&encoded, format!("${}", var))
+    }
+
+    /// Seal `plaintext` under a fresh random nonce.
+    pub fn seal(&self, plaintext: &str) -> String {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .expect("AES-GCM encryption only fails for inputs over 64 GiB");
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        format!("{}{}", SEALED_PREFIX, base64::engine::general_purpose::STANDARD.encode(sealed))
+    }
+
+    /// Plaintext of a value sealed with this key.
+    pub fn open(&self, sealed: &str) -> Result<String> {
+        let encoded = sealed.strip_prefix(SEALED_PREFIX).ok_or_else(|| anyhow!("value is not encrypted"))?;
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .context("encrypted value is not valid base64")?;
+        if bytes.len() < NONCE_LEN {
+            return Err(anyhow!("encrypted value is truncated"));
+        }
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("failed to decrypt stored data with the key in {}; is it the right key?", self.source))?;
+        String::from_utf8(plaintext).context("decrypted value is not UTF-8")
+    }
+}
+
+/// Whether a stored value is sealed.
+pub fn is_sealed(value: &[u8]) -> bool {
+    value.starts_with(SEALED_PREFIX.as_bytes())
+}
+
+/// `value` sealed with `cipher`, or as it is without one.
+pub fn seal(cipher: Option<&Cipher>, value: String) -> String {
+    match cipher {
+        Some(cipher) => cipher.seal(&value),
+        None => value,
+    }
+}
+
+/// Plaintext of a stored value: opened if it is sealed, as it is otherwise.
+pub fn open<'a>(cipher: Option<&Cipher>, value: &'a str) -> Result<Cow<'a, str>> {
+    if !is_sealed(value.as_bytes()) {
+        return Ok(Cow::Borrowed(value));
+    }
+    match cipher {
+        Some(cipher) => cipher.open(value).map(Cow::Owned),
+        None => Err(anyhow!(
+            "stored data is encrypted; set storage.encryption_key_env to the variable holding its key"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_A: &str = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=";
+    const KEY_B: &str = "Hx4dHBsaGRgXFhUUExIREA8ODQwLCgkIBwYFBAMCAQA=";
+
+    #[test]
+    fn test_seal_and_open() {
+        let cipher = Cipher::new(KEY_A, "test").unwrap();
+        let sealed = cipher.seal("{\"summary\":\"Fixed login\"}");
+        assert!(sealed.starts_with(SEALED_PREFIX));
+        assert!(!sealed.contains("login"));
+        assert_ne!(sealed, cipher.seal("{\"summary\":\"Fixed login\"}"), "each seal uses a fresh nonce");
+        assert_eq!(cipher.open(&sealed).unwrap(), "{\"summary\":\"Fixed login\"}");
+
+        let other = Cipher::new(KEY_B, "$OTHER").unwrap();
+        assert!(other.open(&sealed).unwrap_err().to_string().contains("$OTHER"));
+    }
+
+    #[test]
+    fn test_open_passes_plain_values_through() {
+        let cipher = Cipher::new(KEY_A, "test").unwrap();
+        assert_eq!(open(Some(&cipher), "{}").unwrap(), "{}");
+        assert_eq!(open(None, "{}").unwrap(), "{}");
+        assert_eq!(seal(None, "{}".to_string()), "{}");
+
+        let sealed = seal(Some(&cipher), "{}".to_string());
+        assert_eq!(open(Some(&cipher), &sealed).unwrap(), "{}");
+        assert!(open(None, &sealed).is_err());
+    }
+
+    #[test]
+    fn test_new_rejects_bad_keys() {
+        assert!(Cipher::new("not base64!", "test").is_err());
+        assert!(Cipher::new("AAEC", "test").unwrap_err().to_string().contains("3 bytes"));
+    }
+}
@@ -0,0 +1,205 @@
+//! Workspace documentation generated from stored state (`zdrive describe`).
+//!
+//! Renders a Markdown overview of each tab: the layout (from the most recent
+//! snapshot), and for each pane its purpose, working directory, startup
+//! command, and recent milestones.
+
+use crate::output::messages::count;
+use crate::types::{IntentEntry, PaneRecord, TabSnapshot};
+use chrono::{DateTime, Local, Utc};
+
+/// Metadata keys rendered in their own fields rather than the generic list.
+const RESERVED_META: [&str; 4] = ["goal", "cwd", "command", "position"];
+
+/// Everything known about one pane, gathered for rendering.
+#[derive(Debug, Clone)]
+pub struct PaneDescription {
+    pub record: PaneRecord,
+    /// Most recent milestones, newest first
+    pub milestones: Vec<IntentEntry>,
+    /// Most recent entry of any type
+    pub last_entry: Option<IntentEntry>,
+}
+
+impl PaneDescription {
+    /// The pane's purpose: its goal, else its latest milestone.
+    pub fn purpose(&self) -> Option<&str> {
+        self.record
+            .meta
+            .get("goal")
+            .map(String::as_str)
+            .or_else(|| self.milestones.first().map(|m| m.summary.as_str()))
+    }
+}
+
+/// A tab with its panes and last known layout.
+#[derive(Debug, Clone)]
+pub struct TabDescription {
+    pub name: String,
+    pub panes: Vec<PaneDescription>,
+    pub layout: Option<TabSnapshot>,
+}
+
+/// Render the workspace document for `session`.
+pub fn render(session: &str, tabs: &[TabDescription], generated_at: DateTime<Utc>) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("# Workspace: {}\n\n", session));
+    out.push_str(&format!(
+        "_Generated by zdrive on {}. Regenerate with `zdrive describe`._\n",
+        generated_at.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+    ));
+
+    for tab in tabs {
+        out.push_str(&format!("\n## Tab: {}\n\n", tab.name));
+        render_layout(&mut out, tab);
+
+        for pane in &tab.panes {
+            render_pane(&mut out, pane, tab.layout.as_ref());
+        }
+    }
+
+    out
+}
+
+fn render_layout(out: &mut String, tab: &TabDescription) {
+    out.push_str("### Layout\n\n");
+    match &tab.layout {
+        Some(layout) => {
+            let direction = if layout.layout.is_empty() { "default" } else { layout.layout.as_str() };
+            out.push_str(&format!("{} layout, {} (from last snapshot):\n\n", direction, count(layout.panes.len(), "pane")));
+            for pane in &layout.panes {
+                let focus = if pane.focused { " (focused)" } else { "" };
+                out.push_str(&format!("{}. {}{}\n", pane.position + 1, pane.name, focus));
+            }
+        }
+        None => {
+            out.push_str(&format!("{}; no snapshot recorded yet:\n\n", count(tab.panes.len(), "pane")));
+            for (index, pane) in tab.panes.iter().enumerate() {
+                out.push_str(&format!("{}. {}\n", index + 1, pane.record.pane_name));
+            }
+        }
+    }
+}
+
+fn render_pane(out: &mut String, pane: &PaneDescription, layout: Option<&TabSnapshot>) {
+    let record = &pane.record;
+    out.push_str(&format!("\n### {}\n\n", record.pane_name));
+    out.push_str(&format!(
+        "**Purpose:** {}\n\n",
+        pane.purpose().unwrap_or("_not recorded — set a goal with `--meta goal=...`_")
+    ));
+
+    // Fall back to what the last snapshot captured for this pane
+    let snapshot = layout.and_then(|l| l.panes.iter().find(|p| p.name == record.pane_name));
+    let cwd = record.meta.get("cwd").or_else(|| snapshot.and_then(|p| p.cwd.as_ref()));
+    let command = record.meta.get("command").or_else(|| snapshot.and_then(|p| p.command.as_ref()));
+
+    if let Some(cwd) = cwd {
+        out.push_str(&format!("- Working directory: `{}`\n", cwd));
+    }
+    if let Some(command) = command {
+        out.push_str(&format!("- Startup command: `{}`\n", command));
+    }
+
+    let mut extra: Vec<_> = record
+        .meta
+        .iter()
+        .filter(|(key, _)| !RESERVED_META.contains(&key.as_str()))
+        .collect();
+    extra.sort();
+    for (key, value) in extra {
+        out.push_str(&format!("- {}: {}\n", key, value));
+    }
+    if record.stale {
+        out.push_str("- Status: stale (not seen in the last reconcile)\n");
+    }
+
+    if !pane.milestones.is_empty() {
+        out.push_str("\n**Recent milestones:**\n\n");
+        for milestone in &pane.milestones {
+            out.push_str(&format!(
+                "- {} — {}\n",
+                milestone.timestamp.with_timezone(&Local).format("%Y-%m-%d"),
+                milestone.summary
+            ));
+        }
+    }
+
+    if let Some(entry) = &pane.last_entry {
+        out.push_str(&format!(
+            "\n**Last activity:** {} ({})\n",
+            entry.summary,
+            entry.timestamp.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{IntentType, PaneSnapshot};
+    use std::collections::HashMap;
+
+    fn pane(name: &str, meta: &[(&str, &str)]) -> PaneRecord {
+        PaneRecord::new(
+            name.to_string(),
+            "work".to_string(),
+            "myapp(dev)".to_string(),
+            "2025-01-01T00:00:00Z".to_string(),
+            meta.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<HashMap<_, _>>(),
+        )
+    }
+
+    #[test]
+    fn test_purpose_prefers_goal_then_milestone() {
+        let milestone = IntentEntry::new("Shipped auth").with_type(IntentType::Milestone);
+        let mut description = PaneDescription {
+            record: pane("api", &[]),
+            milestones: vec![milestone],
+            last_entry: None,
+        };
+        assert_eq!(description.purpose(), Some("Shipped auth"));
+
+        description.record.meta.insert("goal".to_string(), "Token refresh".to_string());
+        assert_eq!(description.purpose(), Some("Token refresh"));
+    }
+
+    #[test]
+    fn test_render_includes_layout_and_pane_details() {
+        let tab = TabDescription {
+            name: "myapp(dev)".to_string(),
+            panes: vec![PaneDescription {
+                record: pane("api", &[("cwd", "/src/api"), ("command", "cargo watch"), ("owner", "sam")]),
+                milestones: Vec::new(),
+                last_entry: Some(IntentEntry::new("Added retries")),
+            }],
+            layout: Some(TabSnapshot {
+                name: "myapp(dev)".to_string(),
+                index: 0,
+                active: true,
+                layout: "vertical".to_string(),
+                panes: vec![PaneSnapshot {
+                    name: "api".to_string(),
+                    position: 0,
+                    cwd: None,
+                    command: None,
+                    pane_id: None,
+                    focused: true,
+                    meta: HashMap::new(),
+                    scrollback: None,
+                }],
+                correlation_id: None,
+            }),
+        };
+
+        let doc = render("work", &[tab], Utc::now());
+        assert!(doc.starts_with("# Workspace: work"));
+        assert!(doc.contains("## Tab: myapp(dev)"));
+        assert!(doc.contains("vertical layout, 1 pane (from last snapshot)"));
+        assert!(doc.contains("1. api (focused)"));
+        assert!(doc.contains("- Working directory: `/src/api`"));
+        assert!(doc.contains("- Startup command: `cargo watch`"));
+        assert!(doc.contains("- owner: sam"));
+        assert!(doc.contains("**Last activity:** Added retries"));
+    }
+}
@@ -0,0 +1,154 @@
+//! Cross-pane digests for `zdrive digest`: intent entries grouped by local
+//! day and pane, for standups and weekly reviews.
+
+use crate::ask::PaneEntry;
+use crate::types::{IntentEntry, IntentType};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Entries from every pane over a period, grouped by day then pane.
+#[derive(Debug, Clone, Serialize)]
+pub struct Digest {
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    /// Newest day first
+    pub days: Vec<DigestDay>,
+    /// LLM executive summary, when requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+}
+
+/// One local calendar day of a digest.
+#[derive(Debug, Clone, Serialize)]
+pub struct DigestDay {
+    pub date: NaiveDate,
+    /// Panes in name order
+    pub panes: Vec<DigestPane>,
+}
+
+/// A pane's entries within one day, oldest first so they read as a log.
+#[derive(Debug, Clone, Serialize)]
+pub struct DigestPane {
+    pub pane_name: String,
+    pub entries: Vec<IntentEntry>,
+}
+
+impl Digest {
+    /// Group entries by the local date they were logged on, then by pane.
+    pub fn build(entries: Vec<PaneEntry>, since: DateTime<Utc>, until: DateTime<Utc>) -> Self {
+        let mut grouped: BTreeMap<NaiveDate, BTreeMap<String, Vec<IntentEntry>>> = BTreeMap::new();
+        for PaneEntry { pane_name, entry } in entries {
+            let date = entry.timestamp.with_timezone(&Local).date_naive();
+            grouped.entry(date).or_default().entry(pane_name).or_default().push(entry);
+        }
+
+        let days = grouped
+            .into_iter()
+            .rev()
+            .map(|(date, panes)| DigestDay {
+                date,
+                panes: panes
+                    .into_iter()
+                    .map(|(pane_name, mut entries)| {
+                        entries.sort_by_key(|e| e.timestamp);
+                        DigestPane { pane_name, entries }
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Self { since, until, days, summary: None }
+    }
+
+    pub fn entry_count(&self) -> usize {
+        self.days.iter().flat_map(|d| &d.panes).map(|p| p.entries.len()).sum()
+    }
+
+    pub fn milestone_count(&self) -> usize {
+        self.days
+            .iter()
+            .flat_map(|d| &d.panes)
+            .flat_map(|p| &p.entries)
+            .filter(|e| e.entry_type == IntentType::Milestone)
+            .count()
+    }
+
+    /// Prompt asking the LLM for an executive summary of the digest.
+    pub fn summary_prompt(&self) -> String {
+        let mut prompt = String::new();
+        prompt.push_str("You are a developer assistant preparing a standup or weekly review. ");
+        prompt.push_str("Below is a developer's work log, grouped by day and by terminal pane ");
+        prompt.push_str("(pane names are usually project(branch)).\n\n");
+
+        for day in &self.days {
+            prompt.push_str(&format!("## {}\n", day.date.format("%A %Y-%m-%d")));
+            for pane in &day.panes {
+                prompt.push_str(&format!("### {}\n", pane.pane_name));
+                for entry in &pane.entries {
+                    let marker = match entry.entry_type {
+                        IntentType::Milestone => " [milestone]",
+                        IntentType::Exploration => " [exploration]",
+                        IntentType::Checkpoint => "",
+                    };
+                    prompt.push_str(&format!("- {}{}\n", entry.summary, marker));
+                }
+            }
+            prompt.push('\n');
+        }
+
+        prompt.push_str("## Instructions:\n");
+        prompt.push_str("Write a short executive summary (3-6 bullet points) of what was accomplished, ");
+        prompt.push_str("leading with milestones, then notable work in progress and open threads. ");
+        prompt.push_str("Group related work across panes. Respond with the bullet points only.");
+        prompt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn pane_entry(pane_name: &str, summary: &str, at: DateTime<Utc>) -> PaneEntry {
+        let mut entry = IntentEntry::new(summary);
+        entry.timestamp = at;
+        PaneEntry { pane_name: pane_name.to_string(), entry }
+    }
+
+    #[test]
+    fn test_build_groups_by_day_and_pane() {
+        let noon = Local.with_ymd_and_hms(2025, 3, 4, 12, 0, 0).unwrap().with_timezone(&Utc);
+        let entries = vec![
+            pane_entry("web(main)", "Styled checkout", noon + Duration::hours(2)),
+            pane_entry("api(payments)", "Refund endpoint", noon + Duration::hours(1)),
+            pane_entry("api(payments)", "Refund schema", noon),
+            pane_entry("api(payments)", "Webhook retries", noon - Duration::days(1)),
+        ];
+
+        let digest = Digest::build(entries, noon - Duration::days(7), noon + Duration::hours(3));
+        assert_eq!(digest.entry_count(), 4);
+        assert_eq!(digest.days.len(), 2);
+
+        let today = &digest.days[0];
+        assert_eq!(today.date, NaiveDate::from_ymd_opt(2025, 3, 4).unwrap());
+        let panes: Vec<_> = today.panes.iter().map(|p| p.pane_name.as_str()).collect();
+        assert_eq!(panes, vec!["api(payments)", "web(main)"]);
+        let summaries: Vec<_> = today.panes[0].entries.iter().map(|e| e.summary.as_str()).collect();
+        assert_eq!(summaries, vec!["Refund schema", "Refund endpoint"]);
+
+        assert_eq!(digest.days[1].panes[0].entries[0].summary, "Webhook retries");
+    }
+
+    #[test]
+    fn test_summary_prompt_marks_milestones() {
+        let now = Utc::now();
+        let mut shipped = pane_entry("api(payments)", "Shipped refunds", now);
+        shipped.entry.entry_type = IntentType::Milestone;
+        let digest = Digest::build(vec![shipped], now - Duration::days(1), now);
+
+        let prompt = digest.summary_prompt();
+        assert!(prompt.contains("### api(payments)\n- Shipped refunds [milestone]\n"));
+        assert!(prompt.ends_with("Respond with the bullet points only."));
+    }
+}
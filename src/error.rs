@@ -0,0 +1,125 @@
+//! Typed errors for library consumers.
+//!
+//! Functions still return `anyhow::Result`, but the failures a caller may
+//! want to handle differently (a missing pane versus Redis being down
+//! versus Zellij being too old) carry a [`PerthError`] somewhere in the
+//! error chain. Find it with [`PerthError::find`]; `zdrive` derives its exit
+//! status from the same classification through [`exit_code`].
+
+use thiserror::Error;
+
+/// Exit status for failures without a more specific classification.
+pub const EXIT_FAILURE: i32 = 1;
+
+#[derive(Debug, Error)]
+pub enum PerthError {
+    /// A pane, tab, entry, template or other named item doesn't exist
+    #[error("{0}")]
+    NotFound(String),
+    /// Stored state changed or disappeared underneath the operation
+    #[error("{0}")]
+    StaleState(String),
+    /// Zellij is missing or too old, or there is no session to act on
+    #[error("{0}")]
+    ZellijUnavailable(String),
+    /// Redis could not be reached or failed a command
+    #[error("{0}")]
+    StorageError(String),
+    /// The LLM provider is unconfigured, unavailable, or failed to answer
+    #[error("{0}")]
+    LlmError(String),
+    /// Sending data to an LLM provider needs consent that wasn't given
+    #[error("{0}")]
+    ConsentRequired(String),
+}
+
+impl PerthError {
+    /// The first `PerthError` in an error's chain, including under any
+    /// context added on the way up.
+    pub fn find(err: &anyhow::Error) -> Option<&PerthError> {
+        // downcast_ref sees errors attached as context; chain() sees sources
+        err.downcast_ref::<PerthError>()
+            .or_else(|| err.chain().find_map(|cause| cause.downcast_ref::<PerthError>()))
+    }
+
+    /// Stable snake_case name, e.g. for JSON error output.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PerthError::NotFound(_) => "not_found",
+            PerthError::StaleState(_) => "stale_state",
+            PerthError::ZellijUnavailable(_) => "zellij_unavailable",
+            PerthError::StorageError(_) => "storage_error",
+            PerthError::LlmError(_) => "llm_error",
+            PerthError::ConsentRequired(_) => "consent_required",
+        }
+    }
+
+    /// Exit status `zdrive` uses for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            PerthError::NotFound(_) => 2,
+            PerthError::StaleState(_) => 3,
+            PerthError::ZellijUnavailable(_) => 4,
+            PerthError::StorageError(_) => 5,
+            PerthError::LlmError(_) => 6,
+            PerthError::ConsentRequired(_) => 7,
+        }
+    }
+}
+
+/// Kind of a failed command's error: its [`PerthError`], or "storage_error"
+/// for any Redis failure that wasn't classified where it happened.
+pub fn kind(err: &anyhow::Error) -> Option<&'static str> {
+    match PerthError::find(err) {
+        Some(perth) => Some(perth.kind()),
+        None if is_redis_error(err) => Some("storage_error"),
+        None => None,
+    }
+}
+
+/// Exit status for a failed command: the code of its [`PerthError`], the
+/// storage code for any Redis failure, otherwise [`EXIT_FAILURE`].
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    match PerthError::find(err) {
+        Some(perth) => perth.exit_code(),
+        None if is_redis_error(err) => PerthError::StorageError(String::new()).exit_code(),
+        None => EXIT_FAILURE,
+    }
+}
+
+fn is_redis_error(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| cause.is::<redis::RedisError>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{anyhow, Context};
+
+    #[test]
+    fn test_exit_code_looks_through_context() {
+        let err = Err::<(), _>(PerthError::NotFound("pane 'api' not found".to_string()))
+            .context("failed to jump")
+            .unwrap_err();
+        assert_eq!(exit_code(&err), 2);
+        assert_eq!(kind(&err), Some("not_found"));
+        assert_eq!(format!("{:#}", err), "failed to jump: pane 'api' not found");
+
+        let wrapped = Err::<(), _>(anyhow!("HTTP 503"))
+            .context(PerthError::LlmError("LLM request failed".to_string()))
+            .unwrap_err();
+        assert_eq!(exit_code(&wrapped), 6);
+
+        let plain = anyhow!("something else");
+        assert_eq!(exit_code(&plain), EXIT_FAILURE);
+        assert_eq!(kind(&plain), None);
+    }
+
+    #[test]
+    fn test_redis_failures_are_storage_errors() {
+        let redis = redis::RedisError::from((redis::ErrorKind::IoError, "connection refused"));
+        let err = anyhow::Error::new(redis).context("failed to read history");
+        assert_eq!(exit_code(&err), 5);
+        assert_eq!(kind(&err), Some("storage_error"));
+    }
+}
@@ -0,0 +1,365 @@
+//! Handoff bundle parsing for `zdrive takeover`.
+//!
+//! A handoff bundle is the exported history of one pane, handed to whoever
+//! picks the work up next. Both export formats produced by `pane history`
+//! are accepted:
+//!
+//! - `--format json` / `json-compact`: `{ "schema_version": ..., "pane": ..., "entries": [...] }`
+//! - `--format markdown`: YAML frontmatter, `## YYYY-MM-DD` headers and
+//!   `- {emoji}{source}{detail} **HH:MM** summary #tag` entry lines
+//!
+//! Either format may carry the exporting pane's active `goal`, with its
+//! `goal_id` and `goal_set_at` (JSON fields or frontmatter keys). Markdown
+//! marks entries that reported progress on it with a `  > 🎯 delta` line.
+//! Annotations travel with the bundle: an `annotations` array in JSON, or
+//! `  > 💬 note — author` lines beneath their entry in Markdown.
+
+use crate::types::{Annotation, IntentEntry, IntentSource, IntentType};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeZone, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+/// Parsed contents of a handoff bundle.
+#[derive(Debug, Clone, Default)]
+pub struct HandoffBundle {
+    /// Pane the bundle was exported from, if recorded
+    pub pane: Option<String>,
+    /// Goal carried over to the new owner, if recorded
+    pub goal: Option<String>,
+    /// ID of that goal, referenced by the entries that reported progress on it
+    pub goal_id: Option<Uuid>,
+    /// When that goal was set
+    pub goal_set_at: Option<DateTime<Utc>>,
+    /// Entries in history order (newest first), all marked as imported
+    pub entries: Vec<IntentEntry>,
+    /// Annotations on those entries, oldest first
+    pub annotations: Vec<Annotation>,
+}
+
+#[derive(Deserialize)]
+struct JsonBundle {
+    pane: Option<String>,
+    goal: Option<String>,
+    goal_id: Option<Uuid>,
+    goal_set_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    entries: Vec<IntentEntry>,
+    #[serde(default)]
+    annotations: Vec<Annotation>,
+}
+
+/// Parse a handoff bundle, detecting JSON vs Markdown from its content.
+pub fn parse(content: &str) -> Result<HandoffBundle> {
+    let mut bundle = if content.trim_start().starts_with('{') {
+        parse_json(content)?
+    } else {
+        parse_markdown(content)?
+    };
+
+    for entry in &mut bundle.entries {
+        entry.source = IntentSource::Imported;
+    }
+
+    Ok(bundle)
+}
+
+fn parse_json(content: &str) -> Result<HandoffBundle> {
+    let parsed: JsonBundle =
+        serde_json::from_str(content).context("failed to parse JSON handoff bundle")?;
+
+    Ok(HandoffBundle {
+        pane: parsed.pane,
+        goal: parsed.goal,
+        goal_id: parsed.goal_id,
+        goal_set_at: parsed.goal_set_at,
+        entries: parsed.entries,
+        annotations: parsed.annotations,
+    })
+}
+
+fn parse_markdown(content: &str) -> Result<HandoffBundle> {
+    let mut bundle = HandoffBundle::default();
+    let mut lines = content.lines().peekable();
+
+    // YAML frontmatter (only the keys we care about)
+    if lines.peek().map(|l| l.trim()) == Some("---") {
+        lines.next();
+        for line in lines.by_ref() {
+            let line = line.trim();
+            if line == "---" {
+                break;
+            }
+            if let Some((key, value)) = line.split_once(':') {
+                let value = value.trim();
+                // Quoted values are JSON-style strings, so quotes inside survive
+                let value = serde_json::from_str::<String>(value).unwrap_or_else(|_| value.trim_matches('"').to_string());
+                match key.trim() {
+                    "pane" if !value.is_empty() => bundle.pane = Some(value),
+                    "goal" if !value.is_empty() => bundle.goal = Some(value),
+                    "goal_id" => bundle.goal_id = value.parse().ok(),
+                    "goal_set_at" => bundle.goal_set_at = DateTime::parse_from_rfc3339(&value).ok().map(|t| t.with_timezone(&Utc)),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    let mut current_date: Option<NaiveDate> = None;
+
+    for (idx, line) in lines.enumerate() {
+        if let Some(date) = line.strip_prefix("## ") {
+            let date = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+                .with_context(|| format!("invalid date header '{}'", line.trim()))?;
+            current_date = Some(date);
+        } else if let Some(artifact) = line.strip_prefix("  - ") {
+            let entry = bundle
+                .entries
+                .last_mut()
+                .ok_or_else(|| anyhow!("artifact line {} has no preceding entry", idx + 1))?;
+            entry
+                .artifacts
+                .push(artifact.trim().trim_matches('`').to_string());
+        } else if let Some(delta) = line.strip_prefix("  > 🎯") {
+            // Without a recorded goal ID, marked entries still share one
+            let goal_id = *bundle.goal_id.get_or_insert_with(Uuid::new_v4);
+            let entry = bundle
+                .entries
+                .last_mut()
+                .ok_or_else(|| anyhow!("goal line {} has no preceding entry", idx + 1))?;
+            entry.goal_id = Some(goal_id);
+            if !delta.trim().is_empty() {
+                entry.goal_delta = Some(delta.trim().to_string());
+            }
+        } else if let Some(note) = line.strip_prefix("  > 💬 ") {
+            let entry = bundle
+                .entries
+                .last()
+                .ok_or_else(|| anyhow!("annotation line {} has no preceding entry", idx + 1))?;
+            let annotation = match note.rsplit_once(" — ") {
+                Some((note, author)) => Annotation::new(entry.id, note.trim()).with_author(author.trim()),
+                None => Annotation::new(entry.id, note.trim()),
+            };
+            bundle.annotations.push(annotation);
+        } else if let Some(rest) = line.strip_prefix("- ") {
+            bundle.entries.push(parse_entry_line(rest, current_date)?);
+        }
+    }
+
+    Ok(bundle)
+}
+
+/// Parse `{emoji}{source_tag}{detail} **HH:MM** summary #tag #tag` into an entry.
+fn parse_entry_line(line: &str, date: Option<NaiveDate>) -> Result<IntentEntry> {
+    let (prefix, rest) = line
+        .split_once("**")
+        .ok_or_else(|| anyhow!("malformed entry line '- {}'", line))?;
+    let (time, summary) = rest
+        .split_once("**")
+        .ok_or_else(|| anyhow!("malformed entry line '- {}'", line))?;
+
+    let entry_type = if prefix.contains('🌟') {
+        IntentType::Milestone
+    } else if prefix.contains('🔍') {
+        IntentType::Exploration
+    } else {
+        IntentType::Checkpoint
+    };
+
+    // Whatever follows the type emoji and source tag names the logging program
+    let detail = prefix
+        .trim()
+        .trim_start_matches(['🌟', '📍', '🔍'])
+        .trim_start()
+        .trim_start_matches(['⚡', '🤖', '📥'])
+        .trim();
+
+    // Trailing `#tag` tokens are the entry's tags
+    let mut summary = summary.trim();
+    let mut tags = Vec::new();
+    while let Some((rest, tag)) = summary.rsplit_once(' ') {
+        if tag.len() < 2 || !tag.starts_with('#') {
+            break;
+        }
+        tags.push(tag.to_string());
+        summary = rest.trim_end();
+    }
+    tags.reverse();
+
+    let mut entry = IntentEntry::new(summary).with_type(entry_type).with_tags(tags);
+    if !detail.is_empty() {
+        entry = entry.with_source_detail(detail);
+    }
+
+    if let Some(date) = date {
+        let time = NaiveTime::parse_from_str(time.trim(), "%H:%M")
+            .with_context(|| format!("invalid entry time '{}'", time.trim()))?;
+        entry.timestamp = Utc.from_utc_datetime(&date.and_time(time));
+    }
+
+    Ok(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARKDOWN: &str = "---
+pane: old-owner
+goal: Ship the auth refactor
+entries: 3
+latest: 2025-01-02
+earliest: 2025-01-01
+exported: 2025-01-02T18:00:00
+---
+
+# Session: old-owner
+
+## 2025-01-02
+
+- 🌟 **17:30** Finished token refresh
+  - `src/auth.rs`
+  - `src/session.rs`
+  > 💬 review: missing error handling in retry loop — sam
+- 📍 ⚡ **09:15** Wired up middleware
+
+## 2025-01-01
+
+- 🔍 🤖 **14:00** Investigated session storage";
+
+    #[test]
+    fn test_parse_markdown_bundle() {
+        let bundle = parse(MARKDOWN).unwrap();
+
+        assert_eq!(bundle.pane.as_deref(), Some("old-owner"));
+        assert_eq!(bundle.goal.as_deref(), Some("Ship the auth refactor"));
+        assert_eq!(bundle.entries.len(), 3);
+
+        let first = &bundle.entries[0];
+        assert_eq!(first.summary, "Finished token refresh");
+        assert_eq!(first.entry_type, IntentType::Milestone);
+        assert_eq!(first.artifacts, vec!["src/auth.rs", "src/session.rs"]);
+        assert_eq!(first.timestamp.to_rfc3339(), "2025-01-02T17:30:00+00:00");
+
+        assert_eq!(bundle.entries[1].entry_type, IntentType::Checkpoint);
+        assert_eq!(bundle.entries[2].entry_type, IntentType::Exploration);
+        assert_eq!(bundle.entries[2].timestamp.to_rfc3339(), "2025-01-01T14:00:00+00:00");
+    }
+
+    #[test]
+    fn test_parse_markdown_annotations() {
+        let bundle = parse(MARKDOWN).unwrap();
+
+        assert_eq!(bundle.annotations.len(), 1);
+        let annotation = &bundle.annotations[0];
+        assert_eq!(annotation.entry_id, bundle.entries[0].id);
+        assert_eq!(annotation.note, "review: missing error handling in retry loop");
+        assert_eq!(annotation.author.as_deref(), Some("sam"));
+    }
+
+    #[test]
+    fn test_parse_marks_entries_imported() {
+        let bundle = parse(MARKDOWN).unwrap();
+        assert!(bundle.entries.iter().all(|e| e.source == IntentSource::Imported));
+    }
+
+    #[test]
+    fn test_parse_json_bundle() {
+        let entry = IntentEntry::new("Fixed login timeout")
+            .with_type(IntentType::Milestone)
+            .with_source(IntentSource::Agent);
+        let json = serde_json::json!({
+            "schema_version": "2.0",
+            "pane": "old-owner",
+            "goal": "Stabilize login",
+            "entries": [entry],
+        })
+        .to_string();
+
+        let bundle = parse(&json).unwrap();
+        assert_eq!(bundle.pane.as_deref(), Some("old-owner"));
+        assert_eq!(bundle.goal.as_deref(), Some("Stabilize login"));
+        assert_eq!(bundle.entries.len(), 1);
+        assert_eq!(bundle.entries[0].id, entry.id);
+        assert_eq!(bundle.entries[0].source, IntentSource::Imported);
+    }
+
+    #[test]
+    fn test_markdown_export_round_trips() {
+        use crate::output::OutputFormatter;
+        use crate::types::{Goal, GoalProgress};
+
+        let goal = Goal::new("Ship \"auth\": v2");
+        let mut tagged = IntentEntry::new("Fixed token refresh")
+            .with_goal(goal.id)
+            .with_goal_delta("Refresh tokens rotate")
+            .with_type(IntentType::Milestone)
+            .with_source(IntentSource::Agent)
+            .with_source_detail("claude-code")
+            .with_tags(vec!["bugfix".to_string(), "auth".to_string()]);
+        tagged.timestamp = Utc.with_ymd_and_hms(2025, 1, 2, 17, 30, 0).unwrap();
+        let mut plain = IntentEntry::new("Read the #auth docs first");
+        plain.timestamp = Utc.with_ymd_and_hms(2025, 1, 1, 9, 15, 0).unwrap();
+
+        let progress = GoalProgress { goal: goal.clone(), entries: vec![tagged.clone()] };
+        let markdown = OutputFormatter::new()
+            .with_goal(Some(progress))
+            .format_markdown(&[tagged.clone(), plain.clone()], "old-owner");
+
+        let bundle = parse(&markdown).unwrap();
+        assert_eq!(bundle.pane.as_deref(), Some("old-owner"));
+        assert_eq!(bundle.goal.as_deref(), Some("Ship \"auth\": v2"));
+        assert_eq!(bundle.goal_id, Some(goal.id));
+        assert_eq!(bundle.goal_set_at, Some(goal.set_at));
+        assert_eq!(bundle.entries.len(), 2);
+
+        let first = &bundle.entries[0];
+        assert_eq!(first.summary, tagged.summary);
+        assert_eq!(first.entry_type, IntentType::Milestone);
+        assert_eq!(first.source_detail.as_deref(), Some("claude-code"));
+        assert_eq!(first.tags, vec!["bugfix", "auth"]);
+        assert_eq!(first.timestamp, tagged.timestamp);
+        assert_eq!(first.goal_id, Some(goal.id));
+        assert_eq!(first.goal_delta.as_deref(), Some("Refresh tokens rotate"));
+
+        let second = &bundle.entries[1];
+        assert_eq!(second.summary, plain.summary);
+        assert!(second.tags.is_empty());
+        assert!(second.source_detail.is_none());
+        assert!(second.goal_id.is_none());
+        assert_eq!(second.timestamp, plain.timestamp);
+    }
+
+    #[test]
+    fn test_json_export_round_trips() {
+        use crate::types::{Goal, HistoryOutput};
+
+        let goal = Goal::new("Stabilize login");
+        let entry = IntentEntry::new("Fixed login timeout").with_goal(goal.id).with_tags(vec!["auth".to_string()]);
+        let body = HistoryOutput {
+            pane: "old-owner".to_string(),
+            goal: Some(goal.title.clone()),
+            goal_id: Some(goal.id),
+            goal_set_at: Some(goal.set_at),
+            entries: vec![entry.clone()],
+            annotations: vec![Annotation::new(entry.id, "check the retry path")],
+        };
+        let json = crate::output::json::document("pane history", serde_json::to_value(body).unwrap()).to_string();
+
+        let bundle = parse(&json).unwrap();
+        assert_eq!(bundle.pane.as_deref(), Some("old-owner"));
+        assert_eq!(bundle.goal.as_deref(), Some("Stabilize login"));
+        assert_eq!(bundle.goal_id, Some(goal.id));
+        assert_eq!(bundle.goal_set_at, Some(goal.set_at));
+        assert_eq!(bundle.entries[0].id, entry.id);
+        assert_eq!(bundle.entries[0].goal_id, Some(goal.id));
+        assert_eq!(bundle.entries[0].tags, vec!["auth"]);
+        assert_eq!(bundle.annotations.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_markdown_rejects_malformed_entry() {
+        let result = parse("## 2025-01-01\n\n- 📍 no time marker here");
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,309 @@
+//! Shell integration for automatic checkpoints.
+//!
+//! `zdrive hook install` adds a prompt hook to the user's shell rc file that
+//! runs `zdrive _hook` in the background after every command inside Zellij.
+//! Each call bumps a per-pane command counter in Redis; once the buffered
+//! activity crosses the `hooks.*` thresholds, an Automated checkpoint is
+//! logged with `commands_run` set and the buffer starts over.
+//!
+//! `zdrive hook install-git` adds a post-commit hook to a repository that
+//! runs `zdrive hook commit`, logging each commit (subject, SHA and changed
+//! files) to the pane working in that repository.
+//!
+//! Like the keybinding installer, hooks are wrapped in marker comments so
+//! re-running an installer replaces them instead of duplicating them.
+
+use crate::keybind::InstallOutcome;
+use anyhow::{anyhow, Context, Result};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const BEGIN_MARKER: &str = "# BEGIN zdrive hook";
+const END_MARKER: &str = "# END zdrive hook";
+
+/// A shell with a supported prompt hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Shell {
+    Zsh,
+    Bash,
+    Fish,
+}
+
+impl Shell {
+    /// The shell named by `$SHELL`, if it's one we support.
+    pub fn detect() -> Option<Shell> {
+        let shell = env::var("SHELL").ok()?;
+        match Path::new(&shell).file_name()?.to_str()? {
+            "zsh" => Some(Shell::Zsh),
+            "bash" => Some(Shell::Bash),
+            "fish" => Some(Shell::Fish),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Shell::Zsh => "zsh",
+            Shell::Bash => "bash",
+            Shell::Fish => "fish",
+        }
+    }
+
+    /// The rc file the hook is installed into.
+    pub fn rc_path(self) -> PathBuf {
+        let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
+        match self {
+            Shell::Zsh => match env::var("ZDOTDIR") {
+                Ok(dir) => Path::new(&dir).join(".zshrc"),
+                Err(_) => Path::new(&home).join(".zshrc"),
+            },
+            Shell::Bash => Path::new(&home).join(".bashrc"),
+            Shell::Fish => {
+                let config = env::var("XDG_CONFIG_HOME")
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|_| Path::new(&home).join(".config"));
+                config.join("fish").join("config.fish")
+            }
+        }
+    }
+}
+
+/// The hook for `shell`, wrapped in marker comments.
+///
+/// Each hook skips prompts that didn't follow a command (the first prompt,
+/// or an empty line) and runs detached so the prompt never waits on Redis.
+pub fn snippet(shell: Shell) -> String {
+    let body = match shell {
+        Shell::Zsh => "\
+_zdrive_preexec() { _zdrive_ran=1 }
+_zdrive_precmd() {
+    [[ -n $_zdrive_ran && -n $ZELLIJ ]] && (zdrive _hook >/dev/null 2>&1 &)
+    unset _zdrive_ran
+}
+autoload -Uz add-zsh-hook
+add-zsh-hook preexec _zdrive_preexec
+add-zsh-hook precmd _zdrive_precmd
+",
+        Shell::Bash => "\
+_zdrive_precmd() {
+    local status=$? histnum
+    read -r histnum _ <<< \"$(builtin history 1)\"
+    if [[ -n $_zdrive_histnum && $histnum != \"$_zdrive_histnum\" && -n $ZELLIJ ]]; then
+        (zdrive _hook >/dev/null 2>&1 &)
+    fi
+    _zdrive_histnum=$histnum
+    return $status
+}
+PROMPT_COMMAND=\"_zdrive_precmd${PROMPT_COMMAND:+;$PROMPT_COMMAND}\"
+",
+        Shell::Fish => "\
+function _zdrive_postexec --on-event fish_postexec
+    if test -n \"$argv\"; and set -q ZELLIJ
+        zdrive _hook >/dev/null 2>&1 &
+        disown 2>/dev/null
+    end
+end
+",
+    };
+    format!("{}\n{}{}\n", BEGIN_MARKER, body, END_MARKER)
+}
+
+/// Insert or replace the hook within existing rc file contents.
+pub fn apply(contents: &str, shell: Shell) -> (String, InstallOutcome) {
+    if let (Some(start), Some(end)) = (contents.find(BEGIN_MARKER), contents.find(END_MARKER)) {
+        if start < end {
+            let line_end = contents[end..]
+                .find('\n')
+                .map(|i| end + i + 1)
+                .unwrap_or(contents.len());
+
+            let mut updated = String::with_capacity(contents.len());
+            updated.push_str(&contents[..start]);
+            updated.push_str(&snippet(shell));
+            updated.push_str(&contents[line_end..]);
+            return (updated, InstallOutcome::Updated);
+        }
+    }
+
+    let mut updated = contents.to_string();
+    if !updated.is_empty() && !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+    if !updated.is_empty() {
+        updated.push('\n');
+    }
+    updated.push_str(&snippet(shell));
+    (updated, InstallOutcome::Installed)
+}
+
+/// Install the hook for `shell` into the rc file at `path`.
+pub fn install(path: &Path, shell: Shell) -> Result<InstallOutcome> {
+    let contents = if path.exists() {
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?
+    } else {
+        String::new()
+    };
+
+    let (updated, outcome) = apply(&contents, shell);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("failed to create directory: {}", parent.display()))?;
+    }
+    fs::write(path, updated).with_context(|| format!("failed to write {}", path.display()))?;
+
+    Ok(outcome)
+}
+
+/// The post-commit hook block, wrapped in marker comments.
+pub fn git_snippet() -> String {
+    format!(
+        "{}\ncommand -v zdrive >/dev/null 2>&1 && (zdrive hook commit >/dev/null 2>&1 &)\n{}\n",
+        BEGIN_MARKER, END_MARKER
+    )
+}
+
+/// Insert or replace the zdrive block within an existing post-commit hook.
+///
+/// The block goes right after the shebang, so it runs even if the rest of
+/// the hook exits early; it runs in the background and can't change the
+/// hook's exit status.
+pub fn apply_git(contents: &str) -> (String, InstallOutcome) {
+    if let (Some(start), Some(end)) = (contents.find(BEGIN_MARKER), contents.find(END_MARKER)) {
+        if start < end {
+            let line_end = contents[end..]
+                .find('\n')
+                .map(|i| end + i + 1)
+                .unwrap_or(contents.len());
+
+            let mut updated = String::with_capacity(contents.len());
+            updated.push_str(&contents[..start]);
+            updated.push_str(&git_snippet());
+            updated.push_str(&contents[line_end..]);
+            return (updated, InstallOutcome::Updated);
+        }
+    }
+
+    let insert_at = if contents.starts_with("#!") {
+        contents.find('\n').map(|i| i + 1).unwrap_or(contents.len())
+    } else {
+        0
+    };
+
+    let mut updated = String::with_capacity(contents.len());
+    if insert_at == 0 {
+        updated.push_str("#!/bin/sh\n");
+    } else {
+        updated.push_str(&contents[..insert_at]);
+        if !updated.ends_with('\n') {
+            updated.push('\n');
+        }
+    }
+    updated.push_str(&git_snippet());
+    updated.push_str(&contents[insert_at..]);
+    (updated, InstallOutcome::Installed)
+}
+
+/// Path of the post-commit hook for the repository containing `cwd`,
+/// honoring `core.hooksPath` and worktrees.
+pub fn git_hook_path(cwd: &Path) -> Result<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", "hooks/post-commit"])
+        .current_dir(cwd)
+        .output()
+        .context("failed to run git")?;
+    if !output.status.success() {
+        return Err(anyhow!("{} is not inside a git repository", cwd.display()));
+    }
+    let path = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    Ok(if path.is_absolute() { path } else { cwd.join(path) })
+}
+
+/// Install the post-commit hook at `path` and make it executable.
+pub fn install_git(path: &Path) -> Result<InstallOutcome> {
+    let contents = if path.exists() {
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?
+    } else {
+        String::new()
+    };
+
+    let (updated, outcome) = apply_git(&contents);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("failed to create directory: {}", parent.display()))?;
+    }
+    fs::write(path, updated).with_context(|| format!("failed to write {}", path.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("failed to make {} executable", path.display()))?;
+    }
+
+    Ok(outcome)
+}
+
+/// Summary for an automatic checkpoint, e.g.
+/// "Ran 25 commands in api-server over 40m".
+pub fn checkpoint_summary(commands: usize, elapsed: chrono::Duration, cwd: Option<&Path>) -> String {
+    let mut summary = format!("Ran {} command{}", commands, if commands == 1 { "" } else { "s" });
+    if let Some(dir) = cwd.and_then(|cwd| cwd.file_name()) {
+        summary.push_str(&format!(" in {}", dir.to_string_lossy()));
+    }
+    let minutes = elapsed.num_minutes();
+    if minutes >= 60 {
+        summary.push_str(&format!(" over {}h{:02}m", minutes / 60, minutes % 60));
+    } else if minutes > 0 {
+        summary.push_str(&format!(" over {}m", minutes));
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_appends_then_replaces_hook() {
+        let (updated, outcome) = apply("export EDITOR=vim", Shell::Zsh);
+        assert_eq!(outcome, InstallOutcome::Installed);
+        assert!(updated.starts_with("export EDITOR=vim\n\n# BEGIN zdrive hook\n"));
+        assert!(updated.contains("add-zsh-hook precmd _zdrive_precmd"));
+
+        // Switching shells replaces the block rather than adding another
+        let (replaced, outcome) = apply(&format!("{}alias ll='ls -l'\n", updated), Shell::Bash);
+        assert_eq!(outcome, InstallOutcome::Updated);
+        assert_eq!(replaced.matches(BEGIN_MARKER).count(), 1);
+        assert!(replaced.contains("PROMPT_COMMAND="));
+        assert!(!replaced.contains("add-zsh-hook"));
+        assert!(replaced.ends_with("# END zdrive hook\nalias ll='ls -l'\n"));
+    }
+
+    #[test]
+    fn test_apply_git_keeps_existing_hook() {
+        let (created, outcome) = apply_git("");
+        assert_eq!(outcome, InstallOutcome::Installed);
+        assert!(created.starts_with("#!/bin/sh\n# BEGIN zdrive hook\n"));
+
+        // Existing hooks keep their shebang and run after the block
+        let existing = "#!/bin/bash\nnotify-send committed\nexit 0\n";
+        let (updated, outcome) = apply_git(existing);
+        assert_eq!(outcome, InstallOutcome::Installed);
+        assert!(updated.starts_with("#!/bin/bash\n# BEGIN zdrive hook\n"));
+        assert!(updated.ends_with("# END zdrive hook\nnotify-send committed\nexit 0\n"));
+
+        let (reinstalled, outcome) = apply_git(&updated);
+        assert_eq!(outcome, InstallOutcome::Updated);
+        assert_eq!(reinstalled, updated);
+    }
+
+    #[test]
+    fn test_checkpoint_summary() {
+        let cwd = Path::new("/work/api-server");
+        assert_eq!(checkpoint_summary(25, chrono::Duration::minutes(40), Some(cwd)), "Ran 25 commands in api-server over 40m");
+        assert_eq!(checkpoint_summary(1, chrono::Duration::seconds(20), None), "Ran 1 command");
+        assert_eq!(checkpoint_summary(3, chrono::Duration::minutes(95), None), "Ran 3 commands over 1h35m");
+    }
+}
@@ -0,0 +1,216 @@
+//! Fuzzy pane finder for `zdrive jump`.
+//!
+//! Matching works like fzf: each whitespace-separated query term must appear
+//! in order (not necessarily adjacent) somewhere in a pane's name, tab,
+//! session or last intent. Runs of adjacent characters and matches at the
+//! start of a word score higher, and ties keep the most recently accessed
+//! pane first.
+//!
+//! At the prompt the best match is shown as a hint while typing, Tab lists
+//! every match, and Enter jumps to the best one (with an empty query, the
+//! most recently accessed pane).
+
+use crate::output::messages::Icon;
+use anyhow::Result;
+use colored::Colorize;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::borrow::Cow;
+
+// Score adjustments per matched character
+const MATCH: i64 = 1;
+const ADJACENT_BONUS: i64 = 5;
+const WORD_START_BONUS: i64 = 8;
+const MAX_GAP_PENALTY: i64 = 3;
+
+/// A tracked pane offered by the finder.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub pane_name: String,
+    pub tab: String,
+    pub session: String,
+    /// Summary of the pane's most recent history entry
+    pub last_intent: Option<String>,
+}
+
+impl Candidate {
+    /// The text queries are matched against.
+    fn haystack(&self) -> String {
+        format!(
+            "{} {} {} {}",
+            self.pane_name,
+            self.tab,
+            self.session,
+            self.last_intent.as_deref().unwrap_or("")
+        )
+    }
+
+    /// One line for listing: name, location, and last intent.
+    pub fn display(&self) -> String {
+        match &self.last_intent {
+            Some(intent) => format!("{}  ({} · {})  {}", self.pane_name, self.tab, self.session, intent),
+            None => format!("{}  ({} · {})", self.pane_name, self.tab, self.session),
+        }
+    }
+}
+
+/// Score one query term against `text`, or `None` if its characters don't
+/// all appear in order.
+fn score_term(term: &[char], text: &[char]) -> Option<i64> {
+    let mut score = 0;
+    let mut matched = 0;
+    let mut previous: Option<usize> = None;
+
+    for (i, c) in text.iter().enumerate() {
+        if matched == term.len() {
+            break;
+        }
+        if c.to_lowercase().ne(term[matched].to_lowercase()) {
+            continue;
+        }
+        score += MATCH;
+        if i == 0 || !text[i - 1].is_alphanumeric() {
+            score += WORD_START_BONUS;
+        }
+        match previous {
+            Some(p) if p + 1 == i => score += ADJACENT_BONUS,
+            Some(p) => score -= ((i - p - 1) as i64).min(MAX_GAP_PENALTY),
+            None => {}
+        }
+        previous = Some(i);
+        matched += 1;
+    }
+
+    (matched == term.len()).then_some(score)
+}
+
+/// Score a query against `text`; every term must match. An empty query
+/// matches everything equally.
+pub fn score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.chars().collect();
+    query.split_whitespace().try_fold(0, |total, term| {
+        let term: Vec<char> = term.chars().collect();
+        score_term(&term, &text).map(|score| total + score)
+    })
+}
+
+/// Candidates matching `query`, best first. The sort is stable, so equal
+/// scores keep their input order.
+pub fn rank<'a>(query: &str, candidates: &'a [Candidate]) -> Vec<&'a Candidate> {
+    let mut scored: Vec<(i64, &Candidate)> = candidates
+        .iter()
+        .filter_map(|candidate| score(query, &candidate.haystack()).map(|score| (score, candidate)))
+        .collect();
+    scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+    scored.into_iter().map(|(_, candidate)| candidate).collect()
+}
+
+/// The best match for `query`, if any.
+pub fn best<'a>(query: &str, candidates: &'a [Candidate]) -> Option<&'a Candidate> {
+    rank(query, candidates).into_iter().next()
+}
+
+struct JumpHelper {
+    candidates: Vec<Candidate>,
+}
+
+impl Completer for JumpHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, _pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let matches = rank(line, &self.candidates)
+            .into_iter()
+            .map(|candidate| Pair {
+                display: candidate.display(),
+                replacement: candidate.pane_name.clone(),
+            })
+            .collect();
+        Ok((0, matches))
+    }
+}
+
+impl Hinter for JumpHelper {
+    type Hint = String;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<String> {
+        if pos < line.len() {
+            return None;
+        }
+        best(line, &self.candidates).map(|candidate| format!("  {} {}", Icon::Forward.glyph(), candidate.display()))
+    }
+}
+
+impl Highlighter for JumpHelper {
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(hint.dimmed().to_string())
+    }
+}
+
+impl Validator for JumpHelper {}
+
+impl Helper for JumpHelper {}
+
+/// Run the finder. Returns `None` if the user cancelled or nothing matched.
+pub fn prompt(candidates: &[Candidate]) -> Result<Option<Candidate>> {
+    let mut editor: Editor<JumpHelper, DefaultHistory> = Editor::new()?;
+    editor.set_helper(Some(JumpHelper {
+        candidates: candidates.to_vec(),
+    }));
+
+    let line = match editor.readline("jump> ") {
+        Ok(line) => line,
+        Err(ReadlineError::Interrupted | ReadlineError::Eof) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    Ok(best(&line, candidates).cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(pane_name: &str, tab: &str, last_intent: Option<&str>) -> Candidate {
+        Candidate {
+            pane_name: pane_name.to_string(),
+            tab: tab.to_string(),
+            session: "dev".to_string(),
+            last_intent: last_intent.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_score_requires_ordered_characters() {
+        assert!(score("apsv", "api-server").is_some());
+        assert!(score("API", "api-server").is_some());
+        assert!(score("vsa", "api-server").is_none());
+        assert!(score("api zzz", "api-server").is_none());
+        assert_eq!(score("", "anything"), Some(0));
+        // Adjacent, word-start matches beat scattered ones
+        assert!(score("ser", "api-server") > score("ser", "supervisor"));
+    }
+
+    #[test]
+    fn test_rank_prefers_better_matches_then_input_order() {
+        let candidates = vec![
+            candidate("logs", "monitoring", Some("Tailing api errors")),
+            candidate("api", "backend", Some("Refactoring handlers")),
+            candidate("web", "frontend", None),
+        ];
+
+        let ranked: Vec<&str> = rank("api", &candidates).iter().map(|c| c.pane_name.as_str()).collect();
+        assert_eq!(ranked, vec!["api", "logs"]);
+
+        // Terms can match different fields
+        assert_eq!(best("back refac", &candidates).unwrap().pane_name, "api");
+
+        // An empty query keeps the input (recency) order
+        assert_eq!(best("", &candidates).unwrap().pane_name, "logs");
+        assert!(best("nomatch", &candidates).is_none());
+    }
+}
@@ -0,0 +1,487 @@
+//! Minimal reader for the KDL that `zellij action dump-layout` prints.
+//!
+//! Only what snapshots need is extracted: tab names and how many panes each
+//! tab has. The reader tokenizes properly (strings with escapes, raw strings,
+//! comments, `/-` slashdash) and tracks block nesting, so panes defined in
+//! templates and swap layouts are not mistaken for open panes.
+//!
+//! [`parse`] exposes the same reader as a node tree, which is enough for
+//! property-only documents like `perth.kdl` workspace files.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Nodes whose children describe the actual layout. Panes nested in any
+/// other block (`new_tab_template`, `swap_tiled_layout`, `pane_template`...)
+/// are definitions, not panes.
+const LAYOUT_NODES: &[&str] = &["layout", "tab", "pane", "floating_panes"];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Equals,
+}
+
+#[derive(Debug, PartialEq)]
+enum Event {
+    Node {
+        name: String,
+        props: HashMap<String, String>,
+        has_children: bool,
+        commented_out: bool,
+    },
+    End,
+}
+
+struct Frame {
+    /// Whether panes among this block's children are real panes
+    layout_children: bool,
+    /// This block is a pane that has not (yet) shown pane children
+    leaf_pane: bool,
+    is_tab: bool,
+}
+
+/// Convert `dump-layout` KDL into the JSON shape snapshots read:
+/// `{"tabs": [{"name": ..., "panes": [{"name": "unnamed"}, ...]}]}`.
+///
+/// Only leaf panes are counted (split containers are not panes). Tabs
+/// without a `name` get Zellij's default "Tab #N". A layout without tabs
+/// becomes a single "default" tab.
+pub fn layout_to_json(kdl: &str) -> Value {
+    let mut tabs: Vec<(String, usize)> = Vec::new();
+    let mut root_panes = 0;
+    let mut current_tab: Option<usize> = None;
+    let mut stack: Vec<Frame> = Vec::new();
+
+    let mut count_pane = |current_tab: Option<usize>, tabs: &mut Vec<(String, usize)>| match current_tab {
+        Some(index) => tabs[index].1 += 1,
+        None => root_panes += 1,
+    };
+
+    for event in events(kdl) {
+        match event {
+            Event::Node { name, props, has_children, commented_out } => {
+                let in_layout = !commented_out && stack.last().is_none_or(|frame| frame.layout_children);
+                let is_pane = in_layout && name == "pane";
+                let is_tab = in_layout && name == "tab" && current_tab.is_none();
+
+                if is_pane {
+                    if let Some(parent) = stack.last_mut() {
+                        parent.leaf_pane = false;
+                    }
+                    if !has_children {
+                        count_pane(current_tab, &mut tabs);
+                    }
+                }
+                if is_tab {
+                    let tab_name = props
+                        .get("name")
+                        .cloned()
+                        .unwrap_or_else(|| format!("Tab #{}", tabs.len() + 1));
+                    tabs.push((tab_name, 0));
+                    if has_children {
+                        current_tab = Some(tabs.len() - 1);
+                    }
+                }
+                if has_children {
+                    stack.push(Frame {
+                        layout_children: in_layout && LAYOUT_NODES.contains(&name.as_str()),
+                        leaf_pane: is_pane,
+                        is_tab,
+                    });
+                }
+            }
+            Event::End => {
+                // Unbalanced closing braces are ignored
+                let Some(frame) = stack.pop() else {
+                    continue;
+                };
+                if frame.leaf_pane {
+                    count_pane(current_tab, &mut tabs);
+                }
+                if frame.is_tab {
+                    current_tab = None;
+                }
+            }
+        }
+    }
+
+    if tabs.is_empty() && root_panes > 0 {
+        tabs.push(("default".to_string(), root_panes));
+    }
+
+    let tabs: Vec<Value> = tabs
+        .into_iter()
+        .map(|(name, panes)| json!({ "name": name, "panes": vec![json!({ "name": "unnamed" }); panes] }))
+        .collect();
+    json!({ "tabs": tabs })
+}
+
+/// A KDL node: its name, `key=value` properties and child block.
+/// Positional arguments are not kept.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub name: String,
+    pub props: HashMap<String, String>,
+    pub children: Vec<Node>,
+}
+
+/// Parse a KDL document into its top-level nodes, dropping nodes (and
+/// blocks) commented out with `/-`.
+pub fn parse(kdl: &str) -> Vec<Node> {
+    // Each frame is an open block: its node (None if commented out) and children so far
+    let mut stack: Vec<(Option<Node>, Vec<Node>)> = vec![(None, Vec::new())];
+
+    for event in events(kdl) {
+        match event {
+            Event::Node { name, props, has_children, commented_out } => {
+                let node = Node { name, props, children: Vec::new() };
+                if has_children {
+                    stack.push(((!commented_out).then_some(node), Vec::new()));
+                } else if !commented_out {
+                    if let Some((_, siblings)) = stack.last_mut() {
+                        siblings.push(node);
+                    }
+                }
+            }
+            Event::End => {
+                // Unbalanced closing braces are ignored
+                if stack.len() < 2 {
+                    continue;
+                }
+                let (node, children) = stack.pop().unwrap_or_default();
+                if let (Some(mut node), Some((_, siblings))) = (node, stack.last_mut()) {
+                    node.children = children;
+                    siblings.push(node);
+                }
+            }
+        }
+    }
+
+    // Close blocks left open at the end of the document
+    while stack.len() > 1 {
+        let (node, children) = stack.pop().unwrap_or_default();
+        if let (Some(mut node), Some((_, siblings))) = (node, stack.last_mut()) {
+            node.children = children;
+            siblings.push(node);
+        }
+    }
+    stack.pop().map(|(_, nodes)| nodes).unwrap_or_default()
+}
+
+/// Flatten a KDL document into node and block-end events.
+fn events(kdl: &str) -> Vec<Event> {
+    let chars: Vec<char> = kdl.chars().collect();
+    let mut events = Vec::new();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut commented_out = false;
+    let mut i = 0;
+
+    let flush = |tokens: &mut Vec<Token>, commented_out: &mut bool, has_children: bool, events: &mut Vec<Event>| {
+        if tokens.is_empty() && !has_children {
+            return;
+        }
+        let name = match tokens.first() {
+            Some(Token::Word(word) | Token::Str(word)) => word.clone(),
+            _ => String::new(),
+        };
+        let mut props = HashMap::new();
+        for window in tokens.windows(3) {
+            if let [Token::Word(key), Token::Equals, Token::Word(value) | Token::Str(value)] = window {
+                props.insert(key.clone(), value.clone());
+            }
+        }
+        events.push(Event::Node { name, props, has_children, commented_out: *commented_out });
+        tokens.clear();
+        *commented_out = false;
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match c {
+            '\n' | ';' => {
+                flush(&mut tokens, &mut commented_out, false, &mut events);
+                i += 1;
+            }
+            '\\' if next == Some('\n') || (next == Some('\r') && chars.get(i + 2) == Some(&'\n')) => {
+                // Line continuation
+                i += if next == Some('\r') { 3 } else { 2 };
+            }
+            '/' if next == Some('/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '/' if next == Some('*') => {
+                i = skip_block_comment(&chars, i);
+            }
+            '/' if next == Some('-') => {
+                if tokens.is_empty() {
+                    commented_out = true;
+                }
+                i += 2;
+            }
+            '{' => {
+                flush(&mut tokens, &mut commented_out, true, &mut events);
+                i += 1;
+            }
+            '}' => {
+                flush(&mut tokens, &mut commented_out, false, &mut events);
+                events.push(Event::End);
+                i += 1;
+            }
+            '=' => {
+                tokens.push(Token::Equals);
+                i += 1;
+            }
+            '"' => {
+                let (value, end) = read_string(&chars, i + 1);
+                tokens.push(Token::Str(value));
+                i = end;
+            }
+            'r' if matches!(next, Some('"') | Some('#')) && raw_string_start(&chars, i).is_some() => {
+                let (value, end) = read_raw_string(&chars, i);
+                tokens.push(Token::Str(value));
+                i = end;
+            }
+            '(' => {
+                // Type annotation, e.g. (u8)10
+                while i < chars.len() && chars[i] != ')' && chars[i] != '\n' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            c if c.is_whitespace() => i += 1,
+            _ => {
+                // Always consume at least one char so stray `)`, `/` or `\` make progress
+                let start = i;
+                i += 1;
+                while i < chars.len() && !is_word_end(chars[i]) {
+                    i += 1;
+                }
+                tokens.push(Token::Word(chars[start..i].iter().collect()));
+            }
+        }
+    }
+    flush(&mut tokens, &mut commented_out, false, &mut events);
+    events
+}
+
+fn is_word_end(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '{' | '}' | '(' | ')' | ';' | '=' | '"' | '/' | '\\')
+}
+
+/// Index just past a (possibly nested) `/* ... */` comment starting at `start`.
+fn skip_block_comment(chars: &[char], start: usize) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+    while i < chars.len() {
+        match (chars[i], chars.get(i + 1)) {
+            ('/', Some('*')) => {
+                depth += 1;
+                i += 2;
+            }
+            ('*', Some('/')) => {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    chars.len()
+}
+
+/// Read an escaped string whose opening quote is just before `start`.
+/// Returns the value and the index just past the closing quote.
+fn read_string(chars: &[char], start: usize) -> (String, usize) {
+    let mut value = String::new();
+    let mut i = start;
+    while i < chars.len() {
+        match chars[i] {
+            '"' => return (value, i + 1),
+            '\\' => {
+                i += 1;
+                match chars.get(i) {
+                    Some('n') => value.push('\n'),
+                    Some('r') => value.push('\r'),
+                    Some('t') => value.push('\t'),
+                    Some('b') => value.push('\u{8}'),
+                    Some('f') => value.push('\u{c}'),
+                    Some('u') if chars.get(i + 1) == Some(&'{') => {
+                        let close = chars[i..].iter().position(|&c| c == '}').map(|p| i + p);
+                        let decoded = close.and_then(|close| {
+                            let hex: String = chars[i + 2..close].iter().collect();
+                            u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32).map(|c| (c, close))
+                        });
+                        match decoded {
+                            Some((c, close)) => {
+                                value.push(c);
+                                i = close;
+                            }
+                            None => value.push('u'),
+                        }
+                    }
+                    Some(&other) => value.push(other),
+                    None => break,
+                }
+                i += 1;
+            }
+            c => {
+                value.push(c);
+                i += 1;
+            }
+        }
+    }
+    // Unterminated: take the rest
+    (value, chars.len())
+}
+
+/// Number of `#`s of a raw string (`r"..."`, `r#"..."#`) starting at `start`.
+fn raw_string_start(chars: &[char], start: usize) -> Option<usize> {
+    let hashes = chars[start + 1..].iter().take_while(|&&c| c == '#').count();
+    (chars.get(start + 1 + hashes) == Some(&'"')).then_some(hashes)
+}
+
+fn read_raw_string(chars: &[char], start: usize) -> (String, usize) {
+    let hashes = raw_string_start(chars, start).unwrap_or(0);
+    let body = start + hashes + 2;
+    let mut i = body;
+    while i < chars.len() {
+        if chars[i] == '"' && chars[i + 1..].iter().take(hashes).filter(|&&c| c == '#').count() == hashes {
+            return (chars[body..i].iter().collect(), i + 1 + hashes);
+        }
+        i += 1;
+    }
+    (chars[body.min(chars.len())..].iter().collect(), chars.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn summary(layout: &Value) -> Vec<(String, usize)> {
+        layout["tabs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tab| (tab["name"].as_str().unwrap().to_string(), tab["panes"].as_array().unwrap().len()))
+            .collect()
+    }
+
+    fn quote(s: &str) -> String {
+        let mut quoted = String::from('"');
+        for c in s.chars() {
+            match c {
+                '"' => quoted.push_str("\\\""),
+                '\\' => quoted.push_str("\\\\"),
+                '\n' => quoted.push_str("\\n"),
+                '\r' => quoted.push_str("\\r"),
+                '\t' => quoted.push_str("\\t"),
+                c => quoted.push(c),
+            }
+        }
+        quoted.push('"');
+        quoted
+    }
+
+    #[test]
+    fn test_dump_layout() {
+        let kdl = r#"layout {
+    cwd "/home/me"
+    tab name="editor" focus=true hide_floating_panes=true {
+        pane size=1 borderless=true {
+            plugin location="zellij:tab-bar"
+        }
+        pane split_direction="vertical" {
+            pane command="nvim" cwd="src" {
+                args "main.rs"
+            }
+            pane
+        }
+    }
+    tab name="logs \"prod\"" {
+        pane
+    }
+    new_tab_template {
+        pane
+        pane
+    }
+    swap_tiled_layout name="stacked" {
+        tab min_panes=5 {
+            pane stacked=true { children; }
+        }
+    }
+}
+"#;
+        assert_eq!(
+            summary(&layout_to_json(kdl)),
+            vec![("editor".to_string(), 3), ("logs \"prod\"".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_comments_and_untitled_tabs() {
+        let kdl = "layout {\n  // tab name=\"ghost\" { pane; }\n  /* pane */\n  tab {\n    pane; /-pane\n    pane\n  }\n  tab name=r#\"raw \"name\"\"#\n}\n";
+        assert_eq!(
+            summary(&layout_to_json(kdl)),
+            vec![("Tab #1".to_string(), 2), ("raw \"name\"".to_string(), 0)]
+        );
+    }
+
+    #[test]
+    fn test_parse_tree() {
+        let kdl = "tab name=\"api\" {\n  pane name=api run=\"cargo run\"\n  /-pane name=old { pane; }\n}\ntab name=docs { pane name=notes";
+        let nodes = parse(kdl);
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0].props["name"], "api");
+        assert_eq!(nodes[0].children.len(), 1);
+        assert_eq!(nodes[0].children[0].props["run"], "cargo run");
+        // Unterminated blocks are closed at the end
+        assert_eq!(nodes[1].children[0].props["name"], "notes");
+    }
+
+    #[test]
+    fn test_root_panes_become_default_tab() {
+        assert_eq!(summary(&layout_to_json("layout {\n pane\n pane\n}")), vec![("default".to_string(), 2)]);
+        assert!(summary(&layout_to_json("")).is_empty());
+    }
+
+    proptest! {
+        #[test]
+        fn prop_never_panics(kdl in "[a-z {}\"/*\\\\;=#r()\\n-]{0,200}|\\PC{0,200}") {
+            let _ = layout_to_json(&kdl);
+        }
+
+        #[test]
+        fn prop_recovers_tabs(
+            tabs in proptest::collection::vec(("\\PC{0,12}", 0usize..4, any::<bool>()), 1..5),
+            template in any::<bool>(),
+        ) {
+            let mut kdl = String::from("layout {\n");
+            for (name, panes, nested) in &tabs {
+                kdl.push_str(&format!("    tab name={} {{\n", quote(name)));
+                if *nested && *panes > 0 {
+                    kdl.push_str("        pane split_direction=\"vertical\" {\n");
+                    kdl.push_str(&"            pane // a comment { \n".repeat(*panes));
+                    kdl.push_str("        }\n");
+                } else {
+                    kdl.push_str(&"        pane\n".repeat(*panes));
+                }
+                kdl.push_str("    }\n");
+            }
+            if template {
+                kdl.push_str("    new_tab_template {\n        pane\n        pane\n    }\n");
+            }
+            kdl.push_str("}\n");
+
+            let expected: Vec<(String, usize)> = tabs.iter().map(|(name, panes, _)| (name.clone(), *panes)).collect();
+            prop_assert_eq!(summary(&layout_to_json(&kdl)), expected);
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//! Zellij keybinding installer for `zdrive quicklog`.
+//!
+//! Adds a `shared_except "locked"` binding to the user's Zellij config that
+//! opens a floating pane running `zdrive quicklog`, which closes on exit.
+//! The snippet is wrapped in marker comments so re-running the installer
+//! replaces it instead of duplicating it.
+
+use anyhow::{Context, Result};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_KEY: &str = "Alt q";
+
+const BEGIN_MARKER: &str = "// BEGIN zdrive quicklog";
+const END_MARKER: &str = "// END zdrive quicklog";
+
+/// Outcome of installing the keybinding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstallOutcome {
+    /// Binding was added to the config
+    Installed,
+    /// An existing zdrive binding was replaced
+    Updated,
+}
+
+/// Returns the path to the Zellij config file.
+///
+/// Honors `ZELLIJ_CONFIG_FILE`, then `ZELLIJ_CONFIG_DIR`, then the XDG default.
+pub fn zellij_config_path() -> PathBuf {
+    if let Ok(file) = env::var("ZELLIJ_CONFIG_FILE") {
+        return PathBuf::from(file);
+    }
+    if let Ok(dir) = env::var("ZELLIJ_CONFIG_DIR") {
+        return Path::new(&dir).join("config.kdl");
+    }
+    if let Ok(dir) = env::var("XDG_CONFIG_HOME") {
+        return Path::new(&dir).join("zellij").join("config.kdl");
+    }
+
+    let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
+    Path::new(&home)
+        .join(".config")
+        .join("zellij")
+        .join("config.kdl")
+}
+
+/// Build the `shared_except "locked"` block binding `key` to a floating quicklog pane.
+pub fn binding_block(key: &str, indent: &str) -> String {
+    let lines = [
+        BEGIN_MARKER.to_string(),
+        "shared_except \"locked\" {".to_string(),
+        format!("    bind \"{}\" {{", key),
+        "        Run \"zdrive\" \"quicklog\" {".to_string(),
+        "            floating true".to_string(),
+        "            close_on_exit true".to_string(),
+        "        }".to_string(),
+        "        SwitchToMode \"Normal\"".to_string(),
+        "    }".to_string(),
+        "}".to_string(),
+        END_MARKER.to_string(),
+    ];
+
+    lines
+        .iter()
+        .map(|line| format!("{}{}\n", indent, line))
+        .collect()
+}
+
+/// Build a standalone `keybinds { ... }` snippet for configs without one.
+pub fn snippet(key: &str) -> String {
+    format!("keybinds {{\n{}}}\n", binding_block(key, "    "))
+}
+
+/// Insert or replace the quicklog binding within existing config contents.
+pub fn apply(contents: &str, key: &str) -> (String, InstallOutcome) {
+    // Replace a previously installed block in place
+    if let (Some(start), Some(end)) = (contents.find(BEGIN_MARKER), contents.find(END_MARKER)) {
+        if start < end {
+            let line_start = contents[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+            let indent = &contents[line_start..start];
+            let line_end = contents[end..]
+                .find('\n')
+                .map(|i| end + i + 1)
+                .unwrap_or(contents.len());
+
+            let mut updated = String::with_capacity(contents.len());
+            updated.push_str(&contents[..line_start]);
+            updated.push_str(&binding_block(key, indent));
+            updated.push_str(&contents[line_end..]);
+            return (updated, InstallOutcome::Updated);
+        }
+    }
+
+    // Zellij only reads the first top-level keybinds node, so nest inside it
+    let mut offset = 0;
+    for line in contents.split_inclusive('\n') {
+        if line.starts_with("keybinds") && line.trim_end().ends_with('{') {
+            let insert_at = offset + line.len();
+            let mut updated = String::with_capacity(contents.len());
+            updated.push_str(&contents[..insert_at]);
+            if !line.ends_with('\n') {
+                updated.push('\n');
+            }
+            updated.push_str(&binding_block(key, "    "));
+            updated.push_str(&contents[insert_at..]);
+            return (updated, InstallOutcome::Installed);
+        }
+        offset += line.len();
+    }
+
+    let mut updated = contents.to_string();
+    if !updated.is_empty() && !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+    if !updated.is_empty() {
+        updated.push('\n');
+    }
+    updated.push_str(&snippet(key));
+    (updated, InstallOutcome::Installed)
+}
+
+/// Install the quicklog binding into the Zellij config at `path`.
+pub fn install(path: &Path, key: &str) -> Result<InstallOutcome> {
+    let contents = if path.exists() {
+        fs::read_to_string(path)
+            .with_context(|| format!("failed to read Zellij config: {}", path.display()))?
+    } else {
+        String::new()
+    };
+
+    let (updated, outcome) = apply(&contents, key);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create config directory: {}", parent.display()))?;
+    }
+    fs::write(path, updated)
+        .with_context(|| format!("failed to write Zellij config: {}", path.display()))?;
+
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_to_empty_config() {
+        let (updated, outcome) = apply("", "Alt q");
+        assert_eq!(outcome, InstallOutcome::Installed);
+        assert!(updated.starts_with("keybinds {\n"));
+        assert!(updated.contains("bind \"Alt q\""));
+        assert!(updated.contains("Run \"zdrive\" \"quicklog\""));
+        assert!(updated.contains("floating true"));
+    }
+
+    #[test]
+    fn test_apply_nests_into_existing_keybinds() {
+        let config = "theme \"dracula\"\nkeybinds {\n    normal {\n    }\n}\n";
+        let (updated, outcome) = apply(config, "Alt q");
+
+        assert_eq!(outcome, InstallOutcome::Installed);
+        assert_eq!(updated.matches("keybinds").count(), 1);
+        let keybinds = updated.find("keybinds {").unwrap();
+        let binding = updated.find(BEGIN_MARKER).unwrap();
+        let normal = updated.find("normal {").unwrap();
+        assert!(keybinds < binding && binding < normal);
+    }
+
+    #[test]
+    fn test_apply_replaces_previous_install() {
+        let (first, _) = apply("", "Alt q");
+        let (second, outcome) = apply(&first, "Ctrl g");
+
+        assert_eq!(outcome, InstallOutcome::Updated);
+        assert_eq!(second.matches(BEGIN_MARKER).count(), 1);
+        assert!(second.contains("bind \"Ctrl g\""));
+        assert!(!second.contains("bind \"Alt q\""));
+    }
+}
@@ -4,6 +4,94 @@
 //! - Pane state tracking and persistence via Redis
 //! - Intent history logging for cognitive context preservation
 //! - Zellij terminal multiplexer integration
+//! - A multiplexer driver trait with an in-memory mock for tests
+//! - LLM summarization with secret filtering and context collection
+//! - Text, Markdown, HTML, CSV and JSON rendering of history
+//! - Bloodbank (AMQP) event publishing
+//! - Prometheus metrics for long-running modes
+//! - Optional OTLP tracing of Redis operations
+//! - Optional at-rest encryption of history and snapshots
+//!
+//! The `zdrive` binary is a thin command-line layer over this crate. To
+//! embed Perth's tracking in another tool, load the user's [`config::Config`]
+//! and drive an [`orchestrator::Orchestrator`]:
+//!
+//! ```no_run
+//! use zellij_driver::bloodbank::EventPublisher;
+//! use zellij_driver::config::Config;
+//! use zellij_driver::orchestrator::Orchestrator;
+//! use zellij_driver::state::StateManager;
+//! use zellij_driver::types::IntentEntry;
+//! use zellij_driver::zellij::ZellijDriver;
+//!
+//! # async fn run() -> anyhow::Result<()> {
+//! let config = Config::load()?;
+//! let state = StateManager::new(&config.redis_url).await?;
+//! let publisher = EventPublisher::new(config.bloodbank.clone());
+//! let mut orchestrator = Orchestrator::new(state, ZellijDriver::new(), publisher);
+//! orchestrator.log_intent("api-work", &IntentEntry::new("Wired token refresh")).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Failures worth handling programmatically (a missing pane, Redis being
+//! down, Zellij being too old) carry an [`error::PerthError`] in their
+//! `anyhow` chain.
+//!
+//! Tests can swap [`zellij::ZellijDriver`] for
+//! [`multiplexer::MockMultiplexer`], which records actions in memory.
+//!
+//! # Features
+//!
+//! All on by default. The `zdrive` binary builds with any of them off;
+//! commands that need a missing one (`listen`, `events tail`, `snapshot`)
+//! fail saying which feature it was built without.
+//!
+//! - `events`: Bloodbank publishing and consuming over AMQP. Without it
+//!   [`bloodbank::EventPublisher`] only writes the local event log.
+//! - `llm`: the network LLM providers. Without it only the local `none`
+//!   (heuristic) summarizer is available.
+//! - `otlp`: OpenTelemetry trace export. Without it a configured
+//!   `observability.otlp_endpoint` is warned about and tracing stays off.
+//! - `snapshots`: the [`snapshot`] and [`restore`] modules and zstd
+//!   compressed exports. Snapshots already stored in Redis can still be
+//!   listed and read.
 
+pub mod activity;
+pub mod artifacts;
+pub mod ask;
+pub mod bloodbank;
+pub mod config;
+pub mod context;
+pub mod crypto;
+pub mod describe;
+pub mod digest;
+pub mod error;
+pub mod filter;
+pub mod handoff;
+pub mod hook;
+pub mod jump;
+mod kdl;
+pub mod keybind;
+pub mod llm;
+pub mod metrics;
+pub mod multiplexer;
+pub mod obsidian;
+pub mod orchestrator;
+pub mod output;
+pub mod quicklog;
+pub mod release;
+pub mod report;
+#[cfg(feature = "snapshots")]
+pub mod restore;
+pub mod retention;
+#[cfg(feature = "snapshots")]
+pub mod snapshot;
 pub mod state;
+pub mod stats;
+pub mod telemetry;
+pub mod template;
+pub mod tracker;
 pub mod types;
+pub mod workspace;
+pub mod zellij;
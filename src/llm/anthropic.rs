@@ -1,8 +1,11 @@
-use super::{LLMProvider, SessionContext, SummarizationResult};
+use super::retry::ApiError;
+use super::stream::{read_lines, sse_data};
+use super::{parse_summary, Completion, LLMProvider, SessionContext, SummarizationResult};
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
-use reqwest::Client;
+use reqwest::{Client, Response};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 const ANTHROPIC_API_URL: &str = "https://api.anthropic.com/v1/messages";
 const ANTHROPIC_VERSION: &str = "2023-06-01";
@@ -50,20 +53,22 @@ impl AnthropicProvider {
             prompt.push_str("```\n\n");
         }
 
+        if let Some(scrollback) = &context.scrollback {
+            prompt.push_str("## Recent Terminal Output:\n```\n");
+            prompt.push_str(scrollback);
+            prompt.push_str("\n```\n\n");
+        }
+
         if let Some(diff) = &context.git_diff {
             if !diff.is_empty() {
                 prompt.push_str("## Git Diff:\n```diff\n");
-                // Truncate large diffs
-                if diff.len() > 4000 {
-                    prompt.push_str(&diff[..4000]);
-                    prompt.push_str("\n... (truncated)\n");
-                } else {
-                    prompt.push_str(diff);
-                }
+                prompt.push_str(diff);
                 prompt.push_str("```\n\n");
             }
         }
 
+        prompt.push_str(&context.git_activity_prompt());
+
         if !context.active_files.is_empty() {
             prompt.push_str("## Active Files:\n");
             for file in &context.active_files {
@@ -85,6 +90,36 @@ impl AnthropicProvider {
 
         prompt
     }
+
+    fn request(&self, prompt: String, stream: bool) -> AnthropicRequest {
+        AnthropicRequest {
+            model: self.model.clone(),
+            max_tokens: self.max_tokens,
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: prompt,
+            }],
+            stream,
+        }
+    }
+
+    async fn send(&self, request: &AnthropicRequest) -> Result<Response> {
+        let response = self
+            .client
+            .post(ANTHROPIC_API_URL)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .header("content-type", "application/json")
+            .json(request)
+            .send()
+            .await
+            .context("failed to send request to Anthropic API")?;
+
+        if !response.status().is_success() {
+            return Err(ApiError::from_response("Anthropic", response).await.into());
+        }
+        Ok(response)
+    }
 }
 
 #[derive(Serialize)]
@@ -92,6 +127,8 @@ struct AnthropicRequest {
     model: String,
     max_tokens: u32,
     messages: Vec<Message>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
 }
 
 #[derive(Serialize)]
@@ -117,48 +154,11 @@ struct Usage {
     output_tokens: u32,
 }
 
-#[derive(Deserialize)]
-struct SummaryJson {
-    summary: String,
-    #[serde(rename = "type")]
-    entry_type: Option<String>,
-    key_files: Option<Vec<String>>,
-}
-
 #[async_trait]
 impl LLMProvider for AnthropicProvider {
     async fn summarize(&self, context: &SessionContext) -> Result<SummarizationResult> {
         let prompt = self.build_prompt(context);
-
-        let request = AnthropicRequest {
-            model: self.model.clone(),
-            max_tokens: self.max_tokens,
-            messages: vec![Message {
-                role: "user".to_string(),
-                content: prompt,
-            }],
-        };
-
-        let response = self
-            .client
-            .post(ANTHROPIC_API_URL)
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", ANTHROPIC_VERSION)
-            .header("content-type", "application/json")
-            .json(&request)
-            .send()
-            .await
-            .context("failed to send request to Anthropic API")?;
-
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(anyhow!(
-                "Anthropic API error ({}): {}",
-                status,
-                error_text
-            ));
-        }
+        let response = self.send(&self.request(prompt, false)).await?;
 
         let api_response: AnthropicResponse = response
             .json()
@@ -171,35 +171,72 @@ impl LLMProvider for AnthropicProvider {
             .and_then(|c| c.text.as_ref())
             .ok_or_else(|| anyhow!("no text content in Anthropic response"))?;
 
-        // Try to parse as JSON, fall back to using raw text as summary
-        let (summary, suggested_type, key_files) = match serde_json::from_str::<SummaryJson>(text) {
-            Ok(parsed) => (
-                parsed.summary,
-                parsed.entry_type,
-                parsed.key_files.unwrap_or_default(),
-            ),
-            Err(_) => {
-                // If not valid JSON, use the raw text as the summary
-                (text.clone(), None, Vec::new())
-            }
-        };
+        let (summary, suggested_type, key_files) = parse_summary(text)?;
 
-        let tokens_used = api_response
-            .usage
-            .map(|u| u.input_tokens + u.output_tokens);
+        let usage = api_response.usage;
 
         Ok(SummarizationResult {
             summary,
             suggested_type,
             key_files,
-            tokens_used,
+            tokens_used: usage.as_ref().map(|u| u.input_tokens + u.output_tokens),
+            input_tokens: usage.as_ref().map(|u| u.input_tokens),
+            output_tokens: usage.as_ref().map(|u| u.output_tokens),
         })
     }
 
+    async fn summarize_streaming(
+        &self,
+        context: &SessionContext,
+        on_text: &mut (dyn for<'s> FnMut(&'s str) + Send),
+    ) -> Result<SummarizationResult> {
+        self.complete(&self.build_prompt(context), on_text).await?.into_summary()
+    }
+
+    async fn complete(&self, prompt: &str, on_text: &mut (dyn for<'s> FnMut(&'s str) + Send)) -> Result<Completion> {
+        let response = self.send(&self.request(prompt.to_string(), true)).await?;
+
+        let mut completion = Completion::default();
+        read_lines(response, |line| {
+            let Some(data) = sse_data(line) else {
+                return Ok(());
+            };
+            let event: Value = serde_json::from_str(data).context("malformed Anthropic stream event")?;
+            match event["type"].as_str() {
+                Some("message_start") => {
+                    completion.input_tokens = event["message"]["usage"]["input_tokens"].as_u64().map(|n| n as u32);
+                }
+                Some("content_block_delta") => {
+                    completion.push(event["delta"]["text"].as_str().unwrap_or_default(), on_text);
+                }
+                Some("message_delta") => {
+                    completion.output_tokens = event["usage"]["output_tokens"].as_u64().map(|n| n as u32);
+                }
+                Some("error") => {
+                    let message = event["error"]["message"].as_str().unwrap_or("unknown error");
+                    return Err(anyhow!("Anthropic stream error: {}", message));
+                }
+                _ => {}
+            }
+            Ok(())
+        })
+        .await?;
+
+        Ok(completion)
+    }
+
     fn name(&self) -> &'static str {
         "anthropic"
     }
 
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn prompt(&self, context: &SessionContext) -> String {
+        self.build_prompt(context)
+    }
+
     fn is_available(&self) -> bool {
         !self.api_key.is_empty()
     }
@@ -249,6 +286,22 @@ mod tests {
         assert!(prompt.contains("cargo test"));
     }
 
+    #[test]
+    fn test_build_prompt_with_scrollback() {
+        let provider = AnthropicProvider::new(
+            "test-key".to_string(),
+            "claude-sonnet-4-20250514".to_string(),
+            1024,
+        );
+
+        let without = provider.build_prompt(&SessionContext::new("build"));
+        assert!(!without.contains("Recent Terminal Output"));
+
+        let context = SessionContext::new("build").with_scrollback("test auth::refresh ... FAILED");
+        let prompt = provider.build_prompt(&context);
+        assert!(prompt.contains("## Recent Terminal Output:\n```\ntest auth::refresh ... FAILED\n```"));
+    }
+
     #[test]
     fn test_is_available() {
         let provider = AnthropicProvider::new(
@@ -0,0 +1,194 @@
+use super::{estimate_tokens, SessionContext};
+use serde::{Deserialize, Serialize};
+
+const TRUNCATED: &str = "\n... (truncated)\n";
+
+/// Relative shares of the context budget given to each variable-size part
+/// of a [`SessionContext`]. A zero weight drops that part whenever the
+/// context is over budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BudgetWeights {
+    pub history: u32,
+    pub diff: u32,
+    pub files: u32,
+    pub scrollback: u32,
+}
+
+impl Default for BudgetWeights {
+    fn default() -> Self {
+        Self {
+            history: 3,
+            diff: 4,
+            files: 1,
+            scrollback: 2,
+        }
+    }
+}
+
+/// Trim `context` so its shell history, diff, active files, and scrollback
+/// fit in roughly `budget` tokens, split by `weights`. Parts that need less
+/// than their share give the rest to the others, so a small diff leaves
+/// more room for history. The branch, commits, and other fixed-size fields
+/// are left alone.
+pub fn fit_to_budget(mut context: SessionContext, budget: u32, weights: &BudgetWeights) -> SessionContext {
+    let demands = [
+        lines_tokens(&context.shell_history),
+        context.git_diff.as_deref().map_or(0, estimate_tokens),
+        lines_tokens(&context.active_files),
+        context.scrollback.as_deref().map_or(0, estimate_tokens),
+    ];
+    if demands.iter().sum::<u32>() <= budget {
+        return context;
+    }
+
+    let weights = [weights.history, weights.diff, weights.files, weights.scrollback];
+    let [history, diff, files, scrollback] = allocate(budget, demands, weights);
+
+    let skip = context.shell_history.len() - fit_lines(context.shell_history.iter().rev(), history);
+    context.shell_history.drain(..skip);
+    context.git_diff = context.git_diff.take().and_then(|d| truncate_head(d, diff));
+    context.active_files.truncate(fit_lines(context.active_files.iter(), files));
+    context.scrollback = context.scrollback.take().and_then(|s| truncate_tail(s, scrollback));
+    context
+}
+
+/// Split `budget` across parts in proportion to their weights, capping each
+/// at its demand and handing the surplus to parts that still want more.
+fn allocate(budget: u32, demands: [u32; 4], weights: [u32; 4]) -> [u32; 4] {
+    let mut granted = [0u32; 4];
+    let mut open: Vec<usize> = (0..4).filter(|&i| weights[i] > 0 && demands[i] > 0).collect();
+    let mut remaining = budget;
+
+    while !open.is_empty() && remaining > 0 {
+        let total_weight: u64 = open.iter().map(|&i| weights[i] as u64).sum();
+        let share = |i: usize| (remaining as u64 * weights[i] as u64 / total_weight) as u32;
+
+        // Parts that fit in their share are settled; repeat with the rest
+        let (settled, unsettled): (Vec<usize>, Vec<usize>) =
+            open.iter().partition(|&&i| demands[i] <= share(i));
+        if settled.is_empty() {
+            for &i in &unsettled {
+                granted[i] = share(i);
+            }
+            break;
+        }
+        for &i in &settled {
+            granted[i] = demands[i];
+            remaining -= demands[i];
+        }
+        open = unsettled;
+    }
+    granted
+}
+
+fn lines_tokens(lines: &[String]) -> u32 {
+    lines.iter().map(|line| estimate_tokens(line) + 1).sum()
+}
+
+/// How many of `lines`, taken in order, fit in `budget` tokens.
+fn fit_lines<'a>(lines: impl Iterator<Item = &'a String>, budget: u32) -> usize {
+    let mut used = 0;
+    lines
+        .take_while(|line| {
+            used += estimate_tokens(line) + 1;
+            used <= budget
+        })
+        .count()
+}
+
+/// Keep the start of `text` within `budget` tokens, marking the cut.
+fn truncate_head(text: String, budget: u32) -> Option<String> {
+    if estimate_tokens(&text) <= budget {
+        return Some(text);
+    }
+    // Only text that gets cut needs room for the marker
+    let keep = (budget as usize * 4).saturating_sub(TRUNCATED.len());
+    if keep == 0 {
+        return None;
+    }
+    let mut kept: String = text.chars().take(keep).collect();
+    kept.push_str(TRUNCATED);
+    Some(kept)
+}
+
+/// Keep the end of `text` within `budget` tokens, where the latest output is.
+fn truncate_tail(text: String, budget: u32) -> Option<String> {
+    let keep = budget as usize * 4;
+    let len = text.chars().count();
+    if keep == 0 {
+        return None;
+    }
+    if len <= keep {
+        return Some(text);
+    }
+    Some(text.chars().skip(len - keep).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("cargo test --test case_{:03}", i)).collect()
+    }
+
+    #[test]
+    fn test_context_within_budget_is_untouched() {
+        let context = SessionContext::new("api")
+            .with_shell_history(commands(3))
+            .with_git_diff("+fn main() {}");
+        let fitted = fit_to_budget(context.clone(), 1_000, &BudgetWeights::default());
+        assert_eq!(fitted.shell_history, context.shell_history);
+        assert_eq!(fitted.git_diff, context.git_diff);
+    }
+
+    #[test]
+    fn test_large_diff_is_truncated_to_its_share() {
+        let context = SessionContext::new("api")
+            .with_shell_history(commands(200))
+            .with_git_diff("é".repeat(20_000));
+        let fitted = fit_to_budget(context, 1_000, &BudgetWeights::default());
+
+        let diff = fitted.git_diff.unwrap();
+        assert!(diff.ends_with("... (truncated)\n"));
+        assert!(estimate_tokens(&diff) <= 1_000 * 4 / 7);
+
+        // History keeps the newest commands
+        assert_eq!(fitted.shell_history.last().unwrap(), "cargo test --test case_199");
+        assert!(fitted.shell_history.len() < 200);
+    }
+
+    #[test]
+    fn test_unused_share_goes_to_other_parts() {
+        // A tiny diff leaves nearly the whole budget for history
+        let context = SessionContext::new("api")
+            .with_shell_history(commands(200))
+            .with_git_diff("+x");
+        let fitted = fit_to_budget(context, 1_000, &BudgetWeights::default());
+        assert_eq!(fitted.git_diff.as_deref(), Some("+x"));
+        assert!(lines_tokens(&fitted.shell_history) > 900);
+        assert!(lines_tokens(&fitted.shell_history) <= 1_000);
+    }
+
+    #[test]
+    fn test_zero_weight_drops_part_when_over_budget() {
+        let weights = BudgetWeights {
+            scrollback: 0,
+            ..BudgetWeights::default()
+        };
+        let context = SessionContext::new("api")
+            .with_git_diff("a".repeat(8_000))
+            .with_scrollback("error: linker failed");
+        let fitted = fit_to_budget(context, 500, &weights);
+        assert!(fitted.scrollback.is_none());
+        assert!(fitted.git_diff.is_some());
+    }
+
+    #[test]
+    fn test_scrollback_keeps_latest_output() {
+        let scrollback = format!("{}\nFAILED", "ok\n".repeat(5_000));
+        let context = SessionContext::new("api").with_scrollback(scrollback);
+        let fitted = fit_to_budget(context, 100, &BudgetWeights::default());
+        assert!(fitted.scrollback.unwrap().ends_with("FAILED"));
+    }
+}
@@ -0,0 +1,315 @@
+use super::retry::ApiError;
+use super::stream::{read_lines, sse_data};
+use super::{parse_summary, Completion, LLMProvider, SessionContext, SummarizationResult};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use reqwest::{Client, Response};
+use serde::{Deserialize, Serialize};
+
+const GEMINI_API_URL: &str = "https://generativelanguage.googleapis.com/v1beta/models";
+
+/// Google Gemini provider for LLM summarization.
+pub struct GeminiProvider {
+    client: Client,
+    api_key: String,
+    model: String,
+    max_tokens: u32,
+}
+
+impl GeminiProvider {
+    pub fn new(api_key: String, model: String, max_tokens: u32) -> Self {
+        Self {
+            client: Client::new(),
+            api_key,
+            model,
+            max_tokens,
+        }
+    }
+
+    fn endpoint(&self) -> String {
+        format!("{}/{}:generateContent", GEMINI_API_URL, self.model)
+    }
+
+    fn stream_endpoint(&self) -> String {
+        format!("{}/{}:streamGenerateContent?alt=sse", GEMINI_API_URL, self.model)
+    }
+
+    fn request(&self, prompt: String, json: bool) -> GeminiRequest {
+        GeminiRequest {
+            contents: vec![Content {
+                role: Some("user".to_string()),
+                parts: vec![Part { text: Some(prompt) }],
+            }],
+            generation_config: GenerationConfig {
+                max_output_tokens: self.max_tokens,
+                response_mime_type: json.then(|| "application/json".to_string()),
+            },
+        }
+    }
+
+    async fn send(&self, url: String, request: &GeminiRequest) -> Result<Response> {
+        let response = self
+            .client
+            .post(url)
+            .header("x-goog-api-key", &self.api_key)
+            .header("Content-Type", "application/json")
+            .json(request)
+            .send()
+            .await
+            .context("failed to send request to Gemini API")?;
+
+        if !response.status().is_success() {
+            return Err(ApiError::from_response("Gemini", response).await.into());
+        }
+        Ok(response)
+    }
+
+    fn build_prompt(&self, context: &SessionContext) -> String {
+        let mut prompt = String::new();
+
+        prompt.push_str("You are a developer assistant helping to summarize a coding session. ");
+        prompt.push_str("Based on the following context, generate a concise summary of what was accomplished.\n\n");
+
+        prompt.push_str(&format!("## Pane: {}\n\n", context.pane_name));
+
+        if let Some(branch) = &context.git_branch {
+            prompt.push_str(&format!("## Git Branch: {}\n\n", branch));
+        }
+
+        if !context.cwd.is_empty() {
+            prompt.push_str(&format!("## Working Directory: {}\n\n", context.cwd));
+        }
+
+        if !context.shell_history.is_empty() {
+            prompt.push_str("## Recent Commands:\n```\n");
+            for cmd in &context.shell_history {
+                prompt.push_str(cmd);
+                prompt.push('\n');
+            }
+            prompt.push_str("```\n\n");
+        }
+
+        if let Some(scrollback) = &context.scrollback {
+            prompt.push_str("## Recent Terminal Output:\n```\n");
+            prompt.push_str(scrollback);
+            prompt.push_str("\n```\n\n");
+        }
+
+        if let Some(diff) = &context.git_diff {
+            if !diff.is_empty() {
+                prompt.push_str("## Git Diff:\n```diff\n");
+                prompt.push_str(diff);
+                prompt.push_str("```\n\n");
+            }
+        }
+
+        prompt.push_str(&context.git_activity_prompt());
+
+        if !context.active_files.is_empty() {
+            prompt.push_str("## Active Files:\n");
+            for file in &context.active_files {
+                prompt.push_str(&format!("- {}\n", file));
+            }
+            prompt.push('\n');
+        }
+
+        if let Some(existing) = &context.existing_summary {
+            prompt.push_str(&format!("## Previous Summary:\n{}\n\n", existing));
+        }
+
+        prompt.push_str("## Instructions:\n");
+        prompt.push_str("1. Generate a brief (1-2 sentence) summary of what was accomplished\n");
+        prompt.push_str("2. Suggest whether this is a 'milestone', 'checkpoint', or 'exploration'\n");
+        prompt.push_str("3. List any key files that were modified\n\n");
+        prompt.push_str("Respond in this exact JSON format:\n");
+        prompt.push_str(r#"{"summary": "...", "type": "checkpoint|milestone|exploration", "key_files": ["file1.rs", "file2.rs"]}"#);
+
+        prompt
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiRequest {
+    contents: Vec<Content>,
+    generation_config: GenerationConfig,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Content {
+    #[serde(default)]
+    role: Option<String>,
+    #[serde(default)]
+    parts: Vec<Part>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Part {
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig {
+    max_output_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_mime_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    usage_metadata: Option<UsageMetadata>,
+}
+
+#[derive(Deserialize)]
+struct Candidate {
+    content: Option<Content>,
+}
+
+impl GeminiResponse {
+    /// Text of the first candidate's parts, joined.
+    fn text(&self) -> String {
+        self.candidates
+            .first()
+            .and_then(|c| c.content.as_ref())
+            .map(|content| content.parts.iter().filter_map(|p| p.text.as_deref()).collect())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageMetadata {
+    prompt_token_count: Option<u32>,
+    candidates_token_count: Option<u32>,
+    total_token_count: Option<u32>,
+}
+
+#[async_trait]
+impl LLMProvider for GeminiProvider {
+    async fn summarize(&self, context: &SessionContext) -> Result<SummarizationResult> {
+        let prompt = self.build_prompt(context);
+        let response = self.send(self.endpoint(), &self.request(prompt, true)).await?;
+
+        let api_response: GeminiResponse = response
+            .json()
+            .await
+            .context("failed to parse Gemini API response")?;
+
+        let text = Some(api_response.text())
+            .filter(|text| !text.is_empty())
+            .ok_or_else(|| anyhow!("no content in Gemini response"))?;
+
+        let (summary, suggested_type, key_files) = parse_summary(&text)?;
+
+        let usage = api_response.usage_metadata;
+
+        Ok(SummarizationResult {
+            summary,
+            suggested_type,
+            key_files,
+            tokens_used: usage.as_ref().and_then(|u| u.total_token_count),
+            input_tokens: usage.as_ref().and_then(|u| u.prompt_token_count),
+            output_tokens: usage.as_ref().and_then(|u| u.candidates_token_count),
+        })
+    }
+
+    async fn complete(&self, prompt: &str, on_text: &mut (dyn for<'s> FnMut(&'s str) + Send)) -> Result<Completion> {
+        let request = self.request(prompt.to_string(), false);
+        let response = self.send(self.stream_endpoint(), &request).await?;
+
+        // Each event is a partial GenerateContentResponse; usage arrives with the last
+        let mut completion = Completion::default();
+        read_lines(response, |line| {
+            let Some(data) = sse_data(line) else {
+                return Ok(());
+            };
+            let chunk: GeminiResponse = serde_json::from_str(data).context("malformed Gemini stream event")?;
+            completion.push(&chunk.text(), on_text);
+            if let Some(usage) = chunk.usage_metadata {
+                completion.input_tokens = usage.prompt_token_count.or(completion.input_tokens);
+                completion.output_tokens = usage.candidates_token_count.or(completion.output_tokens);
+            }
+            Ok(())
+        })
+        .await?;
+
+        Ok(completion)
+    }
+
+    fn name(&self) -> &'static str {
+        "gemini"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn prompt(&self, context: &SessionContext) -> String {
+        self.build_prompt(context)
+    }
+
+    fn is_available(&self) -> bool {
+        !self.api_key.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoint_includes_model() {
+        let provider = GeminiProvider::new("test-key".to_string(), "gemini-2.0-flash".to_string(), 1024);
+        assert_eq!(
+            provider.endpoint(),
+            "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.0-flash:generateContent"
+        );
+    }
+
+    #[test]
+    fn test_request_uses_json_mode() {
+        let request = GeminiRequest {
+            contents: vec![Content {
+                role: Some("user".to_string()),
+                parts: vec![Part { text: Some("hi".to_string()) }],
+            }],
+            generation_config: GenerationConfig {
+                max_output_tokens: 256,
+                response_mime_type: Some("application/json".to_string()),
+            },
+        };
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["generationConfig"]["responseMimeType"], "application/json");
+        assert_eq!(json["generationConfig"]["maxOutputTokens"], 256);
+        assert_eq!(json["contents"][0]["parts"][0]["text"], "hi");
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let body = r#"{
+            "candidates": [{"content": {"role": "model", "parts": [{"text": "{\"summary\": \"Done\"}"}]}}],
+            "usageMetadata": {"promptTokenCount": 10, "candidatesTokenCount": 5, "totalTokenCount": 15}
+        }"#;
+        let response: GeminiResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(response.candidates.len(), 1);
+        let usage = response.usage_metadata.unwrap();
+        assert_eq!(usage.total_token_count, Some(15));
+        assert_eq!(usage.prompt_token_count, Some(10));
+        assert_eq!(usage.candidates_token_count, Some(5));
+    }
+
+    #[test]
+    fn test_is_available() {
+        let provider = GeminiProvider::new("key".to_string(), "gemini-2.0-flash".to_string(), 1024);
+        assert!(provider.is_available());
+
+        let empty_provider = GeminiProvider::new(String::new(), "gemini-2.0-flash".to_string(), 1024);
+        assert!(!empty_provider.is_available());
+    }
+}
@@ -0,0 +1,238 @@
+//! Local summarizer used when no LLM is configured, or as a fallback when
+//! the configured one fails.
+//!
+//! Builds a checkpoint from what is already on the machine: the branch, the
+//! files `git status` reports as changed, and the most frequent recent
+//! commands. Nothing leaves the machine.
+
+use super::{Completion, LLMProvider, SessionContext, SummarizationResult};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::process::Command;
+
+/// Commands whose first argument is a subcommand worth keeping ("cargo test").
+const SUBCOMMAND_TOOLS: &[&str] = &[
+    "cargo", "git", "npm", "pnpm", "yarn", "bun", "docker", "kubectl", "go", "make", "just", "uv", "poetry",
+    "mise", "terraform", "zdrive",
+];
+
+/// How many commands and files are named in the summary.
+const TOP_COMMANDS: usize = 3;
+const NAMED_FILES: usize = 3;
+/// Changed files reported as key files.
+const MAX_KEY_FILES: usize = 10;
+
+/// Summarizer that never makes a network call.
+pub struct HeuristicProvider;
+
+impl HeuristicProvider {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for HeuristicProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl LLMProvider for HeuristicProvider {
+    async fn summarize(&self, context: &SessionContext) -> Result<SummarizationResult> {
+        let changed = if context.cwd.is_empty() { Vec::new() } else { git_changed_files(&context.cwd) };
+        Ok(summarize_locally(context, &changed))
+    }
+
+    async fn complete(&self, _prompt: &str, _on_text: &mut (dyn for<'s> FnMut(&'s str) + Send)) -> Result<Completion> {
+        Err(anyhow!(
+            "LLM unavailable: LLM provider disabled. Set [llm].provider in config to enable."
+        ))
+    }
+
+    fn name(&self) -> &'static str {
+        "heuristic"
+    }
+
+    fn model(&self) -> &str {
+        "local"
+    }
+
+    fn prompt(&self, _context: &SessionContext) -> String {
+        "(no prompt: summarized locally from branch, git status and recent commands)".to_string()
+    }
+
+    fn is_available(&self) -> bool {
+        true
+    }
+}
+
+/// Build a checkpoint summary from the context and the changed files.
+pub fn summarize_locally(context: &SessionContext, changed_files: &[String]) -> SummarizationResult {
+    let mut parts = Vec::new();
+
+    if !changed_files.is_empty() {
+        let mut named: Vec<&str> = changed_files.iter().take(NAMED_FILES).map(String::as_str).collect();
+        let more = changed_files.len().saturating_sub(NAMED_FILES);
+        let more = if more > 0 { format!(", +{} more", more) } else { String::new() };
+        named.sort_unstable();
+        parts.push(format!(
+            "changed {} ({}{})",
+            crate::output::messages::count(changed_files.len(), "file"),
+            named.join(", "),
+            more
+        ));
+    }
+
+    let commands = top_commands(&context.shell_history, TOP_COMMANDS);
+    if !commands.is_empty() {
+        let ran: Vec<String> = commands
+            .iter()
+            .map(|(command, n)| if *n > 1 { format!("{} ×{}", command, n) } else { command.clone() })
+            .collect();
+        parts.push(format!("ran {}", ran.join(", ")));
+    }
+
+    let place = match &context.git_branch {
+        Some(branch) => format!("Worked on {}", branch),
+        None => format!("Worked in {}", if context.cwd.is_empty() { &context.pane_name } else { &context.cwd }),
+    };
+    let summary = if parts.is_empty() {
+        format!("{}: no changes or recent commands detected", place)
+    } else {
+        format!("{}: {}", place, parts.join("; "))
+    };
+
+    SummarizationResult {
+        summary,
+        suggested_type: Some("checkpoint".to_string()),
+        key_files: changed_files.iter().take(MAX_KEY_FILES).cloned().collect(),
+        tokens_used: None,
+        input_tokens: None,
+        output_tokens: None,
+    }
+}
+
+/// The `limit` most frequent commands, by tool (plus subcommand for tools
+/// like git and cargo), most frequent first; ties keep first-seen order.
+fn top_commands(history: &[String], limit: usize) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+
+    for line in history {
+        let Some(command) = command_key(line) else {
+            continue;
+        };
+        match index.get(&command) {
+            Some(&i) => counts[i].1 += 1,
+            None => {
+                index.insert(command.clone(), counts.len());
+                counts.push((command, 1));
+            }
+        }
+    }
+
+    // Stable sort keeps first-seen order among ties
+    counts.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+    counts.truncate(limit);
+    counts
+}
+
+/// "cargo test --release" -> "cargo test", "ls -la" -> "ls". Skips leading
+/// `VAR=value` assignments and `sudo`.
+fn command_key(line: &str) -> Option<String> {
+    let mut words = line
+        .split_whitespace()
+        .skip_while(|w| (w.contains('=') && !w.starts_with('-')) || *w == "sudo");
+    let tool = words.next()?;
+    let tool = tool.rsplit('/').next().unwrap_or(tool);
+    if !SUBCOMMAND_TOOLS.contains(&tool) {
+        return Some(tool.to_string());
+    }
+    match words.next() {
+        Some(sub) if sub.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') && !sub.starts_with('-') => {
+            Some(format!("{} {}", tool, sub))
+        }
+        _ => Some(tool.to_string()),
+    }
+}
+
+/// Files `git status` reports as changed in `cwd` (empty outside a repo).
+fn git_changed_files(cwd: &str) -> Vec<String> {
+    Command::new("git")
+        .args(["status", "--porcelain"])
+        .current_dir(cwd)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| parse_porcelain(&String::from_utf8_lossy(&o.stdout)))
+        .unwrap_or_default()
+}
+
+/// Paths from `git status --porcelain` output; renames give the new path.
+fn parse_porcelain(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let path = line.get(3..)?;
+            let path = path.rsplit(" -> ").next().unwrap_or(path);
+            let path = path.trim().trim_matches('"');
+            (!path.is_empty()).then(|| path.to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(commands: &[&str]) -> Vec<String> {
+        commands.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn test_top_commands() {
+        let history = history(&[
+            "cargo test",
+            "git status",
+            "cargo test --release",
+            "RUST_LOG=debug cargo test",
+            "vim src/main.rs",
+            "git status",
+            "sudo /usr/bin/docker ps -a",
+        ]);
+        assert_eq!(
+            top_commands(&history, 3),
+            vec![("cargo test".to_string(), 3), ("git status".to_string(), 2), ("vim".to_string(), 1)]
+        );
+        assert_eq!(command_key("sudo /usr/bin/docker ps -a").as_deref(), Some("docker ps"));
+        assert_eq!(command_key("   "), None);
+    }
+
+    #[test]
+    fn test_parse_porcelain() {
+        let output = " M src/main.rs\n?? notes.md\nR  old.rs -> src/new.rs\nA  \"with space.rs\"\n";
+        assert_eq!(parse_porcelain(output), vec!["src/main.rs", "notes.md", "src/new.rs", "with space.rs"]);
+    }
+
+    #[test]
+    fn test_summarize_locally() {
+        let context = SessionContext::new("api")
+            .with_git_branch("feature/retry")
+            .with_shell_history(history(&["cargo test", "cargo test", "git diff"]));
+        let changed = history(&["src/retry.rs", "src/lib.rs", "tests/retry.rs", "Cargo.toml"]);
+
+        let result = summarize_locally(&context, &changed);
+        assert_eq!(
+            result.summary,
+            "Worked on feature/retry: changed 4 files (src/lib.rs, src/retry.rs, tests/retry.rs, +1 more); \
+             ran cargo test ×2, git diff"
+        );
+        assert_eq!(result.suggested_type.as_deref(), Some("checkpoint"));
+        assert_eq!(result.key_files, changed);
+
+        let idle = summarize_locally(&SessionContext::new("api"), &[]);
+        assert_eq!(idle.summary, "Worked in api: no changes or recent commands detected");
+    }
+}
@@ -1,4 +1,4 @@
-use super::{LLMProvider, SessionContext, SummarizationResult};
+use super::{Completion, LLMProvider, SessionContext, SummarizationResult};
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 
@@ -22,10 +22,22 @@ impl LLMProvider for NoOpProvider {
         Err(anyhow!("LLM unavailable: {}", self.reason))
     }
 
+    async fn complete(&self, _prompt: &str, _on_text: &mut (dyn for<'s> FnMut(&'s str) + Send)) -> Result<Completion> {
+        Err(anyhow!("LLM unavailable: {}", self.reason))
+    }
+
     fn name(&self) -> &'static str {
         "noop"
     }
 
+    fn model(&self) -> &str {
+        "none"
+    }
+
+    fn prompt(&self, _context: &SessionContext) -> String {
+        String::new()
+    }
+
     fn is_available(&self) -> bool {
         false
     }
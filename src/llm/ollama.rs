@@ -1,7 +1,9 @@
-use super::{LLMProvider, SessionContext, SummarizationResult};
+use super::retry::ApiError;
+use super::stream::read_lines;
+use super::{parse_summary, Completion, LLMProvider, SessionContext, SummarizationResult};
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
-use reqwest::Client;
+use reqwest::{Client, Response};
 use serde::{Deserialize, Serialize};
 
 /// Ollama provider for local LLM summarization.
@@ -24,6 +26,36 @@ impl OllamaProvider {
         format!("{}/api/generate", self.endpoint.trim_end_matches('/'))
     }
 
+    /// Send a prompt with streaming on, passing each piece of the response to `on_text`.
+    async fn stream(&self, prompt: String, json: bool, on_text: &mut (dyn for<'s> FnMut(&'s str) + Send)) -> Result<Completion> {
+        let request = OllamaRequest {
+            model: self.model.clone(),
+            prompt,
+            stream: true,
+            format: json.then(|| "json".to_string()),
+        };
+        let response = self.send(&request).await?;
+
+        // Streamed as one JSON object per line; the last carries the counts
+        let mut completion = Completion::default();
+        read_lines(response, |line| {
+            if line.trim().is_empty() {
+                return Ok(());
+            }
+            let chunk: OllamaResponse = serde_json::from_str(line).context("malformed Ollama stream line")?;
+            if let Some(error) = chunk.error {
+                return Err(anyhow!("Ollama stream error: {}", error));
+            }
+            completion.push(&chunk.response, on_text);
+            completion.input_tokens = chunk.prompt_eval_count.or(completion.input_tokens);
+            completion.output_tokens = chunk.eval_count.or(completion.output_tokens);
+            Ok(())
+        })
+        .await?;
+
+        Ok(completion)
+    }
+
     fn build_prompt(&self, context: &SessionContext) -> String {
         let mut prompt = String::new();
 
@@ -49,20 +81,22 @@ impl OllamaProvider {
             prompt.push_str("```\n\n");
         }
 
+        if let Some(scrollback) = &context.scrollback {
+            prompt.push_str("## Recent Terminal Output:\n```\n");
+            prompt.push_str(scrollback);
+            prompt.push_str("\n```\n\n");
+        }
+
         if let Some(diff) = &context.git_diff {
             if !diff.is_empty() {
                 prompt.push_str("## Git Diff:\n```diff\n");
-                // Truncate large diffs (Ollama has smaller context windows)
-                if diff.len() > 2000 {
-                    prompt.push_str(&diff[..2000]);
-                    prompt.push_str("\n... (truncated)\n");
-                } else {
-                    prompt.push_str(diff);
-                }
+                prompt.push_str(diff);
                 prompt.push_str("```\n\n");
             }
         }
 
+        prompt.push_str(&context.git_activity_prompt());
+
         if !context.active_files.is_empty() {
             prompt.push_str("## Active Files:\n");
             for file in &context.active_files {
@@ -84,6 +118,22 @@ impl OllamaProvider {
 
         prompt
     }
+
+    async fn send(&self, request: &OllamaRequest) -> Result<Response> {
+        let response = self
+            .client
+            .post(self.api_url())
+            .header("Content-Type", "application/json")
+            .json(request)
+            .send()
+            .await
+            .context("failed to send request to Ollama API")?;
+
+        if !response.status().is_success() {
+            return Err(ApiError::from_response("Ollama", response).await.into());
+        }
+        Ok(response)
+    }
 }
 
 #[derive(Serialize)]
@@ -91,26 +141,23 @@ struct OllamaRequest {
     model: String,
     prompt: String,
     stream: bool,
-    format: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<String>,
 }
 
+/// A complete response, or one line of a streamed response
 #[derive(Deserialize)]
 struct OllamaResponse {
+    #[serde(default)]
     response: String,
     #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
     eval_count: Option<u32>,
     #[serde(default)]
     prompt_eval_count: Option<u32>,
 }
 
-#[derive(Deserialize)]
-struct SummaryJson {
-    summary: String,
-    #[serde(rename = "type")]
-    entry_type: Option<String>,
-    key_files: Option<Vec<String>>,
-}
-
 #[async_trait]
 impl LLMProvider for OllamaProvider {
     async fn summarize(&self, context: &SessionContext) -> Result<SummarizationResult> {
@@ -120,23 +167,10 @@ impl LLMProvider for OllamaProvider {
             model: self.model.clone(),
             prompt,
             stream: false,
-            format: "json".to_string(),
+            format: Some("json".to_string()),
         };
 
-        let response = self
-            .client
-            .post(&self.api_url())
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await
-            .context("failed to send request to Ollama API")?;
-
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(anyhow!("Ollama API error ({}): {}", status, error_text));
-        }
+        let response = self.send(&request).await?;
 
         let api_response: OllamaResponse = response
             .json()
@@ -145,18 +179,7 @@ impl LLMProvider for OllamaProvider {
 
         let text = &api_response.response;
 
-        // Try to parse as JSON, fall back to using raw text as summary
-        let (summary, suggested_type, key_files) = match serde_json::from_str::<SummaryJson>(text) {
-            Ok(parsed) => (
-                parsed.summary,
-                parsed.entry_type,
-                parsed.key_files.unwrap_or_default(),
-            ),
-            Err(_) => {
-                // If not valid JSON, use the raw text as the summary
-                (text.clone(), None, Vec::new())
-            }
-        };
+        let (summary, suggested_type, key_files) = parse_summary(text)?;
 
         // Ollama provides eval_count (output tokens) and prompt_eval_count (input tokens)
         let tokens_used = match (api_response.prompt_eval_count, api_response.eval_count) {
@@ -171,13 +194,35 @@ impl LLMProvider for OllamaProvider {
             suggested_type,
             key_files,
             tokens_used,
+            input_tokens: api_response.prompt_eval_count,
+            output_tokens: api_response.eval_count,
         })
     }
 
+    async fn summarize_streaming(
+        &self,
+        context: &SessionContext,
+        on_text: &mut (dyn for<'s> FnMut(&'s str) + Send),
+    ) -> Result<SummarizationResult> {
+        self.stream(self.build_prompt(context), true, on_text).await?.into_summary()
+    }
+
+    async fn complete(&self, prompt: &str, on_text: &mut (dyn for<'s> FnMut(&'s str) + Send)) -> Result<Completion> {
+        self.stream(prompt.to_string(), false, on_text).await
+    }
+
     fn name(&self) -> &'static str {
         "ollama"
     }
 
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn prompt(&self, context: &SessionContext) -> String {
+        self.build_prompt(context)
+    }
+
     fn is_available(&self) -> bool {
         // Ollama is available if we have an endpoint configured
         !self.endpoint.is_empty()
@@ -187,6 +232,7 @@ impl LLMProvider for OllamaProvider {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::llm::{fit_to_budget, BudgetWeights, DEFAULT_OLLAMA_CONTEXT_TOKENS};
 
     #[test]
     fn test_build_prompt_basic() {
@@ -226,14 +272,15 @@ mod tests {
         let provider =
             OllamaProvider::new("http://localhost:11434".to_string(), "llama3.2".to_string());
 
-        // Create a diff larger than 2000 chars (smaller limit for Ollama)
-        let large_diff = "a".repeat(3000);
+        // Ollama's default budget is smaller than a 10000-char diff
+        let large_diff = "a".repeat(10_000);
         let context = SessionContext::new("test").with_git_diff(large_diff);
+        let context = fit_to_budget(context, DEFAULT_OLLAMA_CONTEXT_TOKENS, &BudgetWeights::default());
 
         let prompt = provider.build_prompt(&context);
 
         assert!(prompt.contains("(truncated)"));
-        assert!(prompt.len() < 4000); // Should be truncated
+        assert!(prompt.len() < 8000); // Should be truncated
     }
 
     #[test]
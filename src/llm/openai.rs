@@ -1,29 +1,91 @@
-use super::{LLMProvider, SessionContext, SummarizationResult};
+use super::retry::ApiError;
+use super::stream::{read_lines, sse_data};
+use super::{parse_summary, Completion, LLMProvider, SessionContext, SummarizationResult};
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
-use reqwest::Client;
+use reqwest::{Client, Response};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 const OPENAI_API_URL: &str = "https://api.openai.com/v1/chat/completions";
 
 /// OpenAI GPT provider for LLM summarization.
+///
+/// Also serves any OpenAI-compatible chat completions server (llama.cpp,
+/// vLLM, LM Studio, LiteLLM) via [`OpenAIProvider::compatible`].
 pub struct OpenAIProvider {
     client: Client,
-    api_key: String,
+    endpoint: String,
+    api_key: Option<String>,
     model: String,
     max_tokens: u32,
+    /// Request `json_object` responses (not every compatible server supports it)
+    json_mode: bool,
+    name: &'static str,
 }
 
 impl OpenAIProvider {
     pub fn new(api_key: String, model: String, max_tokens: u32) -> Self {
         Self {
             client: Client::new(),
-            api_key,
+            endpoint: OPENAI_API_URL.to_string(),
+            api_key: Some(api_key),
             model,
             max_tokens,
+            json_mode: true,
+            name: "openai",
         }
     }
 
+    /// Provider for an OpenAI-compatible server rooted at `base_url`
+    /// (e.g. `http://localhost:8080/v1`). The bearer token is optional.
+    pub fn compatible(base_url: &str, api_key: Option<String>, model: String, max_tokens: u32) -> Self {
+        Self {
+            client: Client::new(),
+            endpoint: format!("{}/chat/completions", base_url.trim_end_matches('/')),
+            api_key: api_key.filter(|key| !key.is_empty()),
+            model,
+            max_tokens,
+            json_mode: false,
+            name: "openai-compatible",
+        }
+    }
+
+    /// Send a prompt with streaming on, passing each content delta to `on_text`.
+    async fn stream(&self, prompt: String, json: bool, on_text: &mut (dyn for<'s> FnMut(&'s str) + Send)) -> Result<Completion> {
+        let request = OpenAIRequest {
+            model: self.model.clone(),
+            max_tokens: self.max_tokens,
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: prompt,
+            }],
+            response_format: json.then(|| ResponseFormat {
+                format_type: "json_object".to_string(),
+            }),
+            stream: true,
+            stream_options: Some(StreamOptions { include_usage: true }),
+        };
+        let response = self.send(&request).await?;
+
+        let mut completion = Completion::default();
+        read_lines(response, |line| {
+            let Some(data) = sse_data(line) else {
+                return Ok(());
+            };
+            let chunk: Value = serde_json::from_str(data).context("malformed OpenAI stream chunk")?;
+            completion.push(chunk["choices"][0]["delta"]["content"].as_str().unwrap_or_default(), on_text);
+            if let Some(usage) = chunk.get("usage").filter(|u| !u.is_null()) {
+                completion.input_tokens = usage["prompt_tokens"].as_u64().map(|n| n as u32);
+                completion.output_tokens = usage["completion_tokens"].as_u64().map(|n| n as u32);
+            }
+            Ok(())
+        })
+        .await?;
+
+        Ok(completion)
+    }
+
     fn build_prompt(&self, context: &SessionContext) -> String {
         let mut prompt = String::new();
 
@@ -49,20 +111,22 @@ impl OpenAIProvider {
             prompt.push_str("```\n\n");
         }
 
+        if let Some(scrollback) = &context.scrollback {
+            prompt.push_str("## Recent Terminal Output:\n```\n");
+            prompt.push_str(scrollback);
+            prompt.push_str("\n```\n\n");
+        }
+
         if let Some(diff) = &context.git_diff {
             if !diff.is_empty() {
                 prompt.push_str("## Git Diff:\n```diff\n");
-                // Truncate large diffs
-                if diff.len() > 4000 {
-                    prompt.push_str(&diff[..4000]);
-                    prompt.push_str("\n... (truncated)\n");
-                } else {
-                    prompt.push_str(diff);
-                }
+                prompt.push_str(diff);
                 prompt.push_str("```\n\n");
             }
         }
 
+        prompt.push_str(&context.git_activity_prompt());
+
         if !context.active_files.is_empty() {
             prompt.push_str("## Active Files:\n");
             for file in &context.active_files {
@@ -84,6 +148,27 @@ impl OpenAIProvider {
 
         prompt
     }
+
+    async fn send(&self, request: &OpenAIRequest) -> Result<Response> {
+        let mut builder = self
+            .client
+            .post(&self.endpoint)
+            .header("Content-Type", "application/json");
+        if let Some(key) = &self.api_key {
+            builder = builder.header("Authorization", format!("Bearer {}", key));
+        }
+
+        let response = builder
+            .json(request)
+            .send()
+            .await
+            .with_context(|| format!("failed to send request to {}", self.endpoint))?;
+
+        if !response.status().is_success() {
+            return Err(ApiError::from_response("OpenAI", response).await.into());
+        }
+        Ok(response)
+    }
 }
 
 #[derive(Serialize)]
@@ -91,7 +176,18 @@ struct OpenAIRequest {
     model: String,
     max_tokens: u32,
     messages: Vec<Message>,
-    response_format: ResponseFormat,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+}
+
+#[derive(Serialize)]
+struct StreamOptions {
+    /// Ask for a final chunk carrying token usage
+    include_usage: bool,
 }
 
 #[derive(Serialize)]
@@ -128,14 +224,6 @@ struct Usage {
     completion_tokens: u32,
 }
 
-#[derive(Deserialize)]
-struct SummaryJson {
-    summary: String,
-    #[serde(rename = "type")]
-    entry_type: Option<String>,
-    key_files: Option<Vec<String>>,
-}
-
 #[async_trait]
 impl LLMProvider for OpenAIProvider {
     async fn summarize(&self, context: &SessionContext) -> Result<SummarizationResult> {
@@ -148,26 +236,14 @@ impl LLMProvider for OpenAIProvider {
                 role: "user".to_string(),
                 content: prompt,
             }],
-            response_format: ResponseFormat {
+            response_format: self.json_mode.then(|| ResponseFormat {
                 format_type: "json_object".to_string(),
-            },
+            }),
+            stream: false,
+            stream_options: None,
         };
 
-        let response = self
-            .client
-            .post(OPENAI_API_URL)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await
-            .context("failed to send request to OpenAI API")?;
-
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(anyhow!("OpenAI API error ({}): {}", status, error_text));
-        }
+        let response = self.send(&request).await?;
 
         let api_response: OpenAIResponse = response
             .json()
@@ -180,43 +256,57 @@ impl LLMProvider for OpenAIProvider {
             .and_then(|c| c.message.content.as_ref())
             .ok_or_else(|| anyhow!("no content in OpenAI response"))?;
 
-        // Try to parse as JSON, fall back to using raw text as summary
-        let (summary, suggested_type, key_files) = match serde_json::from_str::<SummaryJson>(text) {
-            Ok(parsed) => (
-                parsed.summary,
-                parsed.entry_type,
-                parsed.key_files.unwrap_or_default(),
-            ),
-            Err(_) => {
-                // If not valid JSON, use the raw text as the summary
-                (text.clone(), None, Vec::new())
-            }
-        };
+        let (summary, suggested_type, key_files) = parse_summary(text)?;
 
-        let tokens_used = api_response
-            .usage
-            .map(|u| u.prompt_tokens + u.completion_tokens);
+        let usage = api_response.usage;
 
         Ok(SummarizationResult {
             summary,
             suggested_type,
             key_files,
-            tokens_used,
+            tokens_used: usage.as_ref().map(|u| u.prompt_tokens + u.completion_tokens),
+            input_tokens: usage.as_ref().map(|u| u.prompt_tokens),
+            output_tokens: usage.as_ref().map(|u| u.completion_tokens),
         })
     }
 
+    async fn summarize_streaming(
+        &self,
+        context: &SessionContext,
+        on_text: &mut (dyn for<'s> FnMut(&'s str) + Send),
+    ) -> Result<SummarizationResult> {
+        self.stream(self.build_prompt(context), self.json_mode, on_text).await?.into_summary()
+    }
+
+    async fn complete(&self, prompt: &str, on_text: &mut (dyn for<'s> FnMut(&'s str) + Send)) -> Result<Completion> {
+        self.stream(prompt.to_string(), false, on_text).await
+    }
+
     fn name(&self) -> &'static str {
-        "openai"
+        self.name
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn prompt(&self, context: &SessionContext) -> String {
+        self.build_prompt(context)
     }
 
     fn is_available(&self) -> bool {
-        !self.api_key.is_empty()
+        match self.name {
+            // Local servers commonly run without auth
+            "openai-compatible" => !self.endpoint.is_empty(),
+            _ => self.api_key.as_deref().is_some_and(|key| !key.is_empty()),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::llm::{fit_to_budget, BudgetWeights, DEFAULT_CONTEXT_TOKENS};
 
     #[test]
     fn test_build_prompt_basic() {
@@ -253,14 +343,15 @@ mod tests {
     fn test_build_prompt_truncates_large_diff() {
         let provider = OpenAIProvider::new("test-key".to_string(), "gpt-4o-mini".to_string(), 1024);
 
-        // Create a diff larger than 4000 chars
-        let large_diff = "a".repeat(5000);
+        // Create a diff larger than the default budget
+        let large_diff = "a".repeat(20_000);
         let context = SessionContext::new("test").with_git_diff(large_diff);
+        let context = fit_to_budget(context, DEFAULT_CONTEXT_TOKENS, &BudgetWeights::default());
 
         let prompt = provider.build_prompt(&context);
 
         assert!(prompt.contains("(truncated)"));
-        assert!(prompt.len() < 6000); // Should be truncated
+        assert!(prompt.len() < 18_000); // Should be truncated
     }
 
     #[test]
@@ -272,4 +363,15 @@ mod tests {
         let empty_provider = OpenAIProvider::new(String::new(), "gpt-4o-mini".to_string(), 1024);
         assert!(!empty_provider.is_available());
     }
+
+    #[test]
+    fn test_compatible_endpoint_and_optional_auth() {
+        let provider = OpenAIProvider::compatible("http://localhost:8080/v1/", None, "local".to_string(), 512);
+
+        assert_eq!(provider.endpoint, "http://localhost:8080/v1/chat/completions");
+        assert_eq!(provider.name(), "openai-compatible");
+        assert!(provider.api_key.is_none());
+        assert!(!provider.json_mode);
+        assert!(provider.is_available());
+    }
 }
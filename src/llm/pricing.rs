@@ -0,0 +1,69 @@
+//! Per-model price table for estimating LLM spend.
+//!
+//! Prices are list prices in USD per million tokens and only approximate
+//! what a provider bills. Models are matched by the longest known prefix,
+//! so dated snapshots (`claude-sonnet-4-20250514`) share their family's price.
+
+/// (model prefix, input $/M tokens, output $/M tokens)
+const PRICES: &[(&str, f64, f64)] = &[
+    // Anthropic
+    ("claude-opus-4", 15.00, 75.00),
+    ("claude-sonnet-4", 3.00, 15.00),
+    ("claude-3-7-sonnet", 3.00, 15.00),
+    ("claude-3-5-sonnet", 3.00, 15.00),
+    ("claude-3-5-haiku", 0.80, 4.00),
+    ("claude-3-haiku", 0.25, 1.25),
+    // OpenAI
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4o", 2.50, 10.00),
+    ("gpt-4.1-nano", 0.10, 0.40),
+    ("gpt-4.1-mini", 0.40, 1.60),
+    ("gpt-4.1", 2.00, 8.00),
+    ("o3-mini", 1.10, 4.40),
+    // Google
+    ("gemini-2.5-pro", 1.25, 10.00),
+    ("gemini-2.5-flash", 0.30, 2.50),
+    ("gemini-2.0-flash", 0.10, 0.40),
+    ("gemini-1.5-pro", 1.25, 5.00),
+    ("gemini-1.5-flash", 0.075, 0.30),
+];
+
+/// Input and output price per million tokens for `model`, if known.
+pub fn price_per_million(model: &str) -> Option<(f64, f64)> {
+    PRICES
+        .iter()
+        .filter(|(prefix, _, _)| model.starts_with(prefix))
+        .max_by_key(|(prefix, _, _)| prefix.len())
+        .map(|&(_, input, output)| (input, output))
+}
+
+/// Estimated cost in USD of one request, or `None` for an unknown model.
+/// Requests to local Ollama models are free.
+pub fn estimate_cost(provider: &str, model: &str, input_tokens: u32, output_tokens: u32) -> Option<f64> {
+    if provider == "ollama" {
+        return Some(0.0);
+    }
+    let (input, output) = price_per_million(model)?;
+    Some((input * input_tokens as f64 + output * output_tokens as f64) / 1_000_000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_longest_prefix_wins() {
+        assert_eq!(price_per_million("gpt-4o-mini-2024-07-18"), Some((0.15, 0.60)));
+        assert_eq!(price_per_million("gpt-4o"), Some((2.50, 10.00)));
+        assert_eq!(price_per_million("claude-sonnet-4-20250514"), Some((3.00, 15.00)));
+        assert_eq!(price_per_million("mystery-model"), None);
+    }
+
+    #[test]
+    fn test_estimate_cost() {
+        let cost = estimate_cost("anthropic", "claude-sonnet-4-20250514", 1_000_000, 100_000).unwrap();
+        assert!((cost - 4.5).abs() < 1e-9);
+        assert_eq!(estimate_cost("ollama", "llama3.2", 5000, 500), Some(0.0));
+        assert_eq!(estimate_cost("openai-compatible", "local-model", 5000, 500), None);
+    }
+}
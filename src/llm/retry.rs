@@ -0,0 +1,374 @@
+//! Retry layer for transient LLM API failures.
+//!
+//! Providers report non-success responses as [`ApiError`], which carries the
+//! HTTP status and any `Retry-After` hint. [`RetryingProvider`] retries 429s,
+//! 5xx responses and connection failures with jittered exponential backoff,
+//! so only a request that still fails after the last attempt reaches the
+//! caller (and counts against the circuit breaker).
+
+use super::{Completion, LLMProvider, SessionContext, SummarizationResult, DEFAULT_MAX_RETRIES};
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::{header::RETRY_AFTER, Response, StatusCode};
+use std::fmt;
+use std::time::Duration;
+
+/// Backoff settings for retrying transient failures.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt (0 disables retrying)
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on each subsequent retry
+    pub base_delay: Duration,
+    /// Upper bound on any single delay. A `Retry-After` longer than this
+    /// is not waited out; the error is returned instead.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_MAX_RETRIES,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(8),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Jittered exponential delay before retry number `attempt` (0-based):
+    /// a random point in the upper half of `base * 2^attempt`, capped.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let half = ceiling / 2;
+        let jitter = (uuid::Uuid::new_v4().as_u128() % (half.as_millis() + 1)) as u64;
+        half + Duration::from_millis(jitter)
+    }
+
+    /// How long to wait before retrying after `error`, or `None` to give up.
+    fn delay_for(&self, error: &anyhow::Error, attempt: u32) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+
+        if let Some(api_error) = error.downcast_ref::<ApiError>() {
+            if !api_error.is_transient() {
+                return None;
+            }
+            return match api_error.retry_after {
+                Some(wait) if wait > self.max_delay => None,
+                Some(wait) => Some(wait),
+                None => Some(self.backoff(attempt)),
+            };
+        }
+
+        let transport = error
+            .chain()
+            .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+            .any(|e| e.is_connect() || e.is_timeout());
+        transport.then(|| self.backoff(attempt))
+    }
+}
+
+/// A non-success HTTP response from an LLM API.
+#[derive(Debug)]
+pub struct ApiError {
+    /// Display name of the provider, e.g. "Anthropic"
+    pub provider: &'static str,
+    pub status: StatusCode,
+    /// Parsed `Retry-After` header, if the server sent one
+    pub retry_after: Option<Duration>,
+    pub body: String,
+}
+
+impl ApiError {
+    /// Build from a failed response, consuming its body.
+    pub async fn from_response(provider: &'static str, response: Response) -> Self {
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_retry_after);
+        let body = response.text().await.unwrap_or_default();
+        Self { provider, status, retry_after, body }
+    }
+
+    /// Rate limiting and server errors are worth retrying; other 4xx are not.
+    pub fn is_transient(&self) -> bool {
+        self.status == StatusCode::TOO_MANY_REQUESTS || self.status.is_server_error()
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} API error ({}): {}", self.provider, self.status, self.body)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// Parse a `Retry-After` value: delay seconds or an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = at.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Note how many attempts were made on an error that is being given up on.
+fn gave_up(error: anyhow::Error, retries: u32) -> anyhow::Error {
+    if retries > 0 {
+        error.context(format!("gave up after {} attempts", retries + 1))
+    } else {
+        error
+    }
+}
+
+/// Wraps a provider, retrying transient failures per a [`RetryPolicy`].
+pub struct RetryingProvider {
+    inner: Box<dyn LLMProvider>,
+    policy: RetryPolicy,
+}
+
+impl RetryingProvider {
+    pub fn new(inner: Box<dyn LLMProvider>, policy: RetryPolicy) -> Self {
+        Self { inner, policy }
+    }
+}
+
+#[async_trait]
+impl LLMProvider for RetryingProvider {
+    async fn summarize(&self, context: &SessionContext) -> Result<SummarizationResult> {
+        let mut attempt = 0;
+        loop {
+            match self.inner.summarize(context).await {
+                Ok(result) => return Ok(result),
+                Err(e) => match self.policy.delay_for(&e, attempt) {
+                    Some(delay) => {
+                        tokio::time::sleep(delay).await;
+                        attempt += 1;
+                    }
+                    None => return Err(gave_up(e, attempt)),
+                },
+            }
+        }
+    }
+
+    /// Like `complete`, retries only failures before any text streamed.
+    async fn summarize_streaming(
+        &self,
+        context: &SessionContext,
+        on_text: &mut (dyn for<'s> FnMut(&'s str) + Send),
+    ) -> Result<SummarizationResult> {
+        let mut attempt = 0;
+        loop {
+            let mut streamed = false;
+            let result = {
+                let mut tracking = |text: &str| {
+                    streamed = true;
+                    on_text(text);
+                };
+                self.inner.summarize_streaming(context, &mut tracking).await
+            };
+            match result {
+                Ok(summary) => return Ok(summary),
+                Err(e) if streamed => return Err(e),
+                Err(e) => match self.policy.delay_for(&e, attempt) {
+                    Some(delay) => {
+                        tokio::time::sleep(delay).await;
+                        attempt += 1;
+                    }
+                    None => return Err(gave_up(e, attempt)),
+                },
+            }
+        }
+    }
+
+    /// Retries only failures before any text streamed; once part of the
+    /// answer has been shown, a retry would repeat it.
+    async fn complete(&self, prompt: &str, on_text: &mut (dyn for<'s> FnMut(&'s str) + Send)) -> Result<Completion> {
+        let mut attempt = 0;
+        loop {
+            let mut streamed = false;
+            let result = {
+                let mut tracking = |text: &str| {
+                    streamed = true;
+                    on_text(text);
+                };
+                self.inner.complete(prompt, &mut tracking).await
+            };
+            match result {
+                Ok(completion) => return Ok(completion),
+                Err(e) if streamed => return Err(e),
+                Err(e) => match self.policy.delay_for(&e, attempt) {
+                    Some(delay) => {
+                        tokio::time::sleep(delay).await;
+                        attempt += 1;
+                    }
+                    None => return Err(gave_up(e, attempt)),
+                },
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn prompt(&self, context: &SessionContext) -> String {
+        self.inner.prompt(context)
+    }
+
+    fn is_available(&self) -> bool {
+        self.inner.is_available()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn api_error(status: u16, retry_after: Option<Duration>) -> anyhow::Error {
+        ApiError {
+            provider: "Test",
+            status: StatusCode::from_u16(status).unwrap(),
+            retry_after,
+            body: String::new(),
+        }
+        .into()
+    }
+
+    /// Fails with the given statuses in order, then succeeds.
+    struct Flaky {
+        failures: Vec<u16>,
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl LLMProvider for Flaky {
+        async fn summarize(&self, _context: &SessionContext) -> Result<SummarizationResult> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst) as usize;
+            match self.failures.get(call) {
+                Some(&status) => Err(api_error(status, Some(Duration::ZERO))),
+                None => Ok(SummarizationResult {
+                    summary: "done".to_string(),
+                    suggested_type: None,
+                    key_files: Vec::new(),
+                    tokens_used: None,
+                    input_tokens: None,
+                    output_tokens: None,
+                }),
+            }
+        }
+
+        async fn complete(&self, _prompt: &str, on_text: &mut (dyn for<'s> FnMut(&'s str) + Send)) -> Result<Completion> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst) as usize;
+            match self.failures.get(call) {
+                Some(&status) => Err(api_error(status, Some(Duration::ZERO))),
+                None => {
+                    on_text("done");
+                    Ok(Completion { text: "done".to_string(), ..Default::default() })
+                }
+            }
+        }
+
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+
+        fn model(&self) -> &str {
+            "flaky-1"
+        }
+
+        fn prompt(&self, _context: &SessionContext) -> String {
+            String::new()
+        }
+
+        fn is_available(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("12"), Some(Duration::from_secs(12)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn test_delay_for_classifies_errors() {
+        let policy = RetryPolicy::default();
+
+        assert!(policy.delay_for(&api_error(429, None), 0).is_some());
+        assert!(policy.delay_for(&api_error(503, None), 0).is_some());
+        assert!(policy.delay_for(&api_error(401, None), 0).is_none());
+        assert!(policy.delay_for(&api_error(503, None), policy.max_retries).is_none());
+        assert!(policy.delay_for(&anyhow::anyhow!("bad json"), 0).is_none());
+
+        let hinted = api_error(429, Some(Duration::from_secs(2)));
+        assert_eq!(policy.delay_for(&hinted, 0), Some(Duration::from_secs(2)));
+        let too_long = api_error(429, Some(Duration::from_secs(600)));
+        assert!(policy.delay_for(&too_long, 0).is_none());
+    }
+
+    #[test]
+    fn test_backoff_is_bounded() {
+        let policy = RetryPolicy::default();
+        for attempt in 0..10 {
+            let delay = policy.backoff(attempt);
+            assert!(delay <= policy.max_delay);
+        }
+        assert!(policy.backoff(0) >= policy.base_delay / 2);
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_success() {
+        let provider = RetryingProvider::new(
+            Box::new(Flaky { failures: vec![429, 502], calls: AtomicU32::new(0) }),
+            RetryPolicy::default(),
+        );
+        let result = provider.summarize(&SessionContext::new("p")).await.unwrap();
+        assert_eq!(result.summary, "done");
+    }
+
+    #[tokio::test]
+    async fn test_complete_retries_before_streaming() {
+        let provider = RetryingProvider::new(
+            Box::new(Flaky { failures: vec![503], calls: AtomicU32::new(0) }),
+            RetryPolicy::default(),
+        );
+        let mut streamed = String::new();
+        let completion = provider.complete("q", &mut |text| streamed.push_str(text)).await.unwrap();
+        assert_eq!(completion.text, "done");
+        assert_eq!(streamed, "done");
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries() {
+        let provider = RetryingProvider::new(
+            Box::new(Flaky { failures: vec![500; 5], calls: AtomicU32::new(0) }),
+            RetryPolicy::default().with_max_retries(2),
+        );
+        let err = provider.summarize(&SessionContext::new("p")).await.unwrap_err();
+        assert!(err.to_string().contains("gave up after 3 attempts"));
+        assert!(err.downcast_ref::<ApiError>().is_some());
+    }
+}
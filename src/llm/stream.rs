@@ -0,0 +1,82 @@
+//! Incremental reading of streamed LLM responses.
+//!
+//! Anthropic, OpenAI and Gemini stream server-sent events; Ollama streams
+//! newline-delimited JSON. Both are read line by line as chunks arrive.
+
+use anyhow::{Context, Result};
+use reqwest::Response;
+
+/// Splits a byte stream into lines, holding back a trailing partial line
+/// (and any UTF-8 sequence split across chunks) until it is complete.
+#[derive(Default)]
+struct LineBuffer {
+    pending: Vec<u8>,
+}
+
+impl LineBuffer {
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(chunk);
+        let mut lines = Vec::new();
+        while let Some(end) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            lines.push(String::from_utf8_lossy(&line).trim_end_matches(['\r', '\n']).to_string());
+        }
+        lines
+    }
+
+    fn finish(self) -> Option<String> {
+        let rest = String::from_utf8_lossy(&self.pending).trim_end().to_string();
+        (!rest.is_empty()).then_some(rest)
+    }
+}
+
+/// Read a streaming response body, calling `on_line` with each complete line.
+pub(super) async fn read_lines(mut response: Response, mut on_line: impl FnMut(&str) -> Result<()>) -> Result<()> {
+    let mut buffer = LineBuffer::default();
+    while let Some(chunk) = response.chunk().await.context("failed to read streamed response")? {
+        for line in buffer.push(&chunk) {
+            on_line(&line)?;
+        }
+    }
+    match buffer.finish() {
+        Some(line) => on_line(&line),
+        None => Ok(()),
+    }
+}
+
+/// Payload of an SSE `data:` line. Event names, comments, keep-alives and
+/// OpenAI's `[DONE]` sentinel yield `None`.
+pub(super) fn sse_data(line: &str) -> Option<&str> {
+    let data = line.strip_prefix("data:")?.trim_start();
+    (!data.is_empty() && data != "[DONE]").then_some(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_buffer_joins_split_chunks() {
+        let mut buffer = LineBuffer::default();
+        assert!(buffer.push(b"data: {\"a\"").is_empty());
+        assert_eq!(buffer.push(b": 1}\r\n\ndata: x"), vec!["data: {\"a\": 1}", ""]);
+
+        // A multi-byte character split across chunks survives intact
+        let bytes = "é\n".as_bytes();
+        assert!(buffer.push(&bytes[..1]).is_empty());
+        assert_eq!(buffer.push(&bytes[1..]), vec!["data: xé"]);
+
+        buffer.push(b"tail");
+        assert_eq!(buffer.finish(), Some("tail".to_string()));
+    }
+
+    #[test]
+    fn test_sse_data() {
+        assert_eq!(sse_data("data: {\"x\": 1}"), Some("{\"x\": 1}"));
+        assert_eq!(sse_data("data:{}"), Some("{}"));
+        assert_eq!(sse_data("data: [DONE]"), None);
+        assert_eq!(sse_data("event: message_start"), None);
+        assert_eq!(sse_data(": keep-alive"), None);
+        assert_eq!(sse_data(""), None);
+    }
+}
@@ -0,0 +1,272 @@
+//! Validation and repair of structured summarization responses.
+//!
+//! Summarization prompts ask for a JSON object with `summary`, `type` and
+//! `key_files`. A response that does not match is reported as
+//! [`InvalidSummary`] instead of being logged as-is; [`summarize_checked`]
+//! then asks the model once to fix its output before giving up.
+
+use super::{Completion, LLMProvider, SessionContext, SummarizationResult};
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::fmt;
+
+/// Entry types a summary may suggest.
+const ENTRY_TYPES: &[&str] = &["checkpoint", "milestone", "exploration"];
+
+/// Longest part of a bad response quoted back in errors.
+const EXCERPT_CHARS: usize = 200;
+
+/// A summarization response that does not match the requested schema.
+#[derive(Debug, Clone)]
+pub struct InvalidSummary {
+    /// What is wrong with the response
+    pub problem: String,
+    /// The response as received
+    pub raw: String,
+    /// Prompt tokens spent on the response, if reported
+    pub input_tokens: Option<u32>,
+    /// Completion tokens spent on the response, if reported
+    pub output_tokens: Option<u32>,
+}
+
+impl fmt::Display for InvalidSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "malformed summary JSON: {}", self.problem)
+    }
+}
+
+impl std::error::Error for InvalidSummary {}
+
+/// Check a summarization response against the schema and split it into
+/// (summary, suggested type, key files).
+///
+/// Markdown code fences and prose around the JSON object are tolerated,
+/// since models add them despite instructions.
+pub(super) fn parse_summary(text: &str) -> Result<(String, Option<String>, Vec<String>), InvalidSummary> {
+    let invalid = |problem: String| InvalidSummary {
+        problem,
+        raw: text.to_string(),
+        input_tokens: None,
+        output_tokens: None,
+    };
+
+    let json = extract_object(text).ok_or_else(|| invalid("no JSON object in response".to_string()))?;
+    let value: Value = serde_json::from_str(json).map_err(|e| invalid(format!("invalid JSON ({})", e)))?;
+
+    let summary = match value.get("summary") {
+        Some(Value::String(summary)) if !summary.trim().is_empty() => summary.trim().to_string(),
+        Some(Value::String(_)) => return Err(invalid("\"summary\" is empty".to_string())),
+        Some(_) => return Err(invalid("\"summary\" is not a string".to_string())),
+        None => return Err(invalid("missing \"summary\"".to_string())),
+    };
+
+    let entry_type = match value.get("type") {
+        None | Some(Value::Null) => None,
+        Some(Value::String(t)) if ENTRY_TYPES.contains(&t.trim().to_lowercase().as_str()) => {
+            Some(t.trim().to_lowercase())
+        }
+        Some(other) => {
+            return Err(invalid(format!(
+                "\"type\" must be one of {}, got {}",
+                ENTRY_TYPES.join(", "),
+                other
+            )))
+        }
+    };
+
+    let key_files = match value.get("key_files") {
+        None | Some(Value::Null) => Vec::new(),
+        Some(Value::Array(files)) => files
+            .iter()
+            .map(|f| f.as_str().map(str::to_string))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| invalid("\"key_files\" must contain only strings".to_string()))?,
+        Some(_) => return Err(invalid("\"key_files\" is not an array".to_string())),
+    };
+
+    Ok((summary, entry_type, key_files))
+}
+
+/// The outermost `{...}` in `text`, if any.
+fn extract_object(text: &str) -> Option<&str> {
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    (start < end).then(|| &text[start..=end])
+}
+
+/// Follow-up prompt asking the model to correct a malformed response.
+fn repair_prompt(invalid: &InvalidSummary) -> String {
+    format!(
+        "Your previous response could not be used: {}.\n\n\
+        Previous response:\n{}\n\n\
+        Reply with only the corrected JSON object, no markdown or commentary, in this exact format:\n\
+        {{\"summary\": \"...\", \"type\": \"checkpoint|milestone|exploration\", \"key_files\": [\"file1.rs\"]}}",
+        invalid.problem, invalid.raw
+    )
+}
+
+fn add_tokens(a: Option<u32>, b: Option<u32>) -> Option<u32> {
+    match (a, b) {
+        (None, None) => None,
+        (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+    }
+}
+
+/// Summarize, streaming the response to `on_text`, and make sure the result
+/// matches the schema.
+///
+/// A malformed response gets exactly one "fix your JSON" follow-up call.
+/// If that is malformed too, the error says so rather than returning junk.
+/// Token counts include both calls.
+pub async fn summarize_checked(
+    provider: &dyn LLMProvider,
+    context: &SessionContext,
+    on_text: &mut (dyn for<'s> FnMut(&'s str) + Send),
+) -> Result<SummarizationResult> {
+    let first = match provider.summarize_streaming(context, on_text).await {
+        Ok(result) => return Ok(result),
+        Err(e) => match e.downcast::<InvalidSummary>() {
+            Ok(invalid) => invalid,
+            Err(e) => return Err(e),
+        },
+    };
+
+    let repaired: Completion = provider.complete(&repair_prompt(&first), on_text).await?;
+    let (input_tokens, output_tokens) = (
+        add_tokens(first.input_tokens, repaired.input_tokens),
+        add_tokens(first.output_tokens, repaired.output_tokens),
+    );
+
+    match parse_summary(&repaired.text) {
+        Ok((summary, suggested_type, key_files)) => Ok(SummarizationResult {
+            summary,
+            suggested_type,
+            key_files,
+            tokens_used: add_tokens(input_tokens, output_tokens),
+            input_tokens,
+            output_tokens,
+        }),
+        Err(second) => Err(anyhow!(
+            "{} responded with malformed summary JSON twice ({}; after repair: {}). Nothing was logged.\n\
+            Response: {}",
+            provider.name(),
+            first.problem,
+            second.problem,
+            excerpt(&second.raw)
+        )),
+    }
+}
+
+fn excerpt(text: &str) -> String {
+    let text = text.trim();
+    match text.char_indices().nth(EXCERPT_CHARS) {
+        Some((cut, _)) => format!("{}…", &text[..cut]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_parse_summary_validates_schema() {
+        let fenced = "Here you go:\n```json\n{\"summary\": \"Fixed login\", \"type\": \"Milestone\", \"key_files\": [\"auth.rs\"]}\n```";
+        assert_eq!(
+            parse_summary(fenced).unwrap(),
+            ("Fixed login".to_string(), Some("milestone".to_string()), vec!["auth.rs".to_string()])
+        );
+
+        for (bad, problem) in [
+            ("Just text", "no JSON object"),
+            ("{\"summary\": \"cut off", "no JSON object"),
+            ("{\"summary\": }", "invalid JSON"),
+            ("{\"type\": \"checkpoint\"}", "missing \"summary\""),
+            ("{\"summary\": \"  \"}", "\"summary\" is empty"),
+            ("{\"summary\": \"x\", \"type\": \"bugfix\"}", "\"type\" must be one of"),
+            ("{\"summary\": \"x\", \"key_files\": [1]}", "only strings"),
+        ] {
+            let err = parse_summary(bad).unwrap_err();
+            assert!(err.problem.contains(problem), "{:?}: {}", bad, err.problem);
+            assert_eq!(err.raw, bad);
+        }
+    }
+
+    /// Returns each scripted response in turn, for both summarize and complete.
+    struct Scripted {
+        responses: Vec<&'static str>,
+        calls: AtomicUsize,
+    }
+
+    impl Scripted {
+        fn next(&self) -> Completion {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            Completion {
+                text: self.responses[call].to_string(),
+                input_tokens: Some(10),
+                output_tokens: Some(5),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl LLMProvider for Scripted {
+        async fn summarize(&self, _context: &SessionContext) -> Result<SummarizationResult> {
+            self.next().into_summary()
+        }
+
+        async fn complete(&self, _prompt: &str, _on_text: &mut (dyn for<'s> FnMut(&'s str) + Send)) -> Result<Completion> {
+            Ok(self.next())
+        }
+
+        fn name(&self) -> &'static str {
+            "scripted"
+        }
+
+        fn model(&self) -> &str {
+            "test"
+        }
+
+        fn prompt(&self, _context: &SessionContext) -> String {
+            String::new()
+        }
+
+        fn is_available(&self) -> bool {
+            true
+        }
+    }
+
+    async fn run(responses: Vec<&'static str>) -> (Result<SummarizationResult>, usize) {
+        let provider = Scripted { responses, calls: AtomicUsize::new(0) };
+        let result = summarize_checked(&provider, &SessionContext::new("p"), &mut |_: &str| {}).await;
+        (result, provider.calls.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test]
+    async fn test_valid_response_needs_no_repair() {
+        let (result, calls) = run(vec!["{\"summary\": \"Done\"}"]).await;
+        assert_eq!(result.unwrap().summary, "Done");
+        assert_eq!(calls, 1);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_response_is_repaired_once() {
+        let (result, calls) = run(vec!["Sure! I fixed the login bug.", "{\"summary\": \"Fixed the login bug\"}"]).await;
+        let result = result.unwrap();
+        assert_eq!(result.summary, "Fixed the login bug");
+        assert_eq!(result.input_tokens, Some(20));
+        assert_eq!(result.tokens_used, Some(30));
+        assert_eq!(calls, 2);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_failed_repair() {
+        let (result, calls) = run(vec!["nope", "still nope", "{\"summary\": \"unused\"}"]).await;
+        let message = result.unwrap_err().to_string();
+        assert!(message.contains("malformed summary JSON twice"), "{}", message);
+        assert!(message.contains("still nope"));
+        assert_eq!(calls, 2);
+    }
+}
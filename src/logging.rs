@@ -0,0 +1,58 @@
+//! Diagnostics via `tracing`.
+//!
+//! Warnings go to stderr by default; `-v` adds info events and `-vv` debug
+//! events such as every Zellij action and AMQP connection. `--log-file` (or
+//! `log.file`) appends them, timestamped, to a file instead.
+
+use anyhow::{Context, Result};
+use std::fs::{self, OpenOptions};
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::Level;
+
+/// The most verbose level shown for a `-v` count.
+pub fn level(verbose: u8) -> Level {
+    match verbose {
+        0 => Level::WARN,
+        1 => Level::INFO,
+        2 => Level::DEBUG,
+        _ => Level::TRACE,
+    }
+}
+
+/// Install the global subscriber. Call once, before anything logs.
+pub fn init(verbose: u8, file: Option<&Path>) -> Result<()> {
+    let builder = tracing_subscriber::fmt().with_max_level(level(verbose));
+    match file {
+        Some(path) => {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).with_context(|| format!("failed to create log directory {}", dir.display()))?;
+            }
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("failed to open log file {}", path.display()))?;
+            builder.with_ansi(false).with_writer(Mutex::new(file)).init();
+        }
+        None => {
+            let color = crate::output::theme::current().color_enabled(std::io::stderr().is_terminal());
+            builder.without_time().with_target(false).with_ansi(color).with_writer(std::io::stderr).init();
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_per_verbosity() {
+        assert_eq!(level(0), Level::WARN);
+        assert_eq!(level(1), Level::INFO);
+        assert_eq!(level(2), Level::DEBUG);
+        assert_eq!(level(5), Level::TRACE);
+    }
+}
@@ -1,33 +1,40 @@
-mod bloodbank;
+mod capabilities;
 mod cli;
-mod config;
-mod context;
-mod filter;
-mod llm;
-mod orchestrator;
-mod output;
-mod restore;
-mod snapshot;
-mod state;
-mod types;
-mod zellij;
+mod logging;
+mod plumbing;
+mod schema;
+mod serve;
 
 use anyhow::{anyhow, Result};
 use bloodbank::EventPublisher;
 use clap::{CommandFactory, FromArgMatches};
-use cli::{collect_meta, command_name, Cli, Command, ConfigAction, OutputFormat, PaneAction, TabAction};
+use cli::{collect_meta, command_name, ArtifactsAction, Cli, Command, ConfigAction, EventsAction, ExportAction, FilterAction, GoalAction, HookAction, KeybindAction, LlmAction, OnConflict, OutputFormat, PaneAction, PlumbingAction, ReportAction, StateAction, SyncAction, TabAction, TemplateAction, TrashAction};
 use config::Config;
+use error::PerthError;
 use orchestrator::Orchestrator;
+use output::messages::{self, Icon};
+use output::progress::Spinner;
 use output::OutputFormatter;
 use state::StateManager;
-use types::IntentEntry;
+use types::{HistoryOutput, IntentEntry};
 use zellij::ZellijDriver;
+use zellij_driver::multiplexer::MultiplexerDriver;
+use zellij_driver::{
+    activity, artifacts, ask, bloodbank, config, crypto, describe, error, filter, handoff, hook, jump, keybind, llm, metrics, obsidian,
+    orchestrator, output, quicklog, report, retention, state, telemetry, template, tracker, types, workspace, zellij,
+};
+#[cfg(feature = "snapshots")]
+use zellij_driver::{context, restore, snapshot};
+use serde_json::json;
+use std::io::Write;
 
 #[tokio::main]
 async fn main() {
-    if let Err(err) = run().await {
+    let result = run().await;
+    telemetry::flush().await;
+    if let Err(err) = result {
         eprintln!("{err}");
-        std::process::exit(1);
+        std::process::exit(error::exit_code(&err));
     }
 }
 
@@ -37,117 +44,464 @@ async fn run() -> Result<()> {
     let command = Cli::command().name(name_static);
     let matches = command.get_matches();
     let cli = Cli::from_arg_matches(&matches)?;
+    metrics::inc("perth_commands_total", &[("command", matches.subcommand_name().unwrap_or("none"))]);
     let config = Config::load()?;
-    let state = StateManager::new(&config.redis_url).await?;
+    output::theme::init(output::theme::Theme::from_config(&config.display.theme, cli.no_color, cli.ascii)?);
+    logging::init(cli.verbose, cli.log_file.as_deref().or(config.log.file.as_deref()))?;
+    if let Some(endpoint) = &config.observability.otlp_endpoint {
+        telemetry::init(endpoint);
+    }
+    let _trace = telemetry::root_span(format!("zdrive {}", matches.subcommand_name().unwrap_or("none")));
+
+    // JSON documents are named after the subcommand, e.g. "pane history"
+    let command_path = subcommand_path(&matches);
+    let format = cli.format;
+    if format.is_json() && !has_json_output(&cli.command) {
+        return Err(anyhow!("'{}' has no JSON output; run it without --format", command_path));
+    }
+
+    // Filter checks are purely local, so they work without Redis
+    if let Command::Filter(args) = &cli.command {
+        return run_filter(&args.action, format, &command_path, &config.filter);
+    }
+    // Schemas are generated from the types alone
+    if let Command::Schema { kind } = &cli.command {
+        let schema = match kind {
+            Some(kind) => schema::generate(*kind)?,
+            None => schema::generate_all()?,
+        };
+        match format {
+            OutputFormat::JsonCompact => println!("{}", serde_json::to_string(&schema)?),
+            _ => println!("{}", serde_json::to_string_pretty(&schema)?),
+        }
+        return Ok(());
+    }
+    // So does watching the exchange
+    #[cfg(feature = "events")]
+    if let Command::Events(cli::EventsArgs { action: EventsAction::Tail { patterns } }) = &cli.command {
+        return run_events_tail(patterns, format, &config.bloodbank).await;
+    }
+    #[cfg(not(feature = "events"))]
+    if let Command::Events(cli::EventsArgs { action: EventsAction::Tail { .. } }) = &cli.command {
+        return Err(feature_missing("events tail", "events"));
+    }
+    let redis_url = match &config.redis_replica_url {
+        Some(replica) if !cli.primary && reads_only(&cli.command) => replica,
+        _ => &config.redis_url,
+    };
+    let state = StateManager::new(redis_url)
+        .await?
+        .with_pane_ttl_days(config.storage.pane_ttl_days)
+        .with_history_archive(config.storage.archive_history)
+        .with_trash_ttl_days(Some(config.storage.trash_ttl_days))
+        .with_encryption(config.storage.encryption_key_env.as_deref().map(crypto::Cipher::from_env).transpose()?);
     let zellij = ZellijDriver::new();
-    let events = EventPublisher::new(config.bloodbank.clone());
+    let events = EventPublisher::new(config.bloodbank.clone()).with_outbox(state.event_outbox());
 
     // Check Zellij version for commands that interact with Zellij
     if needs_zellij_check(&cli.command) {
         zellij.check_version().await?;
     }
 
-    let mut orchestrator = Orchestrator::new(state, zellij, events);
+    let mut orchestrator = Orchestrator::new(state, zellij, events)
+        .with_scrollback_capture(config.privacy.scrollback_consent)
+        .with_context_config(config.context.clone())
+        .with_filter_config(config.filter.clone())
+        .with_tab_config(config.tab.clone())
+        .with_snapshot_config(config.snapshot.clone())
+        .with_session_creation(match &cli.command {
+            Command::Pane(args) => args.create_session,
+            Command::Snapshot(args) => matches!(args.action, cli::SnapshotAction::Restore { create_session: true, .. }),
+            _ => false,
+        });
+
+    // `zdrive log` is `pane log` for the current pane
+    let command = match cli.command {
+        Command::Log { summary, pane, entry_type, source, source_detail, artifacts, tags } => {
+            let name = match pane {
+                Some(pane) => pane,
+                None => orchestrator.current_pane().await?,
+            };
+            let action = PaneAction::Log {
+                name,
+                summary: Some(summary),
+                entry_type,
+                source,
+                source_detail,
+                artifacts,
+                tags,
+                goal: false,
+                goal_delta: None,
+                append_artifacts: Vec::new(),
+            };
+            Command::Pane(cli::PaneArgs { action: Some(action), ..Default::default() })
+        }
+        command => command,
+    };
 
-    match cli.command {
+    match command {
         Command::Pane(args) => {
             if let Some(action) = args.action {
                 match action {
                     PaneAction::Info { name } => {
                         let info = orchestrator.pane_info(name).await?;
-                        let json = serde_json::to_string_pretty(&info)?;
-                        println!("{json}");
+                        if format.is_json() {
+                            output::json::print(format, &command_path, &info)?;
+                        } else {
+                            println!("{}", serde_json::to_string_pretty(&info)?);
+                        }
                         if matches!(info.status, types::PaneStatus::Missing) {
                             std::process::exit(2);
                         }
                         return Ok(());
                     }
-                    PaneAction::Log { name, summary, entry_type, source, artifacts } => {
-                        // Resolve artifact paths (try absolute, fallback to as-is for non-existent)
-                        let resolved_artifacts: Vec<String> = artifacts
-                            .into_iter()
-                            .map(|p| {
-                                std::fs::canonicalize(&p)
-                                    .map(|abs| abs.to_string_lossy().to_string())
-                                    .unwrap_or(p)
-                            })
-                            .collect();
-
-                        let entry = IntentEntry::new(&summary)
+                    PaneAction::Log { name, summary, entry_type, source, source_detail, artifacts, tags, goal, goal_delta, append_artifacts } => {
+                        if let Some((entry_id, paths)) = append_artifacts.split_first() {
+                            let paths = resolve_artifacts(paths.to_vec());
+                            let (entry, added) = orchestrator.append_artifacts(&name, entry_id, &paths).await?;
+                            if format.is_json() {
+                                output::json::print(format, &command_path, json!({ "pane": name, "entry": entry, "added": added }))?;
+                            } else {
+                                println!("{}", messages::appended_artifacts(&entry, &name, added));
+                            }
+                            return Ok(());
+                        }
+                        // clap requires a summary unless appending
+                        let summary = summary.unwrap_or_default();
+                        let resolved_artifacts = resolve_artifacts(artifacts);
+
+                        // Record a branch switch first so it precedes this entry in history
+                        let mut also_logged = Vec::new();
+                        if let Some(cwd) = orchestrator.pane_working_dir(&name).await? {
+                            if let Some(switch) = orchestrator.check_branch(&name, &cwd).await? {
+                                if !format.is_json() {
+                                    println!("Logged branch switch for '{}': {}", name, switch.summary);
+                                }
+                                also_logged.push(switch);
+                            }
+                            if let Some(release) = orchestrator.check_release(&name, &cwd, &config.release).await? {
+                                if !format.is_json() {
+                                    println!("Logged release for '{}': {}", name, release.summary);
+                                }
+                                also_logged.push(release);
+                            }
+                        }
+
+                        let mut entry = IntentEntry::new(&summary)
                             .with_type(entry_type)
                             .with_source(source)
-                            .with_artifacts(resolved_artifacts);
+                            .with_artifacts(resolved_artifacts)
+                            .with_tags(tags);
+                        let source_detail = source_detail
+                            .or_else(|| std::env::var("PERTH_SOURCE_DETAIL").ok())
+                            .map(|detail| detail.trim().to_string())
+                            .filter(|detail| !detail.is_empty());
+                        if let Some(detail) = source_detail {
+                            entry = entry.with_source_detail(detail);
+                        }
+                        if goal || goal_delta.is_some() {
+                            let active = orchestrator.active_goal(&name).await?.ok_or_else(|| {
+                                anyhow!("pane '{}' has no active goal. Set one with:\n  zdrive goal set {} \"<goal>\"", name, name)
+                            })?;
+                            entry = entry.with_goal(active.id);
+                        }
+                        if let Some(delta) = goal_delta {
+                            entry = entry.with_goal_delta(delta);
+                        }
                         orchestrator.log_intent(&name, &entry).await?;
+                        if format.is_json() {
+                            output::json::print(format, &command_path, json!({ "pane": name, "entry": entry, "also_logged": also_logged }))?;
+                            return Ok(());
+                        }
 
                         let artifact_count = entry.artifacts.len();
-                        let source_tag = match source {
-                            types::IntentSource::Agent => " [agent]",
-                            _ => "",
+                        let source_tag = match (source, &entry.source_detail) {
+                            (types::IntentSource::Agent, Some(detail)) => format!(" [agent: {}]", detail),
+                            (types::IntentSource::Agent, None) => " [agent]".to_string(),
+                            (_, Some(detail)) => format!(" [{}]", detail),
+                            _ => String::new(),
+                        };
+                        println!("{}", messages::logged_entry(entry.entry_type_str(), &name, &source_tag, &summary, artifact_count));
+                        return Ok(());
+                    }
+                    PaneAction::BranchCheck { name, cwd } => {
+                        let cwd = match cwd {
+                            Some(dir) => std::path::PathBuf::from(dir),
+                            None => std::env::current_dir()?,
+                        };
+                        let entry = orchestrator.check_branch(&name, &cwd).await?;
+                        if format.is_json() {
+                            output::json::print(format, &command_path, json!({ "pane": name, "entry": entry }))?;
+                        } else if let Some(entry) = entry {
+                            println!("Logged branch switch for '{}': {}", name, entry.summary);
+                        }
+                        return Ok(());
+                    }
+                    PaneAction::Annotate { name, entry_id, note, author } => {
+                        let author = author.or_else(|| std::env::var("USER").ok());
+                        let annotation = orchestrator.annotate(&name, &entry_id, note, author).await?;
+                        if format.is_json() {
+                            output::json::print(format, &command_path, json!({ "pane": name, "annotation": annotation }))?;
+                        } else {
+                            println!("Annotated entry {} in '{}'", &annotation.entry_id.simple().to_string()[..8], name);
+                        }
+                        return Ok(());
+                    }
+                    PaneAction::Artifacts { name, check, remap, dry_run } => {
+                        let remapped = match &remap {
+                            Some((old, new)) => Some(orchestrator.remap_artifacts(&name, old, new, dry_run).await?),
+                            None => None,
                         };
-                        if artifact_count > 0 {
-                            println!(
-                                "Logged {} for '{}'{}: {} ({} artifact{})",
-                                entry.entry_type_str().to_lowercase(),
-                                name,
-                                source_tag,
-                                summary,
-                                artifact_count,
-                                if artifact_count == 1 { "" } else { "s" }
-                            );
+                        // A dry run reports the remap only; the paths haven't changed yet
+                        let reports = orchestrator.pane_artifacts(&name, check && !dry_run).await?;
+                        let missing = reports.iter().filter(|r| r.status == Some(artifacts::ArtifactStatus::Missing)).count();
+
+                        if format.is_json() {
+                            output::json::print(format, &command_path, json!({ "pane": name, "artifacts": reports, "missing": missing, "remap": remapped }))?;
                         } else {
-                            println!("Logged {} for '{}'{}: {}", entry.entry_type_str().to_lowercase(), name, source_tag, summary);
+                            if let (Some(result), Some((old, _))) = (&remapped, &remap) {
+                                let verb = if dry_run { "Would remap" } else { "Remapped" };
+                                println!("{} {} in '{}'", verb, messages::count(result.entries_updated, "entry"), name);
+                                for path in &result.paths {
+                                    println!("  {} {} {}", path.from, Icon::Forward, path.to);
+                                }
+                                if result.archived_entries > 0 {
+                                    println!(
+                                        "  {} {} under {} left unchanged (archived entries are read-only)",
+                                        Icon::Warning,
+                                        messages::count(result.archived_entries, "archived entry"),
+                                        old
+                                    );
+                                }
+                                println!();
+                            }
+                            if reports.is_empty() {
+                                println!("No artifacts logged for '{}'", name);
+                            }
+                            for report in &reports {
+                                let marker = match report.status {
+                                    Some(artifacts::ArtifactStatus::Exists) => format!("{} ", Icon::Success),
+                                    Some(artifacts::ArtifactStatus::Missing) => format!("{} ", Icon::Failure),
+                                    Some(artifacts::ArtifactStatus::Unchecked) => "- ".to_string(),
+                                    None => String::new(),
+                                };
+                                println!("{}{}  ({})", marker, report.path, messages::count(report.entries, "entry"));
+                            }
+                            if check && !dry_run {
+                                println!();
+                                match missing {
+                                    0 => println!("All artifact paths exist"),
+                                    n => println!("{} missing; rewrite moved paths with --remap OLD=NEW", messages::count(n, "path")),
+                                }
+                            }
+                        }
+                        if missing > 0 {
+                            std::process::exit(1);
                         }
                         return Ok(());
                     }
-                    PaneAction::History { name, last, entry_type, format } => {
-                        let mut history = orchestrator.get_history(&name, last).await?;
+                    PaneAction::History {
+                        name,
+                        last,
+                        offset,
+                        since,
+                        until,
+                        include_archived,
+                        entry_type,
+                        tag,
+                        page_size,
+                        budget,
+                        follow,
+                        no_pager,
+                    } => {
+                        if page_size.is_some() && !matches!(format, OutputFormat::Text) {
+                            return Err(anyhow!("--page-size only works with --format text"));
+                        }
+                        if page_size == Some(0) {
+                            return Err(anyhow!("--page-size must be at least 1"));
+                        }
+                        if follow && matches!(format, OutputFormat::Markdown | OutputFormat::Context | OutputFormat::Html) {
+                            return Err(anyhow!("--follow works with --format text, json, json-compact, csv or jsonl"));
+                        }
+
+                        // Subscribe before reading so nothing logged in between is missed
+                        let updates = if follow { Some(orchestrator.follow_history(&name).await?) } else { None };
+
+                        let query = state::HistoryQuery { offset, limit: last, since, until, include_archived, tag: tag.clone() };
+                        let mut history = orchestrator.query_history(&name, &query).await?;
 
                         // Apply type filter if specified (client-side filtering)
                         if let Some(filter_type) = entry_type {
                             history.retain(|entry| entry.entry_type == filter_type);
                         }
 
-                        match format {
-                            OutputFormat::Json => {
-                                let output = serde_json::json!({
-                                    "schema_version": "2.0",
-                                    "pane": name,
-                                    "entries": history,
-                                });
-                                println!("{}", serde_json::to_string_pretty(&output)?);
-                            }
-                            OutputFormat::JsonCompact => {
-                                let output = serde_json::json!({
-                                    "schema_version": "2.0",
-                                    "pane": name,
-                                    "entries": history,
-                                });
-                                println!("{}", serde_json::to_string(&output)?);
-                            }
-                            OutputFormat::Text => {
-                                let formatter = OutputFormatter::new();
-                                println!("{}", formatter.format_history(&history, &name));
-                            }
-                            OutputFormat::Markdown => {
-                                let formatter = OutputFormatter::new();
-                                println!("{}", formatter.format_markdown(&history, &name));
-                            }
-                            OutputFormat::Context => {
-                                let formatter = OutputFormatter::new();
-                                println!("{}", formatter.format_context(&history, &name));
+                        // Only annotations on the entries being shown
+                        let shown: std::collections::HashSet<_> = history.iter().map(|e| e.id).collect();
+                        let mut annotations = orchestrator.get_annotations(&name).await?;
+                        annotations.retain(|a| shown.contains(&a.entry_id));
+
+                        if let Some(page_size) = page_size {
+                            let formatter = OutputFormatter::new().with_annotations(annotations);
+                            page_history(&formatter, &history, &name, page_size)?;
+                        } else {
+                            match format {
+                                // Followed JSON is one entry per line, backlog included
+                                OutputFormat::Json | OutputFormat::JsonCompact if follow => {
+                                    for entry in &history {
+                                        println!("{}", serde_json::to_string(entry)?);
+                                    }
+                                }
+                                OutputFormat::Json | OutputFormat::JsonCompact => {
+                                    let goal = orchestrator.active_goal(&name).await?;
+                                    let body = HistoryOutput {
+                                        pane: name.clone(),
+                                        goal_id: goal.as_ref().map(|goal| goal.id),
+                                        goal_set_at: goal.as_ref().map(|goal| goal.set_at),
+                                        goal: goal.map(|goal| goal.title),
+                                        entries: history,
+                                        annotations,
+                                    };
+                                    output::json::print(format, &command_path, body)?;
+                                }
+                                OutputFormat::Text | OutputFormat::Markdown => {
+                                    let formatter = OutputFormatter::new().with_annotations(annotations);
+                                    let text = if matches!(format, OutputFormat::Text) {
+                                        formatter.format_history(&history, &name)
+                                    } else {
+                                        // The goal travels in the frontmatter so `takeover` can pick it up
+                                        formatter
+                                            .with_goal(orchestrator.goal_progress(&name).await?)
+                                            .format_markdown(&history, &name)
+                                    };
+                                    // The followed stream keeps printing below the backlog
+                                    let pager = if no_pager || follow { None } else { output::pager::command(config.display.pager.as_deref()) };
+                                    output::pager::print(&text, pager.as_deref())?;
+                                }
+                                OutputFormat::Csv => {
+                                    println!("{}", output::records::CSV_HEADER);
+                                    for entry in &history {
+                                        println!("{}", output::records::csv_row(&name, entry));
+                                    }
+                                }
+                                OutputFormat::Jsonl => {
+                                    for entry in &history {
+                                        println!("{}", output::records::jsonl_line(&name, entry)?);
+                                    }
+                                }
+                                OutputFormat::Html => {
+                                    let formatter = OutputFormatter::new().with_annotations(annotations);
+                                    print!("{}", formatter.format_html(&history, &name));
+                                }
+                                OutputFormat::Context => {
+                                    let mut context = config.display.context.clone();
+                                    if let Some(budget) = budget {
+                                        context.budget_tokens = budget;
+                                    }
+                                    let formatter = OutputFormatter::new()
+                                        .with_goal(orchestrator.goal_progress(&name).await?)
+                                        .with_context_display(context);
+                                    println!("{}", formatter.format_context(&history, &name));
+                                }
                             }
                         }
+
+                        if let Some(updates) = updates {
+                            follow_history(updates, &name, entry_type, tag.as_deref(), format, &shown).await?;
+                        }
+                        return Ok(());
+                    }
+                    PaneAction::Snapshot { name, dry_run, show_redactions, .. } if dry_run || show_redactions => {
+                        let preview = orchestrator.snapshot_preview(&name, &config.llm).await?;
+
+                        if format.is_json() {
+                            output::json::print(format, &command_path, json!({ "pane": name, "preview": preview }))?;
+                            return Ok(());
+                        }
+                        if !dry_run {
+                            println!("=== REDACTIONS (nothing sent) ===");
+                            println!();
+                            println!("{}", OutputFormatter::new().format_redactions(&preview.redactions));
+                            return Ok(());
+                        }
+
+                        println!("=== DRY RUN (nothing sent) ===");
+                        println!();
+                        println!("  Provider: {}", preview.provider);
+                        println!("  Model: {}", preview.model);
+                        println!("  Estimated tokens: ~{}", preview.estimated_tokens);
+                        if let Some(cost) = preview.estimated_cost_usd {
+                            println!("  Estimated input cost: ~${:.4}", cost);
+                        }
+                        if !preview.available {
+                            println!("  Note: provider '{}' is not available; a real snapshot would fail.", preview.provider);
+                        }
+                        if !config.privacy.consent_given && preview.provider != "none" {
+                            println!("  Note: consent not granted; run 'zdrive config consent --grant' to send it.");
+                        }
+                        println!();
+                        println!("--- prompt ---");
+                        println!("{}", preview.prompt);
+                        println!("--- end prompt ---");
+                        if show_redactions {
+                            println!();
+                            println!("{}", OutputFormatter::new().format_redactions(&preview.redactions));
+                        }
                         return Ok(());
                     }
-                    PaneAction::Snapshot { name } => {
-                        let llm_config = config.llm.clone();
+                    PaneAction::Snapshot { name, fallback, timeout, tags, .. } => {
+                        let mut release = None;
+                        if let Some(cwd) = orchestrator.pane_working_dir(&name).await? {
+                            release = orchestrator.check_release(&name, &cwd, &config.release).await?;
+                            if let Some(release) = release.as_ref().filter(|_| !format.is_json()) {
+                                println!("Logged release for '{}': {}", name, release.summary);
+                            }
+                        }
+                        let mut llm_config = config.llm.clone();
+                        if let Some(secs) = timeout {
+                            llm_config.timeout_secs = secs;
+                        }
                         let consent_given = config.privacy.consent_given;
-                        let result = orchestrator.snapshot(&name, &llm_config, consent_given).await?;
+                        let spinner = Spinner::start(format!("Summarizing with {}", llm_config.provider));
+                        let received = spinner.counter();
+                        let mut llm_failed = false;
+                        let result = orchestrator
+                            .snapshot(
+                                &name,
+                                &llm_config,
+                                consent_given,
+                                &tags,
+                                &mut |_: &anyhow::Error| {
+                                    llm_failed = true;
+                                    fallback
+                                },
+                                &mut |text: &str| {
+                                    received.fetch_add(text.len(), std::sync::atomic::Ordering::Relaxed);
+                                },
+                            )
+                            .await;
+                        spinner.finish();
+
+                        // Offer the local summary when the LLM itself failed
+                        let result = match result {
+                            Err(e) if llm_failed && offer_heuristic_fallback(&e)? => {
+                                let local = llm::LLMConfig { provider: "none".to_string(), ..llm_config };
+                                orchestrator.snapshot(&name, &local, consent_given, &tags, &mut |_: &anyhow::Error| false, &mut |_: &str| {}).await?
+                            }
+                            result => result?,
+                        };
+
+                        if format.is_json() {
+                            output::json::print(format, &command_path, json!({ "pane": name, "snapshot": result, "also_logged": release }))?;
+                            return Ok(());
+                        }
 
                         println!("Generated snapshot for '{}':", name);
                         println!();
                         println!("  Summary: {}", result.summary);
                         println!("  Type: {:?}", result.entry_type);
+                        if !result.tags.is_empty() {
+                            println!("  Tags: {}", result.tags.join(", "));
+                        }
 
                         if !result.key_files.is_empty() {
                             println!("  Key files:");
@@ -156,19 +510,28 @@ async fn run() -> Result<()> {
                             }
                         }
 
+                        if result.summarizer == "heuristic" {
+                            println!("  Summarizer: local heuristic (no LLM)");
+                        }
                         if let Some(tokens) = result.tokens_used {
                             println!("  Tokens used: {}", tokens);
                         }
+                        if let Some(cost) = result.cost_usd {
+                            println!("  Estimated cost: ${:.4}", cost);
+                        }
 
                         return Ok(());
                     }
-                    PaneAction::Batch { tab, panes, cwd, layout } => {
+                    PaneAction::Batch { tab, panes, cwd, run, layout } => {
                         let vertical = matches!(layout, cli::SplitDirection::Vertical);
-                        let result = orchestrator.batch_panes(tab, panes, cwd, vertical).await?;
+                        let result = orchestrator.batch_panes(None, tab, panes, cwd, run, vertical).await?;
+                        if format.is_json() {
+                            output::json::print(format, &command_path, &result)?;
+                            return Ok(());
+                        }
 
-                        println!("Created {} pane{} in tab '{}' (session '{}')",
-                            result.panes_created.len(),
-                            if result.panes_created.len() == 1 { "" } else { "s" },
+                        println!("Created {} in tab '{}' (session '{}')",
+                            messages::count(result.panes_created.len(), "pane"),
                             result.tab_name,
                             result.session
                         );
@@ -193,37 +556,43 @@ async fn run() -> Result<()> {
             }
 
             let pane_name = args.name.ok_or_else(|| anyhow!("pane name is required"))?;
-            let meta = collect_meta(args.meta);
+            let mut meta = collect_meta(args.meta);
+            if let Some(command) = args.run {
+                meta.insert(orchestrator::COMMAND_META_KEY.to_string(), command);
+            }
             let show_last_intent = config.display.show_last_intent;
-            orchestrator
-                .open_pane(pane_name, args.tab, args.session, meta, show_last_intent)
+            let location = orchestrator
+                .open_pane(pane_name.clone(), args.tab, args.session, meta, show_last_intent)
                 .await?;
+            if format.is_json() {
+                let info = orchestrator.pane_info(pane_name).await?;
+                output::json::print(format, &command_path, &info)?;
+            } else if !location.attached {
+                let attach = if args.attach { Some(true) } else if args.no_attach { Some(false) } else { None };
+                offer_attach(&zellij, &pane_name, &location, attach).await?;
+            }
         }
         Command::Tab(args) => {
             match args.action {
-                Some(TabAction::Create { name, correlation_id, strict, meta }) => {
-                    // Validate tab naming convention (STORY-039)
-                    let name_valid = config.tab.validate_name(&name);
-                    if !name_valid {
-                        if strict {
-                            return Err(anyhow!(
-                                "Tab name '{}' does not match naming convention.\n\
-                                 Expected format: {}\n\
-                                 Use --strict=false to proceed anyway.",
-                                name,
-                                config.tab.format_hint()
-                            ));
-                        } else {
-                            eprintln!(
-                                "Warning: Tab name '{}' does not match naming convention.",
-                                name
-                            );
-                            eprintln!("  Expected format: {}", config.tab.format_hint());
+                Some(TabAction::Create { name, from_git, correlation_id, strict, force, meta }) => {
+                    let name = match name {
+                        Some(name) => name,
+                        None if from_git => {
+                            let cwd = std::env::current_dir()?;
+                            activity::git_tab_name(&cwd).ok_or_else(|| {
+                                anyhow!("--from-git needs a git checkout, but {} is not inside one", cwd.display())
+                            })?
                         }
-                    }
-
+                        None => return Err(anyhow!("a tab name or --from-git is required")),
+                    };
+                    // Naming convention (STORY-039): warn, or fail under --strict / tab.enforce_naming
+                    let check = config.tab.naming_check(strict, force);
                     let meta_map = collect_meta(meta);
-                    let result = orchestrator.create_tab(name, correlation_id, meta_map).await?;
+                    let result = orchestrator.create_tab(name, correlation_id, meta_map, check).await?;
+                    if format.is_json() {
+                        output::json::print(format, &command_path, &result)?;
+                        return Ok(());
+                    }
 
                     if result.created {
                         print!("Created tab '{}'", result.tab_name);
@@ -239,6 +608,7 @@ async fn run() -> Result<()> {
                 }
                 Some(TabAction::Info { name }) => {
                     match orchestrator.tab_info(&name).await? {
+                        Some(tab) if format.is_json() => output::json::print(format, &command_path, &tab)?,
                         Some(tab) => {
                             let json = serde_json::to_string_pretty(&tab)?;
                             println!("{}", json);
@@ -249,11 +619,79 @@ async fn run() -> Result<()> {
                         }
                     }
                 }
+                Some(TabAction::Find { correlation_id }) => {
+                    let tabs = orchestrator.find_tabs(&correlation_id).await?;
+                    if format.is_json() {
+                        output::json::print(format, &command_path, json!({ "correlation_id": correlation_id, "tabs": tabs }))?;
+                    } else if tabs.is_empty() {
+                        println!("No tabs with correlation ID '{}'.", correlation_id);
+                    } else {
+                        for tab in tabs {
+                            let archived = if tab.archived_at.is_some() { " (archived)" } else { "" };
+                            println!("{}{} in session '{}', created {}", tab.tab_name, archived, tab.session, tab.created_at);
+                        }
+                    }
+                }
+                Some(TabAction::List { session, all_sessions }) => {
+                    let tabs = orchestrator.list_tabs(session.as_deref(), all_sessions).await?;
+                    if format.is_json() {
+                        output::json::print(format, &command_path, json!({ "tabs": tabs }))?;
+                    } else if tabs.is_empty() {
+                        println!("No tracked tabs found.");
+                    } else {
+                        println!("Tabs:");
+                        for summary in tabs {
+                            let tab = &summary.tab;
+                            print!("\n  {}", tab.tab_name);
+                            if tab.archived_at.is_some() {
+                                print!(" (archived)");
+                            }
+                            println!();
+                            if all_sessions {
+                                println!("    Session: {}", tab.session);
+                            }
+                            if let Some(id) = &tab.correlation_id {
+                                println!("    Correlation: {}", id);
+                            }
+                            println!("    Panes: {} | Last accessed: {}", summary.panes, tab.last_accessed);
+                        }
+                    }
+                }
+                Some(TabAction::Delete { name, session, close_zellij, with_panes }) => {
+                    let backup = orchestrator.auto_backup("tab-delete").await;
+                    let result = orchestrator.delete_tab(&name, session.as_deref(), close_zellij, with_panes).await?;
+                    let trashed = config.storage.trash_ttl_days > 0;
+                    if format.is_json() {
+                        let mut body = serde_json::to_value(&result)?;
+                        body["trashed"] = json!(trashed);
+                        if let Some(backup) = backup {
+                            body["backup_snapshot"] = json!(backup);
+                        }
+                        output::json::print(format, &command_path, body)?;
+                        return Ok(());
+                    }
+                    if let Some(backup) = &backup {
+                        println!("{}", messages::backup_snapshot(backup));
+                    }
+                    if trashed {
+                        println!("Tab '{}' moved to trash (restore with 'zdrive trash list').", name);
+                    } else {
+                        println!("Tab '{}' deleted.", name);
+                    }
+                    if result.closed {
+                        println!("Closed it in session '{}'.", result.session);
+                    }
+                    if !result.deleted_panes.is_empty() {
+                        println!("Deleted panes: {}", result.deleted_panes.join(", "));
+                    }
+                }
                 None => {
                     // Backwards compatibility: just ensure the tab exists
                     let tab_name = args.name.ok_or_else(|| anyhow!("tab name is required"))?;
                     let created = orchestrator.ensure_tab(&tab_name).await?;
-                    if created {
+                    if format.is_json() {
+                        output::json::print(format, &command_path, json!({ "tab": tab_name, "created": created }))?;
+                    } else if created {
                         println!("Created tab '{}'", tab_name);
                     } else {
                         println!("Focused tab '{}'", tab_name);
@@ -261,11 +699,98 @@ async fn run() -> Result<()> {
                 }
             }
         }
-        Command::Reconcile => {
-            orchestrator.reconcile().await?;
+        Command::Reconcile(args) => {
+            let mut result = orchestrator.reconcile().await?;
+            if let Some(older_than) = args.prune {
+                let candidates = orchestrator.prune_candidates(older_than).await?;
+                if !candidates.is_empty() && (args.yes || confirm_prune(&candidates)?) {
+                    result.backup_snapshot = orchestrator.auto_backup("prune").await;
+                    result.pruned_panes = orchestrator.prune_panes(&candidates).await?;
+                    result.pruned = result.pruned_panes.len();
+                }
+            }
+            if format.is_json() {
+                output::json::print(format, &command_path, &result)?;
+            } else {
+                println!(
+                    "reconcile: session={} total={} seen={} stale={} skipped={}",
+                    result.session, result.total, result.seen, result.stale, result.skipped
+                );
+                if let Some(backup) = &result.backup_snapshot {
+                    println!("{}", messages::backup_snapshot(backup));
+                }
+                if args.prune.is_some() {
+                    println!("Pruned {}", messages::count(result.pruned, "stale pane"));
+                }
+            }
+        }
+        Command::Up { file, diff } => {
+            let path = match file {
+                Some(path) => path,
+                None => {
+                    let cwd = std::env::current_dir()?;
+                    workspace::find(&cwd).ok_or_else(|| {
+                        anyhow!("no {} found in {} or its parents", workspace::FILE_NAMES.join(" or "), cwd.display())
+                    })?
+                }
+            };
+            let workspace = workspace::load(&path)?;
+            let plan = if diff {
+                orchestrator.workspace_plan(&workspace, None).await?
+            } else {
+                orchestrator.apply_workspace(&workspace, None).await?
+            };
+
+            if format.is_json() {
+                output::json::print(format, &command_path, json!({ "dry_run": diff, "plan": plan }))?;
+            } else {
+                print_workspace_plan(&plan, diff);
+            }
+        }
+        Command::List(args) if format.is_json() => {
+            let query = orchestrator::PaneListQuery::from(args);
+            let mut panes = Vec::new();
+            for record in orchestrator.list_panes(&query).await? {
+                let mut pane = plumbing::pane_json(&record);
+                if query.details {
+                    pane["last_intent"] = json!(orchestrator.last_intent(&record.pane_name).await?);
+                }
+                panes.push(pane);
+            }
+            output::json::print(format, &command_path, json!({ "panes": panes }))?;
         }
-        Command::List => {
-            orchestrator.visualize().await?;
+        Command::List(args) => {
+            orchestrator.visualize(&args.into()).await?;
+        }
+        Command::Jump { query, stale } => {
+            let candidates = orchestrator.jump_candidates(stale).await?;
+            if candidates.is_empty() {
+                return Err(anyhow!("no tracked panes to jump to"));
+            }
+            let target = if query.is_empty() {
+                match jump::prompt(&candidates)? {
+                    Some(target) => target,
+                    None => {
+                        println!("No pane selected.");
+                        return Ok(());
+                    }
+                }
+            } else {
+                let query = query.join(" ");
+                jump::best(&query, &candidates)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("no tracked pane matches '{}'", query))?
+            };
+
+            // Zellij can't switch sessions from inside one, so say how instead
+            let active = zellij.active_session_name();
+            if active.as_deref() != Some(target.session.as_str()) {
+                println!("{}", messages::attach_instructions(&target.pane_name, &target.session, active.is_some()));
+                return Ok(());
+            }
+            orchestrator
+                .open_pane(target.pane_name, None, None, Default::default(), config.display.show_last_intent)
+                .await?;
         }
         Command::Config(args) => {
             match args.action {
@@ -274,6 +799,10 @@ async fn run() -> Result<()> {
                 }
                 ConfigAction::Set { key, value } => {
                     let old_value = Config::set_value(&key, &value)?;
+                    if format.is_json() {
+                        output::json::print(format, &command_path, json!({ "key": key, "old_value": old_value, "value": value }))?;
+                        return Ok(());
+                    }
 
                     match old_value {
                         Some(old) => {
@@ -284,7 +813,40 @@ async fn run() -> Result<()> {
                         }
                     }
                 }
-                ConfigAction::Consent { grant, revoke } => {
+                ConfigAction::Consent { grant, revoke, scrollback } if format.is_json() => {
+                    match (grant || revoke, scrollback) {
+                        (false, _) => {}
+                        (true, true) => Config::set_scrollback_consent(grant)?,
+                        (true, false) if grant => Config::grant_consent()?,
+                        (true, false) => Config::revoke_consent()?,
+                    }
+                    let privacy = Config::load()?.privacy;
+                    let body = json!({
+                        "consent_given": privacy.consent_given,
+                        "consent_timestamp": privacy.consent_timestamp,
+                        "scrollback_consent": privacy.scrollback_consent,
+                    });
+                    output::json::print(format, &command_path, body)?;
+                }
+                ConfigAction::Consent { grant, revoke, scrollback: true } => {
+                    if grant || revoke {
+                        Config::set_scrollback_consent(grant)?;
+                    }
+                    let granted = if grant || revoke { grant } else { config.privacy.scrollback_consent };
+                    if granted {
+                        println!("Scrollback consent: GRANTED");
+                        println!("Snapshots run from inside a pane will include the tail of its terminal output,");
+                        println!("with secrets filtered.");
+                        if !config.privacy.consent_given {
+                            println!();
+                            println!("Nothing is sent until LLM consent is granted too: zdrive config consent --grant");
+                        }
+                    } else {
+                        println!("Scrollback consent: NOT GRANTED");
+                        println!("To include terminal output in snapshots: zdrive config consent --grant --scrollback");
+                    }
+                }
+                ConfigAction::Consent { grant, revoke, scrollback: false } => {
                     if grant {
                         Config::grant_consent()?;
                         println!("Consent granted for LLM data sharing.");
@@ -316,6 +878,9 @@ async fn run() -> Result<()> {
                 }
             }
         }
+        #[cfg(not(feature = "snapshots"))]
+        Command::Snapshot(_) => return Err(feature_missing("snapshot", "snapshots")),
+        #[cfg(feature = "snapshots")]
         Command::Snapshot(args) => {
             use cli::SnapshotAction;
             use snapshot::StateCapture;
@@ -323,17 +888,27 @@ async fn run() -> Result<()> {
             let state_capture = StateCapture::new(zellij::ZellijDriver::new());
 
             match args.action {
-                SnapshotAction::Create { name, description, parent, format } => {
+                SnapshotAction::Create { name, description, parent, scrollback, scrollback_lines } => {
+                    let state_capture = if scrollback {
+                        if !config.privacy.scrollback_consent {
+                            return Err(anyhow!("capturing pane output needs consent; run 'zdrive config consent --grant --scrollback'"));
+                        }
+                        state_capture.with_scrollback(scrollback_lines, filter::SecretFilter::with_config(&config.filter)?)
+                    } else {
+                        state_capture
+                    };
+
                     // Look up parent snapshot if provided
                     let parent_id = if let Some(parent_name) = parent {
                         match orchestrator.get_snapshot(&parent_name).await {
                             Ok(parent_snapshot) => {
-                                println!("Using parent snapshot: {} (ID: {})", parent_snapshot.name, parent_snapshot.id);
+                                if !format.is_json() {
+                                    println!("Using parent snapshot: {} (ID: {})", parent_snapshot.name, parent_snapshot.id);
+                                }
                                 Some(parent_snapshot.id)
                             }
                             Err(e) => {
-                                eprintln!("Warning: Parent snapshot '{}' not found: {}", parent_name, e);
-                                eprintln!("Creating full snapshot instead of incremental.");
+                                tracing::warn!(snapshot = %parent_name, "parent snapshot not found, creating a full snapshot instead: {:#}", e);
                                 None
                             }
                         }
@@ -342,35 +917,24 @@ async fn run() -> Result<()> {
                     };
 
                     // Capture session state
-                    let (snapshot, report) = state_capture
+                    let (mut snapshot, report) = state_capture
                         .capture_session(name.clone(), description, parent_id)
                         .await?;
 
                     // Save to Redis
-                    orchestrator.save_snapshot(&snapshot).await?;
+                    orchestrator.save_snapshot(&mut snapshot).await?;
 
                     // Enforce retention policy
                     if let Ok(deleted) = orchestrator.enforce_snapshot_retention(&snapshot.session, config.snapshot.retention_limit).await {
-                        if deleted > 0 {
-                            println!("  (Cleaned up {} old snapshot{})", deleted, if deleted == 1 { "" } else { "s" });
+                        if deleted > 0 && !format.is_json() {
+                            println!("  {}", messages::cleaned_up_snapshots(deleted));
                         }
                     }
 
                     // Format output
                     match format {
-                        OutputFormat::Json => {
-                            let output = serde_json::json!({
-                                "snapshot": snapshot,
-                                "report": report,
-                            });
-                            println!("{}", serde_json::to_string_pretty(&output)?);
-                        }
-                        OutputFormat::JsonCompact => {
-                            let output = serde_json::json!({
-                                "snapshot": snapshot,
-                                "report": report,
-                            });
-                            println!("{}", serde_json::to_string(&output)?);
+                        OutputFormat::Json | OutputFormat::JsonCompact => {
+                            output::json::print(format, &command_path, json!({ "snapshot": snapshot, "report": report }))?;
                         }
                         _ => {
                             // Text format
@@ -403,7 +967,7 @@ async fn run() -> Result<()> {
                         }
                     }
                 }
-                SnapshotAction::List { all_sessions, format } => {
+                SnapshotAction::List { all_sessions } => {
                     let snapshots = if all_sessions {
                         orchestrator.list_all_snapshots().await?
                     } else {
@@ -411,11 +975,8 @@ async fn run() -> Result<()> {
                     };
 
                     match format {
-                        OutputFormat::Json => {
-                            println!("{}", serde_json::to_string_pretty(&snapshots)?);
-                        }
-                        OutputFormat::JsonCompact => {
-                            println!("{}", serde_json::to_string(&snapshots)?);
+                        OutputFormat::Json | OutputFormat::JsonCompact => {
+                            output::json::print(format, &command_path, json!({ "snapshots": snapshots }))?;
                         }
                         _ => {
                             // Text format
@@ -436,15 +997,12 @@ async fn run() -> Result<()> {
                         }
                     }
                 }
-                SnapshotAction::Show { name, format } => {
+                SnapshotAction::Show { name, scrollback } => {
                     let snapshot = orchestrator.get_snapshot(&name).await?;
 
                     match format {
-                        OutputFormat::Json => {
-                            println!("{}", serde_json::to_string_pretty(&snapshot)?);
-                        }
-                        OutputFormat::JsonCompact => {
-                            println!("{}", serde_json::to_string(&snapshot)?);
+                        OutputFormat::Json | OutputFormat::JsonCompact => {
+                            output::json::print(format, &command_path, &snapshot)?;
                         }
                         _ => {
                             // Text format
@@ -467,7 +1025,7 @@ async fn run() -> Result<()> {
                                     if ancestry.len() > 1 {
                                         println!("  Ancestry chain ({} snapshots):", ancestry.len());
                                         for (i, ancestor) in ancestry.iter().enumerate() {
-                                            let prefix = if i == 0 { "    → " } else { "      ← " };
+                                            let prefix = if i == 0 { format!("    {} ", Icon::Forward) } else { format!("      {} ", Icon::Back) };
                                             println!("{}{} ({})", prefix, ancestor.name,
                                                 ancestor.created_at.format("%Y-%m-%d %H:%M:%S"));
                                         }
@@ -485,6 +1043,18 @@ async fn run() -> Result<()> {
                                     if let Some(cwd) = &pane.cwd {
                                         println!("        CWD: {}", cwd);
                                     }
+                                    if scrollback {
+                                        match pane.scrollback_text() {
+                                            Ok(Some(text)) => {
+                                                println!("        Output:");
+                                                for line in text.lines() {
+                                                    println!("          {}", line);
+                                                }
+                                            }
+                                            Ok(None) => println!("        Output: not captured"),
+                                            Err(e) => println!("        Output: unreadable ({:#})", e),
+                                        }
+                                    }
                                 }
                             }
                         }
@@ -492,22 +1062,57 @@ async fn run() -> Result<()> {
                 }
                 SnapshotAction::Delete { name } => {
                     orchestrator.delete_snapshot(&name).await?;
-                    println!("Snapshot '{}' deleted.", name);
+                    let trashed = config.storage.trash_ttl_days > 0;
+                    if format.is_json() {
+                        output::json::print(format, &command_path, json!({ "snapshot": name, "trashed": trashed }))?;
+                    } else if trashed {
+                        println!("Snapshot '{}' moved to trash (restore with 'zdrive trash list').", name);
+                    } else {
+                        println!("Snapshot '{}' deleted.", name);
+                    }
+                }
+                SnapshotAction::Export { name, output: path } => {
+                    let snapshot = orchestrator.get_snapshot(&name).await?;
+                    snapshot::export_file(&snapshot, &path)?;
+                    if format.is_json() {
+                        output::json::print(format, &command_path, json!({ "snapshot": name, "path": path }))?;
+                    } else {
+                        println!("Snapshot '{}' exported to {}", name, path.display());
+                    }
+                }
+                SnapshotAction::Import { file, name, session, force } => {
+                    let mut snapshot = snapshot::import_file(&file)?;
+                    if let Some(name) = name {
+                        snapshot.name = name;
+                    }
+                    let snapshot = orchestrator.import_snapshot(snapshot, session.as_deref(), force).await?;
+                    if format.is_json() {
+                        output::json::print(format, &command_path, json!({ "snapshot": snapshot }))?;
+                    } else {
+                        println!("Snapshot '{}' imported into session '{}'", snapshot.name, snapshot.session);
+                        println!("  Tabs: {} | Panes: {}", snapshot.tabs.len(), snapshot.pane_count);
+                        println!("  Restore with: zdrive snapshot restore {}", snapshot.name);
+                    }
                 }
-                SnapshotAction::Restore { name, dry_run, format } => {
-                    // Load snapshot
+                SnapshotAction::Restore { name, dry_run, into, create_session: _, tab, pane } => {
+                    // Load snapshot, keeping only the selected tab or pane
                     let snapshot = orchestrator.get_snapshot(&name).await?;
+                    let snapshot = restore::select(&snapshot, tab.as_deref(), pane.as_deref())?;
+
+                    // Back up the session the restore changes, unless restoring into another one
+                    let backup = if dry_run || into.is_some() { None } else { orchestrator.auto_backup("restore").await };
 
                     // Perform restoration
-                    let report = orchestrator.restore_snapshot(&snapshot, dry_run).await?;
+                    let report = orchestrator.restore_snapshot(&snapshot, dry_run, into.as_deref()).await?;
 
                     // Format output
                     match format {
-                        OutputFormat::Json => {
-                            println!("{}", serde_json::to_string_pretty(&report)?);
-                        }
-                        OutputFormat::JsonCompact => {
-                            println!("{}", serde_json::to_string(&report)?);
+                        OutputFormat::Json | OutputFormat::JsonCompact => {
+                            let mut body = json!({ "snapshot": name, "dry_run": dry_run, "report": report });
+                            if let Some(backup) = backup {
+                                body["backup_snapshot"] = json!(backup);
+                            }
+                            output::json::print(format, &command_path, body)?;
                         }
                         _ => {
                             // Text format
@@ -518,12 +1123,25 @@ async fn run() -> Result<()> {
                             println!("Restoration: {}", snapshot.name);
                             println!("  Status: {:?}", report.status);
                             println!("  Session: {}", report.session);
+                            if report.session_created {
+                                println!("  (started in the background)");
+                            }
+                            if let Some(original) = &report.original_session {
+                                println!("  Original session: {}", original);
+                            }
                             println!("  Tabs restored: {}", report.tabs_restored);
                             println!("  Tabs failed: {}", report.tabs_failed);
                             println!("  Panes restored: {}", report.panes_restored);
                             println!("  Panes failed: {}", report.panes_failed);
                             println!("  Duration: {}ms", report.duration_ms);
 
+                            if !report.missing_cwds.is_empty() {
+                                println!("\nMissing on this machine ({}):", report.missing_cwds.len());
+                                for cwd in &report.missing_cwds {
+                                    println!("  {}", cwd);
+                                }
+                            }
+
                             if !report.warnings.is_empty() {
                                 println!("\nWarnings ({}):", report.warnings.len());
                                 for warning in &report.warnings {
@@ -538,17 +1156,14 @@ async fn run() -> Result<()> {
                                 }
                             }
 
-                            if report.status == crate::types::RestoreStatus::Success {
-                                println!("\n✓ Session successfully restored from snapshot");
-                            } else if report.status == crate::types::RestoreStatus::Partial {
-                                println!("\n⚠ Session partially restored (see warnings above)");
-                            } else {
-                                println!("\n✗ Session restoration failed (see errors above)");
+                            println!("\n{}", messages::restore_outcome(&report.status));
+                            if let Some(backup) = &backup {
+                                println!("{}", messages::backup_snapshot(backup));
                             }
                         }
                     }
                 }
-                SnapshotAction::Daemon { interval, prefix, incremental } => {
+                SnapshotAction::Daemon { interval, prefix, incremental, adaptive, pane, min_edits, metrics_addr } => {
                     use tokio::time::{interval as tokio_interval, Duration};
                     use chrono::Local;
 
@@ -556,131 +1171,1665 @@ async fn run() -> Result<()> {
                     println!("  Interval: {} seconds", interval);
                     println!("  Prefix: {}", prefix);
                     println!("  Incremental: {}", incremental);
+                    if let Some(pane) = &pane {
+                        println!("  Adaptive: yes (summarizing '{}', min edits {})", pane, min_edits);
+                    }
+                    if let Some(addr) = metrics_addr {
+                        metrics::serve(addr).await?;
+                        println!("  Metrics: http://{}/metrics", addr);
+                    }
                     println!("  Press CTRL+C to stop\n");
 
                     let mut interval_timer = tokio_interval(Duration::from_secs(interval));
                     let mut last_snapshot_name: Option<String> = None;
+                    let mut monitor = activity::ActivityMonitor::new(min_edits);
+                    let collector = context::ContextCollector::new()?;
 
                     loop {
-                        interval_timer.tick().await;
-
-                        // Generate snapshot name with timestamp
-                        let timestamp = Local::now().format("%Y-%m-%d-%H%M%S");
-                        let snapshot_name = format!("{}-{}", prefix, timestamp);
-
-                        // Determine parent if incremental
-                        let parent = if incremental {
-                            last_snapshot_name.clone()
-                        } else {
-                            None
-                        };
-
-                        // Create snapshot
-                        println!("[{}] Creating snapshot: {}", Local::now().format("%H:%M:%S"), snapshot_name);
-
-                        match orchestrator.get_snapshot(&snapshot_name).await {
-                            Ok(_) => {
-                                // Snapshot already exists (unlikely with timestamp)
-                                eprintln!("  Snapshot '{}' already exists, skipping", snapshot_name);
-                                continue;
-                            }
-                            Err(_) => {
-                                // Doesn't exist, proceed to create
+                        // Snapshots listen for Ctrl-C to cancel the LLM call, which
+                        // replaces the default handler; stop cleanly here instead
+                        tokio::select! {
+                            _ = interval_timer.tick() => {}
+                            _ = tokio::signal::ctrl_c() => {
+                                println!("\nSnapshot daemon stopped");
+                                break;
                             }
                         }
+                        // Send the previous tick's spans; the daemon may never exit normally
+                        telemetry::flush().await;
 
-                        // Look up parent ID if specified
-                        let parent_id = if let Some(parent_name) = parent {
-                            match orchestrator.get_snapshot(&parent_name).await {
-                                Ok(parent_snapshot) => Some(parent_snapshot.id),
-                                Err(_) => None,
-                            }
-                        } else {
-                            None
-                        };
-
-                        // Capture and save snapshot
-                        match state_capture.capture_session(snapshot_name.clone(), None, parent_id).await {
-                            Ok((snapshot, report)) => {
-                                if let Err(e) = orchestrator.save_snapshot(&snapshot).await {
-                                    eprintln!("  Failed to save snapshot: {}", e);
-                                    continue;
-                                }
-
-                                // Enforce retention policy
-                                if let Ok(deleted) = orchestrator.enforce_snapshot_retention(&snapshot.session, config.snapshot.retention_limit).await {
-                                    if deleted > 0 {
-                                        println!("  (Cleaned up {} old snapshot{})", deleted, if deleted == 1 { "" } else { "s" });
-                                    }
-                                }
+                        if adaptive {
+                            let Some(cwd) = orchestrator.activity_dir(pane.as_deref()).await? else {
+                                continue;
+                            };
+                            let last_command = collector.recent_commands().ok().and_then(|c| c.last().cloned());
+                            let sample = activity::ActivitySample::collect(&cwd, last_command);
 
-                                println!("  ✓ Snapshot saved: {} tabs, {} panes",
-                                    snapshot.tabs.len(), snapshot.pane_count);
+                            let Some(trigger) = monitor.observe(sample) else {
+                                continue;
+                            };
+                            println!("[{}] Activity: {}", Local::now().format("%H:%M:%S"), trigger);
 
-                                if !report.warnings.is_empty() {
-                                    println!("  ⚠ {} warnings", report.warnings.len());
+                            if let Some(pane) = &pane {
+                                match orchestrator.snapshot(pane, &config.llm, config.privacy.consent_given, &[], &mut |_: &anyhow::Error| false, &mut |_: &str| {}).await {
+                                    Ok(result) => println!("  {} Logged {:?} for '{}': {}", Icon::Success, result.entry_type, pane, result.summary),
+                                    Err(e) => tracing::warn!(pane = %pane, "failed to summarize: {:#}", e),
                                 }
-
-                                // Track for incremental next time
-                                last_snapshot_name = Some(snapshot_name);
-                            }
-                            Err(e) => {
-                                eprintln!("  Failed to create snapshot: {}", e);
                             }
                         }
+
+                        // Link to the previous auto-snapshot if incremental
+                        let parent = if incremental { last_snapshot_name.as_deref() } else { None };
+                        if let Some(name) = auto_snapshot(&mut orchestrator, &state_capture, &prefix, parent, config.snapshot.retention_limit).await {
+                            last_snapshot_name = Some(name);
+                        }
                     }
                 }
             }
         }
-        Command::Migrate(args) => {
-            let result = orchestrator.migrate_keyspace(args.dry_run).await?;
-
-            if args.dry_run {
-                println!("=== DRY RUN (no changes made) ===\n");
+        Command::Template(args) => match args.action {
+            TemplateAction::Save { name, description, session, tab, vars, force } => {
+                let (template, skipped) = orchestrator
+                    .save_template(&name, description, session.as_deref(), tab.as_deref(), &vars, force)
+                    .await?;
+                if format.is_json() {
+                    output::json::print(format, &command_path, json!({ "template": template, "skipped": skipped }))?;
+                    return Ok(());
+                }
+                let panes: usize = template.tabs.iter().map(|tab| tab.panes.len()).sum();
+                println!(
+                    "Saved template '{}' from session '{}': {}, {}",
+                    template.name,
+                    template.source_session,
+                    messages::count(template.tabs.len(), "tab"),
+                    messages::count(panes, "pane")
+                );
+                if !template.variables.is_empty() {
+                    let names: Vec<&str> = template.variables.keys().map(String::as_str).collect();
+                    println!("  Variables: {}", names.join(", "));
+                }
+                if !skipped.is_empty() {
+                    println!("  Skipped (opened without a tab): {}", skipped.join(", "));
+                }
             }
-
-            println!("Migration Summary:");
-            println!("  Total keys found: {}", result.total_keys);
-            println!("  Migrated: {}", result.migrated_count);
-            println!("  Skipped: {}", result.skipped_count);
-            println!("  Errors: {}", result.error_count);
-
-            if !result.would_migrate.is_empty() {
-                println!("\nWould migrate:");
-                for m in &result.would_migrate {
-                    println!("  {}", m);
+            TemplateAction::Apply { name, session, tab, vars, diff } => {
+                let template = orchestrator.get_template(&name).await?;
+                let workspace = template::render(&template, &vars, tab.as_deref(), &std::env::current_dir()?)?;
+                let plan = if diff {
+                    orchestrator.workspace_plan(&workspace, session.as_deref()).await?
+                } else {
+                    orchestrator.apply_workspace(&workspace, session.as_deref()).await?
+                };
+                if format.is_json() {
+                    output::json::print(format, &command_path, json!({ "dry_run": diff, "plan": plan }))?;
+                } else {
+                    print_workspace_plan(&plan, diff);
                 }
             }
-
-            if !result.migrated.is_empty() {
-                println!("\nMigrated:");
-                for m in &result.migrated {
-                    println!("  {}", m);
+            TemplateAction::List => {
+                let templates = orchestrator.list_templates().await?;
+                if format.is_json() {
+                    output::json::print(format, &command_path, json!({ "templates": templates }))?;
+                } else if templates.is_empty() {
+                    println!("No templates found.");
+                } else {
+                    println!("Templates:");
+                    for template in templates {
+                        let panes: usize = template.tabs.iter().map(|tab| tab.panes.len()).sum();
+                        println!("\n  {}", template.name);
+                        println!("    From: {} ({})", template.source_session, template.created_at.format("%Y-%m-%d %H:%M:%S"));
+                        println!("    Tabs: {} | Panes: {}", template.tabs.len(), panes);
+                        if !template.variables.is_empty() {
+                            let names: Vec<&str> = template.variables.keys().map(String::as_str).collect();
+                            println!("    Variables: {}", names.join(", "));
+                        }
+                        if let Some(desc) = &template.description {
+                            println!("    Description: {}", desc);
+                        }
+                    }
                 }
             }
-
-            if !result.skipped.is_empty() {
-                println!("\nSkipped:");
-                for s in &result.skipped {
-                    println!("  {}", s);
+            TemplateAction::Show { name } => {
+                let template = orchestrator.get_template(&name).await?;
+                if format.is_json() {
+                    output::json::print(format, &command_path, &template)?;
+                    return Ok(());
+                }
+                println!("Template: {}", template.name);
+                println!("  From: {} ({})", template.source_session, template.created_at.format("%Y-%m-%d %H:%M:%S"));
+                if let Some(desc) = &template.description {
+                    println!("  Description: {}", desc);
+                }
+                if !template.variables.is_empty() {
+                    println!("\n  Variables:");
+                    for (var, default) in &template.variables {
+                        println!("    {} (default: {})", var, default);
+                    }
+                }
+                println!("\n  Tabs ({}):", template.tabs.len());
+                for tab in &template.tabs {
+                    println!("    {} ({})", tab.name, messages::count(tab.panes.len(), "pane"));
+                    for pane in &tab.panes {
+                        println!("      - {}", pane.name);
+                        if let Some(cwd) = &pane.cwd {
+                            println!("        CWD: {}", cwd);
+                        }
+                        if let Some(command) = &pane.command {
+                            println!("        Run: {}", command);
+                        }
+                    }
                 }
             }
-
-            if !result.errors.is_empty() {
-                println!("\nErrors:");
-                for e in &result.errors {
-                    eprintln!("  {}", e);
+            TemplateAction::Delete { name } => {
+                orchestrator.delete_template(&name).await?;
+                let trashed = config.storage.trash_ttl_days > 0;
+                if format.is_json() {
+                    output::json::print(format, &command_path, json!({ "template": name, "trashed": trashed }))?;
+                } else if trashed {
+                    println!("Template '{}' moved to trash (restore with 'zdrive trash list').", name);
+                } else {
+                    println!("Template '{}' deleted.", name);
                 }
-                std::process::exit(1);
             }
-        }
-    }
+        },
+        Command::Takeover(args) => {
+            let content = std::fs::read_to_string(&args.bundle)
+                .map_err(|e| anyhow!("failed to read handoff bundle '{}': {}", args.bundle, e))?;
+            let mut bundle = handoff::parse(&content)?;
+            if args.goal.is_some() {
+                // A different goal starts fresh; imported entries don't count toward it
+                bundle.goal = args.goal;
+                bundle.goal_id = None;
+                bundle.goal_set_at = None;
+            }
+
+            let result = orchestrator
+                .takeover(args.pane, args.tab, args.session, bundle)
+                .await?;
+            if format.is_json() {
+                output::json::print(format, &command_path, &result)?;
+                return Ok(());
+            }
+
+            match &result.handoff_from {
+                Some(from) => println!("Took over '{}' from '{}'", result.pane_name, from),
+                None => println!("Took over '{}'", result.pane_name),
+            }
+            println!("  Imported: {}", messages::count(result.entries_imported, "entry"));
+            if let Some(goal) = &result.goal {
+                println!("  Goal: {}", goal);
+            }
+        }
+        Command::Log { .. } => unreachable!("rewritten to pane log above"),
+        Command::Here => {
+            let (pane, inference) = orchestrator.infer_current_pane().await?;
+            let record = orchestrator.get_pane_record(&pane).await?;
+            let last_intent = orchestrator.get_history(&pane, Some(1)).await?.into_iter().next();
+            if format.is_json() {
+                let body = json!({
+                    "pane": pane,
+                    "inferred_from": inference,
+                    "tracked": record.is_some(),
+                    "session": record.as_ref().map(|r| &r.session),
+                    "tab": record.as_ref().map(|r| &r.tab),
+                    "last_intent": last_intent,
+                });
+                output::json::print(format, &command_path, body)?;
+                return Ok(());
+            }
+            match &record {
+                Some(record) => println!("{}  ({} · {})", pane, record.tab, record.session),
+                None => println!("{}  (not tracked)", pane),
+            }
+            println!("  via {}", inference.describe());
+            if let Some(entry) = last_intent {
+                println!("  last: {}", entry.summary);
+            }
+        }
+        Command::Quicklog { pane } => {
+            let pane = match pane {
+                Some(pane) => pane,
+                None => orchestrator.current_pane().await?,
+            };
+
+            let tags = quicklog::collect_tags(&orchestrator.get_history(&pane, None).await?);
+            let Some(input) = quicklog::prompt(&pane, tags)? else {
+                println!("Nothing logged.");
+                return Ok(());
+            };
+
+            let resolved_artifacts: Vec<String> = input
+                .artifacts
+                .into_iter()
+                .map(|p| {
+                    std::fs::canonicalize(&p)
+                        .map(|abs| abs.to_string_lossy().to_string())
+                        .unwrap_or(p)
+                })
+                .collect();
+
+            let entry = IntentEntry::new(&input.summary)
+                .with_type(input.entry_type)
+                .with_artifacts(resolved_artifacts);
+            orchestrator.log_intent(&pane, &entry).await?;
+            println!(
+                "Logged {} for '{}': {}",
+                entry.entry_type_str().to_lowercase(),
+                pane,
+                input.summary
+            );
+        }
+        Command::Keybind(args) => match args.action {
+            KeybindAction::Install { key, config: config_path, print } => {
+                if print {
+                    if format.is_json() {
+                        output::json::print(format, &command_path, json!({ "key": key, "snippet": keybind::snippet(&key) }))?;
+                    } else {
+                        print!("{}", keybind::snippet(&key));
+                    }
+                    return Ok(());
+                }
+
+                let path = config_path
+                    .map(std::path::PathBuf::from)
+                    .unwrap_or_else(keybind::zellij_config_path);
+                let outcome = keybind::install(&path, &key)?;
+                if format.is_json() {
+                    let updated = matches!(outcome, keybind::InstallOutcome::Updated);
+                    output::json::print(format, &command_path, json!({ "key": key, "path": path, "updated": updated }))?;
+                    return Ok(());
+                }
+                match outcome {
+                    keybind::InstallOutcome::Installed => {
+                        println!("Installed quicklog keybinding '{}' in {}", key, path.display());
+                    }
+                    keybind::InstallOutcome::Updated => {
+                        println!("Updated quicklog keybinding to '{}' in {}", key, path.display());
+                    }
+                }
+                println!("Restart Zellij or reload its config for the binding to take effect.");
+            }
+        },
+        Command::Hook(args) => match args.action {
+            HookAction::Install { shell, rc, print } => {
+                let shell = shell
+                    .or_else(hook::Shell::detect)
+                    .ok_or_else(|| anyhow!("couldn't detect a supported shell from $SHELL; pass --shell zsh|bash|fish"))?;
+                if print {
+                    if format.is_json() {
+                        output::json::print(format, &command_path, json!({ "shell": shell.name(), "snippet": hook::snippet(shell) }))?;
+                    } else {
+                        print!("{}", hook::snippet(shell));
+                    }
+                    return Ok(());
+                }
+
+                let path = rc.map(std::path::PathBuf::from).unwrap_or_else(|| shell.rc_path());
+                let outcome = hook::install(&path, shell)?;
+                if format.is_json() {
+                    let updated = matches!(outcome, keybind::InstallOutcome::Updated);
+                    output::json::print(format, &command_path, json!({ "shell": shell.name(), "path": path, "updated": updated }))?;
+                    return Ok(());
+                }
+                match outcome {
+                    keybind::InstallOutcome::Installed => println!("Installed {} hook in {}", shell.name(), path.display()),
+                    keybind::InstallOutcome::Updated => println!("Updated {} hook in {}", shell.name(), path.display()),
+                }
+                println!(
+                    "Open a new shell to start logging checkpoints every {} commands or {} minutes.",
+                    config.hooks.checkpoint_commands, config.hooks.checkpoint_minutes
+                );
+            }
+            HookAction::InstallGit { repo, print } => {
+                if print {
+                    if format.is_json() {
+                        output::json::print(format, &command_path, json!({ "snippet": hook::git_snippet() }))?;
+                    } else {
+                        print!("{}", hook::git_snippet());
+                    }
+                    return Ok(());
+                }
+
+                let repo = match repo {
+                    Some(repo) => repo,
+                    None => std::env::current_dir()?,
+                };
+                let path = hook::git_hook_path(&repo)?;
+                let outcome = hook::install_git(&path)?;
+                if format.is_json() {
+                    let updated = matches!(outcome, keybind::InstallOutcome::Updated);
+                    output::json::print(format, &command_path, json!({ "path": path, "updated": updated }))?;
+                    return Ok(());
+                }
+                match outcome {
+                    keybind::InstallOutcome::Installed => println!("Installed post-commit hook in {}", path.display()),
+                    keybind::InstallOutcome::Updated => println!("Updated post-commit hook in {}", path.display()),
+                }
+                println!("Commits will be logged to the pane working in this repository.");
+            }
+            HookAction::Commit => {
+                // Runs after every commit, so failures are only logged
+                let cwd = std::env::current_dir()?;
+                match orchestrator.log_commit(&cwd).await {
+                    Ok(Some((pane, entry))) => tracing::debug!(pane = %pane, "logged commit: {}", entry.summary),
+                    Ok(None) => tracing::debug!("no tracked pane is working in {}", cwd.display()),
+                    Err(e) => tracing::debug!("git hook skipped: {:#}", e),
+                }
+            }
+        },
+        Command::HookTick => {
+            // Runs after every shell command, so failures are only logged
+            let cwd = std::env::current_dir().ok();
+            match orchestrator.record_shell_command(&config.hooks, cwd.as_deref()).await {
+                Ok(Some((pane, entry))) => tracing::debug!(pane = %pane, "logged checkpoint: {}", entry.summary),
+                Ok(None) => {}
+                Err(e) => tracing::debug!("shell hook skipped: {:#}", e),
+            }
+        }
+        Command::State(args) => match args.action {
+            StateAction::Gc { dry_run } if format.is_json() => {
+                // No policy removes nothing, which is still a valid report
+                let result = if config.retention.is_empty() {
+                    orchestrator::GcResult::default()
+                } else {
+                    orchestrator.gc(&config.retention, dry_run).await?
+                };
+                let pruned: Vec<_> = result.pruned.iter().map(|(pane, removed)| json!({ "pane": pane, "removed": removed })).collect();
+                let body = json!({
+                    "dry_run": dry_run,
+                    "panes_scanned": result.panes_scanned,
+                    "entries_removed": result.entries_removed,
+                    "pruned": pruned,
+                });
+                output::json::print(format, &command_path, body)?;
+            }
+            StateAction::Gc { dry_run } => {
+                if config.retention.is_empty() {
+                    println!("No retention policy configured; nothing to remove.");
+                    println!("Set one with: zdrive config set retention.exploration 30d");
+                    return Ok(());
+                }
+
+                let result = orchestrator.gc(&config.retention, dry_run).await?;
+                if dry_run {
+                    println!("=== DRY RUN (no changes made) ===\n");
+                }
+                for (pane, removed) in &result.pruned {
+                    println!("  {}: {}", pane, messages::count(*removed, "entry"));
+                }
+                println!(
+                    "{} {} across {}",
+                    if dry_run { "Would remove" } else { "Removed" },
+                    messages::count(result.entries_removed, "expired entry"),
+                    messages::count(result.panes_scanned, "pane")
+                );
+            }
+            StateAction::Stats { top, sample } => {
+                let stats = orchestrator.keyspace_stats(sample, top).await?;
+                match format {
+                    OutputFormat::Json | OutputFormat::JsonCompact => output::json::print(format, &command_path, &stats)?,
+                    _ => println!("{}", OutputFormatter::new().format_keyspace_stats(&stats)),
+                }
+            }
+            StateAction::Rekey { old_key_env, new_key_env, batch, restart } => {
+                if old_key_env == new_key_env {
+                    return Err(anyhow::anyhow!("--old-key-env and --new-key-env name the same variable; nothing to rotate"));
+                }
+                let old_key = crypto::Cipher::from_env(&old_key_env)?;
+                let new_key = crypto::Cipher::from_env(&new_key_env)?;
+                if restart {
+                    orchestrator.clear_rekey_cursor().await?;
+                } else if orchestrator.rekey_cursor().await?.is_some() {
+                    eprintln!("Resuming an interrupted rekey (pass --restart to start over)");
+                }
+
+                // Progress goes to stderr so a JSON report stays parseable
+                let mut total = state::RekeyBatch::default();
+                let mut batches = 0;
+                while !total.done {
+                    let result = orchestrator.rekey_batch(&old_key, &new_key, batch.max(1)).await?;
+                    total.merge(&result);
+                    batches += 1;
+                    if result.rekeyed > 0 || total.done {
+                        eprintln!(
+                            "  batch {}: {} re-encrypted ({} so far, {} scanned)",
+                            batches,
+                            messages::count(result.rekeyed, "value"),
+                            total.rekeyed,
+                            messages::count(total.keys, "key")
+                        );
+                    }
+                }
+
+                if format.is_json() {
+                    output::json::print(format, &command_path, &total)?;
+                } else {
+                    println!(
+                        "Re-encrypted {} across {}",
+                        messages::count(total.rekeyed, "value"),
+                        messages::count(total.keys, "key")
+                    );
+                    if total.current > 0 {
+                        println!("  {} already under the new key", messages::count(total.current, "value"));
+                    }
+                    if total.unreadable > 0 {
+                        println!("  {} neither key could decrypt (left as they are)", messages::count(total.unreadable, "value"));
+                    }
+                    if total.changed > 0 {
+                        println!("  {} changed while being moved; run the command again to finish", messages::count(total.changed, "value"));
+                    }
+                    println!("Now point Perth at the new key: zdrive config set storage.encryption_key_env {}", new_key_env);
+                }
+            }
+        },
+        #[cfg(not(feature = "events"))]
+        Command::Listen { .. } => return Err(feature_missing("listen", "events")),
+        #[cfg(feature = "events")]
+        Command::Listen { metrics_addr } => {
+            if let Some(addr) = metrics_addr {
+                metrics::serve(addr).await?;
+            }
+            run_listener(&mut orchestrator, &config).await?
+        }
+        Command::Watch { interval, snapshot_interval, prefix, plugin, metrics_addr } => {
+            #[cfg(not(feature = "snapshots"))]
+            if snapshot_interval.is_some() {
+                return Err(feature_missing("watch --snapshot-interval", "snapshots"));
+            }
+            if let Some(addr) = metrics_addr {
+                metrics::serve(addr).await?;
+            }
+            run_watch(&mut orchestrator, &config, interval, snapshot_interval, &prefix, plugin.as_deref()).await?
+        }
+        Command::Serve { listen } => serve::run(orchestrator, config.clone(), listen).await?,
+        Command::Digest { since, until, summary } => {
+            let until = until.unwrap_or_else(chrono::Utc::now);
+            let llm = summary.then_some((&config.llm, config.privacy.consent_given));
+            let digest = orchestrator.digest(since, until, llm).await?;
+            let formatter = OutputFormatter::new();
+            match format {
+                OutputFormat::Json | OutputFormat::JsonCompact => output::json::print(format, &command_path, &digest)?,
+                OutputFormat::Markdown => println!("{}", formatter.format_digest_markdown(&digest)),
+                OutputFormat::Csv => {
+                    println!("{}", output::records::CSV_HEADER);
+                    for pane in digest.days.iter().flat_map(|day| &day.panes) {
+                        for entry in &pane.entries {
+                            println!("{}", output::records::csv_row(&pane.pane_name, entry));
+                        }
+                    }
+                }
+                OutputFormat::Jsonl => {
+                    for pane in digest.days.iter().flat_map(|day| &day.panes) {
+                        for entry in &pane.entries {
+                            println!("{}", output::records::jsonl_line(&pane.pane_name, entry)?);
+                        }
+                    }
+                }
+                OutputFormat::Text | OutputFormat::Context | OutputFormat::Html => {
+                    println!("{}", formatter.format_digest(&digest))
+                }
+            }
+        }
+        Command::Tags { pane } => {
+            let panes = orchestrator.tag_counts(pane.as_deref()).await?;
+            if format.is_json() {
+                output::json::print(format, &command_path, json!({ "panes": panes }))?;
+                return Ok(());
+            }
+            if panes.is_empty() {
+                println!("No tagged entries");
+            }
+            for (i, pane) in panes.iter().enumerate() {
+                if i > 0 {
+                    println!();
+                }
+                println!("{}", pane.pane);
+                let width = pane.tags.iter().map(|t| t.tag.len()).max().unwrap_or(0);
+                for tag in &pane.tags {
+                    println!("  {:<width$}  {}", tag.tag, tag.count, width = width);
+                }
+            }
+        }
+        Command::Goal(args) => match args.action {
+            GoalAction::Set { name, title } => {
+                let (goal, replaced) = orchestrator.set_goal(&name, &title).await?;
+                if format.is_json() {
+                    output::json::print(format, &command_path, json!({ "pane": name, "goal": goal, "abandoned": replaced }))?;
+                    return Ok(());
+                }
+                println!("Set goal for '{}': {}", name, goal.title);
+                if let Some(replaced) = replaced {
+                    println!("  Abandoned previous goal: {}", replaced.title);
+                }
+            }
+            GoalAction::Show { name } => {
+                let progress = orchestrator.goal_progress(&name).await?;
+                if format.is_json() {
+                    output::json::print(format, &command_path, json!({ "pane": name, "goal": progress }))?;
+                    return Ok(());
+                }
+                let Some(progress) = progress else {
+                    println!("No active goal for '{}'", name);
+                    return Ok(());
+                };
+                println!("Goal for '{}': {}", name, progress.goal.title);
+                println!("  Set: {}", progress.goal.set_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"));
+                if progress.entries.is_empty() {
+                    println!("  No progress logged yet (use 'zdrive pane log {} ... --goal-delta')", name);
+                } else {
+                    println!("  Progress:");
+                    for entry in &progress.entries {
+                        let delta = entry.goal_delta.as_deref().unwrap_or(&entry.summary);
+                        println!("    - {} {}", entry.timestamp.with_timezone(&chrono::Local).format("%m-%d %H:%M"), delta);
+                    }
+                }
+            }
+            GoalAction::Complete { name, note } => {
+                let (goal, entry) = orchestrator.complete_goal(&name, note).await?;
+                if format.is_json() {
+                    output::json::print(format, &command_path, json!({ "pane": name, "goal": goal, "entry": entry }))?;
+                    return Ok(());
+                }
+                println!("Completed goal for '{}': {}", name, goal.title);
+                println!("{}", messages::logged_entry(entry.entry_type_str(), &name, "", &entry.summary, 0));
+            }
+            GoalAction::List { name } => {
+                let goals = orchestrator.goals(&name).await?;
+                if format.is_json() {
+                    output::json::print(format, &command_path, json!({ "pane": name, "goals": goals }))?;
+                    return Ok(());
+                }
+                if goals.is_empty() {
+                    println!("No goals for '{}'", name);
+                }
+                for goal in &goals {
+                    let status = match goal.status {
+                        types::GoalStatus::Active => "active",
+                        types::GoalStatus::Completed => "completed",
+                        types::GoalStatus::Abandoned => "abandoned",
+                    };
+                    println!("{:<9}  {}  {}", status, goal.set_at.with_timezone(&chrono::Local).format("%Y-%m-%d"), goal.title);
+                }
+            }
+        },
+        Command::Artifacts(args) => match args.action {
+            ArtifactsAction::WhoTouched { path, last } => {
+                let touches = orchestrator.who_touched(&artifact_lookup_keys(&path), last).await?;
+                if format.is_json() {
+                    output::json::print(format, &command_path, json!({ "artifact": path, "touches": touches }))?;
+                    return Ok(());
+                }
+                if touches.is_empty() {
+                    println!("No logged entries touched '{}'", path);
+                    println!("  Entries logged before the artifact index existed: zdrive artifacts reindex");
+                    return Ok(());
+                }
+                for touch in &touches {
+                    let place = match (&touch.session, &touch.tab) {
+                        (Some(session), Some(tab)) => format!(" ({} / {})", session, tab),
+                        _ => String::new(),
+                    };
+                    println!(
+                        "{}  {}{}  [{}] {}  {}",
+                        touch.entry.timestamp.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+                        touch.pane,
+                        place,
+                        touch.entry.entry_type_str(),
+                        touch.entry.summary,
+                        touch.entry.short_id()
+                    );
+                }
+            }
+            ArtifactsAction::Reindex => {
+                let indexed = orchestrator.reindex_artifacts().await?;
+                if format.is_json() {
+                    output::json::print(format, &command_path, json!({ "indexed": indexed }))?;
+                } else {
+                    println!("Indexed {}", messages::count(indexed, "artifact reference"));
+                }
+            }
+        },
+        Command::Report(args) => match args.action {
+            ReportAction::Time { by, week, since, until, gap, csv } => {
+                if gap <= chrono::Duration::zero() {
+                    return Err(anyhow!("--gap must be positive"));
+                }
+                let since = if week { report::week_start() } else { since };
+                let until = until.unwrap_or_else(chrono::Utc::now);
+                let report = orchestrator.time_report(since, until, by, gap).await?;
+                if csv {
+                    print!("{}", report.to_csv());
+                } else if format.is_json() {
+                    output::json::print(format, &command_path, &report)?;
+                } else {
+                    println!("{}", report.to_table());
+                }
+            }
+        },
+        Command::Export(args) => match args.action {
+            ExportAction::Html { out, pane, include_archived } => {
+                let histories = orchestrator.export_histories(pane.as_deref(), include_archived).await?;
+                if histories.is_empty() {
+                    return Err(anyhow!("no history to export"));
+                }
+                std::fs::create_dir_all(&out).map_err(|e| anyhow!("failed to create {}: {}", out.display(), e))?;
+
+                let file_names = output::pane_file_names(histories.iter().map(|h| h.pane.as_str()), "html");
+                let mut index = Vec::new();
+                for (history, file_name) in histories.into_iter().zip(file_names) {
+                    let formatter = OutputFormatter::new().with_annotations(history.annotations);
+                    let path = out.join(&file_name);
+                    std::fs::write(&path, formatter.format_html(&history.entries, &history.pane))
+                        .map_err(|e| anyhow!("failed to write {}: {}", path.display(), e))?;
+                    index.push((history.pane, file_name, history.entries.len()));
+                }
+                let index_path = out.join("index.html");
+                std::fs::write(&index_path, output::html::index_page(&index))
+                    .map_err(|e| anyhow!("failed to write {}: {}", index_path.display(), e))?;
+
+                if format.is_json() {
+                    let pages: Vec<_> =
+                        index.iter().map(|(pane, file, entries)| json!({ "pane": pane, "file": file, "entries": entries })).collect();
+                    output::json::print(format, &command_path, json!({ "index": index_path, "pages": pages }))?;
+                } else {
+                    println!("Exported {} to {}", messages::count(index.len(), "pane"), index_path.display());
+                }
+            }
+        },
+        Command::Sync(args) => match args.action {
+            SyncAction::Obsidian { vault, folder, include_archived } => {
+                if !vault.is_dir() {
+                    return Err(anyhow!("vault '{}' is not a directory", vault.display()));
+                }
+                let notes = orchestrator.obsidian_notes(include_archived).await?;
+                let report = obsidian::sync(&vault.join(folder), notes)?;
+                if format.is_json() {
+                    output::json::print(format, &command_path, &report)?;
+                } else {
+                    println!(
+                        "Synced {} to {} ({} unchanged)",
+                        messages::count(report.written.len(), "note"),
+                        report.dir.display(),
+                        report.unchanged.len()
+                    );
+                }
+            }
+        },
+        Command::Stats { pane, since, until, top } => {
+            let until = until.unwrap_or_else(chrono::Utc::now);
+            let stats = orchestrator.activity_stats(pane.as_deref(), since, until, top).await?;
+            if format.is_json() {
+                output::json::print(format, &command_path, &stats)?;
+            } else {
+                println!("{}", stats.to_dashboard(messages::unicode()));
+            }
+        }
+        Command::Ask { question, pane, since, limit } => {
+            let question = question.join(" ");
+            let mut stdout = std::io::stdout();
+            // JSON output waits for the whole answer instead of streaming it
+            let mut print_chunk = |text: &str| {
+                if !format.is_json() {
+                    print!("{}", text);
+                    let _ = stdout.flush();
+                }
+            };
+            let options = ask::AskOptions { pane, since, limit };
+            let result = orchestrator
+                .ask(&question, &options, &config.llm, config.privacy.consent_given, &mut print_chunk)
+                .await?;
+            if format.is_json() {
+                let sources: Vec<_> = result
+                    .sources
+                    .iter()
+                    .map(|source| json!({ "pane": source.pane_name, "entry": source.entry }))
+                    .collect();
+                let body = json!({
+                    "question": question,
+                    "answer": result.completion.text,
+                    "sources": sources,
+                    "cost_usd": result.cost_usd,
+                });
+                output::json::print(format, &command_path, body)?;
+                return Ok(());
+            }
+            println!();
+
+            let panes: std::collections::BTreeSet<_> = result.sources.iter().map(|s| s.pane_name.as_str()).collect();
+            println!();
+            println!(
+                "Based on {} from {}",
+                messages::count(result.sources.len(), "entry"),
+                messages::count(panes.len(), "pane")
+            );
+            if let Some(cost) = result.cost_usd {
+                println!("Estimated cost: ${:.4}", cost);
+            }
+        }
+        Command::Filter(_) => unreachable!("filter commands run before connecting to Redis"),
+        Command::Schema { .. } => unreachable!("schema runs before connecting to Redis"),
+        Command::Events(args) => match args.action {
+            EventsAction::Flush => {
+                match orchestrator.flush_events().await? {
+                    sent if format.is_json() => output::json::print(format, &command_path, json!({ "published": sent }))?,
+                    0 => println!("No queued events"),
+                    sent => println!("Published {}", messages::count(sent, "queued event")),
+                }
+            }
+            EventsAction::Tail { .. } => unreachable!("events tail runs before connecting to Redis"),
+        },
+        Command::Llm(args) => match args.action {
+            LlmAction::Usage { month } => {
+                let mut usage = orchestrator.llm_usage(month.as_deref()).await?;
+                if month.is_none() {
+                    usage = total_llm_usage(usage);
+                }
+                match format {
+                    OutputFormat::Json | OutputFormat::JsonCompact => {
+                        output::json::print(format, &command_path, json!({ "month": month, "usage": usage }))?
+                    }
+                    _ => {
+                        let period = month.as_deref().unwrap_or("all time");
+                        println!("{}", OutputFormatter::new().format_llm_usage(&usage, period));
+                    }
+                }
+            }
+        },
+        Command::Describe { tab, session, output } => {
+            let session = session
+                .or_else(|| std::env::var("ZELLIJ_SESSION_NAME").ok())
+                .ok_or_else(|| PerthError::ZellijUnavailable("not inside a zellij session; pass --session".to_string()))?;
+            let tabs = orchestrator.describe_workspace(&session, tab.as_deref()).await?;
+            let document = describe::render(&session, &tabs, chrono::Utc::now());
+
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, document)?;
+                    println!("Wrote workspace description to {}", path.display());
+                }
+                None => print!("{}", document),
+            }
+        }
+        Command::Capabilities => {
+            let report = capabilities::probe(&config, &zellij).await;
+            match format {
+                OutputFormat::Json | OutputFormat::JsonCompact => output::json::print(format, &command_path, &report)?,
+                _ => println!("{}", report.display()),
+            }
+        }
+        Command::Plumbing(args) => {
+            let response = match run_plumbing(&mut orchestrator, args.action).await {
+                Ok(body) => plumbing::envelope(body),
+                Err(err) => {
+                    println!("{}", plumbing::error(&err));
+                    std::process::exit(error::exit_code(&err));
+                }
+            };
+            println!("{}", response);
+        }
+        Command::Trash(args) => match args.action {
+            TrashAction::List => {
+                let items = orchestrator.list_trash().await?;
+                if format.is_json() {
+                    output::json::print(format, &command_path, json!({ "items": items }))?;
+                    return Ok(());
+                }
+                if items.is_empty() {
+                    println!("Trash is empty.");
+                    return Ok(());
+                }
+                for item in &items {
+                    println!(
+                        "{}  {:<8} {}  (deleted {})",
+                        item.id,
+                        item.kind,
+                        item.name,
+                        item.deleted_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+                    );
+                }
+            }
+            TrashAction::Restore { id } => {
+                let item = orchestrator.restore_trash(&id).await?;
+                if format.is_json() {
+                    output::json::print(format, &command_path, json!({ "restored": item }))?;
+                } else {
+                    println!("Restored {} '{}'.", item.kind, item.name);
+                }
+            }
+            TrashAction::Empty { id } => {
+                let purged = orchestrator.empty_trash(id.as_deref()).await?;
+                if format.is_json() {
+                    output::json::print(format, &command_path, json!({ "purged": purged }))?;
+                } else {
+                    println!("Permanently deleted {}.", messages::count(purged, "trashed item"));
+                }
+            }
+        },
+        Command::Migrate(args) => {
+            let on_conflict = args.on_conflict;
+            let result = orchestrator
+                .migrate_keyspace(args.dry_run, |conflict| match on_conflict {
+                    OnConflict::KeepOld => Ok(Some(conflict.old.clone())),
+                    OnConflict::KeepNew => Ok(None),
+                    OnConflict::Merge => Ok(Some(conflict.merged())),
+                    OnConflict::Prompt => prompt_conflict(conflict),
+                })
+                .await?;
+
+            if format.is_json() {
+                let mut body = serde_json::to_value(&result)?;
+                body["dry_run"] = json!(args.dry_run);
+                output::json::print(format, &command_path, body)?;
+                if !result.errors.is_empty() {
+                    std::process::exit(1);
+                }
+                return Ok(());
+            }
+
+            if args.dry_run {
+                println!("=== DRY RUN (no changes made) ===\n");
+            }
+
+            println!("Migration Summary:");
+            println!("  Total keys found: {}", result.total_keys);
+            println!("  Migrated: {}", result.migrated_count);
+            println!("  Skipped: {}", result.skipped_count);
+            println!("  Resolved conflicts: {}", result.resolved_count);
+            println!("  Errors: {}", result.error_count);
+
+            if !result.would_migrate.is_empty() {
+                println!("\nWould migrate:");
+                for m in &result.would_migrate {
+                    println!("  {}", m);
+                }
+            }
+
+            if !result.migrated.is_empty() {
+                println!("\nMigrated:");
+                for m in &result.migrated {
+                    println!("  {}", m);
+                }
+            }
+
+            if !result.skipped.is_empty() {
+                println!("\nSkipped:");
+                for s in &result.skipped {
+                    println!("  {}", s);
+                }
+            }
+
+            if !result.resolved.is_empty() {
+                println!("\nResolved ({:?}):", args.on_conflict);
+                for r in &result.resolved {
+                    println!("  {}", r);
+                }
+            }
+
+            if !result.conflicts.is_empty() {
+                println!("\nConflicts (use --on-conflict to resolve):");
+                for c in &result.conflicts {
+                    println!("  {}", c);
+                }
+            }
+
+            if !result.errors.is_empty() {
+                println!("\nErrors:");
+                for e in &result.errors {
+                    eprintln!("  {}", e);
+                }
+                std::process::exit(1);
+            }
+        }
+    }
 
     Ok(())
 }
 
+/// Error for a command this build can't run because `feature` was left out.
+#[cfg(not(all(feature = "events", feature = "snapshots")))]
+fn feature_missing(command: &str, feature: &str) -> anyhow::Error {
+    anyhow!("`zdrive {}` is not available: zdrive was built without the `{}` feature", command, feature)
+}
+
 /// Determines if a command needs Zellij version check.
 /// Commands that only interact with Redis don't need Zellij.
+async fn run_plumbing(orchestrator: &mut Orchestrator, action: PlumbingAction) -> Result<serde_json::Value> {
+    use serde_json::json;
+
+    match action {
+        PlumbingAction::GetPane { name } => {
+            let record = orchestrator.get_pane_record(&name).await?;
+            Ok(json!({ "pane": record.as_ref().map(plumbing::pane_json) }))
+        }
+        PlumbingAction::PutEntry { pane, json } => {
+            let input = plumbing::read_json_input(json.as_deref())?;
+            let entry = plumbing::entry_from_json(input)?;
+            orchestrator.log_intent(&pane, &entry).await?;
+            Ok(json!({ "pane": pane, "entry": entry }))
+        }
+        PlumbingAction::ScanKeys { pattern } => {
+            let keys: Vec<_> = orchestrator
+                .scan_keys(&pattern)
+                .await?
+                .into_iter()
+                .map(|(key, key_type)| json!({ "key": key, "type": key_type }))
+                .collect();
+            Ok(json!({ "pattern": pattern, "keys": keys }))
+        }
+        PlumbingAction::RawEvent { event_type, json, correlation_id } => {
+            let payload = plumbing::read_json_input(json.as_deref())?;
+            let published = orchestrator
+                .publish_raw_event(&event_type, payload, correlation_id.as_deref())
+                .await;
+            Ok(json!({ "event_type": event_type, "published": published }))
+        }
+    }
+}
+
+/// Consume completion events until the connection drops, archiving tabs
+/// as their grace periods pass.
+#[cfg(feature = "events")]
+async fn run_listener(orchestrator: &mut Orchestrator, config: &Config) -> Result<()> {
+    use futures_util::StreamExt;
+    use lapin::options::BasicAckOptions;
+    use tokio::time::{interval, Duration};
+    use tracing::Instrument;
+
+    if !config.bloodbank.enabled {
+        return Err(anyhow!("Bloodbank is disabled; enable it with: zdrive config set bloodbank.enabled true"));
+    }
+    let completion_events = &config.bloodbank.completion_events;
+    let command_events = &config.bloodbank.command_events;
+    if completion_events.is_empty() && command_events.is_empty() {
+        return Err(anyhow!(
+            "no events configured; for example: zdrive config set bloodbank.completion_events github.pr.merged"
+        ));
+    }
+    let matches_any = |patterns: &[String], event_type: &str| {
+        patterns.iter().any(|pattern| bloodbank::topic_matches(pattern, event_type))
+    };
+
+    let events: Vec<String> = completion_events.iter().chain(command_events).cloned().collect();
+    let mut consumer = bloodbank::subscribe(&config.bloodbank, &events).await?;
+    println!("Listening on {} for: {}", config.bloodbank.listen_queue(), events.join(", "));
+    println!("  Archive grace: {}", retention::format_max_age(Some(config.bloodbank.archive_grace)));
+    println!("  Press CTRL+C to stop\n");
+
+    let mut archive_timer = interval(Duration::from_secs(30));
+    loop {
+        tokio::select! {
+            delivery = consumer.next() => {
+                let Some(delivery) = delivery else {
+                    return Err(anyhow!("Bloodbank consumer closed"));
+                };
+                let delivery = delivery?;
+                let now = chrono::Local::now().format("%H:%M:%S");
+                // Each event is its own trace, tagged with the event's correlation ID
+                let trace = telemetry::root_span(format!("listen {}", delivery.routing_key));
+                let event = bloodbank::IncomingEvent::parse(delivery.routing_key.as_str(), &delivery.data);
+                let correlation_id = event.as_ref().ok().and_then(|event| event.correlation_id()).map(str::to_string);
+                if let Some(id) = &correlation_id {
+                    telemetry::set_correlation_id(id);
+                }
+                // Diagnostics while handling it carry the same context
+                let span = tracing::info_span!("event", event_type = %delivery.routing_key, correlation_id = correlation_id.as_deref());
+                async {
+                    match event {
+                        Ok(event) if matches_any(command_events, &event.event_type) && event.command().is_some() => {
+                            let request = event.command().expect("checked above");
+                            metrics::inc("perth_commands_total", &[("command", &event.event_type)]);
+                            // A failed command is answered with a .failed event; keep listening
+                            match orchestrator.handle_command(&event, request).await {
+                                Ok(summary) => println!("[{}] {}: {}", now, event.event_type, summary),
+                                Err(e) => tracing::error!("command failed: {:#}", e),
+                            }
+                        }
+                        // The durable queue may keep bindings from an earlier configuration
+                        Ok(event) if !matches_any(completion_events, &event.event_type) => {
+                            tracing::warn!("ignoring event: not a configured completion or command event")
+                        }
+                        Ok(event) => match event.correlation_id() {
+                            Some(id) => {
+                                let result = orchestrator
+                                    .complete_correlation(&event.event_type, id, config.bloodbank.archive_grace)
+                                    .await?;
+                                println!(
+                                    "[{}] {} [{}]: {}, {} logged",
+                                    now,
+                                    event.event_type,
+                                    id,
+                                    messages::count(result.tabs.len(), "tab"),
+                                    messages::count(result.milestones, "milestone")
+                                );
+                            }
+                            None => tracing::warn!("ignoring completion event without a correlation_id"),
+                        },
+                        Err(e) => tracing::warn!("ignoring malformed event: {:#}", e),
+                    }
+                    Ok::<_, anyhow::Error>(())
+                }
+                .instrument(span)
+                .await?;
+                drop(trace);
+                telemetry::flush().await;
+                // Malformed events are acked too, so they aren't redelivered forever
+                delivery.ack(BasicAckOptions::default()).await?;
+            }
+            _ = archive_timer.tick() => {
+                telemetry::flush().await;
+                for tab in orchestrator.archive_due_tabs().await? {
+                    println!(
+                        "[{}] Archived tab '{}' ({})",
+                        chrono::Local::now().format("%H:%M:%S"),
+                        tab.tab_name,
+                        tab.session
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Show a field-level diff of a migration conflict and ask which side to
+/// keep for each differing field. Returns `None` when every choice keeps
+/// the existing v2 value.
+fn prompt_conflict(conflict: &state::KeyConflict) -> Result<Option<std::collections::HashMap<String, String>>> {
+    let show = |value: &Option<String>| value.clone().unwrap_or_else(|| "(unset)".to_string());
+
+    println!("\n{} already exists at {}:", conflict.old_key, conflict.new_key);
+    let mut fields = conflict.new.clone();
+    let mut changed = false;
+    for (name, old, new) in conflict.differing_fields() {
+        println!("  {}", name);
+        println!("    old: {}", show(&old));
+        println!("    new: {}", show(&new));
+        let keep_old = loop {
+            print!("  Keep [o]ld or [n]ew? [n] ");
+            std::io::stdout().flush()?;
+            let mut answer = String::new();
+            if std::io::stdin().read_line(&mut answer)? == 0 {
+                return Err(anyhow!("migration aborted: no answer on stdin"));
+            }
+            match answer.trim().to_lowercase().as_str() {
+                "" | "n" | "new" => break false,
+                "o" | "old" => break true,
+                _ => println!("  Please answer 'o' or 'n'."),
+            }
+        };
+        if keep_old {
+            changed = true;
+            match old {
+                Some(value) => fields.insert(name, value),
+                None => fields.remove(&name),
+            };
+        }
+    }
+    Ok(changed.then_some(fields))
+}
+
+/// Print history `page_size` entries at a time. On a terminal, wait for
+/// Enter between pages and stop on q; otherwise print everything.
+fn page_history(formatter: &OutputFormatter, history: &[IntentEntry], name: &str, page_size: usize) -> Result<()> {
+    use std::io::IsTerminal;
+
+    if history.is_empty() {
+        println!("{}", formatter.format_history(history, name));
+        return Ok(());
+    }
+    let interactive = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+    let pages = history.chunks(page_size).count();
+
+    for (page, entries) in history.chunks(page_size).enumerate() {
+        if page > 0 {
+            println!();
+        }
+        println!("{}", formatter.format_history(entries, name));
+        if !interactive || page + 1 == pages {
+            continue;
+        }
+
+        let shown = (page * page_size + entries.len()).min(history.len());
+        eprint!("-- {}/{} entries, Enter for more, q to quit -- ", shown, history.len());
+        std::io::stderr().flush()?;
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer)? == 0 || answer.trim().eq_ignore_ascii_case("q") {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Print entries as they are logged until Ctrl-C, skipping any already
+/// shown and any not matching the type filter.
+async fn follow_history(
+    updates: impl futures_util::Stream<Item = Result<IntentEntry>>,
+    name: &str,
+    entry_type: Option<types::IntentType>,
+    tag: Option<&str>,
+    format: OutputFormat,
+    shown: &std::collections::HashSet<uuid::Uuid>,
+) -> Result<()> {
+    use futures_util::StreamExt;
+
+    let formatter = OutputFormatter::new();
+    if matches!(format, OutputFormat::Text) {
+        eprintln!("Following '{}' (Ctrl-C to stop)", name);
+    }
+
+    let mut updates = std::pin::pin!(updates);
+    loop {
+        tokio::select! {
+            entry = updates.next() => {
+                let Some(entry) = entry else {
+                    return Err(anyhow!("lost connection to Redis while following '{}'", name));
+                };
+                let entry = entry?;
+                if shown.contains(&entry.id)
+                    || entry_type.is_some_and(|t| t != entry.entry_type)
+                    || tag.is_some_and(|tag| !entry.has_tag(tag))
+                {
+                    continue;
+                }
+                match format {
+                    OutputFormat::Text => println!("\n{}", formatter.format_history(std::slice::from_ref(&entry), name)),
+                    OutputFormat::Csv => println!("{}", output::records::csv_row(name, &entry)),
+                    OutputFormat::Jsonl => println!("{}", output::records::jsonl_line(name, &entry)?),
+                    _ => println!("{}", serde_json::to_string(&entry)?),
+                }
+                std::io::stdout().flush()?;
+            }
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
+
+/// Run `zdrive filter` commands, which need neither Redis nor Zellij.
+fn run_filter(action: &FilterAction, format: OutputFormat, command_path: &str, config: &filter::FilterConfig) -> Result<()> {
+    let FilterAction::Check { input } = action;
+    let (source, content) = if input == "-" {
+        let mut content = String::new();
+        std::io::Read::read_to_string(&mut std::io::stdin(), &mut content)?;
+        ("stdin".to_string(), content)
+    } else {
+        let content = std::fs::read(input).map_err(|e| anyhow!("failed to read {}: {}", input, e))?;
+        (input.clone(), String::from_utf8_lossy(&content).into_owned())
+    };
+
+    let filter = filter::SecretFilter::with_config(config)?;
+    let redactions: Vec<filter::Redaction> = content
+        .lines()
+        .enumerate()
+        .flat_map(|(i, line)| filter.filter(line).with_source(&format!("{}:{}", source, i + 1)).redactions)
+        .collect();
+
+    match format {
+        OutputFormat::Json | OutputFormat::JsonCompact => {
+            output::json::print(format, command_path, json!({ "source": source, "redactions": redactions }))?
+        }
+        _ => println!("{}", OutputFormatter::new().format_redactions(&redactions)),
+    }
+    if !redactions.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Print events from the exchange until interrupted. Needs only the broker.
+#[cfg(feature = "events")]
+async fn run_events_tail(patterns: &[String], format: OutputFormat, config: &config::BloodbankConfig) -> Result<()> {
+    use futures_util::StreamExt;
+
+    if !config.enabled {
+        return Err(anyhow!("Bloodbank is disabled; enable it with: zdrive config set bloodbank.enabled true"));
+    }
+    let patterns = if patterns.is_empty() {
+        vec![format!("{}.#", config.routing_key_prefix)]
+    } else {
+        patterns.to_vec()
+    };
+
+    let mut consumer = bloodbank::tail(config, &patterns).await?;
+    // Status goes to stderr so JSON output can be piped
+    eprintln!("Watching {} on {} (CTRL+C to stop)", patterns.join(", "), config.exchange);
+
+    let formatter = OutputFormatter::new();
+    loop {
+        tokio::select! {
+            delivery = consumer.next() => {
+                let Some(delivery) = delivery else {
+                    return Err(anyhow!("Bloodbank consumer closed"));
+                };
+                let delivery = delivery?;
+                match format {
+                    OutputFormat::Json => match serde_json::from_slice::<serde_json::Value>(&delivery.data) {
+                        Ok(envelope) => println!("{}", serde_json::to_string_pretty(&envelope)?),
+                        Err(_) => println!("{}", String::from_utf8_lossy(&delivery.data)),
+                    },
+                    OutputFormat::JsonCompact => println!("{}", String::from_utf8_lossy(&delivery.data)),
+                    _ => match bloodbank::IncomingEvent::parse(delivery.routing_key.as_str(), &delivery.data) {
+                        Ok(event) => println!("{}\n", formatter.format_event(&event, chrono::Local::now())),
+                        Err(e) => eprintln!("Malformed event on {}: {}", delivery.routing_key, e),
+                    },
+                }
+                std::io::stdout().flush()?;
+            }
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
+
+/// Run `zdrive watch`: reconcile every `interval` seconds, reporting panes
+/// that closed or came back, and snapshot the session every
+/// `snapshot_interval` seconds if set.
+async fn run_watch(
+    orchestrator: &mut Orchestrator,
+    #[cfg_attr(not(feature = "snapshots"), allow(unused_variables))] config: &Config,
+    interval: u64,
+    snapshot_interval: Option<u64>,
+    prefix: &str,
+    plugin: Option<&str>,
+) -> Result<()> {
+    use anyhow::Context;
+    use chrono::Local;
+    use std::collections::HashSet;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::time::{interval as tokio_interval, Duration};
+
+    let session = ZellijDriver::new()
+        .active_session_name()
+        .ok_or_else(|| PerthError::ZellijUnavailable("not inside a zellij session; watch must run in the session it watches".to_string()))?;
+    println!("Watching session '{}'", session);
+    println!("  Reconcile: every {} seconds", interval);
+    if let Some(secs) = snapshot_interval {
+        println!("  Snapshots: every {} seconds ({}-*)", secs, prefix);
+    }
+
+    // The plugin's child process is killed when `tracker` is dropped
+    let mut tracker_input = None;
+    let mut tracker = match plugin {
+        Some(plugin) => {
+            let url = tracker::plugin_url(plugin);
+            let mut child = ZellijDriver::new().spawn_tracker(&url)?;
+            let stdout = child.stdout.take().ok_or_else(|| anyhow!("zellij pipe has no stdout"))?;
+            let mut stdin = child.stdin.take().ok_or_else(|| anyhow!("zellij pipe has no stdin"))?;
+            stdin.write_all(b"subscribe\n").await.context("failed to subscribe to the tracker plugin")?;
+            tracker_input = Some(stdin);
+            println!("  Tracking: live, via {}", url);
+            Some((child, BufReader::new(stdout).lines()))
+        }
+        None => None,
+    };
+    let mut manifest = tracker::PaneManifest::default();
+    println!("  Press CTRL+C to stop\n");
+
+    #[cfg(feature = "snapshots")]
+    let state_capture = snapshot::StateCapture::new(ZellijDriver::new());
+    let mut reconcile_timer = tokio_interval(Duration::from_secs(interval));
+    let mut snapshot_timer = snapshot_interval.map(|secs| tokio_interval(Duration::from_secs(secs)));
+    let mut ping_timer = tokio_interval(tracker::PING_INTERVAL);
+    // None until the first reconcile, which reports totals instead of changes
+    let mut stale: Option<HashSet<String>> = None;
+
+    loop {
+        let snapshot_tick = async {
+            match snapshot_timer.as_mut() {
+                Some(timer) => timer.tick().await,
+                None => std::future::pending().await,
+            }
+        };
+        let tracker_line = async {
+            match tracker.as_mut() {
+                Some((_, lines)) => lines.next_line().await,
+                None => std::future::pending().await,
+            }
+        };
+        let mut tracker_stopped = false;
+        tokio::select! {
+            _ = reconcile_timer.tick() => {
+                let result = match orchestrator.reconcile().await {
+                    Ok(result) => result,
+                    Err(e) => {
+                        tracing::warn!("reconcile failed: {:#}", e);
+                        continue;
+                    }
+                };
+                let now = Local::now().format("%H:%M:%S");
+                let current: HashSet<String> = result.stale_panes.iter().cloned().collect();
+                match &stale {
+                    None => println!(
+                        "[{}] {} tracked in this session, {} stale",
+                        now,
+                        messages::count(result.seen + result.stale, "pane"),
+                        result.stale
+                    ),
+                    Some(previous) => {
+                        let mut closed: Vec<&String> = current.difference(previous).collect();
+                        let mut back: Vec<&String> = result.seen_panes.iter().filter(|pane| previous.contains(*pane)).collect();
+                        closed.sort();
+                        back.sort();
+                        for pane in closed {
+                            println!("[{}] {} '{}' closed; marked stale", now, Icon::Warning, pane);
+                        }
+                        for pane in back {
+                            println!("[{}] {} '{}' is back", now, Icon::Success, pane);
+                        }
+                    }
+                }
+                stale = Some(current);
+            }
+            _ = ping_timer.tick() => {
+                // Renews the plugin's lease on this subscription
+                if let Some(stdin) = tracker_input.as_mut() {
+                    if let Err(e) = stdin.write_all(b"ping\n").await {
+                        tracing::debug!("failed to ping the tracker plugin: {}", e);
+                        tracker_input = None;
+                    }
+                }
+            }
+            _ = snapshot_tick => {
+                #[cfg(feature = "snapshots")]
+                auto_snapshot(orchestrator, &state_capture, prefix, None, config.snapshot.retention_limit).await;
+            }
+            line = tracker_line => {
+                match line {
+                    Ok(Some(line)) => match tracker::parse(&line) {
+                        Ok(current) => {
+                            let now = Local::now().format("%H:%M:%S");
+                            for event in tracker::diff(&manifest, &current) {
+                                match orchestrator.apply_tracker_event(&session, &event).await {
+                                    Ok(Some(change)) => print_tracked_change(&now.to_string(), &change),
+                                    Ok(None) => {}
+                                    Err(e) => tracing::warn!("failed to apply pane change: {:#}", e),
+                                }
+                            }
+                            manifest = current;
+                        }
+                        Err(e) => tracing::warn!("{:#}", e),
+                    },
+                    Ok(None) | Err(_) => tracker_stopped = true,
+                }
+            }
+            _ = tokio::signal::ctrl_c() => {
+                println!("\nWatch stopped");
+                return Ok(());
+            }
+        }
+        if tracker_stopped {
+            tracker_input = None;
+            if let Some((child, _)) = tracker.take() {
+                let reason = match child.wait_with_output().await {
+                    Ok(output) => String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                    Err(e) => e.to_string(),
+                };
+                let reason = if reason.is_empty() { String::new() } else { format!(" ({})", reason) };
+                println!("{} Tracker plugin stopped{}; reconciling every {} seconds only", Icon::Warning, reason, interval);
+            }
+        }
+        // Send this tick's spans; watch may never exit normally
+        telemetry::flush().await;
+    }
+}
+
+/// One `zdrive watch` line for a change the tracker plugin reported.
+fn print_tracked_change(now: &str, change: &tracker::TrackedChange) {
+    match change {
+        tracker::TrackedChange::Closed(pane) => println!("[{}] {} '{}' closed; marked stale", now, Icon::Warning, pane),
+        tracker::TrackedChange::Back(pane) => println!("[{}] {} '{}' is back", now, Icon::Success, pane),
+        tracker::TrackedChange::RenamedInZellij { pane, title } => {
+            println!("[{}] '{}' was renamed to '{}' in Zellij; still tracked as '{}'", now, pane, title, pane)
+        }
+    }
+}
+
+/// Capture and save a snapshot named `<prefix>-<timestamp>`, linked to
+/// `parent` if it exists, then apply snapshot retention. Failures are logged
+/// rather than returned so a long-running loop keeps going. Returns the new
+/// snapshot's name.
+#[cfg(feature = "snapshots")]
+async fn auto_snapshot(
+    orchestrator: &mut Orchestrator,
+    state_capture: &snapshot::StateCapture,
+    prefix: &str,
+    parent: Option<&str>,
+    retention_limit: usize,
+) -> Option<String> {
+    use chrono::Local;
+
+    // Generate snapshot name with timestamp
+    let timestamp = Local::now().format("%Y-%m-%d-%H%M%S");
+    let snapshot_name = format!("{}-{}", prefix, timestamp);
+
+    println!("[{}] Creating snapshot: {}", Local::now().format("%H:%M:%S"), snapshot_name);
+
+    if orchestrator.get_snapshot(&snapshot_name).await.is_ok() {
+        // Snapshot already exists (unlikely with timestamp)
+        tracing::warn!(snapshot = %snapshot_name, "snapshot already exists, skipping");
+        return None;
+    }
+
+    // Look up parent ID if specified
+    let parent_id = match parent {
+        Some(parent_name) => orchestrator.get_snapshot(parent_name).await.ok().map(|parent| parent.id),
+        None => None,
+    };
+
+    // Capture and save snapshot
+    let (mut snapshot, report) = match state_capture.capture_session(snapshot_name.clone(), None, parent_id).await {
+        Ok(captured) => captured,
+        Err(e) => {
+            tracing::error!(snapshot = %snapshot_name, "failed to create snapshot: {:#}", e);
+            return None;
+        }
+    };
+    if let Err(e) = orchestrator.save_snapshot(&mut snapshot).await {
+        tracing::error!(snapshot = %snapshot_name, "failed to save snapshot: {:#}", e);
+        return None;
+    }
+
+    // Enforce retention policy
+    if let Ok(deleted) = orchestrator.enforce_snapshot_retention(&snapshot.session, retention_limit).await {
+        if deleted > 0 {
+            println!("  {}", messages::cleaned_up_snapshots(deleted));
+        }
+    }
+
+    println!("  {} Snapshot saved: {}, {}", Icon::Success,
+        messages::count(snapshot.tabs.len(), "tab"), messages::count(snapshot.pane_count, "pane"));
+
+    if !report.warnings.is_empty() {
+        println!("  {} {}", Icon::Warning, messages::count(report.warnings.len(), "warning"));
+    }
+
+    Some(snapshot_name)
+}
+
+/// Print what `zdrive up` or `template apply` did, or would do with `--diff`.
+fn print_workspace_plan(plan: &workspace::WorkspacePlan, diff: bool) {
+    let applied = plan.changes.iter().filter(|change| change.is_applied()).count();
+    if applied == 0 {
+        println!("Session '{}' is up to date with {}", plan.session, plan.source);
+    } else {
+        let verb = if diff { "Would apply" } else { "Applied" };
+        println!("{} {} from {} to session '{}':", verb, messages::count(applied, "change"), plan.source, plan.session);
+    }
+    for change in &plan.changes {
+        println!("  {}", change.describe());
+    }
+}
+
+/// Ask before `reconcile --prune` deletes panes. Prompts on stderr so JSON
+/// output stays clean; without a terminal, `--yes` is required.
+fn confirm_prune(panes: &[types::PaneRecord]) -> Result<bool> {
+    use std::io::IsTerminal;
+
+    if !std::io::stdin().is_terminal() {
+        return Err(anyhow!("refusing to prune without confirmation; pass --yes"));
+    }
+    eprintln!("Stale panes to delete, with their history:");
+    for pane in panes {
+        eprintln!("  {} ({} / {}, last seen {})", pane.pane_name, pane.session, pane.tab, pane.last_seen);
+    }
+    eprint!("Delete {}? [y/N] ", messages::count(panes.len(), "pane"));
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// After opening a pane in a session this terminal isn't attached to,
+/// attach to it: with `--attach`, or if the user agrees at a prompt. From
+/// inside Zellij, which can't switch sessions, or without a terminal, say
+/// how instead.
+async fn offer_attach(
+    zellij: &ZellijDriver,
+    pane_name: &str,
+    location: &orchestrator::PaneLocation,
+    attach: Option<bool>,
+) -> Result<()> {
+    use std::io::IsTerminal;
+
+    if location.session_started {
+        println!("Started session '{}' in the background.", location.session);
+    }
+    let inside_zellij = zellij.active_session_name().is_some();
+    let attach = match attach {
+        _ if inside_zellij => false,
+        Some(attach) => attach,
+        None if !std::io::stdin().is_terminal() => false,
+        None => {
+            eprint!("'{}' is ready in session '{}'. Attach now? [Y/n] ", pane_name, location.session);
+            std::io::stderr().flush()?;
+            let mut answer = String::new();
+            std::io::stdin().read_line(&mut answer)?;
+            matches!(answer.trim().to_lowercase().as_str(), "" | "y" | "yes")
+        }
+    };
+
+    if attach {
+        zellij.attach_session(&location.session).await
+    } else {
+        println!("{}", messages::attach_instructions(pane_name, &location.session, inside_zellij));
+        Ok(())
+    }
+}
+
+/// Ask whether to log a local heuristic summary after the LLM failed.
+/// Only asks on a terminal; elsewhere the failure stands.
+fn offer_heuristic_fallback(error: &anyhow::Error) -> Result<bool> {
+    use std::io::IsTerminal;
+
+    if !std::io::stdin().is_terminal() {
+        return Ok(false);
+    }
+    eprintln!("{} {:#}", Icon::Warning, error);
+    eprint!("Log a local summary (branch, git status, recent commands) instead? [y/N] ");
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Resolve artifact paths (try absolute, fallback to as-is for non-existent)
+fn resolve_artifacts(artifacts: Vec<String>) -> Vec<String> {
+    artifacts
+        .into_iter()
+        .map(|p| {
+            std::fs::canonicalize(&p)
+                .map(|abs| abs.to_string_lossy().to_string())
+                .unwrap_or(p)
+        })
+        .collect()
+}
+
+/// Index keys to look an artifact up under: as given, and resolved the way
+/// `pane log --artifacts` stores paths.
+fn artifact_lookup_keys(path: &str) -> Vec<String> {
+    let mut keys = vec![path.to_string()];
+    let resolved = match std::fs::canonicalize(path) {
+        Ok(abs) => Some(abs.to_string_lossy().to_string()),
+        // A deleted file can still be found under its absolute path
+        Err(_) if std::path::Path::new(path).is_relative() => {
+            std::env::current_dir().ok().map(|cwd| cwd.join(path).to_string_lossy().to_string())
+        }
+        Err(_) => None,
+    };
+    keys.extend(resolved.filter(|resolved| resolved != path));
+    keys
+}
+
+/// Fold monthly usage into all-time totals per provider and model.
+fn total_llm_usage(monthly: Vec<types::LlmUsage>) -> Vec<types::LlmUsage> {
+    let mut totals: std::collections::BTreeMap<(String, String), types::LlmUsage> = Default::default();
+    for usage in monthly {
+        let total = totals
+            .entry((usage.provider.clone(), usage.model.clone()))
+            .or_insert_with(|| types::LlmUsage {
+                provider: usage.provider.clone(),
+                model: usage.model.clone(),
+                ..Default::default()
+            });
+        total.requests += usage.requests;
+        total.input_tokens += usage.input_tokens;
+        total.output_tokens += usage.output_tokens;
+        if let Some(cost) = usage.cost_usd {
+            total.cost_usd = Some(total.cost_usd.unwrap_or(0.0) + cost);
+        }
+    }
+    totals.into_values().collect()
+}
+
+/// Commands that never write to Redis and may be served by a read replica.
+///
+/// Reads that tidy up as they go (`here` records pane IDs, `trash list`,
+/// `tab find` and `artifacts who-touched` repair or drop index entries,
+/// `snapshot show` migrates old pointers) and anything calling an LLM
+/// (which records usage) go to the primary.
+fn reads_only(command: &Command) -> bool {
+    match command {
+        Command::List(_) | Command::Describe { .. } | Command::Tags { .. } | Command::Stats { .. } => true,
+        Command::Digest { summary, .. } => !summary,
+        Command::Pane(args) => matches!(
+            args.action,
+            Some(PaneAction::Info { .. } | PaneAction::History { .. } | PaneAction::Artifacts { remap: None, .. })
+        ),
+        Command::Tab(args) => matches!(args.action, Some(TabAction::Info { .. } | TabAction::List { .. })),
+        Command::Snapshot(args) => matches!(args.action, cli::SnapshotAction::List { .. } | cli::SnapshotAction::Export { .. }),
+        Command::Template(args) => matches!(args.action, TemplateAction::List | TemplateAction::Show { .. }),
+        Command::Goal(args) => matches!(args.action, GoalAction::Show { .. } | GoalAction::List { .. }),
+        Command::Plumbing(args) => matches!(args.action, PlumbingAction::GetPane { .. } | PlumbingAction::ScanKeys { .. }),
+        Command::Report(_) | Command::Export(_) | Command::Sync(_) => true,
+        Command::State(args) => matches!(args.action, StateAction::Stats { .. }),
+        Command::Llm(args) => matches!(args.action, LlmAction::Usage { .. }),
+        _ => false,
+    }
+}
+
+/// Whether a command can print a `--format json` document. Interactive and
+/// long-running commands, and those that already write a document of their
+/// own, only print text.
+fn has_json_output(command: &Command) -> bool {
+    match command {
+        Command::Quicklog { .. } | Command::Jump { .. } | Command::Listen { .. } | Command::Watch { .. } => false,
+        Command::Serve { .. } => false,
+        Command::HookTick => false,
+        Command::Describe { .. } => false,
+        Command::Config(args) => !matches!(args.action, ConfigAction::Show),
+        Command::Snapshot(args) => !matches!(args.action, cli::SnapshotAction::Daemon { .. }),
+        _ => true,
+    }
+}
+
+/// Subcommand path as typed, e.g. "pane history" or "capabilities".
+fn subcommand_path(matches: &clap::ArgMatches) -> String {
+    let mut names = Vec::new();
+    let mut current = matches;
+    while let Some((name, sub)) = current.subcommand() {
+        names.push(name);
+        current = sub;
+    }
+    names.join(" ")
+}
+
 fn needs_zellij_check(command: &Command) -> bool {
     match command {
         // These commands interact with Zellij
@@ -689,6 +2838,9 @@ fn needs_zellij_check(command: &Command) -> bool {
             match &args.action {
                 Some(PaneAction::Log { .. }) => false,
                 Some(PaneAction::History { .. }) => false,
+                Some(PaneAction::BranchCheck { .. }) => false,
+                Some(PaneAction::Annotate { .. }) => false,
+                Some(PaneAction::Artifacts { .. }) => false,
                 Some(PaneAction::Snapshot { .. }) => false, // Uses Redis + LLM, not Zellij
                 Some(PaneAction::Info { .. }) => true, // Checks pane status via Zellij
                 Some(PaneAction::Batch { .. }) => true, // Creates panes in Zellij
@@ -699,15 +2851,49 @@ fn needs_zellij_check(command: &Command) -> bool {
             // Tab info only uses Redis
             match &args.action {
                 Some(TabAction::Info { .. }) => false,
+                Some(TabAction::Find { .. }) => false,
+                Some(TabAction::List { .. }) => false,
+                Some(TabAction::Delete { close_zellij, .. }) => *close_zellij,
                 Some(TabAction::Create { .. }) => true, // Creating requires Zellij
                 None => true, // Ensuring tab exists requires Zellij
             }
         }
-        Command::Reconcile => true,
-        Command::List => true,
+        Command::Reconcile(_) => true,
+        Command::Up { .. } => true, // Compares with and creates tabs and panes
+        Command::List(_) => true,
+        Command::Jump { .. } => true, // Switches tab and focuses the pane
+        Command::Takeover(_) => true, // Creates the receiving pane in Zellij
+        Command::Template(args) => matches!(args.action, TemplateAction::Save { .. } | TemplateAction::Apply { .. }),
+        Command::Quicklog { .. } => false, // Redis only; session comes from the environment
+        Command::Log { .. } => false, // Same as pane log
+        Command::Here => false, // Redis only; session comes from the environment
+        Command::Keybind(_) => false, // Only edits the Zellij config file
+        Command::Hook(_) => false, // Edits rc files and git hooks, or logs a commit
+        Command::HookTick => false, // Runs after every shell command, so skip the version check
         // These commands only use Redis or local config
         Command::Migrate(_) => false,
         Command::Config(_) => false,
+        Command::State(_) => false,
+        Command::Trash(_) => false,
+        Command::Llm(_) => false,
+        Command::Filter(_) => false, // Local only
+        Command::Listen { .. } => false, // Addresses each tab's session explicitly
+        Command::Watch { .. } => true, // Reads the layout every tick
+        Command::Serve { .. } => false, // Only snapshots need Zellij; they report it per request
+        Command::Events(_) => false, // Redis and Bloodbank only
+        Command::Ask { .. } => false, // Redis + LLM only
+        Command::Digest { .. } => false,
+        Command::Tags { .. } => false,
+        Command::Goal(_) => false, // Redis only
+        Command::Artifacts(_) => false,
+        Command::Report(_) => false,
+        Command::Export(_) => false,
+        Command::Sync(_) => false,
+        Command::Stats { .. } => false,
+        Command::Plumbing(_) => false, // Redis and Bloodbank only
+        Command::Describe { .. } => false, // Reads Redis only
+        Command::Capabilities => false, // Reports the Zellij version instead of requiring it
+        Command::Schema { .. } => false,
         Command::Snapshot(args) => {
             // Create, Restore, and Daemon require Zellij session, others only use Redis
             use cli::SnapshotAction;
@@ -0,0 +1,215 @@
+//! In-process Prometheus metrics.
+//!
+//! Every command records into one registry, but only the long-running modes
+//! (`snapshot daemon`, `listen`) live long enough to be scraped; they serve
+//! it with [`serve`] when given `--metrics-addr`.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Histogram buckets in seconds, from a local Redis round trip to a slow LLM
+const BUCKETS: [f64; 12] = [0.001, 0.005, 0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 15.0, 60.0];
+
+enum Kind {
+    Counter,
+    Histogram,
+}
+
+/// Every metric Perth records, in exposition order.
+const METRICS: &[(&str, Kind, &str)] = &[
+    ("perth_commands_total", Kind::Counter, "Commands run, including commands received by zdrive listen"),
+    ("perth_redis_duration_seconds", Kind::Histogram, "Redis operation latency"),
+    ("perth_llm_duration_seconds", Kind::Histogram, "LLM request latency"),
+    ("perth_llm_tokens_total", Kind::Counter, "LLM tokens sent and received"),
+    ("perth_events_published_total", Kind::Counter, "Bloodbank events confirmed by the broker"),
+    ("perth_events_queued_total", Kind::Counter, "Bloodbank events spooled to the outbox for retry"),
+    ("perth_events_dropped_total", Kind::Counter, "Bloodbank events neither published nor queued"),
+];
+
+type Labels = Vec<(&'static str, String)>;
+
+#[derive(Default)]
+struct Histogram {
+    /// Cumulative count per bucket in [`BUCKETS`]
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+#[derive(Default)]
+struct Registry {
+    counters: BTreeMap<(&'static str, Labels), u64>,
+    histograms: BTreeMap<(&'static str, Labels), Histogram>,
+}
+
+static REGISTRY: LazyLock<Mutex<Registry>> = LazyLock::new(Default::default);
+
+fn registry() -> std::sync::MutexGuard<'static, Registry> {
+    // A panic mid-update leaves at worst one sample off; keep recording
+    REGISTRY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn labels(pairs: &[(&'static str, &str)]) -> Labels {
+    pairs.iter().map(|(key, value)| (*key, value.to_string())).collect()
+}
+
+/// Add `n` to a counter.
+pub fn add(name: &'static str, pairs: &[(&'static str, &str)], n: u64) {
+    *registry().counters.entry((name, labels(pairs))).or_default() += n;
+}
+
+/// Add one to a counter.
+pub fn inc(name: &'static str, pairs: &[(&'static str, &str)]) {
+    add(name, pairs, 1);
+}
+
+/// Record a duration in a histogram.
+pub fn observe(name: &'static str, pairs: &[(&'static str, &str)], elapsed: Duration) {
+    record(name, labels(pairs), elapsed);
+}
+
+fn record(name: &'static str, labels: Labels, elapsed: Duration) {
+    let seconds = elapsed.as_secs_f64();
+    let mut registry = registry();
+    let histogram = registry.histograms.entry((name, labels)).or_default();
+    for (count, bound) in histogram.buckets.iter_mut().zip(BUCKETS) {
+        if seconds <= bound {
+            *count += 1;
+        }
+    }
+    histogram.sum += seconds;
+    histogram.count += 1;
+}
+
+/// Records the time from creation until it is dropped.
+pub struct Timer {
+    name: &'static str,
+    labels: Labels,
+    start: Instant,
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        record(self.name, std::mem::take(&mut self.labels), self.start.elapsed());
+    }
+}
+
+/// Time a Redis operation for `perth_redis_duration_seconds`.
+pub fn redis_timer(op: &'static str) -> Timer {
+    Timer {
+        name: "perth_redis_duration_seconds",
+        labels: labels(&[("op", op)]),
+        start: Instant::now(),
+    }
+}
+
+/// The registry in the Prometheus text exposition format.
+pub fn render() -> String {
+    let registry = registry();
+    let mut out = String::new();
+    for (name, kind, help) in METRICS {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        match kind {
+            Kind::Counter => {
+                let _ = writeln!(out, "# TYPE {} counter", name);
+                for ((_, labels), value) in registry.counters.iter().filter(|((n, _), _)| n == name) {
+                    let _ = writeln!(out, "{}{} {}", name, format_labels(labels, None), value);
+                }
+            }
+            Kind::Histogram => {
+                let _ = writeln!(out, "# TYPE {} histogram", name);
+                for ((_, labels), histogram) in registry.histograms.iter().filter(|((n, _), _)| n == name) {
+                    for (bound, count) in BUCKETS.iter().zip(histogram.buckets) {
+                        let le = bound.to_string();
+                        let _ = writeln!(out, "{}_bucket{} {}", name, format_labels(labels, Some(&le)), count);
+                    }
+                    let _ = writeln!(out, "{}_bucket{} {}", name, format_labels(labels, Some("+Inf")), histogram.count);
+                    let _ = writeln!(out, "{}_sum{} {}", name, format_labels(labels, None), histogram.sum);
+                    let _ = writeln!(out, "{}_count{} {}", name, format_labels(labels, None), histogram.count);
+                }
+            }
+        }
+    }
+    out
+}
+
+fn format_labels(labels: &Labels, le: Option<&str>) -> String {
+    let escape = |value: &str| value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+    let mut pairs: Vec<String> = labels.iter().map(|(key, value)| format!("{}=\"{}\"", key, escape(value))).collect();
+    if let Some(le) = le {
+        pairs.push(format!("le=\"{}\"", le));
+    }
+    if pairs.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+/// Serve [`render`] at `http://<addr>/metrics` in the background. Returns
+/// once the address is bound, so a taken port is reported up front.
+pub async fn serve(addr: SocketAddr) -> Result<()> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("failed to bind metrics endpoint on {}", addr))?;
+    tokio::spawn(async move {
+        loop {
+            if let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(respond(stream));
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Answer one scrape. Only the request line is read; anything but
+/// `/metrics` gets a 404.
+async fn respond(mut stream: TcpStream) {
+    let mut request = [0u8; 1024];
+    let Ok(n) = stream.read(&mut request).await else {
+        return;
+    };
+    let request = String::from_utf8_lossy(&request[..n]);
+    let path = request.split_whitespace().nth(1).unwrap_or("");
+    let (status, body) = if path == "/metrics" {
+        ("200 OK", render())
+    } else {
+        ("404 Not Found", "Not found; metrics are served at /metrics\n".to_string())
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_counters_and_histograms() {
+        // The registry is global; unique labels keep parallel tests apart
+        inc("perth_commands_total", &[("command", "render-test")]);
+        add("perth_commands_total", &[("command", "render-test")], 2);
+        observe("perth_redis_duration_seconds", &[("op", "render-test")], Duration::from_millis(3));
+        drop(redis_timer("render-test \"quoted\""));
+
+        let text = render();
+        assert!(text.contains("# TYPE perth_commands_total counter\n"));
+        assert!(text.contains("perth_commands_total{command=\"render-test\"} 3\n"));
+        assert!(text.contains("perth_redis_duration_seconds_bucket{op=\"render-test\",le=\"0.001\"} 0\n"));
+        assert!(text.contains("perth_redis_duration_seconds_bucket{op=\"render-test\",le=\"0.005\"} 1\n"));
+        assert!(text.contains("perth_redis_duration_seconds_bucket{op=\"render-test\",le=\"+Inf\"} 1\n"));
+        assert!(text.contains("perth_redis_duration_seconds_count{op=\"render-test\"} 1\n"));
+        assert!(text.contains("perth_redis_duration_seconds_count{op=\"render-test \\\"quoted\\\"\"} 1\n"));
+    }
+}
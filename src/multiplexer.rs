@@ -0,0 +1,437 @@
+//! The terminal multiplexer operations Perth drives.
+//!
+//! `Orchestrator`, snapshot capture and
+//! restore work through [`MultiplexerDriver`] rather than Zellij directly.
+//! `ZellijDriver` is the real implementation;
+//! [`MockMultiplexer`] keeps sessions, tabs and panes in memory and records
+//! every action, so orchestration can run without a terminal.
+//!
+//! Every method takes the session to act on, `None` meaning the attached one.
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+#[async_trait]
+pub trait MultiplexerDriver: Send + Sync {
+    /// The session this process runs inside, if any.
+    fn active_session_name(&self) -> Option<String>;
+
+    async fn query_tab_names(&self, session: Option<&str>) -> Result<Vec<String>>;
+
+    async fn new_tab(&self, session: Option<&str>, name: &str) -> Result<()>;
+
+    async fn go_to_tab_name(&self, session: Option<&str>, name: &str) -> Result<()>;
+
+    async fn close_focused_tab(&self, session: Option<&str>) -> Result<()>;
+
+    /// Close a tab by name, by focusing it first.
+    async fn close_tab(&self, session: Option<&str>, name: &str) -> Result<()> {
+        self.go_to_tab_name(session, name).await?;
+        self.close_focused_tab(session).await
+    }
+
+    /// Create a pane split off the focused one in `direction` ("right" or
+    /// "down"), running the user's shell.
+    async fn new_pane(&self, session: Option<&str>, direction: &str, cwd: Option<&str>) -> Result<()>;
+
+    /// Create a pane whose shell has extra environment variables, running
+    /// `command` instead of an interactive shell if given.
+    async fn new_pane_with_env(
+        &self,
+        session: Option<&str>,
+        direction: Option<&str>,
+        cwd: Option<&str>,
+        env: &[(String, String)],
+        command: Option<&str>,
+    ) -> Result<()>;
+
+    /// Create a new pane with vertical split (side by side)
+    async fn new_pane_vertical(&self, session: Option<&str>) -> Result<()> {
+        self.new_pane(session, "right", None).await
+    }
+
+    /// Create a new pane with horizontal split (stacked)
+    async fn new_pane_horizontal(&self, session: Option<&str>) -> Result<()> {
+        self.new_pane(session, "down", None).await
+    }
+
+    /// Create a new pane with specified working directory
+    async fn new_pane_with_cwd(&self, session: Option<&str>, cwd: &str, direction: &str) -> Result<()> {
+        self.new_pane(session, direction, Some(cwd)).await
+    }
+
+    /// Type `text` into the focused pane.
+    async fn write_chars(&self, session: Option<&str>, text: &str) -> Result<()>;
+
+    async fn rename_pane(&self, session: Option<&str>, name: &str) -> Result<()>;
+
+    async fn focus_next_pane(&self, session: Option<&str>) -> Result<()>;
+
+    /// ID of the focused pane, as in `ZELLIJ_PANE_ID`, if it can be told.
+    async fn focused_pane_id(&self, session: Option<&str>) -> Result<Option<String>>;
+
+    /// Positional fallback for panes without a known ID: move focus `index`
+    /// times from the tab's first pane, which breaks when the layout order
+    /// differs from creation order.
+    async fn focus_pane_by_index(&self, session: Option<&str>, index: usize) -> Result<()> {
+        for _ in 0..index {
+            self.focus_next_pane(session).await?;
+        }
+        Ok(())
+    }
+
+    /// Focus the pane with `pane_id` in the current tab by moving focus pane
+    /// by pane until it's reached, giving up after `max_panes` moves.
+    /// Returns whether the pane was reached.
+    ///
+    /// A search that misses stops once focus cycles back to the pane it
+    /// started on, so a positional fallback counts from the same place it
+    /// would have without the search.
+    async fn focus_pane_by_id(&self, session: Option<&str>, pane_id: &str, max_panes: usize) -> Result<bool> {
+        let Some(start) = self.focused_pane_id(session).await? else {
+            return Ok(false);
+        };
+        if start == pane_id {
+            return Ok(true);
+        }
+        for _ in 0..max_panes {
+            self.focus_next_pane(session).await?;
+            match self.focused_pane_id(session).await? {
+                Some(focused) if focused == pane_id => return Ok(true),
+                Some(focused) if focused == start => return Ok(false),
+                Some(_) => {}
+                None => return Ok(false),
+            }
+        }
+        Ok(false)
+    }
+
+    /// Everything the focused pane has printed, scrollback included.
+    async fn dump_screen(&self, session: Option<&str>) -> Result<String>;
+
+    /// The session layout as `{"tabs": [{"name": ..., "panes": [{"name": ...}]}]}`.
+    async fn dump_layout_json(&self, session: Option<&str>) -> Result<Option<Value>>;
+
+    /// Start a detached session.
+    async fn create_background_session(&self, session: &str) -> Result<()>;
+}
+
+/// An in-memory multiplexer. Clones share state, so a test can keep one
+/// handle to script and inspect while the orchestrator owns another.
+#[derive(Clone, Default)]
+pub struct MockMultiplexer {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Default)]
+struct MockState {
+    active: Option<String>,
+    sessions: BTreeMap<String, MockSession>,
+    actions: Vec<String>,
+    /// Action names that fail, with their error messages
+    failures: BTreeMap<String, String>,
+    next_pane_id: u32,
+}
+
+#[derive(Default)]
+struct MockSession {
+    tabs: Vec<MockTab>,
+    focused_tab: usize,
+}
+
+struct MockTab {
+    name: String,
+    panes: Vec<MockPane>,
+    focused_pane: usize,
+}
+
+struct MockPane {
+    id: u32,
+    name: String,
+    /// Everything typed into the pane, standing in for its output
+    screen: String,
+}
+
+impl MockMultiplexer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Act as if running inside `session`, creating it if needed.
+    pub fn with_active_session(self, session: &str) -> Self {
+        let mut state = self.lock();
+        state.active = Some(session.to_string());
+        state.add_session(session);
+        drop(state);
+        self
+    }
+
+    /// Add a running session with one single-pane tab per name.
+    pub fn with_session(self, session: &str, tabs: &[&str]) -> Self {
+        let mut state = self.lock();
+        state.add_session(session);
+        for tab in tabs {
+            state.add_tab(session, tab);
+        }
+        drop(state);
+        self
+    }
+
+    /// Make every later `action` (e.g. "new-tab") fail with `message`.
+    pub fn fail_on(self, action: &str, message: &str) -> Self {
+        self.lock().failures.insert(action.to_string(), message.to_string());
+        self
+    }
+
+    /// Every action performed so far, e.g. "dev: rename-pane api".
+    pub fn actions(&self) -> Vec<String> {
+        self.lock().actions.clone()
+    }
+
+    /// Names of the panes in a tab, in creation order.
+    pub fn pane_names(&self, session: &str, tab: &str) -> Vec<String> {
+        let state = self.lock();
+        state
+            .sessions
+            .get(session)
+            .and_then(|s| s.tabs.iter().find(|t| t.name == tab))
+            .map(|t| t.panes.iter().map(|p| p.name.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Record `action` against a session, failing if it's scripted to or the
+    /// session isn't running.
+    fn record(&self, session: Option<&str>, action: &str, detail: &str) -> Result<String> {
+        let mut state = self.lock();
+        let session = session
+            .map(str::to_string)
+            .or_else(|| state.active.clone())
+            .ok_or_else(|| anyhow!("not inside a session"))?;
+        state.actions.push(format!("{}: {} {}", session, action, detail).trim_end().to_string());
+        if let Some(message) = state.failures.get(action) {
+            return Err(anyhow!("{}", message));
+        }
+        if !state.sessions.contains_key(&session) {
+            return Err(anyhow!("session '{}' not found", session));
+        }
+        Ok(session)
+    }
+
+    fn with_tab<T>(&self, session: &str, f: impl FnOnce(&mut MockState, usize) -> Result<T>) -> Result<T> {
+        let mut state = self.lock();
+        let focused = state.sessions[session].focused_tab;
+        if state.sessions[session].tabs.is_empty() {
+            return Err(anyhow!("session '{}' has no tabs", session));
+        }
+        f(&mut state, focused)
+    }
+}
+
+impl MockState {
+    fn add_session(&mut self, session: &str) {
+        if !self.sessions.contains_key(session) {
+            self.sessions.insert(session.to_string(), MockSession::default());
+        }
+    }
+
+    fn add_tab(&mut self, session: &str, name: &str) {
+        let pane = self.new_pane_record();
+        let session = self.sessions.get_mut(session).expect("session exists");
+        session.tabs.push(MockTab { name: name.to_string(), panes: vec![pane], focused_pane: 0 });
+        session.focused_tab = session.tabs.len() - 1;
+    }
+
+    fn new_pane_record(&mut self) -> MockPane {
+        self.next_pane_id += 1;
+        MockPane { id: self.next_pane_id, name: "unnamed".to_string(), screen: String::new() }
+    }
+
+    fn focused_tab(&mut self, session: &str, index: usize) -> &mut MockTab {
+        &mut self.sessions.get_mut(session).expect("session exists").tabs[index]
+    }
+}
+
+#[async_trait]
+impl MultiplexerDriver for MockMultiplexer {
+    fn active_session_name(&self) -> Option<String> {
+        self.lock().active.clone()
+    }
+
+    async fn query_tab_names(&self, session: Option<&str>) -> Result<Vec<String>> {
+        let session = self.record(session, "query-tab-names", "")?;
+        Ok(self.lock().sessions[&session].tabs.iter().map(|tab| tab.name.clone()).collect())
+    }
+
+    async fn new_tab(&self, session: Option<&str>, name: &str) -> Result<()> {
+        let session = self.record(session, "new-tab", name)?;
+        self.lock().add_tab(&session, name);
+        Ok(())
+    }
+
+    async fn go_to_tab_name(&self, session: Option<&str>, name: &str) -> Result<()> {
+        let session = self.record(session, "go-to-tab-name", name)?;
+        let mut state = self.lock();
+        let session = state.sessions.get_mut(&session).expect("session exists");
+        let index = session
+            .tabs
+            .iter()
+            .position(|tab| tab.name == name)
+            .ok_or_else(|| anyhow!("tab '{}' not found", name))?;
+        session.focused_tab = index;
+        Ok(())
+    }
+
+    async fn close_focused_tab(&self, session: Option<&str>) -> Result<()> {
+        let session = self.record(session, "close-tab", "")?;
+        self.with_tab(&session, |state, index| {
+            let session = state.sessions.get_mut(&session).expect("session exists");
+            session.tabs.remove(index);
+            session.focused_tab = index.saturating_sub(1);
+            Ok(())
+        })
+    }
+
+    async fn new_pane(&self, session: Option<&str>, direction: &str, cwd: Option<&str>) -> Result<()> {
+        self.new_pane_with_env(session, Some(direction), cwd, &[], None).await
+    }
+
+    async fn new_pane_with_env(
+        &self,
+        session: Option<&str>,
+        direction: Option<&str>,
+        cwd: Option<&str>,
+        _env: &[(String, String)],
+        command: Option<&str>,
+    ) -> Result<()> {
+        let detail = [direction, cwd, command].iter().flatten().copied().collect::<Vec<_>>().join(" ");
+        let session = self.record(session, "new-pane", &detail)?;
+        self.with_tab(&session, |state, index| {
+            let pane = state.new_pane_record();
+            let tab = state.focused_tab(&session, index);
+            tab.panes.push(pane);
+            tab.focused_pane = tab.panes.len() - 1;
+            Ok(())
+        })
+    }
+
+    async fn write_chars(&self, session: Option<&str>, text: &str) -> Result<()> {
+        let session = self.record(session, "write-chars", text.trim_end())?;
+        self.with_tab(&session, |state, index| {
+            let tab = state.focused_tab(&session, index);
+            let focused = tab.focused_pane;
+            tab.panes[focused].screen.push_str(text);
+            Ok(())
+        })
+    }
+
+    async fn rename_pane(&self, session: Option<&str>, name: &str) -> Result<()> {
+        let session = self.record(session, "rename-pane", name)?;
+        self.with_tab(&session, |state, index| {
+            let tab = state.focused_tab(&session, index);
+            let focused = tab.focused_pane;
+            tab.panes[focused].name = name.to_string();
+            Ok(())
+        })
+    }
+
+    async fn focus_next_pane(&self, session: Option<&str>) -> Result<()> {
+        let session = self.record(session, "focus-next-pane", "")?;
+        self.with_tab(&session, |state, index| {
+            let tab = state.focused_tab(&session, index);
+            tab.focused_pane = (tab.focused_pane + 1) % tab.panes.len();
+            Ok(())
+        })
+    }
+
+    async fn focused_pane_id(&self, session: Option<&str>) -> Result<Option<String>> {
+        let session = self.record(session, "list-clients", "")?;
+        self.with_tab(&session, |state, index| {
+            let tab = state.focused_tab(&session, index);
+            Ok(tab.panes.get(tab.focused_pane).map(|pane| pane.id.to_string()))
+        })
+    }
+
+    async fn dump_screen(&self, session: Option<&str>) -> Result<String> {
+        let session = self.record(session, "dump-screen", "")?;
+        self.with_tab(&session, |state, index| {
+            let tab = state.focused_tab(&session, index);
+            Ok(tab.panes[tab.focused_pane].screen.clone())
+        })
+    }
+
+    async fn dump_layout_json(&self, session: Option<&str>) -> Result<Option<Value>> {
+        let session = self.record(session, "dump-layout", "")?;
+        let state = self.lock();
+        let focused_tab = state.sessions[&session].focused_tab;
+        let tabs: Vec<Value> = state.sessions[&session]
+            .tabs
+            .iter()
+            .enumerate()
+            .map(|(index, tab)| {
+                let panes: Vec<Value> = tab
+                    .panes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, pane)| json!({ "name": pane.name, "id": pane.id, "focused": i == tab.focused_pane }))
+                    .collect();
+                json!({ "name": tab.name, "active": index == focused_tab, "panes": panes })
+            })
+            .collect();
+        Ok(Some(json!({ "tabs": tabs })))
+    }
+
+    async fn create_background_session(&self, session: &str) -> Result<()> {
+        let mut state = self.lock();
+        state.actions.push(format!("{}: create-background-session", session));
+        if state.sessions.contains_key(session) {
+            return Err(anyhow!("session '{}' already exists", session));
+        }
+        state.add_session(session);
+        state.add_tab(session, "Tab #1");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mock_tracks_tabs_panes_and_focus() {
+        let mock = MockMultiplexer::new().with_active_session("dev").with_session("ops", &["logs"]);
+        let driver = mock.clone();
+
+        driver.new_tab(None, "backend").await.unwrap();
+        driver.rename_pane(None, "api").await.unwrap();
+        driver.new_pane_vertical(None).await.unwrap();
+        driver.rename_pane(None, "tests").await.unwrap();
+        assert_eq!(mock.pane_names("dev", "backend"), vec!["api", "tests"]);
+        assert_eq!(driver.query_tab_names(Some("ops")).await.unwrap(), vec!["logs"]);
+
+        // Focus by ID walks the tab like Zellij's focus-next-pane
+        let api_id = {
+            driver.focus_next_pane(None).await.unwrap();
+            driver.focused_pane_id(None).await.unwrap().unwrap()
+        };
+        driver.focus_next_pane(None).await.unwrap();
+        assert!(driver.focus_pane_by_id(None, &api_id, 2).await.unwrap());
+        assert!(!driver.focus_pane_by_id(None, "999", 2).await.unwrap());
+        // A miss leaves focus where the search started
+        assert_eq!(driver.focused_pane_id(None).await.unwrap(), Some(api_id.clone()));
+
+        driver.close_tab(None, "backend").await.unwrap();
+        assert!(driver.query_tab_names(None).await.unwrap().is_empty());
+        assert!(mock.actions().contains(&"dev: rename-pane api".to_string()));
+
+        assert!(driver.query_tab_names(Some("missing")).await.is_err());
+        let failing = MockMultiplexer::new().with_active_session("dev").fail_on("new-tab", "boom");
+        assert_eq!(failing.new_tab(None, "x").await.unwrap_err().to_string(), "boom");
+    }
+}
@@ -0,0 +1,160 @@
+//! Obsidian vault sync for `zdrive sync obsidian`: one Markdown note per
+//! pane with YAML frontmatter Obsidian can query.
+//!
+//! Notes are rendered deterministically and carry a `sync_hash` of their
+//! content in the frontmatter. A sync only rewrites notes whose hash
+//! changed, so notes for idle panes keep their modification time, and it
+//! never deletes anything from the vault.
+
+use crate::output::{self, OutputFormatter};
+use crate::types::HistoryOutput;
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// Tag added to every synced note, so they can be found in the vault.
+pub const NOTE_TAG: &str = "zdrive";
+
+/// A pane's history with where the pane lives, rendered as one note.
+#[derive(Debug, Clone)]
+pub struct PaneNote {
+    pub history: HistoryOutput,
+    /// Session and tab, when the pane is still tracked
+    pub session: Option<String>,
+    pub tab: Option<String>,
+}
+
+/// What a sync wrote.
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncReport {
+    pub dir: PathBuf,
+    /// Notes created or rewritten
+    pub written: Vec<String>,
+    /// Notes left alone because their history is unchanged
+    pub unchanged: Vec<String>,
+}
+
+impl PaneNote {
+    /// The full note: frontmatter with a hash of everything else, then the
+    /// history as Markdown.
+    pub fn render(&self) -> String {
+        let HistoryOutput { pane, entries, annotations, .. } = &self.history;
+        let body = OutputFormatter::new().with_annotations(annotations.clone()).format_markdown_body(entries, pane);
+
+        let mut frontmatter = vec![format!("pane: {}", yaml_string(pane))];
+        if let Some(session) = &self.session {
+            frontmatter.push(format!("session: {}", yaml_string(session)));
+        }
+        if let Some(tab) = &self.tab {
+            frontmatter.push(format!("tab: {}", yaml_string(tab)));
+        }
+        let tags: BTreeSet<&str> = entries.iter().flat_map(|e| e.tags.iter().map(String::as_str)).collect();
+        frontmatter.push("tags:".to_string());
+        frontmatter.extend(
+            [NOTE_TAG].into_iter().chain(tags.into_iter().filter(|tag| *tag != NOTE_TAG)).map(|tag| format!("  - {}", yaml_string(tag))),
+        );
+        frontmatter.push(format!("entries: {}", entries.len()));
+        if let Some(latest) = entries.iter().map(|e| e.timestamp).max() {
+            frontmatter.push(format!("latest: {}", latest.format("%Y-%m-%dT%H:%M:%SZ")));
+        }
+
+        let hash = fnv1a(frontmatter.iter().map(String::as_str).chain([body.as_str()]));
+        format!("---\n{}\nsync_hash: {:016x}\n---\n\n{}\n", frontmatter.join("\n"), hash, body)
+    }
+}
+
+/// Write a note per pane into `dir`, skipping notes whose `sync_hash`
+/// already matches. File names come from pane names, in name order.
+pub fn sync(dir: &Path, mut notes: Vec<PaneNote>) -> Result<SyncReport> {
+    std::fs::create_dir_all(dir).map_err(|e| anyhow!("failed to create {}: {}", dir.display(), e))?;
+    notes.sort_by(|a, b| a.history.pane.cmp(&b.history.pane));
+
+    let file_names = output::pane_file_names(notes.iter().map(|n| n.history.pane.as_str()), "md");
+    let mut report = SyncReport { dir: dir.to_path_buf(), written: Vec::new(), unchanged: Vec::new() };
+    for (note, file_name) in notes.iter().zip(file_names) {
+        let path = dir.join(&file_name);
+        let content = note.render();
+        let existing = std::fs::read_to_string(&path).ok();
+        if existing.as_deref().and_then(sync_hash) == sync_hash(&content) {
+            report.unchanged.push(file_name);
+            continue;
+        }
+        std::fs::write(&path, content).map_err(|e| anyhow!("failed to write {}: {}", path.display(), e))?;
+        report.written.push(file_name);
+    }
+    Ok(report)
+}
+
+/// The `sync_hash` from a note's frontmatter.
+fn sync_hash(note: &str) -> Option<&str> {
+    let frontmatter = note.strip_prefix("---\n")?.split("\n---").next()?;
+    frontmatter.lines().find_map(|line| line.strip_prefix("sync_hash: "))
+}
+
+/// A double-quoted YAML scalar. JSON strings are valid YAML, and quoting
+/// keeps names with ':' or '#' from being misread.
+fn yaml_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| format!("\"{}\"", value))
+}
+
+/// 64-bit FNV-1a over the parts, separated by newlines. Stable across
+/// builds, unlike the standard library's hasher.
+fn fnv1a<'a>(parts: impl IntoIterator<Item = &'a str>) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts {
+        for byte in part.bytes().chain([b'\n']) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::IntentEntry;
+
+    fn note(pane: &str, summaries: &[&str]) -> PaneNote {
+        let entries = summaries
+            .iter()
+            .map(|s| IntentEntry::new(*s).with_tags(vec!["auth".to_string()]))
+            .collect();
+        PaneNote {
+            history: HistoryOutput { pane: pane.to_string(), goal: None, goal_id: None, goal_set_at: None, entries, annotations: Vec::new() },
+            session: Some("dev".to_string()),
+            tab: Some("api".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_render_frontmatter() {
+        let note = note("api(auth)", &["Token refresh"]);
+        let rendered = note.render();
+        assert!(rendered.starts_with(
+            "---\npane: \"api(auth)\"\nsession: \"dev\"\ntab: \"api\"\ntags:\n  - \"zdrive\"\n  - \"auth\"\nentries: 1\nlatest: "
+        ));
+        assert!(sync_hash(&rendered).is_some_and(|hash| hash.len() == 16));
+        assert!(rendered.contains("---\n\n# Session: api(auth)\n"));
+        assert_eq!(rendered, note.render());
+    }
+
+    #[test]
+    fn test_sync_only_rewrites_changed_notes() {
+        let dir = std::env::temp_dir().join(format!("zdrive-obsidian-{}", uuid::Uuid::new_v4()));
+        let api = note("api(auth)", &["Token refresh"]);
+        let web = note("web", &["Login form"]);
+
+        let first = sync(&dir, vec![api.clone(), web.clone()]).unwrap();
+        assert_eq!(first.written, vec!["api-auth.md", "web.md"]);
+
+        let mut api_changed = api.clone();
+        api_changed.history.entries.push(IntentEntry::new("Revocation"));
+        let second = sync(&dir, vec![web, api_changed]).unwrap();
+        assert_eq!(second.written, vec!["api-auth.md"]);
+        assert_eq!(second.unchanged, vec!["web.md"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,12 +1,45 @@
-use crate::bloodbank::EventPublisher;
+use crate::activity;
+use crate::artifacts::{self, ArtifactReport};
+use crate::ask::{self, AskOptions, PaneEntry};
+use crate::bloodbank::{CommandRequest, EventMetadata, EventPublisher, IncomingEvent};
+use crate::config::{ContextConfig, HooksConfig, NamingCheck, ReleaseConfig, SnapshotConfig, TabConfig};
 use crate::context::ContextCollector;
-use crate::llm::{create_provider, CircuitBreaker, LLMConfig};
-use crate::state::{MigrationResult, StateManager};
-use crate::types::{IntentEntry, IntentSource, IntentType, PaneInfoOutput, PaneRecord, PaneStatus, TabRecord};
+use crate::describe::{PaneDescription, TabDescription};
+use crate::digest::Digest;
+use crate::error::PerthError;
+use crate::filter::{FilterConfig, Redaction, SecretFilter};
+use crate::handoff::HandoffBundle;
+use crate::hook;
+use crate::jump;
+use crate::llm::{
+    create_provider, estimate_cost, estimate_tokens, fit_to_budget, summarize_checked, CircuitBreaker, Completion, HeuristicProvider,
+    LLMConfig, LLMProvider, SessionContext, SummarizationResult,
+};
+use crate::metrics;
+use crate::multiplexer::MultiplexerDriver;
+use crate::obsidian::PaneNote;
+use crate::output::OutputFormatter;
+use crate::release;
+use crate::report::{ReportGroup, TimeReport};
+use crate::retention::RetentionPolicy;
+use crate::crypto::Cipher;
+use crate::state::{HistoryQuery, KeyConflict, KeyspaceStats, MigrationResult, RekeyBatch, StateManager, TrashItem};
+use crate::stats::ActivityStats;
+use crate::telemetry;
+use crate::template;
+use crate::tracker::{PaneEvent, TrackedChange, TrackedPane};
+use crate::types::{
+    Annotation, Goal, GoalProgress, GoalStatus, HistoryOutput, IntentEntry, IntentSource, IntentType, LlmUsage, PaneInfoOutput, PaneRecord, PaneStatus,
+    SessionTemplate, TabRecord, TemplatePane, TemplateTab, tag_counts,
+};
+use crate::workspace::{Workspace, WorkspaceChange, WorkspacePlan};
 use crate::zellij::ZellijDriver;
 use anyhow::{anyhow, Context, Result};
+use futures_util::Stream;
+use serde::Serialize;
 use serde_json::Value;
 use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
 use std::time::Duration;
 use tokio::time::timeout;
@@ -16,18 +49,85 @@ use tokio::time::timeout;
 static LLM_CIRCUIT_BREAKER: LazyLock<CircuitBreaker> = LazyLock::new(CircuitBreaker::new);
 
 const CURRENT_TAB: &str = "current";
+/// Panes to try when focusing by ID in a tab whose pane count is unknown.
+const FOCUS_SEARCH_LIMIT: usize = 16;
 
-pub struct Orchestrator {
+/// Drives Zellij by default; any [`MultiplexerDriver`] works, such as
+/// `MockMultiplexer` in tests.
+pub struct Orchestrator<M: MultiplexerDriver = ZellijDriver> {
     state: StateManager,
-    zellij: ZellijDriver,
+    zellij: M,
     events: EventPublisher,
+    scrollback: bool,
+    context_config: ContextConfig,
+    filter_config: FilterConfig,
+    tab_config: TabConfig,
+    snapshot_config: SnapshotConfig,
+    create_sessions: bool,
 }
 
-impl Orchestrator {
-    pub fn new(state: StateManager, zellij: ZellijDriver, events: EventPublisher) -> Self {
-        Self { state, zellij, events }
+impl<M: MultiplexerDriver + Clone> Orchestrator<M> {
+    /// An orchestrator over Redis state, a multiplexer and an event
+    /// publisher, with default context, filter, tab and snapshot settings.
+    /// The `with_*` builders apply the user's config.
+    pub fn new(state: StateManager, zellij: M, events: EventPublisher) -> Self {
+        Self {
+            state,
+            zellij,
+            events,
+            scrollback: false,
+            context_config: ContextConfig::default(),
+            filter_config: FilterConfig::default(),
+            tab_config: TabConfig::default(),
+            snapshot_config: SnapshotConfig::default(),
+            create_sessions: false,
+        }
+    }
+
+    /// Include the pane's recent terminal output in snapshot context
+    /// (requires `privacy.scrollback_consent`).
+    pub fn with_scrollback_capture(mut self, enabled: bool) -> Self {
+        self.scrollback = enabled;
+        self
+    }
+
+    /// How snapshot context is collected (`[context]` in config).
+    pub fn with_context_config(mut self, config: ContextConfig) -> Self {
+        self.context_config = config;
+        self
+    }
+
+    /// How text is filtered before it is sent to an LLM (`[filter]` in config).
+    pub fn with_filter_config(mut self, config: FilterConfig) -> Self {
+        self.filter_config = config;
+        self
+    }
+
+    /// The naming convention new tabs are checked against (`[tab]` in config).
+    pub fn with_tab_config(mut self, config: TabConfig) -> Self {
+        self.tab_config = config;
+        self
+    }
+
+    /// Safety snapshots and their retention (`[snapshot]` in config).
+    pub fn with_snapshot_config(mut self, config: SnapshotConfig) -> Self {
+        self.snapshot_config = config;
+        self
+    }
+
+    /// Start sessions that aren't running in the background when a pane is
+    /// opened in them, instead of failing (`pane --create-session`).
+    pub fn with_session_creation(mut self, enabled: bool) -> Self {
+        self.create_sessions = enabled;
+        self
     }
 
+    /// Focus a tracked pane, or create it. A new pane runs the command in
+    /// its `command` metadata, if any.
+    ///
+    /// Panes in another session are opened there through `zellij --session`
+    /// without attaching; the returned location says whether the caller
+    /// should offer to attach.
     pub async fn open_pane(
         &mut self,
         pane_name: String,
@@ -35,7 +135,7 @@ impl Orchestrator {
         session: Option<String>,
         meta: HashMap<String, String>,
         show_last_intent: bool,
-    ) -> Result<()> {
+    ) -> Result<PaneLocation> {
         if let Some(record) = self.state.get_pane(&pane_name).await? {
             return self.open_existing_pane(record, session, meta, show_last_intent).await;
         }
@@ -87,18 +187,27 @@ impl Orchestrator {
     /// The correlation ID is appended to the tab name as a suffix (e.g., "myapp(fixes)-pr-42")
     /// and stored in Redis for later querying.
     ///
+    /// New tabs are checked against `tab.naming_pattern` (without the
+    /// suffix) as `check` says; a warning lands in the result's
+    /// `naming_warning`. Existing tabs are focused without a check.
+    ///
     /// Returns a TabCreateResult indicating whether the tab was created or already exists.
     pub async fn create_tab(
         &mut self,
         tab_name: String,
         correlation_id: Option<String>,
         meta: HashMap<String, String>,
+        check: NamingCheck,
     ) -> Result<TabCreateResult> {
         // Determine the target session
         let target_session = self
             .zellij
             .active_session_name()
-            .ok_or_else(|| anyhow!("no active session; must be inside a Zellij session"))?;
+            .ok_or_else(|| PerthError::ZellijUnavailable("no active session; must be inside a Zellij session".to_string()))?;
+
+        if let Some(id) = &correlation_id {
+            telemetry::set_correlation_id(id);
+        }
 
         // Compute the effective tab name (with correlation ID suffix if provided)
         let effective_name = match &correlation_id {
@@ -109,18 +218,52 @@ impl Orchestrator {
         // Check if tab already exists in Zellij
         let tabs = self.zellij.query_tab_names(None).await?;
         if tabs.iter().any(|tab| tab == &effective_name) {
-            // Tab already exists - touch it and focus
+            // Tab already exists - touch it, its panes, and focus
             self.zellij.go_to_tab_name(None, &effective_name).await?;
             self.state.touch_tab(&effective_name, &target_session).await?;
+            let panes: Vec<String> = self
+                .state
+                .list_all_panes()
+                .await?
+                .into_iter()
+                .filter(|p| p.session == target_session && p.tab == effective_name)
+                .map(|p| p.pane_name)
+                .collect();
+            self.state.touch_panes(&panes).await?;
 
             return Ok(TabCreateResult {
                 tab_name: effective_name,
                 correlation_id,
                 created: false,
                 session: target_session,
+                naming_warning: None,
             });
         }
 
+        let naming_warning = match check {
+            NamingCheck::Skip => None,
+            _ if self.tab_config.validate_name(&tab_name) => None,
+            check => {
+                let branch = std::env::current_dir().ok().and_then(|cwd| activity::current_branch(&cwd));
+                let suggestion = self
+                    .tab_config
+                    .suggest_name(&tab_name, branch.as_deref())
+                    .map(|name| format!("; try '{}'", name))
+                    .unwrap_or_default();
+                let message = format!(
+                    "tab name '{}' does not match the naming convention (expected {}){}",
+                    tab_name,
+                    self.tab_config.format_hint(),
+                    suggestion
+                );
+                if check == NamingCheck::Enforce {
+                    return Err(anyhow!("{}, or pass --force to create it anyway", message));
+                }
+                tracing::warn!(tab = %tab_name, "{}", message);
+                Some(message)
+            }
+        };
+
         // Create the tab in Zellij
         self.zellij
             .new_tab(None, &effective_name)
@@ -149,6 +292,7 @@ impl Orchestrator {
             correlation_id,
             created: true,
             session: target_session,
+            naming_warning,
         })
     }
 
@@ -157,11 +301,229 @@ impl Orchestrator {
         let session = self
             .zellij
             .active_session_name()
-            .ok_or_else(|| anyhow!("no active session; must be inside a Zellij session"))?;
+            .ok_or_else(|| PerthError::ZellijUnavailable("no active session; must be inside a Zellij session".to_string()))?;
 
         self.state.get_tab(tab_name, &session).await
     }
 
+    /// Tabs in any session created with `correlation_id`, oldest first.
+    pub async fn find_tabs(&mut self, correlation_id: &str) -> Result<Vec<TabRecord>> {
+        self.state.find_tabs_by_correlation(correlation_id).await
+    }
+
+    /// Tracked tabs with their pane counts, most recently accessed first.
+    /// Without `all_sessions`, lists `session` or else the active session.
+    pub async fn list_tabs(&mut self, session: Option<&str>, all_sessions: bool) -> Result<Vec<TabSummary>> {
+        let tabs = if all_sessions {
+            self.state.list_all_tabs().await?
+        } else {
+            let session = session
+                .map(str::to_string)
+                .or_else(|| self.zellij.active_session_name())
+                .ok_or_else(|| PerthError::ZellijUnavailable("not inside a zellij session; pass --session or --all-sessions".to_string()))?;
+            self.state.list_tabs(&session).await?
+        };
+
+        let panes = self.state.list_all_panes().await?;
+        let mut summaries: Vec<TabSummary> = tabs
+            .into_iter()
+            .map(|tab| {
+                let panes = panes.iter().filter(|p| p.session == tab.session && p.tab == tab.tab_name).count();
+                TabSummary { tab, panes }
+            })
+            .collect();
+        summaries.sort_by(|a, b| b.tab.last_accessed.cmp(&a.tab.last_accessed));
+        Ok(summaries)
+    }
+
+    /// Stop tracking a tab. With `close_zellij` the tab is also closed in
+    /// Zellij and its panes marked stale; with `with_panes` its pane records
+    /// and their history are deleted too.
+    pub async fn delete_tab(
+        &mut self,
+        tab_name: &str,
+        session: Option<&str>,
+        close_zellij: bool,
+        with_panes: bool,
+    ) -> Result<TabDeleteResult> {
+        let (session, action_session) = self.target_session(session)?;
+        if self.state.get_tab(tab_name, &session).await?.is_none() {
+            return Err(PerthError::NotFound(format!("tab '{}' is not tracked in session '{}'", tab_name, session)).into());
+        }
+
+        let mut result = TabDeleteResult {
+            tab_name: tab_name.to_string(),
+            session: session.clone(),
+            closed: false,
+            deleted_panes: Vec::new(),
+        };
+
+        if close_zellij {
+            let open = self.zellij.query_tab_names(action_session.as_deref()).await?.iter().any(|t| t == tab_name);
+            if open {
+                self.zellij.close_tab(action_session.as_deref(), tab_name).await?;
+                result.closed = true;
+            }
+        }
+
+        for pane in self.state.list_all_panes().await? {
+            if pane.session != session || pane.tab != tab_name {
+                continue;
+            }
+            if with_panes {
+                self.state.delete_pane(&pane.pane_name).await?;
+                self.events.pane_deleted(&pane, "tab deleted").await;
+                result.deleted_panes.push(pane.pane_name);
+            } else if result.closed {
+                self.mark_stale(&pane, "tab closed").await?;
+            }
+        }
+
+        self.state.delete_tab(tab_name, &session).await?;
+        Ok(result)
+    }
+
+    /// React to an external completion event (e.g. `github.pr.merged`) for
+    /// `correlation_id`: log a milestone on every pane of each correlated tab
+    /// and schedule the tab to be archived once `grace` has passed.
+    pub async fn complete_correlation(
+        &mut self,
+        event_type: &str,
+        correlation_id: &str,
+        grace: chrono::Duration,
+    ) -> Result<CompletionResult> {
+        let mut result = CompletionResult::default();
+        let tabs: Vec<TabRecord> = self
+            .state
+            .find_tabs_by_correlation(correlation_id)
+            .await?
+            .into_iter()
+            .filter(|t| t.archived_at.is_none())
+            .collect();
+        if tabs.is_empty() {
+            return Ok(result);
+        }
+
+        let panes = self.state.list_all_panes().await?;
+        let due = chrono::Utc::now() + grace;
+        for tab in tabs {
+            for pane in panes.iter().filter(|p| p.session == tab.session && p.tab == tab.tab_name) {
+                let entry = IntentEntry::new(format!("{} [{}]", event_type, correlation_id))
+                    .with_type(IntentType::Milestone)
+                    .with_source(IntentSource::Automated)
+                    .with_correlation_id(correlation_id);
+                self.log_intent(&pane.pane_name, &entry).await?;
+                result.milestones += 1;
+            }
+            self.state
+                .schedule_tab_archive(&tab.tab_name, &tab.session, event_type, due)
+                .await?;
+            result.tabs.push(tab);
+        }
+        Ok(result)
+    }
+
+    /// Carry out a command received over Bloodbank and answer it with a
+    /// `.completed` or `.failed` event. Returns a one-line summary.
+    pub async fn handle_command(&mut self, event: &IncomingEvent, request: Result<CommandRequest>) -> Result<String> {
+        let outcome = match request {
+            Ok(request) => self.run_command(request).await,
+            Err(e) => Err(e),
+        };
+        self.events.command_result(event, outcome.as_ref().map(|(_, result)| result)).await;
+        outcome.map(|(summary, _)| summary)
+    }
+
+    async fn run_command(&mut self, request: CommandRequest) -> Result<(String, Value)> {
+        match request {
+            CommandRequest::CreateTab {
+                tab_name,
+                correlation_id,
+                meta,
+            } => {
+                let check = self.tab_config.naming_check(false, false);
+                let result = self.create_tab(tab_name, correlation_id, meta, check).await?;
+                let summary = if result.created {
+                    format!("created tab '{}'", result.tab_name)
+                } else {
+                    format!("tab '{}' already exists", result.tab_name)
+                };
+                let value = serde_json::json!({
+                    "tab_name": result.tab_name,
+                    "session": result.session,
+                    "correlation_id": result.correlation_id,
+                    "created": result.created,
+                });
+                Ok((summary, value))
+            }
+            CommandRequest::CreatePane {
+                pane_name,
+                tab,
+                correlation_id,
+                meta,
+            } => {
+                // A correlated tab is named `<tab>-<id>`; create it first so
+                // the pane lands in it
+                let tab = match (tab, correlation_id) {
+                    (Some(tab), Some(id)) => {
+                        let check = self.tab_config.naming_check(false, false);
+                        Some(self.create_tab(tab, Some(id), HashMap::new(), check).await?.tab_name)
+                    }
+                    (tab, _) => tab,
+                };
+                self.open_pane(pane_name.clone(), tab, None, meta, false).await?;
+                let record = self
+                    .state
+                    .get_pane(&pane_name)
+                    .await?
+                    .ok_or_else(|| anyhow!("pane '{}' was not recorded", pane_name))?;
+                let summary = format!("opened pane '{}' in tab '{}'", record.pane_name, record.tab);
+                let value = serde_json::json!({
+                    "pane_name": record.pane_name,
+                    "tab": record.tab,
+                    "session": record.session,
+                    "pane_id": record.pane_id,
+                });
+                Ok((summary, value))
+            }
+        }
+    }
+
+    /// Archive tabs whose grace period has passed: close them in Zellij if
+    /// still open, mark their panes stale, stamp the record, and publish
+    /// `perth.tab.archived`.
+    pub async fn archive_due_tabs(&mut self) -> Result<Vec<TabRecord>> {
+        let mut archived = Vec::new();
+        for (session, tab_name, reason) in self.state.take_due_tab_archives(chrono::Utc::now()).await? {
+            let Some(mut record) = self.state.get_tab(&tab_name, &session).await? else {
+                continue;
+            };
+
+            let open = self
+                .zellij
+                .query_tab_names(Some(&session))
+                .await
+                .map(|tabs| tabs.contains(&tab_name))
+                .unwrap_or(false);
+            if open {
+                if let Err(e) = self.zellij.close_tab(Some(&session), &tab_name).await {
+                    tracing::warn!(tab = %tab_name, session = %session, "failed to close tab: {:#}", e);
+                }
+            }
+
+            for pane in self.state.list_all_panes().await? {
+                if pane.session == session && pane.tab == tab_name {
+                    self.mark_stale(&pane, "tab archived").await?;
+                }
+            }
+
+            record.archived_at = Some(self.state.mark_tab_archived(&tab_name, &session).await?);
+            self.events.tab_archived(&record, Some(&reason)).await;
+            archived.push(record);
+        }
+        Ok(archived)
+    }
+
     /// Spawn multiple named panes in a single tab (STORY-037).
     ///
     /// Creates multiple panes sequentially in the specified tab, naming each one
@@ -169,32 +531,33 @@ impl Orchestrator {
     /// position metadata for later focus restoration.
     ///
     /// # Arguments
+    /// * `session` - Session to create panes in (defaults to the active session)
     /// * `tab_name` - The tab to create panes in (will be created if it doesn't exist)
     /// * `pane_names` - Names for each pane to create
     /// * `cwds` - Optional working directories for each pane (shorter list is padded with None)
+    /// * `commands` - Optional commands to run in each pane (shorter list is padded with None)
     /// * `vertical` - If true, creates vertical splits (side by side); if false, horizontal (stacked)
     ///
     /// # Returns
     /// A `BatchResult` containing the list of created and skipped panes.
     pub async fn batch_panes(
         &mut self,
+        session: Option<&str>,
         tab_name: String,
         pane_names: Vec<String>,
         cwds: Vec<String>,
+        commands: Vec<String>,
         vertical: bool,
     ) -> Result<BatchResult> {
         if pane_names.is_empty() {
             return Err(anyhow!("at least one pane name is required"));
         }
 
-        // Determine the target session
-        let target_session = self
-            .zellij
-            .active_session_name()
-            .ok_or_else(|| anyhow!("no active session; must be inside a Zellij session"))?;
+        let (target_session, action_session) = self.target_session(session)?;
+        let action_session = action_session.as_deref();
 
         // Ensure tab exists (creates it if needed)
-        let tab_created = self.ensure_tab_in_session(None, &tab_name).await?;
+        let tab_created = self.ensure_tab_in_session(action_session, &tab_name).await?;
 
         let mut panes_created = Vec::new();
         let mut panes_skipped = Vec::new();
@@ -208,49 +571,54 @@ impl Orchestrator {
                 continue;
             }
 
-            // Get cwd for this pane (if provided)
+            // Get cwd and command for this pane (if provided)
             let cwd = cwds.get(idx).cloned();
+            let command = commands.get(idx).filter(|command| !command.is_empty());
 
+            let env = pane_env(pane_name, &target_session);
+            let abs_cwd = cwd.as_ref().map(|cwd_path| {
+                std::fs::canonicalize(cwd_path)
+                    .map(|p| p.to_string_lossy().to_string())
+                    .unwrap_or_else(|_| cwd_path.clone())
+            });
             if idx == 0 && tab_created {
                 // First pane in a newly created tab - just rename the initial pane
-                self.zellij.rename_pane(None, pane_name).await?;
-            } else {
-                // Create a new pane with split direction
-                if let Some(ref cwd_path) = cwd {
-                    // Resolve to absolute path
-                    let abs_cwd = std::fs::canonicalize(cwd_path)
-                        .map(|p| p.to_string_lossy().to_string())
-                        .unwrap_or_else(|_| cwd_path.clone());
-                    self.zellij
-                        .new_pane_with_cwd(None, &abs_cwd, direction)
-                        .await?;
-                } else if vertical {
-                    self.zellij.new_pane_vertical(None).await?;
-                } else {
-                    self.zellij.new_pane_horizontal(None).await?;
+                self.zellij.rename_pane(action_session, pane_name).await?;
+                self.zellij.write_chars(action_session, &export_command(&env)).await?;
+                if let Some(cwd_path) = &abs_cwd {
+                    self.zellij.write_chars(action_session, &format!(" cd {}\n", shell_quote(cwd_path))).await?;
                 }
-                self.zellij.rename_pane(None, pane_name).await?;
+                if let Some(command) = command {
+                    self.zellij.write_chars(action_session, &format!("{}\n", command)).await?;
+                }
+            } else {
+                // Create a new pane with split direction, resolving cwd to an absolute path
+                self.zellij
+                    .new_pane_with_env(action_session, Some(direction), abs_cwd.as_deref(), &env, command.map(String::as_str))
+                    .await?;
+                self.zellij.rename_pane(action_session, pane_name).await?;
             }
 
             // Store pane in Redis with position metadata
             let now = StateManager::now_string();
             let mut meta = HashMap::new();
             meta.insert("position".to_string(), idx.to_string());
-            if let Some(ref cwd_path) = cwd {
+            if let Some(abs_cwd) = abs_cwd {
                 // Store resolved path in metadata
-                let abs_cwd = std::fs::canonicalize(cwd_path)
-                    .map(|p| p.to_string_lossy().to_string())
-                    .unwrap_or_else(|_| cwd_path.clone());
                 meta.insert("cwd".to_string(), abs_cwd);
             }
+            if let Some(command) = command {
+                meta.insert(COMMAND_META_KEY.to_string(), command.clone());
+            }
 
-            let record = PaneRecord::new(
+            let mut record = PaneRecord::new(
                 pane_name.clone(),
                 target_session.clone(),
                 tab_name.clone(),
                 now,
                 meta,
             );
+            record.pane_id = self.zellij.focused_pane_id(action_session).await.ok().flatten();
             self.state.upsert_pane(&record).await?;
 
             // Publish pane.created event
@@ -267,11 +635,129 @@ impl Orchestrator {
         })
     }
 
-    pub async fn reconcile(&mut self) -> Result<()> {
+    /// Compare a workspace with a session (`zdrive up --diff`), by default
+    /// the active one.
+    ///
+    /// Missing tabs and untracked panes are to be created, and panes without
+    /// history get the file's intent. Tracked panes that live elsewhere or
+    /// were started with a different cwd or command are reported as drift.
+    pub async fn workspace_plan(&mut self, workspace: &Workspace, session: Option<&str>) -> Result<WorkspacePlan> {
+        let (session, action_session) = self.target_session(session)?;
+        let existing_tabs = match action_session.as_deref() {
+            // A session that isn't running yet has no tabs
+            Some(other) => self.zellij.query_tab_names(Some(other)).await.unwrap_or_default(),
+            None => self.zellij.query_tab_names(None).await?,
+        };
+
+        let mut changes = Vec::new();
+        for tab in &workspace.tabs {
+            if !existing_tabs.contains(&tab.name) {
+                changes.push(WorkspaceChange::CreateTab { tab: tab.name.clone() });
+            }
+
+            for pane in &tab.panes {
+                let cwd = canonical_path(&pane.cwd).to_string_lossy().to_string();
+                let record = self.state.get_pane(&pane.name).await?;
+                let has_history = match &record {
+                    Some(record) => {
+                        let drift = [
+                            ("session", Some(&record.session), Some(&session)),
+                            ("tab", Some(&record.tab), Some(&tab.name)),
+                            ("cwd", record.meta.get("cwd"), Some(&cwd)),
+                            ("command", record.meta.get(COMMAND_META_KEY), pane.run.as_ref()),
+                        ];
+                        for (field, tracked, wanted) in drift {
+                            // Panes opened without a cwd or command aren't drift
+                            if tracked.is_some() && tracked != wanted {
+                                changes.push(WorkspaceChange::Drift {
+                                    pane: pane.name.clone(),
+                                    field: field.to_string(),
+                                    tracked: tracked.cloned(),
+                                    workspace: wanted.cloned(),
+                                });
+                            }
+                        }
+                        self.state.get_history_count(&pane.name).await? > 0
+                    }
+                    None => {
+                        changes.push(WorkspaceChange::CreatePane {
+                            tab: tab.name.clone(),
+                            pane: pane.name.clone(),
+                            cwd,
+                            run: pane.run.clone(),
+                        });
+                        false
+                    }
+                };
+
+                if let (Some(intent), false) = (&pane.intent, has_history) {
+                    changes.push(WorkspaceChange::LogIntent { pane: pane.name.clone(), summary: intent.clone() });
+                }
+            }
+        }
+
+        Ok(WorkspacePlan { source: workspace.source.clone(), session, changes })
+    }
+
+    /// Create whatever a workspace describes that's missing (`zdrive up`).
+    ///
+    /// Each tab's missing panes are created with [`Self::batch_panes`], so
+    /// running it again on an unchanged session does nothing. A named
+    /// session that isn't running is started in the background first.
+    pub async fn apply_workspace(&mut self, workspace: &Workspace, session: Option<&str>) -> Result<WorkspacePlan> {
+        let (_, action_session) = self.target_session(session)?;
+        if let Some(other) = action_session.as_deref() {
+            if self.zellij.query_tab_names(Some(other)).await.is_err() {
+                self.zellij.create_background_session(other).await?;
+            }
+        }
+        let plan = self.workspace_plan(workspace, session).await?;
+        let action_session = action_session.as_deref();
+
+        for tab in &workspace.tabs {
+            let missing: Vec<_> = tab
+                .panes
+                .iter()
+                .filter(|pane| {
+                    plan.changes.iter().any(
+                        |change| matches!(change, WorkspaceChange::CreatePane { pane: name, .. } if name == &pane.name),
+                    )
+                })
+                .collect();
+
+            if missing.is_empty() {
+                // Every pane is tracked elsewhere; still give the file its tab
+                if plan.changes.contains(&WorkspaceChange::CreateTab { tab: tab.name.clone() }) {
+                    self.ensure_tab_in_session(action_session, &tab.name).await?;
+                }
+                continue;
+            }
+
+            self.batch_panes(
+                action_session,
+                tab.name.clone(),
+                missing.iter().map(|pane| pane.name.clone()).collect(),
+                missing.iter().map(|pane| pane.cwd.to_string_lossy().to_string()).collect(),
+                missing.iter().map(|pane| pane.run.clone().unwrap_or_default()).collect(),
+                tab.vertical,
+            )
+            .await?;
+        }
+
+        for change in &plan.changes {
+            if let WorkspaceChange::LogIntent { pane, summary } = change {
+                self.log_intent(pane, &IntentEntry::new(summary.clone())).await?;
+            }
+        }
+
+        Ok(plan)
+    }
+
+    pub async fn reconcile(&mut self) -> Result<ReconcileResult> {
         let current_session = self
             .zellij
             .active_session_name()
-            .ok_or_else(|| anyhow!("not inside a zellij session; reconcile requires one"))?;
+            .ok_or_else(|| PerthError::ZellijUnavailable("not inside a zellij session; reconcile requires one".to_string()))?;
 
         let mut layout_panes = HashSet::new();
         let mut layout_confident = false;
@@ -283,42 +769,145 @@ impl Orchestrator {
         }
 
         let pane_names = self.state.list_pane_names().await?;
-        let mut total = 0;
-        let mut seen = 0;
-        let mut stale = 0;
-        let mut skipped = 0;
+        let mut result = ReconcileResult { session: current_session.clone(), ..Default::default() };
 
         for pane_name in pane_names {
-            total += 1;
+            result.total += 1;
             let Some(record) = self.state.get_pane(&pane_name).await? else {
-                skipped += 1;
+                result.skipped += 1;
                 continue;
             };
 
             if record.session != current_session {
-                skipped += 1;
+                result.skipped += 1;
                 continue;
             }
 
             if !layout_confident {
-                skipped += 1;
+                result.skipped += 1;
                 continue;
             }
 
             if layout_panes.contains(&record.pane_name) {
                 self.state.mark_seen(&record.pane_name).await?;
-                seen += 1;
+                if record.stale {
+                    self.events.pane_reconciled(&record).await;
+                }
+                result.seen += 1;
+                result.seen_panes.push(record.pane_name);
             } else {
-                self.state.mark_stale(&record.pane_name).await?;
-                stale += 1;
+                self.mark_stale(&record, "reconcile").await?;
+                result.stale += 1;
+                result.stale_panes.push(record.pane_name);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Apply a pane change reported by the tracker plugin to the panes
+    /// tracked in `session`: closed panes are marked stale at once, reopened
+    /// ones restored, Zellij pane IDs recorded and focus counted as access.
+    pub async fn apply_tracker_event(&mut self, session: &str, event: &PaneEvent) -> Result<Option<TrackedChange>> {
+        match event {
+            PaneEvent::Opened(pane) => self.tracker_pane_seen(session, pane).await,
+            PaneEvent::Renamed { pane, from } => {
+                // zdrive names new panes by renaming them, so this is usually a pane being created
+                if let Some(change) = self.tracker_pane_seen(session, pane).await? {
+                    return Ok(Some(change));
+                }
+                let renamed = self.tracked_pane(session, from).await?.filter(|record| {
+                    record.pane_id.as_deref() == Some(pane.id.to_string().as_str())
+                });
+                Ok(renamed.map(|record| TrackedChange::RenamedInZellij {
+                    pane: record.pane_name,
+                    title: pane.title.clone(),
+                }))
+            }
+            PaneEvent::Closed(pane) => {
+                let id = pane.id.to_string();
+                let by_id = self
+                    .state
+                    .list_all_panes()
+                    .await?
+                    .into_iter()
+                    .find(|record| record.session == session && record.pane_id.as_ref() == Some(&id));
+                let record = match by_id {
+                    Some(record) => Some(record),
+                    None => self.tracked_pane(session, &pane.title).await?,
+                };
+                let Some(record) = record.filter(|record| !record.stale) else {
+                    return Ok(None);
+                };
+                self.mark_stale(&record, "pane closed").await?;
+                Ok(Some(TrackedChange::Closed(record.pane_name)))
+            }
+            PaneEvent::Focused(pane) => {
+                if let Some(record) = self.tracked_pane(session, &pane.title).await? {
+                    self.state.touch_pane(&record.pane_name, &HashMap::new()).await?;
+                }
+                Ok(None)
             }
         }
+    }
+
+    /// Record that a tracked pane is open as `pane`, restoring it if stale.
+    async fn tracker_pane_seen(&mut self, session: &str, pane: &TrackedPane) -> Result<Option<TrackedChange>> {
+        let Some(record) = self.tracked_pane(session, &pane.title).await? else {
+            return Ok(None);
+        };
+        let id = pane.id.to_string();
+        if record.pane_id.as_ref() != Some(&id) {
+            self.state.set_pane_id(&record.pane_name, &id).await?;
+        }
+        self.state.mark_seen(&record.pane_name).await?;
+        if !record.stale {
+            return Ok(None);
+        }
+        self.events.pane_reconciled(&record).await;
+        Ok(Some(TrackedChange::Back(record.pane_name)))
+    }
+
+    async fn tracked_pane(&mut self, session: &str, pane_name: &str) -> Result<Option<PaneRecord>> {
+        Ok(self.state.get_pane(pane_name).await?.filter(|record| record.session == session))
+    }
+
+    /// Stale panes, in any session, last seen more than `older_than` ago,
+    /// oldest first. Records with an unreadable `last_seen` are kept.
+    pub async fn prune_candidates(&mut self, older_than: chrono::Duration) -> Result<Vec<PaneRecord>> {
+        let cutoff = chrono::Utc::now() - older_than;
+        let mut panes: Vec<PaneRecord> = self
+            .state
+            .list_all_panes()
+            .await?
+            .into_iter()
+            .filter(|pane| pane.stale)
+            .filter(|pane| {
+                chrono::DateTime::parse_from_rfc3339(&pane.last_seen).is_ok_and(|seen| seen < cutoff)
+            })
+            .collect();
+        panes.sort_by(|a, b| a.last_seen.cmp(&b.last_seen));
+        Ok(panes)
+    }
 
-        println!(
-            "reconcile: session={} total={} seen={} stale={} skipped={}",
-            current_session, total, seen, stale, skipped
-        );
+    /// Delete pane records and their history, publishing `perth.pane.deleted`
+    /// for each. Returns the names deleted.
+    pub async fn prune_panes(&mut self, panes: &[PaneRecord]) -> Result<Vec<String>> {
+        let mut pruned = Vec::with_capacity(panes.len());
+        for pane in panes {
+            self.state.delete_pane(&pane.pane_name).await?;
+            self.events.pane_deleted(pane, "prune").await;
+            pruned.push(pane.pane_name.clone());
+        }
+        Ok(pruned)
+    }
 
+    /// Mark a pane stale, publishing `perth.pane.stale` if it wasn't already.
+    async fn mark_stale(&mut self, record: &PaneRecord, reason: &str) -> Result<()> {
+        self.state.mark_stale(&record.pane_name).await?;
+        if !record.stale {
+            self.events.pane_stale(record, reason).await;
+        }
         Ok(())
     }
 
@@ -328,7 +917,7 @@ impl Orchestrator {
         session: Option<String>,
         meta: HashMap<String, String>,
         show_last_intent: bool,
-    ) -> Result<()> {
+    ) -> Result<PaneLocation> {
         if let Some(requested_session) = session {
             if requested_session != record.session {
                 return Err(anyhow!(
@@ -339,7 +928,7 @@ impl Orchestrator {
             }
         }
 
-        let action_session = self.ensure_session(&record.session).await?;
+        let (action_session, session_started) = self.ensure_session(&record.session).await?;
 
         if !record.tab.is_empty() && record.tab != CURRENT_TAB {
             if let Err(err) = self
@@ -347,28 +936,47 @@ impl Orchestrator {
                 .go_to_tab_name(action_session.as_deref(), &record.tab)
                 .await
             {
-                self.state.mark_stale(&record.pane_name).await?;
+                self.mark_stale(&record, "tab not found").await?;
                 return Err(err).context("failed to switch to pane tab; marked stale")?;
             }
 
-            // Auto-focus pane by position if stored
-            if let Some(position_str) = record.meta.get("position") {
-                if let Ok(position) = position_str.parse::<usize>() {
-                    if let Err(err) = self
-                        .zellij
-                        .focus_pane_by_index(action_session.as_deref(), position)
-                        .await
-                    {
-                        // Log warning but don't fail - tab is focused, pane focus is best-effort
-                        eprintln!(
-                            "Warning: Could not focus pane '{}' at position {}: {}",
-                            record.pane_name, position, err
-                        );
-                    }
+            // Focus the pane by its Zellij ID, falling back to its stored position
+            let mut focused = false;
+            if let Some(pane_id) = &record.pane_id {
+                // An unreadable layout still gets a bounded search
+                let max_panes = match self.count_panes_in_tab(action_session.as_deref(), &record.tab).await {
+                    Ok(count) if count > 0 => count,
+                    _ => FOCUS_SEARCH_LIMIT,
+                };
+                match self.zellij.focus_pane_by_id(action_session.as_deref(), pane_id, max_panes).await {
+                    Ok(found) => focused = found,
+                    Err(err) => tracing::debug!(pane = %record.pane_name, "could not focus pane by id {}: {:#}", pane_id, err),
+                }
+            }
+            let position = record.meta.get("position").and_then(|position| position.parse::<usize>().ok());
+            if let (false, Some(position)) = (focused, position) {
+                if let Err(err) = self
+                    .zellij
+                    .focus_pane_by_index(action_session.as_deref(), position)
+                    .await
+                {
+                    // Log warning but don't fail - tab is focused, pane focus is best-effort
+                    tracing::warn!(
+                        pane = %record.pane_name,
+                        session = action_session.as_deref(),
+                        "could not focus pane at position {}: {:#}",
+                        position,
+                        err
+                    );
                 }
             }
         }
 
+        // Commands only run when a pane is created
+        let mut meta = meta;
+        if meta.remove(COMMAND_META_KEY).is_some() {
+            tracing::warn!(pane = %record.pane_name, "pane already exists; not running the requested command");
+        }
         self.state.touch_pane(&record.pane_name, &meta).await?;
 
         // Publish pane.opened event
@@ -383,52 +991,16 @@ impl Orchestrator {
             }
         }
 
-        Ok(())
+        Ok(PaneLocation {
+            attached: action_session.is_none(),
+            session: record.session,
+            session_started,
+        })
     }
 
     /// Display a brief resume context when returning to a pane.
     fn display_resume_context(&self, _pane_name: &str, entry: &IntentEntry) {
-        use chrono::{Local, TimeZone};
-        use chrono_humanize::HumanTime;
-
-        // Convert to local time for relative display
-        let local_time = Local.from_utc_datetime(&entry.timestamp.naive_utc());
-        let human_time = HumanTime::from(local_time);
-
-        // Determine type icon
-        let type_icon = match entry.entry_type {
-            IntentType::Milestone => "★",
-            IntentType::Checkpoint => "●",
-            IntentType::Exploration => "◈",
-        };
-
-        // Source indicator
-        let source_indicator = match entry.source {
-            IntentSource::Agent => " 🤖",
-            IntentSource::Automated => " ⚡",
-            IntentSource::Manual => "",
-        };
-
-        // Check if terminal supports color
-        use std::io::IsTerminal;
-        let use_color = std::env::var("NO_COLOR").is_err() && std::io::stderr().is_terminal();
-
-        if use_color {
-            use colored::Colorize;
-            eprintln!(
-                "{} {} {} {}{}",
-                "Resuming:".cyan(),
-                type_icon.yellow(),
-                entry.summary.white(),
-                human_time.to_string().dimmed(),
-                source_indicator
-            );
-        } else {
-            eprintln!(
-                "Resuming: {} {} ({}){}",
-                type_icon, entry.summary, human_time, source_indicator
-            );
-        }
+        eprintln!("{}", OutputFormatter::for_stderr().format_resume_banner(entry));
     }
 
     async fn create_pane(
@@ -437,7 +1009,7 @@ impl Orchestrator {
         tab: Option<String>,
         session: Option<String>,
         meta: HashMap<String, String>,
-    ) -> Result<()> {
+    ) -> Result<PaneLocation> {
         let target_session = match session {
             Some(session) => session,
             None => self
@@ -446,7 +1018,7 @@ impl Orchestrator {
                 .ok_or_else(|| anyhow!("no active session; pass --session"))?,
         };
 
-        let action_session = self.ensure_session(&target_session).await?;
+        let (action_session, session_started) = self.ensure_session(&target_session).await?;
 
         let mut created_tab = false;
         let final_tab = if let Some(tab_name) = tab {
@@ -465,12 +1037,26 @@ impl Orchestrator {
             0 // For current tab, position tracking is unreliable; use 0 as fallback
         };
 
+        // Tools in the pane identify it by PERTH_PANE and PERTH_SESSION. A new
+        // tab's first pane is already running, so it gets them typed in.
+        let env = pane_env(&pane_name, &target_session);
+        let command = meta.get(COMMAND_META_KEY).filter(|command| !command.is_empty());
         if created_tab {
             self.zellij
                 .rename_pane(action_session.as_deref(), &pane_name)
                 .await?;
+            self.zellij
+                .write_chars(action_session.as_deref(), &export_command(&env))
+                .await?;
+            if let Some(command) = command {
+                self.zellij
+                    .write_chars(action_session.as_deref(), &format!("{}\n", command))
+                    .await?;
+            }
         } else {
-            self.zellij.new_pane(action_session.as_deref()).await?;
+            self.zellij
+                .new_pane_with_env(action_session.as_deref(), None, None, &env, command.map(String::as_str))
+                .await?;
             self.zellij
                 .rename_pane(action_session.as_deref(), &pane_name)
                 .await?;
@@ -481,37 +1067,58 @@ impl Orchestrator {
         meta_with_position.insert("position".to_string(), position.to_string());
 
         let now = StateManager::now_string();
-        let record = PaneRecord::new(pane_name, target_session, final_tab, now, meta_with_position);
+        let mut record = PaneRecord::new(pane_name, target_session, final_tab, now, meta_with_position);
+        // The new pane has focus, so this is its ID
+        record.pane_id = self.zellij.focused_pane_id(action_session.as_deref()).await.ok().flatten();
         self.state.upsert_pane(&record).await?;
 
         // Publish pane.created event
         self.events.pane_created(&record).await;
 
-        Ok(())
+        Ok(PaneLocation {
+            session: record.session,
+            attached: action_session.is_none(),
+            session_started,
+        })
     }
 
-    async fn ensure_session(&self, target_session: &str) -> Result<Option<String>> {
-        if let Some(current) = self.zellij.active_session_name() {
-            if current == target_session {
-                return Ok(None);
-            }
-            return Err(anyhow!(
+    /// The session a command targets and the `--session` to pass Zellij
+    /// actions for it (None when it's the active session). From inside
+    /// Zellij only the active session can be targeted.
+    fn target_session(&self, session: Option<&str>) -> Result<(String, Option<String>)> {
+        match (self.zellij.active_session_name(), session) {
+            (Some(active), None) => Ok((active, None)),
+            (Some(active), Some(target)) if active == target => Ok((active, None)),
+            (Some(active), Some(target)) => Err(anyhow!(
                 "target session '{}' is not active (current '{}'); detach and retry",
-                target_session,
-                current
-            ));
+                target,
+                active
+            )),
+            (None, Some(target)) => Ok((target.to_string(), Some(target.to_string()))),
+            (None, None) => Err(PerthError::ZellijUnavailable("not inside a zellij session; pass --session".to_string()).into()),
         }
+    }
 
-        match self.zellij.query_tab_names(Some(target_session)).await {
-            Ok(_) => Ok(Some(target_session.to_string())),
-            Err(_) => {
-                self.zellij.attach_session(target_session).await?;
-                Err(anyhow!(
-                    "attached to session '{}'; re-run command to continue",
-                    target_session
-                ))
-            }
+    /// The `--session` to pass Zellij actions for `target_session` (None
+    /// when it's the attached session), and whether it had to be started.
+    /// Other sessions are driven in place, even from inside Zellij; one that
+    /// isn't running is started in the background if session creation is on.
+    async fn ensure_session(&self, target_session: &str) -> Result<(Option<String>, bool)> {
+        if self.zellij.active_session_name().as_deref() == Some(target_session) {
+            return Ok((None, false));
+        }
+
+        if self.zellij.query_tab_names(Some(target_session)).await.is_ok() {
+            return Ok((Some(target_session.to_string()), false));
+        }
+        if !self.create_sessions {
+            return Err(anyhow!(
+                "session '{}' is not running; pass --create-session to start it in the background",
+                target_session
+            ));
         }
+        self.zellij.create_background_session(target_session).await?;
+        Ok((Some(target_session.to_string()), true))
     }
 
     async fn count_panes_in_tab(
@@ -552,183 +1159,1399 @@ impl Orchestrator {
     // Intent History Methods (Perth v2.0)
     // ========================================================================
 
-    /// Log an intent entry for a pane
+    /// Log an intent entry for a pane.
+    ///
+    /// Entries without a correlation ID inherit the one on the pane's tab, so
+    /// the entry and its events can be traced back to what triggered the work.
     pub async fn log_intent(&mut self, pane_name: &str, entry: &IntentEntry) -> Result<()> {
-        self.state.log_intent(pane_name, entry).await?;
+        let mut entry = entry.clone();
+        if entry.correlation_id.is_none() {
+            entry.correlation_id = self.tab_correlation_id(pane_name).await?;
+        }
+        if let Some(id) = &entry.correlation_id {
+            telemetry::set_correlation_id(id);
+        }
+        self.state.log_intent(pane_name, &entry).await?;
 
         // Publish intent.logged event (and milestone.recorded if applicable)
         let session = self.zellij.active_session_name();
-        self.events.intent_logged(pane_name, entry, session.as_deref()).await;
+        self.events.intent_logged(pane_name, &entry, session.as_deref()).await;
 
         Ok(())
     }
 
-    /// Get intent history for a pane
-    pub async fn get_history(&mut self, pane_name: &str, limit: Option<usize>) -> Result<Vec<IntentEntry>> {
-        self.state.get_history(pane_name, limit).await
+    /// Correlation ID of the tab a pane is registered to, if any.
+    async fn tab_correlation_id(&mut self, pane_name: &str) -> Result<Option<String>> {
+        let Some(pane) = self.state.get_pane(pane_name).await? else {
+            return Ok(None);
+        };
+        Ok(self
+            .state
+            .get_tab(&pane.tab, &pane.session)
+            .await?
+            .and_then(|tab| tab.correlation_id))
     }
 
-    /// Generate an LLM-powered snapshot of recent work
-    ///
-    /// Requires user consent to be granted before sending data to an LLM provider.
-    /// The 'none' provider does not require consent (no data is sent).
-    ///
-    /// Uses a circuit breaker to prevent cascading failures:
-    /// - Opens after 3 consecutive failures
-    /// - Half-opens after 5 minute cooldown
-    /// - Single success closes the circuit
-    pub async fn snapshot(&mut self, pane_name: &str, llm_config: &LLMConfig, consent_given: bool) -> Result<SnapshotResult> {
-        const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(30);
+    /// Fetch a pane's stored record without touching access times.
+    pub async fn get_pane_record(&mut self, pane_name: &str) -> Result<Option<PaneRecord>> {
+        self.state.get_pane(pane_name).await
+    }
 
-        // Check circuit breaker first (before any expensive operations)
-        if llm_config.provider != "none" {
-            LLM_CIRCUIT_BREAKER.allow_request().map_err(|msg| anyhow!("{}", msg))?;
+    /// Stored pane records matching `query`, in its order.
+    pub async fn list_panes(&mut self, query: &PaneListQuery) -> Result<Vec<PaneRecord>> {
+        let mut panes: Vec<PaneRecord> = self
+            .state
+            .list_all_panes()
+            .await?
+            .into_iter()
+            .filter(|pane| query.session.as_ref().is_none_or(|session| &pane.session == session))
+            .filter(|pane| query.tab.as_ref().is_none_or(|tab| &pane.tab == tab))
+            .filter(|pane| !query.stale_only || pane.stale)
+            .collect();
+        match query.sort {
+            PaneSort::Name => {
+                panes.sort_by(|a, b| (&a.session, &a.tab, &a.pane_name).cmp(&(&b.session, &b.tab, &b.pane_name)))
+            }
+            // RFC 3339 timestamps in UTC sort chronologically as strings
+            PaneSort::LastAccessed => panes.sort_by(|a, b| b.last_accessed.cmp(&a.last_accessed)),
         }
+        Ok(panes)
+    }
 
-        // Create LLM provider
-        let provider = create_provider(llm_config);
-        if !provider.is_available() {
-            return Err(anyhow!(
-                "LLM provider '{}' is not available. Configure API key or use a different provider.",
-                llm_config.provider
-            ));
+    /// Summary of a pane's most recent history entry.
+    pub async fn last_intent(&mut self, pane_name: &str) -> Result<Option<String>> {
+        Ok(self.state.get_history(pane_name, Some(1)).await?.into_iter().next().map(|entry| entry.summary))
+    }
+
+    /// List stored keys matching a Redis glob pattern, with their types.
+    pub async fn scan_keys(&mut self, pattern: &str) -> Result<Vec<(String, String)>> {
+        self.state.scan_keys(pattern).await
+    }
+
+    /// Publish an arbitrary event through Bloodbank. Returns false if publishing is disabled.
+    pub async fn publish_raw_event(&self, event_type: &str, payload: Value, correlation_id: Option<&str>) -> bool {
+        if !self.events.is_enabled() {
+            return false;
         }
 
-        // Check consent for providers that send data externally
-        // The 'none' provider doesn't send data, so it doesn't require consent
-        if llm_config.provider != "none" && !consent_given {
-            return Err(anyhow!(
-                "LLM consent not granted.\n\n\
-                The snapshot command sends shell history, git diff, and file information\n\
-                to '{}' for AI-powered summarization.\n\n\
-                To grant consent, run:\n\
-                  zdrive config consent --grant\n\n\
-                To see what data would be sent:\n\
-                  zdrive config consent --help",
-                llm_config.provider
-            ));
+        let mut metadata = EventMetadata::default();
+        if let Some(session) = self.zellij.active_session_name() {
+            metadata = metadata.with_session(session);
         }
+        if let Some(id) = correlation_id {
+            metadata = metadata.with_correlation_id(id);
+        }
+        self.events.publish(event_type, payload, metadata).await;
+        true
+    }
 
-        // Collect context
-        let collector = ContextCollector::new()
-            .context("failed to create context collector")?;
+    /// Publish events queued in the outbox while RabbitMQ was unavailable.
+    /// Returns how many were sent.
+    pub async fn flush_events(&self) -> Result<usize> {
+        if !self.events.is_enabled() {
+            return Err(anyhow!("Bloodbank is disabled; enable it with: zdrive config set bloodbank.enabled true"));
+        }
+        self.events.flush().await
+    }
 
-        let cwd = std::env::current_dir().ok();
-        let context = collector
-            .collect(pane_name, cwd.as_deref())
-            .context("failed to collect context")?;
+    /// Directory to run a pane's git and release checks in: the `cwd` recorded
+    /// for the pane, or the current directory when none is recorded.
+    pub async fn pane_working_dir(&mut self, pane_name: &str) -> Result<Option<PathBuf>> {
+        let record = self.state.get_pane(pane_name).await?;
+        Ok(match self.pane_cwd(pane_name, record.as_ref(), false).await {
+            Some(cwd) => Some(cwd),
+            None => std::env::current_dir().ok(),
+        })
+    }
 
-        // Get existing summary if any (to provide continuity)
-        let existing = self.state.get_history(pane_name, Some(1)).await.ok()
-            .and_then(|h| h.into_iter().next())
-            .map(|e| e.summary);
+    /// Directory the snapshot daemon samples for activity: the working
+    /// directory of the pane it summarizes, or the current one without a pane.
+    pub async fn activity_dir(&mut self, pane_name: Option<&str>) -> Result<Option<PathBuf>> {
+        match pane_name {
+            Some(pane_name) => self.pane_working_dir(pane_name).await,
+            None => Ok(std::env::current_dir().ok()),
+        }
+    }
 
-        let context = if let Some(summary) = existing {
-            context.with_existing_summary(summary)
-        } else {
-            context
+    /// Log an automated checkpoint if the pane's git branch changed since last check.
+    ///
+    /// The last seen branch is cached as `git_branch` pane metadata. Untracked panes
+    /// and directories outside a git work tree are ignored. Returns the logged entry.
+    pub async fn check_branch(&mut self, pane_name: &str, cwd: &Path) -> Result<Option<IntentEntry>> {
+        let Some(record) = self.state.get_pane(pane_name).await? else {
+            return Ok(None);
+        };
+        let Some(branch) = activity::current_branch(cwd) else {
+            return Ok(None);
         };
 
-        // Call LLM with timeout and track circuit breaker state
-        let llm_result = timeout(SNAPSHOT_TIMEOUT, provider.summarize(&context)).await;
+        let previous = record.meta.get("git_branch").map(String::as_str);
+        let entry = activity::branch_switch_entry(previous, &branch);
+        if previous != Some(branch.as_str()) {
+            self.state.set_pane_meta(pane_name, "git_branch", &branch).await?;
+        }
 
-        // Handle the result and update circuit breaker
-        let result = match llm_result {
-            Ok(Ok(result)) => {
-                // Success - close the circuit
+        if let Some(entry) = &entry {
+            self.log_intent(pane_name, entry).await?;
+        }
+        Ok(entry)
+    }
+
+    /// Log a milestone if the project in `cwd` released a new version since
+    /// the pane was last checked. The first check of a project only records
+    /// its version, as does a check after the pane moves to another project.
+    pub async fn check_release(&mut self, pane_name: &str, cwd: &Path, config: &ReleaseConfig) -> Result<Option<IntentEntry>> {
+        let Some(record) = self.state.get_pane(pane_name).await? else {
+            return Ok(None);
+        };
+        let root = release::project_root(cwd);
+        let (enabled, changelog) = config.for_project(&root);
+        if !enabled {
+            return Ok(None);
+        }
+        let Some(version) = release::detect_version(&root) else {
+            return Ok(None);
+        };
+
+        let root_str = root.to_string_lossy().to_string();
+        let same_project = record.meta.get("project_root") == Some(&root_str);
+        let previous = record.meta.get("project_version").filter(|_| same_project).map(String::as_str);
+
+        let diff = previous
+            .filter(|p| release::is_release(p, &version))
+            .and_then(|p| release::write_changelog_diff(&root, &changelog, p, &version));
+        let entry = release::release_entry(previous, &version, diff.as_deref());
+
+        if previous != Some(version.as_str()) {
+            self.state.set_pane_meta(pane_name, "project_version", &version).await?;
+        }
+        if !same_project {
+            self.state.set_pane_meta(pane_name, "project_root", &root_str).await?;
+        }
+
+        if let Some(entry) = &entry {
+            self.log_intent(pane_name, entry).await?;
+        }
+        Ok(entry)
+    }
+
+    /// Get intent history for a pane
+    pub async fn get_history(&mut self, pane_name: &str, limit: Option<usize>) -> Result<Vec<IntentEntry>> {
+        self.state.get_history(pane_name, limit).await
+    }
+
+    /// Get a page of intent history for a pane, optionally bounded by time
+    pub async fn query_history(&mut self, pane_name: &str, query: &HistoryQuery) -> Result<Vec<IntentEntry>> {
+        self.state.query_history(pane_name, query).await
+    }
+
+    /// Entries logged to a pane from now on, as they are logged
+    pub async fn follow_history(&self, pane_name: &str) -> Result<impl Stream<Item = Result<IntentEntry>> + 'static> {
+        self.state.follow_history(pane_name).await
+    }
+
+    /// Attach a note to an existing history entry.
+    ///
+    /// `entry_id` may be the full UUID or any unambiguous prefix of it, such as
+    /// the short ID shown by `pane history`. Archived entries can be annotated too.
+    pub async fn annotate(
+        &mut self,
+        pane_name: &str,
+        entry_id: &str,
+        note: String,
+        author: Option<String>,
+    ) -> Result<Annotation> {
+        let mut entries = self.state.get_history(pane_name, None).await?;
+        entries.extend(self.state.get_archived_history(pane_name).await?);
+        let entry = find_entry(&entries, pane_name, entry_id)?;
+
+        let mut annotation = Annotation::new(entry.id, note);
+        if let Some(author) = author {
+            annotation = annotation.with_author(author);
+        }
+        self.state.add_annotation(pane_name, &annotation).await?;
+        Ok(annotation)
+    }
+
+    /// Set the goal a pane is working toward. A goal that was still active
+    /// is marked abandoned and returned with the new one.
+    pub async fn set_goal(&mut self, pane_name: &str, title: &str) -> Result<(Goal, Option<Goal>)> {
+        let title = title.trim();
+        if title.is_empty() {
+            return Err(anyhow!("goal cannot be empty"));
+        }
+        let goal = Goal::new(title);
+        let replaced = self.activate_goal(pane_name, &goal).await?;
+        Ok((goal, replaced))
+    }
+
+    /// Save `goal` as the pane's active goal, abandoning the one it replaces.
+    async fn activate_goal(&mut self, pane_name: &str, goal: &Goal) -> Result<Option<Goal>> {
+        let replaced = match self.active_goal(pane_name).await? {
+            Some(mut previous) => {
+                previous.close(GoalStatus::Abandoned);
+                self.state.save_goal(pane_name, &previous).await?;
+                Some(previous)
+            }
+            None => None,
+        };
+        self.state.save_goal(pane_name, goal).await?;
+        Ok(replaced)
+    }
+
+    /// The goal a pane is currently working toward, if any.
+    pub async fn active_goal(&mut self, pane_name: &str) -> Result<Option<Goal>> {
+        Ok(self.state.get_goals(pane_name).await?.into_iter().rev().find(Goal::is_active))
+    }
+
+    /// Every goal set for a pane, oldest first.
+    pub async fn goals(&mut self, pane_name: &str) -> Result<Vec<Goal>> {
+        self.state.get_goals(pane_name).await
+    }
+
+    /// The active goal with the entries that reported progress on it.
+    pub async fn goal_progress(&mut self, pane_name: &str) -> Result<Option<GoalProgress>> {
+        let Some(goal) = self.active_goal(pane_name).await? else {
+            return Ok(None);
+        };
+        let query = HistoryQuery { since: Some(goal.set_at), include_archived: true, limit: Some(usize::MAX), ..Default::default() };
+        let mut entries: Vec<IntentEntry> = self
+            .state
+            .query_history(pane_name, &query)
+            .await?
+            .into_iter()
+            .filter(|entry| entry.goal_id == Some(goal.id))
+            .collect();
+        entries.reverse();
+        Ok(Some(GoalProgress { goal, entries }))
+    }
+
+    /// Complete a pane's active goal and log a milestone recording it, with
+    /// `note` as its goal delta. Returns the closed goal and the milestone.
+    pub async fn complete_goal(&mut self, pane_name: &str, note: Option<String>) -> Result<(Goal, IntentEntry)> {
+        let Some(mut goal) = self.active_goal(pane_name).await? else {
+            return Err(anyhow!(
+                "pane '{}' has no active goal. Set one with:\n  zdrive goal set {} \"<goal>\"",
+                pane_name,
+                pane_name
+            ));
+        };
+        goal.close(GoalStatus::Completed);
+        self.state.save_goal(pane_name, &goal).await?;
+
+        let mut entry = IntentEntry::new(format!("Completed goal: {}", goal.title))
+            .with_type(IntentType::Milestone)
+            .with_source(IntentSource::Automated)
+            .with_goal(goal.id);
+        if let Some(note) = note {
+            entry = entry.with_goal_delta(note);
+        }
+        self.log_intent(pane_name, &entry).await?;
+        Ok((goal, entry))
+    }
+
+    /// Entries that list any of `artifacts`, newest first, with the pane's
+    /// current session and tab. Index entries whose history entry is gone
+    /// are dropped from the index.
+    pub async fn who_touched(&mut self, artifacts: &[String], limit: usize) -> Result<Vec<ArtifactTouch>> {
+        let mut refs = Vec::new();
+        for artifact in artifacts {
+            for (pane_name, id) in self.state.artifact_touches(artifact).await? {
+                refs.push((artifact.clone(), pane_name, id));
+            }
+        }
+
+        let everything = HistoryQuery { include_archived: true, limit: Some(usize::MAX), ..Default::default() };
+        let mut histories: HashMap<String, Vec<IntentEntry>> = HashMap::new();
+        let mut seen = HashSet::new();
+        let mut touches = Vec::new();
+        for (artifact, pane_name, id) in refs {
+            if !seen.insert(id) {
+                continue;
+            }
+            if !histories.contains_key(&pane_name) {
+                let entries = self.state.query_history(&pane_name, &everything).await?;
+                histories.insert(pane_name.clone(), entries);
+            }
+            let Some(entry) = histories[&pane_name].iter().find(|entry| entry.id == id).cloned() else {
+                self.state.unindex_artifact(&artifact, &pane_name, &id).await?;
+                continue;
+            };
+            let record = self.state.get_pane(&pane_name).await?;
+            touches.push(ArtifactTouch {
+                session: record.as_ref().map(|r| r.session.clone()),
+                tab: record.map(|r| r.tab),
+                pane: pane_name,
+                entry,
+            });
+        }
+
+        touches.sort_by_key(|touch| std::cmp::Reverse(touch.entry.timestamp));
+        touches.truncate(limit);
+        Ok(touches)
+    }
+
+    /// Artifacts listed in a pane's live and archived history, most recently
+    /// logged first. With `check`, each one's path is checked for existence.
+    pub async fn pane_artifacts(&mut self, pane_name: &str, check: bool) -> Result<Vec<ArtifactReport>> {
+        let everything = HistoryQuery { include_archived: true, limit: Some(usize::MAX), ..Default::default() };
+        let entries = self.state.query_history(pane_name, &everything).await?;
+        let mut reports = artifacts::collect(&entries);
+        if check {
+            for report in &mut reports {
+                report.status = Some(artifacts::check(&report.path));
+            }
+        }
+        Ok(reports)
+    }
+
+    /// Rewrite artifact paths under `old` to sit under `new` in a pane's
+    /// live history, keeping the artifact index in step. Archived entries
+    /// are read-only; they are only counted.
+    pub async fn remap_artifacts(&mut self, pane_name: &str, old: &str, new: &str, dry_run: bool) -> Result<RemapResult> {
+        let live = HistoryQuery { limit: Some(usize::MAX), ..Default::default() };
+        let mut result = RemapResult::default();
+        for entry in self.state.query_history(pane_name, &live).await? {
+            let changes: Vec<(String, String)> = entry
+                .artifacts
+                .iter()
+                .filter_map(|artifact| artifacts::remap(artifact, old, new).map(|to| (artifact.clone(), to)))
+                .collect();
+            if changes.is_empty() {
+                continue;
+            }
+
+            if !dry_run {
+                let updated = self
+                    .state
+                    .update_entry(pane_name, entry.id, |entry| {
+                        let mut changed = false;
+                        for artifact in entry.artifacts.iter_mut() {
+                            if let Some(to) = artifacts::remap(artifact, old, new) {
+                                *artifact = to;
+                                changed = true;
+                            }
+                        }
+                        changed
+                    })
+                    .await?;
+                // Pruned since it was read
+                if updated.is_none() {
+                    continue;
+                }
+                for (from, _) in &changes {
+                    self.state.unindex_artifact(from, pane_name, &entry.id).await?;
+                }
+            }
+
+            result.entries_updated += 1;
+            for (from, to) in changes {
+                if !result.paths.iter().any(|path| path.from == from) {
+                    result.paths.push(RemappedPath { from, to });
+                }
+            }
+        }
+
+        result.archived_entries = self
+            .state
+            .get_archived_history(pane_name)
+            .await?
+            .iter()
+            .filter(|entry| entry.artifacts.iter().any(|artifact| artifacts::remap(artifact, old, new).is_some()))
+            .count();
+        result.paths.sort_by(|a, b| a.from.cmp(&b.from));
+        Ok(result)
+    }
+
+    /// Rebuild the artifact index from every pane's live and archived
+    /// history. Returns how many (artifact, entry) pairs were indexed.
+    pub async fn reindex_artifacts(&mut self) -> Result<usize> {
+        let everything = HistoryQuery { include_archived: true, limit: Some(usize::MAX), ..Default::default() };
+        let mut count = 0;
+        for pane_name in self.state.list_pane_names().await? {
+            let entries = self.state.query_history(&pane_name, &everything).await?;
+            count += self.state.index_artifacts(&pane_name, &entries).await?;
+        }
+        Ok(count)
+    }
+
+    /// Add artifacts to an entry already in a pane's history, instead of
+    /// logging a duplicate entry.
+    ///
+    /// `entry_id` is `last` for the newest entry, or a full UUID or unambiguous
+    /// prefix. Only live entries can be changed. Artifacts the entry already
+    /// lists are skipped. Returns the updated entry and how many were added.
+    pub async fn append_artifacts(
+        &mut self,
+        pane_name: &str,
+        entry_id: &str,
+        artifacts: &[String],
+    ) -> Result<(IntentEntry, usize)> {
+        let entries = self.state.get_history(pane_name, None).await?;
+        let id = if entry_id.trim().eq_ignore_ascii_case("last") {
+            entries
+                .first()
+                .ok_or_else(|| PerthError::NotFound(format!("pane '{}' has no history entries", pane_name)))?
+                .id
+        } else {
+            find_entry(&entries, pane_name, entry_id)?.id
+        };
+
+        let mut added = 0;
+        let entry = self
+            .state
+            .update_entry(pane_name, id, |entry| {
+                added = 0;
+                for artifact in artifacts {
+                    if !entry.artifacts.contains(artifact) {
+                        entry.artifacts.push(artifact.clone());
+                        added += 1;
+                    }
+                }
+                added > 0
+            })
+            .await?
+            .ok_or_else(|| PerthError::StaleState(format!("entry '{}' is no longer in pane '{}' history", entry_id, pane_name)))?;
+        Ok((entry, added))
+    }
+
+    /// Get all annotations for a pane, oldest first
+    pub async fn get_annotations(&mut self, pane_name: &str) -> Result<Vec<Annotation>> {
+        self.state.get_annotations(pane_name).await
+    }
+
+    /// Resolve the pane the user is working in.
+    pub async fn current_pane(&mut self) -> Result<String> {
+        Ok(self.infer_current_pane().await?.0)
+    }
+
+    /// Resolve the pane the user is working in, and how.
+    ///
+    /// Tries, in order: the pane named by `PERTH_PANE`; the tracked pane whose
+    /// recorded Zellij pane ID is `ZELLIJ_PANE_ID`; the tracked pane that has
+    /// focus in the active session; and the pane in the active session whose
+    /// working directory holds the current directory. Anything else is an
+    /// error rather than a guess, so entries never land in the wrong pane.
+    pub async fn infer_current_pane(&mut self) -> Result<(String, PaneInference)> {
+        let session = self.zellij.active_session_name();
+        let zellij_pane_id = std::env::var("ZELLIJ_PANE_ID").ok().filter(|id| !id.is_empty());
+
+        if let Some(pane) = std::env::var("PERTH_PANE").ok().filter(|pane| !pane.is_empty()) {
+            // Remember the pane ID so tools that don't inherit PERTH_PANE can find it
+            if let (Some(id), Some(record)) = (&zellij_pane_id, self.state.get_pane(&pane).await?) {
+                if session.as_ref() == Some(&record.session) && record.pane_id.as_ref() != Some(id) {
+                    self.state.set_pane_id(&pane, id).await?;
+                }
+            }
+            return Ok((pane, PaneInference::Env));
+        }
+
+        let session = session.ok_or_else(|| anyhow!("no active session; pass a pane name"))?;
+
+        let panes: Vec<PaneRecord> = self
+            .state
+            .list_all_panes()
+            .await?
+            .into_iter()
+            .filter(|pane| !pane.stale && pane.session == session)
+            .collect();
+        let with_id = |id: &String| panes.iter().find(|pane| pane.pane_id.as_ref() == Some(id)).map(|pane| pane.pane_name.clone());
+
+        if let Some(pane) = zellij_pane_id.as_ref().and_then(with_id) {
+            return Ok((pane, PaneInference::PaneId));
+        }
+
+        let focused = match self.zellij.focused_pane_id(Some(&session)).await {
+            Ok(focused) => focused,
+            Err(e) => {
+                tracing::debug!("failed to read the focused pane: {:#}", e);
+                None
+            }
+        };
+        if let Some(pane) = focused.as_ref().and_then(with_id) {
+            return Ok((pane, PaneInference::Focused));
+        }
+
+        if let Ok(cwd) = std::env::current_dir() {
+            let cwd = canonical_path(&cwd);
+            // The deepest pane directory holding the current one wins
+            let pane = self
+                .pane_cwds(Some(&session))
+                .await?
+                .into_iter()
+                .filter(|(_, pane_cwd)| cwd.starts_with(pane_cwd))
+                .fold(None::<(String, PathBuf)>, |best, (name, pane_cwd)| match &best {
+                    Some((_, best_cwd)) if best_cwd.components().count() >= pane_cwd.components().count() => best,
+                    _ => Some((name, pane_cwd)),
+                });
+            if let Some((pane, _)) = pane {
+                return Ok((pane, PaneInference::Cwd));
+            }
+        }
+
+        Err(PerthError::NotFound(format!(
+            "can't tell which tracked pane this is in session '{}'; pass a pane name or set PERTH_PANE",
+            session
+        ))
+        .into())
+    }
+
+    /// Working directories of live tracked panes, active session first, then
+    /// most recently accessed first. Only panes in `session` are included
+    /// when it's given. Panes without a recorded `cwd` are looked up in the
+    /// active session's layout, and panes with neither are left out.
+    async fn pane_cwds(&mut self, session: Option<&str>) -> Result<Vec<(String, PathBuf)>> {
+        let active = self.zellij.active_session_name();
+        let mut panes: Vec<PaneRecord> = self
+            .state
+            .list_all_panes()
+            .await?
+            .into_iter()
+            .filter(|pane| !pane.stale && session.is_none_or(|session| pane.session == session))
+            .collect();
+        panes.sort_by_key(|pane| (Some(&pane.session) != active.as_ref(), std::cmp::Reverse(pane.last_accessed.clone())));
+
+        let layout = match active {
+            Some(_) => self.zellij.dump_layout_json(None).await.ok().flatten(),
+            None => None,
+        };
+
+        Ok(panes
+            .into_iter()
+            .filter_map(|pane| {
+                let cwd = pane.meta.get("cwd").map(String::as_str).or_else(|| {
+                    layout
+                        .as_ref()
+                        .filter(|_| Some(&pane.session) == active.as_ref())
+                        .and_then(|layout| find_pane_cwd(layout, &pane.pane_name))
+                })?;
+                Some((pane.pane_name.clone(), canonical_path(Path::new(cwd))))
+            })
+            .collect())
+    }
+
+    /// Count a command run in the current pane, for the shell hook, and log
+    /// an Automated checkpoint once the buffered activity crosses the
+    /// thresholds. Returns the pane and the entry, if one was logged.
+    pub async fn record_shell_command(&mut self, hooks: &HooksConfig, cwd: Option<&Path>) -> Result<Option<(String, IntentEntry)>> {
+        let pane = self.current_pane().await?;
+        let (commands, since) = self.state.buffer_hook_command(&pane).await?;
+        let elapsed = chrono::Utc::now() - since;
+        if !hooks.crossed(commands, elapsed) || !self.state.take_hook_buffer(&pane).await? {
+            return Ok(None);
+        }
+
+        let entry = IntentEntry::new(hook::checkpoint_summary(commands, elapsed, cwd))
+            .with_source(IntentSource::Automated)
+            .with_commands_run(commands);
+        self.log_intent(&pane, &entry).await?;
+        Ok(Some((pane, entry)))
+    }
+
+    /// Log the commit at HEAD of the repository containing `cwd`, for the git
+    /// post-commit hook, to the pane working in that repository. Returns the
+    /// pane and the entry, or `None` if no tracked pane is working there.
+    pub async fn log_commit(&mut self, cwd: &Path) -> Result<Option<(String, IntentEntry)>> {
+        let root = release::project_root(cwd);
+        let Some(commit) = activity::last_commit(&root) else {
+            return Ok(None);
+        };
+        let Some(pane) = self.pane_in_dir(&root).await? else {
+            return Ok(None);
+        };
+
+        let entry = activity::commit_entry(&commit);
+        self.log_intent(&pane, &entry).await?;
+        Ok(Some((pane, entry)))
+    }
+
+    /// The tracked pane working in `dir`: the most recently accessed pane
+    /// whose working directory is inside it, preferring the active session.
+    pub async fn pane_in_dir(&mut self, dir: &Path) -> Result<Option<String>> {
+        let dir = canonical_path(dir);
+        Ok(self
+            .pane_cwds(None)
+            .await?
+            .into_iter()
+            .find(|(_, cwd)| cwd.starts_with(&dir))
+            .map(|(pane, _)| pane))
+    }
+
+    /// Tracked panes for `zdrive jump`, most recently accessed first, each
+    /// with its latest intent. Stale panes are left out unless asked for.
+    pub async fn jump_candidates(&mut self, include_stale: bool) -> Result<Vec<jump::Candidate>> {
+        let mut panes: Vec<PaneRecord> = self
+            .state
+            .list_all_panes()
+            .await?
+            .into_iter()
+            .filter(|pane| include_stale || !pane.stale)
+            .collect();
+        panes.sort_by(|a, b| b.last_accessed.cmp(&a.last_accessed));
+
+        let mut candidates = Vec::with_capacity(panes.len());
+        for pane in panes {
+            let last_intent = self.state.get_history(&pane.pane_name, Some(1)).await?.into_iter().next();
+            candidates.push(jump::Candidate {
+                pane_name: pane.pane_name,
+                tab: pane.tab,
+                session: pane.session,
+                last_intent: last_intent.map(|entry| entry.summary),
+            });
+        }
+        Ok(candidates)
+    }
+
+    /// Take over work from a handoff bundle.
+    ///
+    /// Opens (or creates) the target pane, records the origin pane as metadata,
+    /// sets the bundle's goal as the pane's active goal (keeping its ID, so
+    /// imported progress still counts), and seeds its history with the
+    /// bundle's entries. The target
+    /// pane must not already have history so the imported timeline stays ordered.
+    pub async fn takeover(
+        &mut self,
+        pane_name: String,
+        tab: Option<String>,
+        session: Option<String>,
+        bundle: HandoffBundle,
+    ) -> Result<TakeoverResult> {
+        if self.state.get_history_count(&pane_name).await? > 0 {
+            return Err(anyhow!(
+                "pane '{}' already has intent history; take over into a fresh pane",
+                pane_name
+            ));
+        }
+
+        let mut meta = HashMap::new();
+        if let Some(from) = &bundle.pane {
+            meta.insert("handoff_from".to_string(), from.clone());
+        }
+
+        self.open_pane(pane_name.clone(), tab, session, meta, false).await?;
+
+        if let Some(title) = &bundle.goal {
+            // Keep the exported goal's identity so imported entries that
+            // reported progress on it still count toward it
+            let mut goal = Goal::new(title.trim());
+            if let Some(id) = bundle.goal_id {
+                goal.id = id;
+            }
+            if let Some(set_at) = bundle.goal_set_at {
+                goal.set_at = set_at;
+            }
+            // Markdown times are to the minute, which can land before set_at
+            let linked = bundle.entries.iter().filter(|e| e.goal_id == Some(goal.id));
+            if let Some(earliest) = linked.map(|e| e.timestamp).min() {
+                goal.set_at = goal.set_at.min(earliest);
+            }
+            self.activate_goal(&pane_name, &goal).await.context("failed to set the handed-off goal")?;
+        }
+
+        // History is newest-first, so seed oldest-first to preserve ordering
+        for entry in bundle.entries.iter().rev() {
+            self.state
+                .log_intent(&pane_name, entry)
+                .await
+                .context("failed to seed imported history")?;
+        }
+
+        // Entries keep their IDs when seeded, so annotation threads carry over as-is
+        let seeded: HashSet<_> = bundle.entries.iter().map(|e| e.id).collect();
+        for annotation in bundle.annotations.iter().filter(|a| seeded.contains(&a.entry_id)) {
+            self.state
+                .add_annotation(&pane_name, annotation)
+                .await
+                .context("failed to seed imported annotations")?;
+        }
+
+        Ok(TakeoverResult {
+            pane_name,
+            handoff_from: bundle.pane,
+            goal: bundle.goal,
+            entries_imported: bundle.entries.len(),
+        })
+    }
+
+    /// Generate an LLM-powered snapshot of recent work
+    ///
+    /// Requires user consent to be granted before sending data to an LLM provider.
+    /// The 'none' provider does not require consent (no data is sent); it
+    /// summarizes locally from the branch, git status and recent commands.
+    ///
+    /// Uses a circuit breaker to prevent cascading failures:
+    /// - Opens after 3 consecutive failures
+    /// - Half-opens after 5 minute cooldown
+    /// - Single success closes the circuit
+    ///
+    /// Transient API errors are retried inside the provider (see `llm.max_retries`),
+    /// so only a request that fails every attempt counts as a breaker failure.
+    ///
+    /// The raw LLM response is streamed to `on_text` for progress display.
+    /// A response that is not the requested JSON gets one repair request;
+    /// if that fails too, nothing is logged. Ctrl-C while waiting on the LLM
+    /// cancels the snapshot before anything is written.
+    ///
+    /// When the LLM cannot be used (unavailable, no consent, breaker open,
+    /// error or timeout), `fallback` is asked whether to log a local heuristic
+    /// summary instead; returning false reports the LLM error.
+    pub async fn snapshot(
+        &mut self,
+        pane_name: &str,
+        llm_config: &LLMConfig,
+        consent_given: bool,
+        tags: &[String],
+        fallback: &mut (dyn FnMut(&anyhow::Error) -> bool + Send),
+        on_text: &mut (dyn for<'s> FnMut(&'s str) + Send),
+    ) -> Result<SnapshotResult> {
+        let (result, summarizer, cost_usd) =
+            match self.summarize_snapshot(pane_name, llm_config, consent_given, on_text).await? {
+                Ok(summarized) => summarized,
+                Err(e) if !fallback(&e) => return Err(e),
+                Err(_) => {
+                    let context = self.collect_snapshot_context(pane_name).await?;
+                    let provider = HeuristicProvider::new();
+                    (provider.summarize(&context).await?, provider.name(), None)
+                }
+            };
+
+        // Determine entry type from LLM suggestion
+        let entry_type = match result.suggested_type.as_deref() {
+            Some("milestone") => IntentType::Milestone,
+            Some("exploration") => IntentType::Exploration,
+            _ => IntentType::Checkpoint,
+        };
+
+        // Create and store the intent entry
+        let mut entry = IntentEntry::new(&result.summary)
+            .with_type(entry_type)
+            .with_source(IntentSource::Automated)
+            .with_artifacts(result.key_files.clone())
+            .with_tags(tags.to_vec());
+        entry.correlation_id = self.tab_correlation_id(pane_name).await?;
+
+        self.state.log_intent(pane_name, &entry).await
+            .context("failed to log generated intent")?;
+
+        Ok(SnapshotResult {
+            summary: result.summary,
+            entry_type,
+            key_files: result.key_files,
+            tokens_used: result.tokens_used,
+            cost_usd,
+            summarizer: summarizer.to_string(),
+            tags: entry.tags,
+        })
+    }
+
+    /// The LLM part of `snapshot`: returns the summary, the summarizer's name
+    /// and the estimated cost. The inner error is an LLM failure a fallback
+    /// may cover; the outer one (including Ctrl-C) ends the snapshot.
+    async fn summarize_snapshot(
+        &mut self,
+        pane_name: &str,
+        llm_config: &LLMConfig,
+        consent_given: bool,
+        on_text: &mut (dyn for<'s> FnMut(&'s str) + Send),
+    ) -> Result<Result<(SummarizationResult, &'static str, Option<f64>)>> {
+        let llm_timeout = llm_config.timeout();
+
+        // Check circuit breaker first (before any expensive operations)
+        if llm_config.provider != "none" {
+            if let Err(msg) = LLM_CIRCUIT_BREAKER.allow_request() {
+                return Ok(Err(PerthError::LlmError(msg.to_string()).into()));
+            }
+        }
+
+        // Create LLM provider
+        let provider = create_provider(llm_config);
+        if !provider.is_available() {
+            return Ok(Err(PerthError::LlmError(format!(
+                "LLM provider '{}' is not available. Configure API key or use a different provider.",
+                llm_config.provider
+            ))
+            .into()));
+        }
+
+        // Check consent for providers that send data externally
+        // The 'none' provider doesn't send data, so it doesn't require consent
+        if llm_config.provider != "none" && !consent_given {
+            return Ok(Err(PerthError::ConsentRequired(format!(
+                "LLM consent not granted.\n\n\
+                The snapshot command sends shell history, git diff, and file information\n\
+                to '{}' for AI-powered summarization.\n\n\
+                To grant consent, run:\n\
+                  zdrive config consent --grant\n\n\
+                To see what data would be sent:\n\
+                  zdrive config consent --help",
+                llm_config.provider
+            ))
+            .into()));
+        }
+
+        let context = self.collect_snapshot_context(pane_name).await?;
+        let context = fit_to_budget(context, llm_config.context_budget(), &self.context_config.weights);
+
+        // Call LLM with timeout and track circuit breaker state. Nothing has
+        // been written yet, so an interrupt can simply abandon the request.
+        let span = llm_span("summarize", provider.as_ref());
+        let started = std::time::Instant::now();
+        let llm_result = tokio::select! {
+            result = timeout(llm_timeout, summarize_checked(provider.as_ref(), &context, on_text)) => result,
+            _ = tokio::signal::ctrl_c() => {
+                return Err(anyhow!("snapshot cancelled; nothing was logged for '{}'", pane_name));
+            }
+        };
+        record_llm_metrics(provider.name(), &llm_result, started.elapsed(), span);
+
+        // Handle the result and update circuit breaker
+        let result = match llm_result {
+            Ok(Ok(result)) => {
+                // Success - close the circuit
                 if llm_config.provider != "none" {
                     LLM_CIRCUIT_BREAKER.record_success();
                 }
                 result
             }
             Ok(Err(e)) => {
-                // LLM error - record failure
-                if llm_config.provider != "none" {
-                    LLM_CIRCUIT_BREAKER.record_failure();
-                }
-                return Err(e).context("LLM summarization failed");
+                // LLM error - record failure
+                if llm_config.provider != "none" {
+                    LLM_CIRCUIT_BREAKER.record_failure();
+                }
+                return Ok(Err(e).context(PerthError::LlmError("LLM summarization failed".to_string())));
+            }
+            Err(_) => {
+                // Timeout - record failure
+                if llm_config.provider != "none" {
+                    LLM_CIRCUIT_BREAKER.record_failure();
+                }
+                return Ok(Err(PerthError::LlmError(format!(
+                    "LLM request timed out after {} seconds.\n\n\
+                    Slow local models may need longer; retry with --timeout SECS or:\n\
+                    zdrive config set llm.timeout_secs 120\n\n\
+                    You can still log entries manually:\n\
+                    zdrive pane log {} \"<your summary>\"",
+                    llm_timeout.as_secs(),
+                    pane_name
+                ))
+                .into()));
+            }
+        };
+
+        // Account tokens and spend; providers that only report a total are
+        // counted as all input
+        let mut cost_usd = None;
+        if llm_config.provider != "none" {
+            let input_tokens = result.input_tokens.or(result.tokens_used).unwrap_or(0);
+            let output_tokens = result.output_tokens.unwrap_or(0);
+            cost_usd = estimate_cost(provider.name(), provider.model(), input_tokens, output_tokens);
+            record_llm_tokens(provider.name(), input_tokens, output_tokens);
+            let usage = LlmUsage::request(provider.name(), provider.model(), input_tokens, output_tokens, cost_usd);
+            if let Err(e) = self.state.record_llm_usage(&usage).await {
+                tracing::warn!(pane = pane_name, provider = provider.name(), "failed to record LLM usage: {:#}", e);
+            }
+        }
+
+        Ok(Ok((result, provider.name(), cost_usd)))
+    }
+
+    /// Cumulative LLM usage per month, provider and model (optionally one month).
+    pub async fn llm_usage(&mut self, month: Option<&str>) -> Result<Vec<LlmUsage>> {
+        self.state.get_llm_usage(month).await
+    }
+
+    /// Answer a free-form question about past work from intent history.
+    ///
+    /// Entries since `options.since` (live and archived) from one pane, or
+    /// every known pane, are ranked by keyword relevance; the best
+    /// `options.limit` are sent with the question and the answer is streamed
+    /// to `on_text` as it arrives.
+    pub async fn ask(
+        &mut self,
+        question: &str,
+        options: &AskOptions,
+        llm_config: &LLMConfig,
+        consent_given: bool,
+        on_text: &mut (dyn for<'s> FnMut(&'s str) + Send),
+    ) -> Result<AskResult> {
+        let query = HistoryQuery { since: options.since, include_archived: true, limit: Some(usize::MAX), ..Default::default() };
+        let candidates = self.collect_pane_entries(options.pane.as_deref(), &query).await?;
+        if candidates.is_empty() {
+            return Err(anyhow!("no intent history to answer from"));
+        }
+
+        let sources = ask::select_entries(candidates, question, options.limit);
+        let prompt = ask::build_prompt(question, &sources);
+        let purpose = "The ask command sends your question and matching intent history";
+        let (completion, cost_usd) = self.complete(&prompt, llm_config, consent_given, purpose, on_text).await?;
+
+        Ok(AskResult { completion, sources, cost_usd })
+    }
+
+    /// Intent entries from every pane between `since` and `until`, grouped
+    /// by day and pane. With `llm_config`, an executive summary is added.
+    pub async fn digest(
+        &mut self,
+        since: chrono::DateTime<chrono::Utc>,
+        until: chrono::DateTime<chrono::Utc>,
+        llm: Option<(&LLMConfig, bool)>,
+    ) -> Result<Digest> {
+        let query = HistoryQuery {
+            since: Some(since),
+            until: Some(until),
+            include_archived: true,
+            limit: Some(usize::MAX),
+            ..Default::default()
+        };
+        let entries = self.collect_pane_entries(None, &query).await?;
+        let mut digest = Digest::build(entries, since, until);
+
+        if let Some((llm_config, consent_given)) = llm {
+            if digest.days.is_empty() {
+                return Ok(digest);
+            }
+            let purpose = "The digest --summary option sends the digest's intent summaries";
+            let (completion, _) = self
+                .complete(&digest.summary_prompt(), llm_config, consent_given, purpose, &mut |_: &str| {})
+                .await?;
+            digest.summary = Some(completion.text);
+        }
+
+        Ok(digest)
+    }
+
+    /// Approximate hours per pane, tab or tag between `since` and `until`,
+    /// inferred from when entries were logged. Entries further apart than
+    /// `gap` start a new work session.
+    pub async fn time_report(
+        &mut self,
+        since: chrono::DateTime<chrono::Utc>,
+        until: chrono::DateTime<chrono::Utc>,
+        by: ReportGroup,
+        gap: chrono::Duration,
+    ) -> Result<TimeReport> {
+        let query = HistoryQuery {
+            since: Some(since),
+            until: Some(until),
+            include_archived: true,
+            limit: Some(usize::MAX),
+            ..Default::default()
+        };
+        let entries = self.collect_pane_entries(None, &query).await?;
+        let tabs: HashMap<String, String> =
+            self.state.list_all_panes().await?.into_iter().map(|pane| (pane.pane_name, pane.tab)).collect();
+        Ok(TimeReport::build(entries, &tabs, by, gap, since, until))
+    }
+
+    /// History and annotations for one pane or every known pane, in pane
+    /// name order, for exporting to files. Panes without entries are left
+    /// out.
+    pub async fn export_histories(&mut self, pane_name: Option<&str>, include_archived: bool) -> Result<Vec<HistoryOutput>> {
+        let mut pane_names = match pane_name {
+            Some(name) => vec![name.to_string()],
+            None => self.state.list_pane_names().await?,
+        };
+        pane_names.sort();
+
+        let query = HistoryQuery { include_archived, limit: Some(usize::MAX), ..Default::default() };
+        let mut histories = Vec::new();
+        for pane in pane_names {
+            let entries = self.state.query_history(&pane, &query).await?;
+            if entries.is_empty() {
+                continue;
+            }
+            let annotations = self.state.get_annotations(&pane).await?;
+            histories.push(HistoryOutput { pane, goal: None, goal_id: None, goal_set_at: None, entries, annotations });
+        }
+        Ok(histories)
+    }
+
+    /// A note per pane with history, with the session and tab of panes
+    /// that are still tracked, for `sync obsidian`.
+    pub async fn obsidian_notes(&mut self, include_archived: bool) -> Result<Vec<PaneNote>> {
+        let histories = self.export_histories(None, include_archived).await?;
+        let records: HashMap<String, PaneRecord> =
+            self.state.list_all_panes().await?.into_iter().map(|pane| (pane.pane_name.clone(), pane)).collect();
+        Ok(histories
+            .into_iter()
+            .map(|history| {
+                let record = records.get(&history.pane);
+                PaneNote {
+                    session: record.map(|r| r.session.clone()),
+                    tab: record.map(|r| r.tab.clone()),
+                    history,
+                }
+            })
+            .collect())
+    }
+
+    /// Activity statistics between `since` and `until`, for one pane or
+    /// every known pane, listing the `top` busiest panes.
+    pub async fn activity_stats(
+        &mut self,
+        pane_name: Option<&str>,
+        since: chrono::DateTime<chrono::Utc>,
+        until: chrono::DateTime<chrono::Utc>,
+        top: usize,
+    ) -> Result<ActivityStats> {
+        let query = HistoryQuery {
+            since: Some(since),
+            until: Some(until),
+            include_archived: true,
+            limit: Some(usize::MAX),
+            ..Default::default()
+        };
+        let entries = self.collect_pane_entries(pane_name, &query).await?;
+        Ok(ActivityStats::build(entries, since, until, top))
+    }
+
+    /// Tag frequencies per pane across live and archived history, for one
+    /// pane or every known pane. Panes with no tagged entries are left out.
+    pub async fn tag_counts(&mut self, pane_name: Option<&str>) -> Result<Vec<PaneTags>> {
+        let pane_names = match pane_name {
+            Some(name) => vec![name.to_string()],
+            None => self.state.list_pane_names().await?,
+        };
+        let query = HistoryQuery { include_archived: true, limit: Some(usize::MAX), ..Default::default() };
+        let mut panes = Vec::new();
+        for name in pane_names {
+            let entries = self.state.query_history(&name, &query).await?;
+            let tags: Vec<TagCount> = tag_counts(&entries).into_iter().map(|(tag, count)| TagCount { tag, count }).collect();
+            if !tags.is_empty() {
+                panes.push(PaneTags { pane: name, tags });
+            }
+        }
+        panes.sort_by(|a, b| a.pane.cmp(&b.pane));
+        Ok(panes)
+    }
+
+    /// History entries matching `query` from one pane, or every known pane.
+    async fn collect_pane_entries(&mut self, pane_name: Option<&str>, query: &HistoryQuery) -> Result<Vec<PaneEntry>> {
+        let pane_names = match pane_name {
+            Some(name) => vec![name.to_string()],
+            None => self.state.list_pane_names().await?,
+        };
+        let mut entries = Vec::new();
+        for name in pane_names {
+            for entry in self.state.query_history(&name, query).await? {
+                entries.push(PaneEntry { pane_name: name.clone(), entry });
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Send a free-form prompt to the configured LLM, streaming the answer
+    /// to `on_text`, behind the same consent and circuit breaker checks as
+    /// `snapshot`. The prompt is secret-filtered first and usage recorded.
+    /// `purpose` completes the consent error: "<purpose> to '<provider>'".
+    async fn complete(
+        &mut self,
+        prompt: &str,
+        llm_config: &LLMConfig,
+        consent_given: bool,
+        purpose: &str,
+        on_text: &mut (dyn for<'s> FnMut(&'s str) + Send),
+    ) -> Result<(Completion, Option<f64>)> {
+        // Answers are longer than snapshot summaries and arrive incrementally
+        const COMPLETION_TIMEOUT: Duration = Duration::from_secs(120);
+
+        if llm_config.provider == "none" {
+            return Err(PerthError::LlmError(
+                "this command needs an LLM provider. Configure one with:\n  zdrive config set llm.provider anthropic".to_string(),
+            )
+            .into());
+        }
+        LLM_CIRCUIT_BREAKER.allow_request().map_err(|msg| PerthError::LlmError(msg.to_string()))?;
+
+        let provider = create_provider(llm_config);
+        if !provider.is_available() {
+            return Err(PerthError::LlmError(format!(
+                "LLM provider '{}' is not available. Configure API key or use a different provider.",
+                llm_config.provider
+            ))
+            .into());
+        }
+        if !consent_given {
+            return Err(PerthError::ConsentRequired(format!(
+                "LLM consent not granted.\n\n\
+                {}\n\
+                to '{}'.\n\n\
+                To grant consent, run:\n\
+                  zdrive config consent --grant",
+                purpose,
+                llm_config.provider
+            ))
+            .into());
+        }
+
+        let prompt = SecretFilter::with_config(&self.filter_config)?.filter(prompt).text;
+        let span = llm_span("complete", provider.as_ref());
+        let started = std::time::Instant::now();
+        let completion = timeout(COMPLETION_TIMEOUT, provider.complete(&prompt, on_text)).await;
+        record_llm_metrics(provider.name(), &completion, started.elapsed(), span);
+        let completion = match completion {
+            Ok(Ok(completion)) => {
+                LLM_CIRCUIT_BREAKER.record_success();
+                completion
+            }
+            Ok(Err(e)) => {
+                LLM_CIRCUIT_BREAKER.record_failure();
+                return Err(e).context(PerthError::LlmError("LLM request failed".to_string()));
             }
             Err(_) => {
-                // Timeout - record failure
-                if llm_config.provider != "none" {
-                    LLM_CIRCUIT_BREAKER.record_failure();
-                }
-                return Err(anyhow!(
-                    "LLM request timed out after {} seconds.\n\n\
-                    You can still log entries manually:\n\
-                    zdrive pane log {} \"<your summary>\"",
-                    SNAPSHOT_TIMEOUT.as_secs(),
-                    pane_name
-                ));
+                LLM_CIRCUIT_BREAKER.record_failure();
+                return Err(PerthError::LlmError(format!("LLM request timed out after {} seconds", COMPLETION_TIMEOUT.as_secs())).into());
             }
         };
 
-        // Determine entry type from LLM suggestion
-        let entry_type = match result.suggested_type.as_deref() {
-            Some("milestone") => IntentType::Milestone,
-            Some("exploration") => IntentType::Exploration,
-            _ => IntentType::Checkpoint,
+        // Streams that end without usage are estimated from the text
+        let input_tokens = completion.input_tokens.unwrap_or_else(|| estimate_tokens(&prompt));
+        let output_tokens = completion.output_tokens.unwrap_or_else(|| estimate_tokens(&completion.text));
+        let cost_usd = estimate_cost(provider.name(), provider.model(), input_tokens, output_tokens);
+        record_llm_tokens(provider.name(), input_tokens, output_tokens);
+        let usage = LlmUsage::request(provider.name(), provider.model(), input_tokens, output_tokens, cost_usd);
+        if let Err(e) = self.state.record_llm_usage(&usage).await {
+            tracing::warn!(provider = provider.name(), "failed to record LLM usage: {:#}", e);
+        }
+
+        Ok((completion, cost_usd))
+    }
+
+    /// Show what `snapshot` would send to the LLM without calling it:
+    /// the assembled prompt (after secret filtering), its estimated size and
+    /// cost, and the provider and model. Needs no consent since nothing leaves
+    /// the machine.
+    pub async fn snapshot_preview(&mut self, pane_name: &str, llm_config: &LLMConfig) -> Result<SnapshotPreview> {
+        let provider = create_provider(llm_config);
+        let context = self.collect_snapshot_context(pane_name).await?;
+        let context = fit_to_budget(context, llm_config.context_budget(), &self.context_config.weights);
+        let prompt = provider.prompt(&context);
+        let estimated_tokens = estimate_tokens(&prompt);
+
+        Ok(SnapshotPreview {
+            provider: llm_config.provider.clone(),
+            model: provider.model().to_string(),
+            available: provider.is_available(),
+            estimated_cost_usd: estimate_cost(provider.name(), provider.model(), estimated_tokens, 0),
+            estimated_tokens,
+            prompt,
+            redactions: context.redactions,
+        })
+    }
+
+    /// Gather the filtered session context a snapshot summarizes, including
+    /// the pane's latest summary for continuity. Git and file context come
+    /// from the pane's own directory when it is known, so a snapshot run
+    /// from elsewhere still describes the pane's project.
+    async fn collect_snapshot_context(&mut self, pane_name: &str) -> Result<SessionContext> {
+        let record = self.state.get_pane(pane_name).await?;
+
+        // Zellij dumps the focused pane, which is only the one being
+        // summarized when the snapshot runs from inside its session
+        let in_session = match (self.zellij.active_session_name(), &record) {
+            (Some(active), Some(record)) => record.session == active,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        let scrollback = self.scrollback && in_session;
+        let collector = ContextCollector::new()
+            .context("failed to create context collector")?
+            .with_filter(SecretFilter::with_config(&self.filter_config)?)
+            .with_scrollback(scrollback)
+            .with_ignore_patterns(self.context_config.ignore_patterns.clone());
+
+        let cwd = match self.pane_cwd(pane_name, record.as_ref(), in_session).await {
+            Some(cwd) => Some(cwd),
+            None => std::env::current_dir().ok(),
         };
 
-        // Create and store the intent entry
-        let entry = IntentEntry::new(&result.summary)
-            .with_type(entry_type)
-            .with_source(IntentSource::Automated)
-            .with_artifacts(result.key_files.clone());
+        // Collection walks the filesystem and runs git, so keep it off the
+        // async runtime
+        let name = pane_name.to_string();
+        let context = tokio::task::spawn_blocking(move || collector.collect(&name, cwd.as_deref()))
+            .await
+            .context("context collection panicked")?
+            .context("failed to collect context")?;
 
-        self.state.log_intent(pane_name, &entry).await
-            .context("failed to log generated intent")?;
+        // Get existing summary if any (to provide continuity)
+        let existing = self.state.get_history(pane_name, Some(1)).await.ok()
+            .and_then(|h| h.into_iter().next())
+            .map(|e| e.summary);
 
-        Ok(SnapshotResult {
-            summary: result.summary,
-            entry_type,
-            key_files: result.key_files,
-            tokens_used: result.tokens_used,
+        Ok(match existing {
+            Some(summary) => context.with_existing_summary(summary),
+            None => context,
         })
     }
 
+    /// The pane's working directory: the `cwd` recorded in its metadata, or
+    /// failing that the one Zellij reports for it. Directories that no
+    /// longer exist are ignored.
+    async fn pane_cwd(&self, pane_name: &str, record: Option<&PaneRecord>, in_session: bool) -> Option<PathBuf> {
+        let existing = |cwd: &str| Some(PathBuf::from(cwd)).filter(|path| path.is_absolute() && path.is_dir());
+
+        if let Some(cwd) = record.and_then(|r| r.meta.get("cwd")).and_then(|cwd| existing(cwd)) {
+            return Some(cwd);
+        }
+        if !in_session {
+            return None;
+        }
+        let layout = self.zellij.dump_layout_json(None).await.ok().flatten()?;
+        find_pane_cwd(&layout, pane_name).and_then(existing)
+    }
+
+    /// Apply a retention policy to every known pane's history.
+    pub async fn gc(&mut self, policy: &RetentionPolicy, dry_run: bool) -> Result<GcResult> {
+        let mut result = GcResult::default();
+        if policy.is_empty() {
+            return Ok(result);
+        }
+
+        let now = chrono::Utc::now();
+        for pane_name in self.state.list_pane_names().await? {
+            result.panes_scanned += 1;
+            let removed = self
+                .state
+                .prune_history(&pane_name, dry_run, |entry| policy.is_expired(entry, now))
+                .await?;
+            if removed > 0 {
+                result.entries_removed += removed;
+                result.pruned.push((pane_name, removed));
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Count stored keys by category and estimate their memory use.
+    pub async fn keyspace_stats(&mut self, sample: usize, top: usize) -> Result<KeyspaceStats> {
+        self.state.keyspace_stats(sample, top).await
+    }
+
+    /// Where an interrupted `state rekey` stopped, if one did.
+    pub async fn rekey_cursor(&mut self) -> Result<Option<u64>> {
+        self.state.rekey_cursor().await
+    }
+
+    /// Forget an interrupted `state rekey` so the next batch starts over.
+    pub async fn clear_rekey_cursor(&mut self) -> Result<()> {
+        self.state.clear_rekey_cursor().await
+    }
+
+    /// Move the next batch of stored values from `old` to `new`.
+    pub async fn rekey_batch(&mut self, old: &Cipher, new: &Cipher, count: usize) -> Result<RekeyBatch> {
+        self.state.rekey_batch(old, new, count).await
+    }
+
     /// Migrate from v1.0 (znav:*) to v2.0 (perth:*) keyspace
-    pub async fn migrate_keyspace(&mut self, dry_run: bool) -> Result<MigrationResult> {
-        self.state.migrate_keyspace(dry_run).await
+    pub async fn migrate_keyspace(
+        &mut self,
+        dry_run: bool,
+        resolve: impl FnMut(&KeyConflict) -> Result<Option<HashMap<String, String>>>,
+    ) -> Result<MigrationResult> {
+        self.state.migrate_keyspace(dry_run, resolve).await
     }
 
-    pub async fn visualize(&mut self) -> Result<()> {
-        let panes = self.state.list_all_panes().await?;
+    /// Gather pane records, milestones, and the last snapshot layout for the
+    /// tabs of `session` (or only `tab`), for `zdrive describe`.
+    pub async fn describe_workspace(&mut self, session: &str, tab: Option<&str>) -> Result<Vec<TabDescription>> {
+        let mut panes: Vec<PaneRecord> = self
+            .state
+            .list_all_panes()
+            .await?
+            .into_iter()
+            .filter(|p| p.session == session && tab.is_none_or(|t| p.tab == t))
+            .collect();
+        if panes.is_empty() {
+            return Err(match tab {
+                Some(tab) => PerthError::NotFound(format!("no panes tracked for tab '{}' in session '{}'", tab, session)),
+                None => PerthError::NotFound(format!("no panes tracked in session '{}'", session)),
+            }
+            .into());
+        }
+
+        // Order by recorded position, then name, for a stable document
+        panes.sort_by_key(|p| {
+            let position = p.meta.get("position").and_then(|v| v.parse::<usize>().ok()).unwrap_or(usize::MAX);
+            (p.tab.clone(), position, p.pane_name.clone())
+        });
+
+        let snapshots = self.state.list_snapshots(session).await?;
+        let mut tabs: Vec<TabDescription> = Vec::new();
+        for record in panes {
+            let history = self.state.get_history(&record.pane_name, None).await?;
+            let milestones = history
+                .iter()
+                .filter(|e| e.entry_type == IntentType::Milestone)
+                .take(3)
+                .cloned()
+                .collect();
+            let description = PaneDescription {
+                last_entry: history.into_iter().next(),
+                milestones,
+                record,
+            };
+
+            match tabs.iter_mut().find(|t| t.name == description.record.tab) {
+                Some(tab) => tab.panes.push(description),
+                None => {
+                    // Snapshots are newest first, so this is the latest layout for the tab
+                    let layout = snapshots
+                        .iter()
+                        .find_map(|s| s.tabs.iter().find(|t| t.name == description.record.tab))
+                        .cloned();
+                    tabs.push(TabDescription {
+                        name: description.record.tab.clone(),
+                        panes: vec![description],
+                        layout,
+                    });
+                }
+            }
+        }
+
+        Ok(tabs)
+    }
+
+    /// Print the panes matching `query` as a session → tab → pane tree.
+    /// Sessions and tabs follow the pane order, so sorting by last access
+    /// puts the most recently used session and tab first.
+    pub async fn visualize(&mut self, query: &PaneListQuery) -> Result<()> {
+        let panes = self.list_panes(query).await?;
 
         if panes.is_empty() {
-            println!("No panes tracked in Redis");
+            if query.is_filtered() {
+                println!("No tracked panes match the filters");
+            } else {
+                println!("No panes tracked in Redis");
+            }
             return Ok(());
         }
 
-        // Organize panes by session -> tab
-        let mut sessions: HashMap<String, HashMap<String, Vec<PaneRecord>>> = HashMap::new();
+        // Organize panes by session -> tab, keeping the sorted order
+        let mut sessions: Vec<SessionGroup> = Vec::new();
         for pane in panes {
-            sessions
-                .entry(pane.session.clone())
-                .or_default()
-                .entry(pane.tab.clone())
-                .or_default()
-                .push(pane);
+            let session_idx = match sessions.iter().position(|session| session.name == pane.session) {
+                Some(idx) => idx,
+                None => {
+                    sessions.push(SessionGroup { name: pane.session.clone(), tabs: Vec::new() });
+                    sessions.len() - 1
+                }
+            };
+            let tabs = &mut sessions[session_idx].tabs;
+            match tabs.iter_mut().find(|tab| tab.name == pane.tab) {
+                Some(tab) => tab.panes.push(pane),
+                None => tabs.push(TabGroup { name: pane.tab.clone(), panes: vec![pane] }),
+            }
         }
 
-        // Sort sessions for consistent output
-        let mut session_names: Vec<_> = sessions.keys().cloned().collect();
-        session_names.sort();
-
-        for (session_idx, session_name) in session_names.iter().enumerate() {
-            let is_last_session = session_idx == session_names.len() - 1;
-            let tabs = sessions.get(session_name).unwrap();
+        for (session_idx, SessionGroup { name: session_name, tabs }) in sessions.iter().enumerate() {
+            let is_last_session = session_idx == sessions.len() - 1;
 
             // Print session header
             println!("{}", session_name);
 
-            // Sort tabs for consistent output
-            let mut tab_names: Vec<_> = tabs.keys().cloned().collect();
-            tab_names.sort();
-
-            for (tab_idx, tab_name) in tab_names.iter().enumerate() {
-                let is_last_tab = tab_idx == tab_names.len() - 1;
-                let panes_in_tab = tabs.get(tab_name).unwrap();
+            for (tab_idx, TabGroup { name: tab_name, panes: panes_in_tab }) in tabs.iter().enumerate() {
+                let is_last_tab = tab_idx == tabs.len() - 1;
 
                 // Look up tab in Redis to get correlation ID
                 let correlation_id = self.state.get_tab(tab_name, session_name).await
@@ -749,12 +2572,8 @@ impl Orchestrator {
                 };
                 println!("{} {}", tab_prefix, tab_display);
 
-                // Sort panes by name for consistent output
-                let mut sorted_panes = panes_in_tab.clone();
-                sorted_panes.sort_by(|a, b| a.pane_name.cmp(&b.pane_name));
-
-                for (pane_idx, pane) in sorted_panes.iter().enumerate() {
-                    let is_last_pane = pane_idx == sorted_panes.len() - 1;
+                for (pane_idx, pane) in panes_in_tab.iter().enumerate() {
+                    let is_last_pane = pane_idx == panes_in_tab.len() - 1;
 
                     // Determine the correct tree characters
                     let pane_prefix = if is_last_session && is_last_tab {
@@ -773,7 +2592,15 @@ impl Orchestrator {
 
                     // Build pane display line with status indicator
                     let status_indicator = if pane.stale { "[stale]" } else { "" };
-                    let pane_line = format!("{} {}", pane.pane_name, status_indicator).trim().to_string();
+                    let mut pane_line = format!("{} {}", pane.pane_name, status_indicator).trim().to_string();
+                    if query.details {
+                        if let Some(accessed) = relative_time(&pane.last_accessed) {
+                            pane_line.push_str(&format!(" · {}", accessed));
+                        }
+                        if let Some(intent) = self.last_intent(&pane.pane_name).await? {
+                            pane_line.push_str(&format!(" · {}", intent));
+                        }
+                    }
 
                     println!("{} {}", pane_prefix, pane_line);
 
@@ -812,9 +2639,63 @@ impl Orchestrator {
         Ok(())
     }
 
-    /// Save a session snapshot to Redis
-    pub async fn save_snapshot(&self, snapshot: &crate::types::SessionSnapshot) -> Result<()> {
-        self.state.save_snapshot(snapshot).await
+    /// Save a session snapshot to Redis, first copying each tracked pane's
+    /// metadata into it so a restore can recreate the pane (e.g. rerun its
+    /// `command`).
+    pub async fn save_snapshot(&mut self, snapshot: &mut crate::types::SessionSnapshot) -> Result<()> {
+        for pane in snapshot.tabs.iter_mut().flat_map(|tab| tab.panes.iter_mut()) {
+            if let Some(record) = self.state.get_pane(&pane.name).await? {
+                if record.session == snapshot.session {
+                    if pane.command.is_none() {
+                        pane.command = record.meta.get(COMMAND_META_KEY).cloned();
+                    }
+                    pane.meta = record.meta;
+                }
+            }
+        }
+        self.state.save_snapshot(snapshot).await?;
+        self.events.snapshot_created(snapshot).await;
+        Ok(())
+    }
+
+    /// Snapshot the attached session before a destructive `op`, as
+    /// `auto-pre-<op>-<timestamp>`, so it can be undone with a restore.
+    /// Returns the snapshot name, or None when `snapshot.auto_backup` is off
+    /// or not inside Zellij. A failed backup is logged and doesn't stop the
+    /// operation.
+    #[cfg(feature = "snapshots")]
+    pub async fn auto_backup(&mut self, op: &str) -> Option<String> {
+        use crate::snapshot::StateCapture;
+
+        if !self.snapshot_config.auto_backup || self.zellij.active_session_name().is_none() {
+            return None;
+        }
+
+        let name = format!("auto-pre-{}-{}", op, chrono::Local::now().format("%Y-%m-%d-%H%M%S"));
+        let description = format!("Automatic backup before {}", op);
+        let capture = StateCapture::new(self.zellij.clone());
+        let result = match capture.capture_session(name.clone(), Some(description), None).await {
+            Ok((mut snapshot, _)) => self.save_snapshot(&mut snapshot).await.map(|_| snapshot.session),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(session) => {
+                if let Err(e) = self.enforce_snapshot_retention(&session, self.snapshot_config.retention_limit).await {
+                    tracing::warn!("failed to enforce snapshot retention: {:#}", e);
+                }
+                Some(name)
+            }
+            Err(e) => {
+                tracing::warn!(op, "safety snapshot failed, continuing without one: {:#}", e);
+                None
+            }
+        }
+    }
+
+    /// Without the `snapshots` feature there is nothing to back up with.
+    #[cfg(not(feature = "snapshots"))]
+    pub async fn auto_backup(&mut self, _op: &str) -> Option<String> {
+        None
     }
 
     /// Enforce snapshot retention policy
@@ -827,7 +2708,7 @@ impl Orchestrator {
         let session = self
             .zellij
             .active_session_name()
-            .ok_or_else(|| anyhow!("not inside a zellij session"))?;
+            .ok_or_else(|| PerthError::ZellijUnavailable("not inside a zellij session".to_string()))?;
 
         self.state.list_snapshots(&session).await
     }
@@ -842,31 +2723,118 @@ impl Orchestrator {
         let session = self
             .zellij
             .active_session_name()
-            .ok_or_else(|| anyhow!("not inside a zellij session"))?;
+            .ok_or_else(|| PerthError::ZellijUnavailable("not inside a zellij session".to_string()))?;
 
         self.state.get_snapshot(&session, name).await
     }
 
+    /// Store a snapshot read from an export file under `session`, by
+    /// default the active session or else the one it was taken in. An
+    /// existing snapshot with the same name is only replaced with `force`.
+    pub async fn import_snapshot(
+        &self,
+        mut snapshot: crate::types::SessionSnapshot,
+        session: Option<&str>,
+        force: bool,
+    ) -> Result<crate::types::SessionSnapshot> {
+        if let Some(session) = session.map(str::to_string).or_else(|| self.zellij.active_session_name()) {
+            snapshot.session = session;
+        }
+        if !force && self.state.get_snapshot(&snapshot.session, &snapshot.name).await.is_ok() {
+            return Err(anyhow!(
+                "snapshot '{}' already exists in session '{}'; pass --name to import it under another name or --force to replace it",
+                snapshot.name,
+                snapshot.session
+            ));
+        }
+        self.state.save_snapshot(&snapshot).await?;
+        Ok(snapshot)
+    }
+
     /// Delete a snapshot by name for the current session
     pub async fn delete_snapshot(&self, name: &str) -> Result<()> {
         let session = self
             .zellij
             .active_session_name()
-            .ok_or_else(|| anyhow!("not inside a zellij session"))?;
+            .ok_or_else(|| PerthError::ZellijUnavailable("not inside a zellij session".to_string()))?;
 
         self.state.delete_snapshot(&session, name).await
     }
 
-    /// Restore a session from a snapshot
+    /// List trashed items, most recently deleted first
+    pub async fn list_trash(&self) -> Result<Vec<TrashItem>> {
+        self.state.list_trash().await
+    }
+
+    /// Restore a trashed item to its original location
+    pub async fn restore_trash(&mut self, id: &str) -> Result<TrashItem> {
+        self.state.restore_trash(id).await
+    }
+
+    /// Permanently purge one trashed item, or all of them
+    pub async fn empty_trash(&self, id: Option<&str>) -> Result<usize> {
+        self.state.empty_trash(id).await
+    }
+
+    /// Restore a session from a snapshot, by default into the attached
+    /// session. With `into`, the snapshot is restored into that session
+    /// (started in the background if session creation is on) and the
+    /// tracked records of its panes and tabs are moved there.
+    #[cfg(feature = "snapshots")]
     pub async fn restore_snapshot(
-        &self,
+        &mut self,
         snapshot: &crate::types::SessionSnapshot,
         dry_run: bool,
+        into: Option<&str>,
     ) -> Result<crate::types::RestoreReport> {
         use crate::restore::SessionRestore;
 
-        let restorer = SessionRestore::new(self.zellij.clone());
-        restorer.restore_session(snapshot, dry_run).await
+        let (target, session_created) = match into {
+            Some(session) if dry_run => (Some(session.to_string()), false),
+            Some(session) => self.ensure_session(session).await?,
+            None => (None, false),
+        };
+
+        let restorer = SessionRestore::new(self.zellij.clone()).into_session(target);
+        let mut report = restorer.restore_session(snapshot, dry_run).await?;
+        report.session_created = session_created;
+        if !dry_run {
+            if report.session != snapshot.session {
+                self.move_snapshot_records(snapshot, &report.session).await?;
+            }
+            self.events.snapshot_restored(snapshot, &report).await;
+        }
+        Ok(report)
+    }
+
+    /// Point the records of a snapshot's tracked panes and tabs at `session`
+    /// after restoring it there. Pane IDs are cleared since the restored
+    /// panes have new ones; tab records are copied, leaving the original
+    /// session's in place.
+    #[cfg(feature = "snapshots")]
+    async fn move_snapshot_records(&mut self, snapshot: &crate::types::SessionSnapshot, session: &str) -> Result<()> {
+        for tab in &snapshot.tabs {
+            if !self.state.tab_exists(&tab.name, session).await? {
+                if let Some(record) = self.state.get_tab(&tab.name, &snapshot.session).await? {
+                    self.state.upsert_tab(&TabRecord { session: session.to_string(), archived_at: None, ..record }).await?;
+                }
+            }
+
+            for pane in &tab.panes {
+                let Some(mut record) = self.state.get_pane(&pane.name).await? else {
+                    continue;
+                };
+                if record.session != snapshot.session {
+                    continue;
+                }
+                record.session = session.to_string();
+                record.tab = tab.name.clone();
+                record.pane_id = None;
+                record.stale = false;
+                self.state.upsert_pane(&record).await?;
+            }
+        }
+        Ok(())
     }
 
     /// Get snapshot ancestry chain
@@ -874,10 +2842,150 @@ impl Orchestrator {
         let session = self
             .zellij
             .active_session_name()
-            .ok_or_else(|| anyhow!("not inside a zellij session"))?;
+            .ok_or_else(|| PerthError::ZellijUnavailable("not inside a zellij session".to_string()))?;
 
         self.state.get_snapshot_ancestry(&session, name).await
     }
+
+    // ========================================================================
+    // Session Templates
+    // ========================================================================
+
+    /// Save the tracked structure of a session (by default the active one)
+    /// as a template, replacing `vars` values with placeholders.
+    ///
+    /// Tabs keep Zellij's order and panes their creation order. Stale panes
+    /// are left out, and so are panes opened without a tab, whose tab isn't
+    /// known; their names are returned.
+    pub async fn save_template(
+        &mut self,
+        name: &str,
+        description: Option<String>,
+        session: Option<&str>,
+        tab: Option<&str>,
+        vars: &[(String, String)],
+        force: bool,
+    ) -> Result<(SessionTemplate, Vec<String>)> {
+        template::validate_variables(vars)?;
+        if !force && self.state.get_template(name).await?.is_some() {
+            return Err(anyhow!("template '{}' already exists; pass --force to replace it", name));
+        }
+
+        let session = match session {
+            Some(session) => session.to_string(),
+            None => self
+                .zellij
+                .active_session_name()
+                .ok_or_else(|| PerthError::ZellijUnavailable("not inside a zellij session; pass --session".to_string()))?,
+        };
+        let query = PaneListQuery {
+            session: Some(session.clone()),
+            tab: tab.map(str::to_string),
+            ..Default::default()
+        };
+        let (mut panes, skipped): (Vec<PaneRecord>, Vec<PaneRecord>) = self
+            .list_panes(&query)
+            .await?
+            .into_iter()
+            .filter(|record| !record.stale)
+            .partition(|record| record.tab != CURRENT_TAB);
+        if panes.is_empty() {
+            return Err(anyhow!("no tracked panes in session '{}' to save", session));
+        }
+
+        // Tabs Zellij doesn't report (e.g. another session's) follow in name order
+        let active = self.zellij.active_session_name();
+        let action_session = if active.as_deref() == Some(session.as_str()) { None } else { Some(session.as_str()) };
+        let tab_order = self.zellij.query_tab_names(action_session).await.unwrap_or_default();
+        panes.sort_by_key(|record| {
+            let tab_index = tab_order.iter().position(|tab| tab == &record.tab).unwrap_or(usize::MAX);
+            let position = record.meta.get("position").and_then(|p| p.parse::<usize>().ok()).unwrap_or(usize::MAX);
+            (tab_index, record.tab.clone(), position, record.created_at.clone())
+        });
+        let mut tabs: Vec<TemplateTab> = Vec::new();
+        for record in panes {
+            let pane = TemplatePane {
+                name: record.pane_name,
+                cwd: record.meta.get("cwd").cloned(),
+                command: record.meta.get(COMMAND_META_KEY).cloned(),
+            };
+            match tabs.last_mut() {
+                Some(last) if last.name == record.tab => last.panes.push(pane),
+                _ => tabs.push(TemplateTab { name: record.tab, panes: vec![pane] }),
+            }
+        }
+
+        let template = template::build(name, &session, description, tabs, vars);
+        self.state.save_template(&template).await?;
+        Ok((template, skipped.into_iter().map(|record| record.pane_name).collect()))
+    }
+
+    pub async fn get_template(&self, name: &str) -> Result<SessionTemplate> {
+        self.state
+            .get_template(name)
+            .await?
+            .ok_or_else(|| PerthError::NotFound(format!("template '{}' not found", name)).into())
+    }
+
+    pub async fn list_templates(&self) -> Result<Vec<SessionTemplate>> {
+        self.state.list_templates().await
+    }
+
+    pub async fn delete_template(&self, name: &str) -> Result<()> {
+        self.get_template(name).await?;
+        self.state.delete_template(name).await
+    }
+}
+
+fn llm_span(operation: &str, provider: &dyn LLMProvider) -> telemetry::Span {
+    telemetry::client_span(format!("llm {}", operation))
+        .with_attribute("llm.provider", provider.name())
+        .with_attribute("llm.model", provider.model())
+}
+
+/// Record an LLM request's latency, labelled ok, error, or timeout, and
+/// end its trace span.
+fn record_llm_metrics<T, E>(provider: &str, result: &Result<Result<T>, E>, elapsed: Duration, mut span: telemetry::Span) {
+    let outcome = match result {
+        Ok(Ok(_)) => "ok",
+        Ok(Err(e)) => {
+            span.fail(format!("{:#}", e));
+            "error"
+        }
+        Err(_) => {
+            span.fail("timed out");
+            "timeout"
+        }
+    };
+    metrics::observe("perth_llm_duration_seconds", &[("provider", provider), ("outcome", outcome)], elapsed);
+}
+
+fn record_llm_tokens(provider: &str, input_tokens: u32, output_tokens: u32) {
+    metrics::add("perth_llm_tokens_total", &[("provider", provider), ("direction", "input")], input_tokens as u64);
+    metrics::add("perth_llm_tokens_total", &[("provider", provider), ("direction", "output")], output_tokens as u64);
+}
+
+/// Find the entry whose ID is `entry_id` or starts with it, as shown by `pane history`.
+fn find_entry<'a>(entries: &'a [IntentEntry], pane_name: &str, entry_id: &str) -> Result<&'a IntentEntry> {
+    let prefix = entry_id.trim().replace('-', "").to_lowercase();
+    if prefix.is_empty() {
+        return Err(anyhow!("entry ID cannot be empty"));
+    }
+
+    let matches: Vec<_> = entries
+        .iter()
+        .filter(|e| e.id.simple().to_string().starts_with(&prefix))
+        .collect();
+
+    match matches.as_slice() {
+        [entry] => Ok(entry),
+        [] => Err(PerthError::NotFound(format!("no entry '{}' in pane '{}' history", entry_id, pane_name)).into()),
+        _ => Err(anyhow!(
+            "entry ID '{}' is ambiguous ({} matches); use more characters",
+            entry_id,
+            matches.len()
+        )),
+    }
 }
 
 fn collect_pane_names(value: &Value, panes: &mut HashSet<String>, in_pane_list: bool) {
@@ -909,6 +3017,23 @@ fn collect_pane_names(value: &Value, panes: &mut HashSet<String>, in_pane_list:
     }
 }
 
+/// The `cwd` of the pane named `pane_name` in layout JSON, if it has one.
+fn find_pane_cwd<'a>(value: &'a Value, pane_name: &str) -> Option<&'a str> {
+    match value {
+        Value::Object(map) => {
+            let name = map.get("pane_name").or_else(|| map.get("name")).and_then(|v| v.as_str());
+            if name == Some(pane_name) {
+                if let Some(cwd) = map.get("cwd").and_then(|v| v.as_str()) {
+                    return Some(cwd);
+                }
+            }
+            map.values().find_map(|child| find_pane_cwd(child, pane_name))
+        }
+        Value::Array(items) => items.iter().find_map(|item| find_pane_cwd(item, pane_name)),
+        _ => None,
+    }
+}
+
 fn count_panes_in_tab_from_layout(layout: &Value, target_tab: &str) -> usize {
     // Navigate to the target tab in the layout and count panes
     if let Some(tabs) = layout.get("tabs").and_then(|v| v.as_array()) {
@@ -958,7 +3083,7 @@ fn count_panes_recursive(value: &Value) -> usize {
 }
 
 /// Result of a snapshot operation
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SnapshotResult {
     /// The generated summary
     pub summary: String,
@@ -968,10 +3093,90 @@ pub struct SnapshotResult {
     pub key_files: Vec<String>,
     /// Tokens used (for cost tracking)
     pub tokens_used: Option<u32>,
+    /// Estimated cost in USD, if the model's price is known
+    pub cost_usd: Option<f64>,
+    /// Provider that wrote the summary ("heuristic" when summarized locally)
+    pub summarizer: String,
+    /// Tags given with `--tag`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
 }
 
-/// Result of a tab create operation (STORY-036)
+/// What a snapshot would send, from `snapshot --dry-run`
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotPreview {
+    /// Configured provider name
+    pub provider: String,
+    /// Model the request would go to
+    pub model: String,
+    /// Whether the provider is configured well enough to send it
+    pub available: bool,
+    /// The exact prompt, after secret filtering
+    pub prompt: String,
+    /// Approximate prompt size in tokens
+    pub estimated_tokens: u32,
+    /// Approximate input cost in USD, if the model's price is known
+    pub estimated_cost_usd: Option<f64>,
+    /// What secret filtering removed from the collected context
+    pub redactions: Vec<Redaction>,
+}
+
+/// Answer from `ask`, with the history it was based on
 #[derive(Debug, Clone)]
+pub struct AskResult {
+    /// The streamed answer, in full
+    pub completion: Completion,
+    /// Entries sent as context, most relevant first
+    pub sources: Vec<PaneEntry>,
+    /// Estimated cost in USD, if the model's price is known
+    pub cost_usd: Option<f64>,
+}
+
+/// An entry that listed an artifact, for `zdrive artifacts who-touched`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ArtifactTouch {
+    pub pane: String,
+    /// Where the pane is now, if it is still tracked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tab: Option<String>,
+    pub entry: IntentEntry,
+}
+
+/// Result of `zdrive pane artifacts --remap`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RemapResult {
+    /// Live entries rewritten (or that would be, for a dry run)
+    pub entries_updated: usize,
+    /// Each distinct path rewritten, by old path
+    pub paths: Vec<RemappedPath>,
+    /// Archived entries under the old prefix, left unchanged
+    pub archived_entries: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RemappedPath {
+    pub from: String,
+    pub to: String,
+}
+
+/// Tag frequencies in one pane's history, for `zdrive tags`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PaneTags {
+    pub pane: String,
+    /// Most used first
+    pub tags: Vec<TagCount>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+/// Result of a tab create operation (STORY-036)
+#[derive(Debug, Clone, Serialize)]
 pub struct TabCreateResult {
     /// The effective tab name (may include correlation ID suffix)
     pub tab_name: String,
@@ -981,10 +3186,42 @@ pub struct TabCreateResult {
     pub created: bool,
     /// The session the tab belongs to
     pub session: String,
+    /// Why the name doesn't match `tab.naming_pattern`, if it doesn't
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub naming_warning: Option<String>,
+}
+
+/// Where `open_pane` left a pane.
+#[derive(Debug, Clone, Serialize)]
+pub struct PaneLocation {
+    pub session: String,
+    /// Whether this terminal is attached to that session
+    pub attached: bool,
+    /// Whether the session was started in the background for the pane
+    pub session_started: bool,
+}
+
+/// A tracked tab and how many tracked panes it holds, for `zdrive tab list`.
+#[derive(Debug, Clone, Serialize)]
+pub struct TabSummary {
+    #[serde(flatten)]
+    pub tab: TabRecord,
+    pub panes: usize,
+}
+
+/// Result of `zdrive tab delete`.
+#[derive(Debug, Clone, Serialize)]
+pub struct TabDeleteResult {
+    pub tab_name: String,
+    pub session: String,
+    /// Whether the tab was open in Zellij and got closed
+    pub closed: bool,
+    /// Pane records deleted with `--with-panes`
+    pub deleted_panes: Vec<String>,
 }
 
 /// Result of a batch pane operation (STORY-037)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct BatchResult {
     /// The tab name where panes were created
     pub tab_name: String,
@@ -995,3 +3232,165 @@ pub struct BatchResult {
     /// The session the panes belong to
     pub session: String,
 }
+
+/// Result of a takeover operation
+#[derive(Debug, Clone, Serialize)]
+pub struct TakeoverResult {
+    /// The pane that received the handoff
+    pub pane_name: String,
+    /// The pane the bundle was exported from, if recorded
+    pub handoff_from: Option<String>,
+    /// The goal carried over, if any
+    pub goal: Option<String>,
+    /// Number of history entries imported
+    pub entries_imported: usize,
+}
+
+/// How [`Orchestrator::infer_current_pane`] identified the current pane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaneInference {
+    /// Named by `PERTH_PANE`
+    Env,
+    /// Its recorded pane ID is `ZELLIJ_PANE_ID`
+    PaneId,
+    /// Its recorded pane ID is the session's focused pane
+    Focused,
+    /// Its working directory holds the current directory
+    Cwd,
+}
+
+impl PaneInference {
+    pub fn describe(self) -> &'static str {
+        match self {
+            PaneInference::Env => "$PERTH_PANE",
+            PaneInference::PaneId => "$ZELLIJ_PANE_ID",
+            PaneInference::Focused => "focused pane",
+            PaneInference::Cwd => "working directory",
+        }
+    }
+}
+
+/// Pane metadata holding the command a pane was created to run. Set by
+/// `--run`, it's run when the pane is created and again when it's restored.
+pub const COMMAND_META_KEY: &str = "command";
+
+/// Environment variables identifying a pane to the tools running in it.
+fn pane_env(pane_name: &str, session: &str) -> Vec<(String, String)> {
+    vec![
+        ("PERTH_PANE".to_string(), pane_name.to_string()),
+        ("PERTH_SESSION".to_string(), session.to_string()),
+    ]
+}
+
+/// A shell line exporting `env`, for panes that are already running. The
+/// leading space keeps it out of history in shells that ignore such lines.
+/// `export A=b` works in sh-like shells and fish alike.
+fn export_command(env: &[(String, String)]) -> String {
+    let assignments: Vec<String> = env
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, shell_quote(value)))
+        .collect();
+    format!(" export {}\n", assignments.join(" "))
+}
+
+/// `value` single-quoted for a POSIX shell.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// `path` with symlinks resolved, or as given if it doesn't exist.
+fn canonical_path(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Order of panes in `zdrive list`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum PaneSort {
+    /// By session, tab and pane name
+    #[default]
+    Name,
+    /// Most recently accessed first
+    LastAccessed,
+}
+
+/// Panes of one session in `zdrive list`, grouped by tab
+struct SessionGroup {
+    name: String,
+    tabs: Vec<TabGroup>,
+}
+
+struct TabGroup {
+    name: String,
+    panes: Vec<PaneRecord>,
+}
+
+/// Which panes `zdrive list` shows, and how.
+#[derive(Debug, Clone, Default)]
+pub struct PaneListQuery {
+    /// Only panes in this session
+    pub session: Option<String>,
+    /// Only panes in this tab
+    pub tab: Option<String>,
+    /// Only panes marked stale by reconcile
+    pub stale_only: bool,
+    pub sort: PaneSort,
+    /// Show each pane's last intent and when it was last accessed
+    pub details: bool,
+}
+
+impl PaneListQuery {
+    /// Whether any filter is set, so an empty result isn't an empty store.
+    pub fn is_filtered(&self) -> bool {
+        self.session.is_some() || self.tab.is_some() || self.stale_only
+    }
+}
+
+/// "3 hours ago" for a stored RFC 3339 timestamp, if it parses.
+fn relative_time(timestamp: &str) -> Option<String> {
+    let time = chrono::DateTime::parse_from_rfc3339(timestamp).ok()?;
+    Some(chrono_humanize::HumanTime::from(time).to_string())
+}
+
+/// Result of checking stored panes against the current session's layout
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReconcileResult {
+    /// The session that was checked
+    pub session: String,
+    /// Pane records examined
+    pub total: usize,
+    /// Panes found in the layout
+    pub seen: usize,
+    /// Panes missing from the layout, now marked stale
+    pub stale: usize,
+    /// Panes in other sessions, or all of them when the layout couldn't be read
+    pub skipped: usize,
+    /// Stale records deleted by `--prune`
+    pub pruned: usize,
+    pub seen_panes: Vec<String>,
+    pub stale_panes: Vec<String>,
+    pub pruned_panes: Vec<String>,
+    /// Safety snapshot taken before pruning
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backup_snapshot: Option<String>,
+}
+
+/// Result of handling a completion event
+#[derive(Debug, Clone, Default)]
+pub struct CompletionResult {
+    /// Correlated tabs now scheduled for archive
+    pub tabs: Vec<TabRecord>,
+    /// Milestones logged across their panes
+    pub milestones: usize,
+}
+
+/// Result of applying retention to stored history
+#[derive(Debug, Clone, Default)]
+pub struct GcResult {
+    /// Number of panes whose history was checked
+    pub panes_scanned: usize,
+    /// Total entries removed (or that would be removed in a dry run)
+    pub entries_removed: usize,
+    /// Panes that had entries removed, with the count for each
+    pub pruned: Vec<(String, usize)>,
+}
@@ -0,0 +1,220 @@
+//! Standalone HTML timelines for `pane history --format html` and
+//! `zdrive export html`.
+//!
+//! Pages carry their own stylesheet and no scripts: each day is a
+//! `<details>` element, so days collapse without JavaScript and the file
+//! can be mailed or dropped into a wiki as-is.
+
+use super::{messages, OutputFormatter};
+use crate::types::{IntentEntry, IntentSource, IntentType};
+use chrono::{Local, NaiveDate};
+
+const STYLE: &str = "\
+body{font:15px/1.5 -apple-system,BlinkMacSystemFont,'Segoe UI',sans-serif;max-width:860px;margin:2em auto;padding:0 1em;color:#1f2328;background:#fff}
+h1{font-size:1.6em;margin-bottom:.2em}
+.meta{color:#656d76;margin-top:0}
+details{border-left:3px solid #d0d7de;margin:1.2em 0;padding-left:1em}
+summary{cursor:pointer;font-weight:600;font-size:1.1em}
+summary .count{color:#656d76;font-weight:400;font-size:.9em}
+ul.entries{list-style:none;padding:0}
+li.entry{margin:.8em 0}
+.time{color:#656d76;font-variant-numeric:tabular-nums;margin-right:.4em}
+.badge{display:inline-block;border-radius:1em;padding:0 .6em;font-size:.8em;font-weight:600;margin-right:.4em;color:#fff}
+.milestone{background:#1a7f37}.checkpoint{background:#0969da}.exploration{background:#8250df}
+.source{color:#656d76;font-size:.85em}
+.tag{color:#9a6700;font-size:.85em;margin-left:.3em}
+ul.artifacts{margin:.2em 0 0 1.2em;padding:0;font-size:.9em}
+code{background:#f6f8fa;border-radius:4px;padding:0 .3em}
+blockquote{margin:.3em 0 0 1.2em;color:#656d76;font-size:.9em}
+@media (prefers-color-scheme:dark){body{background:#0d1117;color:#e6edf3}code{background:#161b22}details{border-color:#30363d}}
+";
+
+impl OutputFormatter {
+    /// Render history as a standalone HTML page, newest day first, with
+    /// each day collapsible and annotations beneath their entries.
+    pub fn format_html(&self, entries: &[IntentEntry], pane_name: &str) -> String {
+        let mut sorted: Vec<&IntentEntry> = entries.iter().collect();
+        sorted.sort_by_key(|entry| std::cmp::Reverse(entry.timestamp));
+
+        let mut body = Vec::new();
+        body.push(format!("<h1>{}</h1>", escape(pane_name)));
+        let milestones = sorted.iter().filter(|e| e.entry_type == IntentType::Milestone).count();
+        body.push(format!(
+            "<p class=\"meta\">{} · {} · exported {}</p>",
+            messages::count(sorted.len(), "entry"),
+            messages::count(milestones, "milestone"),
+            Local::now().format("%Y-%m-%d %H:%M")
+        ));
+        if sorted.is_empty() {
+            body.push("<p><em>No entries recorded.</em></p>".to_string());
+        }
+
+        let mut days: Vec<(NaiveDate, Vec<&IntentEntry>)> = Vec::new();
+        for entry in sorted {
+            let date = entry.timestamp.with_timezone(&Local).date_naive();
+            match days.last_mut() {
+                Some((day, day_entries)) if *day == date => day_entries.push(entry),
+                _ => days.push((date, vec![entry])),
+            }
+        }
+
+        for (date, day_entries) in days {
+            body.push("<details open>".to_string());
+            body.push(format!(
+                "<summary>{} <span class=\"count\">({})</span></summary>",
+                date.format("%A %Y-%m-%d"),
+                day_entries.len()
+            ));
+            body.push("<ul class=\"entries\">".to_string());
+            for entry in day_entries {
+                body.push(self.html_entry(entry));
+            }
+            body.push("</ul>".to_string());
+            body.push("</details>".to_string());
+        }
+
+        page(&format!("Session: {}", pane_name), &body.join("\n"))
+    }
+
+    fn html_entry(&self, entry: &IntentEntry) -> String {
+        let type_class = match entry.entry_type {
+            IntentType::Milestone => "milestone",
+            IntentType::Checkpoint => "checkpoint",
+            IntentType::Exploration => "exploration",
+        };
+        let mut html = format!(
+            "<li class=\"entry\"><span class=\"time\">{}</span><span class=\"badge {}\">{}</span>{}",
+            entry.timestamp.with_timezone(&Local).format("%H:%M"),
+            type_class,
+            type_class,
+            escape(&entry.summary)
+        );
+
+        let source = match (entry.source, entry.source_detail.as_deref()) {
+            (IntentSource::Manual, None) => None,
+            (_, Some(detail)) => Some(format!("{} · {}", entry.source_str(), detail)),
+            (_, None) => Some(entry.source_str().to_string()),
+        };
+        if let Some(source) = source {
+            html.push_str(&format!(" <span class=\"source\">({})</span>", escape(&source)));
+        }
+        for tag in &entry.tags {
+            html.push_str(&format!("<span class=\"tag\">#{}</span>", escape(tag)));
+        }
+
+        if !entry.artifacts.is_empty() {
+            html.push_str("<ul class=\"artifacts\">");
+            for artifact in &entry.artifacts {
+                html.push_str(&format!("<li>{}</li>", artifact_link(artifact)));
+            }
+            html.push_str("</ul>");
+        }
+        for annotation in self.annotations_for(entry) {
+            let author = annotation.author.as_deref().map(|a| format!(" — {}", escape(a))).unwrap_or_default();
+            html.push_str(&format!("<blockquote>{}{}</blockquote>", escape(&annotation.note), author));
+        }
+        html.push_str("</li>");
+        html
+    }
+}
+
+/// Index page linking exported pane pages, given as (pane name, file name,
+/// entry count).
+pub fn index_page(panes: &[(String, String, usize)]) -> String {
+    let mut body = vec!["<h1>Session history</h1>".to_string()];
+    body.push(format!(
+        "<p class=\"meta\">{} · exported {}</p>",
+        messages::count(panes.len(), "pane"),
+        Local::now().format("%Y-%m-%d %H:%M")
+    ));
+    body.push("<ul>".to_string());
+    for (pane_name, file_name, entries) in panes {
+        body.push(format!(
+            "<li><a href=\"{}\">{}</a> <span class=\"source\">({})</span></li>",
+            escape(file_name),
+            escape(pane_name),
+            messages::count(*entries, "entry")
+        ));
+    }
+    body.push("</ul>".to_string());
+    page("Session history", &body.join("\n"))
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n{}\n</body>\n</html>\n",
+        escape(title),
+        STYLE,
+        body
+    )
+}
+
+/// URLs and absolute paths become links; anything else is shown as code.
+fn artifact_link(artifact: &str) -> String {
+    let href = if artifact.starts_with("http://") || artifact.starts_with("https://") {
+        Some(artifact.to_string())
+    } else if artifact.starts_with('/') {
+        Some(format!("file://{}", artifact))
+    } else {
+        None
+    };
+    match href {
+        Some(href) => format!("<a href=\"{}\"><code>{}</code></a>", escape(&href), escape(artifact)),
+        None => format!("<code>{}</code>", escape(artifact)),
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Annotation;
+    use chrono::Duration;
+
+    #[test]
+    fn test_format_html_escapes_and_links() {
+        let mut entry = IntentEntry::new("Fixed <script> injection");
+        entry.entry_type = IntentType::Milestone;
+        entry.artifacts = vec!["/src/auth.rs".into(), "https://example.com/pr/1".into(), "notes.md".into()];
+        entry.tags = vec!["security".into()];
+        let annotation = Annotation::new(entry.id, "Needs a test").with_author("dana");
+        let formatter = OutputFormatter::new().with_annotations(vec![annotation]);
+
+        let html = formatter.format_html(&[entry], "api(auth)");
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Session: api(auth)</title>"));
+        assert!(html.contains("<span class=\"badge milestone\">milestone</span>Fixed &lt;script&gt; injection"));
+        assert!(html.contains("<a href=\"file:///src/auth.rs\"><code>/src/auth.rs</code></a>"));
+        assert!(html.contains("<a href=\"https://example.com/pr/1\">"));
+        assert!(html.contains("<li><code>notes.md</code></li>"));
+        assert!(html.contains("<span class=\"tag\">#security</span>"));
+        assert!(html.contains("<blockquote>Needs a test — dana</blockquote>"));
+    }
+
+    #[test]
+    fn test_format_html_groups_days_newest_first() {
+        let older = IntentEntry::new("older");
+        let mut newer = IntentEntry::new("newer");
+        newer.timestamp = older.timestamp + Duration::days(2);
+
+        let html = OutputFormatter::new().format_html(&[older, newer], "api");
+        assert_eq!(html.matches("<details open>").count(), 2);
+        assert!(html.find("newer").unwrap() < html.find("older").unwrap());
+    }
+}
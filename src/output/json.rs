@@ -0,0 +1,62 @@
+//! Machine-readable output for `--format json` and `json-compact`.
+//!
+//! Every command prints one JSON object carrying `schema_version` and
+//! `command` (the subcommand path, e.g. "pane history") alongside its own
+//! fields, so scripts can tell what they were given. Fields may be added
+//! within a schema version but are never renamed or removed. Streaming
+//! output (`pane history --follow`, `events tail`) is one bare record per
+//! line instead.
+
+use super::OutputFormat;
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+/// Version of the documents below, continuing from the `pane history`
+/// schema they grew out of.
+pub const SCHEMA_VERSION: &str = "2.0";
+
+/// Wrap a command's output in the versioned document. A body that versions
+/// its own schema (like `capabilities`) keeps its `schema_version`; anything
+/// but an object goes under `data`.
+pub fn document(command: &str, body: Value) -> Value {
+    let mut object = Map::new();
+    object.insert("schema_version".to_string(), json!(SCHEMA_VERSION));
+    object.insert("command".to_string(), json!(command));
+    match body {
+        Value::Object(fields) => object.extend(fields),
+        other => {
+            object.insert("data".to_string(), other);
+        }
+    }
+    Value::Object(object)
+}
+
+/// Print a command's document: indented for `json`, one line for `json-compact`.
+pub fn print(format: OutputFormat, command: &str, body: impl Serialize) -> Result<()> {
+    let document = document(command, serde_json::to_value(body)?);
+    match format {
+        OutputFormat::JsonCompact => println!("{}", serde_json::to_string(&document)?),
+        _ => println!("{}", serde_json::to_string_pretty(&document)?),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_envelope() {
+        let value = document("pane history", json!({ "pane": "api", "entries": [] }));
+        assert_eq!(value["schema_version"], SCHEMA_VERSION);
+        assert_eq!(value["command"], "pane history");
+        assert_eq!(value["pane"], "api");
+
+        // Bodies with their own schema keep it; non-objects are nested
+        let value = document("capabilities", json!({ "schema_version": "1.0" }));
+        assert_eq!(value["schema_version"], "1.0");
+        let value = document("snapshot list", json!([1, 2]));
+        assert_eq!(value["data"], json!([1, 2]));
+    }
+}
@@ -0,0 +1,182 @@
+//! User-facing message fragments shared across commands.
+//!
+//! Counts go through [`count`] so nouns are pluralized correctly ("1 entry",
+//! "2 entries"), and status markers go through [`Icon`], which falls back to
+//! ASCII when output is not a color terminal. Commands build their messages
+//! here rather than inline, so alternative output modes only need to touch
+//! this module.
+
+use crate::types::{IntentEntry, RestoreStatus};
+use std::fmt;
+use std::io::IsTerminal;
+use std::sync::LazyLock;
+
+/// Whether icons may use Unicode symbols: the same rule as color in
+/// [`super::OutputFormatter`], unless the theme asks for ASCII.
+static UNICODE: LazyLock<bool> = LazyLock::new(|| {
+    let theme = super::theme::current();
+    !theme.ascii && theme.color_enabled(std::io::stdout().is_terminal())
+});
+
+/// Whether output may use Unicode symbols and animation.
+pub fn unicode() -> bool {
+    *UNICODE
+}
+
+/// Plural form of a regular English noun.
+pub fn plural(noun: &str) -> String {
+    let consonant_y = noun.strip_suffix('y').is_some_and(|stem| {
+        stem.chars().last().is_some_and(|c| !"aeiou".contains(c.to_ascii_lowercase()))
+    });
+    if consonant_y {
+        format!("{}ies", &noun[..noun.len() - 1])
+    } else if ["s", "x", "z", "ch", "sh"].iter().any(|end| noun.ends_with(end)) {
+        format!("{}es", noun)
+    } else {
+        format!("{}s", noun)
+    }
+}
+
+/// `n` followed by the noun, pluralized unless `n` is 1.
+pub fn count(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}", n, plural(noun))
+    }
+}
+
+/// Status and direction markers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Icon {
+    Success,
+    Warning,
+    Failure,
+    /// Points at the current item in a chain
+    Forward,
+    /// Points back at an earlier item in a chain
+    Back,
+}
+
+impl Icon {
+    /// The marker for the current output: Unicode on a color terminal,
+    /// ASCII otherwise.
+    pub fn glyph(self) -> &'static str {
+        self.themed(unicode())
+    }
+
+    pub fn themed(self, unicode: bool) -> &'static str {
+        match (self, unicode) {
+            (Icon::Success, true) => "✓",
+            (Icon::Success, false) => "[ok]",
+            (Icon::Warning, true) => "⚠",
+            (Icon::Warning, false) => "[!]",
+            (Icon::Failure, true) => "✗",
+            (Icon::Failure, false) => "[x]",
+            (Icon::Forward, true) => "→",
+            (Icon::Forward, false) => "->",
+            (Icon::Back, true) => "←",
+            (Icon::Back, false) => "<-",
+        }
+    }
+}
+
+impl fmt::Display for Icon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.glyph())
+    }
+}
+
+/// Confirmation after `pane log`, e.g. "Logged milestone for 'api': Done (2 artifacts)".
+pub fn logged_entry(entry_type: &str, pane_name: &str, source_tag: &str, summary: &str, artifacts: usize) -> String {
+    let mut message = format!("Logged {} for '{}'{}: {}", entry_type.to_lowercase(), pane_name, source_tag, summary);
+    if artifacts > 0 {
+        message.push_str(&format!(" ({})", count(artifacts, "artifact")));
+    }
+    message
+}
+
+/// Confirmation after `pane log --append-artifacts`, e.g.
+/// "Added 2 artifacts to checkpoint 3f2a9c1d in 'api' (3 total)".
+pub fn appended_artifacts(entry: &IntentEntry, pane_name: &str, added: usize) -> String {
+    let id = entry.id.simple().to_string();
+    let target = format!("{} {} in '{}'", entry.entry_type_str().to_lowercase(), &id[..8], pane_name);
+    if added == 0 {
+        return format!("No new artifacts for {}; all already listed", target);
+    }
+    format!("Added {} to {} ({} total)", count(added, "artifact"), target, entry.artifacts.len())
+}
+
+/// How to reach a pane in a session other than the current one, which
+/// Zellij can't switch to from inside a session.
+pub fn attach_instructions(pane_name: &str, session: &str, inside_zellij: bool) -> String {
+    let mut message = format!("'{}' is in session '{}'.", pane_name, session);
+    if inside_zellij {
+        message.push_str(" Detach (Ctrl-o d), then run:");
+    } else {
+        message.push_str(" Run:");
+    }
+    message.push_str(&format!("\n  zellij attach {}\n  zdrive pane {}", session, pane_name));
+    message
+}
+
+/// Note after snapshot retention removes older snapshots.
+pub fn cleaned_up_snapshots(deleted: usize) -> String {
+    format!("(Cleaned up {})", count(deleted, "old snapshot"))
+}
+
+/// Note after a safety snapshot was taken before a destructive command.
+pub fn backup_snapshot(name: &str) -> String {
+    format!("Safety snapshot '{}' saved (undo with 'zdrive snapshot restore {}')", name, name)
+}
+
+/// Final line of `snapshot restore`.
+pub fn restore_outcome(status: &RestoreStatus) -> String {
+    match status {
+        RestoreStatus::Success => format!("{} Session successfully restored from snapshot", Icon::Success),
+        RestoreStatus::Partial => format!("{} Session partially restored (see warnings above)", Icon::Warning),
+        RestoreStatus::Failed => format!("{} Session restoration failed (see errors above)", Icon::Failure),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_pluralizes() {
+        assert_eq!(count(1, "entry"), "1 entry");
+        assert_eq!(count(0, "entry"), "0 entries");
+        assert_eq!(count(2, "day"), "2 days");
+        assert_eq!(count(3, "pane"), "3 panes");
+        assert_eq!(count(2, "match"), "2 matches");
+        assert_eq!(count(2, "old snapshot"), "2 old snapshots");
+    }
+
+    #[test]
+    fn test_icons_fall_back_to_ascii() {
+        assert_eq!(Icon::Success.themed(true), "✓");
+        assert_eq!(Icon::Success.themed(false), "[ok]");
+        assert_eq!(Icon::Forward.themed(false), "->");
+    }
+
+    #[test]
+    fn test_logged_entry() {
+        assert_eq!(logged_entry("Milestone", "api", "", "Done", 0), "Logged milestone for 'api': Done");
+        assert_eq!(
+            logged_entry("checkpoint", "api", " [agent]", "Done", 1),
+            "Logged checkpoint for 'api' [agent]: Done (1 artifact)"
+        );
+    }
+
+    #[test]
+    fn test_appended_artifacts() {
+        let entry = IntentEntry::new("Done").with_artifacts(vec!["a.rs".to_string(), "b.rs".to_string()]);
+        let id = &entry.id.simple().to_string()[..8];
+        assert_eq!(
+            appended_artifacts(&entry, "api", 1),
+            format!("Added 1 artifact to checkpoint {} in 'api' (2 total)", id)
+        );
+        assert!(appended_artifacts(&entry, "api", 0).starts_with("No new artifacts"));
+    }
+}
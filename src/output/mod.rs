@@ -0,0 +1,1176 @@
+pub mod html;
+pub mod json;
+pub mod messages;
+pub mod pager;
+pub mod progress;
+pub mod records;
+pub mod theme;
+
+use crate::bloodbank::IncomingEvent;
+use crate::config::ContextDisplayConfig;
+use crate::digest::Digest;
+use crate::filter::Redaction;
+use crate::state::KeyspaceStats;
+use crate::types::{Annotation, GoalProgress, IntentEntry, IntentSource, IntentType, LlmUsage};
+use chrono::{DateTime, Local, Utc};
+use chrono_humanize::HumanTime;
+use clap::ValueEnum;
+use colored::Colorize;
+use std::collections::{HashMap, HashSet};
+use std::io::IsTerminal;
+use theme::Theme;
+use uuid::Uuid;
+
+/// Output format for commands
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable text (default)
+    #[default]
+    Text,
+    /// Pretty-printed JSON
+    Json,
+    /// Compact single-line JSON
+    JsonCompact,
+    /// Markdown with YAML frontmatter (Obsidian-compatible)
+    Markdown,
+    /// Standalone HTML timeline with collapsible days (pane history)
+    Html,
+    /// One CSV row per entry, with a header (pane history and digest)
+    Csv,
+    /// One JSON object per line per entry, with its pane (pane history and digest)
+    Jsonl,
+    /// LLM-optimized context for prompt injection (~1000 tokens)
+    Context,
+}
+
+impl OutputFormat {
+    /// Whether this is one of the JSON formats.
+    pub fn is_json(self) -> bool {
+        matches!(self, OutputFormat::Json | OutputFormat::JsonCompact)
+    }
+}
+
+pub struct OutputFormatter {
+    use_color: bool,
+    /// Badge colors and icon style, from `[display.theme]` and the global flags
+    theme: Theme,
+    terminal_width: Option<usize>,
+    /// Annotation threads keyed by entry ID, rendered beneath their entry
+    annotations: HashMap<Uuid, Vec<Annotation>>,
+    /// Active goal shown in context output
+    goal: Option<GoalProgress>,
+    /// Sections and token budget for context output
+    context: ContextDisplayConfig,
+}
+
+impl OutputFormatter {
+    pub fn new() -> Self {
+        Self::with_color(theme::current().color_enabled(std::io::stdout().is_terminal()))
+    }
+
+    /// A formatter for messages written to stderr, colored only when stderr
+    /// is a terminal
+    pub fn for_stderr() -> Self {
+        Self::with_color(theme::current().color_enabled(std::io::stderr().is_terminal()))
+    }
+
+    fn with_color(use_color: bool) -> Self {
+        let terminal_width = terminal_size::terminal_size().map(|(w, _)| w.0 as usize);
+
+        Self {
+            use_color,
+            theme: theme::current().clone(),
+            terminal_width,
+            annotations: HashMap::new(),
+            goal: None,
+            context: ContextDisplayConfig::default(),
+        }
+    }
+
+    /// Builder method to render annotations beneath the entries they belong to
+    pub fn with_annotations(mut self, annotations: Vec<Annotation>) -> Self {
+        for annotation in annotations {
+            self.annotations.entry(annotation.entry_id).or_default().push(annotation);
+        }
+        self
+    }
+
+    /// Builder method to show the pane's active goal and its progress in
+    /// context output, and its title in Markdown frontmatter
+    pub fn with_goal(mut self, goal: Option<GoalProgress>) -> Self {
+        self.goal = goal;
+        self
+    }
+
+    /// Builder method to choose the sections and token budget of context
+    /// output
+    pub fn with_context_display(mut self, context: ContextDisplayConfig) -> Self {
+        self.context = context;
+        self
+    }
+
+    fn annotations_for(&self, entry: &IntentEntry) -> &[Annotation] {
+        self.annotations.get(&entry.id).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn format_history(&self, entries: &[IntentEntry], pane_name: &str) -> String {
+        if entries.is_empty() {
+            return format!("No history for pane '{}'", pane_name);
+        }
+
+        let mut output = Vec::new();
+
+        for entry in entries {
+            output.push(self.format_entry(entry));
+        }
+
+        output.join("\n\n")
+    }
+
+    fn format_entry(&self, entry: &IntentEntry) -> String {
+        let mut lines = Vec::new();
+
+        // Header line: type badge + source badge (if agent) + relative time + short ID
+        let type_badge = self.format_type_badge(entry.entry_type);
+        let source_badge = self.format_source_badge(entry);
+        let time_str = self.format_relative_time(entry.timestamp);
+        let short_id = if self.use_color {
+            entry.short_id().dimmed().to_string()
+        } else {
+            entry.short_id()
+        };
+
+        if source_badge.is_empty() {
+            lines.push(format!("{} {} {}", type_badge, time_str, short_id));
+        } else {
+            lines.push(format!("{} {} {} {}", type_badge, source_badge, time_str, short_id));
+        }
+
+        // Summary line with wrapping
+        let summary = self.wrap_text(&entry.summary, 2);
+        lines.push(summary);
+
+        if !entry.tags.is_empty() {
+            let tags = format_tags(&entry.tags);
+            lines.push(if self.use_color { format!("  {}", tags.color(self.theme.accent)) } else { format!("  {}", tags) });
+        }
+
+        if let Some(sha) = &entry.commit {
+            let short = &sha[..sha.len().min(7)];
+            if self.use_color {
+                lines.push(format!("  {} {}", "commit".dimmed(), short.yellow()));
+            } else {
+                lines.push(format!("  commit {}", short));
+            }
+        }
+
+        // Artifacts if present
+        if !entry.artifacts.is_empty() {
+            for artifact in &entry.artifacts {
+                let artifact_line = if self.use_color {
+                    format!("  {} {}", self.theme.arrow().dimmed(), artifact.dimmed())
+                } else {
+                    format!("  -> {}", artifact)
+                };
+                lines.push(artifact_line);
+            }
+        }
+
+        // Annotation thread, oldest first
+        for annotation in self.annotations_for(entry) {
+            let mut line = format!("  💬 {}", annotation.note);
+            if let Some(author) = &annotation.author {
+                line.push_str(&format!(" — {}", author));
+            }
+            let when = HumanTime::from(annotation.timestamp).to_string();
+            if self.use_color {
+                lines.push(format!("{} {}", line.italic(), format!("({})", when).dimmed()));
+            } else {
+                lines.push(format!("{} ({})", line, when));
+            }
+        }
+
+        lines.join("\n")
+    }
+
+    fn format_type_badge(&self, entry_type: IntentType) -> String {
+        let label = match entry_type {
+            IntentType::Milestone => "MILESTONE",
+            IntentType::Checkpoint => "CHECKPOINT",
+            IntentType::Exploration => "EXPLORATION",
+        };
+        let badge = format!("[{} {}]", self.theme.type_icon(entry_type), label);
+
+        if !self.use_color {
+            return badge;
+        }
+        let colored = badge.color(self.theme.type_color(entry_type));
+        if entry_type == IntentType::Milestone {
+            colored.bold().to_string()
+        } else {
+            colored.to_string()
+        }
+    }
+
+    fn format_source_badge(&self, entry: &IntentEntry) -> String {
+        let label = match entry.source {
+            IntentSource::Manual => None, // Default, no badge unless attributed
+            IntentSource::Automated => Some("AUTO"),
+            IntentSource::Agent => Some("AGENT"),
+            IntentSource::Imported => Some("IMPORTED"),
+        };
+        let label = label.map(|label| match self.theme.source_icon(entry.source) {
+            Some(icon) => format!("{} {}", icon, label),
+            None => label.to_string(),
+        });
+        let badge = match (label, &entry.source_detail) {
+            (None, None) => return String::new(),
+            (None, Some(detail)) => format!("[{}]", detail),
+            (Some(label), None) => format!("[{}]", label),
+            (Some(label), Some(detail)) => format!("[{}: {}]", label, detail),
+        };
+        if !self.use_color {
+            return badge;
+        }
+        match self.theme.source_color(entry.source) {
+            None => badge.dimmed().to_string(),
+            Some(color) if entry.source == IntentSource::Agent => badge.color(color).bold().to_string(),
+            Some(color) => badge.color(color).to_string(),
+        }
+    }
+
+    /// One-line reminder of a pane's last entry, shown on stderr when
+    /// switching back to it
+    pub fn format_resume_banner(&self, entry: &IntentEntry) -> String {
+        let icon = self.theme.type_icon(entry.entry_type);
+        let when = HumanTime::from(entry.timestamp).to_string();
+        let source = match (self.theme.source_icon(entry.source), &entry.source_detail) {
+            (None, None) => String::new(),
+            (Some(icon), None) => format!(" {}", icon),
+            (None, Some(detail)) => format!(" {}", detail),
+            (Some(icon), Some(detail)) => format!(" {} {}", icon, detail),
+        };
+
+        if self.use_color {
+            format!(
+                "{} {} {} {}{}",
+                "Resuming:".color(self.theme.accent),
+                icon.color(self.theme.type_color(entry.entry_type)),
+                entry.summary,
+                when.dimmed(),
+                source
+            )
+        } else {
+            format!("Resuming: {} {} ({}){}", icon, entry.summary, when, source)
+        }
+    }
+
+    fn format_relative_time(&self, timestamp: DateTime<Utc>) -> String {
+        let local_time: DateTime<Local> = timestamp.into();
+        let human_time = HumanTime::from(timestamp);
+
+        let relative = human_time.to_string();
+        let absolute = local_time.format("%Y-%m-%d %H:%M").to_string();
+
+        if self.use_color {
+            format!("{} ({})", relative.dimmed(), absolute.dimmed())
+        } else {
+            format!("{} ({})", relative, absolute)
+        }
+    }
+
+    /// LLM-optimized context. Over the configured token budget, sections
+    /// are trimmed: next steps first, then older recent entries (down to
+    /// one), then milestones.
+    pub fn format_context(&self, entries: &[IntentEntry], pane_name: &str) -> String {
+        let mut options = self.context.clone();
+        loop {
+            let output = self.render_context(entries, pane_name, &options);
+            if options.budget_tokens == 0 || crate::llm::estimate_tokens(&output) as usize <= options.budget_tokens {
+                return output;
+            }
+            if options.next_steps {
+                options.next_steps = false;
+            } else if options.recent_entries > 1 {
+                options.recent_entries -= 1;
+            } else if options.milestones {
+                options.milestones = false;
+            } else {
+                return output;
+            }
+        }
+    }
+
+    fn render_context(&self, entries: &[IntentEntry], pane_name: &str, options: &ContextDisplayConfig) -> String {
+        let mut output = Vec::new();
+
+        // Header with session context
+        output.push(format!("## Session Context: {}", pane_name));
+        output.push(String::new());
+
+        // The active goal and the progress reported toward it, oldest first
+        if let Some(progress) = &self.goal {
+            output.push("### Active Goal".to_string());
+            output.push(format!("**{}** (set {})", progress.goal.title, progress.goal.set_at.format("%Y-%m-%d %H:%M")));
+            if progress.entries.is_empty() {
+                output.push("No progress logged toward it yet.".to_string());
+            } else {
+                output.push("Progress so far:".to_string());
+                for entry in &progress.entries {
+                    let delta = entry.goal_delta.as_deref().unwrap_or(&entry.summary);
+                    output.push(format!("- {} {}", entry.timestamp.format("%m-%d %H:%M"), delta));
+                }
+            }
+            output.push(String::new());
+        }
+
+        if entries.is_empty() {
+            output.push("This is a new session with no prior history.".to_string());
+            output.push(String::new());
+            output.push("### Recommended First Steps".to_string());
+            output.push("1. Review the current codebase state".to_string());
+            output.push("2. Identify the main objective for this session".to_string());
+            output.push("3. Log your initial intent with `zdrive pane log`".to_string());
+            return output.join("\n");
+        }
+
+        // Calculate session stats
+        let total_entries = entries.len();
+        let milestone_count = entries.iter().filter(|e| e.entry_type == IntentType::Milestone).count();
+        let agent_count = entries.iter().filter(|e| e.source == IntentSource::Agent).count();
+        let human_count = entries.iter().filter(|e| e.source == IntentSource::Manual).count();
+
+        // Session overview
+        output.push("### Session Overview".to_string());
+        output.push(format!("- Total entries: {} ({} milestones)", total_entries, milestone_count));
+        if agent_count > 0 {
+            output.push(format!("- Agent contributions: {} entries", agent_count));
+        }
+        if human_count > 0 {
+            output.push(format!("- Human entries: {}", human_count));
+        }
+
+        // Time span
+        if let (Some(newest), Some(oldest)) = (entries.first(), entries.last()) {
+            let duration = newest.timestamp - oldest.timestamp;
+            let hours = duration.num_hours();
+            let mins = duration.num_minutes() % 60;
+            if hours > 0 {
+                output.push(format!("- Session duration: {}h {}m", hours, mins));
+            } else if mins > 0 {
+                output.push(format!("- Session duration: {}m", mins));
+            }
+        }
+        output.push(String::new());
+
+        // Recent activity, limited for token efficiency
+        output.push("### Recent Activity".to_string());
+        let recent_entries: Vec<_> = entries.iter().take(options.recent_entries).collect();
+        for entry in &recent_entries {
+            let type_marker = match entry.entry_type {
+                IntentType::Milestone => "🌟 MILESTONE",
+                IntentType::Checkpoint => "●",
+                IntentType::Exploration => "🔍",
+            };
+            let source = match entry.source {
+                IntentSource::Agent => Some("agent"),
+                IntentSource::Automated => Some("auto"),
+                IntentSource::Imported => Some("imported"),
+                IntentSource::Manual => None,
+            };
+            let source_marker = match (source, &entry.source_detail) {
+                (None, None) => String::new(),
+                (None, Some(detail)) => format!(" [{}]", detail),
+                (Some(source), None) => format!(" [{}]", source),
+                (Some(source), Some(detail)) => format!(" [{}: {}]", source, detail),
+            };
+            let time = entry.timestamp.format("%H:%M").to_string();
+            let tags = if entry.tags.is_empty() { String::new() } else { format!(" {}", format_tags(&entry.tags)) };
+            output.push(format!("- {} ({}{}) {}{}", type_marker, time, source_marker, entry.summary, tags));
+
+            // Include artifacts for milestones (they're important)
+            if entry.entry_type == IntentType::Milestone && !entry.artifacts.is_empty() {
+                for artifact in &entry.artifacts {
+                    output.push(format!("  - `{}`", artifact));
+                }
+            }
+        }
+        output.push(String::new());
+
+        // Last checkpoint (most recent entry)
+        if let Some(last) = entries.first() {
+            output.push("### Current State".to_string());
+            output.push(format!("Last checkpoint: **{}**", last.summary));
+            if !last.artifacts.is_empty() {
+                output.push(format!("Key files: {}", last.artifacts.join(", ")));
+            }
+            output.push(String::new());
+        }
+
+        // Identify milestones for context
+        let milestones: Vec<_> = entries.iter()
+            .filter(|e| e.entry_type == IntentType::Milestone)
+            .take(3)
+            .collect();
+
+        if options.milestones && !milestones.is_empty() {
+            output.push("### Key Milestones".to_string());
+            for m in milestones {
+                output.push(format!("- {} ({})", m.summary, m.timestamp.format("%Y-%m-%d")));
+            }
+            output.push(String::new());
+        }
+
+        // Suggested next steps based on history
+        if !options.next_steps {
+            return output.join("\n").trim_end().to_string();
+        }
+        output.push("### Suggested Next Steps".to_string());
+        if let Some(last) = entries.first() {
+            match last.entry_type {
+                IntentType::Exploration => {
+                    output.push("1. Review findings from the exploration".to_string());
+                    output.push("2. Decide on implementation approach".to_string());
+                    output.push("3. Log a milestone when committing to a direction".to_string());
+                }
+                IntentType::Milestone => {
+                    output.push("1. Verify the milestone is stable".to_string());
+                    output.push("2. Identify the next feature or fix to tackle".to_string());
+                    output.push("3. Log a checkpoint to track progress".to_string());
+                }
+                IntentType::Checkpoint => {
+                    output.push("1. Continue from the last checkpoint".to_string());
+                    output.push("2. Log progress as you work".to_string());
+                    output.push("3. Mark significant achievements as milestones".to_string());
+                }
+            }
+        }
+
+        output.join("\n")
+    }
+
+    pub fn format_markdown(&self, entries: &[IntentEntry], pane_name: &str) -> String {
+        let mut output = Vec::new();
+
+        // YAML frontmatter
+        output.push("---".to_string());
+        output.push(format!("pane: {}", pane_name));
+        if let Some(progress) = &self.goal {
+            output.push(format!("goal: {}", serde_json::Value::from(progress.goal.title.as_str())));
+            output.push(format!("goal_id: {}", progress.goal.id));
+            output.push(format!("goal_set_at: {}", progress.goal.set_at.to_rfc3339()));
+        }
+        output.push(format!("entries: {}", entries.len()));
+        if let Some(first) = entries.first() {
+            output.push(format!("latest: {}", first.timestamp.format("%Y-%m-%d")));
+        }
+        if let Some(last) = entries.last() {
+            output.push(format!("earliest: {}", last.timestamp.format("%Y-%m-%d")));
+        }
+        output.push(format!("exported: {}", chrono::Local::now().format("%Y-%m-%dT%H:%M:%S")));
+        output.push("---".to_string());
+        output.push(String::new());
+        output.push(self.format_markdown_body(entries, pane_name));
+        output.join("\n")
+    }
+
+    /// Markdown history without frontmatter: a title, then entries grouped
+    /// by date. Contains nothing time-dependent, so unchanged history
+    /// renders identically.
+    pub fn format_markdown_body(&self, entries: &[IntentEntry], pane_name: &str) -> String {
+        let mut output = Vec::new();
+
+        // Title
+        output.push(format!("# Session: {}", pane_name));
+        output.push(String::new());
+
+        if entries.is_empty() {
+            output.push("*No entries recorded.*".to_string());
+            return output.join("\n");
+        }
+
+        // Group entries by date
+        let mut current_date = String::new();
+
+        for entry in entries {
+            let entry_date = entry.timestamp.format("%Y-%m-%d").to_string();
+
+            if entry_date != current_date {
+                if !current_date.is_empty() {
+                    output.push(String::new());
+                }
+                output.push(format!("## {}", entry_date));
+                output.push(String::new());
+                current_date = entry_date;
+            }
+
+            // Entry line with type emoji, source tag, and time
+            let emoji = match entry.entry_type {
+                IntentType::Milestone => "🌟",
+                IntentType::Checkpoint => "📍",
+                IntentType::Exploration => "🔍",
+            };
+
+            let source_tag = match entry.source {
+                IntentSource::Manual => "",
+                IntentSource::Automated => " ⚡",
+                IntentSource::Agent => " 🤖",
+                IntentSource::Imported => " 📥",
+            };
+
+            let detail = entry.source_detail.as_deref().map(|d| format!(" {}", d)).unwrap_or_default();
+
+            let time = entry.timestamp.format("%H:%M").to_string();
+            let tags = if entry.tags.is_empty() { String::new() } else { format!(" {}", format_tags(&entry.tags)) };
+            output.push(format!("- {}{}{} **{}** {}{}", emoji, source_tag, detail, time, entry.summary, tags));
+
+            // Artifacts as sub-bullets with file links
+            for artifact in &entry.artifacts {
+                // Create Obsidian-compatible file link if it looks like a path
+                if artifact.contains('/') || artifact.contains('.') {
+                    output.push(format!("  - `{}`", artifact));
+                } else {
+                    output.push(format!("  - {}", artifact));
+                }
+            }
+
+            // Progress on the active goal, so a handoff keeps counting it
+            if entry.goal_id.is_some() && entry.goal_id == self.goal.as_ref().map(|p| p.goal.id) {
+                match &entry.goal_delta {
+                    Some(delta) => output.push(format!("  > 🎯 {}", delta)),
+                    None => output.push("  > 🎯".to_string()),
+                }
+            }
+
+            // Annotations as quoted sub-lines beneath the entry
+            for annotation in self.annotations_for(entry) {
+                match &annotation.author {
+                    Some(author) => output.push(format!("  > 💬 {} — {}", annotation.note, author)),
+                    None => output.push(format!("  > 💬 {}", annotation.note)),
+                }
+            }
+        }
+
+        output.join("\n")
+    }
+
+    /// Format `zdrive state stats` as an aligned table.
+    pub fn format_keyspace_stats(&self, stats: &KeyspaceStats) -> String {
+        let mut output = Vec::new();
+        let header = format!("{:<12} {:>8} {:>12}", "GROUP", "KEYS", "MEMORY");
+        output.push(if self.use_color { header.bold().to_string() } else { header });
+
+        let memory = |bytes: u64| {
+            if stats.memory_usage_available {
+                format!("~{}", format_bytes(bytes))
+            } else {
+                "n/a".to_string()
+            }
+        };
+        for group in &stats.groups {
+            output.push(format!("{:<12} {:>8} {:>12}", group.category, group.keys, memory(group.bytes)));
+        }
+        output.push(format!("{:<12} {:>8} {:>12}", "total", stats.total_keys(), memory(stats.total_bytes())));
+
+        if !stats.memory_usage_available {
+            output.push(String::new());
+            output.push("MEMORY USAGE is not available on this server; memory was not estimated.".to_string());
+        }
+
+        if !stats.largest_histories.is_empty() {
+            output.push(String::new());
+            output.push("Largest histories:".to_string());
+            for history in &stats.largest_histories {
+                output.push(format!(
+                    "  {:<30} {:>5} entries {:>12}",
+                    history.pane,
+                    history.entries,
+                    memory(history.bytes)
+                ));
+            }
+        }
+
+        output.join("\n")
+    }
+
+    /// Format LLM usage as a table with a totals row. `period` labels the header.
+    pub fn format_llm_usage(&self, usage: &[LlmUsage], period: &str) -> String {
+        if usage.is_empty() {
+            return format!("No LLM usage recorded ({}).", period);
+        }
+
+        let mut output = vec![format!("LLM usage ({}):", period), String::new()];
+        let header = format!(
+            "{:<12} {:<28} {:>8} {:>12} {:>12} {:>10}",
+            "PROVIDER", "MODEL", "REQUESTS", "INPUT", "OUTPUT", "COST"
+        );
+        output.push(if self.use_color { header.bold().to_string() } else { header });
+
+        let cost = |usd: Option<f64>| usd.map(|c| format!("${:.4}", c)).unwrap_or_else(|| "-".to_string());
+        for row in usage {
+            output.push(format!(
+                "{:<12} {:<28} {:>8} {:>12} {:>12} {:>10}",
+                row.provider, row.model, row.requests, row.input_tokens, row.output_tokens, cost(row.cost_usd)
+            ));
+        }
+
+        let known: Vec<f64> = usage.iter().filter_map(|row| row.cost_usd).collect();
+        output.push(format!(
+            "{:<12} {:<28} {:>8} {:>12} {:>12} {:>10}",
+            "total",
+            "",
+            usage.iter().map(|row| row.requests).sum::<u64>(),
+            usage.iter().map(|row| row.input_tokens).sum::<u64>(),
+            usage.iter().map(|row| row.output_tokens).sum::<u64>(),
+            cost((!known.is_empty()).then(|| known.iter().sum()))
+        ));
+
+        if known.len() < usage.len() {
+            output.push(String::new());
+            output.push("Models marked '-' have no known price and are excluded from the total.".to_string());
+        }
+
+        output.join("\n")
+    }
+
+    /// Format a secret-filter redaction report, one row per redaction.
+    pub fn format_redactions(&self, redactions: &[Redaction]) -> String {
+        if redactions.is_empty() {
+            return "No redactions: nothing matched the secret filter.".to_string();
+        }
+
+        let mut output = vec![format!("{} redaction(s):", redactions.len()), String::new()];
+        let header = format!("{:<20} {:<20} {}", "SOURCE", "PATTERN", "PREVIEW");
+        output.push(if self.use_color { header.bold().to_string() } else { header });
+        for redaction in redactions {
+            output.push(format!("{:<20} {:<20} {}", redaction.source, redaction.pattern, redaction.preview));
+        }
+
+        output.join("\n")
+    }
+
+    /// Format an event seen by `zdrive events tail`: a header with the time
+    /// received, event type, correlation ID, and session, then one line per
+    /// payload field.
+    pub fn format_event(&self, event: &IncomingEvent, received: DateTime<Local>) -> String {
+        let event_type = if self.use_color {
+            event.event_type.color(self.theme.accent).bold().to_string()
+        } else {
+            event.event_type.clone()
+        };
+        let mut header = format!("{} {}", received.format("%H:%M:%S"), event_type);
+        if let Some(id) = event.correlation_id() {
+            header.push_str(&format!(" [{}]", id));
+        }
+        if let Some(session) = event.metadata.get("session").and_then(|s| s.as_str()) {
+            header.push_str(&format!(" @{}", session));
+        }
+
+        let mut output = vec![header];
+        match event.payload.as_object() {
+            Some(fields) => {
+                for (key, value) in fields {
+                    let value = match value {
+                        serde_json::Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    };
+                    output.push(format!("  {}: {}", key, value));
+                }
+            }
+            None if !event.payload.is_null() => output.push(format!("  {}", event.payload)),
+            None => {}
+        }
+        output.join("\n")
+    }
+
+    /// Format `zdrive digest` for the terminal.
+    pub fn format_digest(&self, digest: &Digest) -> String {
+        let period = format!(
+            "{} to {}",
+            digest.since.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+            digest.until.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+        );
+        if digest.days.is_empty() {
+            return format!("No intent entries from {}.", period);
+        }
+
+        let title = format!(
+            "Digest {} (entries: {}, milestones: {})",
+            period,
+            digest.entry_count(),
+            digest.milestone_count()
+        );
+        let mut output = vec![if self.use_color { title.bold().to_string() } else { title }];
+
+        if let Some(summary) = &digest.summary {
+            output.push(String::new());
+            output.push(summary.trim().to_string());
+        }
+
+        for day in &digest.days {
+            output.push(String::new());
+            let heading = day.date.format("%A %Y-%m-%d").to_string();
+            output.push(if self.use_color { heading.bold().underline().to_string() } else { heading });
+            for pane in &day.panes {
+                output.push(if self.use_color { format!("  {}", pane.pane_name.color(self.theme.accent)) } else { format!("  {}", pane.pane_name) });
+                for entry in &pane.entries {
+                    let time = entry.timestamp.with_timezone(&Local).format("%H:%M");
+                    output.push(format!(
+                        "    {} {} {}{}",
+                        time,
+                        self.format_type_badge(entry.entry_type),
+                        entry.summary,
+                        entry.source_detail.as_deref().map(|d| format!(" (via {})", d)).unwrap_or_default()
+                    ));
+                }
+            }
+        }
+
+        output.join("\n")
+    }
+
+    /// Format `zdrive digest` as Markdown, for pasting into standup notes.
+    pub fn format_digest_markdown(&self, digest: &Digest) -> String {
+        let mut output = vec![format!(
+            "# Digest: {} – {}",
+            digest.since.with_timezone(&Local).format("%Y-%m-%d"),
+            digest.until.with_timezone(&Local).format("%Y-%m-%d")
+        )];
+        output.push(String::new());
+        output.push(format!(
+            "*Entries: {} · Milestones: {}*",
+            digest.entry_count(),
+            digest.milestone_count()
+        ));
+
+        if let Some(summary) = &digest.summary {
+            output.push(String::new());
+            output.push("## Summary".to_string());
+            output.push(String::new());
+            output.push(summary.trim().to_string());
+        }
+
+        for day in &digest.days {
+            output.push(String::new());
+            output.push(format!("## {}", day.date.format("%A %Y-%m-%d")));
+            for pane in &day.panes {
+                output.push(String::new());
+                output.push(format!("### {}", pane.pane_name));
+                output.push(String::new());
+                for entry in &pane.entries {
+                    let emoji = match entry.entry_type {
+                        IntentType::Milestone => "🌟 ",
+                        IntentType::Checkpoint => "",
+                        IntentType::Exploration => "🔍 ",
+                    };
+                    let time = entry.timestamp.with_timezone(&Local).format("%H:%M");
+                    let via = entry.source_detail.as_deref().map(|d| format!(" *(via {})*", d)).unwrap_or_default();
+                    output.push(format!("- {}**{}** {}{}", emoji, time, entry.summary, via));
+                }
+            }
+        }
+
+        output.join("\n")
+    }
+
+    fn wrap_text(&self, text: &str, indent: usize) -> String {
+        let width = self.terminal_width.unwrap_or(80);
+        let available = width.saturating_sub(indent);
+
+        if text.len() <= available {
+            return format!("{:indent$}{}", "", text, indent = indent);
+        }
+
+        let mut lines = Vec::new();
+        let mut current_line = String::new();
+        let indent_str = " ".repeat(indent);
+
+        for word in text.split_whitespace() {
+            if current_line.is_empty() {
+                current_line = word.to_string();
+            } else if current_line.len() + 1 + word.len() <= available {
+                current_line.push(' ');
+                current_line.push_str(word);
+            } else {
+                lines.push(format!("{}{}", indent_str, current_line));
+                current_line = word.to_string();
+            }
+        }
+
+        if !current_line.is_empty() {
+            lines.push(format!("{}{}", indent_str, current_line));
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Tags as hashtags, e.g. `#bugfix #auth`.
+fn format_tags(tags: &[String]) -> String {
+    tags.iter().map(|tag| format!("#{}", tag)).collect::<Vec<_>>().join(" ")
+}
+
+/// File names for per-pane files such as exported pages and notes.
+/// Characters outside `[A-Za-z0-9._-]` become '-', and names that collide
+/// after that get a numeric suffix, so the same pane names in the same
+/// order always map to the same files.
+pub fn pane_file_names<'a>(pane_names: impl IntoIterator<Item = &'a str>, extension: &str) -> Vec<String> {
+    let mut taken = HashSet::new();
+    pane_names
+        .into_iter()
+        .map(|name| {
+            let slug: String = name
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() || "._-".contains(c) { c } else { '-' })
+                .collect();
+            let slug = slug.trim_matches(['-', '.']).to_string();
+            let slug = if slug.is_empty() { "pane".to_string() } else { slug };
+            let mut file_name = format!("{}.{}", slug, extension);
+            let mut n = 2;
+            while !taken.insert(file_name.clone()) {
+                file_name = format!("{}-{}.{}", slug, n, extension);
+                n += 1;
+            }
+            file_name
+        })
+        .collect()
+}
+
+/// Human-readable byte count, e.g. `1.5 MiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+impl Default for OutputFormatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::IntentEntry;
+
+    #[test]
+    fn test_format_empty_history() {
+        let formatter = OutputFormatter {
+            use_color: false,
+            theme: Theme::default(),
+            terminal_width: Some(80),
+            annotations: HashMap::new(),
+            goal: None,
+            context: ContextDisplayConfig::default(),
+        };
+        let result = formatter.format_history(&[], "test-pane");
+        assert_eq!(result, "No history for pane 'test-pane'");
+    }
+
+    #[test]
+    fn test_format_type_badge_no_color() {
+        let formatter = OutputFormatter {
+            use_color: false,
+            theme: Theme::default(),
+            terminal_width: Some(80),
+            annotations: HashMap::new(),
+            goal: None,
+            context: ContextDisplayConfig::default(),
+        };
+
+        assert_eq!(
+            formatter.format_type_badge(IntentType::Milestone),
+            "[★ MILESTONE]"
+        );
+        assert_eq!(
+            formatter.format_type_badge(IntentType::Checkpoint),
+            "[● CHECKPOINT]"
+        );
+        assert_eq!(
+            formatter.format_type_badge(IntentType::Exploration),
+            "[◈ EXPLORATION]"
+        );
+    }
+
+    #[test]
+    fn test_ascii_theme_badges_and_resume_banner() {
+        let formatter = OutputFormatter {
+            use_color: false,
+            theme: Theme { ascii: true, ..Theme::default() },
+            terminal_width: Some(80),
+            annotations: HashMap::new(),
+            goal: None,
+            context: ContextDisplayConfig::default(),
+        };
+        let entry = IntentEntry::new("Wire up OAuth")
+            .with_type(IntentType::Milestone)
+            .with_source(IntentSource::Agent);
+
+        assert_eq!(formatter.format_type_badge(IntentType::Milestone), "[* MILESTONE]");
+        assert_eq!(formatter.format_source_badge(&entry), "[AGENT]");
+        let banner = formatter.format_resume_banner(&entry);
+        assert!(banner.starts_with("Resuming: * Wire up OAuth ("), "{}", banner);
+        assert!(banner.is_ascii());
+    }
+
+    #[test]
+    fn test_format_source_badge_with_detail() {
+        let formatter = OutputFormatter {
+            use_color: false,
+            theme: Theme::default(),
+            terminal_width: Some(80),
+            annotations: HashMap::new(),
+            goal: None,
+            context: ContextDisplayConfig::default(),
+        };
+
+        let agent = IntentEntry::new("x").with_source(IntentSource::Agent);
+        assert_eq!(formatter.format_source_badge(&agent), "[🤖 AGENT]");
+        let agent = agent.with_source_detail("claude-code");
+        assert_eq!(formatter.format_source_badge(&agent), "[🤖 AGENT: claude-code]");
+
+        let manual = IntentEntry::new("x");
+        assert_eq!(formatter.format_source_badge(&manual), "");
+        assert_eq!(formatter.format_source_badge(&manual.with_source_detail("vim")), "[vim]");
+    }
+
+    #[test]
+    fn test_wrap_text() {
+        let formatter = OutputFormatter {
+            use_color: false,
+            theme: Theme::default(),
+            terminal_width: Some(40),
+            annotations: HashMap::new(),
+            goal: None,
+            context: ContextDisplayConfig::default(),
+        };
+
+        let short = "Short text";
+        assert_eq!(formatter.wrap_text(short, 2), "  Short text");
+
+        let long = "This is a longer text that should wrap across multiple lines";
+        let wrapped = formatter.wrap_text(long, 2);
+        for line in wrapped.lines() {
+            assert!(line.len() <= 40);
+        }
+    }
+
+    #[test]
+    fn test_format_entry_with_artifacts() {
+        let formatter = OutputFormatter {
+            use_color: false,
+            theme: Theme::default(),
+            terminal_width: Some(80),
+            annotations: HashMap::new(),
+            goal: None,
+            context: ContextDisplayConfig::default(),
+        };
+
+        let entry = IntentEntry::new("Implemented feature X")
+            .with_type(IntentType::Milestone)
+            .with_artifacts(vec!["src/feature.rs".to_string()]);
+
+        let formatted = formatter.format_entry(&entry);
+        assert!(formatted.contains("[★ MILESTONE]"));
+        assert!(formatted.contains("Implemented feature X"));
+        assert!(formatted.contains("src/feature.rs"));
+    }
+
+    #[test]
+    fn test_tags_render_in_every_format() {
+        let formatter = OutputFormatter {
+            use_color: false,
+            theme: Theme::default(),
+            terminal_width: Some(80),
+            annotations: HashMap::new(),
+            goal: None,
+            context: ContextDisplayConfig::default(),
+        };
+        let entry = IntentEntry::new("Fixed token refresh").with_tags(vec!["bugfix".to_string(), "auth".to_string()]);
+        let entries = [entry];
+
+        assert!(formatter.format_history(&entries, "api").contains("\n  #bugfix #auth"));
+        assert!(formatter.format_markdown(&entries, "api").contains("Fixed token refresh #bugfix #auth"));
+        assert!(formatter.format_context(&entries, "api").contains("Fixed token refresh #bugfix #auth"));
+    }
+
+    #[test]
+    fn test_context_shows_active_goal_progress() {
+        let goal = crate::types::Goal::new("Ship OAuth");
+        let entry = IntentEntry::new("Wired token refresh").with_goal(goal.id).with_goal_delta("Refresh tokens rotate");
+        let formatter = OutputFormatter {
+            use_color: false,
+            theme: Theme::default(),
+            terminal_width: Some(80),
+            annotations: HashMap::new(),
+            goal: Some(GoalProgress { goal, entries: vec![entry.clone()] }),
+            context: ContextDisplayConfig::default(),
+        };
+
+        let context = formatter.format_context(&[entry], "api");
+        assert!(context.contains("### Active Goal\n**Ship OAuth**"));
+        assert!(context.contains("Refresh tokens rotate"));
+        assert!(!formatter.with_goal(None).format_context(&[], "api").contains("Active Goal"));
+    }
+
+    #[test]
+    fn test_context_sections_and_budget() {
+        let mut entries: Vec<IntentEntry> = (0..8).map(|i| IntentEntry::new(format!("Step {}", i))).collect();
+        entries[7].entry_type = IntentType::Milestone;
+
+        let two_recent = ContextDisplayConfig { recent_entries: 2, milestones: false, ..Default::default() };
+        let context = OutputFormatter::new().with_context_display(two_recent).format_context(&entries, "api");
+        assert!(context.contains("Step 1") && !context.contains("Step 2"));
+        assert!(!context.contains("### Key Milestones"));
+        assert!(context.contains("### Suggested Next Steps"));
+
+        // Just over budget: only the next steps go
+        let full = OutputFormatter::new().format_context(&entries, "api");
+        let budget = ContextDisplayConfig { budget_tokens: crate::llm::estimate_tokens(&full) as usize - 1, ..Default::default() };
+        let trimmed = OutputFormatter::new().with_context_display(budget).format_context(&entries, "api");
+        assert!(!trimmed.contains("### Suggested Next Steps"));
+        assert!(trimmed.contains("### Key Milestones") && trimmed.contains("Step 4"));
+
+        // Far over budget: one recent entry and no milestones
+        let tiny = ContextDisplayConfig { budget_tokens: 1, ..Default::default() };
+        let minimal = OutputFormatter::new().with_context_display(tiny).format_context(&entries, "api");
+        assert!(minimal.contains("Step 0") && !minimal.contains("Step 1"));
+        assert!(!minimal.contains("### Key Milestones"));
+    }
+
+    #[test]
+    fn test_annotations_render_beneath_entry() {
+        let entry = IntentEntry::new("Added retry loop");
+        let other = IntentEntry::new("Unrelated work");
+        let formatter = OutputFormatter {
+            use_color: false,
+            theme: Theme::default(),
+            terminal_width: Some(80),
+            annotations: HashMap::new(),
+            goal: None,
+            context: ContextDisplayConfig::default(),
+        }
+        .with_annotations(vec![
+            Annotation::new(entry.id, "review: missing error handling in retry loop").with_author("sam"),
+        ]);
+
+        let formatted = formatter.format_entry(&entry);
+        assert!(formatted.contains(&entry.short_id()));
+        assert!(formatted.contains("  💬 review: missing error handling in retry loop — sam"));
+        assert!(!formatter.format_entry(&other).contains("💬"));
+
+        let markdown = formatter.format_markdown(&[entry], "api");
+        assert!(markdown.contains("  > 💬 review: missing error handling in retry loop — sam"));
+    }
+
+    #[test]
+    fn test_format_llm_usage_totals_known_costs() {
+        let formatter = OutputFormatter {
+            use_color: false,
+            theme: Theme::default(),
+            terminal_width: Some(80),
+            annotations: HashMap::new(),
+            goal: None,
+            context: ContextDisplayConfig::default(),
+        };
+        let usage = vec![
+            LlmUsage { provider: "anthropic".into(), model: "claude-sonnet-4".into(), requests: 2, input_tokens: 1000, output_tokens: 100, cost_usd: Some(0.0045), ..Default::default() },
+            LlmUsage { provider: "openai".into(), model: "local-model".into(), requests: 1, input_tokens: 500, output_tokens: 50, cost_usd: None, ..Default::default() },
+        ];
+
+        let formatted = formatter.format_llm_usage(&usage, "2025-01");
+        assert!(formatted.contains("LLM usage (2025-01)"));
+        assert!(formatted.lines().any(|l| l.starts_with("total") && l.contains("1500") && l.ends_with("$0.0045")));
+        assert!(formatted.contains("no known price"));
+        assert_eq!(formatter.format_llm_usage(&[], "all time"), "No LLM usage recorded (all time).");
+    }
+
+    #[test]
+    fn test_format_event() {
+        let formatter = OutputFormatter {
+            use_color: false,
+            theme: Theme::default(),
+            terminal_width: Some(80),
+            annotations: HashMap::new(),
+            goal: None,
+            context: ContextDisplayConfig::default(),
+        };
+        let body = br#"{"event_type": "perth.intent.logged",
+                        "payload": {"pane_name": "fix-auth", "artifacts": ["src/auth.rs"]},
+                        "metadata": {"correlation_id": "pr-42", "session": "dev"}}"#;
+        let event = IncomingEvent::parse("perth.intent.logged", body).unwrap();
+        let received = Local::now();
+
+        let lines: Vec<String> = formatter.format_event(&event, received).lines().map(String::from).collect();
+        assert_eq!(lines[0], format!("{} perth.intent.logged [pr-42] @dev", received.format("%H:%M:%S")));
+        assert!(lines.contains(&"  pane_name: fix-auth".to_string()));
+        assert!(lines.contains(&r#"  artifacts: ["src/auth.rs"]"#.to_string()));
+    }
+
+    #[test]
+    fn test_format_redactions() {
+        let formatter = OutputFormatter {
+            use_color: false,
+            theme: Theme::default(),
+            terminal_width: Some(80),
+            annotations: HashMap::new(),
+            goal: None,
+            context: ContextDisplayConfig::default(),
+        };
+        assert!(formatter.format_redactions(&[]).starts_with("No redactions"));
+
+        let report = formatter.format_redactions(&[Redaction {
+            source: "git diff".to_string(),
+            pattern: "api_key".to_string(),
+            preview: "api_*** (18 chars)".to_string(),
+        }]);
+        assert!(report.starts_with("1 redaction(s):"));
+        assert!(report.ends_with(&format!("{:<20} {:<20} api_*** (18 chars)", "git diff", "api_key")));
+    }
+
+    #[test]
+    fn test_format_digest_markdown() {
+        let formatter = OutputFormatter {
+            use_color: false,
+            theme: Theme::default(),
+            terminal_width: Some(80),
+            annotations: HashMap::new(),
+            goal: None,
+            context: ContextDisplayConfig::default(),
+        };
+        let now = Utc::now();
+        let mut shipped = IntentEntry::new("Shipped refunds");
+        shipped.entry_type = IntentType::Milestone;
+        let entries = vec![crate::ask::PaneEntry { pane_name: "api(payments)".into(), entry: shipped }];
+        let mut digest = Digest::build(entries, now - chrono::Duration::days(7), now);
+        digest.summary = Some("- Refunds are live\n".into());
+
+        let markdown = formatter.format_digest_markdown(&digest);
+        assert!(markdown.contains("*Entries: 1 · Milestones: 1*"));
+        assert!(markdown.contains("## Summary\n\n- Refunds are live\n\n## "));
+        assert!(markdown.contains("### api(payments)\n\n- 🌟 **"));
+        assert!(markdown.ends_with("Shipped refunds"));
+    }
+
+    #[test]
+    fn test_pane_file_names_are_unique() {
+        let names = pane_file_names(["api(main)", "api/main", "..", "web"], "html");
+        assert_eq!(names, vec!["api-main.html", "api-main-2.html", "pane.html", "web.html"]);
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024), "5.0 MiB");
+    }
+}
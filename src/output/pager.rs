@@ -0,0 +1,82 @@
+//! Pager for long text output, the way git does it.
+//!
+//! Output goes through a pager only when stdout is a terminal and the text
+//! is taller than it. The pager is `display.pager`, then `$PAGER`, then
+//! `less`; setting `display.pager` to "" or "cat" turns paging off.
+
+use anyhow::{anyhow, Result};
+use std::io::{ErrorKind, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+/// Pager used when neither `display.pager` nor `$PAGER` is set.
+pub const DEFAULT_PAGER: &str = "less";
+
+/// The pager command to run, or `None` if paging is turned off.
+pub fn command(configured: Option<&str>) -> Option<String> {
+    resolve(configured, std::env::var("PAGER").ok().as_deref())
+}
+
+fn resolve(configured: Option<&str>, env_pager: Option<&str>) -> Option<String> {
+    let pager = configured.or(env_pager).unwrap_or(DEFAULT_PAGER).trim();
+    if pager.is_empty() || pager == "cat" {
+        None
+    } else {
+        Some(pager.to_string())
+    }
+}
+
+/// Whether `text` fits on a screen of `rows` lines, leaving one for the
+/// shell prompt.
+fn fits(text: &str, rows: usize) -> bool {
+    text.lines().count() < rows
+}
+
+/// Print `text` followed by a newline, through `pager` when one is given,
+/// stdout is a terminal, and the text doesn't fit on the screen.
+pub fn print(text: &str, pager: Option<&str>) -> Result<()> {
+    let rows = terminal_size::terminal_size().map(|(_, h)| h.0 as usize);
+    let pager = match (pager, rows) {
+        (Some(pager), Some(rows)) if std::io::stdout().is_terminal() && !fits(text, rows) => pager,
+        _ => {
+            println!("{}", text);
+            return Ok(());
+        }
+    };
+
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(pager).stdin(Stdio::piped());
+    // Like git: quit if it fits after all, keep colors, don't clear the screen
+    if std::env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+    let mut child = command.spawn().map_err(|e| anyhow!("failed to start pager '{}': {}", pager, e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager closing early (e.g. 'q' in less) is not an error
+        match writeln!(stdin, "{}", text) {
+            Err(e) if e.kind() != ErrorKind::BrokenPipe => return Err(anyhow!("failed to write to pager: {}", e)),
+            _ => {}
+        }
+    }
+    child.wait().map_err(|e| anyhow!("failed to wait for pager '{}': {}", pager, e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_pager() {
+        assert_eq!(resolve(None, None).as_deref(), Some("less"));
+        assert_eq!(resolve(None, Some("most")).as_deref(), Some("most"));
+        assert_eq!(resolve(Some("less -S"), Some("most")).as_deref(), Some("less -S"));
+        assert_eq!(resolve(Some(""), Some("most")), None);
+        assert_eq!(resolve(None, Some("cat")), None);
+    }
+
+    #[test]
+    fn test_fits_leaves_room_for_prompt() {
+        assert!(fits("a\nb", 3));
+        assert!(!fits("a\nb\nc", 3));
+    }
+}
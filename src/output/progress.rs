@@ -0,0 +1,74 @@
+//! Progress indicator for long-running LLM calls.
+//!
+//! The spinner draws on stderr so it never mixes with command output, and
+//! only when stderr is a terminal.
+
+use super::messages;
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+const UNICODE_FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const ASCII_FRAMES: &[&str] = &["|", "/", "-", "\\"];
+
+/// An animated "working" line showing elapsed time and how much of a
+/// streamed response has arrived.
+pub struct Spinner {
+    received: Arc<AtomicUsize>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl Spinner {
+    /// Start drawing `label` with a spinner, if stderr is a terminal.
+    pub fn start(label: impl Into<String>) -> Self {
+        let received = Arc::new(AtomicUsize::new(0));
+        if !std::io::stderr().is_terminal() {
+            return Self { received, task: None };
+        }
+
+        let label = label.into();
+        let counter = Arc::clone(&received);
+        let frames = if messages::unicode() { UNICODE_FRAMES } else { ASCII_FRAMES };
+        let task = tokio::spawn(async move {
+            let started = Instant::now();
+            let mut ticker = tokio::time::interval(Duration::from_millis(100));
+            for frame in frames.iter().cycle() {
+                ticker.tick().await;
+                let mut line = format!("{} {}… {}s", frame, label, started.elapsed().as_secs());
+                let bytes = counter.load(Ordering::Relaxed);
+                if bytes > 0 {
+                    line.push_str(&format!(" ({} received)", super::format_bytes(bytes as u64)));
+                }
+                eprint!("\r\x1b[2K{}", line);
+                let _ = std::io::stderr().flush();
+            }
+        });
+        Self { received, task: Some(task) }
+    }
+
+    /// Counter to add streamed bytes to as they arrive.
+    pub fn counter(&self) -> Arc<AtomicUsize> {
+        Arc::clone(&self.received)
+    }
+
+    /// Stop the spinner and clear its line.
+    pub fn finish(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+            eprint!("\r\x1b[2K");
+            let _ = std::io::stderr().flush();
+        }
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
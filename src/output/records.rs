@@ -0,0 +1,88 @@
+//! Line-oriented history records for `--format csv` and `--format jsonl`,
+//! for spreadsheets and log pipelines.
+//!
+//! Both carry the pane name on every record so output from several panes
+//! can be concatenated. CSV flattens artifacts and tags into single
+//! columns separated by [`LIST_SEPARATOR`].
+
+use crate::types::IntentEntry;
+use anyhow::Result;
+use serde::Serialize;
+
+/// Separator for list values (artifacts, tags) within one CSV column.
+pub const LIST_SEPARATOR: &str = ";";
+
+/// Header row matching [`csv_row`].
+pub const CSV_HEADER: &str =
+    "pane,id,timestamp,type,source,source_detail,summary,artifacts,tags,goal_delta,commit,correlation_id";
+
+/// One entry as a JSON object with the pane name alongside its fields.
+#[derive(Serialize)]
+struct PaneRecord<'a> {
+    pane: &'a str,
+    #[serde(flatten)]
+    entry: &'a IntentEntry,
+}
+
+/// One entry as a single line of JSON.
+pub fn jsonl_line(pane_name: &str, entry: &IntentEntry) -> Result<String> {
+    Ok(serde_json::to_string(&PaneRecord { pane: pane_name, entry })?)
+}
+
+/// One entry as a CSV row, without the trailing newline.
+pub fn csv_row(pane_name: &str, entry: &IntentEntry) -> String {
+    let fields = [
+        pane_name.to_string(),
+        entry.id.to_string(),
+        entry.timestamp.to_rfc3339(),
+        entry.entry_type_str().to_lowercase(),
+        entry.source_str().to_string(),
+        entry.source_detail.clone().unwrap_or_default(),
+        entry.summary.clone(),
+        entry.artifacts.join(LIST_SEPARATOR),
+        entry.tags.join(LIST_SEPARATOR),
+        entry.goal_delta.clone().unwrap_or_default(),
+        entry.commit.clone().unwrap_or_default(),
+        entry.correlation_id.clone().unwrap_or_default(),
+    ];
+    fields.iter().map(|field| csv_field(field)).collect::<Vec<_>>().join(",")
+}
+
+/// Quote a CSV field if it holds a comma, quote or line break.
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_row_flattens_lists_and_quotes() {
+        let mut entry = IntentEntry::new("Fixed \"login\", finally")
+            .with_tags(vec!["auth".to_string(), "bugfix".to_string()]);
+        entry.artifacts = vec!["src/auth.rs".to_string(), "src/session.rs".to_string()];
+
+        let row = csv_row("api(main)", &entry);
+        let expected_tail = r#",checkpoint,manual,,"Fixed ""login"", finally",src/auth.rs;src/session.rs,auth;bugfix,,,"#;
+        assert!(row.starts_with(&format!("api(main),{},", entry.id)));
+        assert!(row.ends_with(expected_tail), "{}", row);
+        assert_eq!(row.matches(',').count() - 1, CSV_HEADER.matches(',').count());
+    }
+
+    #[test]
+    fn test_jsonl_line_includes_pane() {
+        let entry = IntentEntry::new("Refund endpoint");
+        let line = jsonl_line("api", &entry).unwrap();
+        assert!(!line.contains('\n'));
+
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["pane"], "api");
+        assert_eq!(value["summary"], "Refund endpoint");
+        assert_eq!(value["id"], entry.id.to_string());
+    }
+}
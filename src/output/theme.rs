@@ -0,0 +1,165 @@
+//! Output theme: badge colors and whether icons are Unicode or ASCII.
+//!
+//! The theme is built once at startup from `[display.theme]` and the global
+//! `--no-color` and `--ascii` flags, then read by [`super::OutputFormatter`]
+//! and [`super::messages`] so every command styles output the same way.
+
+use crate::config::ThemeConfig;
+use crate::types::{IntentSource, IntentType};
+use anyhow::{anyhow, Result};
+use colored::Color;
+use std::sync::OnceLock;
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    /// Never color output, even on a terminal
+    pub no_color: bool,
+    /// Use ASCII in place of Unicode icons and emoji
+    pub ascii: bool,
+    pub milestone: Color,
+    pub checkpoint: Color,
+    pub exploration: Color,
+    pub automated: Color,
+    pub agent: Color,
+    pub imported: Color,
+    /// Headings and highlighted names
+    pub accent: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            no_color: false,
+            ascii: false,
+            milestone: Color::Yellow,
+            checkpoint: Color::Green,
+            exploration: Color::Cyan,
+            automated: Color::Blue,
+            agent: Color::Magenta,
+            imported: Color::White,
+            accent: Color::Cyan,
+        }
+    }
+}
+
+impl Theme {
+    /// Theme from config, with the command-line flags taking precedence.
+    pub fn from_config(config: &ThemeConfig, no_color: bool, ascii: bool) -> Result<Self> {
+        Ok(Self {
+            no_color: no_color || !config.color,
+            ascii: ascii || config.ascii,
+            milestone: parse_color(&config.milestone)?,
+            checkpoint: parse_color(&config.checkpoint)?,
+            exploration: parse_color(&config.exploration)?,
+            automated: parse_color(&config.automated)?,
+            agent: parse_color(&config.agent)?,
+            imported: parse_color(&config.imported)?,
+            accent: parse_color(&config.accent)?,
+        })
+    }
+
+    /// Whether output to a stream should be colored: the theme allows it,
+    /// `NO_COLOR` is unset, and the stream is a terminal.
+    pub fn color_enabled(&self, is_terminal: bool) -> bool {
+        !self.no_color && std::env::var_os("NO_COLOR").is_none() && is_terminal
+    }
+
+    pub fn type_color(&self, entry_type: IntentType) -> Color {
+        match entry_type {
+            IntentType::Milestone => self.milestone,
+            IntentType::Checkpoint => self.checkpoint,
+            IntentType::Exploration => self.exploration,
+        }
+    }
+
+    /// Badge color for a source; manual entries are dimmed instead.
+    pub fn source_color(&self, source: IntentSource) -> Option<Color> {
+        match source {
+            IntentSource::Manual => None,
+            IntentSource::Automated => Some(self.automated),
+            IntentSource::Agent => Some(self.agent),
+            IntentSource::Imported => Some(self.imported),
+        }
+    }
+
+    pub fn type_icon(&self, entry_type: IntentType) -> &'static str {
+        match (entry_type, self.ascii) {
+            (IntentType::Milestone, false) => "★",
+            (IntentType::Checkpoint, false) => "●",
+            (IntentType::Exploration, false) => "◈",
+            (IntentType::Milestone, true) => "*",
+            (IntentType::Checkpoint, true) => "o",
+            (IntentType::Exploration, true) => "?",
+        }
+    }
+
+    /// Emoji shown before a source label; none in ASCII mode or for manual
+    /// entries.
+    pub fn source_icon(&self, source: IntentSource) -> Option<&'static str> {
+        if self.ascii {
+            return None;
+        }
+        match source {
+            IntentSource::Manual => None,
+            IntentSource::Automated => Some("⚡"),
+            IntentSource::Agent => Some("🤖"),
+            IntentSource::Imported => Some("📥"),
+        }
+    }
+
+    /// Arrow in front of artifacts and suggestions.
+    pub fn arrow(&self) -> &'static str {
+        if self.ascii { "->" } else { "→" }
+    }
+}
+
+/// Install the theme for this process. Only the first call takes effect.
+pub fn init(theme: Theme) {
+    if theme.no_color {
+        // Also covers styling done directly through `colored`
+        colored::control::set_override(false);
+    }
+    let _ = THEME.set(theme);
+}
+
+/// The installed theme, or the default if none was installed.
+pub fn current() -> &'static Theme {
+    THEME.get_or_init(Theme::default)
+}
+
+/// A color by name: black, red, green, yellow, blue, magenta, cyan, white,
+/// optionally prefixed with "bright ".
+pub fn parse_color(name: &str) -> Result<Color> {
+    name.trim()
+        .replace('_', " ")
+        .parse()
+        .map_err(|_| anyhow!("unknown color '{}' (use black, red, green, yellow, blue, magenta, cyan or white, optionally 'bright ...')", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_config_flags_override() {
+        let config = ThemeConfig { milestone: "bright_red".to_string(), ..ThemeConfig::default() };
+        let theme = Theme::from_config(&config, true, false).unwrap();
+        assert_eq!(theme.milestone, Color::BrightRed);
+        assert!(theme.no_color && !theme.ascii);
+        assert!(!theme.color_enabled(true));
+
+        let ascii = Theme::from_config(&ThemeConfig { ascii: true, ..ThemeConfig::default() }, false, false).unwrap();
+        assert_eq!(ascii.type_icon(IntentType::Milestone), "*");
+        assert_eq!(ascii.source_icon(IntentSource::Agent), None);
+        assert_eq!(ascii.arrow(), "->");
+    }
+
+    #[test]
+    fn test_unknown_color_is_rejected() {
+        let config = ThemeConfig { agent: "mauve".to_string(), ..ThemeConfig::default() };
+        let err = Theme::from_config(&config, false, false).unwrap_err();
+        assert!(err.to_string().contains("unknown color 'mauve'"));
+    }
+}
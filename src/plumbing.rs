@@ -0,0 +1,125 @@
+//! Stable, JSON-only building blocks for scripts and agents (`zdrive plumbing`).
+//!
+//! Porcelain commands are free to change their output; plumbing output is a
+//! compatibility contract. Every response is a single JSON object carrying
+//! `plumbing_version`, and failures are reported as `{"error": ...}` with a
+//! non-zero exit status. Fields may be added within a version but are never
+//! renamed or removed.
+
+use crate::types::{IntentEntry, PaneRecord};
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Map, Value};
+use std::io::Read;
+
+pub const PLUMBING_VERSION: u32 = 1;
+
+/// Wrap a response body in the versioned envelope.
+pub fn envelope(body: Value) -> Value {
+    let mut object = Map::new();
+    object.insert("plumbing_version".to_string(), json!(PLUMBING_VERSION));
+    if let Value::Object(fields) = body {
+        object.extend(fields);
+    }
+    Value::Object(object)
+}
+
+/// Error response in the same envelope, with the error's kind (e.g.
+/// "not_found") when it has one.
+pub fn error(err: &anyhow::Error) -> Value {
+    let mut body = json!({ "error": format!("{:#}", err) });
+    if let Some(kind) = crate::error::kind(err) {
+        body["kind"] = json!(kind);
+    }
+    envelope(body)
+}
+
+/// Stable JSON representation of a pane record.
+pub fn pane_json(record: &PaneRecord) -> Value {
+    json!({
+        "name": record.pane_name,
+        "session": record.session,
+        "tab": record.tab,
+        "pane_id": record.pane_id,
+        "created_at": record.created_at,
+        "last_seen": record.last_seen,
+        "last_accessed": record.last_accessed,
+        "stale": record.stale,
+        "meta": record.meta,
+    })
+}
+
+/// Parse JSON from an argument, or from stdin when the argument is absent or `-`.
+pub fn read_json_input(arg: Option<&str>) -> Result<Value> {
+    let raw = match arg {
+        Some(text) if text != "-" => text.to_string(),
+        _ => {
+            let mut buffer = String::new();
+            std::io::stdin()
+                .read_to_string(&mut buffer)
+                .context("failed to read JSON from stdin")?;
+            buffer
+        }
+    };
+    serde_json::from_str(&raw).context("input is not valid JSON")
+}
+
+/// Build an entry from JSON, generating `id` and `timestamp` when omitted.
+pub fn entry_from_json(mut value: Value) -> Result<IntentEntry> {
+    let object = value
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("entry must be a JSON object"))?;
+    let defaults = IntentEntry::new("");
+    object.entry("id").or_insert_with(|| json!(defaults.id));
+    object.entry("timestamp").or_insert_with(|| json!(defaults.timestamp));
+
+    serde_json::from_value(value).context("invalid entry")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_envelope_adds_version() {
+        let value = envelope(json!({ "keys": [] }));
+        assert_eq!(value["plumbing_version"], PLUMBING_VERSION);
+        assert!(value["keys"].is_array());
+    }
+
+    #[test]
+    fn test_error_envelope() {
+        let value = error(&anyhow::anyhow!("pane not found"));
+        assert_eq!(value["error"], "pane not found");
+        assert_eq!(value["plumbing_version"], PLUMBING_VERSION);
+        assert!(value.get("kind").is_none());
+
+        let value = error(&crate::error::PerthError::NotFound("pane 'api' not found".to_string()).into());
+        assert_eq!(value["kind"], "not_found");
+    }
+
+    #[test]
+    fn test_entry_from_json_fills_defaults() {
+        let entry = entry_from_json(json!({ "summary": "Ran migrations", "entry_type": "milestone" })).unwrap();
+        assert_eq!(entry.summary, "Ran migrations");
+        assert_eq!(entry.entry_type, crate::types::IntentType::Milestone);
+
+        assert!(entry_from_json(json!({ "entry_type": "milestone" })).is_err());
+        assert!(entry_from_json(json!("just text")).is_err());
+    }
+
+    #[test]
+    fn test_pane_json_fields() {
+        let record = PaneRecord::new(
+            "api".to_string(),
+            "work".to_string(),
+            "backend".to_string(),
+            "2025-01-01T00:00:00Z".to_string(),
+            HashMap::from([("goal".to_string(), "ship".to_string())]),
+        );
+        let value = pane_json(&record);
+        assert_eq!(value["name"], "api");
+        assert_eq!(value["meta"]["goal"], "ship");
+        assert_eq!(value["stale"], false);
+    }
+}
@@ -0,0 +1,219 @@
+//! Interactive one-line prompt for `zdrive quicklog`.
+//!
+//! - Press `m`, `c` or `e` on an empty line to switch between milestone,
+//!   checkpoint and exploration (the prompt label updates in place)
+//! - `#` completes tags already used in the pane's history
+//! - `@` completes file paths; `@path` tokens are logged as artifacts
+
+use crate::types::{IntentEntry, IntentType};
+use anyhow::Result;
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{
+    Cmd, ConditionalEventHandler, Context, Editor, Event, EventContext, EventHandler, Helper,
+    KeyEvent, RepeatCount,
+};
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+
+/// What the user entered at the quicklog prompt.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuicklogInput {
+    pub summary: String,
+    pub entry_type: IntentType,
+    pub artifacts: Vec<String>,
+}
+
+/// Collect `#tags` used in existing entry summaries and tag lists, sorted
+/// and deduplicated.
+pub fn collect_tags(entries: &[IntentEntry]) -> Vec<String> {
+    let mut tags: BTreeSet<String> = entries
+        .iter()
+        .flat_map(|entry| entry.summary.split_whitespace())
+        .filter_map(|word| {
+            let tag = word.trim_end_matches(|c: char| !c.is_alphanumeric() && c != '-' && c != '_');
+            (tag.len() > 1 && tag.starts_with('#')).then(|| tag.to_string())
+        })
+        .collect();
+    tags.extend(entries.iter().flat_map(|entry| &entry.tags).map(|tag| format!("#{}", tag)));
+    tags.into_iter().collect()
+}
+
+/// Split a prompt line into its summary and `@artifact` tokens.
+pub fn parse_line(line: &str) -> (String, Vec<String>) {
+    let mut words = Vec::new();
+    let mut artifacts = Vec::new();
+
+    for word in line.split_whitespace() {
+        match word.strip_prefix('@') {
+            Some(path) if !path.is_empty() => artifacts.push(path.to_string()),
+            _ => words.push(word),
+        }
+    }
+
+    (words.join(" "), artifacts)
+}
+
+/// Fixed-width label so the prompt width never changes when toggling type.
+fn type_label(entry_type: IntentType) -> &'static str {
+    match entry_type {
+        IntentType::Milestone => "milestone  ",
+        IntentType::Checkpoint => "checkpoint ",
+        IntentType::Exploration => "exploration",
+    }
+}
+
+struct QuicklogHelper {
+    entry_type: Arc<Mutex<IntentType>>,
+    tags: Vec<String>,
+    files: FilenameCompleter,
+}
+
+impl Completer for QuicklogHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let start = line[..pos].rfind(char::is_whitespace).map(|i| i + 1).unwrap_or(0);
+        let word = &line[start..pos];
+
+        if word.starts_with('#') {
+            let candidates = self
+                .tags
+                .iter()
+                .filter(|tag| tag.starts_with(word))
+                .map(|tag| Pair {
+                    display: tag.clone(),
+                    replacement: tag.clone(),
+                })
+                .collect();
+            return Ok((start, candidates));
+        }
+
+        if word.starts_with('@') {
+            // '@' is a filename break character, so completion starts after it
+            return self.files.complete(line, pos, ctx);
+        }
+
+        Ok((pos, Vec::new()))
+    }
+}
+
+impl Hinter for QuicklogHelper {
+    type Hint = String;
+}
+
+impl Highlighter for QuicklogHelper {
+    fn highlight_prompt<'b, 's: 'b, 'p: 'b>(&'s self, prompt: &'p str, _default: bool) -> Cow<'b, str> {
+        let entry_type = *self.entry_type.lock().expect("entry type lock poisoned");
+        // The prompt is always built with the checkpoint label; swap in the current one
+        Cow::Owned(prompt.replacen(type_label(IntentType::Checkpoint), type_label(entry_type), 1))
+    }
+}
+
+impl Validator for QuicklogHelper {}
+
+impl Helper for QuicklogHelper {}
+
+/// Switches entry type when m/c/e is pressed on an empty line; types the key otherwise.
+struct TypeToggle {
+    entry_type: Arc<Mutex<IntentType>>,
+    target: IntentType,
+}
+
+impl ConditionalEventHandler for TypeToggle {
+    fn handle(&self, _evt: &Event, _n: RepeatCount, _positive: bool, ctx: &EventContext) -> Option<Cmd> {
+        if !ctx.line().is_empty() {
+            return None;
+        }
+        *self.entry_type.lock().expect("entry type lock poisoned") = self.target;
+        Some(Cmd::Repaint)
+    }
+}
+
+/// Run the prompt for `pane_name`. Returns `None` if the user cancelled or entered nothing.
+pub fn prompt(pane_name: &str, tags: Vec<String>) -> Result<Option<QuicklogInput>> {
+    let entry_type = Arc::new(Mutex::new(IntentType::Checkpoint));
+
+    let mut editor: Editor<QuicklogHelper, DefaultHistory> = Editor::new()?;
+    editor.set_helper(Some(QuicklogHelper {
+        entry_type: Arc::clone(&entry_type),
+        tags,
+        files: FilenameCompleter::new(),
+    }));
+
+    for (key, target) in [
+        ('m', IntentType::Milestone),
+        ('c', IntentType::Checkpoint),
+        ('e', IntentType::Exploration),
+    ] {
+        editor.bind_sequence(
+            KeyEvent::from(key),
+            EventHandler::Conditional(Box::new(TypeToggle {
+                entry_type: Arc::clone(&entry_type),
+                target,
+            })),
+        );
+    }
+
+    let prompt = format!("[{}] {}> ", type_label(IntentType::Checkpoint), pane_name);
+    let line = match editor.readline(&prompt) {
+        Ok(line) => line,
+        Err(ReadlineError::Interrupted | ReadlineError::Eof) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let (summary, artifacts) = parse_line(&line);
+    if summary.is_empty() {
+        return Ok(None);
+    }
+
+    let entry_type = *entry_type.lock().expect("entry type lock poisoned");
+    Ok(Some(QuicklogInput {
+        summary,
+        entry_type,
+        artifacts,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line_extracts_artifacts() {
+        let (summary, artifacts) = parse_line("Fixed #auth timeout @src/auth.rs @Cargo.toml");
+        assert_eq!(summary, "Fixed #auth timeout");
+        assert_eq!(artifacts, vec!["src/auth.rs", "Cargo.toml"]);
+    }
+
+    #[test]
+    fn test_parse_line_keeps_bare_at_sign() {
+        let (summary, artifacts) = parse_line("Paired with @ the team");
+        assert_eq!(summary, "Paired with @ the team");
+        assert!(artifacts.is_empty());
+    }
+
+    #[test]
+    fn test_collect_tags_dedupes_and_strips_punctuation() {
+        let entries = vec![
+            IntentEntry::new("Fixed #auth bug, see #perf."),
+            IntentEntry::new("More #auth work"),
+            IntentEntry::new("Plain # sign"),
+        ];
+        assert_eq!(collect_tags(&entries), vec!["#auth", "#perf"]);
+    }
+
+    #[test]
+    fn test_type_labels_have_equal_width() {
+        let widths: Vec<usize> = [IntentType::Milestone, IntentType::Checkpoint, IntentType::Exploration]
+            .iter()
+            .map(|t| type_label(*t).len())
+            .collect();
+        assert!(widths.windows(2).all(|w| w[0] == w[1]));
+    }
+}
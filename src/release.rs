@@ -0,0 +1,141 @@
+//! Release detection for automatic milestones.
+//!
+//! A project's version is read from its `Cargo.toml` or `package.json`,
+//! falling back to the latest git tag. When it moves forward between checks
+//! of a pane, a "released vX.Y.Z" milestone is logged with the changelog
+//! diff since the previous release saved as an artifact.
+
+use crate::activity::git_output;
+use crate::types::{IntentEntry, IntentSource, IntentType};
+use semver::Version;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Root of the project containing `cwd`: the git top level, else `cwd`.
+pub fn project_root(cwd: &Path) -> PathBuf {
+    git_output(cwd, &["rev-parse", "--show-toplevel"])
+        .map(PathBuf::from)
+        .unwrap_or_else(|| cwd.to_path_buf())
+}
+
+/// Detect the current version (without a leading `v`) of the project at `root`:
+/// `Cargo.toml`, then `package.json`, then the most recent git tag.
+pub fn detect_version(root: &Path) -> Option<String> {
+    cargo_version(&root.join("Cargo.toml"))
+        .or_else(|| package_json_version(&root.join("package.json")))
+        .or_else(|| {
+            git_output(root, &["describe", "--tags", "--abbrev=0"])
+                .map(|tag| tag.trim_start_matches('v').to_string())
+        })
+}
+
+fn cargo_version(path: &Path) -> Option<String> {
+    let manifest: toml::Value = fs::read_to_string(path).ok()?.parse().ok()?;
+    // Workspace members may inherit the version from [workspace.package]
+    manifest
+        .get("package")
+        .and_then(|p| p.get("version"))
+        .and_then(|v| v.as_str())
+        .or_else(|| {
+            manifest
+                .get("workspace")
+                .and_then(|w| w.get("package"))
+                .and_then(|p| p.get("version"))
+                .and_then(|v| v.as_str())
+        })
+        .map(str::to_string)
+}
+
+fn package_json_version(path: &Path) -> Option<String> {
+    let manifest: serde_json::Value = serde_json::from_str(&fs::read_to_string(path).ok()?).ok()?;
+    manifest.get("version")?.as_str().map(str::to_string)
+}
+
+/// Whether moving from `previous` to `current` is a release.
+///
+/// Versions that parse as semver must increase, so checking out an older
+/// branch is not mistaken for a release; anything else only has to change.
+pub fn is_release(previous: &str, current: &str) -> bool {
+    match (Version::parse(previous), Version::parse(current)) {
+        (Ok(previous), Ok(current)) => current > previous,
+        _ => previous != current,
+    }
+}
+
+/// Build the milestone logged for a release.
+/// Returns `None` on first sight of a version or when it did not move forward.
+pub fn release_entry(previous: Option<&str>, current: &str, changelog_diff: Option<&Path>) -> Option<IntentEntry> {
+    previous.filter(|p| is_release(p, current))?;
+    let entry = IntentEntry::new(format!("released v{}", current))
+        .with_type(IntentType::Milestone)
+        .with_source(IntentSource::Automated);
+    Some(match changelog_diff {
+        Some(path) => entry.with_artifacts(vec![path.display().to_string()]),
+        None => entry,
+    })
+}
+
+/// Save the changelog diff since `previous` and return its path.
+///
+/// Diffs against the previous release's tag (`v1.3.0` or `1.3.0`) when it
+/// exists, else against the last commit. The diff is written under the
+/// repository's git directory so it never shows up as an untracked file.
+pub fn write_changelog_diff(root: &Path, changelog: &str, previous: &str, current: &str) -> Option<PathBuf> {
+    if !root.join(changelog).exists() {
+        return None;
+    }
+
+    let base = [format!("v{}", previous), previous.to_string()]
+        .into_iter()
+        .find(|tag| git_output(root, &["rev-parse", "--verify", "--quiet", &format!("refs/tags/{}", tag)]).is_some())
+        .unwrap_or_else(|| "HEAD~1".to_string());
+    let diff = git_output(root, &["diff", &base, "--", changelog])?;
+
+    let git_dir = git_output(root, &["rev-parse", "--absolute-git-dir"])?;
+    let dir = Path::new(&git_dir).join("perth").join("releases");
+    fs::create_dir_all(&dir).ok()?;
+    let path = dir.join(format!("v{}.diff", current));
+    fs::write(&path, diff + "\n").ok()?;
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_release() {
+        assert!(is_release("1.3.0", "1.4.0"));
+        assert!(!is_release("1.4.0", "1.3.0"));
+        assert!(!is_release("1.4.0", "1.4.0"));
+        assert!(is_release("2024.1", "2024.2"));
+    }
+
+    #[test]
+    fn test_release_entry() {
+        assert!(release_entry(None, "1.4.0", None).is_none());
+        assert!(release_entry(Some("1.4.0"), "1.4.0", None).is_none());
+
+        let diff = Path::new("/repo/.git/perth/releases/v1.4.0.diff");
+        let entry = release_entry(Some("1.3.0"), "1.4.0", Some(diff)).unwrap();
+        assert_eq!(entry.summary, "released v1.4.0");
+        assert_eq!(entry.entry_type, IntentType::Milestone);
+        assert_eq!(entry.source, IntentSource::Automated);
+        assert_eq!(entry.artifacts, vec!["/repo/.git/perth/releases/v1.4.0.diff"]);
+    }
+
+    #[test]
+    fn test_cargo_version_reads_package_and_workspace() {
+        let dir = std::env::temp_dir().join(format!("perth-release-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let manifest = dir.join("Cargo.toml");
+
+        fs::write(&manifest, "[package]\nname = \"app\"\nversion = \"1.4.0\"\n").unwrap();
+        assert_eq!(cargo_version(&manifest).as_deref(), Some("1.4.0"));
+
+        fs::write(&manifest, "[workspace]\nmembers = []\n[workspace.package]\nversion = \"0.9.1\"\n").unwrap();
+        assert_eq!(cargo_version(&manifest).as_deref(), Some("0.9.1"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,209 @@
+//! Time reports for `zdrive report time`: approximate hours per pane, tab
+//! or tag, inferred from when intent entries were logged.
+//!
+//! A pane's entries are split into work sessions wherever two consecutive
+//! entries are further apart than the idle gap. Each session counts from
+//! its first entry to its last, plus a lead-in for the work before the
+//! first entry was logged. The result is an estimate, not a timesheet.
+
+use crate::ask::PaneEntry;
+use crate::output::records::csv_field;
+use chrono::{DateTime, Datelike, Duration, Local, TimeZone, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Time credited before the first entry of each session.
+pub const SESSION_LEAD_IN_MINUTES: i64 = 15;
+
+/// What report rows are grouped by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportGroup {
+    #[default]
+    Pane,
+    Tab,
+    /// Entries with several tags count toward each; untagged entries are
+    /// grouped as "(untagged)"
+    Tag,
+}
+
+impl ReportGroup {
+    fn label(self) -> &'static str {
+        match self {
+            ReportGroup::Pane => "PANE",
+            ReportGroup::Tab => "TAB",
+            ReportGroup::Tag => "TAG",
+        }
+    }
+}
+
+/// Approximate time spent over a period, one row per pane, tab or tag.
+#[derive(Debug, Clone, Serialize)]
+pub struct TimeReport {
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    pub by: ReportGroup,
+    /// Entries further apart than this start a new session
+    pub gap_minutes: i64,
+    /// Most hours first
+    pub rows: Vec<TimeRow>,
+    pub total_hours: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TimeRow {
+    pub key: String,
+    pub hours: f64,
+    pub sessions: usize,
+    pub entries: usize,
+}
+
+impl TimeReport {
+    /// Build a report from entries in the period. `tabs` maps pane names
+    /// to their tab, for grouping by tab.
+    pub fn build(
+        entries: Vec<PaneEntry>,
+        tabs: &HashMap<String, String>,
+        by: ReportGroup,
+        gap: Duration,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Self {
+        // Sessions are found per pane (and per tag), then summed into rows
+        let mut streams: BTreeMap<(String, String), Vec<DateTime<Utc>>> = BTreeMap::new();
+        for PaneEntry { pane_name, entry } in entries {
+            let keys = match by {
+                ReportGroup::Pane => vec![pane_name.clone()],
+                ReportGroup::Tab => vec![tabs.get(&pane_name).cloned().unwrap_or_else(|| "(untracked)".to_string())],
+                ReportGroup::Tag if entry.tags.is_empty() => vec!["(untagged)".to_string()],
+                ReportGroup::Tag => entry.tags.clone(),
+            };
+            for key in keys {
+                streams.entry((key, pane_name.clone())).or_default().push(entry.timestamp);
+            }
+        }
+
+        let mut rows: BTreeMap<String, TimeRow> = BTreeMap::new();
+        for ((key, _), mut times) in streams {
+            times.sort();
+            let row = rows.entry(key.clone()).or_insert(TimeRow { key, hours: 0.0, sessions: 0, entries: 0 });
+            row.entries += times.len();
+            for (start, end) in sessions(&times, gap) {
+                row.sessions += 1;
+                row.hours += (end - start + Duration::minutes(SESSION_LEAD_IN_MINUTES)).num_seconds() as f64 / 3600.0;
+            }
+        }
+
+        let mut rows: Vec<TimeRow> = rows.into_values().collect();
+        rows.sort_by(|a, b| b.hours.total_cmp(&a.hours).then_with(|| a.key.cmp(&b.key)));
+        let total_hours = rows.iter().map(|row| row.hours).sum();
+        Self { since, until, by, gap_minutes: gap.num_minutes(), rows, total_hours }
+    }
+
+    /// Aligned text table with a total row.
+    pub fn to_table(&self) -> String {
+        let mut lines = vec![format!(
+            "Time by {} from {} to {} (sessions split at {}m gaps)",
+            self.by.label().to_lowercase(),
+            self.since.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+            self.until.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+            self.gap_minutes
+        )];
+        lines.push(String::new());
+        if self.rows.is_empty() {
+            lines.push("No entries in this period".to_string());
+            return lines.join("\n");
+        }
+
+        let width = self.rows.iter().map(|row| row.key.chars().count()).max().unwrap_or(0).max(5);
+        lines.push(format!("{:<width$}  {:>7}  {:>8}  {:>7}", self.by.label(), "HOURS", "SESSIONS", "ENTRIES"));
+        for row in &self.rows {
+            lines.push(format!("{:<width$}  {:>7.2}  {:>8}  {:>7}", row.key, row.hours, row.sessions, row.entries));
+        }
+        let sessions: usize = self.rows.iter().map(|row| row.sessions).sum();
+        let entries: usize = self.rows.iter().map(|row| row.entries).sum();
+        lines.push(format!("{:<width$}  {:>7.2}  {:>8}  {:>7}", "TOTAL", self.total_hours, sessions, entries));
+        lines.join("\n")
+    }
+
+    /// CSV with a header row, hours to two decimals.
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("{},hours,sessions,entries\n", self.by.label().to_lowercase());
+        for row in &self.rows {
+            csv.push_str(&format!("{},{:.2},{},{}\n", csv_field(&row.key), row.hours, row.sessions, row.entries));
+        }
+        csv
+    }
+}
+
+/// Start and end of each run of timestamps no more than `gap` apart.
+/// `times` must be sorted.
+fn sessions(times: &[DateTime<Utc>], gap: Duration) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let mut sessions: Vec<(DateTime<Utc>, DateTime<Utc>)> = Vec::new();
+    for &time in times {
+        match sessions.last_mut() {
+            Some((_, end)) if time - *end <= gap => *end = time,
+            _ => sessions.push((time, time)),
+        }
+    }
+    sessions
+}
+
+/// Local midnight at the start of the current week (Monday).
+pub fn week_start() -> DateTime<Utc> {
+    let today = Local::now().date_naive();
+    let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    let midnight = monday.and_hms_opt(0, 0, 0).expect("midnight is valid");
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|local| local.with_timezone(&Utc))
+        .unwrap_or_else(|| midnight.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::IntentEntry;
+
+    fn entry_at(pane: &str, minutes: i64, tags: &[&str]) -> PaneEntry {
+        let mut entry = IntentEntry::new("work").with_tags(tags.iter().map(|t| t.to_string()).collect());
+        entry.timestamp = DateTime::from_timestamp(0, 0).unwrap() + Duration::minutes(minutes);
+        PaneEntry { pane_name: pane.to_string(), entry }
+    }
+
+    fn build(entries: Vec<PaneEntry>, by: ReportGroup) -> TimeReport {
+        let tabs = HashMap::from([("api".to_string(), "myapp".to_string()), ("web".to_string(), "myapp".to_string())]);
+        let now = Utc::now();
+        TimeReport::build(entries, &tabs, by, Duration::minutes(30), now, now)
+    }
+
+    #[test]
+    fn test_sessions_split_at_gaps() {
+        // 0-20m is one session (20m + 15m lead-in), 120m is another (15m)
+        let report = build(vec![entry_at("api", 0, &[]), entry_at("api", 20, &[]), entry_at("api", 120, &[])], ReportGroup::Pane);
+        assert_eq!(report.rows.len(), 1);
+        assert_eq!(report.rows[0].sessions, 2);
+        assert_eq!(report.rows[0].entries, 3);
+        assert!((report.rows[0].hours - 50.0 / 60.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_group_by_tab_and_tag() {
+        let entries = vec![entry_at("api", 0, &["auth"]), entry_at("web", 0, &["auth", "ui"]), entry_at("cli", 0, &[])];
+
+        let by_tab = build(entries.clone(), ReportGroup::Tab);
+        let keys: Vec<(&str, usize)> = by_tab.rows.iter().map(|r| (r.key.as_str(), r.sessions)).collect();
+        assert_eq!(keys, vec![("myapp", 2), ("(untracked)", 1)]);
+
+        let by_tag = build(entries, ReportGroup::Tag);
+        let keys: Vec<&str> = by_tag.rows.iter().map(|r| r.key.as_str()).collect();
+        assert_eq!(keys, vec!["auth", "(untagged)", "ui"]);
+    }
+
+    #[test]
+    fn test_csv_quotes_keys() {
+        let report = build(vec![entry_at("api,v2", 0, &[])], ReportGroup::Pane);
+        assert_eq!(report.to_csv(), "pane,hours,sessions,entries\n\"api,v2\",0.25,1,1\n");
+    }
+}
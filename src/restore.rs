@@ -1,19 +1,32 @@
+use crate::error::PerthError;
+use crate::multiplexer::MultiplexerDriver;
+use crate::orchestrator::COMMAND_META_KEY;
 use crate::types::{RestoreReport, RestoreWarning, SessionSnapshot, TabSnapshot};
 use crate::zellij::ZellijDriver;
 use anyhow::{anyhow, Context, Result};
 use chrono::Utc;
+use std::path::Path;
 
 /// Session restoration module.
 ///
 /// Handles recreating Zellij sessions from snapshots, including tabs, panes,
 /// working directories, and layout configuration.
-pub struct SessionRestore {
-    zellij: ZellijDriver,
+pub struct SessionRestore<M: MultiplexerDriver = ZellijDriver> {
+    zellij: M,
+    /// Session to restore into, `None` for the attached one
+    target: Option<String>,
 }
 
-impl SessionRestore {
-    pub fn new(zellij: ZellijDriver) -> Self {
-        Self { zellij }
+impl<M: MultiplexerDriver> SessionRestore<M> {
+    pub fn new(zellij: M) -> Self {
+        Self { zellij, target: None }
+    }
+
+    /// Restore into `session` instead of the attached session. The session
+    /// must already be running.
+    pub fn into_session(mut self, session: Option<String>) -> Self {
+        self.target = session;
+        self
     }
 
     /// Restore a session from a snapshot.
@@ -29,19 +42,23 @@ impl SessionRestore {
         snapshot: &SessionSnapshot,
         dry_run: bool,
     ) -> Result<RestoreReport> {
-        // Get current session
-        let current_session = self
-            .zellij
-            .active_session_name()
-            .ok_or_else(|| anyhow!("not inside a zellij session; restore requires active session"))?;
+        // Get target session
+        let target_session = self
+            .target
+            .clone()
+            .or_else(|| self.zellij.active_session_name())
+            .ok_or_else(|| PerthError::ZellijUnavailable("not inside a zellij session; restore requires active session or --into".to_string()))?;
 
         // Initialize report
-        let mut report = RestoreReport::new(snapshot.name.clone(), current_session);
+        let mut report = RestoreReport::new(snapshot.name.clone(), target_session.clone());
+        if target_session != snapshot.session {
+            report.original_session = Some(snapshot.session.clone());
+        }
         let start_time = Utc::now();
 
         // Get existing tabs to avoid duplicates
         let existing_tabs = if !dry_run {
-            self.zellij.query_tab_names(None).await?
+            self.zellij.query_tab_names(self.target.as_deref()).await?
         } else {
             vec![]
         };
@@ -93,7 +110,11 @@ impl SessionRestore {
             for pane in &tab.panes {
                 println!("    [DRY RUN] Would create pane: {} at position {}", pane.name, pane.position);
                 if let Some(cwd) = &pane.cwd {
-                    println!("      CWD: {}", cwd);
+                    let missing = if existing_cwd(pane, &tab.name, report).is_none() { " (missing)" } else { "" };
+                    println!("      CWD: {}{}", cwd, missing);
+                }
+                if let Some(command) = pane.meta.get(COMMAND_META_KEY) {
+                    println!("      Command: {}", command);
                 }
             }
 
@@ -102,20 +123,20 @@ impl SessionRestore {
 
         // Create or switch to tab
         if tab_exists {
-            self.zellij.go_to_tab_name(None, &tab.name).await
+            self.zellij.go_to_tab_name(self.target.as_deref(), &tab.name).await
                 .context("failed to switch to existing tab")?;
 
             let warning = RestoreWarning::info(format!("Tab '{}' already exists, switching to it", tab.name))
                 .for_component(format!("tab '{}'", tab.name));
             report.add_warning(warning);
         } else {
-            self.zellij.new_tab(None, &tab.name).await
+            self.zellij.new_tab(self.target.as_deref(), &tab.name).await
                 .context("failed to create tab")?;
         }
 
         // Restore panes in this tab
         for (idx, pane) in tab.panes.iter().enumerate() {
-            match self.restore_pane(pane, idx, &tab.name, report).await {
+            match self.restore_pane(pane, idx, !tab_exists, &tab.name, report).await {
                 Ok(_) => {
                     report.panes_restored += 1;
                 }
@@ -131,20 +152,31 @@ impl SessionRestore {
         Ok(())
     }
 
-    /// Restore a single pane.
+    /// Restore a single pane. In a tab that was just created the first pane
+    /// already exists and is reused; in an existing tab every pane is new,
+    /// leaving the panes already there alone.
     async fn restore_pane(
         &self,
         pane: &crate::types::PaneSnapshot,
         index: usize,
+        new_tab: bool,
         tab_name: &str,
         report: &mut RestoreReport,
     ) -> Result<()> {
+        let session = self.target.as_deref();
+        let cwd = existing_cwd(pane, tab_name, report);
+
         // Skip first pane (already exists when tab is created)
-        if index == 0 {
+        if index == 0 && new_tab {
             // Just rename it
-            self.zellij.rename_pane(None, &pane.name).await
+            self.zellij.rename_pane(session, &pane.name).await
                 .context("failed to rename first pane")?;
 
+            if let Some(command) = pane.meta.get(COMMAND_META_KEY) {
+                self.zellij.write_chars(session, &format!("{}\n", command)).await
+                    .context("failed to run pane command")?;
+            }
+
             if pane.name == "unnamed" {
                 let warning = RestoreWarning::info("First pane has no name")
                     .for_component(format!("tab '{}'", tab_name));
@@ -157,21 +189,24 @@ impl SessionRestore {
         // Create new pane (default to vertical split)
         let direction = if index % 2 == 0 { "down" } else { "right" };
 
-        if let Some(cwd) = &pane.cwd {
-            self.zellij.new_pane_with_cwd(None, cwd, direction).await
+        if let Some(command) = pane.meta.get(COMMAND_META_KEY) {
+            self.zellij.new_pane_with_env(session, Some(direction), cwd, &[], Some(command.as_str())).await
+                .context("failed to create pane with command")?;
+        } else if let Some(cwd) = cwd {
+            self.zellij.new_pane_with_cwd(session, cwd, direction).await
                 .context("failed to create pane with CWD")?;
         } else {
             if direction == "down" {
-                self.zellij.new_pane_horizontal(None).await
+                self.zellij.new_pane_horizontal(session).await
                     .context("failed to create horizontal pane")?;
             } else {
-                self.zellij.new_pane_vertical(None).await
+                self.zellij.new_pane_vertical(session).await
                     .context("failed to create vertical pane")?;
             }
         }
 
         // Rename pane
-        self.zellij.rename_pane(None, &pane.name).await
+        self.zellij.rename_pane(session, &pane.name).await
             .context("failed to rename pane")?;
 
         // Warn if pane has no CWD
@@ -185,6 +220,49 @@ impl SessionRestore {
     }
 }
 
+/// The part of `snapshot` to restore: only the tab named `tab`, and within
+/// it (or across all tabs) only the pane named `pane`.
+pub fn select(snapshot: &SessionSnapshot, tab: Option<&str>, pane: Option<&str>) -> Result<SessionSnapshot> {
+    let mut selected = snapshot.clone();
+    if let Some(tab) = tab {
+        selected.tabs.retain(|t| t.name == tab);
+        if selected.tabs.is_empty() {
+            let names: Vec<&str> = snapshot.tabs.iter().map(|t| t.name.as_str()).collect();
+            return Err(anyhow!("snapshot '{}' has no tab '{}' (tabs: {})", snapshot.name, tab, names.join(", ")));
+        }
+    }
+    if let Some(pane) = pane {
+        for tab in &mut selected.tabs {
+            tab.panes.retain(|p| p.name == pane);
+        }
+        selected.tabs.retain(|t| !t.panes.is_empty());
+        if selected.tabs.is_empty() {
+            let scope = tab.map(|tab| format!(" tab '{}'", tab)).unwrap_or_default();
+            return Err(anyhow!("snapshot '{}'{} has no pane '{}'", snapshot.name, scope, pane));
+        }
+    }
+    selected.pane_count = selected.tabs.iter().map(|t| t.panes.len()).sum();
+    Ok(selected)
+}
+
+/// The pane's saved working directory if it exists on this machine. A
+/// missing one is recorded in the report and the pane opens in the
+/// session's default directory instead.
+fn existing_cwd<'a>(pane: &'a crate::types::PaneSnapshot, tab_name: &str, report: &mut RestoreReport) -> Option<&'a str> {
+    let cwd = pane.cwd.as_deref()?;
+    if Path::new(cwd).is_dir() {
+        return Some(cwd);
+    }
+    if !report.missing_cwds.iter().any(|missing| missing == cwd) {
+        report.missing_cwds.push(cwd.to_string());
+    }
+    let warning = RestoreWarning::warning(format!("Working directory {} does not exist on this machine", cwd))
+        .for_component(format!("tab '{}', pane '{}'", tab_name, pane.name))
+        .with_suggestion("the pane opens in the session's default directory");
+    report.add_warning(warning);
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -210,10 +288,91 @@ mod tests {
             pane_id: Some("42".to_string()),
             focused: true,
             meta: HashMap::new(),
+            scrollback: None,
         };
 
         assert_eq!(pane.name, "test-pane");
         assert_eq!(pane.position, 0);
         assert!(pane.focused);
     }
+
+    #[tokio::test]
+    async fn test_restore_into_other_session() {
+        use crate::multiplexer::MockMultiplexer;
+
+        let pane = |name: &str, position: usize, cwd: String| PaneSnapshot {
+            name: name.to_string(),
+            position,
+            cwd: Some(cwd),
+            command: None,
+            pane_id: None,
+            focused: false,
+            meta: HashMap::new(),
+            scrollback: None,
+        };
+        let missing = "/nonexistent/perth/api".to_string();
+        let mut snapshot = SessionSnapshot::new("work", "desktop");
+        snapshot.tabs.push(TabSnapshot {
+            name: "backend".to_string(),
+            index: 0,
+            active: true,
+            layout: "vertical".to_string(),
+            panes: vec![pane("api", 0, missing.clone()), pane("logs", 1, std::env::temp_dir().display().to_string())],
+            correlation_id: None,
+        });
+
+        let mock = MockMultiplexer::new().with_active_session("dev").with_session("laptop", &["Tab #1"]);
+        let report = SessionRestore::new(mock.clone())
+            .into_session(Some("laptop".to_string()))
+            .restore_session(&snapshot, false)
+            .await
+            .unwrap();
+
+        assert_eq!(report.session, "laptop");
+        assert_eq!(report.original_session.as_deref(), Some("desktop"));
+        assert_eq!(report.missing_cwds, vec![missing]);
+        assert_eq!(report.panes_restored, 2);
+        assert_eq!(mock.pane_names("laptop", "backend"), vec!["api", "logs"]);
+        assert!(mock.pane_names("dev", "backend").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_restore_single_pane_into_existing_tab() {
+        use crate::multiplexer::MockMultiplexer;
+
+        let pane = |name: &str, position: usize| PaneSnapshot {
+            name: name.to_string(),
+            position,
+            cwd: None,
+            command: None,
+            pane_id: None,
+            focused: false,
+            meta: HashMap::new(),
+            scrollback: None,
+        };
+        let tab = |name: &str, panes: Vec<PaneSnapshot>| TabSnapshot {
+            name: name.to_string(),
+            index: 0,
+            active: false,
+            layout: "vertical".to_string(),
+            panes,
+            correlation_id: None,
+        };
+        let mut snapshot = SessionSnapshot::new("last-week", "dev");
+        snapshot.tabs = vec![tab("backend", vec![pane("api", 0), pane("logs", 1)]), tab("debug", vec![pane("gdb", 0)])];
+
+        let debug = select(&snapshot, Some("debug"), None).unwrap();
+        assert_eq!(debug.tabs.len(), 1);
+        assert_eq!(debug.pane_count, 1);
+        assert!(select(&snapshot, Some("missing"), None).unwrap_err().to_string().contains("tabs: backend, debug"));
+        assert!(select(&snapshot, Some("debug"), Some("api")).is_err());
+
+        // The user's own pane in the existing tab keeps its name
+        let mock = MockMultiplexer::new().with_session("dev", &["backend"]).with_active_session("dev");
+        mock.rename_pane(None, "mine").await.unwrap();
+        let logs = select(&snapshot, None, Some("logs")).unwrap();
+        let report = SessionRestore::new(mock.clone()).restore_session(&logs, false).await.unwrap();
+        assert_eq!(report.panes_restored, 1);
+        assert_eq!(mock.pane_names("dev", "backend"), vec!["mine", "logs"]);
+    }
 }
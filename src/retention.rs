@@ -0,0 +1,149 @@
+//! Per-type and per-tag retention policies for intent history.
+//!
+//! Configured under `[retention]`, with a max age per entry type and an
+//! optional `[retention.tags]` table keyed by `#tag` (without the `#`).
+//! A matching tag rule overrides the type rule; when several tags match,
+//! the longest retention wins so an entry is never removed early.
+
+use crate::quicklog::collect_tags;
+use crate::types::{IntentEntry, IntentType};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::BTreeMap;
+
+/// Max age per entry type and tag. `None` means keep forever.
+#[derive(Debug, Clone, Default)]
+pub struct RetentionPolicy {
+    pub milestone: Option<Duration>,
+    pub checkpoint: Option<Duration>,
+    pub exploration: Option<Duration>,
+    pub tags: BTreeMap<String, Option<Duration>>,
+}
+
+/// Parse a duration such as `30m`, `2h`, `7d`, or `1w`.
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let split = input.find(|c: char| !c.is_ascii_digit()).unwrap_or(input.len());
+    let (amount, unit) = input.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| format!("invalid duration '{}': use a number and unit like 2h or 30d", input))?;
+
+    match unit {
+        "s" => Ok(Duration::seconds(amount)),
+        "m" => Ok(Duration::minutes(amount)),
+        "h" => Ok(Duration::hours(amount)),
+        "d" => Ok(Duration::days(amount)),
+        "w" => Ok(Duration::weeks(amount)),
+        _ => Err(format!("invalid duration unit '{}': use s, m, h, d, or w", unit)),
+    }
+}
+
+/// Parse a retention value: `never` or a duration such as `30d`.
+pub fn parse_max_age(input: &str) -> Result<Option<Duration>> {
+    if input.trim().eq_ignore_ascii_case("never") {
+        return Ok(None);
+    }
+    parse_duration(input)
+        .map(Some)
+        .map_err(|e| anyhow!("invalid retention '{}': {} (or 'never')", input, e))
+}
+
+/// Render a max age the way it is written in config.
+pub fn format_max_age(age: Option<Duration>) -> String {
+    let Some(age) = age else {
+        return "never".to_string();
+    };
+    let secs = age.num_seconds();
+    [("w", 604_800), ("d", 86_400), ("h", 3_600), ("m", 60)]
+        .iter()
+        .find(|(_, unit)| secs % unit == 0)
+        .map(|(suffix, unit)| format!("{}{}", secs / unit, suffix))
+        .unwrap_or_else(|| format!("{}s", secs))
+}
+
+impl RetentionPolicy {
+    /// Whether any rule could remove entries.
+    pub fn is_empty(&self) -> bool {
+        self.milestone.is_none()
+            && self.checkpoint.is_none()
+            && self.exploration.is_none()
+            && self.tags.values().all(Option::is_none)
+    }
+
+    /// The max age that applies to `entry`, if any.
+    pub fn max_age(&self, entry: &IntentEntry) -> Option<Duration> {
+        let tag_rules: Vec<Option<Duration>> = collect_tags(std::slice::from_ref(entry))
+            .iter()
+            .filter_map(|tag| self.tags.get(tag.trim_start_matches('#')).copied())
+            .collect();
+
+        if tag_rules.is_empty() {
+            return match entry.entry_type {
+                IntentType::Milestone => self.milestone,
+                IntentType::Checkpoint => self.checkpoint,
+                IntentType::Exploration => self.exploration,
+            };
+        }
+
+        // Any "never" tag keeps the entry; otherwise the longest age wins
+        tag_rules.into_iter().try_fold(Duration::zero(), |longest, age| age.map(|a| a.max(longest)))
+    }
+
+    /// Whether `entry` is older than its retention at `now`.
+    pub fn is_expired(&self, entry: &IntentEntry, now: DateTime<Utc>) -> bool {
+        self.max_age(entry)
+            .is_some_and(|age| entry.timestamp < now - age)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aged(summary: &str, entry_type: IntentType, days: i64) -> IntentEntry {
+        let mut entry = IntentEntry::new(summary).with_type(entry_type);
+        entry.timestamp = Utc::now() - Duration::days(days);
+        entry
+    }
+
+    #[test]
+    fn test_parse_max_age() {
+        assert_eq!(parse_max_age("never").unwrap(), None);
+        assert_eq!(parse_max_age("30d").unwrap(), Some(Duration::days(30)));
+        assert!(parse_max_age("soon").is_err());
+        assert_eq!(format_max_age(Some(Duration::days(30))), "30d");
+        assert_eq!(format_max_age(Some(Duration::weeks(2))), "2w");
+        assert_eq!(format_max_age(None), "never");
+    }
+
+    #[test]
+    fn test_type_rules() {
+        let policy = RetentionPolicy {
+            exploration: Some(Duration::days(30)),
+            checkpoint: Some(Duration::days(90)),
+            ..Default::default()
+        };
+        let now = Utc::now();
+
+        assert!(policy.is_expired(&aged("Tried sqlx", IntentType::Exploration, 31), now));
+        assert!(!policy.is_expired(&aged("Tried diesel", IntentType::Exploration, 29), now));
+        assert!(!policy.is_expired(&aged("Saved", IntentType::Checkpoint, 31), now));
+        assert!(!policy.is_expired(&aged("Shipped", IntentType::Milestone, 1000), now));
+    }
+
+    #[test]
+    fn test_tag_rules_override_type_rules() {
+        let mut policy = RetentionPolicy {
+            exploration: Some(Duration::days(30)),
+            ..Default::default()
+        };
+        policy.tags.insert("decision".to_string(), None);
+        policy.tags.insert("wip".to_string(), Some(Duration::days(7)));
+        let now = Utc::now();
+
+        assert!(!policy.is_expired(&aged("Chose Redis #decision", IntentType::Exploration, 60), now));
+        assert!(policy.is_expired(&aged("Half done #wip", IntentType::Milestone, 8), now));
+        assert!(!policy.is_expired(&aged("Kept #wip #decision", IntentType::Checkpoint, 60), now));
+    }
+}
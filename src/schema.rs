@@ -0,0 +1,119 @@
+//! JSON Schemas for Perth's machine-readable output, printed by `zdrive schema`.
+//!
+//! Schemas are generated from the same types the commands serialize, so they
+//! can't drift from the output. Command documents also describe the
+//! `schema_version` and `command` fields added by `--format json`, and every
+//! schema carries the `x-schema-version` it was generated for.
+
+use crate::bloodbank::EventEnvelope;
+use crate::output::json::SCHEMA_VERSION;
+use crate::types::{HistoryOutput, PaneInfoOutput, SessionSnapshot};
+use anyhow::Result;
+use schemars::schema_for;
+use serde_json::{json, Map, Value};
+
+/// An output type with a published schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SchemaKind {
+    /// `pane info` document
+    PaneInfo,
+    /// `pane history` document
+    History,
+    /// `snapshot show` document
+    Snapshot,
+    /// Bloodbank event envelope, as published and printed by `events tail`
+    Event,
+}
+
+impl SchemaKind {
+    pub const ALL: [SchemaKind; 4] = [SchemaKind::PaneInfo, SchemaKind::History, SchemaKind::Snapshot, SchemaKind::Event];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SchemaKind::PaneInfo => "pane-info",
+            SchemaKind::History => "history",
+            SchemaKind::Snapshot => "snapshot",
+            SchemaKind::Event => "event",
+        }
+    }
+
+    /// The command printing this type as a `--format json` document; events
+    /// are printed bare.
+    fn command(self) -> Option<&'static str> {
+        match self {
+            SchemaKind::PaneInfo => Some("pane info"),
+            SchemaKind::History => Some("pane history"),
+            SchemaKind::Snapshot => Some("snapshot show"),
+            SchemaKind::Event => None,
+        }
+    }
+}
+
+/// The JSON Schema for one output type.
+pub fn generate(kind: SchemaKind) -> Result<Value> {
+    let root = match kind {
+        SchemaKind::PaneInfo => schema_for!(PaneInfoOutput),
+        SchemaKind::History => schema_for!(HistoryOutput),
+        SchemaKind::Snapshot => schema_for!(SessionSnapshot),
+        SchemaKind::Event => schema_for!(EventEnvelope<Value>),
+    };
+    let mut schema = serde_json::to_value(root)?;
+    if let Some(command) = kind.command() {
+        add_document_fields(&mut schema, command);
+    }
+    schema["x-schema-version"] = json!(SCHEMA_VERSION);
+    Ok(schema)
+}
+
+/// Every schema, keyed by name.
+pub fn generate_all() -> Result<Value> {
+    let mut schemas = Map::new();
+    for kind in SchemaKind::ALL {
+        schemas.insert(kind.name().to_string(), generate(kind)?);
+    }
+    Ok(json!({ "schema_version": SCHEMA_VERSION, "schemas": schemas }))
+}
+
+/// Describe the fields `output::json::document` wraps a body in. A body with
+/// its own `schema_version` (like a snapshot) keeps that property.
+fn add_document_fields(schema: &mut Value, command: &str) {
+    let mut fields = vec![("command", json!({ "type": "string", "const": command }))];
+    if schema["properties"].get("schema_version").is_none() {
+        fields.push(("schema_version", json!({ "type": "string", "const": SCHEMA_VERSION })));
+    }
+    for (name, property) in fields {
+        schema["properties"][name] = property;
+        if let Some(required) = schema["required"].as_array_mut() {
+            required.push(json!(name));
+        } else {
+            schema["required"] = json!([name]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_schemas_include_envelope() {
+        let schema = generate(SchemaKind::History).unwrap();
+        assert_eq!(schema["x-schema-version"], SCHEMA_VERSION);
+        assert_eq!(schema["properties"]["command"]["const"], "pane history");
+        assert_eq!(schema["properties"]["schema_version"]["const"], SCHEMA_VERSION);
+        assert!(schema["properties"]["entries"].is_object());
+        let required = schema["required"].as_array().unwrap();
+        assert!(required.contains(&json!("schema_version")));
+        assert!(required.contains(&json!("pane")));
+
+        // Snapshots version their own format
+        let schema = generate(SchemaKind::Snapshot).unwrap();
+        assert_eq!(schema["properties"]["schema_version"]["type"], "string");
+        assert!(schema["properties"]["schema_version"].get("const").is_none());
+
+        // Events aren't wrapped in a command document
+        let schema = generate(SchemaKind::Event).unwrap();
+        assert!(schema["properties"].get("command").is_none());
+        assert!(schema["properties"]["metadata"].is_object());
+    }
+}
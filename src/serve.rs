@@ -0,0 +1,537 @@
+//! Local HTTP/JSON API (`zdrive serve`).
+//!
+//! Lets editor plugins, status bars and dashboards read and log intent
+//! without starting a `zdrive` process per request. Like the metrics
+//! endpoint it is a minimal HTTP/1.1 server: one request per connection,
+//! each read on its own task and answered against a shared
+//! [`Orchestrator`]. Bodies use the plumbing JSON shapes, so a pane or entry
+//! looks the same as in `zdrive plumbing` output, and errors carry the same
+//! `error` and `kind`.
+//!
+//! Browsers can reach a loopback port too, so requests must name a loopback
+//! `Host` and carry no `Origin`, and a `POST` body must be sent as
+//! `application/json`. That keeps web pages, including DNS-rebound ones,
+//! from logging intent or taking snapshots.
+//!
+//! | Method | Path | |
+//! |--------|------|-|
+//! | GET | `/health` | Version check |
+//! | GET | `/panes` | Tracked panes (`?session=`, `?tab=`, `?stale=true`) |
+//! | GET | `/panes/{name}` | One pane and its last intent |
+//! | GET | `/panes/{name}/history` | Entries, newest first (`?last=`, `?offset=`, `?tag=`) |
+//! | POST | `/panes/{name}/history` | Log an entry, as for `plumbing put-entry` |
+//! | GET | `/snapshots` | Snapshots across all sessions |
+//! | POST | `/snapshots` | Snapshot the session (`{"name": ..., "description": ...}`) |
+//! | GET | `/metrics` | Prometheus metrics |
+
+use crate::config::Config;
+use crate::error::PerthError;
+use crate::orchestrator::{Orchestrator, PaneListQuery};
+#[cfg(feature = "snapshots")]
+use crate::snapshot;
+use crate::{metrics, plumbing, state, telemetry};
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+
+/// Default `--listen` address.
+pub const DEFAULT_ADDR: &str = "127.0.0.1:7433";
+
+/// Largest request accepted, headers and body together
+const MAX_REQUEST_BYTES: usize = 1024 * 1024;
+
+/// How long a client gets to send its request, so a stalled one can't
+/// hold up the others
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A parsed request. Only what the routes need is kept.
+#[derive(Debug, PartialEq)]
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    /// Header values by lowercased name
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn json(status: u16, body: Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: format!("{}\n", plumbing::envelope(body)),
+        }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self::json(status, json!({ "error": message.into() }))
+    }
+
+    /// Error response for a failed command, with the status its kind maps to.
+    fn failed(err: &anyhow::Error) -> Self {
+        Self {
+            status: status_for(err),
+            content_type: "application/json",
+            body: format!("{}\n", plumbing::error(err)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Route {
+    Health,
+    Metrics,
+    Panes,
+    Pane(String),
+    History(String),
+    Snapshots,
+}
+
+/// Serve the API on `addr` until the process is stopped.
+pub async fn run(orchestrator: Orchestrator, config: Config, addr: SocketAddr) -> Result<()> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("failed to bind API on {}", addr))?;
+    println!("Serving the Perth API at http://{}", addr);
+    if !addr.ip().is_loopback() {
+        tracing::warn!("the API has no authentication; anyone who can reach {} can read and log intent", addr);
+    }
+    println!("  Press CTRL+C to stop\n");
+
+    let orchestrator = Arc::new(Mutex::new(orchestrator));
+    let config = Arc::new(config);
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                tracing::warn!("failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        let orchestrator = Arc::clone(&orchestrator);
+        let config = Arc::clone(&config);
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(&orchestrator, &config, stream).await {
+                tracing::debug!(%peer, "connection failed: {:#}", e);
+            }
+            telemetry::flush().await;
+        });
+    }
+}
+
+async fn handle_connection(orchestrator: &Mutex<Orchestrator>, config: &Config, mut stream: TcpStream) -> Result<()> {
+    let response = match tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream)).await {
+        Ok(Ok(request)) => {
+            let response = match check_request(&request) {
+                Some(rejected) => rejected,
+                None => {
+                    // Requests are answered one at a time; only reading them
+                    // happens concurrently.
+                    let mut orchestrator = orchestrator.lock().await;
+                    let _trace = telemetry::root_span(format!("serve {} {}", request.method, request.path));
+                    handle(&mut orchestrator, config, &request).await
+                }
+            };
+            metrics::inc(
+                "perth_api_requests_total",
+                &[("method", method_label(&request.method)), ("status", &response.status.to_string())],
+            );
+            response
+        }
+        Ok(Err(e)) => Response::error(400, format!("{:#}", e)),
+        Err(_) => Response::error(408, "timed out reading the request"),
+    };
+    write_response(&mut stream, &response).await
+}
+
+async fn read_request(stream: &mut TcpStream) -> Result<Request> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 8192];
+    loop {
+        let n = stream.read(&mut chunk).await.context("failed to read request")?;
+        if n == 0 {
+            return Err(anyhow!("connection closed before the request was complete"));
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+        if let Some(request) = parse_request(&buffer)? {
+            return Ok(request);
+        }
+    }
+}
+
+async fn write_response(stream: &mut TcpStream, response: &Response) -> Result<()> {
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        reason(response.status),
+        response.content_type,
+        response.body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(response.body.as_bytes()).await?;
+    Ok(())
+}
+
+/// Parse `buffer` as an HTTP/1.1 request, or `None` if more of it has yet
+/// to arrive.
+fn parse_request(buffer: &[u8]) -> Result<Option<Request>> {
+    let Some(head_end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") else {
+        if buffer.len() > MAX_REQUEST_BYTES {
+            return Err(anyhow!("request headers are too large"));
+        }
+        return Ok(None);
+    };
+    let head = std::str::from_utf8(&buffer[..head_end]).context("request headers are not UTF-8")?;
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or("").split_whitespace();
+    let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else {
+        return Err(anyhow!("malformed request line"));
+    };
+
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+    let content_length = match headers.get("content-length") {
+        Some(value) => value.parse().context("invalid Content-Length")?,
+        None => 0,
+    };
+    if content_length > MAX_REQUEST_BYTES {
+        return Err(anyhow!("request body is larger than {} bytes", MAX_REQUEST_BYTES));
+    }
+    let body_start = head_end + 4;
+    if buffer.len() < body_start + content_length {
+        return Ok(None);
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect();
+    Ok(Some(Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query,
+        headers,
+        body: buffer[body_start..body_start + content_length].to_vec(),
+    }))
+}
+
+/// Rejection for a request a browser could have sent on a web page's
+/// behalf, or `None` if it may be handled.
+fn check_request(request: &Request) -> Option<Response> {
+    if !request.headers.get("host").is_some_and(|host| is_loopback_host(host)) {
+        return Some(Response::error(403, "Host must be localhost or 127.0.0.1"));
+    }
+    if request.headers.contains_key("origin") {
+        return Some(Response::error(403, "cross-origin requests are not allowed"));
+    }
+    if request.method == "POST" {
+        let json = request
+            .headers
+            .get("content-type")
+            .and_then(|value| value.split(';').next())
+            .is_some_and(|media_type| media_type.trim().eq_ignore_ascii_case("application/json"));
+        if !json {
+            return Some(Response::error(415, "POST bodies must be sent as application/json"));
+        }
+    }
+    None
+}
+
+/// Whether a `Host` header names the loopback interface, with or without a
+/// port.
+fn is_loopback_host(host: &str) -> bool {
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if port.parse::<u16>().is_ok() => name,
+        _ => host,
+    };
+    matches!(name.to_ascii_lowercase().as_str(), "localhost" | "127.0.0.1")
+}
+
+/// `method` label for the request counter, from a fixed set so clients
+/// can't grow the metric without bound.
+fn method_label(method: &str) -> &'static str {
+    match method {
+        "GET" => "GET",
+        "POST" => "POST",
+        _ => "other",
+    }
+}
+
+/// Decode `%XX` escapes and `+` (as a space). Malformed escapes are kept as is.
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                match std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 3;
+                        continue;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            b'+' => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn route(path: &str) -> Option<Route> {
+    let segments: Vec<String> = path.trim_matches('/').split('/').map(percent_decode).collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    match segments.as_slice() {
+        ["health"] => Some(Route::Health),
+        ["metrics"] => Some(Route::Metrics),
+        ["panes"] => Some(Route::Panes),
+        ["panes", name] if !name.is_empty() => Some(Route::Pane(name.to_string())),
+        ["panes", name, "history"] if !name.is_empty() => Some(Route::History(name.to_string())),
+        ["snapshots"] => Some(Route::Snapshots),
+        _ => None,
+    }
+}
+
+async fn handle(orchestrator: &mut Orchestrator, config: &Config, request: &Request) -> Response {
+    let Some(route) = route(&request.path) else {
+        return Response::error(404, format!("no such endpoint: {}", request.path));
+    };
+    let result = match (request.method.as_str(), route) {
+        ("GET", Route::Health) => Ok(Response::json(200, json!({ "status": "ok", "version": env!("CARGO_PKG_VERSION") }))),
+        ("GET", Route::Metrics) => Ok(Response {
+            status: 200,
+            content_type: "text/plain; version=0.0.4",
+            body: metrics::render(),
+        }),
+        ("GET", Route::Panes) => list_panes(orchestrator, request).await,
+        ("GET", Route::Pane(name)) => get_pane(orchestrator, &name).await,
+        ("GET", Route::History(name)) => get_history(orchestrator, &name, request).await,
+        ("POST", Route::History(name)) => log_entry(orchestrator, &name, request).await,
+        ("GET", Route::Snapshots) => orchestrator
+            .list_all_snapshots()
+            .await
+            .map(|snapshots| Response::json(200, json!({ "snapshots": snapshots }))),
+        ("POST", Route::Snapshots) => create_snapshot(orchestrator, config, request).await,
+        (method, _) => Ok(Response::error(405, format!("{} is not allowed on {}", method, request.path))),
+    };
+    result.unwrap_or_else(|e| Response::failed(&e))
+}
+
+async fn list_panes(orchestrator: &mut Orchestrator, request: &Request) -> Result<Response> {
+    let query = PaneListQuery {
+        session: request.query.get("session").cloned(),
+        tab: request.query.get("tab").cloned(),
+        stale_only: request.query.get("stale").is_some_and(|value| value == "true"),
+        ..Default::default()
+    };
+    let panes: Vec<Value> = orchestrator.list_panes(&query).await?.iter().map(plumbing::pane_json).collect();
+    Ok(Response::json(200, json!({ "panes": panes })))
+}
+
+async fn get_pane(orchestrator: &mut Orchestrator, name: &str) -> Result<Response> {
+    let record = orchestrator
+        .get_pane_record(name)
+        .await?
+        .ok_or_else(|| PerthError::NotFound(format!("pane '{}' is not tracked", name)))?;
+    let last_intent = orchestrator.last_intent(name).await?;
+    Ok(Response::json(200, json!({ "pane": plumbing::pane_json(&record), "last_intent": last_intent })))
+}
+
+async fn get_history(orchestrator: &mut Orchestrator, name: &str, request: &Request) -> Result<Response> {
+    let number = |key: &str| -> Result<Option<usize>> {
+        request
+            .query
+            .get(key)
+            .map(|value| value.parse().map_err(|_| anyhow!("{} must be a number, got '{}'", key, value)))
+            .transpose()
+    };
+    let (last, offset) = match (number("last"), number("offset")) {
+        (Ok(last), Ok(offset)) => (last, offset.unwrap_or(0)),
+        (Err(e), _) | (_, Err(e)) => return Ok(Response::error(400, e.to_string())),
+    };
+    let query = state::HistoryQuery { offset, limit: last, tag: request.query.get("tag").cloned(), ..Default::default() };
+    let entries = orchestrator.query_history(name, &query).await?;
+    Ok(Response::json(200, json!({ "pane": name, "entries": entries })))
+}
+
+async fn log_entry(orchestrator: &mut Orchestrator, name: &str, request: &Request) -> Result<Response> {
+    let entry = match serde_json::from_slice(&request.body)
+        .context("body is not valid JSON")
+        .and_then(plumbing::entry_from_json)
+    {
+        Ok(entry) => entry,
+        Err(e) => return Ok(Response::error(400, format!("{:#}", e))),
+    };
+    orchestrator.log_intent(name, &entry).await?;
+    Ok(Response::json(201, json!({ "pane": name, "entry": entry })))
+}
+
+#[cfg(not(feature = "snapshots"))]
+async fn create_snapshot(_orchestrator: &mut Orchestrator, _config: &Config, _request: &Request) -> Result<Response> {
+    Err(crate::feature_missing("serve", "snapshots").context("cannot take a snapshot"))
+}
+
+#[cfg(feature = "snapshots")]
+async fn create_snapshot(orchestrator: &mut Orchestrator, config: &Config, request: &Request) -> Result<Response> {
+    let options: Value = if request.body.is_empty() {
+        json!({})
+    } else {
+        match serde_json::from_slice(&request.body) {
+            Ok(options) => options,
+            Err(e) => return Ok(Response::error(400, format!("body is not valid JSON: {}", e))),
+        }
+    };
+    let name = match options.get("name").and_then(Value::as_str) {
+        Some(name) => name.to_string(),
+        None => format!("api-{}", chrono::Local::now().format("%Y-%m-%d-%H%M%S")),
+    };
+    let description = options.get("description").and_then(Value::as_str).map(str::to_string);
+    match orchestrator.get_snapshot(&name).await {
+        Ok(_) => return Ok(Response::error(409, format!("snapshot '{}' already exists", name))),
+        Err(e) if crate::error::kind(&e) == Some("not_found") => {}
+        Err(e) => return Err(e),
+    }
+
+    let capture = snapshot::StateCapture::new(crate::zellij::ZellijDriver::new());
+    let (mut snapshot, report) = capture.capture_session(name, description, None).await?;
+    orchestrator.save_snapshot(&mut snapshot).await?;
+    if let Err(e) = orchestrator.enforce_snapshot_retention(&snapshot.session, config.snapshot.retention_limit).await {
+        tracing::warn!("failed to enforce snapshot retention: {:#}", e);
+    }
+    Ok(Response::json(201, json!({ "snapshot": snapshot, "report": report })))
+}
+
+/// HTTP status for a failed command, from its error kind.
+fn status_for(err: &anyhow::Error) -> u16 {
+    match crate::error::kind(err) {
+        Some("not_found") => 404,
+        Some("stale_state") => 409,
+        Some("consent_required") => 403,
+        Some("llm_error") => 502,
+        Some("zellij_unavailable" | "storage_error") => 503,
+        _ => 500,
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        415 => "Unsupported Media Type",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request_waits_for_body() {
+        let raw = b"POST /panes/api/history?tag=auth HTTP/1.1\r\nHost: x\r\nContent-Length: 13\r\n\r\n{\"summary\":";
+        assert_eq!(parse_request(raw).unwrap(), None);
+        assert_eq!(parse_request(b"GET /health HTTP/1.1\r\nHost").unwrap(), None);
+
+        let raw = b"POST /panes/api/history?tag=auth HTTP/1.1\r\ncontent-length: 2\r\n\r\n{}";
+        let request = parse_request(raw).unwrap().unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/panes/api/history");
+        assert_eq!(request.query.get("tag").map(String::as_str), Some("auth"));
+        assert!(!request.headers.contains_key("host"));
+        assert_eq!(request.body, b"{}");
+
+        assert!(parse_request(b"\r\n\r\n").is_err());
+    }
+
+    fn request(method: &str, headers: &[(&str, &str)]) -> Request {
+        Request {
+            method: method.to_string(),
+            path: "/panes/api/history".to_string(),
+            query: HashMap::new(),
+            headers: headers.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+            body: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_check_request_rejects_browser_requests() {
+        let status = |request: Request| check_request(&request).map(|response| response.status);
+        let json = ("content-type", "application/json; charset=utf-8");
+
+        assert_eq!(status(request("GET", &[("host", "localhost")])), None);
+        assert_eq!(status(request("GET", &[("host", "127.0.0.1:7433")])), None);
+        assert_eq!(status(request("POST", &[("host", "LocalHost:7433"), json])), None);
+
+        assert_eq!(status(request("GET", &[])), Some(403));
+        assert_eq!(status(request("GET", &[("host", "evil.example:7433")])), Some(403));
+        assert_eq!(status(request("GET", &[("host", "localhost.evil.example")])), Some(403));
+        assert_eq!(status(request("POST", &[("host", "127.0.0.1:7433"), ("origin", "https://evil.example"), json])), Some(403));
+        assert_eq!(status(request("POST", &[("host", "127.0.0.1:7433")])), Some(415));
+        assert_eq!(status(request("POST", &[("host", "127.0.0.1:7433"), ("content-type", "text/plain")])), Some(415));
+    }
+
+    #[test]
+    fn test_method_label() {
+        assert_eq!(method_label("GET"), "GET");
+        assert_eq!(method_label("POST"), "POST");
+        assert_eq!(method_label("DELETE"), "other");
+        assert_eq!(method_label("X-RANDOM-1234"), "other");
+    }
+
+    #[test]
+    fn test_route() {
+        assert_eq!(route("/health"), Some(Route::Health));
+        assert_eq!(route("/panes/"), Some(Route::Panes));
+        assert_eq!(route("/panes/api%20work"), Some(Route::Pane("api work".to_string())));
+        assert_eq!(route("/panes/api/history"), Some(Route::History("api".to_string())));
+        assert_eq!(route("/panes/api/notes"), None);
+        assert_eq!(route("/"), None);
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("myapp%28fixes%29"), "myapp(fixes)");
+        assert_eq!(percent_decode("a+b"), "a b");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+    }
+
+    #[test]
+    fn test_status_for_error_kind() {
+        assert_eq!(status_for(&PerthError::NotFound("pane 'api' is not tracked".to_string()).into()), 404);
+        assert_eq!(status_for(&PerthError::ZellijUnavailable("not inside a zellij session".to_string()).into()), 503);
+        assert_eq!(status_for(&anyhow!("something else")), 500);
+    }
+}
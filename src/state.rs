@@ -1,27 +1,226 @@
-use crate::types::{IntentEntry, PaneRecord, TabRecord};
+use crate::crypto::{self, Cipher};
+use crate::error::PerthError;
+use crate::metrics;
+use crate::telemetry;
+use crate::types::{Annotation, Goal, IntentEntry, LlmUsage, PaneRecord, TabRecord};
 use anyhow::{Context, Result};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures_util::{Stream, StreamExt};
 use redis::aio::MultiplexedConnection;
 use redis::AsyncCommands;
 use redis::AsyncIter;
-use std::collections::HashMap;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
 
 const META_PREFIX: &str = "meta:";
 const DEFAULT_HISTORY_LIMIT: usize = 100;
+const HISTORY_SCAN_CHUNK: isize = 50;
+/// Entries allowed past the cap before they are moved to the archive in one chunk
+const ARCHIVE_BATCH: usize = 50;
+
+const TRASH_INDEX_KEY: &str = "perth:trash:index";
+
+/// Prefix of `perth:snapshot-ids:{uuid}`, which holds the snapshot's key
+const SNAPSHOT_ID_PREFIX: &str = "perth:snapshot-ids:";
+/// Where the pointers used to live. It shares `perth:snapshots:{session}:`
+/// with a session named `by-id`, so pointers are moved out as they are read.
+const LEGACY_SNAPSHOT_ID_PREFIX: &str = "perth:snapshots:by-id:";
+
+/// Sorted set of tabs awaiting archive, scored by due time (unix seconds)
+const TAB_ARCHIVE_QUEUE_KEY: &str = "perth:archive:pending";
+
+/// Hash of cumulative LLM usage; fields are `{month}|{provider}|{model}|{metric}`
+const LLM_USAGE_KEY: &str = "perth:llm:usage";
+
+/// List of serialized Bloodbank events that could not be published, oldest first
+const EVENT_OUTBOX_KEY: &str = "perth:events:outbox";
+
+/// Events kept in the outbox; the oldest are dropped beyond this
+pub const EVENT_OUTBOX_LIMIT: isize = 10_000;
+
+/// Pop the head of a list (KEYS[1]) only if it is still ARGV[1], so two
+/// processes flushing the outbox never remove each other's events.
+const POP_IF_HEAD_SCRIPT: &str = r#"
+if redis.call('LINDEX', KEYS[1], 0) == ARGV[1] then
+    redis.call('LPOP', KEYS[1])
+    return 1
+end
+return 0
+"#;
+
+/// Atomically removes and returns entries past the cap (ARGV[1]) from a history list.
+const TAKE_OVERFLOW_SCRIPT: &str = r#"
+local overflow = redis.call('LRANGE', KEYS[1], ARGV[1], -1)
+if #overflow > 0 then
+    redis.call('LTRIM', KEYS[1], 0, ARGV[1] - 1)
+end
+return overflow
+"#;
+
+/// Replace one history element, but only if it still has the exact
+/// serialized form the caller read (compare-and-set on a list element).
+const REPLACE_ENTRY_SCRIPT: &str = r#"
+local entries = redis.call('LRANGE', KEYS[1], 0, -1)
+for i, json in ipairs(entries) do
+    if json == ARGV[1] then
+        redis.call('LSET', KEYS[1], i - 1, ARGV[2])
+        return 1
+    end
+end
+return 0
+"#;
+
+/// Attempts at rewriting an entry before giving up on concurrent changes
+const UPDATE_ENTRY_ATTEMPTS: usize = 5;
+
+/// Replace a string value, keeping its expiry, but only if it is still
+/// the one the caller read.
+const REPLACE_VALUE_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    redis.call('SET', KEYS[1], ARGV[2], 'KEEPTTL')
+    return 1
+end
+return 0
+"#;
+
+/// Replace a hash field, but only if it is still the value the caller read.
+const REPLACE_FIELD_SCRIPT: &str = r#"
+if redis.call('HGET', KEYS[1], ARGV[1]) == ARGV[2] then
+    redis.call('HSET', KEYS[1], ARGV[1], ARGV[3])
+    return 1
+end
+return 0
+"#;
+
+/// Rename trashed keys (KEYS[3..2+n]) back to their original names
+/// (KEYS[3+n..]) and drop the manifest (KEYS[1]) and its index entry
+/// (KEYS[2], member ARGV[1]). Restores nothing and returns the 1-based
+/// position of the first original name already in use, if any.
+const RESTORE_KEYS_SCRIPT: &str = r#"
+local n = (#KEYS - 2) / 2
+for i = 1, n do
+    if redis.call('EXISTS', KEYS[2 + n + i]) == 1 then
+        return i
+    end
+end
+for i = 1, n do
+    redis.call('RENAME', KEYS[2 + i], KEYS[2 + n + i])
+    redis.call('PERSIST', KEYS[2 + n + i])
+end
+redis.call('DEL', KEYS[1])
+redis.call('ZREM', KEYS[2], ARGV[1])
+return 0
+"#;
+
+/// Replace a history list (KEYS[1]) with a merged one, but only if it still
+/// holds exactly the ARGV[2] entries the caller read (ARGV[3..2+n]); the merged
+/// list follows them. Also drops the trashed entries (KEYS[2]), the manifest
+/// (KEYS[3]) and its index entry (KEYS[4], member ARGV[1]). Returns -1 if the
+/// trashed entries are gone.
+const RESTORE_ENTRIES_SCRIPT: &str = r#"
+if redis.call('EXISTS', KEYS[2]) == 0 then
+    return -1
+end
+local n = tonumber(ARGV[2])
+local live = redis.call('LRANGE', KEYS[1], 0, -1)
+if #live ~= n then
+    return 0
+end
+for i = 1, n do
+    if live[i] ~= ARGV[2 + i] then
+        return 0
+    end
+end
+redis.call('DEL', KEYS[1])
+for i = n + 3, #ARGV do
+    redis.call('RPUSH', KEYS[1], ARGV[i])
+end
+redis.call('DEL', KEYS[2], KEYS[3])
+redis.call('ZREM', KEYS[4], ARGV[1])
+return 1
+"#;
+
+/// SCAN cursor an interrupted `state rekey` resumes from
+const REKEY_CURSOR_KEY: &str = "perth:rekey:cursor";
 
 pub struct StateManager {
+    client: redis::Client,
     conn: MultiplexedConnection,
+    /// Expiry applied to pane hashes and history lists (None = keep forever)
+    pane_ttl_secs: Option<i64>,
+    /// Archive entries evicted by the history cap instead of dropping them
+    archive_history: bool,
+    /// Grace period for trashed items (None = delete immediately)
+    trash_ttl_secs: Option<i64>,
+    /// Key history entries and snapshots are sealed with (None = plain JSON)
+    cipher: Option<Cipher>,
 }
 
 impl StateManager {
+    /// Connect to Redis at `redis_url`. Panes never expire and evicted
+    /// history is dropped unless the `with_*` builders say otherwise.
     pub async fn new(redis_url: &str) -> Result<Self> {
         let client =
-            redis::Client::open(redis_url).context("failed to create redis client")?;
+            redis::Client::open(redis_url).context(PerthError::StorageError("failed to create redis client".to_string()))?;
         let conn = client
             .get_multiplexed_tokio_connection()
             .await
-            .context("failed to connect to redis")?;
-        Ok(Self { conn })
+            .context(PerthError::StorageError("failed to connect to redis".to_string()))?;
+        Ok(Self {
+            client,
+            conn,
+            pane_ttl_secs: None,
+            archive_history: false,
+            trash_ttl_secs: None,
+            cipher: None,
+        })
+    }
+
+    /// Builder method to expire pane records after `days` without activity.
+    /// `None` or `Some(0)` keeps pane records forever.
+    pub fn with_pane_ttl_days(mut self, days: Option<u64>) -> Self {
+        self.pane_ttl_secs = days
+            .filter(|d| *d > 0)
+            .map(|d| (d * 24 * 60 * 60) as i64);
+        self
+    }
+
+    /// Builder method to move entries beyond the history cap into a
+    /// compressed archive list rather than discarding them.
+    pub fn with_history_archive(mut self, enabled: bool) -> Self {
+        self.archive_history = enabled;
+        self
+    }
+
+    /// Builder method to move deleted data to the trash for `days` before it
+    /// is purged. `None` or `Some(0)` deletes immediately.
+    pub fn with_trash_ttl_days(mut self, days: Option<u64>) -> Self {
+        self.trash_ttl_secs = days
+            .filter(|d| *d > 0)
+            .map(|d| (d * 24 * 60 * 60) as i64);
+        self
+    }
+
+    /// Builder method to seal history entries and snapshots with `cipher`
+    /// before they are written. Sealed values are opened on read either way,
+    /// as long as the key is given.
+    pub fn with_encryption(mut self, cipher: Option<Cipher>) -> Self {
+        self.cipher = cipher;
+        self
+    }
+
+    /// A stored history entry, opened if it is sealed.
+    fn decode_entry(&self, stored: &str) -> Result<IntentEntry> {
+        Ok(serde_json::from_str(&crypto::open(self.cipher.as_ref(), stored)?)?)
+    }
+
+    /// A stored snapshot, opened if it is sealed.
+    fn decode_snapshot(&self, stored: &str) -> Result<crate::types::SessionSnapshot> {
+        Ok(serde_json::from_str(&crypto::open(self.cipher.as_ref(), stored)?)?)
     }
 
     pub fn now_string() -> String {
@@ -29,6 +228,7 @@ impl StateManager {
     }
 
     pub async fn get_pane(&mut self, pane_name: &str) -> Result<Option<PaneRecord>> {
+        let _timer = instrument("get_pane");
         let key = pane_key(pane_name);
         let map: HashMap<String, String> = self.conn.hgetall(&key).await?;
         if map.is_empty() {
@@ -75,6 +275,7 @@ impl StateManager {
     }
 
     pub async fn upsert_pane(&mut self, record: &PaneRecord) -> Result<()> {
+        let _timer = instrument("upsert_pane");
         let key = pane_key(&record.pane_name);
         let mut fields: Vec<(String, String)> = Vec::new();
         fields.push(("session".to_string(), record.session.clone()));
@@ -96,10 +297,11 @@ impl StateManager {
         }
 
         let _: () = self.conn.hset_multiple(key, &fields).await?;
-        Ok(())
+        self.refresh_ttl(&record.pane_name).await
     }
 
     pub async fn touch_pane(&mut self, pane_name: &str, meta_updates: &HashMap<String, String>) -> Result<()> {
+        let _timer = instrument("touch_pane");
         let key = pane_key(pane_name);
         let now = Self::now_string();
         let mut fields: Vec<(String, String)> = vec![
@@ -113,7 +315,7 @@ impl StateManager {
         }
 
         let _: () = self.conn.hset_multiple(key, &fields).await?;
-        Ok(())
+        self.refresh_ttl(pane_name).await
     }
 
     pub async fn mark_seen(&mut self, pane_name: &str) -> Result<()> {
@@ -124,6 +326,58 @@ impl StateManager {
             ("stale".to_string(), "false".to_string()),
         ];
         let _: () = self.conn.hset_multiple(key, &fields).await?;
+        self.refresh_ttl(pane_name).await
+    }
+
+    /// Update last_accessed on several panes in one pipelined round trip,
+    /// refreshing their TTLs. Staleness is left alone: a pane counts as seen
+    /// only once reconcile finds it in Zellij.
+    pub async fn touch_panes(&mut self, pane_names: &[String]) -> Result<()> {
+        if pane_names.is_empty() {
+            return Ok(());
+        }
+
+        let now = Self::now_string();
+        let mut pipe = redis::pipe();
+        for pane_name in pane_names {
+            pipe.hset(pane_key(pane_name), "last_accessed", &now).ignore();
+            if let Some(ttl) = self.pane_ttl_secs {
+                pipe.expire(pane_key(pane_name), ttl).ignore()
+                    .expire(history_key(pane_name), ttl).ignore()
+                    .expire(archive_key(pane_name), ttl).ignore()
+                    .expire(annotations_key(pane_name), ttl).ignore()
+                    .expire(goals_key(pane_name), ttl).ignore();
+            }
+        }
+        let _: () = pipe
+            .query_async(&mut self.conn)
+            .await
+            .context("failed to touch panes")?;
+        Ok(())
+    }
+
+    /// Reset the expiry on every key of a pane, if a TTL is configured.
+    async fn refresh_ttl(&mut self, pane_name: &str) -> Result<()> {
+        if let Some(ttl) = self.pane_ttl_secs {
+            let _: () = self.conn.expire(pane_key(pane_name), ttl).await?;
+            let _: () = self.conn.expire(history_key(pane_name), ttl).await?;
+            let _: () = self.conn.expire(archive_key(pane_name), ttl).await?;
+            let _: () = self.conn.expire(annotations_key(pane_name), ttl).await?;
+            let _: () = self.conn.expire(goals_key(pane_name), ttl).await?;
+        }
+        Ok(())
+    }
+
+    /// Record the Zellij pane ID of a pane without touching access times.
+    pub async fn set_pane_id(&mut self, pane_name: &str, pane_id: &str) -> Result<()> {
+        let _: () = self.conn.hset(pane_key(pane_name), "pane_id", pane_id).await?;
+        Ok(())
+    }
+
+    /// Set a single metadata field on a pane without touching access times.
+    pub async fn set_pane_meta(&mut self, pane_name: &str, key: &str, value: &str) -> Result<()> {
+        let key_name = format!("{}{}", META_PREFIX, key);
+        let _: () = self.conn.hset(pane_key(pane_name), key_name, value).await?;
         Ok(())
     }
 
@@ -144,7 +398,107 @@ impl StateManager {
         Ok(names)
     }
 
+    /// List keys matching `pattern` with their Redis types, sorted by key.
+    pub async fn scan_keys(&mut self, pattern: &str) -> Result<Vec<(String, String)>> {
+        let mut keys = Vec::new();
+        {
+            let mut iter: AsyncIter<String> = self.conn.scan_match(pattern).await?;
+            while let Some(key) = iter.next_item().await {
+                keys.push(key);
+            }
+        }
+        keys.sort();
+
+        let mut typed = Vec::with_capacity(keys.len());
+        for key in keys {
+            let key_type: String = redis::cmd("TYPE").arg(&key).query_async(&mut self.conn).await?;
+            typed.push((key, key_type));
+        }
+        Ok(typed)
+    }
+
+    /// Count Perth keys by category and estimate their memory use.
+    ///
+    /// Memory comes from `MEMORY USAGE` on up to `sample` keys per category,
+    /// extrapolated to the full count. Histories are ranked by length and the
+    /// `top` longest are measured individually.
+    pub async fn keyspace_stats(&mut self, sample: usize, top: usize) -> Result<KeyspaceStats> {
+        let mut keys = Vec::new();
+        for pattern in ["znav:*", "perth:*"] {
+            let mut iter: AsyncIter<String> = self.conn.scan_match(pattern).await?;
+            while let Some(key) = iter.next_item().await {
+                keys.push(key);
+            }
+        }
+        keys.sort();
+
+        let mut stats = KeyspaceStats {
+            memory_usage_available: true,
+            ..Default::default()
+        };
+        for category in KEY_CATEGORIES {
+            let members: Vec<&String> = keys.iter().filter(|k| key_category(k) == category).collect();
+            let mut group = KeyGroupStats {
+                category,
+                keys: members.len(),
+                ..Default::default()
+            };
+
+            if sample > 0 && !members.is_empty() && stats.memory_usage_available {
+                // Spread the sample evenly across the sorted keys
+                let step = members.len().div_ceil(sample);
+                let mut measured = 0u64;
+                for key in members.iter().step_by(step) {
+                    match self.memory_usage(key).await {
+                        Some(bytes) => measured += bytes,
+                        None => {
+                            stats.memory_usage_available = false;
+                            break;
+                        }
+                    }
+                    group.sampled += 1;
+                }
+                if stats.memory_usage_available && group.sampled > 0 {
+                    group.bytes = measured * group.keys as u64 / group.sampled as u64;
+                }
+            }
+            stats.groups.push(group);
+        }
+
+        let mut histories = Vec::new();
+        for key in keys.iter().filter(|k| key_category(k) == "histories") {
+            let entries: usize = self.conn.llen(key).await?;
+            let pane = key
+                .strip_prefix("perth:pane:")
+                .and_then(|k| k.strip_suffix(":history"))
+                .unwrap_or(key)
+                .to_string();
+            histories.push(HistorySize { pane, entries, bytes: 0 });
+        }
+        histories.sort_by(|a, b| b.entries.cmp(&a.entries).then_with(|| a.pane.cmp(&b.pane)));
+        histories.truncate(top);
+        if stats.memory_usage_available {
+            for history in &mut histories {
+                history.bytes = self.memory_usage(&history_key(&history.pane)).await.unwrap_or(0);
+            }
+        }
+        stats.largest_histories = histories;
+
+        Ok(stats)
+    }
+
+    /// Approximate bytes used by `key`, or `None` if `MEMORY USAGE` is unavailable.
+    async fn memory_usage(&mut self, key: &str) -> Option<u64> {
+        let bytes: redis::RedisResult<Option<u64>> = redis::cmd("MEMORY")
+            .arg("USAGE")
+            .arg(key)
+            .query_async(&mut self.conn)
+            .await;
+        bytes.ok().map(|bytes| bytes.unwrap_or(0))
+    }
+
     pub async fn list_all_panes(&mut self) -> Result<Vec<PaneRecord>> {
+        let _timer = instrument("list_all_panes");
         let names = self.list_pane_names().await?;
         let mut panes = Vec::new();
         for name in names {
@@ -163,30 +517,223 @@ impl StateManager {
     /// - LPUSH to history list (newest first)
     /// - Update last_intent on pane hash
     /// - LTRIM to maintain max entries
+    /// - PUBLISH the entry for `follow_history` subscribers
+    ///
+    /// All writes run in a single MULTI/EXEC transaction so the history list and
+    /// the pane's last_intent fields never disagree, even with concurrent writers.
+    ///
+    /// With archiving enabled the list may grow `ARCHIVE_BATCH` past the cap,
+    /// at which point the overflow is compressed into the archive as one chunk.
     pub async fn log_intent(&mut self, pane_name: &str, entry: &IntentEntry) -> Result<()> {
+        let _timer = instrument("log_intent");
         let history_key = history_key(pane_name);
         let pane_key = pane_key(pane_name);
 
         // Serialize entry to JSON
         let json = serde_json::to_string(entry)
             .context("failed to serialize IntentEntry")?;
+        let json = crypto::seal(self.cipher.as_ref(), json);
+
+        let last_intent_fields = [
+            ("last_intent", crypto::seal(self.cipher.as_ref(), entry.summary.clone())),
+            ("last_intent_at", entry.timestamp.to_rfc3339()),
+        ];
+
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            // LPUSH to add newest entry at head of list
+            .lpush(&history_key, &json)
+            // Update last_intent summary on pane hash for quick access
+            .hset_multiple(&pane_key, &last_intent_fields).ignore()
+            // Notify anyone following this pane's history
+            .publish(history_channel(pane_name), &json).ignore();
+
+        // Reverse index from each artifact to the entries that mention it
+        for artifact in &entry.artifacts {
+            pipe.zadd(artifact_index_key(artifact), artifact_member(pane_name, &entry.id), entry.timestamp.timestamp_millis())
+                .ignore();
+        }
+
+        if !self.archive_history {
+            // LTRIM to maintain max entries (keep indices 0 to LIMIT-1)
+            pipe.ltrim(&history_key, 0, (DEFAULT_HISTORY_LIMIT - 1) as isize).ignore();
+        }
+
+        if let Some(ttl) = self.pane_ttl_secs {
+            pipe.expire(&pane_key, ttl).ignore()
+                .expire(&history_key, ttl).ignore()
+                .expire(archive_key(pane_name), ttl).ignore()
+                .expire(annotations_key(pane_name), ttl).ignore()
+                .expire(goals_key(pane_name), ttl).ignore();
+        }
+
+        let (length,): (usize,) = pipe
+            .query_async(&mut self.conn)
+            .await
+            .context("failed to log intent")?;
 
-        // LPUSH to add newest entry at head of list
-        let _: () = self.conn.lpush(&history_key, &json).await?;
+        if self.archive_history && length >= DEFAULT_HISTORY_LIMIT + ARCHIVE_BATCH {
+            self.archive_overflow(pane_name).await?;
+        }
 
-        // Update last_intent summary on pane hash for quick access
-        let _: () = self.conn.hset(&pane_key, "last_intent", &entry.summary).await?;
-        let _: () = self.conn.hset(&pane_key, "last_intent_at", entry.timestamp.to_rfc3339()).await?;
+        Ok(())
+    }
 
-        // LTRIM to maintain max entries (keep indices 0 to LIMIT-1)
-        let _: () = self.conn.ltrim(&history_key, 0, (DEFAULT_HISTORY_LIMIT - 1) as isize).await?;
+    /// Rewrite a live history entry in place, keeping its position.
+    ///
+    /// `update` edits the entry and returns whether anything changed. The
+    /// element is swapped atomically by a Lua script only if no one else has
+    /// changed it since it was read; otherwise the update is re-applied to the
+    /// fresh entry. Returns the updated entry, or None when `id` is not in
+    /// the live history (archived entries are read-only).
+    pub async fn update_entry<F>(&mut self, pane_name: &str, id: uuid::Uuid, mut update: F) -> Result<Option<IntentEntry>>
+    where
+        F: FnMut(&mut IntentEntry) -> bool,
+    {
+        let history_key = history_key(pane_name);
+        for _ in 0..UPDATE_ENTRY_ATTEMPTS {
+            let live: Vec<String> = self.conn.lrange(&history_key, 0, -1).await?;
+            let found = live.into_iter().find_map(|json| {
+                let entry = self.decode_entry(&json).ok()?;
+                (entry.id == id).then_some((json, entry))
+            });
+            let Some((old_json, mut entry)) = found else {
+                return Ok(None);
+            };
+
+            if !update(&mut entry) {
+                return Ok(Some(entry));
+            }
+            let new_json = serde_json::to_string(&entry).context("failed to serialize IntentEntry")?;
+            let new_json = crypto::seal(self.cipher.as_ref(), new_json);
+            let replaced: i32 = redis::Script::new(REPLACE_ENTRY_SCRIPT)
+                .key(&history_key)
+                .arg(&old_json)
+                .arg(&new_json)
+                .invoke_async(&mut self.conn)
+                .await
+                .context("failed to update history entry")?;
+            if replaced == 1 {
+                self.index_artifacts(pane_name, std::slice::from_ref(&entry)).await?;
+                return Ok(Some(entry));
+            }
+        }
+        Err(PerthError::StaleState(format!("history entry {} kept changing; try again", id)).into())
+    }
+
+    /// Add entries' artifacts to the artifact index. Entries already indexed
+    /// are left as they are. Returns how many (artifact, entry) pairs were sent.
+    pub async fn index_artifacts(&mut self, pane_name: &str, entries: &[IntentEntry]) -> Result<usize> {
+        let mut pipe = redis::pipe();
+        let mut count = 0;
+        for entry in entries {
+            for artifact in &entry.artifacts {
+                pipe.zadd(artifact_index_key(artifact), artifact_member(pane_name, &entry.id), entry.timestamp.timestamp_millis())
+                    .ignore();
+                count += 1;
+            }
+        }
+        if count > 0 {
+            let _: () = pipe.query_async(&mut self.conn).await.context("failed to index artifacts")?;
+        }
+        Ok(count)
+    }
+
+    /// Entries that list `artifact`, newest first, as (pane name, entry ID).
+    ///
+    /// The index is not cleaned when history is pruned or deleted, so callers
+    /// should drop (and [`unindex_artifact`](Self::unindex_artifact)) entries
+    /// they can no longer find.
+    pub async fn artifact_touches(&mut self, artifact: &str) -> Result<Vec<(String, uuid::Uuid)>> {
+        let members: Vec<String> = self.conn.zrevrange(artifact_index_key(artifact), 0, -1).await?;
+        Ok(members
+            .iter()
+            .filter_map(|member| {
+                let (id, pane_name) = member.split_once(':')?;
+                Some((pane_name.to_string(), uuid::Uuid::parse_str(id).ok()?))
+            })
+            .collect())
+    }
 
+    /// Remove one entry from an artifact's index.
+    pub async fn unindex_artifact(&mut self, artifact: &str, pane_name: &str, id: &uuid::Uuid) -> Result<()> {
+        let _: () = self.conn.zrem(artifact_index_key(artifact), artifact_member(pane_name, id)).await?;
         Ok(())
     }
 
+    /// Entries logged to a pane from now on, as they are logged.
+    ///
+    /// Uses a dedicated pub/sub connection; entries logged before the
+    /// subscription is established are not delivered.
+    pub async fn follow_history(&self, pane_name: &str) -> Result<impl Stream<Item = Result<IntentEntry>> + 'static> {
+        let mut pubsub = self
+            .client
+            .get_async_pubsub()
+            .await
+            .context("failed to open pub/sub connection")?;
+        pubsub
+            .subscribe(history_channel(pane_name))
+            .await
+            .context("failed to subscribe to history updates")?;
+
+        let cipher = self.cipher.clone();
+        Ok(pubsub.into_on_message().map(move |msg| -> Result<IntentEntry> {
+            let json: String = msg.get_payload()?;
+            let json = crypto::open(cipher.as_ref(), &json)?;
+            serde_json::from_str(&json).context("failed to deserialize IntentEntry from history update")
+        }))
+    }
+
+    /// Move entries beyond the history cap into the pane's archive.
+    /// Returns the number of entries archived.
+    ///
+    /// The overflow is removed from the history list atomically and stored as
+    /// a single gzip-compressed JSON chunk, pushed to the head of the archive
+    /// list so chunks stay newest-first.
+    pub async fn archive_overflow(&mut self, pane_name: &str) -> Result<usize> {
+        let overflow: Vec<String> = redis::Script::new(TAKE_OVERFLOW_SCRIPT)
+            .key(history_key(pane_name))
+            .arg(DEFAULT_HISTORY_LIMIT)
+            .invoke_async(&mut self.conn)
+            .await
+            .context("failed to trim history overflow")?;
+        if overflow.is_empty() {
+            return Ok(0);
+        }
+
+        let chunk = compress_chunk(&overflow)?;
+        let archive_key = archive_key(pane_name);
+        let _: () = self
+            .conn
+            .lpush(&archive_key, chunk)
+            .await
+            .context("failed to write history archive")?;
+        if let Some(ttl) = self.pane_ttl_secs {
+            let _: () = self.conn.expire(&archive_key, ttl).await?;
+        }
+
+        Ok(overflow.len())
+    }
+
+    /// Read every archived entry for a pane, newest first.
+    pub async fn get_archived_history(&mut self, pane_name: &str) -> Result<Vec<IntentEntry>> {
+        let chunks: Vec<Vec<u8>> = self.conn.lrange(archive_key(pane_name), 0, -1).await?;
+
+        let mut history = Vec::new();
+        for chunk in chunks {
+            for json in decompress_chunk(&chunk)? {
+                let entry: IntentEntry = self.decode_entry(&json)
+                    .context("failed to deserialize IntentEntry from archive")?;
+                history.push(entry);
+            }
+        }
+        Ok(history)
+    }
+
     /// Get intent history for a pane.
     /// Returns entries newest-first, up to the specified limit.
     pub async fn get_history(&mut self, pane_name: &str, limit: Option<usize>) -> Result<Vec<IntentEntry>> {
+        let _timer = instrument("get_history");
         let history_key = history_key(pane_name);
         let limit = limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
 
@@ -195,7 +742,7 @@ impl StateManager {
 
         let mut history = Vec::with_capacity(entries.len());
         for json in entries {
-            let entry: IntentEntry = serde_json::from_str(&json)
+            let entry: IntentEntry = self.decode_entry(&json)
                 .context("failed to deserialize IntentEntry from history")?;
             history.push(entry);
         }
@@ -203,6 +750,97 @@ impl StateManager {
         Ok(history)
     }
 
+    /// Get a page of intent history for a pane, newest first.
+    ///
+    /// Without a time range or tag this is a single LRANGE. Otherwise the
+    /// list is walked in chunks from the head, stopping as soon as entries are
+    /// older than `since`, so only the requested window is deserialized.
+    pub async fn query_history(&mut self, pane_name: &str, query: &HistoryQuery) -> Result<Vec<IntentEntry>> {
+        let _timer = instrument("query_history");
+        let history_key = history_key(pane_name);
+        let limit = query.limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        if query.include_archived {
+            let live: Vec<String> = self.conn.lrange(&history_key, 0, -1).await?;
+            let mut entries = Vec::with_capacity(live.len());
+            for json in live {
+                let entry: IntentEntry = self.decode_entry(&json)
+                    .context("failed to deserialize IntentEntry from history")?;
+                entries.push(entry);
+            }
+            entries.extend(self.get_archived_history(pane_name).await?);
+
+            return Ok(entries
+                .into_iter()
+                .filter(|entry| query.until.is_none_or(|until| entry.timestamp <= until))
+                .take_while(|entry| query.since.is_none_or(|since| entry.timestamp >= since))
+                .filter(|entry| query.tag.as_ref().is_none_or(|tag| entry.has_tag(tag)))
+                .skip(query.offset)
+                .take(limit)
+                .collect());
+        }
+
+        if query.since.is_none() && query.until.is_none() && query.tag.is_none() {
+            let start = query.offset as isize;
+            // Saturate so `limit: Some(usize::MAX)` means the whole list
+            let stop = start.saturating_add(limit.min(isize::MAX as usize) as isize - 1);
+            let entries: Vec<String> = self
+                .conn
+                .lrange(&history_key, start, stop)
+                .await?;
+            return entries
+                .iter()
+                .map(|json| {
+                    self.decode_entry(json)
+                        .context("failed to deserialize IntentEntry from history")
+                })
+                .collect();
+        }
+
+        let mut history = Vec::new();
+        let mut skipped = 0;
+        let mut start = 0;
+
+        loop {
+            let chunk: Vec<String> = self
+                .conn
+                .lrange(&history_key, start, start + HISTORY_SCAN_CHUNK - 1)
+                .await?;
+            if chunk.is_empty() {
+                return Ok(history);
+            }
+
+            for json in chunk {
+                let entry: IntentEntry = self.decode_entry(&json)
+                    .context("failed to deserialize IntentEntry from history")?;
+
+                if query.until.is_some_and(|until| entry.timestamp > until) {
+                    continue;
+                }
+                if query.since.is_some_and(|since| entry.timestamp < since) {
+                    return Ok(history);
+                }
+                if query.tag.as_ref().is_some_and(|tag| !entry.has_tag(tag)) {
+                    continue;
+                }
+                if skipped < query.offset {
+                    skipped += 1;
+                    continue;
+                }
+
+                history.push(entry);
+                if history.len() >= limit {
+                    return Ok(history);
+                }
+            }
+
+            start += HISTORY_SCAN_CHUNK;
+        }
+    }
+
     /// Get the count of history entries for a pane.
     pub async fn get_history_count(&mut self, pane_name: &str) -> Result<usize> {
         let history_key = history_key(pane_name);
@@ -210,19 +848,222 @@ impl StateManager {
         Ok(count)
     }
 
-    /// Clear all history for a pane.
-    pub async fn clear_history(&mut self, pane_name: &str) -> Result<()> {
+    /// Attach an annotation to an entry in a pane's history.
+    pub async fn add_annotation(&mut self, pane_name: &str, annotation: &Annotation) -> Result<()> {
+        let json = serde_json::to_string(annotation).context("failed to serialize Annotation")?;
+        let _: () = self.conn.rpush(annotations_key(pane_name), json).await?;
+        self.refresh_ttl(pane_name).await
+    }
+
+    /// All annotations for a pane, oldest first.
+    pub async fn get_annotations(&mut self, pane_name: &str) -> Result<Vec<Annotation>> {
+        let entries: Vec<String> = self.conn.lrange(annotations_key(pane_name), 0, -1).await?;
+        entries
+            .iter()
+            .map(|json| serde_json::from_str(json).context("failed to deserialize Annotation"))
+            .collect()
+    }
+
+    /// Store a pane goal, replacing any stored goal with the same ID.
+    pub async fn save_goal(&mut self, pane_name: &str, goal: &Goal) -> Result<()> {
+        let json = serde_json::to_string(goal).context("failed to serialize Goal")?;
+        let _: () = self.conn.hset(goals_key(pane_name), goal.id.to_string(), json).await?;
+        self.refresh_ttl(pane_name).await
+    }
+
+    /// All goals for a pane, oldest first.
+    pub async fn get_goals(&mut self, pane_name: &str) -> Result<Vec<Goal>> {
+        let stored: HashMap<String, String> = self.conn.hgetall(goals_key(pane_name)).await?;
+        let mut goals = stored
+            .values()
+            .map(|json| serde_json::from_str(json).context("failed to deserialize Goal"))
+            .collect::<Result<Vec<Goal>>>()?;
+        goals.sort_by_key(|goal| goal.set_at);
+        Ok(goals)
+    }
+
+    /// Count a shell command run in a pane, for the shell hook. Returns the
+    /// number of buffered commands and when the first of them ran.
+    pub async fn buffer_hook_command(&mut self, pane_name: &str) -> Result<(usize, DateTime<Utc>)> {
+        let key = hook_key(pane_name);
+        let (count, since): (usize, String) = redis::pipe()
+            .atomic()
+            .hset_nx(&key, "since", Self::now_string()).ignore()
+            .hincr(&key, "commands", 1)
+            .hget(&key, "since")
+            .query_async(&mut self.conn)
+            .await
+            .context("failed to buffer hook command")?;
+        let since = DateTime::parse_from_rfc3339(&since)
+            .map(|ts| ts.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now());
+        Ok((count, since))
+    }
+
+    /// Clear a pane's buffered hook commands. Returns false if another call
+    /// already took them, so concurrent hooks log a single checkpoint.
+    pub async fn take_hook_buffer(&mut self, pane_name: &str) -> Result<bool> {
+        let removed: usize = self.conn.del(hook_key(pane_name)).await?;
+        Ok(removed > 0)
+    }
+
+    /// Add an LLM request's tokens and estimated cost to its monthly totals.
+    pub async fn record_llm_usage(&mut self, usage: &LlmUsage) -> Result<()> {
+        let prefix = format!("{}|{}|{}", usage.month, usage.provider, usage.model);
+        let field = |metric: &str| format!("{}|{}", prefix, metric);
+
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .hincr(LLM_USAGE_KEY, field("requests"), usage.requests).ignore()
+            .hincr(LLM_USAGE_KEY, field("input_tokens"), usage.input_tokens).ignore()
+            .hincr(LLM_USAGE_KEY, field("output_tokens"), usage.output_tokens).ignore();
+        // Costs are kept in micro-dollars so totals stay exact integers
+        if let Some(cost) = usage.cost_usd {
+            pipe.hincr(LLM_USAGE_KEY, field("cost_micros"), (cost * 1_000_000.0).round() as i64).ignore();
+        }
+        let _: () = pipe
+            .query_async(&mut self.conn)
+            .await
+            .context("failed to record LLM usage")?;
+        Ok(())
+    }
+
+    /// Monthly LLM usage per provider and model, optionally for one `YYYY-MM`
+    /// month, sorted by month then provider and model.
+    pub async fn get_llm_usage(&mut self, month: Option<&str>) -> Result<Vec<LlmUsage>> {
+        let fields: HashMap<String, i64> = self.conn.hgetall(LLM_USAGE_KEY).await?;
+
+        let mut totals: std::collections::BTreeMap<(String, String, String), LlmUsage> = Default::default();
+        for (field, value) in fields {
+            let Some((prefix, metric)) = field.rsplit_once('|') else {
+                continue;
+            };
+            let mut parts = prefix.splitn(3, '|');
+            let (Some(m), Some(provider), Some(model)) = (parts.next(), parts.next(), parts.next()) else {
+                continue;
+            };
+            if month.is_some_and(|wanted| wanted != m) {
+                continue;
+            }
+
+            let usage = totals
+                .entry((m.to_string(), provider.to_string(), model.to_string()))
+                .or_insert_with(|| LlmUsage {
+                    month: m.to_string(),
+                    provider: provider.to_string(),
+                    model: model.to_string(),
+                    ..Default::default()
+                });
+            let value = value.max(0) as u64;
+            match metric {
+                "requests" => usage.requests = value,
+                "input_tokens" => usage.input_tokens = value,
+                "output_tokens" => usage.output_tokens = value,
+                "cost_micros" => usage.cost_usd = Some(value as f64 / 1_000_000.0),
+                _ => {}
+            }
+        }
+
+        Ok(totals.into_values().collect())
+    }
+
+    /// Remove history entries (live and archived) for which `expired` returns true.
+    /// Returns the number of entries removed, or that would be removed when `dry_run`.
+    ///
+    /// Live entries are removed with LREM on their exact serialized form, so
+    /// entries logged concurrently are never lost.
+    pub async fn prune_history<F>(&mut self, pane_name: &str, dry_run: bool, mut expired: F) -> Result<usize>
+    where
+        F: FnMut(&IntentEntry) -> bool,
+    {
         let history_key = history_key(pane_name);
-        let _: () = self.conn.del(&history_key).await?;
+        let live: Vec<String> = self.conn.lrange(&history_key, 0, -1).await?;
+
+        let mut removed = Vec::new();
+        for json in live {
+            let entry: IntentEntry = self.decode_entry(&json)
+                .context("failed to deserialize IntentEntry from history")?;
+            if expired(&entry) {
+                if !dry_run {
+                    let _: () = self.conn.lrem(&history_key, 1, &json).await?;
+                }
+                removed.push(json);
+            }
+        }
+
+        let archive_key = archive_key(pane_name);
+        let chunks: Vec<Vec<u8>> = self.conn.lrange(&archive_key, 0, -1).await?;
+        let mut kept_chunks = Vec::with_capacity(chunks.len());
+        let mut archive_removed = 0;
+        for chunk in &chunks {
+            let mut kept = Vec::new();
+            for json in decompress_chunk(chunk)? {
+                let entry: IntentEntry = self.decode_entry(&json)
+                    .context("failed to deserialize IntentEntry from archive")?;
+                if expired(&entry) {
+                    archive_removed += 1;
+                    removed.push(json);
+                } else {
+                    kept.push(json);
+                }
+            }
+            if !kept.is_empty() {
+                kept_chunks.push(compress_chunk(&kept)?);
+            }
+        }
+
+        if archive_removed > 0 && !dry_run {
+            let mut pipe = redis::pipe();
+            pipe.atomic().del(&archive_key).ignore();
+            if !kept_chunks.is_empty() {
+                pipe.rpush(&archive_key, kept_chunks).ignore();
+                if let Some(ttl) = self.pane_ttl_secs {
+                    pipe.expire(&archive_key, ttl).ignore();
+                }
+            }
+            let _: () = pipe
+                .query_async(&mut self.conn)
+                .await
+                .context("failed to rewrite history archive")?;
+        }
+
+        let removed_count = removed.len();
+        if !dry_run && removed_count > 0 {
+            self.trash_entries(pane_name, removed).await?;
+        }
+
+        Ok(removed_count)
+    }
+
+    /// Delete a pane record with its history, archive, annotations and goals.
+    /// Everything is moved to the trash when a grace period is configured.
+    pub async fn delete_pane(&mut self, pane_name: &str) -> Result<()> {
+        let keys = [
+            pane_key(pane_name),
+            history_key(pane_name),
+            archive_key(pane_name),
+            annotations_key(pane_name),
+            goals_key(pane_name),
+        ];
+        self.trash_keys("pane", pane_name, &keys).await.context("failed to delete pane")?;
+        let _: () = self.conn.del(hook_key(pane_name)).await?;
         Ok(())
     }
 
+    /// Clear all history for a pane, including any archived entries.
+    /// The history is moved to the trash when a grace period is configured.
+    pub async fn clear_history(&mut self, pane_name: &str) -> Result<()> {
+        let keys = [history_key(pane_name), archive_key(pane_name), annotations_key(pane_name)];
+        self.trash_keys("history", pane_name, &keys).await
+    }
+
     // ========================================================================
     // Tab Storage Methods (STORY-036)
     // ========================================================================
 
     /// Get a tab record by name.
     pub async fn get_tab(&mut self, tab_name: &str, session: &str) -> Result<Option<TabRecord>> {
+        let _timer = instrument("get_tab");
         let key = tab_key(tab_name, session);
         let map: HashMap<String, String> = self.conn.hgetall(&key).await?;
         if map.is_empty() {
@@ -233,6 +1074,7 @@ impl StateManager {
         let mut correlation_id = None;
         let mut created_at = String::new();
         let mut last_accessed = String::new();
+        let mut archived_at = None;
 
         for (k, v) in map {
             if let Some(meta_key) = k.strip_prefix(META_PREFIX) {
@@ -243,6 +1085,7 @@ impl StateManager {
                 "correlation_id" => correlation_id = Some(v),
                 "created_at" => created_at = v,
                 "last_accessed" => last_accessed = v,
+                "archived_at" => archived_at = Some(v),
                 _ => {}
             }
         }
@@ -253,12 +1096,14 @@ impl StateManager {
             correlation_id,
             created_at,
             last_accessed,
+            archived_at,
             meta,
         }))
     }
 
     /// Create or update a tab record.
     pub async fn upsert_tab(&mut self, record: &TabRecord) -> Result<()> {
+        let _timer = instrument("upsert_tab");
         let key = tab_key(&record.tab_name, &record.session);
         let mut fields: Vec<(String, String)> = Vec::new();
 
@@ -268,12 +1113,23 @@ impl StateManager {
         if let Some(correlation_id) = &record.correlation_id {
             fields.push(("correlation_id".to_string(), correlation_id.clone()));
         }
+        if let Some(archived_at) = &record.archived_at {
+            fields.push(("archived_at".to_string(), archived_at.clone()));
+        }
 
         for (k, v) in &record.meta {
             fields.push((format!("{}{}", META_PREFIX, k), v.clone()));
         }
 
-        let _: () = self.conn.hset_multiple(key, &fields).await?;
+        let _: () = self.conn.hset_multiple(&key, &fields).await?;
+        // A tab recreated under an archived tab's name starts out active
+        if record.archived_at.is_none() {
+            let _: () = self.conn.hdel(&key, "archived_at").await?;
+        }
+        if let Some(correlation_id) = &record.correlation_id {
+            let member = correlation_member(&record.tab_name, &record.session);
+            let _: () = self.conn.sadd(correlation_index_key(correlation_id), member).await?;
+        }
         Ok(())
     }
 
@@ -311,6 +1167,102 @@ impl StateManager {
         Ok(tabs)
     }
 
+    /// List tabs in every session, for lookups by correlation ID.
+    pub async fn list_all_tabs(&mut self) -> Result<Vec<TabRecord>> {
+        let _timer = instrument("list_all_tabs");
+        // Collect keys first to release the iterator borrow
+        let keys: Vec<String> = {
+            let mut iter: AsyncIter<String> = self.conn.scan_match("perth:tab:*").await?;
+            let mut keys = Vec::new();
+            while let Some(key) = iter.next_item().await {
+                keys.push(key);
+            }
+            keys
+        };
+
+        let mut tabs = Vec::new();
+        for key in keys {
+            // perth:tab:{session}:{tab}; tab names may contain ':' but sessions don't
+            let Some((session, tab_name)) = key.strip_prefix("perth:tab:").and_then(|rest| rest.split_once(':')) else {
+                continue;
+            };
+            if let Some(tab) = self.get_tab(tab_name, session).await? {
+                tabs.push(tab);
+            }
+        }
+        Ok(tabs)
+    }
+
+    /// Tabs created with `correlation_id`, oldest first, via the
+    /// `perth:index:correlation:{id}` set. Entries for deleted tabs are
+    /// dropped; an empty index is rebuilt from a scan, covering tabs tracked
+    /// before the index existed or restored from the trash.
+    pub async fn find_tabs_by_correlation(&mut self, correlation_id: &str) -> Result<Vec<TabRecord>> {
+        let _timer = instrument("find_tabs_by_correlation");
+        let index = correlation_index_key(correlation_id);
+        let members: Vec<String> = self.conn.smembers(&index).await?;
+
+        let mut tabs = Vec::new();
+        if members.is_empty() {
+            for tab in self.list_all_tabs().await? {
+                if tab.correlation_id.as_deref() == Some(correlation_id) {
+                    let _: () = self.conn.sadd(&index, correlation_member(&tab.tab_name, &tab.session)).await?;
+                    tabs.push(tab);
+                }
+            }
+        } else {
+            for member in members {
+                let Some((session, tab_name)) = member.split_once(':') else {
+                    continue;
+                };
+                match self.get_tab(tab_name, session).await? {
+                    Some(tab) if tab.correlation_id.as_deref() == Some(correlation_id) => tabs.push(tab),
+                    _ => {
+                        let _: () = self.conn.srem(&index, &member).await?;
+                    }
+                }
+            }
+        }
+        tabs.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+        Ok(tabs)
+    }
+
+    /// Mark a tab archived now, returning the timestamp recorded.
+    pub async fn mark_tab_archived(&mut self, tab_name: &str, session: &str) -> Result<String> {
+        let key = tab_key(tab_name, session);
+        let now = Self::now_string();
+        let _: () = self.conn.hset(&key, "archived_at", &now).await?;
+        Ok(now)
+    }
+
+    /// Queue a tab to be archived at `due`, recording the event that completed
+    /// it. Rescheduling for the same reason replaces the due time.
+    pub async fn schedule_tab_archive(&mut self, tab_name: &str, session: &str, reason: &str, due: DateTime<Utc>) -> Result<()> {
+        let member = serde_json::to_string(&(session, tab_name, reason))?;
+        let _: () = self.conn.zadd(TAB_ARCHIVE_QUEUE_KEY, member, due.timestamp()).await?;
+        Ok(())
+    }
+
+    /// Remove and return the (session, tab, reason) entries whose archive time has passed.
+    pub async fn take_due_tab_archives(&mut self, now: DateTime<Utc>) -> Result<Vec<(String, String, String)>> {
+        let members: Vec<String> = self
+            .conn
+            .zrangebyscore(TAB_ARCHIVE_QUEUE_KEY, "-inf", now.timestamp())
+            .await?;
+        let mut due = Vec::new();
+        for member in members {
+            let removed: i64 = self.conn.zrem(TAB_ARCHIVE_QUEUE_KEY, &member).await?;
+            // Another listener already took it
+            if removed == 0 {
+                continue;
+            }
+            if let Ok(item) = serde_json::from_str(&member) {
+                due.push(item);
+            }
+        }
+        Ok(due)
+    }
+
     /// Check if a tab exists.
     pub async fn tab_exists(&mut self, tab_name: &str, session: &str) -> Result<bool> {
         let key = tab_key(tab_name, session);
@@ -318,13 +1270,28 @@ impl StateManager {
         Ok(exists)
     }
 
+    /// Delete a tab record, moving it to the trash when a grace period is configured.
+    pub async fn delete_tab(&self, tab_name: &str, session: &str) -> Result<()> {
+        self.trash_keys("tab", tab_name, &[tab_key(tab_name, session)])
+            .await
+            .context("failed to delete tab")
+    }
+
     // ========================================================================
     // Migration Methods (v1.0 → v2.0)
     // ========================================================================
 
     /// Migrate from znav:* to perth:* keyspace.
     /// Returns (migrated_count, skipped_count, error_count).
-    pub async fn migrate_keyspace(&mut self, dry_run: bool) -> Result<MigrationResult> {
+    ///
+    /// When the v2 target already exists with different fields, `resolve`
+    /// decides the outcome: `Some(fields)` replaces the target's fields,
+    /// `None` leaves it as it is. Conflicts are only reported on a dry run.
+    pub async fn migrate_keyspace(
+        &mut self,
+        dry_run: bool,
+        mut resolve: impl FnMut(&KeyConflict) -> Result<Option<HashMap<String, String>>>,
+    ) -> Result<MigrationResult> {
         let mut result = MigrationResult::default();
 
         // Scan for znav:pane:* keys (v1.0 pane data)
@@ -359,8 +1326,13 @@ impl StateManager {
             // Check if target key already exists
             let exists: bool = self.conn.exists(&new_key).await?;
             if exists {
-                result.skipped.push(format!("{} -> {} (already exists)", old_key, new_key));
-                result.skipped_count += 1;
+                let conflict = KeyConflict {
+                    old: self.conn.hgetall(&old_key).await?,
+                    new: self.conn.hgetall(&new_key).await?,
+                    old_key,
+                    new_key,
+                };
+                self.resolve_conflict(conflict, dry_run, &mut resolve, &mut result).await?;
                 continue;
             }
 
@@ -385,21 +1357,96 @@ impl StateManager {
         Ok(result)
     }
 
-    /// Save a session snapshot to Redis
+    async fn resolve_conflict(
+        &mut self,
+        conflict: KeyConflict,
+        dry_run: bool,
+        resolve: &mut impl FnMut(&KeyConflict) -> Result<Option<HashMap<String, String>>>,
+        result: &mut MigrationResult,
+    ) -> Result<()> {
+        let label = format!("{} -> {}", conflict.old_key, conflict.new_key);
+        let differing = conflict.differing_fields().len();
+        if differing == 0 {
+            result.skipped.push(format!("{} (already exists, identical)", label));
+            result.skipped_count += 1;
+            return Ok(());
+        }
+        if dry_run {
+            result.conflicts.push(format!("{} ({} differing fields)", label, differing));
+            return Ok(());
+        }
+
+        let Some(fields) = resolve(&conflict)? else {
+            result.skipped.push(format!("{} (already exists, kept)", label));
+            result.skipped_count += 1;
+            return Ok(());
+        };
+
+        // Update in place rather than recreating the hash, so the target
+        // keeps its TTL
+        let stale: Vec<&String> = conflict.new.keys().filter(|k| !fields.contains_key(*k)).collect();
+        let pairs: Vec<(&String, &String)> = fields.iter().collect();
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        if !stale.is_empty() {
+            pipe.hdel(&conflict.new_key, stale).ignore();
+        }
+        if !pairs.is_empty() {
+            pipe.hset_multiple(&conflict.new_key, &pairs).ignore();
+        }
+        let _: () = pipe.query_async(&mut self.conn).await?;
+
+        result.resolved.push(label);
+        result.resolved_count += 1;
+        Ok(())
+    }
+
+    /// Save a session snapshot to Redis, along with its `by-id` pointer
     pub async fn save_snapshot(&self, snapshot: &crate::types::SessionSnapshot) -> Result<()> {
+        let _timer = instrument("save_snapshot");
         let key = snapshot.redis_key();
         let json = serde_json::to_string(snapshot)
             .context("failed to serialize snapshot")?;
+        let json = crypto::seal(self.cipher.as_ref(), json);
 
-        let _: () = self.conn
-            .clone()
-            .set(&key, json)
+        let _: () = redis::pipe()
+            .atomic()
+            .set(&key, json).ignore()
+            .set(snapshot_id_key(&snapshot.id), &key).ignore()
+            .query_async(&mut self.conn.clone())
             .await
             .context("failed to save snapshot to redis")?;
 
         Ok(())
     }
 
+    /// Get a snapshot by ID through its `by-id` pointer, in any session.
+    /// A pointer left behind by a snapshot overwritten under the same name
+    /// is removed.
+    pub async fn get_snapshot_by_id(&self, id: &uuid::Uuid) -> Result<Option<crate::types::SessionSnapshot>> {
+        let pointer = snapshot_id_key(id);
+        let mut conn = self.conn.clone();
+        let mut key: Option<String> = conn.get(&pointer).await?;
+        if key.is_none() {
+            let legacy = format!("{}{}", LEGACY_SNAPSHOT_ID_PREFIX, id);
+            key = conn.get(&legacy).await?;
+            if key.is_some() {
+                let _: () = conn.rename(&legacy, &pointer).await?;
+            }
+        }
+        let Some(key) = key else {
+            return Ok(None);
+        };
+        let json: Option<String> = conn.get(&key).await?;
+        match json.and_then(|json| self.decode_snapshot(&json).ok()) {
+            Some(snapshot) if snapshot.id == *id => Ok(Some(snapshot)),
+            _ => {
+                let _: () = conn.del(&pointer).await?;
+                Ok(None)
+            }
+        }
+    }
+
     /// List snapshots for a specific session
     pub async fn list_snapshots(&self, session: &str) -> Result<Vec<crate::types::SessionSnapshot>> {
         let pattern = format!("perth:snapshots:{}:*", escape_redis_pattern(session));
@@ -412,14 +1459,14 @@ impl StateManager {
         let mut snapshots = Vec::new();
         for key in keys {
             if let Ok(json) = self.conn.clone().get::<_, String>(&key).await {
-                if let Ok(snapshot) = serde_json::from_str::<crate::types::SessionSnapshot>(&json) {
+                if let Ok(snapshot) = self.decode_snapshot(&json) {
                     snapshots.push(snapshot);
                 }
             }
         }
 
         // Sort by creation time (newest first)
-        snapshots.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        snapshots.sort_by_key(|snapshot| std::cmp::Reverse(snapshot.created_at));
 
         Ok(snapshots)
     }
@@ -436,75 +1483,145 @@ impl StateManager {
         let mut snapshots = Vec::new();
         for key in keys {
             if let Ok(json) = self.conn.clone().get::<_, String>(&key).await {
-                if let Ok(snapshot) = serde_json::from_str::<crate::types::SessionSnapshot>(&json) {
+                if let Ok(snapshot) = self.decode_snapshot(&json) {
                     snapshots.push(snapshot);
                 }
             }
         }
 
         // Sort by creation time (newest first)
-        snapshots.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        snapshots.sort_by_key(|snapshot| std::cmp::Reverse(snapshot.created_at));
 
         Ok(snapshots)
     }
 
     /// Get a snapshot by name
     pub async fn get_snapshot(&self, session: &str, name: &str) -> Result<crate::types::SessionSnapshot> {
+        let _timer = instrument("get_snapshot");
         let key = format!("perth:snapshots:{}:{}", session, name);
-        let json: String = self.conn
+        let json: Option<String> = self.conn
             .clone()
             .get(&key)
             .await
-            .context("snapshot not found")?;
+            .context("failed to read snapshot")?;
+        let json = json.ok_or_else(|| PerthError::NotFound(format!("snapshot '{}' not found in session '{}'", name, session)))?;
 
-        let snapshot = serde_json::from_str(&json)
+        let snapshot = self.decode_snapshot(&json)
             .context("failed to deserialize snapshot")?;
 
         Ok(snapshot)
     }
 
-    /// Delete a snapshot by name
+    /// Delete a snapshot by name (moved to the trash when a grace period is configured)
     pub async fn delete_snapshot(&self, session: &str, name: &str) -> Result<()> {
         let key = format!("perth:snapshots:{}:{}", session, name);
+        let mut keys = vec![key];
+        // The pointer goes with the snapshot, so restoring it from the trash restores both
+        if let Ok(snapshot) = self.get_snapshot(session, name).await {
+            keys.push(snapshot_id_key(&snapshot.id));
+        }
+        self.trash_keys("snapshot", &format!("{}/{}", session, name), &keys)
+            .await
+            .context("failed to delete snapshot")
+    }
+
+    /// Save a session template, replacing any template with the same name
+    pub async fn save_template(&self, template: &crate::types::SessionTemplate) -> Result<()> {
+        let json = serde_json::to_string(template).context("failed to serialize template")?;
         let _: () = self.conn
             .clone()
-            .del(&key)
+            .set(template.redis_key(), json)
             .await
-            .context("failed to delete snapshot")?;
-
+            .context("failed to save template to redis")?;
         Ok(())
     }
 
+    /// Get a session template by name
+    pub async fn get_template(&self, name: &str) -> Result<Option<crate::types::SessionTemplate>> {
+        let json: Option<String> = self.conn
+            .clone()
+            .get(template_key(name))
+            .await
+            .context("failed to read template")?;
+        json.map(|json| serde_json::from_str(&json).context("failed to deserialize template"))
+            .transpose()
+    }
+
+    /// List all session templates, sorted by name
+    pub async fn list_templates(&self) -> Result<Vec<crate::types::SessionTemplate>> {
+        let keys: Vec<String> = self.conn
+            .clone()
+            .keys("perth:templates:*")
+            .await
+            .context("failed to scan template keys")?;
+
+        let mut templates = Vec::new();
+        for key in keys {
+            if let Ok(json) = self.conn.clone().get::<_, String>(&key).await {
+                if let Ok(template) = serde_json::from_str::<crate::types::SessionTemplate>(&json) {
+                    templates.push(template);
+                }
+            }
+        }
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(templates)
+    }
+
+    /// Delete a session template (moved to the trash when a grace period is configured)
+    pub async fn delete_template(&self, name: &str) -> Result<()> {
+        self.trash_keys("template", name, &[template_key(name)])
+            .await
+            .context("failed to delete template")
+    }
+
     /// Get snapshot ancestry chain (parent, grandparent, etc.)
     ///
-    /// Returns snapshots from newest to oldest, stopping when parent_id is None
-    /// or when a parent cannot be found.
+    /// Returns snapshots from newest to oldest, stopping when parent_id is None,
+    /// when a parent cannot be found, or when the chain loops back on itself.
+    /// Parents are resolved through their `by-id` pointers; the session's
+    /// snapshots are indexed once if one is missing, for snapshots saved
+    /// before the pointers existed.
     pub async fn get_snapshot_ancestry(&self, session: &str, name: &str) -> Result<Vec<crate::types::SessionSnapshot>> {
-        let mut ancestry = Vec::new();
         let mut current = self.get_snapshot(session, name).await?;
-
-        ancestry.push(current.clone());
+        let mut seen = HashSet::from([current.id]);
+        let mut ancestry = vec![current.clone()];
+        let mut indexed = false;
 
         // Walk up the parent chain
         while let Some(parent_id) = current.parent_id {
-            // Find parent by ID (need to scan all snapshots in session)
-            let snapshots = self.list_snapshots(session).await?;
+            if !seen.insert(parent_id) {
+                tracing::warn!(snapshot = %name, parent = %parent_id, "snapshot ancestry loops back on itself; stopping");
+                break;
+            }
 
-            match snapshots.into_iter().find(|s| s.id == parent_id) {
-                Some(parent) => {
-                    ancestry.push(parent.clone());
-                    current = parent;
-                }
-                None => {
-                    // Parent not found, stop traversal
-                    break;
-                }
+            let mut parent = self.get_snapshot_by_id(&parent_id).await?;
+            if parent.is_none() && !indexed {
+                self.index_snapshots(session).await?;
+                indexed = true;
+                parent = self.get_snapshot_by_id(&parent_id).await?;
             }
+
+            // Parent not found, stop traversal
+            let Some(parent) = parent else {
+                break;
+            };
+            ancestry.push(parent.clone());
+            current = parent;
         }
 
         Ok(ancestry)
     }
 
+    /// Write `by-id` pointers for every snapshot in a session.
+    async fn index_snapshots(&self, session: &str) -> Result<()> {
+        let mut pipe = redis::pipe();
+        for snapshot in self.list_snapshots(session).await? {
+            pipe.set(snapshot_id_key(&snapshot.id), snapshot.redis_key()).ignore();
+        }
+        let _: () = pipe.query_async(&mut self.conn.clone()).await?;
+        Ok(())
+    }
+
     /// Enforce snapshot retention policy for a session.
     ///
     /// Keeps the `limit` most recent snapshots and deletes the rest.
@@ -527,19 +1644,650 @@ impl StateManager {
     }
 }
 
+// ============================================================================
+// Event outbox
+// ============================================================================
+
+impl StateManager {
+    /// Handle on the outbox of unpublished Bloodbank events.
+    pub fn event_outbox(&self) -> EventOutbox {
+        EventOutbox {
+            conn: self.conn.clone(),
+        }
+    }
+}
+
+/// Redis list of serialized Bloodbank events waiting for RabbitMQ to come
+/// back. Events are queued at the back and published from the front, so
+/// they go out in the order they were produced.
+#[derive(Clone)]
+pub struct EventOutbox {
+    conn: MultiplexedConnection,
+}
+
+impl EventOutbox {
+    /// Queue an event, dropping the oldest past [`EVENT_OUTBOX_LIMIT`].
+    /// Returns how many events are now queued.
+    pub async fn push(&self, event: &str) -> Result<usize> {
+        let (queued,): (usize,) = redis::pipe()
+            .atomic()
+            .rpush(EVENT_OUTBOX_KEY, event)
+            .ltrim(EVENT_OUTBOX_KEY, -EVENT_OUTBOX_LIMIT, -1).ignore()
+            .query_async(&mut self.conn.clone())
+            .await
+            .context("failed to queue event in outbox")?;
+        Ok(queued.min(EVENT_OUTBOX_LIMIT as usize))
+    }
+
+    /// Up to `count` of the oldest queued events.
+    pub async fn peek(&self, count: usize) -> Result<Vec<String>> {
+        self.conn
+            .clone()
+            .lrange(EVENT_OUTBOX_KEY, 0, count as isize - 1)
+            .await
+            .context("failed to read event outbox")
+    }
+
+    /// Remove `event` from the front of the outbox once it is published.
+    /// Returns false if another process already removed it.
+    pub async fn remove_front(&self, event: &str) -> Result<bool> {
+        let removed: i32 = redis::Script::new(POP_IF_HEAD_SCRIPT)
+            .key(EVENT_OUTBOX_KEY)
+            .arg(event)
+            .invoke_async(&mut self.conn.clone())
+            .await
+            .context("failed to update event outbox")?;
+        Ok(removed == 1)
+    }
+}
+
+// ============================================================================
+// Trash (soft delete)
+// ============================================================================
+
+impl StateManager {
+    /// Delete `keys`, or move them to the trash if a grace period is set.
+    ///
+    /// Trashed keys are renamed to `perth:trash:{id}:{n}` alongside a manifest
+    /// hash at `perth:trash:{id}` recording the original names, and all of
+    /// them expire when the grace period ends.
+    async fn trash_keys(&self, kind: &str, name: &str, keys: &[String]) -> Result<()> {
+        let mut conn = self.conn.clone();
+
+        let Some(ttl) = self.trash_ttl_secs else {
+            let _: () = conn.del(keys).await?;
+            return Ok(());
+        };
+
+        let mut existing = Vec::new();
+        for key in keys {
+            let exists: bool = conn.exists(key).await?;
+            if exists {
+                existing.push(key.clone());
+            }
+        }
+        if existing.is_empty() {
+            return Ok(());
+        }
+
+        let id = new_trash_id();
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for (index, key) in existing.iter().enumerate() {
+            let trashed = trash_data_key(&id, index);
+            pipe.rename(key, &trashed).ignore()
+                .expire(&trashed, ttl).ignore();
+        }
+        add_trash_manifest(&mut pipe, &id, kind, name, &existing, ttl)?;
+
+        let _: () = pipe
+            .query_async(&mut conn)
+            .await
+            .context("failed to move keys to trash")?;
+        Ok(())
+    }
+
+    /// Move individual history entries removed from a pane to the trash.
+    async fn trash_entries(&self, pane_name: &str, entries: Vec<String>) -> Result<()> {
+        let Some(ttl) = self.trash_ttl_secs else {
+            return Ok(());
+        };
+
+        let id = new_trash_id();
+        let trashed = trash_data_key(&id, 0);
+        let name = format!("{} ({} entries)", pane_name, entries.len());
+
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .rpush(&trashed, entries).ignore()
+            .expire(&trashed, ttl).ignore();
+        add_trash_manifest(&mut pipe, &id, "entries", &name, &[history_key(pane_name)], ttl)?;
+
+        let _: () = pipe
+            .query_async(&mut self.conn.clone())
+            .await
+            .context("failed to move history entries to trash")?;
+        Ok(())
+    }
+
+    /// List trashed items, most recently deleted first.
+    /// Items whose grace period has ended are dropped from the index.
+    pub async fn list_trash(&self) -> Result<Vec<TrashItem>> {
+        let mut conn = self.conn.clone();
+        let ids: Vec<String> = conn.zrevrange(TRASH_INDEX_KEY, 0, -1).await?;
+
+        let mut items = Vec::with_capacity(ids.len());
+        for id in ids {
+            match self.get_trash_item(&id).await? {
+                Some(item) => items.push(item),
+                None => {
+                    let _: () = conn.zrem(TRASH_INDEX_KEY, &id).await?;
+                }
+            }
+        }
+        Ok(items)
+    }
+
+    async fn get_trash_item(&self, id: &str) -> Result<Option<TrashItem>> {
+        let map: HashMap<String, String> = self.conn.clone().hgetall(trash_manifest_key(id)).await?;
+        if map.is_empty() {
+            return Ok(None);
+        }
+
+        let field = |name: &str| map.get(name).cloned().unwrap_or_default();
+        let keys: Vec<String> = serde_json::from_str(&field("keys"))
+            .context("failed to parse trash manifest keys")?;
+        let deleted_at = DateTime::parse_from_rfc3339(&field("deleted_at"))
+            .map(|ts| ts.with_timezone(&Utc))
+            .context("failed to parse trash manifest timestamp")?;
+
+        Ok(Some(TrashItem {
+            id: id.to_string(),
+            kind: field("kind"),
+            name: field("name"),
+            deleted_at,
+            keys,
+        }))
+    }
+
+    /// Restore a trashed item to its original keys.
+    ///
+    /// Whole keys are renamed back and fail if the original name is in use.
+    /// Trashed history entries are merged back into the pane's history by time,
+    /// then capped (or the overflow archived) like any other history write.
+    /// Restored pane keys get the configured pane TTL again.
+    pub async fn restore_trash(&mut self, id: &str) -> Result<TrashItem> {
+        let item = self
+            .get_trash_item(id)
+            .await?
+            .ok_or_else(|| PerthError::NotFound(format!("trash item '{}' not found (it may have expired)", id)))?;
+
+        if item.kind == "entries" {
+            let history_key = &item.keys[0];
+            let pane_name = pane_name_from_history_key(history_key)
+                .ok_or_else(|| anyhow::anyhow!("trash item '{}' has an unexpected history key {}", id, history_key))?
+                .to_string();
+            let restored = self.restore_trashed_entries(id, history_key).await?;
+
+            if self.archive_history && restored > DEFAULT_HISTORY_LIMIT {
+                self.archive_overflow(&pane_name).await?;
+            }
+            self.refresh_ttl(&pane_name).await?;
+        } else {
+            let restore = redis::Script::new(RESTORE_KEYS_SCRIPT);
+            let mut script = restore.prepare_invoke();
+            script.key(trash_manifest_key(id)).key(TRASH_INDEX_KEY).arg(id);
+            for index in 0..item.keys.len() {
+                script.key(trash_data_key(id, index));
+            }
+            for key in &item.keys {
+                script.key(key);
+            }
+            let conflict: usize = script
+                .invoke_async(&mut self.conn)
+                .await
+                .context("failed to restore from trash")?;
+            if conflict > 0 {
+                return Err(anyhow::anyhow!(
+                    "cannot restore '{}': {} already exists",
+                    item.name,
+                    item.keys[conflict - 1]
+                ));
+            }
+
+            if item.kind == "pane" || item.kind == "history" {
+                self.refresh_ttl(&item.name).await?;
+            }
+        }
+
+        Ok(item)
+    }
+
+    /// Merge trashed entries back into a history list, newest first.
+    /// Returns the length of the merged list.
+    ///
+    /// The merged list only replaces the live one if the live list is still
+    /// exactly what was read; otherwise the merge is redone on the fresh list.
+    async fn restore_trashed_entries(&mut self, id: &str, history_key: &str) -> Result<usize> {
+        for _ in 0..UPDATE_ENTRY_ATTEMPTS {
+            let live: Vec<String> = self.conn.lrange(history_key, 0, -1).await?;
+            let trashed: Vec<String> = self.conn.lrange(trash_data_key(id, 0), 0, -1).await?;
+
+            let mut entries = Vec::new();
+            for json in live.iter().chain(&trashed) {
+                let entry: IntentEntry = self.decode_entry(json)
+                    .context("failed to deserialize IntentEntry from trash")?;
+                entries.push((entry.timestamp, json));
+            }
+            // Newest first, matching LPUSH order
+            entries.sort_by_key(|&(timestamp, _)| std::cmp::Reverse(timestamp));
+            if !self.archive_history {
+                entries.truncate(DEFAULT_HISTORY_LIMIT);
+            }
+
+            let replaced: i32 = redis::Script::new(RESTORE_ENTRIES_SCRIPT)
+                .key(history_key)
+                .key(trash_data_key(id, 0))
+                .key(trash_manifest_key(id))
+                .key(TRASH_INDEX_KEY)
+                .arg(id)
+                .arg(live.len())
+                .arg(&live)
+                .arg(entries.iter().map(|(_, json)| json.as_str()).collect::<Vec<_>>())
+                .invoke_async(&mut self.conn)
+                .await
+                .context("failed to restore from trash")?;
+            match replaced {
+                1 => return Ok(entries.len()),
+                -1 => {
+                    return Err(PerthError::NotFound(format!("trash item '{}' not found (it may have expired)", id)).into())
+                }
+                _ => {}
+            }
+        }
+        Err(PerthError::StaleState(format!("history for trash item '{}' kept changing; try again", id)).into())
+    }
+
+    /// Permanently delete one trashed item, or everything when `id` is None.
+    /// Returns the number of items purged.
+    pub async fn empty_trash(&self, id: Option<&str>) -> Result<usize> {
+        let items = match id {
+            Some(id) => vec![self
+                .get_trash_item(id)
+                .await?
+                .ok_or_else(|| PerthError::NotFound(format!("trash item '{}' not found (it may have expired)", id)))?],
+            None => self.list_trash().await?,
+        };
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for item in &items {
+            for index in 0..item.keys.len() {
+                pipe.del(trash_data_key(&item.id, index)).ignore();
+            }
+            pipe.del(trash_manifest_key(&item.id)).ignore()
+                .zrem(TRASH_INDEX_KEY, &item.id).ignore();
+        }
+        let _: () = pipe
+            .query_async(&mut self.conn.clone())
+            .await
+            .context("failed to empty trash")?;
+
+        Ok(items.len())
+    }
+}
+
+// ============================================================================
+// Key rotation
+// ============================================================================
+
+impl StateManager {
+    /// Where an interrupted `state rekey` stopped, if one did.
+    pub async fn rekey_cursor(&self) -> Result<Option<u64>> {
+        Ok(self.conn.clone().get(REKEY_CURSOR_KEY).await?)
+    }
+
+    /// Forget an interrupted `state rekey`, so the next run starts over.
+    pub async fn clear_rekey_cursor(&self) -> Result<()> {
+        let _: () = self.conn.clone().del(REKEY_CURSOR_KEY).await?;
+        Ok(())
+    }
+
+    /// Move the sealed values in the next batch of about `count` keys from
+    /// `old` to `new`, then save how far the scan got so an interrupted run
+    /// resumes there. Values `new` already opens are left alone, so going
+    /// over a key twice is harmless.
+    ///
+    /// Strings (snapshots), list elements (history entries and archive
+    /// chunks, trashed or not) and hash fields (last intents) are rewritten
+    /// only if unchanged since they were read; anything written meanwhile
+    /// is counted as changed and left for another run.
+    pub async fn rekey_batch(&self, old: &Cipher, new: &Cipher, count: usize) -> Result<RekeyBatch> {
+        let mut conn = self.conn.clone();
+        let cursor = self.rekey_cursor().await?.unwrap_or(0);
+        let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("COUNT")
+            .arg(count)
+            .query_async(&mut conn)
+            .await
+            .context("failed to scan keys")?;
+
+        let mut batch = RekeyBatch::default();
+        for key in keys.iter().filter(|key| key.starts_with("perth:") || key.starts_with("znav:")) {
+            batch.keys += 1;
+            let kind: String = redis::cmd("TYPE").arg(key).query_async(&mut conn).await?;
+            match kind.as_str() {
+                "string" => {
+                    let value: Vec<u8> = conn.get(key).await?;
+                    let Some(sealed) = batch.rekey(old, new, &value) else {
+                        continue;
+                    };
+                    let replaced: i32 = redis::Script::new(REPLACE_VALUE_SCRIPT)
+                        .key(key)
+                        .arg(&value)
+                        .arg(&sealed)
+                        .invoke_async(&mut conn)
+                        .await?;
+                    batch.written(replaced == 1, 1);
+                }
+                "list" => {
+                    let elements: Vec<Vec<u8>> = conn.lrange(key, 0, -1).await?;
+                    for element in elements {
+                        let (replacement, rekeyed) = if element.starts_with(&[0x1f, 0x8b]) {
+                            // An archive chunk of stored entries
+                            let Ok(entries) = decompress_chunk(&element) else {
+                                continue;
+                            };
+                            let mut rekeyed = 0;
+                            let entries: Vec<String> = entries
+                                .into_iter()
+                                .map(|entry| match batch.rekey(old, new, entry.as_bytes()) {
+                                    Some(sealed) => {
+                                        rekeyed += 1;
+                                        sealed
+                                    }
+                                    None => entry,
+                                })
+                                .collect();
+                            if rekeyed == 0 {
+                                continue;
+                            }
+                            (compress_chunk(&entries)?, rekeyed)
+                        } else {
+                            match batch.rekey(old, new, &element) {
+                                Some(sealed) => (sealed.into_bytes(), 1),
+                                None => continue,
+                            }
+                        };
+                        let replaced: i32 = redis::Script::new(REPLACE_ENTRY_SCRIPT)
+                            .key(key)
+                            .arg(&element)
+                            .arg(&replacement)
+                            .invoke_async(&mut conn)
+                            .await?;
+                        batch.written(replaced == 1, rekeyed);
+                    }
+                }
+                "hash" => {
+                    let fields: HashMap<String, Vec<u8>> = conn.hgetall(key).await?;
+                    for (field, value) in fields {
+                        let Some(sealed) = batch.rekey(old, new, &value) else {
+                            continue;
+                        };
+                        let replaced: i32 = redis::Script::new(REPLACE_FIELD_SCRIPT)
+                            .key(key)
+                            .arg(&field)
+                            .arg(&value)
+                            .arg(&sealed)
+                            .invoke_async(&mut conn)
+                            .await?;
+                        batch.written(replaced == 1, 1);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if next == 0 {
+            self.clear_rekey_cursor().await?;
+            batch.done = true;
+        } else {
+            let _: () = conn.set(REKEY_CURSOR_KEY, next).await?;
+        }
+        Ok(batch)
+    }
+}
+
+/// What one [`StateManager::rekey_batch`] did.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct RekeyBatch {
+    /// Perth keys looked at
+    pub keys: usize,
+    /// Values moved to the new key
+    pub rekeyed: usize,
+    /// Values already sealed with the new key
+    pub current: usize,
+    /// Sealed values neither key opens
+    pub unreadable: usize,
+    /// Values written to while being moved, left for another run
+    pub changed: usize,
+    /// Whether the scan has reached the end of the keyspace
+    pub done: bool,
+}
+
+impl RekeyBatch {
+    /// Add another batch's counts to these.
+    pub fn merge(&mut self, batch: &RekeyBatch) {
+        self.keys += batch.keys;
+        self.rekeyed += batch.rekeyed;
+        self.current += batch.current;
+        self.unreadable += batch.unreadable;
+        self.changed += batch.changed;
+        self.done = batch.done;
+    }
+
+    /// `value` sealed with `new`, if it is sealed with `old`. Plain values
+    /// are skipped; the rest are counted as current or unreadable.
+    fn rekey(&mut self, old: &Cipher, new: &Cipher, value: &[u8]) -> Option<String> {
+        if !crypto::is_sealed(value) {
+            return None;
+        }
+        let value = std::str::from_utf8(value).ok()?;
+        if new.open(value).is_ok() {
+            self.current += 1;
+            return None;
+        }
+        match old.open(value) {
+            Ok(plain) => Some(new.seal(&plain)),
+            Err(_) => {
+                self.unreadable += 1;
+                None
+            }
+        }
+    }
+
+    /// Count `values` re-encrypted values as written, or as changed if the
+    /// stored value moved on first.
+    fn written(&mut self, replaced: bool, values: usize) {
+        if replaced {
+            self.rekeyed += values;
+        } else {
+            self.changed += values;
+        }
+    }
+}
+
+/// Key categories reported by [`StateManager::keyspace_stats`], in display order.
+pub const KEY_CATEGORIES: [&str; 11] = [
+    "panes", "histories", "archives", "annotations", "goals", "tabs", "snapshots", "templates", "indexes", "trash", "other",
+];
+
+/// Which [`KEY_CATEGORIES`] entry a key belongs to.
+fn key_category(key: &str) -> &'static str {
+    if key == TRASH_INDEX_KEY || key.ends_with(":index") || key.starts_with("perth:index:") || key.starts_with(SNAPSHOT_ID_PREFIX) {
+        "indexes"
+    } else if key.starts_with("perth:pane:") && key.ends_with(":history") {
+        "histories"
+    } else if key.starts_with("perth:pane:") && key.ends_with(":archive") {
+        "archives"
+    } else if key.starts_with("perth:pane:") && key.ends_with(":annotations") {
+        "annotations"
+    } else if key.starts_with("perth:pane:") && key.ends_with(":goals") {
+        "goals"
+    } else if key.starts_with("znav:pane:") || key.starts_with("perth:pane:") {
+        "panes"
+    } else if key.starts_with("perth:tab:") {
+        "tabs"
+    } else if key.starts_with("perth:snapshots:") {
+        "snapshots"
+    } else if key.starts_with("perth:templates:") {
+        "templates"
+    } else if key.starts_with("perth:trash:") {
+        "trash"
+    } else {
+        "other"
+    }
+}
+
+/// Key count and estimated memory for one category of keys.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct KeyGroupStats {
+    pub category: &'static str,
+    pub keys: usize,
+    /// Estimated bytes, extrapolated from the sampled keys
+    pub bytes: u64,
+    /// Number of keys measured with MEMORY USAGE
+    pub sampled: usize,
+}
+
+/// Size of one pane's live history.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HistorySize {
+    pub pane: String,
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+/// Keyspace counts and memory estimates for `zdrive state stats`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct KeyspaceStats {
+    pub groups: Vec<KeyGroupStats>,
+    /// Longest histories, longest first
+    pub largest_histories: Vec<HistorySize>,
+    /// False when the server rejected MEMORY USAGE; byte counts are then zero
+    pub memory_usage_available: bool,
+}
+
+impl KeyspaceStats {
+    pub fn total_keys(&self) -> usize {
+        self.groups.iter().map(|g| g.keys).sum()
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.groups.iter().map(|g| g.bytes).sum()
+    }
+}
+
+/// An item moved to the trash by a destructive command.
+#[derive(Debug, Clone, Serialize)]
+pub struct TrashItem {
+    pub id: String,
+    /// What was deleted: snapshot, template, history, or entries
+    pub kind: String,
+    /// Human-readable name of what was deleted
+    pub name: String,
+    pub deleted_at: DateTime<Utc>,
+    /// Original key names, restored in place
+    pub keys: Vec<String>,
+}
+
+/// Pagination and time-range options for history queries.
+#[derive(Debug, Clone, Default)]
+pub struct HistoryQuery {
+    /// Number of matching entries to skip (newest first)
+    pub offset: usize,
+    /// Maximum entries to return (default: 100)
+    pub limit: Option<usize>,
+    /// Only entries at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only entries at or before this time
+    pub until: Option<DateTime<Utc>>,
+    /// Continue into archived entries after the live history
+    pub include_archived: bool,
+    /// Only entries carrying this tag
+    pub tag: Option<String>,
+}
+
 /// Result of a keyspace migration operation.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct MigrationResult {
     pub total_keys: usize,
     pub migrated_count: usize,
     pub skipped_count: usize,
+    /// Existing targets overwritten or merged per the conflict policy
+    pub resolved_count: usize,
     pub error_count: usize,
     pub migrated: Vec<String>,
     pub skipped: Vec<String>,
+    pub resolved: Vec<String>,
     pub would_migrate: Vec<String>,
+    /// Existing targets that differ from their source (dry run only)
+    pub conflicts: Vec<String>,
     pub errors: Vec<String>,
 }
 
+/// A v1 key whose v2 target already exists, as seen by a migration
+/// conflict resolver.
+#[derive(Debug, Clone)]
+pub struct KeyConflict {
+    pub old_key: String,
+    pub new_key: String,
+    /// Fields of the v1 (znav:*) hash
+    pub old: HashMap<String, String>,
+    /// Fields already at the v2 (perth:*) target
+    pub new: HashMap<String, String>,
+}
+
+impl KeyConflict {
+    /// Fields whose values differ, by name, as (field, old, new); `None`
+    /// where one side lacks the field.
+    pub fn differing_fields(&self) -> Vec<(String, Option<String>, Option<String>)> {
+        let mut names: Vec<&String> = self.old.keys().chain(self.new.keys()).collect();
+        names.sort();
+        names.dedup();
+        names
+            .into_iter()
+            .filter(|name| self.old.get(*name) != self.new.get(*name))
+            .map(|name| (name.clone(), self.old.get(name).cloned(), self.new.get(name).cloned()))
+            .collect()
+    }
+
+    /// Union of both hashes. Where both have a field, the value from the
+    /// record seen most recently (by `last_seen`) wins; ties keep the v2 value.
+    pub fn merged(&self) -> HashMap<String, String> {
+        let seen = |fields: &HashMap<String, String>| {
+            fields.get("last_seen").and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+        };
+        let (older, newer) = if seen(&self.old) > seen(&self.new) {
+            (&self.new, &self.old)
+        } else {
+            (&self.old, &self.new)
+        };
+        let mut merged = older.clone();
+        merged.extend(newer.iter().map(|(k, v)| (k.clone(), v.clone())));
+        merged
+    }
+}
+
+/// Time a Redis operation for metrics and trace it when tracing is on.
+fn instrument(op: &'static str) -> (metrics::Timer, telemetry::Span) {
+    let span = telemetry::client_span(format!("redis {}", op))
+        .with_attribute("db.system", "redis")
+        .with_attribute("db.operation", op);
+    (metrics::redis_timer(op), span)
+}
+
 fn escape_redis_pattern(s: &str) -> String {
     let mut escaped = String::with_capacity(s.len());
     for c in s.chars() {
@@ -562,6 +2310,114 @@ fn history_key(pane_name: &str) -> String {
     format!("perth:pane:{}:history", pane_name)
 }
 
+/// The pane a `history_key` belongs to
+fn pane_name_from_history_key(key: &str) -> Option<&str> {
+    key.strip_prefix("perth:pane:")?.strip_suffix(":history")
+}
+
+/// Pub/sub channel announcing new entries in a pane's history
+fn history_channel(pane_name: &str) -> String {
+    format!("perth:pane:{}:history:live", pane_name)
+}
+
+fn annotations_key(pane_name: &str) -> String {
+    format!("perth:pane:{}:annotations", pane_name)
+}
+
+/// Hash of a pane's goals, by goal ID
+fn goals_key(pane_name: &str) -> String {
+    format!("perth:pane:{}:goals", pane_name)
+}
+
+fn archive_key(pane_name: &str) -> String {
+    format!("perth:pane:{}:archive", pane_name)
+}
+
+/// Hash of commands counted by the shell hook since the last checkpoint
+fn hook_key(pane_name: &str) -> String {
+    format!("perth:pane:{}:hook", pane_name)
+}
+
+/// Gzip a batch of serialized entries as a JSON array.
+fn compress_chunk(entries: &[String]) -> Result<Vec<u8>> {
+    let json = serde_json::to_vec(entries).context("failed to serialize archive chunk")?;
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&json)?;
+    encoder.finish().context("failed to compress archive chunk")
+}
+
+fn decompress_chunk(chunk: &[u8]) -> Result<Vec<String>> {
+    let mut json = Vec::new();
+    GzDecoder::new(chunk)
+        .read_to_end(&mut json)
+        .context("failed to decompress archive chunk")?;
+    serde_json::from_slice(&json).context("failed to parse archive chunk")
+}
+
+fn trash_manifest_key(id: &str) -> String {
+    format!("perth:trash:{}", id)
+}
+
+fn trash_data_key(id: &str, index: usize) -> String {
+    format!("perth:trash:{}:{}", id, index)
+}
+
+/// Sortable, collision-resistant trash id, e.g. `20250102T030405-1a2b3c4d`.
+fn new_trash_id() -> String {
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    format!("{}-{}", Utc::now().format("%Y%m%dT%H%M%S"), &suffix[..8])
+}
+
+/// Queue the manifest hash and index entry for a trashed item.
+fn add_trash_manifest(
+    pipe: &mut redis::Pipeline,
+    id: &str,
+    kind: &str,
+    name: &str,
+    keys: &[String],
+    ttl: i64,
+) -> Result<()> {
+    let manifest_key = trash_manifest_key(id);
+    let now = Utc::now();
+    let fields = [
+        ("kind", kind.to_string()),
+        ("name", name.to_string()),
+        ("deleted_at", now.to_rfc3339()),
+        ("keys", serde_json::to_string(keys).context("failed to serialize trash manifest")?),
+    ];
+    pipe.hset_multiple(&manifest_key, &fields).ignore()
+        .expire(&manifest_key, ttl).ignore()
+        .zadd(TRASH_INDEX_KEY, id, now.timestamp()).ignore();
+    Ok(())
+}
+
+fn template_key(name: &str) -> String {
+    format!("perth:templates:{}", name)
+}
+
 fn tab_key(tab_name: &str, session: &str) -> String {
     format!("perth:tab:{}:{}", session, tab_name)
 }
+
+fn snapshot_id_key(id: &uuid::Uuid) -> String {
+    format!("{}{}", SNAPSHOT_ID_PREFIX, id)
+}
+
+fn correlation_index_key(correlation_id: &str) -> String {
+    format!("perth:index:correlation:{}", correlation_id)
+}
+
+/// Sorted set of the entries listing an artifact, scored by entry time
+fn artifact_index_key(artifact: &str) -> String {
+    format!("perth:index:artifact:{}", artifact)
+}
+
+/// `{entry_id}:{pane}`; UUIDs never contain ':'.
+fn artifact_member(pane_name: &str, id: &uuid::Uuid) -> String {
+    format!("{}:{}", id, pane_name)
+}
+
+/// `{session}:{tab}`; sessions never contain ':'.
+fn correlation_member(tab_name: &str, session: &str) -> String {
+    format!("{}:{}", session, tab_name)
+}
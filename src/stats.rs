@@ -0,0 +1,278 @@
+//! Activity statistics for `zdrive stats`: entry counts by type, source and
+//! day, the busiest panes, and how often milestones and checkpoints land.
+
+use crate::ask::PaneEntry;
+use crate::output::messages;
+use crate::types::IntentType;
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Width of the longest bar in the text dashboard.
+const BAR_WIDTH: usize = 30;
+
+/// Sparkline levels, lowest first, with an ASCII fallback.
+const SPARK_UNICODE: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const SPARK_ASCII: [char; 8] = ['_', '.', ':', '-', '=', '+', '*', '#'];
+
+/// Aggregate activity over a period.
+#[derive(Debug, Clone, Serialize)]
+pub struct ActivityStats {
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    pub total_entries: usize,
+    pub pane_count: usize,
+    /// Every entry type, in milestone/checkpoint/exploration order
+    pub by_type: Vec<StatCount>,
+    /// Sources with at least one entry, most entries first
+    pub by_source: Vec<StatCount>,
+    /// Every local day in the period, oldest first, including empty days
+    pub by_day: Vec<DayCount>,
+    /// Panes with the most entries, most first
+    pub busiest_panes: Vec<StatCount>,
+    /// Share of entries that are milestones, from 0 to 1
+    pub milestone_rate: f64,
+    /// Mean time between consecutive checkpoints in the same pane, when any
+    /// pane has two or more
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_checkpoint_interval_minutes: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatCount {
+    pub key: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DayCount {
+    pub date: NaiveDate,
+    pub count: usize,
+}
+
+impl ActivityStats {
+    /// Aggregate entries in the period, keeping the `top` busiest panes.
+    pub fn build(entries: Vec<PaneEntry>, since: DateTime<Utc>, until: DateTime<Utc>, top: usize) -> Self {
+        let total_entries = entries.len();
+
+        let mut by_type: HashMap<&'static str, usize> = HashMap::new();
+        let mut by_source: HashMap<&'static str, usize> = HashMap::new();
+        let mut by_day: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+        let mut by_pane: HashMap<String, usize> = HashMap::new();
+        let mut checkpoints: HashMap<String, Vec<DateTime<Utc>>> = HashMap::new();
+        for PaneEntry { pane_name, entry } in &entries {
+            *by_type.entry(type_key(entry.entry_type)).or_default() += 1;
+            *by_source.entry(entry.source_str()).or_default() += 1;
+            *by_day.entry(entry.timestamp.with_timezone(&Local).date_naive()).or_default() += 1;
+            *by_pane.entry(pane_name.clone()).or_default() += 1;
+            if entry.entry_type == IntentType::Checkpoint {
+                checkpoints.entry(pane_name.clone()).or_default().push(entry.timestamp);
+            }
+        }
+
+        let by_type = [IntentType::Milestone, IntentType::Checkpoint, IntentType::Exploration]
+            .into_iter()
+            .map(|entry_type| StatCount {
+                key: type_key(entry_type).to_string(),
+                count: by_type.get(type_key(entry_type)).copied().unwrap_or(0),
+            })
+            .collect::<Vec<_>>();
+        let milestones = by_type[0].count;
+
+        let first_day = since.with_timezone(&Local).date_naive();
+        let last_day = until.with_timezone(&Local).date_naive();
+        let by_day = first_day
+            .iter_days()
+            .take_while(|date| *date <= last_day)
+            .map(|date| DayCount { date, count: by_day.get(&date).copied().unwrap_or(0) })
+            .collect();
+
+        let pane_count = by_pane.len();
+        let mut busiest_panes = ranked(by_pane.into_iter());
+        busiest_panes.truncate(top);
+
+        let mut gaps: Vec<Duration> = Vec::new();
+        for mut times in checkpoints.into_values() {
+            times.sort();
+            gaps.extend(times.windows(2).map(|pair| pair[1] - pair[0]));
+        }
+        let avg_checkpoint_interval_minutes = (!gaps.is_empty())
+            .then(|| gaps.iter().map(|gap| gap.num_seconds() as f64).sum::<f64>() / gaps.len() as f64 / 60.0);
+
+        Self {
+            since,
+            until,
+            total_entries,
+            pane_count,
+            by_type,
+            by_source: ranked(by_source.into_iter().map(|(source, count)| (source.to_string(), count))),
+            by_day,
+            busiest_panes,
+            milestone_rate: if total_entries == 0 { 0.0 } else { milestones as f64 / total_entries as f64 },
+            avg_checkpoint_interval_minutes,
+        }
+    }
+
+    /// Text dashboard with bars scaled to the largest count in each section.
+    /// `unicode` selects block characters over ASCII.
+    pub fn to_dashboard(&self, unicode: bool) -> String {
+        let mut lines = vec![format!(
+            "Activity from {} to {}",
+            self.since.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+            self.until.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+        )];
+        lines.push(String::new());
+        if self.total_entries == 0 {
+            lines.push("No entries in this period".to_string());
+            return lines.join("\n");
+        }
+
+        lines.push(format!("Entries:             {} across {}", self.total_entries, messages::count(self.pane_count, "pane")));
+        lines.push(format!("Milestone rate:      {:.1}%", self.milestone_rate * 100.0));
+        let interval = match self.avg_checkpoint_interval_minutes {
+            Some(minutes) => format_minutes(minutes),
+            None => "-".to_string(),
+        };
+        lines.push(format!("Checkpoint interval: {} on average", interval));
+
+        let daily: Vec<usize> = self.by_day.iter().map(|day| day.count).collect();
+        let busiest = self.by_day.iter().max_by_key(|day| day.count).map(|day| day.count).unwrap_or(0);
+        lines.push(String::new());
+        lines.push(format!("Per day (max {})", busiest));
+        lines.push(format!("  {}", sparkline(&daily, unicode)));
+
+        for (title, counts) in [("By type", &self.by_type), ("By source", &self.by_source), ("Busiest panes", &self.busiest_panes)] {
+            lines.push(String::new());
+            lines.push(title.to_string());
+            lines.extend(bar_rows(counts, unicode));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Counts sorted most first, then by key.
+fn ranked(counts: impl Iterator<Item = (String, usize)>) -> Vec<StatCount> {
+    let mut ranked: Vec<StatCount> = counts.map(|(key, count)| StatCount { key, count }).collect();
+    ranked.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+    ranked
+}
+
+fn type_key(entry_type: IntentType) -> &'static str {
+    match entry_type {
+        IntentType::Milestone => "milestone",
+        IntentType::Checkpoint => "checkpoint",
+        IntentType::Exploration => "exploration",
+    }
+}
+
+/// One aligned `key  bar  count` row per entry.
+fn bar_rows(counts: &[StatCount], unicode: bool) -> Vec<String> {
+    let width = counts.iter().map(|c| c.key.chars().count()).max().unwrap_or(0);
+    let max = counts.iter().map(|c| c.count).max().unwrap_or(0);
+    let block = if unicode { '█' } else { '#' };
+    counts
+        .iter()
+        .map(|c| {
+            let len = if max == 0 { 0 } else { (c.count * BAR_WIDTH).div_ceil(max) };
+            let bar: String = std::iter::repeat_n(block, len).collect();
+            format!("  {:<width$}  {:<BAR_WIDTH$}  {}", c.key, bar, c.count)
+        })
+        .collect()
+}
+
+/// One character per value, scaled to the largest. Zero is always a space
+/// so empty days stand out.
+fn sparkline(values: &[usize], unicode: bool) -> String {
+    let levels = if unicode { &SPARK_UNICODE } else { &SPARK_ASCII };
+    let max = values.iter().copied().max().unwrap_or(0);
+    values
+        .iter()
+        .map(|&value| match value {
+            0 => ' ',
+            _ => levels[((value - 1) * levels.len() / max).min(levels.len() - 1)],
+        })
+        .collect()
+}
+
+/// "45m", "3h 20m" or "2d 4h".
+fn format_minutes(minutes: f64) -> String {
+    let minutes = minutes.round() as i64;
+    match minutes {
+        m if m < 60 => format!("{}m", m),
+        m if m < 24 * 60 => format!("{}h {}m", m / 60, m % 60),
+        m => format!("{}d {}h", m / (24 * 60), m % (24 * 60) / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{IntentEntry, IntentSource};
+    use chrono::TimeZone;
+
+    fn at(pane: &str, entry_type: IntentType, source: IntentSource, time: DateTime<Utc>) -> PaneEntry {
+        let mut entry = IntentEntry::new("work");
+        entry.entry_type = entry_type;
+        entry.source = source;
+        entry.timestamp = time;
+        PaneEntry { pane_name: pane.to_string(), entry }
+    }
+
+    #[test]
+    fn test_build_counts_and_rates() {
+        let noon = Local.with_ymd_and_hms(2025, 3, 4, 12, 0, 0).unwrap().with_timezone(&Utc);
+        let entries = vec![
+            at("api", IntentType::Checkpoint, IntentSource::Manual, noon),
+            at("api", IntentType::Checkpoint, IntentSource::Automated, noon + Duration::minutes(30)),
+            at("api", IntentType::Milestone, IntentSource::Manual, noon + Duration::minutes(40)),
+            at("web", IntentType::Checkpoint, IntentSource::Agent, noon - Duration::days(2)),
+            at("web", IntentType::Checkpoint, IntentSource::Manual, noon - Duration::days(2) + Duration::minutes(90)),
+        ];
+
+        let stats = ActivityStats::build(entries, noon - Duration::days(3), noon + Duration::hours(1), 1);
+        assert_eq!(stats.total_entries, 5);
+        assert_eq!(stats.pane_count, 2);
+        let types: Vec<(&str, usize)> = stats.by_type.iter().map(|c| (c.key.as_str(), c.count)).collect();
+        assert_eq!(types, vec![("milestone", 1), ("checkpoint", 4), ("exploration", 0)]);
+        assert_eq!(stats.by_source[0].key, "manual");
+        assert_eq!(stats.by_source[0].count, 3);
+        assert!((stats.milestone_rate - 0.2).abs() < 1e-9);
+        // Intervals of 30m (api) and 90m (web), never across panes
+        assert_eq!(stats.avg_checkpoint_interval_minutes, Some(60.0));
+
+        let busiest: Vec<&str> = stats.busiest_panes.iter().map(|c| c.key.as_str()).collect();
+        assert_eq!(busiest, vec!["api"]);
+
+        let days: Vec<usize> = stats.by_day.iter().map(|d| d.count).collect();
+        assert_eq!(days, vec![0, 2, 0, 3]);
+    }
+
+    #[test]
+    fn test_sparkline_scales_and_marks_empty_days() {
+        assert_eq!(sparkline(&[0, 1, 4, 8], true), " ▁▄█");
+        assert_eq!(sparkline(&[0, 1, 4, 8], false), " _-#");
+        assert_eq!(sparkline(&[], true), "");
+    }
+
+    #[test]
+    fn test_dashboard_ascii_bars() {
+        let now = Utc::now();
+        let stats = ActivityStats::build(
+            vec![at("api", IntentType::Milestone, IntentSource::Manual, now)],
+            now - Duration::hours(1),
+            now,
+            5,
+        );
+        let dashboard = stats.to_dashboard(false);
+        assert!(dashboard.contains("Milestone rate:      100.0%"));
+        assert!(dashboard.contains(&format!("  milestone    {:<30}  1", "#".repeat(30))));
+        assert!(!dashboard.contains('█'));
+    }
+
+    #[test]
+    fn test_format_minutes() {
+        assert_eq!(format_minutes(44.6), "45m");
+        assert_eq!(format_minutes(200.0), "3h 20m");
+        assert_eq!(format_minutes(3120.0), "2d 4h");
+    }
+}
@@ -0,0 +1,338 @@
+//! Optional OpenTelemetry tracing, exported as OTLP/HTTP JSON.
+//!
+//! Off unless `observability.otlp_endpoint` is set and the `otlp` feature is
+//! built in; an endpoint without the feature is warned about. Each command (and each
+//! event handled by `zdrive listen`) is one trace under a root span, with a
+//! client span per Zellij subprocess call, Redis operation and LLM request.
+//! Once a Bloodbank correlation ID is known it is attached to every span of
+//! the trace as `perth.correlation_id`. Finished spans are buffered and sent
+//! by [`flush`].
+
+use anyhow::{anyhow, Result};
+#[cfg(feature = "otlp")]
+use anyhow::Context;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+#[cfg(feature = "otlp")]
+use std::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SERVICE_NAME: &str = "perth";
+#[cfg(feature = "otlp")]
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(3);
+/// Spans held between flushes; beyond this the oldest are dropped
+const MAX_BUFFERED_SPANS: usize = 2_048;
+
+// OTLP span kinds
+const KIND_INTERNAL: u8 = 1;
+const KIND_CLIENT: u8 = 3;
+
+struct Tracer {
+    url: String,
+    state: Mutex<TraceState>,
+    warned: AtomicBool,
+}
+
+#[derive(Default)]
+struct TraceState {
+    /// The open root span: (trace ID, span ID)
+    root: Option<(String, String)>,
+    correlation_id: Option<String>,
+    finished: Vec<Value>,
+}
+
+static TRACER: OnceLock<Tracer> = OnceLock::new();
+
+/// Turn tracing on, exporting to the collector at `endpoint`. The OTLP
+/// `/v1/traces` path is appended unless already present.
+#[cfg(feature = "otlp")]
+pub fn init(endpoint: &str) {
+    let endpoint = endpoint.trim_end_matches('/');
+    let url = if endpoint.ends_with("/v1/traces") {
+        endpoint.to_string()
+    } else {
+        format!("{}/v1/traces", endpoint)
+    };
+    let _ = TRACER.set(Tracer {
+        url,
+        state: Mutex::default(),
+        warned: AtomicBool::new(false),
+    });
+}
+
+/// Without the `otlp` feature there is no HTTP client to export with, so
+/// tracing stays off and the configured endpoint is reported as ignored.
+#[cfg(not(feature = "otlp"))]
+pub fn init(endpoint: &str) {
+    tracing::warn!(
+        "observability.otlp_endpoint is set to {} but zdrive was built without the `otlp` feature; tracing is off",
+        endpoint
+    );
+}
+
+fn state() -> Option<MutexGuard<'static, TraceState>> {
+    TRACER.get().map(|tracer| tracer.state.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// Tag the rest of the current trace with a Bloodbank correlation ID.
+pub fn set_correlation_id(id: &str) {
+    if let Some(mut state) = state() {
+        state.correlation_id = Some(id.to_string());
+    }
+}
+
+fn new_id(hex_len: usize) -> String {
+    uuid::Uuid::new_v4().simple().to_string()[..hex_len].to_string()
+}
+
+fn now_nanos() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0)
+}
+
+/// An open span, recorded when dropped. Does nothing when tracing is off.
+pub struct Span {
+    inner: Option<SpanData>,
+}
+
+struct SpanData {
+    trace_id: String,
+    span_id: String,
+    parent_id: Option<String>,
+    name: String,
+    kind: u8,
+    start: u64,
+    attributes: Vec<(&'static str, String)>,
+    error: Option<String>,
+    /// For root spans, the trace this one interrupted, resumed on drop
+    previous: Option<SavedTrace>,
+}
+
+/// An interrupted trace: its root (trace ID, span ID) and correlation ID
+type SavedTrace = (Option<(String, String)>, Option<String>);
+
+/// Start a new trace for one unit of work. Spans opened until this one is
+/// dropped belong to it; an enclosing trace resumes afterwards.
+pub fn root_span(name: impl Into<String>) -> Span {
+    let Some(mut state) = state() else {
+        return Span { inner: None };
+    };
+    let trace_id = new_id(32);
+    let span_id = new_id(16);
+    let previous_root = state.root.replace((trace_id.clone(), span_id.clone()));
+    let previous_correlation = state.correlation_id.take();
+    Span {
+        inner: Some(SpanData {
+            trace_id,
+            span_id,
+            parent_id: None,
+            name: name.into(),
+            kind: KIND_INTERNAL,
+            start: now_nanos(),
+            attributes: Vec::new(),
+            error: None,
+            previous: Some((previous_root, previous_correlation)),
+        }),
+    }
+}
+
+/// Start a span for a call out of the process (Zellij, Redis, an LLM)
+/// under the current root span.
+pub fn client_span(name: impl Into<String>) -> Span {
+    let Some(state) = state() else {
+        return Span { inner: None };
+    };
+    let (trace_id, parent_id) = match &state.root {
+        Some((trace_id, span_id)) => (trace_id.clone(), Some(span_id.clone())),
+        None => (new_id(32), None),
+    };
+    Span {
+        inner: Some(SpanData {
+            trace_id,
+            span_id: new_id(16),
+            parent_id,
+            name: name.into(),
+            kind: KIND_CLIENT,
+            start: now_nanos(),
+            attributes: Vec::new(),
+            error: None,
+            previous: None,
+        }),
+    }
+}
+
+impl Span {
+    /// Builder method to attach an attribute
+    pub fn with_attribute(mut self, key: &'static str, value: impl Into<String>) -> Self {
+        if let Some(data) = &mut self.inner {
+            data.attributes.push((key, value.into()));
+        }
+        self
+    }
+
+    /// Mark the span as failed.
+    pub fn fail(&mut self, error: impl std::fmt::Display) {
+        if let Some(data) = &mut self.inner {
+            data.error = Some(error.to_string());
+        }
+    }
+
+    /// Mark the span as failed if `result` is an error, passing it through.
+    pub fn record<T>(&mut self, result: Result<T>) -> Result<T> {
+        if let Err(e) = &result {
+            self.fail(format!("{:#}", e));
+        }
+        result
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let Some(data) = self.inner.take() else {
+            return;
+        };
+        let Some(mut state) = state() else {
+            return;
+        };
+
+        let mut attributes = data.attributes;
+        if let Some(id) = &state.correlation_id {
+            attributes.push(("perth.correlation_id", id.clone()));
+        }
+        if let Some((root, correlation_id)) = data.previous {
+            state.root = root;
+            state.correlation_id = correlation_id;
+        }
+
+        let mut span = json!({
+            "traceId": data.trace_id,
+            "spanId": data.span_id,
+            "name": data.name,
+            "kind": data.kind,
+            "startTimeUnixNano": data.start.to_string(),
+            "endTimeUnixNano": now_nanos().to_string(),
+            "attributes": attributes.iter().map(|(key, value)| attribute(key, value)).collect::<Vec<_>>(),
+        });
+        if let Some(parent_id) = data.parent_id {
+            span["parentSpanId"] = json!(parent_id);
+        }
+        if let Some(message) = data.error {
+            span["status"] = json!({ "code": 2, "message": message });
+        }
+
+        if state.finished.len() >= MAX_BUFFERED_SPANS {
+            state.finished.remove(0);
+        }
+        state.finished.push(span);
+    }
+}
+
+fn attribute(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+/// Send buffered spans to the collector. Spans are discarded whether or not
+/// the export succeeds, and only the first failure is reported so an
+/// unreachable collector doesn't flood a long-running `listen` or daemon.
+pub async fn flush() {
+    let Some(tracer) = TRACER.get() else {
+        return;
+    };
+    let spans = state().map(|mut state| std::mem::take(&mut state.finished)).unwrap_or_default();
+    if spans.is_empty() {
+        return;
+    }
+    if let Err(e) = export(&tracer.url, spans).await {
+        if !tracer.warned.swap(true, Ordering::Relaxed) {
+            tracing::warn!("{:#}", e);
+        }
+    }
+}
+
+async fn export(url: &str, spans: Vec<Value>) -> Result<()> {
+    let body = json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [
+                    attribute("service.name", SERVICE_NAME),
+                    attribute("service.version", env!("CARGO_PKG_VERSION")),
+                ]
+            },
+            "scopeSpans": [{
+                "scope": { "name": "zdrive" },
+                "spans": spans,
+            }]
+        }]
+    });
+    post(url, &body).await
+}
+
+#[cfg(feature = "otlp")]
+async fn post(url: &str, body: &Value) -> Result<()> {
+    let response = reqwest::Client::new()
+        .post(url)
+        .timeout(EXPORT_TIMEOUT)
+        .json(body)
+        .send()
+        .await
+        .with_context(|| format!("failed to export traces to {}", url))?;
+    if !response.status().is_success() {
+        return Err(anyhow!("failed to export traces to {}: HTTP {}", url, response.status()));
+    }
+    Ok(())
+}
+
+/// Unreachable in practice: [`init`] leaves tracing off without the `otlp`
+/// feature, so there are never spans to send.
+#[cfg(not(feature = "otlp"))]
+async fn post(url: &str, _body: &Value) -> Result<()> {
+    Err(anyhow!("cannot export traces to {}: zdrive was built without the `otlp` feature", url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "otlp")]
+    fn finished(name: &str) -> Value {
+        let state = state().unwrap();
+        state.finished.iter().find(|span| span["name"] == name).cloned().unwrap()
+    }
+
+    #[cfg(feature = "otlp")]
+    #[test]
+    fn test_spans_share_trace_and_correlation() {
+        init("http://localhost:4318/");
+        assert_eq!(TRACER.get().unwrap().url, "http://localhost:4318/v1/traces");
+
+        // Span names are unique so parallel tests recording spans don't interfere
+        {
+            let _root = root_span("test root");
+            set_correlation_id("corr-123");
+            let mut child = client_span("test child").with_attribute("zellij.args", "new-tab");
+            let _ = child.record::<()>(Err(anyhow!("zellij action failed")));
+        }
+
+        let root = finished("test root");
+        let child = finished("test child");
+        assert_eq!(child["traceId"], root["traceId"]);
+        assert_eq!(child["parentSpanId"], root["spanId"]);
+        assert_eq!(root["traceId"].as_str().unwrap().len(), 32);
+        assert_eq!(child["spanId"].as_str().unwrap().len(), 16);
+        assert!(root.get("parentSpanId").is_none());
+        assert_eq!(child["kind"], KIND_CLIENT);
+        assert_eq!(child["status"]["code"], 2);
+        assert!(root.get("status").is_none());
+        assert_eq!(child["attributes"][0], attribute("zellij.args", "new-tab"));
+        assert_eq!(child["attributes"][1], attribute("perth.correlation_id", "corr-123"));
+        assert_eq!(root["attributes"][0], attribute("perth.correlation_id", "corr-123"));
+    }
+
+    #[cfg(not(feature = "otlp"))]
+    #[test]
+    fn test_endpoint_without_otlp_feature_leaves_tracing_off() {
+        init("http://localhost:4318");
+        assert!(TRACER.get().is_none());
+        assert!(root_span("test root").inner.is_none());
+    }
+}
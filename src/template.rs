@@ -0,0 +1,203 @@
+//! Session templates for `zdrive template`.
+//!
+//! `template save` records the tabs and tracked panes of a session. Each
+//! `--var name=value` replaces that value wherever it appears in tab names,
+//! pane names, cwds and commands with a `{{name}}` placeholder, remembering
+//! the value as the variable's default. `template apply` fills the
+//! placeholders back in, from `--var` or the defaults, and brings the result
+//! up like a workspace file.
+
+use crate::types::{SessionTemplate, TemplatePane, TemplateTab};
+use crate::workspace::{Workspace, WorkspacePane, WorkspaceTab};
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Check that variables have usable names and non-empty values.
+pub fn validate_variables(vars: &[(String, String)]) -> Result<()> {
+    for (name, value) in vars {
+        if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(anyhow!("invalid variable name '{}' (use letters, digits, '-' and '_')", name));
+        }
+        if value.is_empty() {
+            return Err(anyhow!("variable '{}' needs a value", name));
+        }
+    }
+    Ok(())
+}
+
+/// Replace each variable's value in `text` with its placeholder, longest
+/// values first so a path isn't split by a shorter value inside it.
+pub fn parameterize(text: &str, vars: &[(String, String)]) -> String {
+    let mut vars: Vec<&(String, String)> = vars.iter().collect();
+    vars.sort_by_key(|(_, value)| std::cmp::Reverse(value.len()));
+
+    // Replace in two passes so a value can't match inside an earlier placeholder
+    let mut result = text.to_string();
+    for (index, (_, value)) in vars.iter().enumerate() {
+        result = result.replace(value.as_str(), &format!("\u{0}{}\u{0}", index));
+    }
+    for (index, (name, _)) in vars.iter().enumerate() {
+        result = result.replace(&format!("\u{0}{}\u{0}", index), &format!("{{{{{}}}}}", name));
+    }
+    result
+}
+
+/// Build a template from tabs of tracked panes.
+pub fn build(
+    name: &str,
+    source_session: &str,
+    description: Option<String>,
+    tabs: Vec<TemplateTab>,
+    vars: &[(String, String)],
+) -> SessionTemplate {
+    let tabs = tabs
+        .into_iter()
+        .map(|tab| TemplateTab {
+            name: parameterize(&tab.name, vars),
+            panes: tab
+                .panes
+                .into_iter()
+                .map(|pane| TemplatePane {
+                    name: parameterize(&pane.name, vars),
+                    cwd: pane.cwd.map(|cwd| parameterize(&cwd, vars)),
+                    command: pane.command.map(|command| parameterize(&command, vars)),
+                })
+                .collect(),
+        })
+        .collect();
+
+    SessionTemplate {
+        name: name.to_string(),
+        created_at: chrono::Utc::now(),
+        description,
+        source_session: source_session.to_string(),
+        variables: vars.iter().cloned().collect(),
+        tabs,
+    }
+}
+
+/// Fill in `text`'s placeholders. Unknown variables are an error.
+fn substitute(text: &str, values: &BTreeMap<String, String>) -> Result<String> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}").map(|end| start + 2 + end) else {
+            break;
+        };
+        let name = rest[start + 2..end].trim();
+        let value = values.get(name).ok_or_else(|| anyhow!("no value for variable '{}'", name))?;
+        result.push_str(&rest[..start]);
+        result.push_str(value);
+        rest = &rest[end + 2..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Turn a template into a workspace, with `overrides` replacing the saved
+/// variable values. With `tab`, every pane goes into that one tab. Panes
+/// without a cwd, or with a relative one, start from `base`.
+pub fn render(
+    template: &SessionTemplate,
+    overrides: &[(String, String)],
+    tab: Option<&str>,
+    base: &Path,
+) -> Result<Workspace> {
+    let mut values = template.variables.clone();
+    for (name, value) in overrides {
+        if !values.contains_key(name) {
+            let known: Vec<&str> = values.keys().map(String::as_str).collect();
+            return Err(anyhow!(
+                "template '{}' has no variable '{}' (variables: {})",
+                template.name,
+                name,
+                if known.is_empty() { "none".to_string() } else { known.join(", ") }
+            ));
+        }
+        values.insert(name.clone(), value.clone());
+    }
+
+    let mut tabs: Vec<WorkspaceTab> = Vec::new();
+    for template_tab in &template.tabs {
+        let mut panes = Vec::new();
+        for pane in &template_tab.panes {
+            let cwd = match &pane.cwd {
+                Some(cwd) => base.join(substitute(cwd, &values)?),
+                None => base.to_path_buf(),
+            };
+            panes.push(WorkspacePane {
+                name: substitute(&pane.name, &values)?,
+                cwd,
+                run: pane.command.as_deref().map(|command| substitute(command, &values)).transpose()?,
+                intent: None,
+            });
+        }
+
+        match (tab, tabs.first_mut()) {
+            (Some(_), Some(merged)) => merged.panes.extend(panes),
+            (Some(tab), None) => tabs.push(WorkspaceTab { name: tab.to_string(), vertical: true, panes }),
+            (None, _) => {
+                let name = substitute(&template_tab.name, &values)?;
+                tabs.push(WorkspaceTab { name, vertical: true, panes });
+            }
+        }
+    }
+
+    Ok(Workspace { source: format!("template '{}'", template.name), tabs })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn test_save_and_render_round_trip() {
+        let saved = vars(&[("repo", "/work/api"), ("branch", "main"), ("root", "/work")]);
+        let tabs = vec![TemplateTab {
+            name: "api(main)".to_string(),
+            panes: vec![
+                TemplatePane {
+                    name: "api-main".to_string(),
+                    cwd: Some("/work/api".to_string()),
+                    command: Some("git checkout main && cargo run".to_string()),
+                },
+                TemplatePane { name: "notes".to_string(), cwd: None, command: None },
+            ],
+        }];
+        let template = build("api-dev", "dev", None, tabs, &saved);
+
+        // The longer repo path wins over the root it contains
+        let pane = &template.tabs[0].panes[0];
+        assert_eq!(template.tabs[0].name, "api({{branch}})");
+        assert_eq!(pane.cwd.as_deref(), Some("{{repo}}"));
+        assert_eq!(pane.command.as_deref(), Some("git checkout {{branch}} && cargo run"));
+
+        let workspace = render(&template, &vars(&[("repo", "/work/web"), ("branch", "feat-x")]), None, Path::new("/tmp")).unwrap();
+        let tab = &workspace.tabs[0];
+        assert_eq!(tab.name, "api(feat-x)");
+        assert_eq!(tab.panes[0].name, "api-feat-x");
+        assert_eq!(tab.panes[0].cwd, Path::new("/work/web"));
+        assert_eq!(tab.panes[0].run.as_deref(), Some("git checkout feat-x && cargo run"));
+        assert_eq!(tab.panes[1].cwd, Path::new("/tmp"));
+
+        // Defaults reproduce the original
+        let original = render(&template, &[], Some("review"), Path::new("/tmp")).unwrap();
+        assert_eq!(original.tabs[0].name, "review");
+        assert_eq!(original.tabs[0].panes[0].name, "api-main");
+    }
+
+    #[test]
+    fn test_rejects_unknown_and_invalid_variables() {
+        let template = build("t", "dev", None, Vec::new(), &vars(&[("repo", "/work")]));
+        assert!(render(&template, &vars(&[("branch", "x")]), None, Path::new("/")).is_err());
+        assert!(substitute("{{missing}}", &BTreeMap::new()).is_err());
+        assert_eq!(substitute("a {{ b", &BTreeMap::new()).unwrap(), "a {{ b");
+        assert!(validate_variables(&vars(&[("has space", "x")])).is_err());
+        assert!(validate_variables(&vars(&[("repo", "")])).is_err());
+    }
+}
@@ -0,0 +1,138 @@
+//! Real-time pane tracking through the `perth-tracker` Zellij plugin.
+//!
+//! `zdrive watch --plugin <url>` starts the plugin with `zellij pipe` and
+//! subscribes to it. Whenever Zellij reports a pane or tab change, the plugin
+//! writes the session's terminal panes as one JSON line:
+//!
+//! ```json
+//! {"panes":[{"id":3,"title":"api","tab":"backend","focused":true}]}
+//! ```
+//!
+//! Comparing consecutive lines gives the opens, closes, renames and focus
+//! changes that `watch` applies to Redis as they happen, instead of waiting
+//! for the next reconcile.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+use std::time::Duration;
+
+/// Name of the pipe `zdrive watch` subscribes on.
+pub const PIPE_NAME: &str = "perth-tracker";
+
+/// How often `watch` writes a line to the pipe to renew its subscription.
+/// The plugin drops subscribers that stay quiet for two of these.
+pub const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A terminal pane as the plugin reports it.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TrackedPane {
+    /// Zellij pane ID, as in `ZELLIJ_PANE_ID`
+    pub id: u32,
+    /// Pane title, which is the Perth name for panes zdrive created
+    pub title: String,
+    pub tab: String,
+    #[serde(default)]
+    pub focused: bool,
+}
+
+/// Every terminal pane in the session at one moment.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct PaneManifest {
+    pub panes: Vec<TrackedPane>,
+}
+
+/// One change between two manifests.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaneEvent {
+    Opened(TrackedPane),
+    Closed(TrackedPane),
+    Renamed { pane: TrackedPane, from: String },
+    Focused(TrackedPane),
+}
+
+/// What applying an event did to a tracked pane, for `zdrive watch` output.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrackedChange {
+    /// The pane closed and was marked stale
+    Closed(String),
+    /// A stale pane is open again
+    Back(String),
+    /// The pane was renamed in Zellij; Perth keeps its name
+    RenamedInZellij { pane: String, title: String },
+}
+
+/// Parse one line of plugin output.
+pub fn parse(line: &str) -> Result<PaneManifest> {
+    serde_json::from_str(line).context("invalid perth-tracker update")
+}
+
+/// Changes from `previous` to `current`, matching panes by ID. Focus is only
+/// reported when it moves to a pane that was already open.
+pub fn diff(previous: &PaneManifest, current: &PaneManifest) -> Vec<PaneEvent> {
+    let mut events = Vec::new();
+    for pane in &current.panes {
+        match previous.panes.iter().find(|old| old.id == pane.id) {
+            None => events.push(PaneEvent::Opened(pane.clone())),
+            Some(old) => {
+                if old.title != pane.title {
+                    events.push(PaneEvent::Renamed { pane: pane.clone(), from: old.title.clone() });
+                }
+                if pane.focused && !old.focused {
+                    events.push(PaneEvent::Focused(pane.clone()));
+                }
+            }
+        }
+    }
+    for old in &previous.panes {
+        if !current.panes.iter().any(|pane| pane.id == old.id) {
+            events.push(PaneEvent::Closed(old.clone()));
+        }
+    }
+    events
+}
+
+/// The Zellij plugin URL for `--plugin`: URLs pass through, paths become
+/// absolute `file:` URLs.
+pub fn plugin_url(plugin: &str) -> String {
+    if plugin.contains(':') {
+        return plugin.to_string();
+    }
+    let path = Path::new(plugin);
+    let path = match std::env::current_dir() {
+        Ok(cwd) if path.is_relative() => cwd.join(path),
+        _ => path.to_path_buf(),
+    };
+    format!("file:{}", path.display())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pane(id: u32, title: &str, focused: bool) -> TrackedPane {
+        TrackedPane { id, title: title.to_string(), tab: "backend".to_string(), focused }
+    }
+
+    #[test]
+    fn test_diff_manifests() {
+        let line = r#"{"panes":[{"id":1,"title":"api","tab":"backend","focused":true},{"id":2,"title":"zsh","tab":"backend"}]}"#;
+        let previous = parse(line).unwrap();
+        assert_eq!(previous.panes[1], pane(2, "zsh", false));
+
+        let current = PaneManifest { panes: vec![pane(2, "logs", true), pane(3, "db", false)] };
+        assert_eq!(
+            diff(&previous, &current),
+            vec![
+                PaneEvent::Renamed { pane: pane(2, "logs", true), from: "zsh".to_string() },
+                PaneEvent::Focused(pane(2, "logs", true)),
+                PaneEvent::Opened(pane(3, "db", false)),
+                PaneEvent::Closed(pane(1, "api", true)),
+            ]
+        );
+        assert!(diff(&current, &current).is_empty());
+        assert!(parse("not json").is_err());
+        assert_eq!(plugin_url("https://example.com/perth-tracker.wasm"), "https://example.com/perth-tracker.wasm");
+        assert!(plugin_url("perth-tracker.wasm").starts_with("file:/"));
+    }
+}
@@ -1,6 +1,14 @@
+use anyhow::Context;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
 use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
 use uuid::Uuid;
 
 // ============================================================================
@@ -12,7 +20,7 @@ use uuid::Uuid;
 /// - `Milestone`: Major accomplishment or significant progress point
 /// - `Checkpoint`: Regular progress marker during work
 /// - `Exploration`: Investigative or research-oriented activity
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum IntentType {
     /// Major accomplishment or significant progress point
@@ -34,7 +42,8 @@ impl Default for IntentType {
 /// - `Manual`: User explicitly logged via CLI command
 /// - `Automated`: System-generated based on activity detection
 /// - `Agent`: Created by an AI agent during assisted workflow
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+/// - `Imported`: Seeded from another pane's handoff bundle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum IntentSource {
     /// User manually logged this entry (default)
@@ -43,6 +52,8 @@ pub enum IntentSource {
     Automated,
     /// Created by an AI agent
     Agent,
+    /// Imported from a handoff bundle
+    Imported,
 }
 
 impl Default for IntentSource {
@@ -55,7 +66,7 @@ impl Default for IntentSource {
 ///
 /// Each IntentEntry captures what the developer was working on at a point in time,
 /// including their goal, artifacts touched, and progress indicators.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct IntentEntry {
     /// Unique identifier for this entry
     pub id: Uuid,
@@ -75,9 +86,25 @@ pub struct IntentEntry {
     /// Description of progress made toward the goal
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub goal_delta: Option<String>,
+    /// The pane goal this entry reports progress on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub goal_id: Option<Uuid>,
     /// How this entry was created
     #[serde(default)]
     pub source: IntentSource,
+    /// Program that logged the entry, e.g. "claude-code" or "aider"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_detail: Option<String>,
+    /// Correlation ID of the tab the pane was in, linking the entry to the
+    /// Bloodbank events that triggered the work
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    /// SHA of the git commit this entry records
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// Free-form labels such as "bugfix", stored lowercase without '#'
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
 }
 
 impl IntentEntry {
@@ -92,7 +119,12 @@ impl IntentEntry {
             artifacts: Vec::new(),
             commands_run: None,
             goal_delta: None,
+            goal_id: None,
             source: IntentSource::default(),
+            source_detail: None,
+            correlation_id: None,
+            commit: None,
+            tags: Vec::new(),
         }
     }
 
@@ -114,12 +146,53 @@ impl IntentEntry {
         self
     }
 
+    /// Builder method to set the program that logged the entry
+    pub fn with_source_detail(mut self, detail: impl Into<String>) -> Self {
+        self.source_detail = Some(detail.into());
+        self
+    }
+
     /// Builder method to set goal delta
     pub fn with_goal_delta(mut self, delta: impl Into<String>) -> Self {
         self.goal_delta = Some(delta.into());
         self
     }
 
+    /// Builder method to link the entry to a goal
+    pub fn with_goal(mut self, goal_id: Uuid) -> Self {
+        self.goal_id = Some(goal_id);
+        self
+    }
+
+    /// Builder method to set the correlation ID
+    pub fn with_correlation_id(mut self, correlation_id: impl Into<String>) -> Self {
+        self.correlation_id = Some(correlation_id.into());
+        self
+    }
+
+    /// Builder method to set the git commit SHA
+    pub fn with_commit(mut self, sha: impl Into<String>) -> Self {
+        self.commit = Some(sha.into());
+        self
+    }
+
+    /// Builder method to set tags, normalized and deduplicated in order
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags.clear();
+        for tag in tags.iter().map(|tag| normalize_tag(tag)).filter(|tag| !tag.is_empty()) {
+            if !self.tags.contains(&tag) {
+                self.tags.push(tag);
+            }
+        }
+        self
+    }
+
+    /// Whether the entry carries `tag` (compared after normalizing)
+    pub fn has_tag(&self, tag: &str) -> bool {
+        let tag = normalize_tag(tag);
+        self.tags.contains(&tag)
+    }
+
     /// Builder method to set commands run count
     pub fn with_commands_run(mut self, count: usize) -> Self {
         self.commands_run = Some(count);
@@ -141,8 +214,122 @@ impl IntentEntry {
             IntentSource::Manual => "manual",
             IntentSource::Automated => "automated",
             IntentSource::Agent => "agent",
+            IntentSource::Imported => "imported",
+        }
+    }
+
+    /// Short form of the entry ID, enough to reference it from the CLI
+    pub fn short_id(&self) -> String {
+        self.id.simple().to_string()[..8].to_string()
+    }
+}
+
+/// Canonical form of a tag: trimmed, lowercase, without a leading '#'.
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().trim_start_matches('#').to_lowercase()
+}
+
+/// How often each tag appears in `entries`, most used first, then by name.
+pub fn tag_counts(entries: &[IntentEntry]) -> Vec<(String, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for tag in entries.iter().flat_map(|entry| &entry.tags) {
+        *counts.entry(tag.as_str()).or_default() += 1;
+    }
+    let mut counts: Vec<(String, usize)> = counts.into_iter().map(|(tag, n)| (tag.to_string(), n)).collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+/// A note attached to an existing history entry, such as a review finding.
+///
+/// Annotations on the same entry form a thread, ordered oldest first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Annotation {
+    /// Unique identifier for this annotation
+    pub id: Uuid,
+    /// The history entry this annotation belongs to
+    pub entry_id: Uuid,
+    /// When the annotation was added
+    pub timestamp: DateTime<Utc>,
+    /// The annotation text
+    pub note: String,
+    /// Who added it, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+}
+
+impl Annotation {
+    pub fn new(entry_id: Uuid, note: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            entry_id,
+            timestamp: Utc::now(),
+            note: note.into(),
+            author: None,
         }
     }
+
+    /// Builder method to set the author
+    pub fn with_author(mut self, author: impl Into<String>) -> Self {
+        self.author = Some(author.into());
+        self
+    }
+}
+
+/// Lifecycle of a pane goal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GoalStatus {
+    /// The goal the pane is working toward; at most one per pane
+    Active,
+    /// Finished with `zdrive goal complete`
+    Completed,
+    /// Replaced by a newer goal before it was completed
+    Abandoned,
+}
+
+/// What a pane is working toward, set with `zdrive goal set`.
+///
+/// Entries logged with a goal delta reference the active goal by ID, so its
+/// progress can be read back from the pane's history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Goal {
+    pub id: Uuid,
+    pub title: String,
+    pub status: GoalStatus,
+    pub set_at: DateTime<Utc>,
+    /// When the goal was completed or abandoned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+impl Goal {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            title: title.into(),
+            status: GoalStatus::Active,
+            set_at: Utc::now(),
+            closed_at: None,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.status == GoalStatus::Active
+    }
+
+    /// Mark the goal completed or abandoned as of now.
+    pub fn close(&mut self, status: GoalStatus) {
+        self.status = status;
+        self.closed_at = Some(Utc::now());
+    }
+}
+
+/// A goal with the history entries that reported progress on it, oldest first.
+#[derive(Debug, Clone, Serialize)]
+pub struct GoalProgress {
+    pub goal: Goal,
+    pub entries: Vec<IntentEntry>,
 }
 
 // ============================================================================
@@ -166,6 +353,9 @@ pub struct TabRecord {
     pub created_at: String,
     /// Last time this tab was accessed
     pub last_accessed: String,
+    /// When the tab was archived after its work completed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<String>,
     /// Additional metadata key-value pairs
     #[serde(default)]
     pub meta: HashMap<String, String>,
@@ -180,6 +370,7 @@ impl TabRecord {
             correlation_id: None,
             created_at: now.clone(),
             last_accessed: now,
+            archived_at: None,
             meta: HashMap::new(),
         }
     }
@@ -256,7 +447,7 @@ impl PaneRecord {
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum PaneStatus {
     Found,
@@ -264,7 +455,7 @@ pub enum PaneStatus {
     Missing,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct PaneInfoOutput {
     pub pane_name: String,
     pub session: String,
@@ -295,6 +486,26 @@ impl PaneInfoOutput {
     }
 }
 
+/// Body of the `pane history --format json` document.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct HistoryOutput {
+    /// The pane whose history this is
+    pub pane: String,
+    /// Title of the pane's active goal, if one is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goal: Option<String>,
+    /// ID of the active goal, which entries reporting progress on it reference
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goal_id: Option<Uuid>,
+    /// When the active goal was set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goal_set_at: Option<DateTime<Utc>>,
+    /// Entries shown, oldest first
+    pub entries: Vec<IntentEntry>,
+    /// Annotations on the entries shown
+    pub annotations: Vec<Annotation>,
+}
+
 // ============================================================================
 // Session Restoration Types (Perth v2.1 - STORY-040)
 // ============================================================================
@@ -303,7 +514,7 @@ impl PaneInfoOutput {
 ///
 /// Captures all information needed to recreate a pane, including
 /// its position, working directory, and running command.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PaneSnapshot {
     /// Pane name (used for identification)
     pub name: String,
@@ -324,12 +535,39 @@ pub struct PaneSnapshot {
     /// Additional metadata from Perth tracking
     #[serde(default)]
     pub meta: HashMap<String, String>,
+    /// Tail of the pane's terminal output, secret-filtered, gzip-compressed
+    /// and base64-encoded; see [`PaneSnapshot::scrollback_text`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scrollback: Option<String>,
+}
+
+impl PaneSnapshot {
+    /// Store `text` as the pane's captured scrollback.
+    pub fn set_scrollback(&mut self, text: &str) -> anyhow::Result<()> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(text.as_bytes())?;
+        self.scrollback = Some(BASE64.encode(encoder.finish()?));
+        Ok(())
+    }
+
+    /// The captured scrollback, if the snapshot included it.
+    pub fn scrollback_text(&self) -> anyhow::Result<Option<String>> {
+        let Some(encoded) = &self.scrollback else {
+            return Ok(None);
+        };
+        let compressed = BASE64.decode(encoded).context("invalid scrollback encoding")?;
+        let mut text = String::new();
+        GzDecoder::new(compressed.as_slice())
+            .read_to_string(&mut text)
+            .context("failed to decompress scrollback")?;
+        Ok(Some(text))
+    }
 }
 
 /// Snapshot of a tab's state including all panes.
 ///
 /// Captures tab layout and pane configuration for restoration.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TabSnapshot {
     /// Tab name
     pub name: String,
@@ -352,7 +590,7 @@ pub struct TabSnapshot {
 ///
 /// This is the top-level structure stored in Redis for restoration.
 /// Redis key format: `perth:snapshots:{session}:{name}`
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SessionSnapshot {
     /// Snapshot schema version for forward compatibility
     pub schema_version: String,
@@ -416,6 +654,56 @@ impl SessionSnapshot {
     }
 }
 
+// ============================================================================
+// Session Templates
+// ============================================================================
+
+/// A pane in a session template. Names, cwds and commands may contain
+/// `{{variable}}` placeholders.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TemplatePane {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// Command the pane runs when created
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+}
+
+/// A tab in a session template and its panes, in order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TemplateTab {
+    pub name: String,
+    pub panes: Vec<TemplatePane>,
+}
+
+/// Reusable session structure saved by `zdrive template save`.
+///
+/// Unlike a snapshot, a template isn't tied to a session: values such as
+/// a repository path or branch are replaced by variables, filled in again
+/// when the template is applied.
+/// Redis key format: `perth:templates:{name}`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SessionTemplate {
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Session the template was saved from
+    pub source_session: String,
+    /// Variable names and the values they replaced, used as defaults
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+    pub tabs: Vec<TemplateTab>,
+}
+
+impl SessionTemplate {
+    /// Get Redis key for this template
+    pub fn redis_key(&self) -> String {
+        format!("perth:templates:{}", self.name)
+    }
+}
+
 /// Warning level for restoration issues.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -510,6 +798,12 @@ pub struct RestoreReport {
     pub snapshot_name: String,
     /// Session that was restored to
     pub session: String,
+    /// Session the snapshot was taken in, when restored into another one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_session: Option<String>,
+    /// Whether the target session had to be started for the restore
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub session_created: bool,
     /// Number of tabs restored
     pub tabs_restored: usize,
     /// Number of panes restored
@@ -522,6 +816,9 @@ pub struct RestoreReport {
     pub restored_at: DateTime<Utc>,
     /// Duration of restoration in milliseconds
     pub duration_ms: u64,
+    /// Saved working directories that don't exist on this machine
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing_cwds: Vec<String>,
     /// Warnings and issues encountered
     #[serde(default)]
     pub warnings: Vec<RestoreWarning>,
@@ -534,12 +831,15 @@ impl RestoreReport {
             status: RestoreStatus::Success,
             snapshot_name: snapshot_name.into(),
             session: session.into(),
+            original_session: None,
+            session_created: false,
             tabs_restored: 0,
             panes_restored: 0,
             tabs_failed: 0,
             panes_failed: 0,
             restored_at: Utc::now(),
             duration_ms: 0,
+            missing_cwds: Vec::new(),
             warnings: Vec::new(),
         }
     }
@@ -573,6 +873,39 @@ impl RestoreReport {
     }
 }
 
+// ============================================================================
+// LLM Usage Accounting
+// ============================================================================
+
+/// LLM token usage and estimated spend for one provider and model in one month.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LlmUsage {
+    /// Month the requests were made, as `YYYY-MM` (UTC)
+    pub month: String,
+    pub provider: String,
+    pub model: String,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Estimated cost in USD; `None` when the model has no known price
+    pub cost_usd: Option<f64>,
+}
+
+impl LlmUsage {
+    /// Usage of a single request made now.
+    pub fn request(provider: &str, model: &str, input_tokens: u32, output_tokens: u32, cost_usd: Option<f64>) -> Self {
+        Self {
+            month: Utc::now().format("%Y-%m").to_string(),
+            provider: provider.to_string(),
+            model: model.to_string(),
+            requests: 1,
+            input_tokens: input_tokens as u64,
+            output_tokens: output_tokens as u64,
+            cost_usd,
+        }
+    }
+}
+
 // ============================================================================
 // Unit Tests
 // ============================================================================
@@ -624,6 +957,23 @@ mod tests {
         assert!(deserialized.goal_delta.is_none());
     }
 
+    #[test]
+    fn test_intent_entry_tags() {
+        let entry = IntentEntry::new("Fixed login").with_tags(vec!["#BugFix".to_string(), "auth".to_string(), "bugfix".to_string(), " ".to_string()]);
+        assert_eq!(entry.tags, vec!["bugfix", "auth"]);
+        assert!(entry.has_tag("#bugfix"));
+        assert!(!entry.has_tag("perf"));
+
+        // Untagged entries serialize without the field and read back empty
+        let json = serde_json::to_string(&IntentEntry::new("Plain")).unwrap();
+        assert!(!json.contains("tags"));
+        let deserialized: IntentEntry = serde_json::from_str(&json).unwrap();
+        assert!(deserialized.tags.is_empty());
+
+        let entries = vec![entry, IntentEntry::new("Retry").with_tags(vec!["auth".to_string()]), IntentEntry::new("Perf").with_tags(vec!["perf".to_string()])];
+        assert_eq!(tag_counts(&entries), vec![("auth".to_string(), 2), ("bugfix".to_string(), 1), ("perf".to_string(), 1)]);
+    }
+
     #[test]
     fn test_intent_type_serialization() {
         // Test enum serialization with lowercase
@@ -662,6 +1012,10 @@ mod tests {
             serde_json::to_string(&IntentSource::Agent).unwrap(),
             "\"agent\""
         );
+        assert_eq!(
+            serde_json::to_string(&IntentSource::Imported).unwrap(),
+            "\"imported\""
+        );
     }
 
     #[test]
@@ -684,6 +1038,11 @@ mod tests {
         // Optional fields with None should not appear (skip_serializing_if)
         assert!(!json.contains("\"commands_run\""));
         assert!(!json.contains("\"goal_delta\""));
+        assert!(!json.contains("\"correlation_id\""));
+        assert!(!json.contains("\"source_detail\""));
+
+        let attributed = serde_json::to_string(&entry.with_source_detail("aider")).unwrap();
+        assert!(attributed.contains("\"source_detail\":\"aider\""));
     }
 
     #[test]
@@ -693,7 +1052,8 @@ mod tests {
             .with_artifacts(vec!["file1.rs".to_string()])
             .with_source(IntentSource::Automated)
             .with_goal_delta("Completed implementation")
-            .with_commands_run(10);
+            .with_commands_run(10)
+            .with_correlation_id("pr-42");
 
         assert_eq!(entry.summary, "Building feature");
         assert_eq!(entry.entry_type, IntentType::Milestone);
@@ -701,6 +1061,7 @@ mod tests {
         assert_eq!(entry.source, IntentSource::Automated);
         assert_eq!(entry.goal_delta, Some("Completed implementation".to_string()));
         assert_eq!(entry.commands_run, Some(10));
+        assert_eq!(entry.correlation_id, Some("pr-42".to_string()));
     }
 
     // ========================================================================
@@ -809,6 +1170,7 @@ mod tests {
             pane_id: Some("1".to_string()),
             focused: true,
             meta: HashMap::new(),
+            scrollback: None,
         };
 
         let pane2 = PaneSnapshot {
@@ -819,6 +1181,7 @@ mod tests {
             pane_id: Some("2".to_string()),
             focused: false,
             meta: HashMap::new(),
+            scrollback: None,
         };
 
         let tab = TabSnapshot {
@@ -919,6 +1282,7 @@ mod tests {
             pane_id: None,
             focused: false,
             meta: HashMap::new(),
+            scrollback: None,
         };
 
         let json = serde_json::to_string(&pane).expect("Failed to serialize");
@@ -927,10 +1291,18 @@ mod tests {
         assert!(!json.contains("cwd"));
         assert!(!json.contains("command"));
         assert!(!json.contains("pane_id"));
+        assert!(!json.contains("scrollback"));
 
         // Deserialize and verify
-        let deserialized: PaneSnapshot = serde_json::from_str(&json).expect("Failed to deserialize");
+        let mut deserialized: PaneSnapshot = serde_json::from_str(&json).expect("Failed to deserialize");
         assert_eq!(deserialized.name, "minimal");
         assert!(deserialized.cwd.is_none());
+        assert!(deserialized.scrollback_text().unwrap().is_none());
+
+        // Scrollback survives the compressed round trip
+        deserialized.set_scrollback("$ cargo test\ntest result: ok").unwrap();
+        let json = serde_json::to_string(&deserialized).unwrap();
+        let restored: PaneSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.scrollback_text().unwrap().as_deref(), Some("$ cargo test\ntest result: ok"));
     }
 }
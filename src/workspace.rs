@@ -0,0 +1,312 @@
+//! Declarative workspace files for `zdrive up`.
+//!
+//! A project describes its tabs and panes in `perth.toml` or `perth.kdl`
+//! at its root. `zdrive up` compares the file with what's open and tracked,
+//! then creates whatever is missing. Running it again changes nothing.
+//!
+//! ```toml
+//! [[tab]]
+//! name = "backend"
+//! cwd = "services"
+//!
+//! [[tab.pane]]
+//! name = "api"
+//! cwd = "api"
+//! run = "cargo watch -x run"
+//! intent = "Wire up auth middleware"
+//! ```
+//!
+//! ```kdl
+//! tab name="backend" cwd="services" {
+//!     pane name="api" cwd="api" run="cargo watch -x run" intent="Wire up auth middleware"
+//! }
+//! ```
+//!
+//! Relative `cwd`s resolve against the tab's `cwd`, which resolves against
+//! the directory holding the workspace file. Tabs may set `layout` to
+//! "vertical" (the default) or "horizontal".
+
+use crate::kdl;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File names `zdrive up` looks for, in order of preference.
+pub const FILE_NAMES: &[&str] = &["perth.toml", "perth.kdl"];
+
+/// Tabs and panes to bring up, with every `cwd` made absolute.
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    /// Where it came from, e.g. the workspace file's path
+    pub source: String,
+    pub tabs: Vec<WorkspaceTab>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkspaceTab {
+    pub name: String,
+    pub vertical: bool,
+    pub panes: Vec<WorkspacePane>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkspacePane {
+    pub name: String,
+    pub cwd: PathBuf,
+    /// Command to run when the pane is created
+    pub run: Option<String>,
+    /// Intent logged if the pane has no history yet
+    pub intent: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileWorkspace {
+    #[serde(default, rename = "tab")]
+    tabs: Vec<FileTab>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileTab {
+    name: String,
+    cwd: Option<String>,
+    layout: Option<String>,
+    #[serde(default, rename = "pane")]
+    panes: Vec<FilePane>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FilePane {
+    name: String,
+    cwd: Option<String>,
+    run: Option<String>,
+    intent: Option<String>,
+}
+
+/// The nearest workspace file in `start` or one of its ancestors.
+pub fn find(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .flat_map(|dir| FILE_NAMES.iter().map(move |name| dir.join(name)))
+        .find(|path| path.is_file())
+}
+
+/// Load a workspace file, picking the format from its extension.
+pub fn load(path: &Path) -> Result<Workspace> {
+    let contents = fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let file = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("kdl") => from_kdl(&contents),
+        _ => toml::from_str(&contents).map_err(anyhow::Error::from),
+    }
+    .with_context(|| format!("invalid workspace file {}", path.display()))?;
+
+    let root = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let tabs = resolve(file, &root).with_context(|| format!("invalid workspace file {}", path.display()))?;
+    Ok(Workspace { source: path.display().to_string(), tabs })
+}
+
+/// Read the `tab`/`pane` nodes of a `perth.kdl` file.
+fn from_kdl(contents: &str) -> Result<FileWorkspace> {
+    let mut tabs = Vec::new();
+    for node in kdl::parse(contents) {
+        if node.name != "tab" {
+            return Err(anyhow!("unexpected node '{}'; expected 'tab'", node.name));
+        }
+        let mut panes = Vec::new();
+        for child in node.children {
+            if child.name != "pane" {
+                return Err(anyhow!("unexpected node '{}' in a tab; expected 'pane'", child.name));
+            }
+            let mut props = child.props;
+            panes.push(FilePane {
+                name: props.remove("name").ok_or_else(|| anyhow!("every pane needs a name"))?,
+                cwd: props.remove("cwd"),
+                run: props.remove("run"),
+                intent: props.remove("intent"),
+            });
+        }
+        let mut props = node.props;
+        tabs.push(FileTab {
+            name: props.remove("name").ok_or_else(|| anyhow!("every tab needs a name"))?,
+            cwd: props.remove("cwd"),
+            layout: props.remove("layout"),
+            panes,
+        });
+    }
+    Ok(FileWorkspace { tabs })
+}
+
+/// Validate names and layouts and make every `cwd` absolute.
+fn resolve(file: FileWorkspace, root: &Path) -> Result<Vec<WorkspaceTab>> {
+    let mut tab_names = HashSet::new();
+    let mut pane_names = HashSet::new();
+    let mut tabs = Vec::new();
+
+    for tab in file.tabs {
+        if tab.name.trim().is_empty() {
+            return Err(anyhow!("tab names cannot be empty"));
+        }
+        if !tab_names.insert(tab.name.clone()) {
+            return Err(anyhow!("tab '{}' is defined more than once", tab.name));
+        }
+        let vertical = match tab.layout.as_deref() {
+            None | Some("vertical") => true,
+            Some("horizontal") => false,
+            Some(other) => {
+                return Err(anyhow!("tab '{}': unknown layout '{}' (expected vertical or horizontal)", tab.name, other))
+            }
+        };
+        let tab_cwd = tab.cwd.map(|cwd| root.join(cwd)).unwrap_or_else(|| root.to_path_buf());
+
+        let mut panes = Vec::new();
+        for pane in tab.panes {
+            if pane.name.trim().is_empty() {
+                return Err(anyhow!("tab '{}': pane names cannot be empty", tab.name));
+            }
+            // Pane names are global in Perth, not per tab
+            if !pane_names.insert(pane.name.clone()) {
+                return Err(anyhow!("pane '{}' is defined more than once", pane.name));
+            }
+            panes.push(WorkspacePane {
+                cwd: pane.cwd.map(|cwd| tab_cwd.join(cwd)).unwrap_or_else(|| tab_cwd.clone()),
+                name: pane.name,
+                run: pane.run.filter(|run| !run.trim().is_empty()),
+                intent: pane.intent.filter(|intent| !intent.trim().is_empty()),
+            });
+        }
+        if panes.is_empty() {
+            return Err(anyhow!("tab '{}' has no panes", tab.name));
+        }
+        tabs.push(WorkspaceTab { name: tab.name, vertical, panes });
+    }
+
+    if tabs.is_empty() {
+        return Err(anyhow!("no tabs defined"));
+    }
+    Ok(tabs)
+}
+
+/// What `zdrive up` did, or would do with `--diff`.
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspacePlan {
+    pub source: String,
+    pub session: String,
+    pub changes: Vec<WorkspaceChange>,
+}
+
+/// One difference between a workspace file and the session.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum WorkspaceChange {
+    CreateTab {
+        tab: String,
+    },
+    CreatePane {
+        tab: String,
+        pane: String,
+        cwd: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        run: Option<String>,
+    },
+    LogIntent {
+        pane: String,
+        summary: String,
+    },
+    /// A tracked pane that differs from the file. `zdrive up` never touches
+    /// running panes, so drift is only reported.
+    Drift {
+        pane: String,
+        field: String,
+        tracked: Option<String>,
+        workspace: Option<String>,
+    },
+}
+
+impl WorkspaceChange {
+    /// One line for `zdrive up` output: `+` for additions, `~` for drift.
+    pub fn describe(&self) -> String {
+        match self {
+            WorkspaceChange::CreateTab { tab } => format!("+ tab '{}'", tab),
+            WorkspaceChange::CreatePane { tab, pane, cwd, run } => {
+                let mut line = format!("+ pane '{}' in tab '{}' ({})", pane, tab, cwd);
+                if let Some(run) = run {
+                    line.push_str(&format!(" running `{}`", run));
+                }
+                line
+            }
+            WorkspaceChange::LogIntent { pane, summary } => format!("+ intent for '{}': {}", pane, summary),
+            WorkspaceChange::Drift { pane, field, tracked, workspace } => format!(
+                "~ pane '{}' {}: {} (file says {}), left as is",
+                pane,
+                field,
+                tracked.as_deref().unwrap_or("none"),
+                workspace.as_deref().unwrap_or("none")
+            ),
+        }
+    }
+
+    /// Whether applying the plan acts on this change.
+    pub fn is_applied(&self) -> bool {
+        !matches!(self, WorkspaceChange::Drift { .. })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toml_and_kdl_resolve_the_same() {
+        let root = Path::new("/work/app");
+        let toml = r#"
+[[tab]]
+name = "backend"
+cwd = "services"
+layout = "horizontal"
+
+[[tab.pane]]
+name = "api"
+cwd = "api"
+run = "cargo run"
+intent = "Wire up auth"
+
+[[tab.pane]]
+name = "logs"
+cwd = "/var/log"
+"#;
+        let kdl = r#"
+tab name="backend" cwd="services" layout="horizontal" {
+    pane name="api" cwd="api" run="cargo run" intent="Wire up auth"
+    pane name="logs" cwd="/var/log"
+}
+"#;
+        let from_toml = resolve(toml::from_str(toml).unwrap(), root).unwrap();
+        let from_kdl = resolve(from_kdl(kdl).unwrap(), root).unwrap();
+
+        for tabs in [&from_toml, &from_kdl] {
+            assert_eq!(tabs.len(), 1);
+            assert!(!tabs[0].vertical);
+            assert_eq!(tabs[0].panes[0].cwd, Path::new("/work/app/services/api"));
+            assert_eq!(tabs[0].panes[0].run.as_deref(), Some("cargo run"));
+            assert_eq!(tabs[0].panes[0].intent.as_deref(), Some("Wire up auth"));
+            assert_eq!(tabs[0].panes[1].cwd, Path::new("/var/log"));
+        }
+    }
+
+    #[test]
+    fn test_rejects_invalid_workspaces() {
+        let root = Path::new("/work");
+        let duplicate = "tab name=a { pane name=x }\ntab name=b { pane name=x }";
+        assert!(resolve(from_kdl(duplicate).unwrap(), root).is_err());
+        assert!(resolve(from_kdl("tab name=a { }").unwrap(), root).is_err());
+        assert!(resolve(from_kdl("tab name=a layout=grid { pane name=x }").unwrap(), root).is_err());
+        assert!(from_kdl("tab { pane name=x }").is_err());
+        assert!(from_kdl("layout { tab name=a }").is_err());
+        assert!(toml::from_str::<FileWorkspace>("[[tab]]\nname = \"a\"\ncolour = \"red\"").is_err());
+    }
+}
@@ -1,13 +1,17 @@
+use crate::error::PerthError;
+use crate::multiplexer::MultiplexerDriver;
+use crate::telemetry;
+use crate::tracker;
 use anyhow::{anyhow, Context, Result};
-use regex::Regex;
+use async_trait::async_trait;
 use semver::{Version, VersionReq};
 use serde_json::Value;
 use std::env;
 use std::process::Stdio;
 use std::sync::OnceLock;
-use tokio::process::Command;
+use tokio::process::{Child, Command};
 
-const MIN_ZELLIJ_VERSION: &str = ">=0.39.0";
+pub const MIN_ZELLIJ_VERSION: &str = ">=0.39.0";
 
 static VERSION_CHECK: OnceLock<Result<Version, String>> = OnceLock::new();
 
@@ -15,6 +19,8 @@ static VERSION_CHECK: OnceLock<Result<Version, String>> = OnceLock::new();
 pub struct ZellijDriver;
 
 impl ZellijDriver {
+    /// A driver for the `zellij` binary on `$PATH`, acting on the session
+    /// this process runs in.
     pub fn new() -> Self {
         Self
     }
@@ -26,7 +32,7 @@ impl ZellijDriver {
         if let Some(result) = VERSION_CHECK.get() {
             return result
                 .clone()
-                .map_err(|e| anyhow!("{}", e));
+                .map_err(|e| PerthError::ZellijUnavailable(e).into());
         }
 
         let result = self.get_zellij_version().await;
@@ -47,7 +53,7 @@ impl ZellijDriver {
                         version, MIN_ZELLIJ_VERSION.trim_start_matches(">=")
                     );
                     let _ = VERSION_CHECK.set(Err(err_msg.clone()));
-                    return Err(anyhow!("{}", err_msg));
+                    return Err(PerthError::ZellijUnavailable(err_msg).into());
                 }
 
                 let _ = VERSION_CHECK.set(Ok(version.clone()));
@@ -56,11 +62,16 @@ impl ZellijDriver {
             Err(e) => {
                 let err_msg = e.to_string();
                 let _ = VERSION_CHECK.set(Err(err_msg.clone()));
-                Err(anyhow!("{}", err_msg))
+                Err(PerthError::ZellijUnavailable(err_msg).into())
             }
         }
     }
 
+    /// The installed Zellij version, without enforcing the minimum.
+    pub async fn installed_version(&self) -> Result<Version> {
+        self.get_zellij_version().await
+    }
+
     async fn get_zellij_version(&self) -> Result<Version> {
         let output = Command::new("zellij")
             .arg("--version")
@@ -84,11 +95,70 @@ impl ZellijDriver {
             .with_context(|| format!("failed to parse Zellij version: {}", version_str))
     }
 
-    pub fn active_session_name(&self) -> Option<String> {
+    pub async fn attach_session(&self, session: &str) -> Result<()> {
+        let status = Command::new("zellij")
+            .arg("attach")
+            .arg(session)
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .status()
+            .await
+            .context("failed to run zellij attach")?;
+
+        if !status.success() {
+            return Err(anyhow!("zellij attach failed"));
+        }
+
+        Ok(())
+    }
+
+    /// Start the `perth-tracker` plugin at the plugin URL `plugin`. Each
+    /// line written to the child's stdin subscribes, or renews the
+    /// subscription; pane updates arrive one JSON line at a time on its
+    /// stdout until the plugin or session goes away.
+    pub fn spawn_tracker(&self, plugin: &str) -> Result<Child> {
+        tracing::debug!(plugin, "zellij pipe");
+        Command::new("zellij")
+            .args(["pipe", "--plugin", plugin, "--name", tracker::PIPE_NAME])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context("failed to run zellij pipe")
+    }
+
+    async fn action(&self, session: Option<&str>, args: &[&str]) -> Result<std::process::Output> {
+        let mut cmd = Command::new("zellij");
+        if let Some(session_name) = session {
+            cmd.arg("--session").arg(session_name);
+        }
+        cmd.arg("action");
+
+        tracing::debug!(session, args = %args.join(" "), "zellij action");
+        let mut span = telemetry::client_span(format!("zellij action {}", args.first().unwrap_or(&"")))
+            .with_attribute("zellij.args", args.join(" "))
+            .with_attribute("zellij.session", session.unwrap_or_default());
+        let output = span.record(cmd.args(args).output().await.context("failed to run zellij action"))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            tracing::debug!(session, args = %args.join(" "), status = %output.status, "zellij action failed");
+            return span.record(Err(anyhow!("zellij action failed: {}", stderr.trim())));
+        }
+
+        Ok(output)
+    }
+}
+
+#[async_trait]
+impl MultiplexerDriver for ZellijDriver {
+    fn active_session_name(&self) -> Option<String> {
         env::var("ZELLIJ_SESSION_NAME").ok()
     }
 
-    pub async fn query_tab_names(&self, session: Option<&str>) -> Result<Vec<String>> {
+    async fn query_tab_names(&self, session: Option<&str>) -> Result<Vec<String>> {
         let output = self.action(session, &["query-tab-names"]).await?;
         let stdout = String::from_utf8_lossy(&output.stdout);
         Ok(stdout
@@ -98,58 +168,109 @@ impl ZellijDriver {
             .collect())
     }
 
-    pub async fn new_tab(&self, session: Option<&str>, name: &str) -> Result<()> {
+    async fn new_tab(&self, session: Option<&str>, name: &str) -> Result<()> {
         self.action(session, &["new-tab", "--name", name]).await?;
         Ok(())
     }
 
-    pub async fn go_to_tab_name(&self, session: Option<&str>, name: &str) -> Result<()> {
+    async fn go_to_tab_name(&self, session: Option<&str>, name: &str) -> Result<()> {
         self.action(session, &["go-to-tab-name", name]).await?;
         Ok(())
     }
 
-    pub async fn new_pane(&self, session: Option<&str>) -> Result<()> {
-        self.action(session, &["new-pane"]).await?;
+    async fn close_focused_tab(&self, session: Option<&str>) -> Result<()> {
+        self.action(session, &["close-tab"]).await?;
         Ok(())
     }
 
-    /// Create a new pane with vertical split (side by side)
-    pub async fn new_pane_vertical(&self, session: Option<&str>) -> Result<()> {
-        self.action(session, &["new-pane", "--direction", "right"]).await?;
+    /// Create a new pane running the user's shell with extra environment
+    /// variables. The pane closes when the shell exits, like a plain pane.
+    ///
+    /// With a `command`, the shell runs it instead and the pane stays open
+    /// when it exits, so Zellij can show its exit status and rerun it.
+    async fn new_pane_with_env(
+        &self,
+        session: Option<&str>,
+        direction: Option<&str>,
+        cwd: Option<&str>,
+        env: &[(String, String)],
+        command: Option<&str>,
+    ) -> Result<()> {
+        let shell = std::env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+        let vars: Vec<String> = env.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+
+        let mut args = vec!["new-pane"];
+        if let Some(direction) = direction {
+            args.extend(["--direction", direction]);
+        }
+        if let Some(cwd) = cwd {
+            args.extend(["--cwd", cwd]);
+        }
+        if command.is_none() {
+            args.push("--close-on-exit");
+        }
+        args.extend(["--", "env"]);
+        args.extend(vars.iter().map(String::as_str));
+        args.push(&shell);
+        if let Some(command) = command {
+            args.extend(["-c", command]);
+        }
+        self.action(session, &args).await?;
         Ok(())
     }
 
-    /// Create a new pane with horizontal split (stacked)
-    pub async fn new_pane_horizontal(&self, session: Option<&str>) -> Result<()> {
-        self.action(session, &["new-pane", "--direction", "down"]).await?;
+    /// Type `text` into the focused pane.
+    async fn write_chars(&self, session: Option<&str>, text: &str) -> Result<()> {
+        self.action(session, &["write-chars", text]).await?;
         Ok(())
     }
 
-    /// Create a new pane with specified working directory
-    pub async fn new_pane_with_cwd(&self, session: Option<&str>, cwd: &str, direction: &str) -> Result<()> {
-        self.action(session, &["new-pane", "--direction", direction, "--cwd", cwd]).await?;
+    async fn new_pane(&self, session: Option<&str>, direction: &str, cwd: Option<&str>) -> Result<()> {
+        let mut args = vec!["new-pane", "--direction", direction];
+        if let Some(cwd) = cwd {
+            args.extend(["--cwd", cwd]);
+        }
+        self.action(session, &args).await?;
         Ok(())
     }
 
-    pub async fn rename_pane(&self, session: Option<&str>, name: &str) -> Result<()> {
+    async fn rename_pane(&self, session: Option<&str>, name: &str) -> Result<()> {
         self.action(session, &["rename-pane", name]).await?;
         Ok(())
     }
 
-    pub async fn focus_next_pane(&self, session: Option<&str>) -> Result<()> {
+    async fn focus_next_pane(&self, session: Option<&str>) -> Result<()> {
         self.action(session, &["focus-next-pane"]).await?;
         Ok(())
     }
 
-    pub async fn focus_pane_by_index(&self, session: Option<&str>, index: usize) -> Result<()> {
-        // Focus panes sequentially to reach target index
-        for _ in 0..index {
-            self.focus_next_pane(session).await?;
-        }
-        Ok(())
+    /// ID of the terminal pane focused by the session's first client, as in
+    /// `ZELLIJ_PANE_ID`. Needs `list-clients` (Zellij 0.40+); `None` when
+    /// the focused pane can't be told.
+    async fn focused_pane_id(&self, session: Option<&str>) -> Result<Option<String>> {
+        let output = self.action(session, &["list-clients"]).await?;
+        Ok(parse_focused_pane_id(&String::from_utf8_lossy(&output.stdout)))
     }
 
-    pub async fn dump_layout_json(&self, session: Option<&str>) -> Result<Option<Value>> {
+    async fn dump_screen(&self, session: Option<&str>) -> Result<String> {
+        // Created up front so the dump can't be redirected through a
+        // pre-existing file or symlink
+        let path = env::temp_dir().join(format!("perth-screen-{}-{}.txt", std::process::id(), uuid::Uuid::new_v4().simple()));
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .context("failed to create screen dump file")?;
+
+        let path_arg = path.to_string_lossy().to_string();
+        let dumped = self.action(session, &["dump-screen", "--full", &path_arg]).await;
+        let contents = std::fs::read(&path);
+        let _ = std::fs::remove_file(&path);
+        dumped?;
+        Ok(String::from_utf8_lossy(&contents.context("failed to read screen dump")?).into_owned())
+    }
+
+    async fn dump_layout_json(&self, session: Option<&str>) -> Result<Option<Value>> {
         // Try without --json since it's not supported in current versions
         // and we will handle the KDL output
         let output = match self.action(session, &["dump-layout"]).await {
@@ -168,104 +289,50 @@ impl ZellijDriver {
         }
 
         // Fallback: Parse KDL output to JSON structure
-        self.parse_kdl_to_json(&stdout).map(Some)
+        Ok(Some(crate::kdl::layout_to_json(&stdout)))
     }
 
-    fn parse_kdl_to_json(&self, kdl: &str) -> Result<Value> {
-        let mut tabs = Vec::new();
-        let mut current_tab_name = None;
-        let mut current_panes = Vec::new();
-        
-        let tab_re = Regex::new(r#"tab\s+name="([^"]+)""#).expect("invalid regex");
-        let pane_re = Regex::new(r#"^\s*pane\b"#).expect("invalid regex");
-        
-        for line in kdl.lines() {
-            let line = line.trim();
-            
-            if let Some(caps) = tab_re.captures(line) {
-                // If we were in a tab, push it
-                if let Some(name) = current_tab_name.take() {
-                    let mut tab = serde_json::Map::new();
-                    tab.insert("name".to_string(), Value::String(name));
-                    tab.insert("panes".to_string(), Value::Array(current_panes));
-                    tabs.push(Value::Object(tab));
-                    current_panes = Vec::new();
-                }
-                current_tab_name = Some(caps[1].to_string());
-            } else if pane_re.is_match(line) {
-                // Add a dummy pane object
-                let mut pane = serde_json::Map::new();
-                pane.insert("name".to_string(), Value::String("unnamed".to_string()));
-                current_panes.push(Value::Object(pane));
-            }
-        }
-        
-        // Flush last tab
-        if let Some(name) = current_tab_name {
-            let mut tab = serde_json::Map::new();
-            tab.insert("name".to_string(), Value::String(name));
-            tab.insert("panes".to_string(), Value::Array(current_panes));
-            tabs.push(Value::Object(tab));
-        } else if tabs.is_empty() {
-            // Handle case where no explicit tabs are defined (root layout is the tab)
-            // Re-scan for panes in the whole file
-             let mut panes = Vec::new();
-             for line in kdl.lines() {
-                 if pane_re.is_match(line.trim()) {
-                     let mut pane = serde_json::Map::new();
-                     pane.insert("name".to_string(), Value::String("unnamed".to_string()));
-                     panes.push(Value::Object(pane));
-                 }
-             }
-             if !panes.is_empty() {
-                 let mut tab = serde_json::Map::new();
-                 tab.insert("name".to_string(), Value::String("default".to_string()));
-                 tab.insert("panes".to_string(), Value::Array(panes));
-                 tabs.push(Value::Object(tab));
-             }
-        }
-
-        let mut root = serde_json::Map::new();
-        root.insert("tabs".to_string(), Value::Array(tabs));
-        Ok(Value::Object(root))
-    }
-
-    pub async fn attach_session(&self, session: &str) -> Result<()> {
-        let status = Command::new("zellij")
-            .arg("attach")
-            .arg(session)
-            .stdin(Stdio::inherit())
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
-            .status()
+    /// Start a detached session, for commands that set one up without
+    /// attaching to it.
+    async fn create_background_session(&self, session: &str) -> Result<()> {
+        let output = Command::new("zellij")
+            .args(["attach", "--create-background", session])
+            .output()
             .await
             .context("failed to run zellij attach")?;
 
-        if !status.success() {
-            return Err(anyhow!("zellij attach failed"));
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("failed to create session '{}': {}", session, stderr.trim()));
         }
 
         Ok(())
     }
+}
 
-    async fn action(&self, session: Option<&str>, args: &[&str]) -> Result<std::process::Output> {
-        let mut cmd = Command::new("zellij");
-        if let Some(session_name) = session {
-            cmd.arg("--session").arg(session_name);
-        }
-        cmd.arg("action");
-
-        let output = cmd
-            .args(args)
-            .output()
-            .await
-            .context("failed to run zellij action")?;
+/// The first client's pane from `zellij action list-clients` output:
+///
+/// ```text
+/// CLIENT_ID ZELLIJ_PANE_ID RUNNING_COMMAND
+/// 1         terminal_3     vim src/main.rs
+/// ```
+///
+/// Plugin panes have no `ZELLIJ_PANE_ID`, so they count as unknown.
+fn parse_focused_pane_id(output: &str) -> Option<String> {
+    let line = output.lines().map(str::trim).find(|line| line.starts_with(|c: char| c.is_ascii_digit()))?;
+    let pane = line.split_whitespace().nth(1)?;
+    pane.strip_prefix("terminal_").map(str::to_string)
+}
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow!("zellij action failed: {}", stderr.trim()));
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        Ok(output)
+    #[test]
+    fn test_parse_focused_pane_id() {
+        let output = "CLIENT_ID ZELLIJ_PANE_ID RUNNING_COMMAND\n1         terminal_3     vim src/main.rs\n2 terminal_7 bash\n";
+        assert_eq!(parse_focused_pane_id(output).as_deref(), Some("3"));
+        assert_eq!(parse_focused_pane_id("CLIENT_ID ZELLIJ_PANE_ID RUNNING_COMMAND\n1 plugin_2 N/A\n"), None);
+        assert_eq!(parse_focused_pane_id(""), None);
     }
 }
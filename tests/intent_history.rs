@@ -3,8 +3,13 @@
 //! Requires Redis to be running. Tests use unique key prefixes to avoid conflicts.
 
 use anyhow::Result;
-use zellij_driver::state::StateManager;
-use zellij_driver::types::{IntentEntry, IntentSource, IntentType};
+use std::collections::{BTreeMap, HashMap};
+use zellij_driver::crypto::Cipher;
+use zellij_driver::state::{KeyConflict, RekeyBatch, StateManager};
+use zellij_driver::types::{
+    Annotation, Goal, GoalStatus, IntentEntry, IntentSource, IntentType, LlmUsage, PaneRecord, SessionSnapshot, SessionTemplate,
+    TabRecord, TemplatePane, TemplateTab,
+};
 
 /// Generate a unique test pane name to avoid conflicts between tests
 fn test_pane_name(test_name: &str) -> String {
@@ -174,3 +179,684 @@ async fn test_empty_history() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_pane_ttl_applied_to_history() -> Result<()> {
+    let mut state = StateManager::new(&redis_url())
+        .await?
+        .with_pane_ttl_days(Some(7));
+    let pane_name = test_pane_name("ttl");
+
+    state.clear_history(&pane_name).await?;
+    state.log_intent(&pane_name, &IntentEntry::new("Expiring entry")).await?;
+
+    let client = redis::Client::open(redis_url())?;
+    let mut conn = client.get_multiplexed_tokio_connection().await?;
+    let ttl: i64 = redis::cmd("TTL")
+        .arg(format!("perth:pane:{}:history", pane_name))
+        .query_async(&mut conn)
+        .await?;
+    assert!(ttl > 0 && ttl <= 7 * 24 * 60 * 60);
+
+    // Logging keeps the pane's other keys alive too
+    let goals_key = format!("perth:pane:{}:goals", pane_name);
+    state.save_goal(&pane_name, &Goal::new("Outlive the TTL")).await?;
+    let _: () = redis::cmd("EXPIRE").arg(&goals_key).arg(60).query_async(&mut conn).await?;
+    state.log_intent(&pane_name, &IntentEntry::new("Still working")).await?;
+    let ttl: i64 = redis::cmd("TTL").arg(&goals_key).query_async(&mut conn).await?;
+    assert!(ttl > 60 && ttl <= 7 * 24 * 60 * 60);
+
+    let _: () = redis::cmd("DEL").arg(&goals_key).query_async(&mut conn).await?;
+    state.clear_history(&pane_name).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_concurrent_log_intent_keeps_last_intent_consistent() -> Result<()> {
+    let pane_name = test_pane_name("concurrent");
+    let mut state = StateManager::new(&redis_url()).await?;
+    state.clear_history(&pane_name).await?;
+
+    let mut writers = Vec::new();
+    for i in 0..8 {
+        let pane_name = pane_name.clone();
+        writers.push(tokio::spawn(async move {
+            let mut state = StateManager::new(&redis_url()).await?;
+            state
+                .log_intent(&pane_name, &IntentEntry::new(format!("Writer {}", i)))
+                .await
+        }));
+    }
+    for writer in writers {
+        writer.await??;
+    }
+
+    // The pane hash must always describe the head of the history list
+    let history = state.get_history(&pane_name, Some(1)).await?;
+    let client = redis::Client::open(redis_url())?;
+    let mut conn = client.get_multiplexed_tokio_connection().await?;
+    let last_intent: String = redis::cmd("HGET")
+        .arg(format!("znav:pane:{}", pane_name))
+        .arg("last_intent")
+        .query_async(&mut conn)
+        .await?;
+    assert_eq!(history[0].summary, last_intent);
+    assert_eq!(state.get_history_count(&pane_name).await?, 8);
+
+    state.clear_history(&pane_name).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_history_offset_and_time_range() -> Result<()> {
+    use chrono::{Duration, Utc};
+    use zellij_driver::state::HistoryQuery;
+
+    let mut state = StateManager::new(&redis_url()).await?;
+    let pane_name = test_pane_name("query");
+    state.clear_history(&pane_name).await?;
+
+    // Five entries, one hour apart, oldest logged first
+    let now = Utc::now();
+    for hours_ago in (0..5).rev() {
+        let mut entry = IntentEntry::new(format!("{}h ago", hours_ago));
+        entry.timestamp = now - Duration::hours(hours_ago);
+        state.log_intent(&pane_name, &entry).await?;
+    }
+
+    let page = HistoryQuery { offset: 1, limit: Some(2), ..Default::default() };
+    let history = state.query_history(&pane_name, &page).await?;
+    let summaries: Vec<&str> = history.iter().map(|e| e.summary.as_str()).collect();
+    assert_eq!(summaries, vec!["1h ago", "2h ago"]);
+
+    let window = HistoryQuery {
+        since: Some(now - Duration::minutes(150)),
+        until: Some(now - Duration::minutes(30)),
+        ..Default::default()
+    };
+    let history = state.query_history(&pane_name, &window).await?;
+    let summaries: Vec<&str> = history.iter().map(|e| e.summary.as_str()).collect();
+    assert_eq!(summaries, vec!["1h ago", "2h ago"]);
+
+    state.clear_history(&pane_name).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_history_by_tag() -> Result<()> {
+    use zellij_driver::state::HistoryQuery;
+
+    let mut state = StateManager::new(&redis_url()).await?;
+    let pane_name = test_pane_name("tags");
+    state.clear_history(&pane_name).await?;
+
+    state.log_intent(&pane_name, &IntentEntry::new("Fixed timeout").with_tags(vec!["bugfix".to_string()])).await?;
+    state.log_intent(&pane_name, &IntentEntry::new("Profiled queries").with_tags(vec!["perf".to_string()])).await?;
+    state.log_intent(&pane_name, &IntentEntry::new("Fixed retry").with_tags(vec!["bugfix".to_string(), "auth".to_string()])).await?;
+
+    // The limit counts matching entries only
+    let query = HistoryQuery { limit: Some(2), tag: Some("#BugFix".to_string()), ..Default::default() };
+    let history = state.query_history(&pane_name, &query).await?;
+    let summaries: Vec<&str> = history.iter().map(|e| e.summary.as_str()).collect();
+    assert_eq!(summaries, vec!["Fixed retry", "Fixed timeout"]);
+    assert_eq!(history[0].tags, vec!["bugfix", "auth"]);
+
+    state.clear_history(&pane_name).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_goals_saved_and_updated_in_place() -> Result<()> {
+    let mut state = StateManager::new(&redis_url()).await?;
+    let pane_name = test_pane_name("goals");
+    state.delete_pane(&pane_name).await?;
+
+    let mut first = Goal::new("Ship OAuth");
+    state.save_goal(&pane_name, &first).await?;
+    first.close(GoalStatus::Abandoned);
+    state.save_goal(&pane_name, &first).await?;
+    let second = Goal::new("Ship SSO");
+    state.save_goal(&pane_name, &second).await?;
+
+    let goals = state.get_goals(&pane_name).await?;
+    assert_eq!(goals.len(), 2);
+    assert_eq!(goals[0].status, GoalStatus::Abandoned);
+    assert!(goals[0].closed_at.is_some());
+    assert_eq!(goals[1], second);
+
+    state.delete_pane(&pane_name).await?;
+    assert!(state.get_goals(&pane_name).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_artifact_index_tracks_logged_entries() -> Result<()> {
+    let mut state = StateManager::new(&redis_url()).await?;
+    let pane_name = test_pane_name("artifacts");
+    let artifact = format!("/tmp/{}/auth.rs", pane_name);
+    state.clear_history(&pane_name).await?;
+
+    let mut first = IntentEntry::new("Sketched auth").with_artifacts(vec![artifact.clone()]);
+    first.timestamp -= chrono::Duration::minutes(1);
+    state.log_intent(&pane_name, &first).await?;
+    let second = IntentEntry::new("Unrelated");
+    state.log_intent(&pane_name, &second).await?;
+
+    // Artifacts appended later are indexed too
+    state
+        .update_entry(&pane_name, second.id, |entry| {
+            entry.artifacts.push(artifact.clone());
+            true
+        })
+        .await?;
+
+    let touches = state.artifact_touches(&artifact).await?;
+    assert_eq!(touches, vec![(pane_name.clone(), second.id), (pane_name.clone(), first.id)]);
+
+    state.unindex_artifact(&artifact, &pane_name, &second.id).await?;
+    state.unindex_artifact(&artifact, &pane_name, &first.id).await?;
+    assert!(state.artifact_touches(&artifact).await?.is_empty());
+
+    state.clear_history(&pane_name).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_archived_history_survives_cap() -> Result<()> {
+    use zellij_driver::state::HistoryQuery;
+
+    let mut state = StateManager::new(&redis_url())
+        .await?
+        .with_history_archive(true);
+    let pane_name = test_pane_name("archive");
+    state.clear_history(&pane_name).await?;
+
+    for i in 0..160 {
+        state.log_intent(&pane_name, &IntentEntry::new(format!("Entry {}", i))).await?;
+    }
+
+    // Overflow is archived in one batch once 150 entries accumulate
+    assert!(state.get_history_count(&pane_name).await? <= 150);
+    let archived = state.get_archived_history(&pane_name).await?;
+    assert_eq!(archived.len(), 50);
+    assert_eq!(archived[0].summary, "Entry 49");
+    assert_eq!(archived[49].summary, "Entry 0");
+
+    let everything = HistoryQuery { limit: Some(1000), include_archived: true, ..Default::default() };
+    let history = state.query_history(&pane_name, &everything).await?;
+    assert_eq!(history.len(), 160);
+    assert_eq!(history[0].summary, "Entry 159");
+    assert_eq!(history[159].summary, "Entry 0");
+
+    state.clear_history(&pane_name).await?;
+    assert!(state.get_archived_history(&pane_name).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cleared_history_can_be_restored_from_trash() -> Result<()> {
+    let mut state = StateManager::new(&redis_url())
+        .await?
+        .with_trash_ttl_days(Some(1));
+    let pane_name = test_pane_name("trash");
+    state.clear_history(&pane_name).await?;
+
+    state.log_intent(&pane_name, &IntentEntry::new("Worth keeping")).await?;
+    state.clear_history(&pane_name).await?;
+    assert_eq!(state.get_history_count(&pane_name).await?, 0);
+
+    let item = state
+        .list_trash()
+        .await?
+        .into_iter()
+        .find(|item| item.kind == "history" && item.name == pane_name)
+        .expect("cleared history should be in the trash");
+    state.restore_trash(&item.id).await?;
+
+    let history = state.get_history(&pane_name, None).await?;
+    assert_eq!(history[0].summary, "Worth keeping");
+
+    state.clear_history(&pane_name).await?;
+    let trashed: Vec<String> = state
+        .list_trash()
+        .await?
+        .into_iter()
+        .filter(|item| item.name == pane_name)
+        .map(|item| item.id)
+        .collect();
+    for id in trashed {
+        state.empty_trash(Some(&id)).await?;
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_restored_entries_respect_history_cap() -> Result<()> {
+    let mut state = StateManager::new(&redis_url())
+        .await?
+        .with_trash_ttl_days(Some(1));
+    let pane_name = test_pane_name("trash_cap");
+    state.clear_history(&pane_name).await?;
+
+    state.log_intent(&pane_name, &IntentEntry::new("Pruned")).await?;
+    let pruned = state.prune_history(&pane_name, false, |entry| entry.summary == "Pruned").await?;
+    assert_eq!(pruned, 1);
+    for i in 0..100 {
+        state.log_intent(&pane_name, &IntentEntry::new(format!("Kept {}", i))).await?;
+    }
+
+    let item = state
+        .list_trash()
+        .await?
+        .into_iter()
+        .find(|item| item.kind == "entries" && item.keys[0].contains(&pane_name))
+        .expect("pruned entries should be in the trash");
+    state.restore_trash(&item.id).await?;
+
+    // The restored entry is the oldest, so the cap drops it again
+    assert_eq!(state.get_history_count(&pane_name).await?, 100);
+    assert!(state.list_trash().await?.iter().all(|trashed| trashed.id != item.id));
+
+    state.clear_history(&pane_name).await?;
+    let trashed: Vec<String> = state
+        .list_trash()
+        .await?
+        .into_iter()
+        .filter(|item| item.name == pane_name)
+        .map(|item| item.id)
+        .collect();
+    for id in trashed {
+        state.empty_trash(Some(&id)).await?;
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_delete_pane_removes_record_and_history() -> Result<()> {
+    let mut state = StateManager::new(&redis_url()).await?;
+    let pane_name = test_pane_name("delete_pane");
+    let record = PaneRecord::new(
+        pane_name.clone(),
+        "test-session".to_string(),
+        "test-tab".to_string(),
+        StateManager::now_string(),
+        HashMap::new(),
+    );
+    state.upsert_pane(&record).await?;
+    state.log_intent(&pane_name, &IntentEntry::new("Soon gone")).await?;
+
+    state.delete_pane(&pane_name).await?;
+    assert!(state.get_pane(&pane_name).await?.is_none());
+    assert_eq!(state.get_history_count(&pane_name).await?, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_annotations_threaded_on_entry() -> Result<()> {
+    let mut state = StateManager::new(&redis_url()).await?;
+    let pane_name = test_pane_name("annotations");
+    state.clear_history(&pane_name).await?;
+
+    let entry = IntentEntry::new("Added retry loop");
+    state.log_intent(&pane_name, &entry).await?;
+
+    let first = Annotation::new(entry.id, "review: missing error handling in retry loop").with_author("sam");
+    let second = Annotation::new(entry.id, "fixed");
+    state.add_annotation(&pane_name, &first).await?;
+    state.add_annotation(&pane_name, &second).await?;
+
+    // Annotations don't add history entries, and come back oldest first
+    assert_eq!(state.get_history_count(&pane_name).await?, 1);
+    let annotations = state.get_annotations(&pane_name).await?;
+    assert_eq!(annotations, vec![first, second]);
+
+    state.clear_history(&pane_name).await?;
+    assert!(state.get_annotations(&pane_name).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_update_entry_rewrites_in_place() -> Result<()> {
+    let mut state = StateManager::new(&redis_url()).await?;
+    let pane_name = test_pane_name("update_entry");
+    state.clear_history(&pane_name).await?;
+
+    let target = IntentEntry::new("Added retry loop").with_artifacts(vec!["src/retry.rs".to_string()]);
+    state.log_intent(&pane_name, &target).await?;
+    state.log_intent(&pane_name, &IntentEntry::new("Later work")).await?;
+
+    let updated = state
+        .update_entry(&pane_name, target.id, |entry| {
+            entry.artifacts.push("tests/retry.rs".to_string());
+            true
+        })
+        .await?
+        .expect("entry is in live history");
+    assert_eq!(updated.artifacts, vec!["src/retry.rs", "tests/retry.rs"]);
+
+    // Same position, no duplicate entry
+    let history = state.get_history(&pane_name, None).await?;
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[1].id, target.id);
+    assert_eq!(history[1].artifacts, updated.artifacts);
+
+    let missing = state.update_entry(&pane_name, uuid::Uuid::new_v4(), |_| true).await?;
+    assert!(missing.is_none());
+
+    state.clear_history(&pane_name).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_follow_history_receives_new_entries() -> Result<()> {
+    use futures_util::StreamExt;
+
+    let mut state = StateManager::new(&redis_url()).await?;
+    let pane_name = test_pane_name("follow");
+    state.clear_history(&pane_name).await?;
+
+    let mut updates = std::pin::pin!(state.follow_history(&pane_name).await?);
+    let entry = IntentEntry::new("Streamed live").with_type(IntentType::Milestone);
+    state.log_intent(&pane_name, &entry).await?;
+
+    let received = tokio::time::timeout(std::time::Duration::from_secs(5), updates.next())
+        .await?
+        .expect("subscription is open")?;
+    assert_eq!(received.id, entry.id);
+    assert_eq!(received.summary, "Streamed live");
+    assert_eq!(received.entry_type, IntentType::Milestone);
+
+    state.clear_history(&pane_name).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_keyspace_stats_counts_histories() -> Result<()> {
+    let mut state = StateManager::new(&redis_url()).await?;
+    let pane_name = test_pane_name("stats");
+    state.clear_history(&pane_name).await?;
+
+    for i in 0..3 {
+        state.log_intent(&pane_name, &IntentEntry::new(format!("Entry {}", i))).await?;
+    }
+
+    let stats = state.keyspace_stats(10, usize::MAX).await?;
+    let histories = stats.groups.iter().find(|g| g.category == "histories").unwrap();
+    assert!(histories.keys >= 1);
+    assert!(stats.total_keys() >= histories.keys);
+
+    let ours = stats
+        .largest_histories
+        .iter()
+        .find(|h| h.pane == pane_name)
+        .expect("history should be listed");
+    assert_eq!(ours.entries, 3);
+
+    state.clear_history(&pane_name).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_llm_usage_accumulates_per_month() -> Result<()> {
+    let mut state = StateManager::new(&redis_url()).await?;
+    let provider = test_pane_name("llm-usage");
+
+    let usage = LlmUsage {
+        month: "1999-12".to_string(),
+        provider: provider.clone(),
+        model: "claude-sonnet-4".to_string(),
+        requests: 1,
+        input_tokens: 1000,
+        output_tokens: 100,
+        cost_usd: Some(0.0045),
+    };
+    state.record_llm_usage(&usage).await?;
+    state.record_llm_usage(&usage).await?;
+
+    let recorded = state.get_llm_usage(Some("1999-12")).await?;
+    let ours = recorded.iter().find(|u| u.provider == provider).expect("usage should be recorded");
+    assert_eq!(ours.requests, 2);
+    assert_eq!(ours.input_tokens, 2000);
+    assert_eq!(ours.output_tokens, 200);
+    assert!((ours.cost_usd.unwrap() - 0.009).abs() < 1e-9);
+
+    assert!(state.get_llm_usage(Some("1999-11")).await?.iter().all(|u| u.provider != provider));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_touch_panes_updates_last_accessed() -> Result<()> {
+    let mut state = StateManager::new(&redis_url()).await?;
+    let old = "2000-01-01T00:00:00+00:00".to_string();
+    let names: Vec<String> = ["touch_a", "touch_b"].iter().map(|n| test_pane_name(n)).collect();
+
+    for name in &names {
+        let record = PaneRecord::new(name.clone(), "s".into(), "t".into(), old.clone(), HashMap::new());
+        state.upsert_pane(&record).await?;
+    }
+
+    state.touch_panes(&names).await?;
+
+    for name in &names {
+        let pane = state.get_pane(name).await?.expect("pane should exist");
+        assert_ne!(pane.last_accessed, old);
+        assert_eq!(pane.last_seen, old, "touching must not mark a pane seen");
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tab_archive_queue_and_lookup() -> Result<()> {
+    let mut state = StateManager::new(&redis_url()).await?;
+    let session = test_pane_name("archive_session");
+    let tab = TabRecord::new("review".to_string(), session.clone(), StateManager::now_string())
+        .with_correlation_id("pr-42");
+    state.upsert_tab(&tab).await?;
+
+    let found = state.list_all_tabs().await?;
+    assert!(found.iter().any(|t| t.session == session && t.correlation_id.as_deref() == Some("pr-42")));
+    let found = state.find_tabs_by_correlation("pr-42").await?;
+    assert!(found.iter().any(|t| t.session == session && t.tab_name == "review"));
+
+    let now = chrono::Utc::now();
+    state.schedule_tab_archive("review", &session, "github.pr.merged", now + chrono::Duration::minutes(10)).await?;
+    let due = state.take_due_tab_archives(now).await?;
+    assert!(due.iter().all(|(s, _, _)| s != &session), "not due before the grace period");
+
+    let due = state.take_due_tab_archives(now + chrono::Duration::minutes(11)).await?;
+    assert!(due.contains(&(session.clone(), "review".to_string(), "github.pr.merged".to_string())));
+    assert!(state.take_due_tab_archives(now + chrono::Duration::minutes(11)).await?.iter().all(|(s, _, _)| s != &session));
+
+    state.mark_tab_archived("review", &session).await?;
+    assert!(state.get_tab("review", &session).await?.unwrap().archived_at.is_some());
+
+    // Recreating the tab clears the archive stamp
+    state.upsert_tab(&tab).await?;
+    assert!(state.get_tab("review", &session).await?.unwrap().archived_at.is_none());
+
+    state.delete_tab("review", &session).await?;
+    assert!(!state.tab_exists("review", &session).await?);
+    let found = state.find_tabs_by_correlation("pr-42").await?;
+    assert!(found.iter().all(|t| t.session != session), "deleted tabs drop out of the index");
+    assert!(state.list_tabs(&session).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_event_outbox_keeps_order() -> Result<()> {
+    let state = StateManager::new(&redis_url()).await?;
+    let outbox = state.event_outbox();
+    // The outbox is a single global list; leave a real backlog alone
+    if !outbox.peek(1).await?.is_empty() {
+        return Ok(());
+    }
+
+    let first = format!(r#"{{"event_type": "perth.test.first", "pid": {}}}"#, std::process::id());
+    let second = format!(r#"{{"event_type": "perth.test.second", "pid": {}}}"#, std::process::id());
+    assert_eq!(outbox.push(&first).await?, 1);
+    assert_eq!(outbox.push(&second).await?, 2);
+    assert_eq!(outbox.peek(10).await?, vec![first.clone(), second.clone()]);
+
+    // Only the head can be removed, so events go out in order
+    assert!(!outbox.remove_front(&second).await?);
+    assert!(outbox.remove_front(&first).await?);
+    assert!(outbox.remove_front(&second).await?);
+    assert!(outbox.peek(10).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_template_save_list_delete() -> Result<()> {
+    let state = StateManager::new(&redis_url()).await?;
+    let name = test_pane_name("template");
+
+    let mut variables = BTreeMap::new();
+    variables.insert("branch".to_string(), "main".to_string());
+    let template = SessionTemplate {
+        name: name.clone(),
+        created_at: chrono::Utc::now(),
+        description: None,
+        source_session: "dev".to_string(),
+        variables,
+        tabs: vec![TemplateTab {
+            name: "api".to_string(),
+            panes: vec![TemplatePane { name: "api-{{branch}}".to_string(), cwd: None, command: Some("cargo run".to_string()) }],
+        }],
+    };
+    state.save_template(&template).await?;
+
+    let loaded = state.get_template(&name).await?.expect("template saved");
+    assert_eq!(loaded.tabs, template.tabs);
+    assert_eq!(loaded.variables["branch"], "main");
+    assert!(state.list_templates().await?.iter().any(|t| t.name == name));
+
+    state.delete_template(&name).await?;
+    assert!(state.get_template(&name).await?.is_none());
+    Ok(())
+}
+
+#[test]
+fn test_key_conflict_merge_prefers_recent() {
+    let fields = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    };
+    let conflict = KeyConflict {
+        old_key: "znav:pane:api".into(),
+        new_key: "perth:pane:api".into(),
+        old: fields(&[("last_seen", "2025-03-02T00:00:00+00:00"), ("tab", "review"), ("meta:goal", "refunds")]),
+        new: fields(&[("last_seen", "2025-03-01T00:00:00+00:00"), ("tab", "main"), ("status", "stale")]),
+    };
+
+    let diff: Vec<_> = conflict.differing_fields().into_iter().map(|(name, _, _)| name).collect();
+    assert_eq!(diff, vec!["last_seen", "meta:goal", "status", "tab"]);
+
+    // The v1 record was seen more recently, so its values win
+    let merged = conflict.merged();
+    assert_eq!(merged["tab"], "review");
+    assert_eq!(merged["meta:goal"], "refunds");
+    assert_eq!(merged["status"], "stale");
+    assert_eq!(merged["last_seen"], "2025-03-02T00:00:00+00:00");
+}
+
+#[tokio::test]
+async fn test_snapshot_ancestry_by_id() -> Result<()> {
+    let state = StateManager::new(&redis_url()).await?;
+    let session = test_pane_name("ancestry_session");
+    let base = SessionSnapshot::new("base", session.clone());
+    let middle = SessionSnapshot::new("middle", session.clone()).with_parent(base.id);
+    let tip = SessionSnapshot::new("tip", session.clone()).with_parent(middle.id);
+    for snapshot in [&base, &middle, &tip] {
+        state.save_snapshot(snapshot).await?;
+    }
+
+    let names = |chain: Vec<SessionSnapshot>| chain.into_iter().map(|s| s.name).collect::<Vec<_>>();
+    assert_eq!(names(state.get_snapshot_ancestry(&session, "tip").await?), ["tip", "middle", "base"]);
+    assert_eq!(state.get_snapshot_by_id(&middle.id).await?.unwrap().name, "middle");
+    assert_eq!(state.list_snapshots(&session).await?.len(), 3, "pointers aren't listed as snapshots");
+
+    // A parent cycle stops the walk instead of looping
+    state.save_snapshot(&base.clone().with_parent(tip.id)).await?;
+    assert_eq!(names(state.get_snapshot_ancestry(&session, "tip").await?), ["tip", "middle", "base"]);
+
+    state.delete_snapshot(&session, "middle").await?;
+    assert!(state.get_snapshot_by_id(&middle.id).await?.is_none());
+    assert_eq!(names(state.get_snapshot_ancestry(&session, "tip").await?), ["tip"]);
+
+    state.delete_snapshot(&session, "base").await?;
+    state.delete_snapshot(&session, "tip").await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_legacy_snapshot_pointer_is_moved() -> Result<()> {
+    let state = StateManager::new(&redis_url()).await?;
+    let session = test_pane_name("legacy_pointer_session");
+    let parent = SessionSnapshot::new("parent", session.clone());
+    let child = SessionSnapshot::new("child", session.clone()).with_parent(parent.id);
+    state.save_snapshot(&parent).await?;
+    state.save_snapshot(&child).await?;
+
+    // Pointers written before they moved out of perth:snapshots:
+    let client = redis::Client::open(redis_url())?;
+    let mut conn = client.get_multiplexed_tokio_connection().await?;
+    let pointer = format!("perth:snapshot-ids:{}", parent.id);
+    let legacy = format!("perth:snapshots:by-id:{}", parent.id);
+    redis::cmd("RENAME").arg(&pointer).arg(&legacy).query_async::<()>(&mut conn).await?;
+
+    assert_eq!(state.get_snapshot_by_id(&parent.id).await?.unwrap().name, "parent");
+    let exists: (bool, bool) = redis::pipe().exists(&pointer).exists(&legacy).query_async(&mut conn).await?;
+    assert_eq!(exists, (true, false));
+    assert_eq!(state.get_snapshot_ancestry(&session, "child").await?.len(), 2);
+
+    state.delete_snapshot(&session, "child").await?;
+    state.delete_snapshot(&session, "parent").await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_encrypted_history_survives_rekey() -> Result<()> {
+    let old_key = Cipher::new("AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=", "old")?;
+    let new_key = Cipher::new("Hx4dHBsaGRgXFhUUExIREA8ODQwLCgkIBwYFBAMCAQA=", "new")?;
+    let pane_name = test_pane_name("rekey");
+    let session = test_pane_name("rekey_session");
+
+    let mut state = StateManager::new(&redis_url()).await?.with_encryption(Some(old_key.clone()));
+    state.clear_history(&pane_name).await?;
+    state.log_intent(&pane_name, &IntentEntry::new("Rotated the signing secret")).await?;
+    state.save_snapshot(&SessionSnapshot::new("before-rekey", session.clone())).await?;
+
+    // Nothing readable is stored in the clear
+    let client = redis::Client::open(redis_url())?;
+    let mut conn = client.get_multiplexed_tokio_connection().await?;
+    let stored: Vec<String> = redis::cmd("LRANGE")
+        .arg(format!("perth:pane:{}:history", pane_name))
+        .arg(0)
+        .arg(-1)
+        .query_async(&mut conn)
+        .await?;
+    assert!(stored.iter().all(|entry| entry.starts_with("enc:v1:") && !entry.contains("signing")));
+
+    state.clear_rekey_cursor().await?;
+    loop {
+        if state.rekey_batch(&old_key, &new_key, 100).await?.done {
+            break;
+        }
+    }
+
+    let mut rotated = StateManager::new(&redis_url()).await?.with_encryption(Some(new_key.clone()));
+    assert_eq!(rotated.get_history(&pane_name, None).await?[0].summary, "Rotated the signing secret");
+    assert_eq!(rotated.get_snapshot(&session, "before-rekey").await?.name, "before-rekey");
+    assert!(state.get_history(&pane_name, None).await.is_err(), "the old key no longer opens anything");
+
+    // A second pass finds everything already moved
+    let mut again = RekeyBatch::default();
+    while !again.done {
+        again.merge(&rotated.rekey_batch(&old_key, &new_key, 100).await?);
+    }
+    assert_eq!(again.rekeyed, 0);
+    assert!(again.current >= 3, "entry, last intent and snapshot are already current");
+
+    rotated.clear_history(&pane_name).await?;
+    rotated.delete_snapshot(&session, "before-rekey").await?;
+    Ok(())
+}
@@ -0,0 +1,214 @@
+//! Integration tests for orchestration logic over the in-memory multiplexer.
+//!
+//! Requires Redis to be running. Sessions and panes are named per test and
+//! process to avoid conflicts.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use zellij_driver::bloodbank::EventPublisher;
+use zellij_driver::config::{BloodbankConfig, NamingCheck};
+use zellij_driver::multiplexer::{MockMultiplexer, MultiplexerDriver};
+use zellij_driver::orchestrator::Orchestrator;
+use zellij_driver::state::StateManager;
+use zellij_driver::types::PaneRecord;
+
+/// Generate a unique name to avoid conflicts between tests
+fn test_name(test_name: &str) -> String {
+    format!("test_{}_{}", test_name, std::process::id())
+}
+
+/// Get Redis URL from environment or use default
+fn redis_url() -> String {
+    std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string())
+}
+
+/// An orchestrator running inside `session` of a fresh mock, and a handle
+/// on the mock to inspect.
+async fn orchestrator(session: &str) -> Result<(Orchestrator<MockMultiplexer>, MockMultiplexer)> {
+    let mock = MockMultiplexer::new().with_active_session(session);
+    let state = StateManager::new(&redis_url()).await?;
+    let events = EventPublisher::new(BloodbankConfig::default());
+    Ok((Orchestrator::new(state, mock.clone(), events), mock))
+}
+
+#[tokio::test]
+async fn test_open_pane_creates_then_focuses_by_id() -> Result<()> {
+    let session = test_name("open_session");
+    let (first, second) = (test_name("open_first"), test_name("open_second"));
+    let (mut orchestrator, mock) = orchestrator(&session).await?;
+
+    orchestrator.open_pane(first.clone(), Some("backend".to_string()), None, HashMap::new(), false).await?;
+    orchestrator.open_pane(second.clone(), Some("backend".to_string()), None, HashMap::new(), false).await?;
+    assert_eq!(mock.pane_names(&session, "backend"), vec![first.clone(), second.clone()]);
+
+    let mut state = StateManager::new(&redis_url()).await?;
+    let record = state.get_pane(&first).await?.expect("pane should be tracked");
+    assert_eq!(record.session, session);
+    assert_eq!(record.tab, "backend");
+    assert_eq!(record.meta.get("position").map(String::as_str), Some("0"));
+    assert!(record.pane_id.is_some());
+
+    // The second pane has focus; reopening the first moves focus back by its ID
+    let location = orchestrator.open_pane(first.clone(), None, None, HashMap::new(), false).await?;
+    assert!(location.attached);
+    assert_eq!(mock.focused_pane_id(None).await?, record.pane_id);
+    assert_eq!(mock.pane_names(&session, "backend").len(), 2);
+
+    state.delete_pane(&first).await?;
+    state.delete_pane(&second).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_create_tab_records_correlation_and_refocuses() -> Result<()> {
+    let session = test_name("tab_session");
+    let (mut orchestrator, mock) = orchestrator(&session).await?;
+
+    let created = orchestrator
+        .create_tab("api(fixes)".to_string(), Some("pr-42".to_string()), HashMap::new(), NamingCheck::Warn)
+        .await?;
+    assert!(created.created);
+    assert_eq!(created.tab_name, "api(fixes)-pr-42");
+    assert_eq!(created.session, session);
+    assert!(created.naming_warning.is_none());
+    assert!(mock.query_tab_names(None).await?.contains(&created.tab_name));
+
+    let record = orchestrator.tab_info(&created.tab_name).await?.expect("tab should be tracked");
+    assert_eq!(record.correlation_id.as_deref(), Some("pr-42"));
+
+    // Creating it again only focuses the existing tab
+    mock.new_tab(None, "other").await?;
+    let again = orchestrator
+        .create_tab("api(fixes)".to_string(), Some("pr-42".to_string()), HashMap::new(), NamingCheck::Warn)
+        .await?;
+    assert!(!again.created);
+    let actions = mock.actions();
+    assert_eq!(actions.iter().filter(|a| a.ends_with("new-tab api(fixes)-pr-42")).count(), 1);
+    assert_eq!(actions.last().map(String::as_str), Some(format!("{}: go-to-tab-name api(fixes)-pr-42", session).as_str()));
+
+    StateManager::new(&redis_url()).await?.delete_tab(&created.tab_name, &session).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_create_tab_enforces_naming_convention() -> Result<()> {
+    let session = test_name("naming_session");
+    let (mut orchestrator, mock) = orchestrator(&session).await?;
+
+    let result = orchestrator
+        .create_tab("no convention".to_string(), None, HashMap::new(), NamingCheck::Enforce)
+        .await;
+    assert!(result.is_err());
+    assert!(mock.actions().iter().all(|a| !a.contains("new-tab")));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reconcile_marks_missing_panes_stale() -> Result<()> {
+    let session = test_name("reconcile_session");
+    let (live, ghost) = (test_name("reconcile_live"), test_name("reconcile_ghost"));
+    let (mut orchestrator, _mock) = orchestrator(&session).await?;
+
+    orchestrator.open_pane(live.clone(), Some("work".to_string()), None, HashMap::new(), false).await?;
+    let mut state = StateManager::new(&redis_url()).await?;
+    let record = PaneRecord::new(ghost.clone(), session.clone(), "work".to_string(), StateManager::now_string(), HashMap::new());
+    state.upsert_pane(&record).await?;
+
+    let result = orchestrator.reconcile().await?;
+    assert_eq!(result.session, session);
+    assert!(result.seen_panes.contains(&live));
+    assert!(result.stale_panes.contains(&ghost));
+    assert!(!state.get_pane(&live).await?.unwrap().stale);
+    assert!(state.get_pane(&ghost).await?.unwrap().stale);
+
+    state.delete_pane(&live).await?;
+    state.delete_pane(&ghost).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_daemon_samples_the_pane_working_dir() -> Result<()> {
+    let session = test_name("daemon_session");
+    let pane = test_name("daemon_pane");
+    let (mut orchestrator, _mock) = orchestrator(&session).await?;
+
+    let pane_dir = std::env::temp_dir();
+    let meta = HashMap::from([("cwd".to_string(), pane_dir.to_string_lossy().to_string())]);
+    let mut state = StateManager::new(&redis_url()).await?;
+    state.upsert_pane(&PaneRecord::new(pane.clone(), session.clone(), "work".to_string(), StateManager::now_string(), meta)).await?;
+
+    assert_eq!(orchestrator.activity_dir(Some(&pane)).await?, Some(pane_dir));
+    assert_eq!(orchestrator.activity_dir(None).await?, Some(std::env::current_dir()?));
+
+    state.delete_pane(&pane).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_open_pane_falls_back_to_position_when_id_misses() -> Result<()> {
+    let session = test_name("fallback_session");
+    let pane = test_name("fallback_pane");
+    let mock = MockMultiplexer::new().with_active_session(&session).with_session(&session, &["backend"]);
+    mock.new_pane_vertical(None).await?;
+    mock.new_pane_vertical(None).await?;
+    let third = mock.focused_pane_id(None).await?;
+    // Back on the tab's first pane, where the positional fallback counts from
+    mock.focus_next_pane(None).await?;
+
+    // The recorded ID is gone (say, Zellij restarted), but its position is known
+    let mut record = PaneRecord::new(
+        pane.clone(),
+        session.clone(),
+        "backend".to_string(),
+        StateManager::now_string(),
+        HashMap::from([("position".to_string(), "2".to_string())]),
+    );
+    record.pane_id = Some("999".to_string());
+    let mut state = StateManager::new(&redis_url()).await?;
+    state.upsert_pane(&record).await?;
+
+    let events = EventPublisher::new(BloodbankConfig::default());
+    let mut orchestrator = Orchestrator::new(StateManager::new(&redis_url()).await?, mock.clone(), events);
+    orchestrator.open_pane(pane.clone(), None, None, HashMap::new(), false).await?;
+    assert_eq!(mock.focused_pane_id(None).await?, third);
+
+    state.delete_pane(&pane).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_takeover_keeps_goal_progress() -> Result<()> {
+    use zellij_driver::handoff;
+    use zellij_driver::output::OutputFormatter;
+    use zellij_driver::types::{Goal, GoalProgress, IntentEntry};
+
+    let session = test_name("takeover_session");
+    let pane = test_name("takeover_pane");
+    let (mut orchestrator, _mock) = orchestrator(&session).await?;
+    let mut state = StateManager::new(&redis_url()).await?;
+    state.clear_history(&pane).await?;
+
+    let goal = Goal::new("Ship OAuth");
+    let mut progress = IntentEntry::new("Wired token refresh").with_goal(goal.id).with_goal_delta("Refresh tokens rotate");
+    progress.timestamp = goal.set_at + chrono::Duration::seconds(30);
+    let mut unrelated = IntentEntry::new("Tidied imports");
+    unrelated.timestamp = progress.timestamp + chrono::Duration::seconds(30);
+    let history = vec![unrelated, progress.clone()];
+
+    // The Markdown export truncates times to the minute, which can predate the goal
+    let markdown = OutputFormatter::new()
+        .with_goal(Some(GoalProgress { goal: goal.clone(), entries: vec![progress.clone()] }))
+        .format_markdown(&history, "old-owner");
+    let result = orchestrator.takeover(pane.clone(), Some("work".to_string()), None, handoff::parse(&markdown)?).await?;
+    assert_eq!(result.goal.as_deref(), Some("Ship OAuth"));
+
+    let carried = orchestrator.goal_progress(&pane).await?.expect("goal should be carried over");
+    assert_eq!(carried.goal.id, goal.id);
+    assert_eq!(carried.entries.len(), 1);
+    assert_eq!(carried.entries[0].summary, progress.summary);
+    assert_eq!(carried.entries[0].goal_delta, progress.goal_delta);
+
+    state.delete_pane(&pane).await?;
+    state.clear_history(&pane).await?;
+    Ok(())
+}